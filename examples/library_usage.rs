@@ -17,7 +17,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             url: Some("https://www.rust-lang.org".to_string()),
             folder: Some("Programming".to_string()),
             date_added: None,
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "2".to_string(),
@@ -25,7 +25,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             url: Some("https://github.com".to_string()),
             folder: Some("Development".to_string()),
             date_added: None,
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "3".to_string(),
@@ -33,7 +33,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             url: Some("https://doc.rust-lang.org".to_string()),
             folder: Some("Programming".to_string()),
             date_added: None,
-            children: None,
+            ..Default::default()
         },
     ];
 
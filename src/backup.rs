@@ -0,0 +1,132 @@
+//! Dated, compressed copies of every browser's bookmarks.
+//!
+//! `backup` writes all profiles' bookmarks as one gzipped export YAML,
+//! `bookmarks-<UTC time>.yaml.gz`, to the backups directory, then deletes
+//! all but the newest `backup.keep`. A run within `backup.interval` of the
+//! last backup does nothing, so the command can be scheduled more often
+//! than backups are wanted. Other files in the directory, such as the
+//! copies `apply` makes, are left alone.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::exporter::BrowserData;
+
+const PREFIX: &str = "bookmarks-";
+const SUFFIX: &str = ".yaml.gz";
+const STAMP: &str = "%Y%m%d-%H%M%S";
+
+/// A backup file, with the time in its name
+#[derive(Debug, Clone)]
+pub struct BackupFile {
+    pub path: PathBuf,
+    pub taken_at: DateTime<Utc>,
+}
+
+/// The backups in `dir`, newest first; none if it doesn't exist
+pub fn list(dir: &Path) -> Result<Vec<BackupFile>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Reading {}", dir.display())),
+    };
+    let mut backups: Vec<BackupFile> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let stamp = name.to_str()?.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?;
+            let taken_at = NaiveDateTime::parse_from_str(stamp, STAMP).ok()?.and_utc();
+            Some(BackupFile {
+                path: entry.path(),
+                taken_at,
+            })
+        })
+        .collect();
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.taken_at));
+    Ok(backups)
+}
+
+/// Whether `interval` has passed since the newest of `backups`
+pub fn is_due(backups: &[BackupFile], interval: Duration, now: DateTime<Utc>) -> bool {
+    backups
+        .first()
+        .is_none_or(|newest| now - newest.taken_at >= interval)
+}
+
+/// Write `data` as a backup taken at `now`
+pub fn write(dir: &Path, data: &[BrowserData], now: DateTime<Utc>) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}{}{}", PREFIX, now.format(STAMP), SUFFIX));
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(serde_yaml::to_string(data)?.as_bytes())?;
+    fs::write(&path, encoder.finish()?).with_context(|| format!("Writing {}", path.display()))?;
+    Ok(path)
+}
+
+/// Delete all but the first `keep` of `backups` (newest first, as [`list`]
+/// returns them), returning the deleted paths
+pub fn prune(backups: &[BackupFile], keep: usize) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for backup in backups.iter().skip(keep) {
+        fs::remove_file(&backup.path)
+            .with_context(|| format!("Deleting {}", backup.path.display()))?;
+        removed.push(backup.path.clone());
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_backups_are_dated_and_pruned() {
+        let dir = tempdir().unwrap();
+        let start = Utc::now() - Duration::days(10);
+        assert!(is_due(&list(dir.path()).unwrap(), Duration::days(1), start));
+
+        for day in 0..4 {
+            write(dir.path(), &[], start + Duration::days(day)).unwrap();
+        }
+        fs::write(dir.path().join("chrome-Default-Bookmarks.json"), "{}").unwrap();
+        let backups = list(dir.path()).unwrap();
+        assert_eq!(backups.len(), 4);
+        assert_eq!(
+            backups[0].taken_at.timestamp(),
+            (start + Duration::days(3)).timestamp()
+        );
+        assert!(!is_due(
+            &backups,
+            Duration::days(1),
+            start + Duration::days(3)
+        ));
+        assert!(is_due(
+            &backups,
+            Duration::days(1),
+            start + Duration::days(4)
+        ));
+
+        let mut yaml = String::new();
+        GzDecoder::new(fs::File::open(&backups[0].path).unwrap())
+            .read_to_string(&mut yaml)
+            .unwrap();
+        assert!(
+            serde_yaml::from_str::<Vec<BrowserData>>(&yaml)
+                .unwrap()
+                .is_empty()
+        );
+
+        let removed = prune(&backups, 2).unwrap();
+        assert_eq!(removed, [backups[2].path.clone(), backups[3].path.clone()]);
+        assert_eq!(list(dir.path()).unwrap().len(), 2);
+        assert!(dir.path().join("chrome-Default-Bookmarks.json").exists());
+    }
+}
@@ -0,0 +1,28 @@
+use anyhow::{Result, anyhow};
+use bookmark::host::{self, NativeHost};
+
+const USAGE: &str = "Usage: bookmark-host [manifest <chrome|firefox> <extension-id>]";
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    // Browsers pass the caller's origin (and Firefox the manifest path)
+    // when starting the host, so any other arguments mean serve
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("manifest") => {
+            let [_, browser, extension_id] = args.as_slice() else {
+                return Err(anyhow!(USAGE));
+            };
+            let path = std::env::current_exe()?;
+            let manifest = host::manifest(browser, extension_id, &path)?;
+            println!("{}", serde_json::to_string_pretty(&manifest)?);
+            Ok(())
+        }
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => NativeHost::new()?.run(),
+    }
+}
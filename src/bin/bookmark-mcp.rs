@@ -1,9 +1,31 @@
-use anyhow::Result;
-use bookmark::mcp::McpServer;
+use anyhow::{Result, anyhow};
+use bookmark::mcp::{HttpTransport, McpServer};
 
 fn main() -> Result<()> {
     env_logger::init();
-    
+
     let server = McpServer::new();
+    let mut args = std::env::args().skip(1);
+    let flag = args.next();
+    if flag.as_deref() == Some("--http") {
+        let addr = args
+            .next()
+            .ok_or_else(|| anyhow!("--http needs an address, e.g. 127.0.0.1:8975"))?;
+        return HttpTransport::new(server).run(&addr);
+    }
+    if flag.as_deref() == Some("--self-test") {
+        // A scratch store, so the user's own is never touched
+        let dir =
+            std::env::temp_dir().join(format!("bookmark-mcp-self-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let report = server.self_test(&dir);
+        std::fs::remove_dir_all(&dir)?;
+        let report = report?;
+        println!("{}", report);
+        if !report.passed() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     server.run()
 }
@@ -1,4 +1,4 @@
-use anyhow::{anyhow, Result};
+use anyhow::{Result, anyhow};
 use dirs;
 use std::fmt;
 use std::fs;
@@ -39,16 +39,6 @@ impl FromStr for Browser {
 }
 
 impl Browser {
-    pub fn from_str(s: &str) -> Result<Self> {
-        match s.to_lowercase().as_str() {
-            "chrome" => Ok(Browser::Chrome),
-            "firefox" => Ok(Browser::Firefox),
-            "safari" => Ok(Browser::Safari),
-            "edge" => Ok(Browser::Edge),
-            _ => Err(anyhow!("Unsupported browser: {}", s)),
-        }
-    }
-
     pub fn get_default_data_dir(&self) -> Result<PathBuf> {
         let home = dirs::home_dir().ok_or_else(|| anyhow!("Could not find home directory"))?;
 
@@ -111,10 +101,10 @@ impl Browser {
                     let path = entry.path();
                     if path.is_dir() {
                         let profile_name = path.file_name().unwrap().to_string_lossy();
-                        if profile_name.contains("Profile") || profile_name == "Default" {
-                            if path.join("Bookmarks").exists() {
-                                profiles.push(path);
-                            }
+                        if (profile_name.contains("Profile") || profile_name == "Default")
+                            && path.join("Bookmarks").exists()
+                        {
+                            profiles.push(path);
                         }
                     }
                 }
@@ -130,10 +120,8 @@ impl Browser {
                 for entry in fs::read_dir(&base_dir)? {
                     let entry = entry?;
                     let path = entry.path();
-                    if path.is_dir() {
-                        if path.join("places.sqlite").exists() {
-                            profiles.push(path);
-                        }
+                    if path.is_dir() && path.join("places.sqlite").exists() {
+                        profiles.push(path);
                     }
                 }
             }
@@ -165,10 +153,10 @@ impl Browser {
                     let path = entry.path();
                     if path.is_dir() {
                         let profile_name = path.file_name().unwrap().to_string_lossy();
-                        if profile_name.contains("Profile") || profile_name == "Default" {
-                            if path.join("Bookmarks").exists() {
-                                profiles.push(path);
-                            }
+                        if (profile_name.contains("Profile") || profile_name == "Default")
+                            && path.join("Bookmarks").exists()
+                        {
+                            profiles.push(path);
                         }
                     }
                 }
@@ -184,10 +172,8 @@ impl Browser {
                 for entry in fs::read_dir(base_dir)? {
                     let entry = entry?;
                     let path = entry.path();
-                    if path.is_dir() {
-                        if path.join("places.sqlite").exists() {
-                            profiles.push(path);
-                        }
+                    if path.is_dir() && path.join("places.sqlite").exists() {
+                        profiles.push(path);
                     }
                 }
             }
@@ -202,6 +188,116 @@ impl Browser {
     }
 }
 
+/// Human-facing details of a browser profile, beyond its directory name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileInfo {
+    /// Name shown in the browser's profile picker (e.g. "Work")
+    pub display_name: Option<String>,
+    /// Account email signed in to the profile, when there is one
+    pub email: Option<String>,
+}
+
+impl Browser {
+    /// Resolve a profile's display name and email.
+    ///
+    /// Chrome and Edge keep these in `Local State` (`profile.info_cache`) next to
+    /// the profile directories, falling back to the profile's own `Preferences`;
+    /// Firefox names profiles in `profiles.ini`. Missing or unreadable files
+    /// yield an empty `ProfileInfo`.
+    pub fn profile_info(&self, profile_path: &Path) -> ProfileInfo {
+        match self {
+            Browser::Chrome | Browser::Edge => chromium_profile_info(profile_path),
+            Browser::Firefox => firefox_profile_info(profile_path),
+            Browser::Safari => ProfileInfo::default(),
+        }
+    }
+}
+
+impl Browser {
+    /// Version of the browser that last opened the profile, if recorded:
+    /// Chrome and Edge write `Last Version` next to the profile directories and
+    /// Firefox keeps `LastVersion` in the profile's `compatibility.ini`
+    pub fn version(&self, profile_path: &Path) -> Option<String> {
+        let version = match self {
+            Browser::Chrome | Browser::Edge => {
+                fs::read_to_string(profile_path.parent()?.join("Last Version")).ok()?
+            }
+            Browser::Firefox => {
+                let ini = fs::read_to_string(profile_path.join("compatibility.ini")).ok()?;
+                // e.g. `LastVersion=128.0_20240704121409/20240704121409`
+                ini.lines()
+                    .find_map(|l| l.trim().strip_prefix("LastVersion="))?
+                    .split('_')
+                    .next()?
+                    .to_string()
+            }
+            Browser::Safari => return None,
+        };
+        let version = version.trim();
+        (!version.is_empty()).then(|| version.to_string())
+    }
+}
+
+fn read_json(path: &Path) -> Option<serde_json::Value> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn non_empty(value: &serde_json::Value) -> Option<String> {
+    value.as_str().filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+fn chromium_profile_info(profile_path: &Path) -> ProfileInfo {
+    let dir_name = profile_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let cached = profile_path
+        .parent()
+        .and_then(|base| read_json(&base.join("Local State")))
+        .map(|state| state["profile"]["info_cache"][dir_name.as_ref()].clone())
+        .unwrap_or_default();
+    let prefs = read_json(&profile_path.join("Preferences")).unwrap_or_default();
+
+    ProfileInfo {
+        display_name: non_empty(&cached["name"]).or_else(|| non_empty(&prefs["profile"]["name"])),
+        email: non_empty(&cached["user_name"])
+            .or_else(|| non_empty(&prefs["account_info"][0]["email"])),
+    }
+}
+
+/// Look up the `Name=` of the `profiles.ini` section whose `Path=` points at
+/// `profile_path`. The ini sits in the Firefox root, one or two levels up
+/// depending on the platform layout.
+fn firefox_profile_info(profile_path: &Path) -> ProfileInfo {
+    for root in profile_path.ancestors().skip(1).take(2) {
+        let Ok(ini) = fs::read_to_string(root.join("profiles.ini")) else {
+            continue;
+        };
+        let mut name = None;
+        for line in ini.lines().map(str::trim) {
+            if line.starts_with('[') {
+                name = None;
+            } else if let Some(value) = line.strip_prefix("Name=") {
+                name = Some(value.to_string());
+            } else if let Some(value) = line.strip_prefix("Path=") {
+                let path = Path::new(value);
+                let path = if path.is_absolute() {
+                    path.to_path_buf()
+                } else {
+                    root.join(path)
+                };
+                if path == profile_path && name.is_some() {
+                    return ProfileInfo {
+                        display_name: name,
+                        email: None,
+                    };
+                }
+            }
+        }
+    }
+    ProfileInfo::default()
+}
+
 pub fn list_all_browsers() -> Result<()> {
     let browsers = ["Chrome", "Firefox", "Safari", "Edge"];
 
@@ -262,6 +358,77 @@ mod tests {
         assert!(Browser::from_str("chrome2").is_err());
     }
 
+    #[test]
+    fn test_chromium_profile_info() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = dir.path().join("Profile 1");
+        let personal = dir.path().join("Profile 2");
+        fs::create_dir_all(&work).unwrap();
+        fs::create_dir_all(&personal).unwrap();
+        fs::write(
+            dir.path().join("Local State"),
+            r#"{"profile":{"info_cache":{"Profile 1":{"name":"Work","user_name":"me@work.example"}}}}"#,
+        )
+        .unwrap();
+        fs::write(
+            personal.join("Preferences"),
+            r#"{"profile":{"name":"Personal"},"account_info":[{"email":"me@home.example"}]}"#,
+        )
+        .unwrap();
+
+        let info = Browser::Chrome.profile_info(&work);
+        assert_eq!(info.display_name.as_deref(), Some("Work"));
+        assert_eq!(info.email.as_deref(), Some("me@work.example"));
+
+        let info = Browser::Edge.profile_info(&personal);
+        assert_eq!(info.display_name.as_deref(), Some("Personal"));
+        assert_eq!(info.email.as_deref(), Some("me@home.example"));
+
+        let missing = dir.path().join("Profile 3");
+        assert_eq!(
+            Browser::Chrome.profile_info(&missing),
+            ProfileInfo::default()
+        );
+    }
+
+    #[test]
+    fn test_browser_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let profile = dir.path().join("Default");
+        fs::create_dir_all(&profile).unwrap();
+        assert_eq!(Browser::Chrome.version(&profile), None);
+
+        fs::write(dir.path().join("Last Version"), "126.0.6478.126\n").unwrap();
+        assert_eq!(
+            Browser::Chrome.version(&profile).as_deref(),
+            Some("126.0.6478.126")
+        );
+
+        fs::write(
+            profile.join("compatibility.ini"),
+            "[Compatibility]\nLastVersion=128.0_20240704121409/20240704121409\nLastOSABI=Linux_x86_64-gcc3\n",
+        )
+        .unwrap();
+        assert_eq!(Browser::Firefox.version(&profile).as_deref(), Some("128.0"));
+    }
+
+    #[test]
+    fn test_firefox_profile_info() {
+        let dir = tempfile::tempdir().unwrap();
+        let profile = dir.path().join("Profiles").join("abcd1234.default-release");
+        fs::create_dir_all(&profile).unwrap();
+        fs::write(
+            dir.path().join("profiles.ini"),
+            "[General]\nStartWithLastProfile=1\n\n\
+             [Profile0]\nName=default-release\nIsRelative=1\nPath=Profiles/abcd1234.default-release\n",
+        )
+        .unwrap();
+
+        let info = Browser::Firefox.profile_info(&profile);
+        assert_eq!(info.display_name.as_deref(), Some("default-release"));
+        assert_eq!(info.email, None);
+    }
+
     #[test]
     fn test_browser_display() {
         assert_eq!(format!("{}", Browser::Chrome), "Chrome");
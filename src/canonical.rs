@@ -0,0 +1,288 @@
+//! Site-specific URL canonicalization.
+//!
+//! Generic normalization (scheme, `www.`, query, trailing slash) misses the
+//! ways a few high-volume sites spell the same page: `youtu.be/ID` and
+//! `youtube.com/watch?v=ID`, `twitter.com` and `x.com`, a GitHub repository
+//! and its `/tree/main`. [`SiteRules::canonicalize`] rewrites those to one
+//! form before dedupe compares URLs and before the graph groups them by
+//! domain. Each site can be switched off in the config's `sites` section.
+//!
+//! Sites that moved to another domain (`hangouts.google.com` to
+//! `meet.google.com`) are handled by host aliases: the old host is swapped
+//! for the new one and the rest of the URL kept. [`BUILTIN_ALIASES`] covers
+//! a few well-known moves, and `sites.aliases` adds more.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use url::Url;
+
+/// Old host and the host it moved to, used unless `builtin_aliases` is off.
+/// `twitter.com` is the `twitter` rule's, which also drops its query.
+pub const BUILTIN_ALIASES: &[(&str, &str)] = &[
+    ("hangouts.google.com", "meet.google.com"),
+    ("travis-ci.org", "travis-ci.com"),
+    ("old.reddit.com", "reddit.com"),
+    ("fb.com", "facebook.com"),
+];
+
+const YOUTUBE_HOSTS: &[&str] = &[
+    "youtube.com",
+    "www.youtube.com",
+    "m.youtube.com",
+    "music.youtube.com",
+    "youtube-nocookie.com",
+    "www.youtube-nocookie.com",
+];
+const TWITTER_HOSTS: &[&str] = &[
+    "twitter.com",
+    "www.twitter.com",
+    "mobile.twitter.com",
+    "x.com",
+    "www.x.com",
+    "mobile.x.com",
+];
+const GITHUB_HOSTS: &[&str] = &["github.com", "www.github.com"];
+
+/// Which site-specific rewrites to apply
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SiteRules {
+    /// `youtu.be/ID`, `/embed/ID`, `/shorts/ID` and extra query parameters
+    /// become `https://www.youtube.com/watch?v=ID`
+    pub youtube: bool,
+    /// `twitter.com` and `mobile.` hosts become `x.com`, without query
+    pub twitter: bool,
+    /// Drop `.git`, `/tree/<branch>` and `/blob/<branch>/README.md` from
+    /// repository URLs
+    pub github: bool,
+    /// Reduce every GitHub URL under a repository to the repository itself,
+    /// so issues, files and pull requests group with it
+    pub github_repo_level: bool,
+    /// Swap the hosts in [`BUILTIN_ALIASES`]
+    pub builtin_aliases: bool,
+    /// More old hosts and the hosts they moved to, e.g.
+    /// `{"docs.example.org": "example.dev"}`; these win over built-in ones
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl Default for SiteRules {
+    fn default() -> Self {
+        Self {
+            youtube: true,
+            twitter: true,
+            github: true,
+            github_repo_level: false,
+            builtin_aliases: true,
+            aliases: BTreeMap::new(),
+        }
+    }
+}
+
+impl SiteRules {
+    /// No rewrites at all
+    pub fn none() -> Self {
+        Self {
+            youtube: false,
+            twitter: false,
+            github: false,
+            github_repo_level: false,
+            builtin_aliases: false,
+            aliases: BTreeMap::new(),
+        }
+    }
+
+    /// The canonical form of `url`, or `url` unchanged when no rule applies
+    pub fn canonicalize(&self, url: &str) -> String {
+        self.rewrite(url)
+            .or_else(|| self.alias(url))
+            .unwrap_or_else(|| url.to_string())
+    }
+
+    /// The host `host` moved to, if it is an alias. `www.` is ignored.
+    pub fn alias_host(&self, host: &str) -> Option<&str> {
+        let host = host.to_lowercase();
+        let host = host.strip_prefix("www.").unwrap_or(&host);
+        self.aliases.get(host).map(String::as_str).or_else(|| {
+            self.builtin_aliases
+                .then(|| BUILTIN_ALIASES.iter().find(|(old, _)| *old == host))
+                .flatten()
+                .map(|(_, new)| *new)
+        })
+    }
+
+    /// `url` with its host swapped for the one it moved to, if it is an
+    /// alias. Unlike [`Self::rewrite`], the query is left to the caller.
+    pub fn alias(&self, url: &str) -> Option<String> {
+        let mut parsed = Url::parse(url).ok()?;
+        let new_host = self.alias_host(parsed.host_str()?)?;
+        parsed.set_host(Some(new_host)).ok()?;
+        Some(parsed.to_string())
+    }
+
+    /// The canonical form of `url` if a site rule recognizes it, after any
+    /// host alias. The result is final: its query (e.g. YouTube's `v`) is
+    /// part of the identity.
+    pub fn rewrite(&self, url: &str) -> Option<String> {
+        let aliased = self.alias(url);
+        let parsed = Url::parse(aliased.as_deref().unwrap_or(url)).ok()?;
+        let host = parsed.host_str().unwrap_or_default().to_lowercase();
+        let segments: Vec<&str> = parsed
+            .path_segments()
+            .map(|s| s.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+
+        if self.youtube && (YOUTUBE_HOSTS.contains(&host.as_str()) || host == "youtu.be") {
+            youtube_video_id(&parsed, &host, &segments)
+                .map(|id| format!("https://www.youtube.com/watch?v={}", id))
+        } else if self.twitter && TWITTER_HOSTS.contains(&host.as_str()) {
+            Some(format!("https://x.com/{}", segments.join("/")))
+        } else if (self.github || self.github_repo_level) && GITHUB_HOSTS.contains(&host.as_str()) {
+            self.github_path(&segments)
+                .map(|path| format!("https://github.com/{}", path))
+        } else {
+            None
+        }
+    }
+
+    /// Host to group `url` under: the canonical URL's host, aliases applied
+    pub fn canonical_host(&self, url: &str) -> Option<String> {
+        let canonical = Url::parse(&self.canonicalize(url)).ok()?;
+        let host = canonical.host_str()?;
+        Some(host.strip_prefix("www.").unwrap_or(host).to_string())
+    }
+
+    fn github_path(&self, segments: &[&str]) -> Option<String> {
+        let [owner, repo, rest @ ..] = segments else {
+            return None;
+        };
+        let repo = repo.strip_suffix(".git").unwrap_or(repo);
+        let repo_path = format!("{}/{}", owner, repo);
+        if self.github_repo_level {
+            return Some(repo_path);
+        }
+        match rest {
+            [] | ["tree", _] | ["blob", _, "README.md" | "readme.md"] => Some(repo_path),
+            _ => None,
+        }
+    }
+}
+
+fn youtube_video_id(parsed: &Url, host: &str, segments: &[&str]) -> Option<String> {
+    let id = match segments {
+        [id] if host == "youtu.be" => id.to_string(),
+        ["watch"] => parsed
+            .query_pairs()
+            .find(|(key, _)| key == "v")
+            .map(|(_, v)| v.into_owned())?,
+        ["embed" | "shorts" | "live" | "v", id] => id.to_string(),
+        _ => return None,
+    };
+    (!id.is_empty()).then_some(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_youtube_forms_share_one_url() {
+        let rules = SiteRules::default();
+        let canonical = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+        for url in [
+            "https://youtu.be/dQw4w9WgXcQ?t=42",
+            "https://m.youtube.com/watch?v=dQw4w9WgXcQ&feature=share",
+            "https://www.youtube.com/watch?list=PL1&v=dQw4w9WgXcQ",
+            "https://www.youtube.com/shorts/dQw4w9WgXcQ",
+            "https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ",
+        ] {
+            assert_eq!(rules.canonicalize(url), canonical, "{}", url);
+        }
+        // Channel pages are left alone
+        assert_eq!(
+            rules.canonicalize("https://www.youtube.com/@rustlang"),
+            "https://www.youtube.com/@rustlang"
+        );
+        assert_eq!(
+            SiteRules::none().canonicalize("https://youtu.be/dQw4w9WgXcQ"),
+            "https://youtu.be/dQw4w9WgXcQ"
+        );
+    }
+
+    #[test]
+    fn test_twitter_and_x_interchange() {
+        let rules = SiteRules::default();
+        assert_eq!(
+            rules.canonicalize("https://mobile.twitter.com/rustlang/status/1?s=20"),
+            "https://x.com/rustlang/status/1"
+        );
+        assert_eq!(
+            rules.canonicalize("https://x.com/rustlang/status/1"),
+            "https://x.com/rustlang/status/1"
+        );
+        assert_eq!(
+            rules
+                .canonical_host("https://twitter.com/rustlang")
+                .as_deref(),
+            Some("x.com")
+        );
+    }
+
+    #[test]
+    fn test_github_noise_and_repo_level() {
+        let rules = SiteRules::default();
+        assert_eq!(
+            rules.canonicalize("https://github.com/rust-lang/rust/tree/master"),
+            "https://github.com/rust-lang/rust"
+        );
+        assert_eq!(
+            rules.canonicalize("https://github.com/rust-lang/rust.git"),
+            "https://github.com/rust-lang/rust"
+        );
+        assert_eq!(
+            rules.canonicalize("https://github.com/rust-lang/rust/issues/1"),
+            "https://github.com/rust-lang/rust/issues/1"
+        );
+
+        let repo_level = SiteRules {
+            github_repo_level: true,
+            ..SiteRules::default()
+        };
+        assert_eq!(
+            repo_level.canonicalize("https://github.com/rust-lang/rust/issues/1"),
+            "https://github.com/rust-lang/rust"
+        );
+        assert_eq!(
+            repo_level.canonicalize("https://github.com/rust-lang"),
+            "https://github.com/rust-lang"
+        );
+    }
+
+    #[test]
+    fn test_moved_hosts_are_aliased() {
+        let mut rules = SiteRules::default();
+        assert_eq!(
+            rules.canonicalize("https://hangouts.google.com/call/abc?authuser=0"),
+            "https://meet.google.com/call/abc?authuser=0"
+        );
+        assert_eq!(
+            rules
+                .canonical_host("https://www.old.reddit.com/r/rust")
+                .as_deref(),
+            Some("reddit.com")
+        );
+        // The alias runs before the site rules
+        rules
+            .aliases
+            .insert("gh.example".to_string(), "github.com".to_string());
+        assert_eq!(
+            rules.canonicalize("https://gh.example/rust-lang/rust/tree/master"),
+            "https://github.com/rust-lang/rust"
+        );
+        assert_eq!(rules.alias("https://meet.google.com/abc"), None);
+
+        rules.builtin_aliases = false;
+        assert_eq!(rules.alias_host("hangouts.google.com"), None);
+        assert_eq!(rules.alias_host("WWW.gh.example"), Some("github.com"));
+    }
+}
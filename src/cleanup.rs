@@ -0,0 +1,248 @@
+//! Temporary files the tool leaves behind.
+//!
+//! Files handed to [`track`] are recorded in a manifest (`artifacts.json` in
+//! the cache directory) with the time they were made. Browser database snapshots and headless Chrome profiles are
+//! removed as soon as they are done with, but a crash or Ctrl-C leaves them
+//! behind; they are recognised by their name prefix instead. [`clean`]
+//! deletes whatever is older than a cutoff, and [`auto_clean`] runs it at
+//! most once an hour so stale files don't pile up.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name prefix of snapshot directories under the snapshots path
+pub const SNAPSHOT_PREFIX: &str = "bookmark-snapshot-";
+/// Name prefix of headless Chrome profiles in the system temp directory
+pub const CHROME_PROFILE_PREFIX: &str = "bookmark-chrome-";
+
+const MANIFEST_FILE: &str = "artifacts.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_cleaned: Option<DateTime<Utc>>,
+    #[serde(default)]
+    files: Vec<TrackedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrackedFile {
+    path: PathBuf,
+    created: DateTime<Utc>,
+}
+
+impl Manifest {
+    fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("Could not parse {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Could not read {}", path.display())),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Could not write {}", path.display()))
+    }
+}
+
+/// Where temporary files are looked for
+struct Locations {
+    manifest: PathBuf,
+    /// Directories swept for leftovers, with the name prefix they carry
+    leftovers: Vec<(PathBuf, &'static str)>,
+}
+
+impl Locations {
+    fn current() -> Self {
+        let paths = crate::paths::get();
+        Self {
+            manifest: paths.cache.join(MANIFEST_FILE),
+            leftovers: vec![
+                (paths.snapshots.clone(), SNAPSHOT_PREFIX),
+                (std::env::temp_dir(), CHROME_PROFILE_PREFIX),
+            ],
+        }
+    }
+}
+
+/// What a cleanup removed, or would remove on a dry run
+#[derive(Debug, Default)]
+pub struct CleanSummary {
+    pub removed: Vec<PathBuf>,
+    pub bytes: u64,
+}
+
+/// Record files the tool just created so a later cleanup can delete them
+pub fn track(files: &[PathBuf]) -> Result<()> {
+    track_in(&Locations::current(), files, Utc::now())
+}
+
+fn track_in(locations: &Locations, files: &[PathBuf], created: DateTime<Utc>) -> Result<()> {
+    let mut manifest = Manifest::load(&locations.manifest)?;
+    manifest.files.extend(files.iter().map(|path| TrackedFile {
+        path: path.clone(),
+        created,
+    }));
+    manifest.save(&locations.manifest)
+}
+
+/// Delete tracked files and leftover snapshots and Chrome profiles made
+/// before `before`, or all of them when it is `None`. A dry run only reports
+/// what would go.
+pub fn clean(before: Option<DateTime<Utc>>, dry_run: bool) -> Result<CleanSummary> {
+    clean_in(&Locations::current(), before, dry_run)
+}
+
+fn clean_in(
+    locations: &Locations,
+    before: Option<DateTime<Utc>>,
+    dry_run: bool,
+) -> Result<CleanSummary> {
+    let stale = |time: DateTime<Utc>| before.is_none_or(|before| time < before);
+    let mut summary = CleanSummary::default();
+    let mut manifest = Manifest::load(&locations.manifest)?;
+
+    let mut kept = Vec::new();
+    for file in manifest.files {
+        if !file.path.exists() {
+            // Already deleted by hand; just forget it
+            continue;
+        }
+        if stale(file.created) {
+            remove(&file.path, dry_run, &mut summary)?;
+            if dry_run {
+                kept.push(file);
+            }
+        } else {
+            kept.push(file);
+        }
+    }
+
+    for (dir, prefix) in &locations.leftovers {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let ours = entry.file_name().to_string_lossy().starts_with(prefix);
+            let modified = entry.metadata().and_then(|m| m.modified()).ok();
+            if ours && modified.is_some_and(|m| stale(m.into())) {
+                remove(&path, dry_run, &mut summary)?;
+            }
+        }
+    }
+
+    if !dry_run {
+        manifest.files = kept;
+        manifest.last_cleaned = Some(Utc::now());
+        manifest.save(&locations.manifest)?;
+    }
+    Ok(summary)
+}
+
+fn remove(path: &Path, dry_run: bool, summary: &mut CleanSummary) -> Result<()> {
+    let bytes = disk_usage(path);
+    if !dry_run {
+        let removed = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        removed.with_context(|| format!("Could not delete {}", path.display()))?;
+    }
+    summary.removed.push(path.to_path_buf());
+    summary.bytes += bytes;
+    Ok(())
+}
+
+fn disk_usage(path: &Path) -> u64 {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|e| disk_usage(&e.path())).sum())
+            .unwrap_or(0),
+        Ok(meta) => meta.len(),
+        Err(_) => 0,
+    }
+}
+
+/// Delete temporary files older than `max_age`, unless a cleanup already ran
+/// in the last hour. Failures are logged, never returned: a cleanup must not
+/// stop the command the user actually ran.
+pub fn auto_clean(max_age: Duration) {
+    let locations = Locations::current();
+    let due = match Manifest::load(&locations.manifest) {
+        Ok(manifest) => manifest
+            .last_cleaned
+            .is_none_or(|last| Utc::now() - last > Duration::hours(1)),
+        Err(e) => {
+            log::warn!("Skipping cleanup of temporary files: {:#}", e);
+            return;
+        }
+    };
+    if !due {
+        return;
+    }
+    match clean_in(&locations, Some(Utc::now() - max_age), false) {
+        Ok(summary) if !summary.removed.is_empty() => log::info!(
+            "Deleted {} stale temporary files ({} bytes)",
+            summary.removed.len(),
+            summary.bytes
+        ),
+        Ok(_) => {}
+        Err(e) => log::warn!("Cleanup of temporary files failed: {:#}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_removes_stale_files_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshots = dir.path().join("snapshots");
+        fs::create_dir_all(snapshots.join("bookmark-snapshot-abc")).unwrap();
+        fs::write(snapshots.join("bookmark-snapshot-abc/places.sqlite"), "db").unwrap();
+        fs::create_dir_all(snapshots.join("unrelated")).unwrap();
+        let locations = Locations {
+            manifest: dir.path().join(MANIFEST_FILE),
+            leftovers: vec![(snapshots.clone(), SNAPSHOT_PREFIX)],
+        };
+
+        let old = dir.path().join("graph_old.html");
+        let new = dir.path().join("graph_new.html");
+        let gone = dir.path().join("graph_gone.html");
+        fs::write(&old, "old").unwrap();
+        fs::write(&new, "new").unwrap();
+        let week_ago = Utc::now() - Duration::days(7);
+        track_in(&locations, &[old.clone(), gone], week_ago).unwrap();
+        track_in(&locations, std::slice::from_ref(&new), Utc::now()).unwrap();
+
+        // Snapshot directories are judged by their modification time
+        let cutoff = Utc::now() - Duration::days(1);
+        let dry = clean_in(&locations, Some(cutoff), true).unwrap();
+        assert_eq!(dry.removed, vec![old.clone()]);
+        assert!(old.exists());
+
+        let summary = clean_in(&locations, Some(cutoff), false).unwrap();
+        assert_eq!(summary.removed, vec![old.clone()]);
+        assert_eq!(summary.bytes, 3);
+        assert!(!old.exists() && new.exists());
+        let manifest = Manifest::load(&locations.manifest).unwrap();
+        assert_eq!(manifest.files.len(), 1);
+        assert!(manifest.last_cleaned.is_some());
+
+        let all = clean_in(&locations, None, false).unwrap();
+        assert_eq!(all.removed.len(), 2);
+        assert!(!snapshots.join("bookmark-snapshot-abc").exists());
+        assert!(snapshots.join("unrelated").exists());
+    }
+}
@@ -1,12 +1,38 @@
 use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
 use crate::browser::Browser;
+use crate::convert::Conversion;
 use crate::deduplication::MergeStrategy;
-use crate::exporter::export_data;
+use crate::diff::BookmarkDiff;
+use crate::digest::{self, Digest};
+use crate::edit::{Edit, EditChange, Filter};
+use crate::enrich::{EnrichOptions, Enricher};
+use crate::exporter::{
+    ApplyOptions, ChecksumStatus, ExportFormat, ExportOptions, MasterPasswordRequired,
+    export_data_with_options,
+};
+use crate::importer::{self, ImportFormat};
+use crate::integrations::github::GithubClient;
+use crate::integrations::papers::{PaperClient, PaperSummary};
+use crate::integrations::pinboard::PinboardClient;
+#[cfg(feature = "screenshots")]
+use crate::integrations::screenshots::{Chrome, ScreenshotOptions, ScreenshotSummary};
+use crate::integrations::wayback::ArchiveOptions;
+use crate::journal::JournalEntry;
 use crate::processor::{BookmarkProcessor, ProcessingConfig};
-use crate::{config, deduplication, exporter, graph, graph_output, organization, utils};
+use crate::search::{SearchOptions, matches_query};
+use crate::store::Store;
+use crate::synthetic::{self, SyntheticConfig};
+use crate::tree::FolderTree;
+use crate::{
+    backup, cleanup, config, deduplication, expiration, exporter, graph, graph_output, journal,
+    merge, organization, paths, reconcile, snapshot, utils,
+};
 
 /// Graph generation parameters (simpler function signature via struct)
 #[derive(Debug)]
@@ -17,6 +43,32 @@ pub struct GraphParams {
     pub max_total: Option<usize>,
     pub domain_only: bool,
     pub since: Option<String>,
+    /// Include tag edges
+    pub tags: bool,
+    /// Include category edges
+    pub categories: bool,
+    /// Enables similarity edges at this threshold when set
+    pub similarity_threshold: Option<f64>,
+    /// Collapse same-domain cliques into counts on the domain edges
+    pub aggregate_edges: bool,
+    /// Search query whose matches seed a focused neighborhood export
+    pub focus: Option<String>,
+    /// Number of hops to expand from the focus seeds
+    pub depth: usize,
+    /// Enrich GitHub repository bookmarks from the API before building
+    pub github: bool,
+    /// `dark`, `light` or a stylesheet for HTML output, overriding the config
+    pub theme: Option<String>,
+    /// Most nodes or edges per HTML data file; 0 keeps one file
+    pub chunk_size: usize,
+    /// `added` or `visited` to color bookmarks by age
+    pub heatmap: Option<String>,
+    /// Precompute node positions (GEXF, JSON and HTML)
+    pub layout: bool,
+    /// Refuse graphs estimated to have more nodes than this
+    pub max_nodes: Option<usize>,
+    /// Refuse graphs estimated to have more edges than this
+    pub max_edges: Option<usize>,
 }
 
 impl GraphParams {
@@ -30,25 +82,26 @@ impl GraphParams {
                 return Err(anyhow::anyhow!(
                     "Invalid detail level: {}. Use overview, standard, or detailed",
                     self.detail
-                ))
+                ));
             }
         };
 
-        let min_date = if let Some(ref date_str) = self.since {
-            Some(
-                chrono::DateTime::parse_from_rfc3339(date_str)
-                    .map_err(|_| {
-                        anyhow::anyhow!(
-                            "Invalid date format: {}. Use ISO 8601 format (e.g., 2024-01-01T00:00:00Z)",
-                            date_str
-                        )
-                    })?
-                    .with_timezone(&chrono::Utc),
-            )
-        } else {
-            None
-        };
+        let min_date = self.since.as_deref().map(parse_since).transpose()?;
+        let heatmap = self.heatmap.as_deref().map(str::parse).transpose()?;
+
+        if let Some(threshold) = self.similarity_threshold
+            && !(0.0..=1.0).contains(&threshold)
+        {
+            return Err(anyhow::anyhow!(
+                "Invalid similarity threshold: {}. Use a value between 0.0 and 1.0",
+                threshold
+            ));
+        }
+
+        let app_config = saved_config()?;
+        app_config.theme.validate()?;
 
+        let defaults = graph::GraphConfig::default();
         Ok(graph::GraphConfig {
             min_domain_threshold: self.min_threshold,
             detail_level,
@@ -56,34 +109,144 @@ impl GraphParams {
             max_total_bookmarks: self.max_total,
             domain_only: self.domain_only,
             min_date,
-            ..Default::default()
+            include_tag_edges: self.tags,
+            include_category_edges: self.categories,
+            include_similarity_edges: self.similarity_threshold.is_some(),
+            similarity_threshold: self
+                .similarity_threshold
+                .unwrap_or(defaults.similarity_threshold),
+            aggregate_edges: self.aggregate_edges,
+            max_nodes: self.max_nodes,
+            max_edges: self.max_edges,
+            sites: app_config.sites,
+            theme: app_config.theme,
+            heatmap,
+            ..defaults
         })
     }
 
+    /// The config's HTML theme, with `--theme` switching the color scheme or
+    /// replacing the stylesheet
+    pub fn html_theme(&self) -> Result<graph::formats::HtmlTheme> {
+        let mut html = saved_config()?.html;
+        match self.theme.as_deref() {
+            None => {}
+            Some("dark") => html.theme = graph::formats::ThemeMode::Dark,
+            Some("light") => html.theme = graph::formats::ThemeMode::Light,
+            Some(css) => html.css = Some(PathBuf::from(css)),
+        }
+        html.load()
+    }
+
     /// Print configuration summary
-    fn print_summary(&self, detail_level: &graph::DetailLevel, min_date: &Option<chrono::DateTime<chrono::Utc>>) {
-        println!("Graph configuration:");
-        println!("  Detail level: {:?}", detail_level);
-        println!("  Min domain threshold: {}", self.min_threshold);
+    fn print_summary(
+        &self,
+        detail_level: &graph::DetailLevel,
+        min_date: &Option<chrono::DateTime<chrono::Utc>>,
+    ) {
+        eprintln!("Graph configuration:");
+        eprintln!("  Detail level: {:?}", detail_level);
+        eprintln!("  Min domain threshold: {}", self.min_threshold);
         if let Some(max_per) = self.max_per_domain {
-            println!("  Max bookmarks per domain: {}", max_per);
+            eprintln!("  Max bookmarks per domain: {}", max_per);
         }
         if let Some(max_tot) = self.max_total {
-            println!("  Max total bookmarks: {}", max_tot);
+            eprintln!("  Max total bookmarks: {}", max_tot);
         }
         if self.domain_only {
-            println!("  Domain-only mode: enabled");
+            eprintln!("  Domain-only mode: enabled");
         }
         if let Some(date) = min_date {
-            println!("  Only bookmarks newer than: {}", date);
+            eprintln!("  Only bookmarks newer than: {}", date);
+        }
+        if self.tags {
+            eprintln!("  Tag edges: enabled");
+        }
+        if !self.categories {
+            eprintln!("  Category edges: disabled");
+        }
+        if let Some(threshold) = self.similarity_threshold {
+            eprintln!("  Similarity edges: threshold {}", threshold);
         }
+        if self.aggregate_edges {
+            eprintln!("  Edge aggregation: enabled");
+        }
+        if let Some(ref query) = self.focus {
+            eprintln!("  Focus: \"{}\" (depth {})", query, self.depth);
+        }
+        if self.github {
+            eprintln!("  GitHub lookups: enabled");
+        }
+        if let Some(ref by) = self.heatmap {
+            eprintln!("  Heatmap: by {}", by);
+        }
+    }
+}
+
+/// Parse `--since` as either a plain date (midnight UTC) or an RFC 3339 timestamp
+fn parse_since(date_str: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
+        return Ok(date.and_time(chrono::NaiveTime::MIN).and_utc());
     }
+    chrono::DateTime::parse_from_rfc3339(date_str)
+        .map(|d| d.with_timezone(&chrono::Utc))
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "Invalid date format: {}. Use YYYY-MM-DD or RFC 3339 (e.g., 2024-01-01T00:00:00Z)",
+                date_str
+            )
+        })
 }
 
+/// Parse `--as-of`: a plain date means the end of that day (UTC), so
+/// bookmarks added on it count; an RFC 3339 timestamp is taken as is
+pub fn parse_as_of(date_str: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    let at = parse_since(date_str)?;
+    if chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d").is_ok() {
+        Ok(at + chrono::Duration::days(1))
+    } else {
+        Ok(at)
+    }
+}
+
+/// Export, prompting once for a Firefox primary password if the profile needs one
+pub fn export_with_password_prompt(
+    browser_name: &str,
+    data_type: &str,
+    output_file: Option<PathBuf>,
+    profile_dir: Option<PathBuf>,
+    options: &ExportOptions,
+) -> Result<()> {
+    match export_data_with_options(
+        browser_name,
+        data_type,
+        output_file.clone(),
+        profile_dir.clone(),
+        options,
+    ) {
+        Err(e) if e.is::<MasterPasswordRequired>() && options.master_password.is_none() => {
+            let password = dialoguer::Password::new()
+                .with_prompt(format!("{} primary password", browser_name))
+                .interact()?;
+            let options = ExportOptions {
+                master_password: Some(password),
+                ..options.clone()
+            };
+            export_data_with_options(browser_name, data_type, output_file, profile_dir, &options)
+        }
+        result => result,
+    }
+}
+
+/// Export every browser found, each data type of each browser on its own
+/// thread. A source still running after `timeout` is reported and left
+/// behind, so one hung profile can't stall the rest.
 pub fn export_all_browsers(
     data_type: &str,
     output_dir: Option<PathBuf>,
     profile_dir: Option<PathBuf>,
+    options: &ExportOptions,
+    timeout: Option<Duration>,
 ) -> Result<()> {
     let browsers = ["Chrome", "Firefox", "Safari", "Edge"];
     let output_dir = output_dir.unwrap_or_else(|| PathBuf::from("."));
@@ -98,95 +261,579 @@ pub fn export_all_browsers(
         _ => vec![data_type],
     };
 
+    let mut sources = Vec::new();
+    let mut jobs: Vec<utils::Job<()>> = Vec::new();
     for browser_name in browsers {
-        if let Ok(browser) = Browser::from_str(browser_name) {
-            if let Ok(profiles) = browser.find_profiles(profile_dir.as_deref()) {
-                if !profiles.is_empty() {
-                    found.push(browser_name);
-                    for export_type in &export_types {
-                        let output_file = output_dir.join(format!(
-                            "{}-{}.yaml",
-                            browser_name.to_lowercase(),
-                            export_type
-                        ));
-                        println!("Exporting {} ({})...", browser_name, export_type);
-                        match export_data(
-                            browser_name,
-                            export_type,
-                            Some(output_file),
-                            profile_dir.clone(),
-                        ) {
-                            Ok(_) => println!("  ✓ Success"),
-                            Err(e) => println!("  ✗ Failed: {}", e),
-                        }
-                    }
-                }
+        if let Ok(browser) = Browser::from_str(browser_name)
+            && let Ok(profiles) = browser.find_profiles(profile_dir.as_deref())
+            && !profiles.is_empty()
+        {
+            found.push(browser_name);
+            for export_type in &export_types {
+                let output_file = output_dir.join(format!(
+                    "{}-{}.{}",
+                    browser_name.to_lowercase(),
+                    export_type,
+                    options.format.extension()
+                ));
+                println!("Exporting {} ({})...", browser_name, export_type);
+                sources.push((browser_name, *export_type, output_file.clone()));
+                let export_type = export_type.to_string();
+                let profile_dir = profile_dir.clone();
+                let options = options.clone();
+                jobs.push(Box::new(move || {
+                    export_data_with_options(
+                        browser_name,
+                        &export_type,
+                        Some(output_file),
+                        profile_dir,
+                        &options,
+                    )
+                }));
             }
         }
     }
 
+    let mut timed_out = Vec::new();
+    let outcomes = utils::run_with_timeout(jobs, timeout);
+    for ((browser_name, export_type, output_file), outcome) in sources.into_iter().zip(outcomes) {
+        let result = match outcome {
+            utils::JobOutcome::Done(Err(e)) if e.is::<MasterPasswordRequired>() => {
+                // Prompts can't share the terminal, so these wait for the rest
+                export_with_password_prompt(
+                    browser_name,
+                    export_type,
+                    Some(output_file),
+                    profile_dir.clone(),
+                    options,
+                )
+            }
+            utils::JobOutcome::Done(result) => result,
+            utils::JobOutcome::TimedOut => {
+                println!(
+                    "  ✗ {} ({}): timed out after {}s",
+                    browser_name,
+                    export_type,
+                    timeout.unwrap_or_default().as_secs()
+                );
+                timed_out.push(format!("{} ({})", browser_name, export_type));
+                continue;
+            }
+        };
+        match result {
+            Ok(_) => println!("  ✓ {} ({})", browser_name, export_type),
+            Err(e) => println!("  ✗ {} ({}): {}", browser_name, export_type, e),
+        }
+    }
+
     if found.is_empty() {
         println!("No browsers found");
     } else {
         println!("\nExported: {}", found.join(", "));
+        if !timed_out.is_empty() {
+            println!("Timed out: {}", timed_out.join(", "));
+        }
     }
     Ok(())
 }
 
-pub fn process_bookmarks(
-    input: &PathBuf,
-    output: &PathBuf,
-    mode: &str,
-    strategy: &str,
-    _org_strategy: &str,
-    preview: bool,
-    backup: bool,
+/// Import a bookmark file and write it out in the export YAML layout, so it
+/// can be fed to `process` like any browser export
+/// Convert an import file to an export, written to `output` (or stdout
+/// unless `store` is set), and with `store` add its bookmarks to the store
+/// unless already there (or, with `update`, overwriting those that are)
+pub fn import_bookmarks(
+    input: &Path,
+    format: &str,
+    output: Option<PathBuf>,
+    store: bool,
+    update: bool,
 ) -> Result<()> {
-    println!("Loading {}...", input.display());
+    let format = ImportFormat::from_str(format)?;
+    let bookmarks = importer::import_file(input, format)?;
+    eprintln!(
+        "Imported {} bookmarks from {}",
+        bookmarks.len(),
+        input.display()
+    );
+
+    let mut data = vec![exporter::BrowserData {
+        browser: format!("{:?}", format).to_lowercase(),
+        profile: input
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        export_date: chrono::Utc::now(),
+        bookmarks: Some(bookmarks),
+        history: None,
+        passwords: None,
+        aliases: None,
+        profile_name: None,
+        profile_email: None,
+        provenance: None,
+    }];
+    let settings = BTreeMap::from([
+        ("format".to_string(), data[0].browser.clone()),
+        ("source".to_string(), data[0].profile.clone()),
+    ]);
+    data[0].provenance = Some(exporter::Provenance::new("import", settings).describe(&data[0]));
+    let yaml_content = exporter::render_export(&data, ExportFormat::Yaml)?;
+
+    match output {
+        Some(path) => {
+            fs::write(&path, yaml_content)?;
+            println!("Data exported to {}", path.display());
+        }
+        None if store => {}
+        None => println!("{}", yaml_content),
+    }
+
+    if store {
+        let app_config = saved_config()?;
+        let deduplicator =
+            deduplication::BookmarkDeduplicator::new(deduplication::DeduplicationConfig {
+                sites: app_config.sites,
+                ..app_config.deduplication
+            });
+        let key = |url: &str| {
+            deduplicator
+                .normalize_url(url)
+                .unwrap_or_else(|_| url.to_string())
+        };
+        let summary = Store::open_default()?.import(data.remove(0), key, update)?;
+        println!(
+            "Store: {} new, {} updated, {} already known or repeated (skipped)",
+            summary.added, summary.updated, summary.skipped
+        );
+    }
+    Ok(())
+}
+
+/// Processing parameters for the `process` command
+#[derive(Debug)]
+pub struct ProcessParams {
+    pub mode: String,
+    pub strategy: String,
+    pub org_strategy: String,
+    pub preview: bool,
+    pub backup: bool,
+    /// Comma-separated stage names overriding the default pipeline
+    pub stages: Option<String>,
+    /// Write the processing report here (JSON if the extension is `.json`, else Markdown)
+    pub report: Option<PathBuf>,
+    /// History export providing visit counts for the `visited` strategy
+    pub history: Option<PathBuf>,
+    /// Probe URLs with HEAD requests to detect media types
+    pub probe_media: bool,
+    /// Wayback lookups for `all` bookmarks or only `dead` ones
+    pub archive: Option<String>,
+    /// Submit pages without a snapshot to Save Page Now
+    pub save_missing: bool,
+    /// Enrich bookmarks of GitHub repositories from the API
+    pub github: bool,
+    /// Dedupe on the URLs redirects end at
+    pub resolve_redirects: bool,
+    /// Write where the organizer files each bookmark and why (JSON if the
+    /// extension is `.json`, else CSV)
+    pub audit: Option<PathBuf>,
+    /// Write an HTML graph of what the run removed and moved
+    pub visual_diff: Option<PathBuf>,
+}
+
+/// The config file's settings, or the defaults when there is no config file.
+/// Unlike `AppConfig::load_or_create`, this never writes one.
+fn saved_config() -> Result<config::AppConfig> {
+    let path = config::AppConfig::get_default_config_path();
+    if path.exists() {
+        config::AppConfig::load_from_file(&path)
+    } else {
+        Ok(config::AppConfig::default())
+    }
+}
+
+fn pinboard_client() -> Result<PinboardClient> {
+    let config = config::AppConfig::load_or_create()?;
+    let token = config.pinboard.api_token.ok_or_else(|| {
+        anyhow::anyhow!(
+            "No Pinboard token configured. Set pinboard.api_token in {}",
+            config::AppConfig::get_default_config_path().display()
+        )
+    })?;
+    Ok(PinboardClient::new(&token))
+}
+
+/// Apply an export file's bookmarks to a browser profile
+pub fn apply_export(
+    input: &Path,
+    browser_name: &str,
+    profile_dir: Option<&Path>,
+    options: &ApplyOptions,
+) -> Result<()> {
+    let browser = Browser::from_str(browser_name)?;
+    if matches!(browser, Browser::Safari) {
+        return Err(anyhow::anyhow!(
+            "Applying bookmarks is only supported for Firefox, Chrome and Edge"
+        ));
+    }
+
+    let content = fs::read_to_string(input)?;
+    let browser_data: Vec<exporter::BrowserData> = serde_yaml::from_str(&content)?;
+    let is_profile =
+        |dir: &Path| dir.join("places.sqlite").exists() || dir.join("Bookmarks").exists();
+    let profile = match profile_dir {
+        Some(dir) if is_profile(dir) => dir.to_path_buf(),
+        _ => {
+            let profiles = browser.find_profiles(profile_dir)?;
+            // With several profiles, go back to the one the file was exported from
+            let mut candidates: Vec<PathBuf> = match profiles.len() {
+                1 => profiles,
+                _ => profiles
+                    .into_iter()
+                    .filter(|p| {
+                        p.file_name().is_some_and(|name| {
+                            browser_data.iter().any(|d| name == d.profile.as_str())
+                        })
+                    })
+                    .collect(),
+            };
+            if candidates.len() != 1 {
+                return Err(anyhow::anyhow!(
+                    "Could not pick a single {} profile; pass one with --profile-dir",
+                    browser_name
+                ));
+            }
+            candidates.remove(0)
+        }
+    };
+    let bookmarks: Vec<exporter::Bookmark> = browser_data
+        .into_iter()
+        .flat_map(|d| d.bookmarks.unwrap_or_default())
+        .collect();
+
+    let summary = exporter::apply_to_browser(&browser, &profile, &bookmarks, options)?;
+    if options.dry_run {
+        println!("Dry run, nothing written to {}", profile.display());
+    } else if !summary.pruned.is_empty() {
+        Store::open_default()?.trash_bookmarks(&summary.pruned, "prune")?;
+        println!("Pruned bookmarks are in the trash (see `bookmark trash list`)");
+    }
+    println!(
+        "Inserted: {} | Updated: {} | Unchanged: {} | Removed: {} | Folders created: {} | Skipped: {}",
+        summary.inserted,
+        summary.updated,
+        summary.unchanged,
+        summary.removed,
+        summary.folders_created,
+        summary.skipped
+    );
+    if let Some(backup) = summary.backup {
+        println!("Backup: {}", backup.display());
+    }
+    Ok(())
+}
+
+/// Write a synthetic collection in the export YAML layout
+pub fn generate_synthetic(config: &SyntheticConfig, output: Option<PathBuf>) -> Result<()> {
+    let start = std::time::Instant::now();
+    let data = synthetic::generate(config);
+    let yaml_content = exporter::render_export(&[data], ExportFormat::Yaml)?;
+    eprintln!(
+        "Generated {} bookmarks across {} domains in {:.2?}",
+        config.count,
+        config.domains,
+        start.elapsed()
+    );
+
+    match output {
+        Some(path) => {
+            fs::write(&path, yaml_content)?;
+            println!("Data exported to {}", path.display());
+        }
+        None => println!("{}", yaml_content),
+    }
+    Ok(())
+}
+
+/// Check an export file's checksum trailer and structure
+pub fn verify_export_file(path: &Path) -> Result<()> {
+    let content = fs::read_to_string(path)?;
+    let verification = exporter::verify_export(&content)
+        .map_err(|e| anyhow::anyhow!("{} failed verification: {}", path.display(), e))?;
+
+    println!(
+        "✓ {}: {:?} export, {} profile(s), {} bookmarks, {} history entries, {} passwords",
+        path.display(),
+        verification.format,
+        verification.profiles,
+        verification.counts.bookmarks,
+        verification.counts.history,
+        verification.counts.passwords
+    );
+    match verification.checksum {
+        ChecksumStatus::Valid => println!("  Checksum valid"),
+        ChecksumStatus::Missing => println!("  No checksum (structure checked only)"),
+    }
+    Ok(())
+}
+
+pub fn pinboard_pull(output: Option<PathBuf>) -> Result<()> {
+    let bookmarks = pinboard_client()?.fetch_all()?;
+    eprintln!("Fetched {} posts from Pinboard", bookmarks.len());
+
+    let mut data = vec![exporter::BrowserData {
+        browser: "pinboard".to_string(),
+        profile: "pinboard".to_string(),
+        export_date: chrono::Utc::now(),
+        bookmarks: Some(bookmarks),
+        history: None,
+        passwords: None,
+        aliases: None,
+        profile_name: None,
+        profile_email: None,
+        provenance: None,
+    }];
+    data[0].provenance =
+        Some(exporter::Provenance::new("pinboard pull", BTreeMap::new()).describe(&data[0]));
+    let yaml_content = exporter::render_export(&data, ExportFormat::Yaml)?;
+
+    match output {
+        Some(path) => {
+            fs::write(&path, yaml_content)?;
+            println!("Data exported to {}", path.display());
+        }
+        None => println!("{}", yaml_content),
+    }
+    Ok(())
+}
+
+pub fn pinboard_push(input: &Path, dry_run: bool) -> Result<()> {
     let content = fs::read_to_string(input)?;
     let browser_data: Vec<exporter::BrowserData> = serde_yaml::from_str(&content)?;
+    let bookmarks: Vec<exporter::Bookmark> = browser_data
+        .into_iter()
+        .flat_map(|d| d.bookmarks.unwrap_or_default())
+        .collect();
+
+    if dry_run {
+        for bookmark in &bookmarks {
+            println!(
+                "Would push: {} ({})",
+                bookmark.title,
+                bookmark.url.as_deref().unwrap_or("no URL, skipped")
+            );
+        }
+        return Ok(());
+    }
+
+    println!(
+        "Pushing {} bookmarks (Pinboard allows one write every 3 seconds)...",
+        bookmarks.len()
+    );
+    let summary = pinboard_client()?.push(&bookmarks)?;
+    println!(
+        "Pushed: {} | Skipped (no URL): {}",
+        summary.pushed, summary.skipped
+    );
+    Ok(())
+}
+
+pub fn process_bookmarks(input: &PathBuf, output: &PathBuf, params: ProcessParams) -> Result<()> {
+    let ProcessParams {
+        mode,
+        strategy,
+        org_strategy: _org_strategy,
+        preview,
+        backup,
+        stages,
+        report,
+        history,
+        probe_media,
+        archive,
+        save_missing,
+        github,
+        resolve_redirects,
+        audit,
+        visual_diff,
+    } = params;
+
+    println!("Loading {}...", input.display());
+    let content = fs::read_to_string(input)?;
+    let mut browser_data: Vec<exporter::BrowserData> = serde_yaml::from_str(&content)?;
+    if let Some(path) = &history {
+        println!("Loading history {}...", path.display());
+        let content = fs::read_to_string(path)?;
+        browser_data.extend(serde_yaml::from_str::<Vec<exporter::BrowserData>>(
+            &content,
+        )?);
+    }
 
     let mut all_bookmarks = Vec::new();
+    let mut visit_counts: HashMap<String, i64> = HashMap::new();
     for data in browser_data {
         if let Some(bookmarks) = data.bookmarks {
             all_bookmarks.extend(bookmarks);
         }
+        for entry in data.history.into_iter().flat_map(|h| h.urls) {
+            *visit_counts.entry(entry.url).or_insert(0) += entry.visit_count;
+        }
     }
 
     println!("Loaded {} bookmarks", all_bookmarks.len());
 
-    let merge_strategy = match strategy {
-        "first" => MergeStrategy::KeepFirst,
-        "last" => MergeStrategy::KeepLast,
-        "recent" => MergeStrategy::KeepMostRecent,
-        "merge" => MergeStrategy::MergeMetadata,
-        _ => return Err(anyhow::anyhow!("Invalid strategy: {}", strategy)),
-    };
+    let merge_strategy = MergeStrategy::from_str(&strategy)?;
+    if matches!(merge_strategy, MergeStrategy::KeepMostVisited) && visit_counts.is_empty() {
+        eprintln!(
+            "Warning: no history loaded; the visited strategy will keep the first duplicate. \
+             Pass --history or an export that includes history."
+        );
+    }
 
     let dedupe_enabled = mode == "dedupe" || mode == "both";
+    let archive = match archive.as_deref() {
+        None => None,
+        Some(which @ ("all" | "dead")) => Some(ArchiveOptions {
+            dead_only: which == "dead",
+            save_missing,
+        }),
+        Some(other) => {
+            return Err(anyhow::anyhow!(
+                "Invalid --archive value: {} (expected all or dead)",
+                other
+            ));
+        }
+    };
+
+    let app_config = saved_config()?;
+    let html_theme = match visual_diff {
+        Some(_) => Some(app_config.html.load()?),
+        None => None,
+    };
 
     let config = ProcessingConfig {
         deduplication_config: deduplication::DeduplicationConfig {
             merge_strategy,
             normalize_urls: dedupe_enabled,
+            sites: app_config.sites.clone(),
+            resolve_redirects,
             ..Default::default()
         },
         organization_config: organization::OrganizationConfig {
             organize_by_domain: _org_strategy == "domain" || _org_strategy == "custom",
-            organize_by_category: _org_strategy == "category" || _org_strategy == "custom",
+            organize_by_category: matches!(_org_strategy.as_str(), "category" | "media" | "custom"),
+            organize_by_media: _org_strategy == "media",
+            custom_rules: app_config.organization.custom_rules,
+            sites: app_config.sites,
             ..Default::default()
         },
         dry_run: preview,
         backup_original: backup,
+        visit_counts,
+        expire_policies: app_config.expire,
+        folder_quotas: app_config.quotas,
+        probe_media,
+        archive,
+        github: github.then_some(app_config.github),
     };
 
-    let processor = BookmarkProcessor::new(config);
+    let mut processor = BookmarkProcessor::new(config);
+    if let Some(names) = stages {
+        let stages = names
+            .split(',')
+            .map(|name| processor.config().stage(name.trim()))
+            .collect::<Result<Vec<_>>>()?;
+        processor = processor.with_stages(stages);
+    }
     let result = processor.process_bookmarks(&all_bookmarks)?;
 
     if !preview {
+        let previous = fs::read_to_string(output).ok();
         processor.export_processed_bookmarks(&result.processed_bookmarks, output)?;
+        let entry = JournalEntry {
+            id: 0,
+            operation: "process".to_string(),
+            at: chrono::Utc::now(),
+            output: output.canonicalize()?,
+            previous,
+            written: fs::read_to_string(output)?,
+            input: all_bookmarks.clone(),
+            settings: processor.provenance_settings(),
+            undone_at: None,
+        };
+        match Store::open_default().and_then(|mut store| store.record_journal(&entry)) {
+            Ok(id) => println!("Recorded as run {}; revert it with `bookmark undo`", id),
+            Err(e) => eprintln!("Warning: could not record this run for undo: {:#}", e),
+        }
+    }
+
+    for error in &result.processing_summary.rule_errors {
+        eprintln!("Warning: skipped invalid rule. {}", error);
     }
 
+    for stage in &result.processing_summary.stages {
+        println!(
+            "  {:<10} {} → {} {:>10.1?}  {}",
+            stage.stage,
+            stage.input_count,
+            stage.output_count,
+            stage.duration,
+            stage.notes.join("; ")
+        );
+    }
+
+    if let Some(path) = report {
+        let content = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::to_string_pretty(&result.processing_summary)?
+        } else {
+            processor.generate_report(&result)
+        };
+        fs::write(&path, content)?;
+        println!("Report written to {}", path.display());
+    }
+    if let Some(path) = audit {
+        let organizer =
+            organization::BookmarkOrganizer::new(processor.config().organization_config.clone());
+        let entries = organizer.audit(&all_bookmarks);
+        let content = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::to_string_pretty(&entries)?
+        } else {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for entry in &entries {
+                writer.serialize(entry)?;
+            }
+            String::from_utf8(writer.into_inner()?)?
+        };
+        fs::write(&path, content)?;
+        println!(
+            "Audit of {} bookmarks written to {}",
+            entries.len(),
+            path.display()
+        );
+    }
+    if let (Some(path), Some(theme)) = (visual_diff, html_theme) {
+        let deduplicator =
+            deduplication::BookmarkDeduplicator::new(deduplication::DeduplicationConfig {
+                resolve_redirects: false,
+                ..processor.config().deduplication_config.clone()
+            });
+        let graph = graph::KnowledgeGraph::visual_diff(
+            &all_bookmarks,
+            &result.processed_bookmarks,
+            |url| {
+                deduplicator
+                    .normalize_url(url)
+                    .unwrap_or_else(|_| url.to_string())
+            },
+        );
+        fs::write(&path, graph::formats::to_html(&graph, &theme))?;
+        let config = &graph.metadata.config;
+        println!(
+            "Visual diff written to {} ({} removed, {} moved)",
+            path.display(),
+            config["removed"],
+            config["moved"]
+        );
+    }
     println!(
         "Original: {} | Final: {} | Duplicates removed: {}",
         result.processing_summary.original_count,
@@ -197,17 +844,332 @@ pub fn process_bookmarks(
     Ok(())
 }
 
+/// Fetch page metadata (and with `papers`, citation metadata) for the
+/// bookmarks in `input` and write the updated export to `output`; without
+/// one, only list what would change
+pub fn enrich_bookmarks(
+    input: &Path,
+    output: Option<&Path>,
+    options: EnrichOptions,
+    papers: bool,
+    bibtex: Option<&Path>,
+    #[cfg(feature = "screenshots")] screenshots: Option<ScreenshotOptions>,
+) -> Result<()> {
+    let content = fs::read_to_string(input)?;
+    let mut browser_data: Vec<exporter::BrowserData> = serde_yaml::from_str(&content)?;
+
+    eprintln!("Fetching pages ({} at a time)...", options.concurrency);
+    let enricher = Enricher::new(options);
+    let mut changes = Vec::new();
+    let mut fetched = 0;
+    let mut failures = Vec::new();
+    for data in &mut browser_data {
+        if let Some(bookmarks) = &mut data.bookmarks {
+            let report = enricher.enrich(bookmarks)?;
+            fetched += report.fetched;
+            changes.extend(report.changes);
+            failures.extend(report.failures);
+        }
+    }
+
+    for change in &changes {
+        println!(
+            "{} {}: {} -> {}",
+            change.url,
+            change.field,
+            change.old.as_deref().unwrap_or("(none)"),
+            change.new
+        );
+    }
+    for failure in &failures {
+        eprintln!("Warning: could not fetch {}", failure);
+    }
+    println!(
+        "Fetched: {} | Changes: {} | Failed: {}",
+        fetched,
+        changes.len(),
+        failures.len()
+    );
+
+    if papers {
+        eprintln!("Looking up papers (one request every 3 seconds)...");
+        let client = PaperClient::new();
+        let mut summary = PaperSummary::default();
+        for data in &mut browser_data {
+            if let Some(bookmarks) = &mut data.bookmarks {
+                let found = client.enrich(bookmarks);
+                summary.papers += found.papers;
+                summary.found += found.found;
+                summary.failures.extend(found.failures);
+            }
+        }
+        for failure in &summary.failures {
+            eprintln!("Warning: {}", failure);
+        }
+        println!(
+            "Papers: {} | With metadata: {} | Failed: {}",
+            summary.papers,
+            summary.found,
+            summary.failures.len()
+        );
+    }
+    #[cfg(feature = "screenshots")]
+    if let Some(options) = screenshots {
+        let dir = paths::get().archives.join("screenshots");
+        eprintln!("Capturing screenshots into {}...", dir.display());
+        let mut chrome = Chrome::launch(None)?;
+        let mut summary = ScreenshotSummary::default();
+        for data in &mut browser_data {
+            if let Some(bookmarks) = &mut data.bookmarks {
+                let captured = chrome.capture(bookmarks, &dir, &options);
+                summary.captured += captured.captured;
+                summary.existing += captured.existing;
+                summary.failures.extend(captured.failures);
+            }
+        }
+        for failure in &summary.failures {
+            eprintln!("Warning: no screenshot of {}", failure);
+        }
+        println!(
+            "Screenshots: {} captured | {} already saved | Failed: {}",
+            summary.captured,
+            summary.existing,
+            summary.failures.len()
+        );
+    }
+    if let Some(path) = bibtex {
+        fs::write(path, exporter::to_bibtex(&browser_data))?;
+        println!("BibTeX written to {}", path.display());
+    }
+
+    match output {
+        Some(path) => {
+            fs::write(
+                path,
+                exporter::render_export(&browser_data, ExportFormat::Yaml)?,
+            )?;
+            println!("Enriched bookmarks written to {}", path.display());
+        }
+        None => println!("Dry run: nothing written"),
+    }
+    Ok(())
+}
+
+/// Apply `edit` to every bookmark `filter` matches, in the export file
+/// `input` (written to `output`, else back to `input`) or in the store. The
+/// changes are listed before anything is written; `dry_run` stops there.
+pub fn edit_bookmarks(
+    filter: &Filter,
+    edit: &Edit,
+    input: Option<&Path>,
+    output: Option<&Path>,
+    dry_run: bool,
+) -> Result<()> {
+    if edit.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Nothing to change; pass --set, --add-tag or --remove-tag"
+        ));
+    }
+
+    let Some(input) = input else {
+        let mut store = Store::open_if_synced()?.ok_or_else(|| {
+            anyhow::anyhow!("The bookmark store is empty; run `bookmark sync` or pass --input")
+        })?;
+        let mut changes = Vec::new();
+        let mut edited = Vec::new();
+        for mut bookmark in store.bookmarks()? {
+            if filter.matches(&bookmark) {
+                let changed = edit.apply(&mut bookmark);
+                if !changed.is_empty() {
+                    changes.extend(changed);
+                    edited.push(bookmark);
+                }
+            }
+        }
+        print_edit_changes(&changes);
+        if dry_run || edited.is_empty() {
+            return Ok(());
+        }
+        let updated = store.update_bookmarks(&edited)?;
+        println!("Updated {} bookmarks in the store", updated);
+        return Ok(());
+    };
+
+    let content = fs::read_to_string(input)?;
+    let mut browser_data: Vec<exporter::BrowserData> = serde_yaml::from_str(&content)?;
+    let mut changes = Vec::new();
+    for data in &mut browser_data {
+        if let Some(bookmarks) = &mut data.bookmarks {
+            changes.extend(edit.apply_matching(filter, bookmarks));
+        }
+    }
+    print_edit_changes(&changes);
+    if dry_run || changes.is_empty() {
+        return Ok(());
+    }
+    let output = output.unwrap_or(input);
+    fs::write(
+        output,
+        exporter::render_export(&browser_data, ExportFormat::Yaml)?,
+    )?;
+    println!("Edited bookmarks written to {}", output.display());
+    Ok(())
+}
+
+/// List edit changes as a diff grouped by bookmark
+fn print_edit_changes(changes: &[EditChange]) {
+    let mut bookmarks = 0;
+    let mut last = None;
+    for change in changes {
+        if last != Some((&change.id, &change.url)) {
+            println!("{}", change.url);
+            last = Some((&change.id, &change.url));
+            bookmarks += 1;
+        }
+        if let Some(old) = &change.old {
+            println!("  - {}: {}", change.field, old);
+        }
+        if let Some(new) = &change.new {
+            println!("  + {}: {}", change.field, new);
+        }
+    }
+    println!("Changes: {} across {} bookmarks", changes.len(), bookmarks);
+}
+
+/// Turn an export file's folders into tags or its tags into folders
+pub fn convert_bookmarks(input: &Path, output: &Path, conversion: &Conversion) -> Result<()> {
+    let content = fs::read_to_string(input)?;
+    let mut browser_data: Vec<exporter::BrowserData> = serde_yaml::from_str(&content)?;
+
+    let mut converted = 0;
+    let mut total = 0;
+    for data in &mut browser_data {
+        if let Some(bookmarks) = &mut data.bookmarks {
+            let before = exporter::flatten_bookmarks(bookmarks);
+            let after = conversion.apply(bookmarks);
+            converted += before
+                .iter()
+                .zip(&after)
+                .filter(|(b, a)| b.folder != a.folder || b.tags != a.tags)
+                .count();
+            total += after.len();
+            *bookmarks = after;
+        }
+    }
+
+    fs::write(
+        output,
+        exporter::render_export(&browser_data, ExportFormat::Yaml)?,
+    )?;
+    println!(
+        "Converted {} of {} bookmarks, written to {}",
+        converted,
+        total,
+        output.display()
+    );
+    Ok(())
+}
+
+/// Where `graph` sends its output
+pub enum GraphTarget {
+    File(PathBuf),
+    /// `-o -`: the graph alone on stdout, for pipelines
+    Stdout,
+    Clipboard,
+}
+
+/// Build and write a knowledge graph. Status messages go to stderr so that
+/// `-o -` leaves nothing but the graph on stdout.
+/// Bookmarks and history to graph. Graphs of every browser come from the
+/// store once it has been synced, unless `live` is set; `as_of` reads the
+/// store as it stood then (see [`Store::bookmarks_as_of`]).
+fn load_graph_data(
+    browser: &str,
+    data_type: &str,
+    live: bool,
+    as_of: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<(Vec<exporter::Bookmark>, Vec<exporter::UrlEntry>)> {
+    if let Some(at) = as_of {
+        if browser != "all" {
+            return Err(anyhow::anyhow!(
+                "--as-of reads the bookmark store, which merges every browser; drop --browser"
+            ));
+        }
+        let store = Store::open_if_synced()?.ok_or_else(|| {
+            anyhow::anyhow!("--as-of reads the bookmark store; run `bookmark sync` first")
+        })?;
+        eprintln!(
+            "Using bookmark store as it stood before {}",
+            at.format("%Y-%m-%d %H:%M UTC")
+        );
+        let bookmarks = match data_type {
+            "history" => Vec::new(),
+            _ => store
+                .bookmarks_as_of(at)?
+                .into_iter()
+                .map(|(bookmark, _)| bookmark)
+                .collect(),
+        };
+        let history = match data_type {
+            "bookmarks" => Vec::new(),
+            _ => store.history_as_of(at)?,
+        };
+        return Ok((bookmarks, history));
+    }
+    if browser == "all"
+        && !live
+        && let Some(store) = Store::open_if_synced()?
+    {
+        if let Some(synced) = store.last_synced()? {
+            eprintln!(
+                "Using bookmark store (last synced {}); pass --live to read browsers directly",
+                synced.format("%Y-%m-%d %H:%M UTC")
+            );
+        }
+        let bookmarks = match data_type {
+            "history" => Vec::new(),
+            _ => store.bookmarks()?,
+        };
+        let history = match data_type {
+            "bookmarks" => Vec::new(),
+            _ => store.history()?,
+        };
+        return Ok((bookmarks, history));
+    }
+    exporter::load_browser_data(browser, data_type)
+}
+
 pub fn generate_graph(
     browser: &str,
     data_type: &str,
     format: &str,
-    output: PathBuf,
+    target: GraphTarget,
     params: GraphParams,
+    live: bool,
+    as_of: Option<chrono::DateTime<chrono::Utc>>,
 ) -> Result<()> {
-    println!("Generating knowledge graph...");
+    if params.layout && !matches!(format, "gexf" | "json" | "html") {
+        return Err(anyhow::anyhow!(
+            "--layout only applies to gexf, json and html output, not {}",
+            format
+        ));
+    }
+    eprintln!("Generating knowledge graph...");
 
-    let (bookmarks, history) = exporter::load_browser_data(browser, data_type)?;
+    let (mut bookmarks, history) = load_graph_data(browser, data_type, live, as_of)?;
     let config = params.to_config()?;
+    let theme = params.html_theme()?;
+    if params.github {
+        let token = saved_config()?.github.token();
+        let summary = GithubClient::new(token.as_deref()).enrich(&mut bookmarks);
+        eprintln!(
+            "GitHub: {} of {} repository bookmarks enriched",
+            summary.enriched, summary.repos
+        );
+        if let Some(error) = summary.error {
+            eprintln!("Warning: GitHub lookups stopped early: {}", error);
+        }
+    }
 
     // Print configuration summary
     let detail_level = config.detail_level;
@@ -222,45 +1184,883 @@ pub fn generate_graph(
         _ => return Err(anyhow::anyhow!("Invalid data type")),
     };
 
-    // Handle output based on format
-    if format == "html" {
-        let (html_path, data_path) = graph_output::write_html_output(&graph, &output)?;
-        graph_output::print_output_summary(&html_path, &data_path, &graph);
-        utils::open_file(&html_path)?;
-    } else {
-        write_simple_format(&graph, &output, format)?;
+    let mut graph = match params.focus {
+        Some(ref query) => focus_graph(&graph, query, params.depth)?,
+        None => graph,
+    };
+    if params.layout {
+        eprintln!("  Laying out {} nodes...", graph.nodes.len());
+        graph.apply_layout();
     }
 
-    println!("✓ Graph generated: {}", output.display());
-    println!(
+    // Handle output based on format
+    let destination = match target {
+        GraphTarget::File(output) if format == "html" => {
+            let (html_path, data_path) =
+                graph_output::write_html_output(&graph, &output, &theme, params.chunk_size)?;
+            graph_output::print_output_summary(&html_path, &data_path, &graph);
+            utils::open_file(&html_path)?;
+            output.display().to_string()
+        }
+        GraphTarget::File(output) => {
+            fs::write(&output, render_graph(&graph, format, &theme)?)?;
+            output.display().to_string()
+        }
+        GraphTarget::Stdout => {
+            print!("{}", render_graph(&graph, format, &theme)?);
+            "stdout".to_string()
+        }
+        GraphTarget::Clipboard => {
+            utils::copy_to_clipboard(&render_graph(&graph, format, &theme)?)?;
+            "clipboard".to_string()
+        }
+    };
+
+    eprintln!("✓ Graph generated: {}", destination);
+    eprintln!(
         "  Nodes: {} (bookmarks: {}, domains: {}, folders: {})",
         graph.metadata.total_nodes,
         graph.metadata.bookmark_count,
         graph.metadata.domain_count,
         graph.metadata.folder_count
     );
-    println!("  Edges: {}", graph.metadata.total_edges);
+    eprintln!("  Edges: {}", graph.metadata.total_edges);
 
     Ok(())
 }
 
-/// Write graph in simple formats (DOT, JSON, GEXF)
-fn write_simple_format(graph: &graph::KnowledgeGraph, output: &Path, format: &str) -> Result<()> {
-    let content = match format {
+/// Seed the graph with bookmark nodes matching `query` and keep their neighborhood
+fn focus_graph(
+    graph: &graph::KnowledgeGraph,
+    query: &str,
+    depth: usize,
+) -> Result<graph::KnowledgeGraph> {
+    let options = SearchOptions {
+        title_only: false,
+        url_only: false,
+        limit: usize::MAX,
+        media_type: None,
+        sort: Default::default(),
+    };
+    let seeds: Vec<String> = graph
+        .nodes
+        .iter()
+        .filter(|n| n.node_type == graph::NodeType::Bookmark)
+        .filter(|n| matches_query(&n.title, n.url.as_deref(), query, &options))
+        .map(|n| n.id.clone())
+        .collect();
+
+    if seeds.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No bookmarks in the graph match focus query '{}'. Try --detail detailed or a broader query",
+            query
+        ));
+    }
+
+    eprintln!("  Focus seeds: {} matching bookmarks", seeds.len());
+    let mut focused = graph.neighborhood(&seeds, depth);
+    focused
+        .metadata
+        .config
+        .insert("focus".to_string(), format!("{} (depth {})", query, depth));
+    Ok(focused)
+}
+
+/// Render a graph as a single document. HTML is the self-contained variant
+/// with the data inlined, since there is no directory to put a data file in.
+fn render_graph(
+    graph: &graph::KnowledgeGraph,
+    format: &str,
+    theme: &graph::formats::HtmlTheme,
+) -> Result<String> {
+    Ok(match format {
         "dot" => graph::formats::to_dot(graph),
         "json" => graph::formats::to_json(graph),
         "gexf" => graph::formats::to_gexf(graph),
+        "mermaid" => graph::formats::to_mermaid(graph),
+        "html" => graph::formats::to_html(graph, theme),
         _ => {
             return Err(anyhow::anyhow!(
-                "Invalid format: {}. Use dot, json, gexf, or html",
+                "Invalid format: {}. Use dot, json, gexf, mermaid, or html",
                 format
-            ))
+            ));
         }
+    })
+}
+
+/// Refresh the bookmark store from one browser or all of them
+pub fn sync_store(browser: &str, data_type: &str, full: bool) -> Result<()> {
+    let mut store = Store::open_default()?;
+    let synced = store.sync(browser, data_type, full)?;
+    if synced.is_empty() {
+        println!("No browser profiles found to sync.");
+        return Ok(());
+    }
+
+    for profile in &synced {
+        let counts: Vec<String> = [
+            ("bookmarks", profile.bookmarks),
+            ("history", profile.history),
+        ]
+        .into_iter()
+        .filter_map(|(label, count)| count.map(|n| format!("{} {}", n, label)))
+        .collect();
+        let mode = if profile.incremental {
+            "new since last sync"
+        } else {
+            "full"
+        };
+        println!(
+            "✓ {} ({}): {} ({})",
+            profile.browser,
+            profile.profile,
+            counts.join(", "),
+            mode
+        );
+    }
+    let stats = store.stats()?;
+    println!(
+        "Store {}: {} bookmarks, {} history URLs from {} profiles",
+        paths::get().store.display(),
+        stats.bookmarks,
+        stats.history,
+        stats.profiles
+    );
+    let trashed: usize = synced.iter().map(|p| p.trashed).sum();
+    if trashed > 0 {
+        println!(
+            "Moved {} bookmarks deleted from every browser to the trash (see `bookmark trash list`)",
+            trashed
+        );
+    }
+    store.empty_trash(Some(saved_config()?.trash.cutoff()))?;
+    Ok(())
+}
+
+/// Show the store's trash
+pub fn trash_list() -> Result<()> {
+    let mut store = Store::open_default()?;
+    store.empty_trash(Some(saved_config()?.trash.cutoff()))?;
+    let entries = store.trash()?;
+    if entries.is_empty() {
+        println!("The trash is empty.");
+        return Ok(());
+    }
+    for entry in &entries {
+        println!(
+            "{:>5}  {}  {:<5}  {}",
+            entry.id,
+            entry.deleted_at.format("%Y-%m-%d %H:%M"),
+            entry.reason,
+            entry.bookmark.title
+        );
+        println!(
+            "       {}{}",
+            entry.bookmark.url.as_deref().unwrap_or_default(),
+            entry
+                .bookmark
+                .folder
+                .as_deref()
+                .map(|f| format!("  ({})", f))
+                .unwrap_or_default()
+        );
+    }
+    println!(
+        "{} bookmarks in the trash; restore with `bookmark trash restore <id>... -o file.yaml`",
+        entries.len()
+    );
+    Ok(())
+}
+
+/// Take bookmarks out of the trash into an export file, ready for `apply`
+pub fn trash_restore(ids: &[i64], output: &Path) -> Result<()> {
+    let mut store = Store::open_default()?;
+    let bookmarks = store.restore_from_trash(ids)?;
+    let data = exporter::BrowserData {
+        browser: "trash".to_string(),
+        profile: "restored".to_string(),
+        export_date: chrono::Utc::now(),
+        bookmarks: Some(bookmarks),
+        history: None,
+        passwords: None,
+        aliases: None,
+        profile_name: None,
+        profile_email: None,
+        provenance: None,
     };
-    fs::write(output, content)?;
+    fs::write(
+        output,
+        exporter::render_export(&[data], ExportFormat::Yaml)?,
+    )?;
+    println!(
+        "Restored {} bookmarks to {}; add them back with `bookmark apply {}`",
+        ids.len(),
+        output.display(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Delete trashed bookmarks for good: all, or those older than `older_than` days
+pub fn trash_empty(older_than: Option<u32>) -> Result<()> {
+    let mut store = Store::open_default()?;
+    let before = older_than.map(|days| chrono::Utc::now() - chrono::Duration::days(days.into()));
+    let deleted = store.empty_trash(before)?;
+    println!("Deleted {} bookmarks from the trash", deleted);
     Ok(())
 }
 
+/// Undo a `process` run: the latest, or run `id` from the journal
+pub fn undo(id: Option<i64>, force: bool, list: bool) -> Result<()> {
+    let mut store = Store::open_default()?;
+    let journal = store.journal()?;
+    if list {
+        if journal.is_empty() {
+            println!("Nothing recorded yet.");
+        }
+        for entry in &journal {
+            println!(
+                "{:>4}  {}  {:<8} {} bookmarks in, {}{}",
+                entry.id,
+                entry.at.format("%Y-%m-%d %H:%M"),
+                entry.operation,
+                entry.input.len(),
+                entry.output.display(),
+                if entry.undone_at.is_some() {
+                    "  (undone)"
+                } else {
+                    ""
+                }
+            );
+        }
+        return Ok(());
+    }
+
+    let entry = match id {
+        Some(id) => journal
+            .into_iter()
+            .find(|e| e.id == id)
+            .ok_or_else(|| anyhow::anyhow!("No run {} in the journal", id))?,
+        None => journal
+            .into_iter()
+            .find(|e| e.undone_at.is_none())
+            .ok_or_else(|| anyhow::anyhow!("Nothing to undo"))?,
+    };
+    let undone = journal::undo(&entry, force)?;
+    store.mark_undone(entry.id)?;
+    match undone {
+        journal::Undone::Restored => println!(
+            "Restored {} as it was before {} run {} ({})",
+            entry.output.display(),
+            entry.operation,
+            entry.id,
+            entry.at.format("%Y-%m-%d %H:%M")
+        ),
+        journal::Undone::Removed => println!(
+            "Removed {}, which {} run {} created",
+            entry.output.display(),
+            entry.operation,
+            entry.id
+        ),
+    }
+    Ok(())
+}
+
+/// Back up every browser's bookmarks if `backup.interval` has passed (or
+/// `force`), then prune to `backup.keep`; with `list`, show the backups
+pub fn backup(force: bool, list: bool) -> Result<()> {
+    let settings = saved_config()?.backup;
+    let dir = &paths::get().backups;
+    let existing = backup::list(dir)?;
+    if list {
+        if existing.is_empty() {
+            println!("No backups in {}", dir.display());
+        }
+        for file in &existing {
+            let size = fs::metadata(&file.path).map(|m| m.len()).unwrap_or(0);
+            println!(
+                "{}  {:>8} bytes  {}",
+                file.taken_at.format("%Y-%m-%d %H:%M"),
+                size,
+                file.path.display()
+            );
+        }
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now();
+    if !force && !backup::is_due(&existing, settings.interval()?, now) {
+        println!(
+            "Last backup was {} (backup.interval is {}); nothing to do. Pass --force to back up anyway.",
+            existing[0].taken_at.format("%Y-%m-%d %H:%M"),
+            settings.interval
+        );
+        return Ok(());
+    }
+
+    let mut data = Vec::new();
+    for browser_name in ["chrome", "firefox", "safari", "edge"] {
+        match exporter::collect_browser_data(
+            browser_name,
+            "bookmarks",
+            None,
+            &ExportOptions::default(),
+        ) {
+            Ok(profiles) => data.extend(profiles),
+            Err(e) => log::debug!("Skipping {}: {}", browser_name, e),
+        }
+    }
+    if data.is_empty() {
+        return Err(anyhow::anyhow!("No browser profiles found to back up"));
+    }
+    let bookmarks: usize = data
+        .iter()
+        .map(|profile| {
+            exporter::flatten_bookmarks(profile.bookmarks.as_deref().unwrap_or_default()).len()
+        })
+        .sum();
+    let path = backup::write(dir, &data, now)?;
+    println!(
+        "Backed up {} bookmarks from {} profiles to {}",
+        bookmarks,
+        data.len(),
+        path.display()
+    );
+
+    for removed in backup::prune(&backup::list(dir)?, settings.keep)? {
+        println!("Deleted old backup {}", removed.display());
+    }
+    Ok(())
+}
+
+/// Save the store's bookmarks, or an export file's, as a snapshot
+pub fn snapshot_create(input: Option<&Path>, label: Option<String>) -> Result<()> {
+    let bookmarks = match input {
+        Some(path) => {
+            let content = fs::read_to_string(path)?;
+            serde_yaml::from_str::<Vec<exporter::BrowserData>>(&content)?
+                .into_iter()
+                .flat_map(|data| data.bookmarks.unwrap_or_default())
+                .collect()
+        }
+        None => Store::open_default()?.bookmarks()?,
+    };
+    let snapshot = snapshot::Snapshot::new(&bookmarks, label);
+    match Store::open_default()?.save_snapshot(&snapshot)? {
+        Some(existing) => println!(
+            "Unchanged since snapshot {} ({})",
+            existing.id,
+            existing.created_at.format("%Y-%m-%d %H:%M")
+        ),
+        None => println!(
+            "Snapshot {}: {} bookmarks",
+            snapshot.id,
+            snapshot.bookmarks.len()
+        ),
+    }
+    Ok(())
+}
+
+pub fn snapshot_list() -> Result<()> {
+    let snapshots = Store::open_default()?.snapshots()?;
+    if snapshots.is_empty() {
+        println!("No snapshots yet; take one with `bookmark snapshot create`.");
+        return Ok(());
+    }
+    for info in &snapshots {
+        let line = format!(
+            "{}  {}  {:>6} bookmarks  {}",
+            info.id,
+            info.created_at.format("%Y-%m-%d %H:%M"),
+            info.bookmarks,
+            info.label.as_deref().unwrap_or_default()
+        );
+        println!("{}", line.trim_end());
+    }
+    Ok(())
+}
+
+/// The bookmarks of a snapshot, or of the store for `current`
+fn snapshot_side(store: &Store, id: &str) -> Result<Vec<exporter::Bookmark>> {
+    if id == "current" {
+        return Ok(snapshot::Snapshot::new(&store.bookmarks()?, None).bookmarks);
+    }
+    Ok(store.snapshot(id)?.bookmarks)
+}
+
+/// Compare two snapshots, either of which may be `current`
+pub fn snapshot_diff(old: &str, new: &str, output: &str) -> Result<()> {
+    let store = Store::open_default()?;
+    print_diff(
+        &BookmarkDiff::between(&snapshot_side(&store, old)?, &snapshot_side(&store, new)?),
+        output,
+    )
+}
+
+/// Write a snapshot's bookmarks to an export file, ready for `apply`
+pub fn snapshot_restore(id: &str, output: &Path) -> Result<()> {
+    let snapshot = Store::open_default()?.snapshot(id)?;
+    let count = snapshot.bookmarks.len();
+    let data = exporter::BrowserData {
+        browser: "snapshot".to_string(),
+        profile: snapshot.id.clone(),
+        export_date: snapshot.created_at,
+        // Snapshots keep no ids; number the bookmarks as an import would
+        bookmarks: Some(
+            snapshot
+                .bookmarks
+                .into_iter()
+                .enumerate()
+                .map(|(i, bookmark)| exporter::Bookmark {
+                    id: (i + 1).to_string(),
+                    ..bookmark
+                })
+                .collect(),
+        ),
+        history: None,
+        passwords: None,
+        aliases: None,
+        profile_name: None,
+        profile_email: None,
+        provenance: None,
+    };
+    fs::write(
+        output,
+        exporter::render_export(&[data], ExportFormat::Yaml)?,
+    )?;
+    println!(
+        "Wrote {} bookmarks from snapshot {} to {}; put them back with `bookmark apply {} --prune`",
+        count,
+        snapshot.id,
+        output.display(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Delete stale temporary files at the start of a run, as configured
+pub fn auto_clean() {
+    match saved_config() {
+        Ok(config) => cleanup::auto_clean(config.cleanup.max_age()),
+        Err(e) => log::warn!("Skipping cleanup of temporary files: {:#}", e),
+    }
+}
+
+/// Write a digest of the bookmarks added in the last `since` (`7d`), and
+/// post it to `webhook` when given
+pub fn digest(
+    browser: &str,
+    since: &str,
+    format: &str,
+    output: Option<PathBuf>,
+    webhook: Option<&str>,
+    live: bool,
+) -> Result<()> {
+    let period = expiration::parse_age(since)?;
+    let (bookmarks, _) = load_graph_data(browser, "bookmarks", live, None)?;
+    let digest = Digest::build(&bookmarks, period, chrono::Utc::now());
+    let content = match format {
+        "markdown" | "md" => digest.to_markdown(),
+        "html" => digest.to_html(),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Invalid digest format: {}. Use markdown or html",
+                format
+            ));
+        }
+    };
+    match output {
+        Some(path) => {
+            fs::write(&path, content)?;
+            eprintln!(
+                "Digest of {} bookmarks written to {}",
+                digest.total(),
+                path.display()
+            );
+        }
+        None => print!("{}", content),
+    }
+    if let Some(url) = webhook {
+        digest::post_webhook(url, &digest.to_markdown())?;
+        eprintln!("Digest posted to the webhook");
+    }
+    Ok(())
+}
+
+/// Bookmarks of an export file (YAML or JSON), or read from a browser's
+/// profiles when `source` names a browser rather than a file
+fn load_diff_side(source: &str) -> Result<Vec<exporter::Bookmark>> {
+    let path = Path::new(source);
+    if path.exists() {
+        let content = fs::read_to_string(path)?;
+        let browser_data: Vec<exporter::BrowserData> = serde_yaml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Could not read {} as an export: {}", source, e))?;
+        return Ok(browser_data
+            .into_iter()
+            .flat_map(|data| data.bookmarks.unwrap_or_default())
+            .collect());
+    }
+    Browser::from_str(source)
+        .map_err(|_| anyhow::anyhow!("{} is neither an export file nor a browser", source))?;
+    Ok(exporter::load_browser_data(source, "bookmarks")?.0)
+}
+
+/// Compare two exports or browsers and print what was added, removed,
+/// retitled and moved, as text or JSON
+pub fn diff_bookmarks(old: &str, new: &str, output: &str) -> Result<()> {
+    print_diff(
+        &BookmarkDiff::between(&load_diff_side(old)?, &load_diff_side(new)?),
+        output,
+    )
+}
+
+fn print_diff(diff: &BookmarkDiff, output: &str) -> Result<()> {
+    match output {
+        "text" => print!("{}", diff.to_text()),
+        "json" => println!("{}", serde_json::to_string_pretty(&diff)?),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Invalid diff output: {}. Use text or json",
+                output
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Print the folder hierarchy of an export file, or of the store or browsers
+pub fn print_tree(
+    input: Option<&Path>,
+    browser: &str,
+    prefix: Option<&str>,
+    depth: Option<usize>,
+    ascii: bool,
+    live: bool,
+    as_of: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<()> {
+    let bookmarks = match input {
+        Some(path) => {
+            let content = fs::read_to_string(path)?;
+            let browser_data: Vec<exporter::BrowserData> = serde_yaml::from_str(&content)?;
+            browser_data
+                .into_iter()
+                .flat_map(|data| data.bookmarks.unwrap_or_default())
+                .collect()
+        }
+        None => load_graph_data(browser, "bookmarks", live, as_of)?.0,
+    };
+    let tree = FolderTree::build(&bookmarks);
+    let folder = match prefix {
+        Some(prefix) => tree
+            .find(prefix)
+            .ok_or_else(|| anyhow::anyhow!("No folder at {}", prefix))?,
+        None => &tree,
+    };
+    print!("{}", folder.render(depth, ascii));
+    Ok(())
+}
+
+pub fn clean(older_than: Option<u32>, all: bool, dry_run: bool) -> Result<()> {
+    let before = if all {
+        None
+    } else {
+        let max_age = match older_than {
+            Some(days) => chrono::Duration::days(days.into()),
+            None => saved_config()?.cleanup.max_age(),
+        };
+        Some(chrono::Utc::now() - max_age)
+    };
+    let summary = cleanup::clean(before, dry_run)?;
+    for path in &summary.removed {
+        println!("  {}", path.display());
+    }
+    println!(
+        "{} {} temporary files ({:.1} MB)",
+        if dry_run { "Would delete" } else { "Deleted" },
+        summary.removed.len(),
+        summary.bytes as f64 / 1_000_000.0
+    );
+    Ok(())
+}
+
+/// One side of `sync --between`: `browser` or `browser:profile`
+struct SyncSide {
+    name: String,
+    browser: Browser,
+    profile: PathBuf,
+}
+
+impl SyncSide {
+    fn parse(spec: &str) -> Result<Self> {
+        let (name, wanted) = match spec.trim().split_once(':') {
+            Some((name, profile)) => (name, Some(profile)),
+            None => (spec.trim(), None),
+        };
+        let browser = Browser::from_str(name)?;
+        if matches!(browser, Browser::Safari) {
+            return Err(anyhow::anyhow!(
+                "Safari bookmarks can't be written back; sync Firefox, Chrome or Edge"
+            ));
+        }
+        let mut profiles: Vec<PathBuf> = browser
+            .find_profiles(None)?
+            .into_iter()
+            .filter(|p| wanted.is_none_or(|w| p.file_name().is_some_and(|n| n == w)))
+            .collect();
+        match profiles.len() {
+            1 => Ok(Self {
+                name: name.to_lowercase(),
+                browser,
+                profile: profiles.remove(0),
+            }),
+            0 => Err(anyhow::anyhow!("No {} profile found for '{}'", name, spec)),
+            _ => Err(anyhow::anyhow!(
+                "{} has several profiles; pick one as {}:<profile>",
+                name,
+                name
+            )),
+        }
+    }
+
+    /// `browser:profile`, as used in the baseline key
+    fn key(&self) -> String {
+        let profile = self
+            .profile
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        format!("{}:{}", self.name, profile)
+    }
+}
+
+fn describe_entry(entry: Option<&reconcile::Entry>) -> String {
+    match entry {
+        Some(entry) => format!("\"{}\" in {}", entry.title, entry.folder),
+        None => "deleted".to_string(),
+    }
+}
+
+/// Merge another machine's store into this one, writing any conflicts to
+/// `conflicts` for `resolve`
+pub fn merge_store(other: &Path, conflicts: &Path) -> Result<()> {
+    let mut store = Store::open_default()?;
+    let summary = merge::merge(&mut store, other)?;
+    println!(
+        "Merged {}: {} added, {} unchanged, {} conflicts",
+        other.display(),
+        summary.added,
+        summary.unchanged,
+        summary.conflicts.len()
+    );
+    if !summary.conflicts.is_empty() {
+        merge::ConflictsFile {
+            other: other.to_path_buf(),
+            conflicts: summary.conflicts,
+        }
+        .save(conflicts)?;
+        println!(
+            "Pick a side for each in {}, then run `bookmark resolve {}`",
+            conflicts.display(),
+            conflicts.display()
+        );
+    }
+    Ok(())
+}
+
+/// Merge export files into one, deduplicated with `strategy` or else the
+/// configured merge strategy
+pub fn merge_exports(inputs: &[PathBuf], output: &Path, strategy: Option<&str>) -> Result<()> {
+    let mut profiles = Vec::new();
+    let mut sources = Vec::new();
+    for input in inputs {
+        let content = fs::read_to_string(input)?;
+        profiles.extend(
+            serde_yaml::from_str::<Vec<exporter::BrowserData>>(&content).map_err(|e| {
+                anyhow::anyhow!("Could not read {} as an export: {}", input.display(), e)
+            })?,
+        );
+        sources.push(
+            input
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+        );
+    }
+
+    let app_config = saved_config()?;
+    let mut config = deduplication::DeduplicationConfig {
+        sites: app_config.sites,
+        ..app_config.deduplication
+    };
+    if let Some(strategy) = strategy {
+        config.merge_strategy = MergeStrategy::from_str(strategy)?;
+    }
+    let visited = matches!(config.merge_strategy, MergeStrategy::KeepMostVisited);
+
+    let merged = merge::merge_exports(profiles, &sources, config)?;
+    if visited && merged.data.history.is_none() {
+        eprintln!(
+            "Warning: no input includes history; the visited strategy kept the first duplicate."
+        );
+    }
+    let kept = merged.data.bookmarks.as_ref().map_or(0, Vec::len);
+    fs::write(
+        output,
+        exporter::render_export(&[merged.data], ExportFormat::Yaml)?,
+    )?;
+    println!(
+        "Merged {} files: {} bookmarks read, {} duplicates removed, {} written to {}",
+        inputs.len(),
+        merged.read,
+        merged.duplicates_removed,
+        kept,
+        output.display()
+    );
+    Ok(())
+}
+
+/// Apply the decisions made in a conflicts file written by `sync --from`
+pub fn resolve_conflicts(path: &Path) -> Result<()> {
+    let file = merge::ConflictsFile::load(path)?;
+    let summary = merge::resolve(&mut Store::open_default()?, &file.conflicts)?;
+    println!(
+        "Resolved {} conflicts, {} still undecided",
+        summary.applied, summary.unresolved
+    );
+    if summary.missing > 0 {
+        println!(
+            "{} decided bookmarks are no longer in the store and were skipped",
+            summary.missing
+        );
+    }
+    Ok(())
+}
+
+/// Sync two browsers' bookmarks with each other, e.g. `chrome,firefox`
+pub fn sync_between(between: &str, conflict: &str, dry_run: bool) -> Result<()> {
+    let sides: Vec<&str> = between.split(',').collect();
+    let [left, right] = sides.as_slice() else {
+        return Err(anyhow::anyhow!(
+            "--between takes two browsers, e.g. chrome,firefox"
+        ));
+    };
+    let (left, right) = (SyncSide::parse(left)?, SyncSide::parse(right)?);
+    if left.profile == right.profile {
+        return Err(anyhow::anyhow!("--between needs two different profiles"));
+    }
+    let policy = reconcile::ConflictPolicy::from_str(conflict)?;
+
+    let read = |side: &SyncSide| -> Result<Vec<exporter::Bookmark>> {
+        let data = exporter::read_profile(&side.name, &side.profile, "bookmarks", None)?;
+        Ok(data.bookmarks.unwrap_or_default())
+    };
+    let (left_bookmarks, right_bookmarks) = (read(&left)?, read(&right)?);
+
+    let mut keys = [left.key(), right.key()];
+    keys.sort();
+    let pair = keys.join(",");
+    let mut store = Store::open_default()?;
+    let baseline = store.pair_baseline(&pair)?;
+
+    let result = reconcile::reconcile(
+        &baseline,
+        &left_bookmarks,
+        &right_bookmarks,
+        policy,
+        |conflict| {
+            let items = [
+                format!("{}: {}", left.name, describe_entry(conflict.left.as_ref())),
+                format!(
+                    "{}: {}",
+                    right.name,
+                    describe_entry(conflict.right.as_ref())
+                ),
+            ];
+            let selection = dialoguer::Select::new()
+                .with_prompt(format!("Conflict for {}", conflict.url))
+                .items(&items)
+                .default(0)
+                .interact()?;
+            Ok(match selection {
+                0 => reconcile::Side::Left,
+                _ => reconcile::Side::Right,
+            })
+        },
+    )?;
+
+    for (conflict, side) in &result.conflicts {
+        let (winner, entry) = match side {
+            reconcile::Side::Left => (&left.name, conflict.left.as_ref()),
+            reconcile::Side::Right => (&right.name, conflict.right.as_ref()),
+        };
+        println!(
+            "⚠ Conflict {}: kept {} ({})",
+            conflict.url,
+            winner,
+            describe_entry(entry)
+        );
+    }
+
+    let options = ApplyOptions {
+        dry_run,
+        prune: true,
+        backup_dir: None,
+    };
+    for (side, own, changes) in [
+        (&left, &left_bookmarks, &result.left_changes),
+        (&right, &right_bookmarks, &result.right_changes),
+    ] {
+        println!(
+            "{} ({}): +{} added, -{} removed, ~{} changed",
+            side.name,
+            side.profile.display(),
+            changes.added.len(),
+            changes.removed.len(),
+            changes.updated.len()
+        );
+        if changes.is_empty() {
+            continue;
+        }
+        let target = reconcile::target_bookmarks(own, &result.merged, &side.browser);
+        let summary = exporter::apply_to_browser(&side.browser, &side.profile, &target, &options)?;
+        if !dry_run {
+            store.trash_bookmarks(&summary.pruned, "prune")?;
+        }
+        if let Some(backup) = summary.backup {
+            println!("  Backup: {}", backup.display());
+        }
+    }
+
+    if dry_run {
+        println!("Dry run, nothing written");
+    } else {
+        store.set_pair_baseline(&pair, &result.merged)?;
+        println!(
+            "✓ {} and {} in sync ({} bookmarks)",
+            left.name,
+            right.name,
+            result.merged.len()
+        );
+    }
+    Ok(())
+}
+
+/// List every location the tool uses, with the variable that relocates it
+pub fn print_paths() {
+    for (name, path, env_var) in paths::get().entries() {
+        let status = if path.exists() {
+            ""
+        } else {
+            ", not created yet"
+        };
+        println!("{:<10} {}  [{}{}]", name, path.display(), env_var, status);
+    }
+    println!(
+        "\nFlags override the environment: --config, --store, --cache-dir, --backup-dir, --archive-dir, --snapshot-dir"
+    );
+}
+
 pub fn handle_config(show: bool, create_sample: Option<PathBuf>, list_rules: bool) -> Result<()> {
     if let Some(path) = create_sample {
         config::AppConfig::create_sample_config(&path)?;
@@ -298,10 +2098,10 @@ pub fn handle_config(show: bool, create_sample: Option<PathBuf>, list_rules: boo
 pub fn list_all_browsers() -> Result<()> {
     println!("Available browsers:");
     for browser_name in &["Chrome", "Firefox", "Safari", "Edge"] {
-        if let Ok(browser) = Browser::from_str(browser_name) {
-            if let Ok(profiles) = browser.find_profiles(None) {
-                println!("  {}: {} profile(s)", browser_name, profiles.len());
-            }
+        if let Ok(browser) = Browser::from_str(browser_name)
+            && let Ok(profiles) = browser.find_profiles(None)
+        {
+            println!("  {}: {} profile(s)", browser_name, profiles.len());
         }
     }
     Ok(())
@@ -315,7 +2115,14 @@ pub fn list_browser_profiles(browser_name: &str) -> Result<()> {
     } else {
         println!("Profiles for {}:", browser_name);
         for (i, p) in profiles.iter().enumerate() {
-            println!("  {}: {}", i + 1, p.display());
+            let info = browser.profile_info(p);
+            let label = match (info.display_name, info.email) {
+                (Some(name), Some(email)) => format!("{} <{}> - ", name, email),
+                (Some(name), None) => format!("{} - ", name),
+                (None, Some(email)) => format!("<{}> - ", email),
+                (None, None) => String::new(),
+            };
+            println!("  {}: {}{}", i + 1, label, p.display());
         }
     }
     Ok(())
@@ -1,10 +1,16 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
+use crate::canonical::SiteRules;
 use crate::deduplication::{DeduplicationConfig, MergeStrategy};
+use crate::expiration::{ExpireAction, ExpirePolicy};
+use crate::graph::GraphTheme;
+use crate::graph::formats::{HtmlTheme, ThemeMode};
 use crate::organization::{OrganizationConfig, OrganizationRule};
+use crate::quota::{FolderQuota, OverflowAction};
+use crate::search::RankingWeights;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppConfig {
@@ -12,6 +18,164 @@ pub struct AppConfig {
     pub organization: OrganizationConfig,
     pub backup_enabled: bool,
     pub dry_run_by_default: bool,
+    #[serde(default)]
+    pub pinboard: PinboardConfig,
+    /// Folder expiration policies applied by `process`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expire: Vec<ExpirePolicy>,
+    /// Folder size limits enforced by `process`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quotas: Vec<FolderQuota>,
+    /// Site-specific URL rewrites and host aliases used by dedupe, graph
+    /// domain grouping and organization
+    #[serde(default)]
+    pub sites: SiteRules,
+    #[serde(default)]
+    pub github: GithubConfig,
+    /// Node colors for `graph` output
+    #[serde(default, skip_serializing_if = "GraphTheme::is_empty")]
+    pub theme: GraphTheme,
+    #[serde(default)]
+    pub html: HtmlConfig,
+    #[serde(default)]
+    pub trash: TrashConfig,
+    #[serde(default)]
+    pub cleanup: CleanupConfig,
+    /// Folders (and everything under them) the extractors drop before
+    /// anything else sees them, e.g. `["Banking", "Private"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub never_export_folders: Vec<String>,
+    #[serde(default)]
+    pub search: SearchConfig,
+    #[serde(default)]
+    pub backup: BackupConfig,
+}
+
+/// How `search` and `open` rank matches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchConfig {
+    #[serde(default)]
+    pub weights: RankingWeights,
+}
+
+/// Pinboard API credentials
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PinboardConfig {
+    /// API token in `username:TOKEN` form, from https://pinboard.in/settings/password
+    pub api_token: Option<String>,
+}
+
+/// GitHub API credentials for repository enrichment
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GithubConfig {
+    /// Personal access token; public repositories need no scopes. Falls back
+    /// to `GITHUB_TOKEN`, and unauthenticated lookups are rate limited hard
+    pub api_token: Option<String>,
+}
+
+impl GithubConfig {
+    /// The configured token, else `GITHUB_TOKEN` from the environment
+    pub fn token(&self) -> Option<String> {
+        self.api_token
+            .clone()
+            .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+            .filter(|t| !t.is_empty())
+    }
+}
+
+/// How long the store keeps deleted bookmarks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashConfig {
+    /// Days before a trashed bookmark is deleted for good
+    pub retention_days: u32,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self { retention_days: 30 }
+    }
+}
+
+impl TrashConfig {
+    /// Entries deleted before this have outlived the retention window
+    pub fn cutoff(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::Utc::now() - chrono::Duration::days(self.retention_days.into())
+    }
+}
+
+/// When temporary files the tool made are deleted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupConfig {
+    /// Days before graph pages, snapshots and other temporary files are
+    /// deleted at the start of a run
+    pub max_age_days: u32,
+}
+
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        Self { max_age_days: 7 }
+    }
+}
+
+impl CleanupConfig {
+    pub fn max_age(&self) -> chrono::Duration {
+        chrono::Duration::days(self.max_age_days.into())
+    }
+}
+
+/// How often `backup` copies the browsers' bookmarks, and how many copies
+/// it keeps
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Least time between backups, e.g. `1d` or `12h`; a run sooner than
+    /// this does nothing
+    pub interval: String,
+    /// Backups kept; older ones are deleted
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            interval: "1d".to_string(),
+            keep: 14,
+        }
+    }
+}
+
+impl BackupConfig {
+    pub fn interval(&self) -> Result<chrono::Duration> {
+        crate::expiration::parse_age(&self.interval)
+    }
+}
+
+/// Look of the HTML pages the tool writes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HtmlConfig {
+    /// `dark` or `light`
+    #[serde(default)]
+    pub theme: ThemeMode,
+    /// Stylesheet injected after the built-in styles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub css: Option<PathBuf>,
+}
+
+impl HtmlConfig {
+    /// The configured theme with its stylesheet read in
+    pub fn load(&self) -> Result<HtmlTheme> {
+        let custom_css = match &self.css {
+            Some(path) => Some(
+                fs::read_to_string(path)
+                    .with_context(|| format!("Could not read stylesheet {}", path.display()))?,
+            ),
+            None => None,
+        };
+        Ok(HtmlTheme {
+            mode: self.theme,
+            custom_css,
+        })
+    }
 }
 
 impl Default for AppConfig {
@@ -21,6 +185,18 @@ impl Default for AppConfig {
             organization: OrganizationConfig::default(),
             backup_enabled: true,
             dry_run_by_default: false,
+            pinboard: PinboardConfig::default(),
+            expire: Vec::new(),
+            quotas: Vec::new(),
+            sites: SiteRules::default(),
+            github: GithubConfig::default(),
+            theme: GraphTheme::default(),
+            html: HtmlConfig::default(),
+            trash: TrashConfig::default(),
+            cleanup: CleanupConfig::default(),
+            never_export_folders: Vec::new(),
+            search: SearchConfig::default(),
+            backup: BackupConfig::default(),
         }
     }
 }
@@ -59,11 +235,9 @@ impl AppConfig {
         Ok(())
     }
 
+    /// The config file in effect; see [`crate::paths`] for how it is located
     pub fn get_default_config_path() -> PathBuf {
-        dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("bookmark-manager")
-            .join("config.yaml")
+        crate::paths::get().config.clone()
     }
 
     pub fn load_or_create() -> Result<Self> {
@@ -81,11 +255,14 @@ impl AppConfig {
                 ignore_protocol: true,
                 case_sensitive: false,
                 merge_strategy: MergeStrategy::MergeMetadata,
+                sites: SiteRules::default(),
+                resolve_redirects: false,
             },
             organization: OrganizationConfig {
                 organize_by_domain: true,
                 organize_by_category: true,
                 organize_by_date: false,
+                organize_by_media: false,
                 custom_rules: vec![
                     OrganizationRule {
                         name: "Development".to_string(),
@@ -111,9 +288,31 @@ impl AppConfig {
                 ],
                 folder_separator: "/".to_string(),
                 preserve_existing: true,
+                sites: SiteRules::default(),
             },
             backup_enabled: true,
             dry_run_by_default: false,
+            pinboard: PinboardConfig::default(),
+            expire: vec![ExpirePolicy {
+                folder: "Temp".to_string(),
+                after: "30d".to_string(),
+                action: ExpireAction::Archive,
+                to: None,
+            }],
+            quotas: vec![FolderQuota {
+                folder: "Read Later".to_string(),
+                max: 200,
+                overflow: OverflowAction::Move,
+            }],
+            sites: SiteRules::default(),
+            github: GithubConfig::default(),
+            theme: GraphTheme::default(),
+            html: HtmlConfig::default(),
+            trash: TrashConfig::default(),
+            cleanup: CleanupConfig::default(),
+            never_export_folders: Vec::new(),
+            search: SearchConfig::default(),
+            backup: BackupConfig::default(),
         };
 
         sample_config.save_to_file(output_path)?;
@@ -136,7 +335,7 @@ impl AppConfig {
         // Sort rules by priority
         self.organization
             .custom_rules
-            .sort_by(|a, b| b.priority.cmp(&a.priority));
+            .sort_by_key(|r| std::cmp::Reverse(r.priority));
     }
 
     pub fn remove_custom_rule(&mut self, rule_name: &str) -> Result<()> {
@@ -163,7 +362,9 @@ impl AppConfig {
             && !self.deduplication.ignore_fragment
         {
             // This is just a warning, not an error
-            eprintln!("Warning: URL normalization is enabled but query parameters and fragments are not ignored");
+            eprintln!(
+                "Warning: URL normalization is enabled but query parameters and fragments are not ignored"
+            );
         }
 
         // Validate organization rules
@@ -177,7 +378,7 @@ impl AppConfig {
             }
 
             // Test regex compilation
-            if let Err(e) = regex::Regex::new(&rule.pattern) {
+            if let Err(e) = crate::organization::compile_rule_pattern(&rule.pattern) {
                 return Err(anyhow::anyhow!(
                     "Invalid regex in rule '{}': {}",
                     rule.name,
@@ -186,6 +387,13 @@ impl AppConfig {
             }
         }
 
+        for policy in &self.expire {
+            policy.validate()?;
+        }
+        for quota in &self.quotas {
+            quota.validate()?;
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,164 @@
+//! Converting between folders and tags.
+//!
+//! Browsers file each bookmark in one folder; services such as Pinboard and
+//! Raindrop give it several tags instead. [`folders_to_tags`] turns every
+//! segment of a bookmark's folder path into a tag and flattens the tree;
+//! [`tags_to_folders`] goes the other way, filing each tagged bookmark in a
+//! folder named after its primary tag. Browser roots (`toolbar`, `Other
+//! bookmarks`, ...) are not topics, so both keep a bookmark in its root.
+
+use crate::exporter::{Bookmark, FOLDER_PATH_SEPARATOR, flatten_bookmarks, is_root_folder};
+
+/// Which way to convert
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conversion {
+    FoldersToTags,
+    /// Tags to folders, preferring these tags as the folder in this order
+    TagsToFolders(Vec<String>),
+}
+
+impl Conversion {
+    /// Convert `bookmarks`, returning them flattened
+    pub fn apply(&self, bookmarks: &[Bookmark]) -> Vec<Bookmark> {
+        match self {
+            Conversion::FoldersToTags => folders_to_tags(bookmarks),
+            Conversion::TagsToFolders(order) => tags_to_folders(bookmarks, order),
+        }
+    }
+}
+
+/// Tag each bookmark with its folder path's segments and leave it directly
+/// in its browser root, or at the top level. Existing tags are kept; a
+/// segment matching one of them (ignoring case) adds nothing.
+pub fn folders_to_tags(bookmarks: &[Bookmark]) -> Vec<Bookmark> {
+    flatten_bookmarks(bookmarks)
+        .into_iter()
+        .map(|mut bookmark| {
+            let Some(folder) = bookmark.folder.take() else {
+                return bookmark;
+            };
+            let mut segments = folder
+                .split(FOLDER_PATH_SEPARATOR)
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .peekable();
+            bookmark.folder = segments.next_if(|s| is_root_folder(s)).map(str::to_string);
+
+            let mut tags = bookmark.tags.take().unwrap_or_default();
+            for segment in segments {
+                if !tags.iter().any(|t| t.eq_ignore_ascii_case(segment)) {
+                    tags.push(segment.to_string());
+                }
+            }
+            bookmark.tags = (!tags.is_empty()).then_some(tags);
+            bookmark
+        })
+        .collect()
+}
+
+/// Move each tagged bookmark to a folder named after its primary tag: the
+/// first of `primary_order` it has (ignoring case), else its first tag. The
+/// folder stays under the bookmark's browser root if it had one. Tags are
+/// kept, and untagged bookmarks stay where they are.
+pub fn tags_to_folders(bookmarks: &[Bookmark], primary_order: &[String]) -> Vec<Bookmark> {
+    flatten_bookmarks(bookmarks)
+        .into_iter()
+        .map(|mut bookmark| {
+            let Some(primary) =
+                primary_tag(bookmark.tags.as_deref().unwrap_or_default(), primary_order)
+            else {
+                return bookmark;
+            };
+            let root = bookmark
+                .folder
+                .as_deref()
+                .and_then(|f| f.split(FOLDER_PATH_SEPARATOR).next())
+                .filter(|first| is_root_folder(first));
+            let folder = match root {
+                Some(root) => format!("{}{}{}", root, FOLDER_PATH_SEPARATOR, primary),
+                None => primary.to_string(),
+            };
+            bookmark.folder = Some(folder);
+            bookmark
+        })
+        .collect()
+}
+
+/// The first tag of `order` among `tags`, else the first of `tags`
+fn primary_tag<'a>(tags: &'a [String], order: &[String]) -> Option<&'a str> {
+    order
+        .iter()
+        .find_map(|wanted| tags.iter().find(|t| t.eq_ignore_ascii_case(wanted.trim())))
+        .or_else(|| tags.first())
+        .map(|t| t.trim_matches('/'))
+        .filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(title: &str, folder: Option<&str>, tags: &[&str]) -> Bookmark {
+        Bookmark {
+            id: title.to_string(),
+            title: title.to_string(),
+            url: Some(format!("https://example.com/{}", title)),
+            folder: folder.map(str::to_string),
+            date_added: None,
+            tags: (!tags.is_empty()).then(|| tags.iter().map(|t| t.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_folders_to_tags() {
+        let converted = folders_to_tags(&[
+            bookmark("rustbook", Some("toolbar/Dev/Rust"), &["rust", "book"]),
+            bookmark("recipes", Some("Cooking"), &[]),
+            bookmark("loose", None, &[]),
+        ]);
+
+        assert_eq!(converted[0].folder.as_deref(), Some("toolbar"));
+        assert_eq!(
+            converted[0].tags,
+            Some(vec![
+                "rust".to_string(),
+                "book".to_string(),
+                "Dev".to_string()
+            ])
+        );
+        assert_eq!(converted[1].folder, None);
+        assert_eq!(converted[1].tags, Some(vec!["Cooking".to_string()]));
+        assert_eq!(converted[2].folder, None);
+        assert_eq!(converted[2].tags, None);
+    }
+
+    #[test]
+    fn test_tags_to_folders() {
+        let order = vec!["rust".to_string(), "python".to_string()];
+        let converted = tags_to_folders(
+            &[
+                bookmark("rustbook", Some("toolbar"), &["book", "Rust"]),
+                bookmark("pandas", Some("Work/Data"), &["data", "python"]),
+                bookmark("recipes", None, &["cooking", "food"]),
+                bookmark("loose", Some("Misc"), &[]),
+            ],
+            &order,
+        );
+
+        let folders: Vec<_> = converted.iter().map(|b| b.folder.as_deref()).collect();
+        assert_eq!(
+            folders,
+            vec![
+                Some("toolbar/Rust"),
+                Some("python"),
+                Some("cooking"),
+                Some("Misc")
+            ]
+        );
+        assert_eq!(
+            converted[0].tags,
+            Some(vec!["book".to_string(), "Rust".to_string()])
+        );
+    }
+}
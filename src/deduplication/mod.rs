@@ -1,11 +1,16 @@
+mod redirects;
 #[cfg(test)]
 mod tests;
 
-use anyhow::Result;
+pub use redirects::RedirectResolver;
+
+use anyhow::{Result, anyhow};
 use chrono::Utc;
 use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
 use url::Url;
 
+use crate::canonical::SiteRules;
 use crate::exporter::Bookmark;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -17,6 +22,14 @@ pub struct DeduplicationConfig {
     pub ignore_protocol: bool,
     pub case_sensitive: bool,
     pub merge_strategy: MergeStrategy,
+    /// Site-specific rewrites applied before the generic normalization;
+    /// configured through the config file's top-level `sites`
+    #[serde(skip)]
+    pub sites: SiteRules,
+    /// Follow redirects and compare the URLs they end at (one request per
+    /// URL, cached)
+    #[serde(default)]
+    pub resolve_redirects: bool,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -24,10 +37,34 @@ pub enum MergeStrategy {
     KeepFirst,
     KeepLast,
     KeepMostRecent,
+    /// Keep the duplicate whose title occurs most often in the group. This is
+    /// a vote over title strings and ignores browsing history; see
+    /// `KeepMostVisited` for that.
     KeepMostFrequent,
+    /// Keep the duplicate whose exact URL has the highest history
+    /// `visit_count`. Requires `BookmarkDeduplicator::with_visit_counts`;
+    /// unvisited URLs count as 0 and ties keep the earliest bookmark.
+    KeepMostVisited,
     MergeMetadata,
 }
 
+impl FromStr for MergeStrategy {
+    type Err = anyhow::Error;
+
+    /// The names the `--strategy` flags take
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "first" => Ok(MergeStrategy::KeepFirst),
+            "last" => Ok(MergeStrategy::KeepLast),
+            "recent" => Ok(MergeStrategy::KeepMostRecent),
+            "frequent" => Ok(MergeStrategy::KeepMostFrequent),
+            "visited" => Ok(MergeStrategy::KeepMostVisited),
+            "merge" => Ok(MergeStrategy::MergeMetadata),
+            _ => Err(anyhow!("Invalid strategy: {}", s)),
+        }
+    }
+}
+
 impl Default for DeduplicationConfig {
     fn default() -> Self {
         Self {
@@ -38,6 +75,8 @@ impl Default for DeduplicationConfig {
             ignore_protocol: true,
             case_sensitive: false,
             merge_strategy: MergeStrategy::MergeMetadata,
+            sites: SiteRules::default(),
+            resolve_redirects: false,
         }
     }
 }
@@ -52,33 +91,40 @@ pub struct DeduplicationResult {
 
 pub struct BookmarkDeduplicator {
     config: DeduplicationConfig,
+    /// History visit counts keyed by URL, used by `KeepMostVisited`
+    visit_counts: HashMap<String, i64>,
+    /// Set when `resolve_redirects` is on
+    redirects: Option<RedirectResolver>,
 }
 
 impl BookmarkDeduplicator {
     pub fn new(config: DeduplicationConfig) -> Self {
-        Self { config }
+        let redirects = config.resolve_redirects.then(RedirectResolver::new);
+        Self {
+            config,
+            visit_counts: HashMap::new(),
+            redirects,
+        }
     }
 
-    pub fn deduplicate(&self, bookmarks: &[Bookmark]) -> Result<DeduplicationResult> {
-        let mut url_groups: HashMap<String, Vec<Bookmark>> = HashMap::new();
-        let mut seen_urls: HashSet<String> = HashSet::new();
-
-        // Group bookmarks by normalized URL
-        for bookmark in bookmarks {
-            if let Some(ref url) = bookmark.url {
-                let normalized_url = self.normalize_url(url)?;
-
-                if seen_urls.contains(&normalized_url) {
-                    if let Some(group) = url_groups.get_mut(&normalized_url) {
-                        group.push(bookmark.clone());
-                    }
-                } else {
-                    seen_urls.insert(normalized_url.clone());
-                    url_groups.insert(normalized_url, vec![bookmark.clone()]);
-                }
-            }
+    /// Resolve redirects through `resolver`, e.g. one with a cache file,
+    /// instead of a fresh in-memory one. Has no effect unless
+    /// `resolve_redirects` is on.
+    pub fn with_redirect_resolver(mut self, resolver: RedirectResolver) -> Self {
+        if self.config.resolve_redirects {
+            self.redirects = Some(resolver);
         }
+        self
+    }
 
+    /// Supply history visit counts (URL -> visits) for `KeepMostVisited`
+    pub fn with_visit_counts(mut self, visit_counts: HashMap<String, i64>) -> Self {
+        self.visit_counts = visit_counts;
+        self
+    }
+
+    pub fn deduplicate(&self, bookmarks: &[Bookmark]) -> Result<DeduplicationResult> {
+        let url_groups = self.group_by_url(bookmarks)?;
         let mut unique_bookmarks = Vec::new();
         let mut duplicates_removed = 0;
         let mut duplicates_found = 0;
@@ -107,8 +153,53 @@ impl BookmarkDeduplicator {
         })
     }
 
-    fn normalize_url(&self, url_str: &str) -> Result<String> {
-        let mut url = Url::parse(url_str)?;
+    /// The sets of bookmarks `deduplicate` would merge, keyed by their
+    /// normalized URL, largest first
+    pub fn duplicate_groups(&self, bookmarks: &[Bookmark]) -> Result<Vec<(String, Vec<Bookmark>)>> {
+        let mut groups: Vec<(String, Vec<Bookmark>)> = self
+            .group_by_url(bookmarks)?
+            .into_iter()
+            .filter(|(_, group)| group.len() > 1)
+            .collect();
+        groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+        Ok(groups)
+    }
+
+    /// Group bookmarks by normalized URL, after following redirects
+    fn group_by_url(&self, bookmarks: &[Bookmark]) -> Result<HashMap<String, Vec<Bookmark>>> {
+        let mut url_groups: HashMap<String, Vec<Bookmark>> = HashMap::new();
+        for bookmark in bookmarks {
+            if let Some(ref url) = bookmark.url {
+                let normalized_url = match &self.redirects {
+                    Some(redirects) => self.normalize_url(&redirects.resolve(url))?,
+                    None => self.normalize_url(url)?,
+                };
+                url_groups
+                    .entry(normalized_url)
+                    .or_default()
+                    .push(bookmark.clone());
+            }
+        }
+
+        if let Some(redirects) = &self.redirects
+            && let Err(e) = redirects.save()
+        {
+            log::warn!("Could not save the redirect cache: {}", e);
+        }
+        Ok(url_groups)
+    }
+
+    /// The form two URLs must share to count as duplicates under this config
+    pub fn normalize_url(&self, url_str: &str) -> Result<String> {
+        // A site rule's query is part of the page's identity, so keep it
+        let site_canonical = self.config.sites.rewrite(url_str);
+        let aliased = self.config.sites.alias(url_str);
+        let mut url = Url::parse(
+            site_canonical
+                .as_deref()
+                .or(aliased.as_deref())
+                .unwrap_or(url_str),
+        )?;
 
         if self.config.ignore_protocol {
             url.set_scheme("http").ok();
@@ -116,8 +207,7 @@ impl BookmarkDeduplicator {
 
         if self.config.ignore_www {
             let host = url.host_str().unwrap_or("").to_string();
-            if host.starts_with("www.") {
-                let new_host = &host[4..];
+            if let Some(new_host) = host.strip_prefix("www.") {
                 url.set_host(Some(new_host))?;
             }
         }
@@ -127,7 +217,7 @@ impl BookmarkDeduplicator {
             url.set_path(&path);
         }
 
-        if self.config.ignore_query_params {
+        if self.config.ignore_query_params && site_canonical.is_none() {
             url.set_query(None);
         }
 
@@ -174,6 +264,21 @@ impl BookmarkDeduplicator {
 
                 Ok(bookmark.clone())
             }
+            MergeStrategy::KeepMostVisited => {
+                // Reversed so that ties resolve to the earliest bookmark
+                let most_visited = bookmarks
+                    .iter()
+                    .rev()
+                    .max_by_key(|b| {
+                        b.url
+                            .as_ref()
+                            .and_then(|u| self.visit_counts.get(u))
+                            .copied()
+                            .unwrap_or(0)
+                    })
+                    .unwrap();
+                Ok(most_visited.clone())
+            }
             MergeStrategy::MergeMetadata => {
                 let first_bookmark = &bookmarks[0];
 
@@ -188,10 +293,17 @@ impl BookmarkDeduplicator {
 
                 let mut folders = Vec::new();
                 for bookmark in bookmarks {
-                    if let Some(ref folder) = bookmark.folder {
-                        if !folders.contains(&folder.clone()) {
-                            folders.push(folder.clone());
-                        }
+                    if let Some(ref folder) = bookmark.folder
+                        && !folders.contains(&folder.clone())
+                    {
+                        folders.push(folder.clone());
+                    }
+                }
+
+                let mut tags: Vec<String> = Vec::new();
+                for tag in bookmarks.iter().flat_map(|b| b.tags.iter().flatten()) {
+                    if !tags.contains(tag) {
+                        tags.push(tag.clone());
                     }
                 }
 
@@ -209,44 +321,64 @@ impl BookmarkDeduplicator {
                     url: first_bookmark.url.clone(),
                     folder,
                     date_added,
-                    children: None,
+                    keyword: bookmarks.iter().find_map(|b| b.keyword.clone()),
+                    tags: (!tags.is_empty()).then_some(tags),
+                    description: bookmarks.iter().find_map(|b| b.description.clone()),
+                    media_type: bookmarks.iter().find_map(|b| b.media_type),
+                    archive_url: bookmarks.iter().find_map(|b| b.archive_url.clone()),
+                    github: bookmarks.iter().find_map(|b| b.github.clone()),
+                    canonical_url: bookmarks.iter().find_map(|b| b.canonical_url.clone()),
+                    paper: bookmarks.iter().find_map(|b| b.paper.clone()),
+                    screenshot: bookmarks.iter().find_map(|b| b.screenshot.clone()),
+                    page: bookmarks.iter().find_map(|b| b.page.clone()),
+                    ..Default::default()
                 })
             }
         }
     }
 }
 
-pub fn find_potential_duplicates(
-    bookmarks: &[Bookmark],
-) -> Result<Vec<(Bookmark, Bookmark, f64)>> {
-    let mut duplicates = Vec::new();
-
-    for (i, bookmark1) in bookmarks.iter().enumerate() {
-        for bookmark2 in bookmarks.iter().skip(i + 1) {
-            if let (Some(url1), Some(url2)) = (&bookmark1.url, &bookmark2.url) {
-                let similarity = calculate_url_similarity(url1, url2)?;
+/// Pairs of bookmarks whose URLs look alike, with a similarity from 0 to 1
+/// above 0.8, in bookmark order. Host, path and query weigh 0.5, 0.3 and
+/// 0.2, so only URLs on the same host (`www.` aside) can qualify and only
+/// those are compared.
+pub fn find_potential_duplicates(bookmarks: &[Bookmark]) -> Result<Vec<(Bookmark, Bookmark, f64)>> {
+    let mut by_host: HashMap<Option<String>, Vec<(usize, Url)>> = HashMap::new();
+    for (i, bookmark) in bookmarks.iter().enumerate() {
+        if let Some(url) = &bookmark.url {
+            let parsed = Url::parse(url)?;
+            by_host
+                .entry(bare_host(&parsed).map(str::to_string))
+                .or_default()
+                .push((i, parsed));
+        }
+    }
 
+    let mut pairs = Vec::new();
+    for group in by_host.values() {
+        for (k, (i, url1)) in group.iter().enumerate() {
+            for (j, url2) in &group[k + 1..] {
+                let similarity = calculate_url_similarity(url1, url2);
                 if similarity > 0.8 {
-                    duplicates.push((bookmark1.clone(), bookmark2.clone(), similarity));
+                    pairs.push((*i, *j, similarity));
                 }
             }
         }
     }
+    pairs.sort_by_key(|&(i, j, _)| (i, j));
 
-    Ok(duplicates)
+    Ok(pairs
+        .into_iter()
+        .map(|(i, j, similarity)| (bookmarks[i].clone(), bookmarks[j].clone(), similarity))
+        .collect())
 }
 
-fn calculate_url_similarity(url1: &str, url2: &str) -> Result<f64> {
-    let parsed1 = Url::parse(url1)?;
-    let parsed2 = Url::parse(url2)?;
-
+fn calculate_url_similarity(parsed1: &Url, parsed2: &Url) -> f64 {
     let mut score = 0.0;
-    let mut factors = 0;
 
-    if parsed1.host_str() == parsed2.host_str() {
+    if bare_host(parsed1) == bare_host(parsed2) {
         score += 0.5;
     }
-    factors += 1;
 
     let path1 = normalize_path(parsed1.path());
     let path2 = normalize_path(parsed2.path());
@@ -259,7 +391,6 @@ fn calculate_url_similarity(url1: &str, url2: &str) -> Result<f64> {
         let path_similarity = jaccard_similarity(&path_parts1, &path_parts2);
         score += path_similarity * 0.3;
     }
-    factors += 1;
 
     if let (Some(query1), Some(query2)) = (parsed1.query(), parsed2.query()) {
         let params1: HashSet<&str> = query1.split('&').collect();
@@ -270,9 +401,13 @@ fn calculate_url_similarity(url1: &str, url2: &str) -> Result<f64> {
     } else if parsed1.query().is_none() && parsed2.query().is_none() {
         score += 0.2;
     }
-    factors += 1;
 
-    Ok(score / factors as f64)
+    score
+}
+
+fn bare_host(url: &Url) -> Option<&str> {
+    url.host_str()
+        .map(|host| host.strip_prefix("www.").unwrap_or(host))
 }
 
 fn normalize_path(path: &str) -> String {
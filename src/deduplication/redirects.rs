@@ -0,0 +1,96 @@
+//! Following redirects so bookmarks of the same page compare equal.
+//!
+//! `http://` links that now answer on `https://`, short links and moved pages
+//! only collide once the redirect is followed. Resolving costs a request per
+//! URL, so final URLs are cached in memory and, optionally, in a JSON file
+//! that later runs reuse.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub struct RedirectResolver {
+    agent: ureq::Agent,
+    /// Final URL by requested URL; unreachable URLs are not cached
+    cache: Mutex<HashMap<String, String>>,
+    cache_file: Option<PathBuf>,
+}
+
+impl Default for RedirectResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RedirectResolver {
+    pub fn new() -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .redirects(10)
+                .build(),
+            cache: Mutex::new(HashMap::new()),
+            cache_file: None,
+        }
+    }
+
+    /// Seed the cache from `path` and write it back there with [`Self::save`].
+    /// A missing or unreadable file starts an empty cache.
+    pub fn with_cache_file(mut self, path: PathBuf) -> Self {
+        if let Ok(content) = fs::read_to_string(&path) {
+            match serde_json::from_str(&content) {
+                Ok(cache) => self.cache = Mutex::new(cache),
+                Err(e) => log::warn!("Ignoring redirect cache {}: {}", path.display(), e),
+            }
+        }
+        self.cache_file = Some(path);
+        self
+    }
+
+    /// The URL `url` ends up at, or `url` itself when it doesn't redirect,
+    /// isn't HTTP, or can't be reached
+    pub fn resolve(&self, url: &str) -> String {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return url.to_string();
+        }
+        if let Some(resolved) = self.cache.lock().unwrap().get(url) {
+            return resolved.clone();
+        }
+
+        let response = match self.agent.head(url).call() {
+            // Some servers refuse HEAD; the redirect still shows on GET
+            Err(ureq::Error::Status(405 | 501, _)) => self.agent.get(url).call(),
+            other => other,
+        };
+        let resolved = match response {
+            Ok(response) => response.get_url().to_string(),
+            // Error statuses still tell where the redirects led
+            Err(ureq::Error::Status(_, response)) => response.get_url().to_string(),
+            Err(e) => {
+                log::debug!("Could not resolve {}: {}", url, e);
+                return url.to_string();
+            }
+        };
+        let mut cache = self.cache.lock().unwrap();
+        cache.insert(url.to_string(), resolved.clone());
+        // The destination is final too; don't ask again when it is bookmarked
+        cache.insert(resolved.clone(), resolved.clone());
+        resolved
+    }
+
+    /// Write the cache to its file, if one was given
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.cache_file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let cache = self.cache.lock().unwrap();
+        fs::write(path, serde_json::to_string_pretty(&*cache)?)?;
+        Ok(())
+    }
+}
@@ -21,6 +21,41 @@ fn test_url_normalization() {
     );
 }
 
+#[test]
+fn test_site_rules_in_normalization() {
+    let deduplicator = BookmarkDeduplicator::new(DeduplicationConfig::default());
+    let normalize = |url: &str| deduplicator.normalize_url(url).unwrap();
+
+    // The video id survives ignore_query_params, so different videos stay apart
+    assert_eq!(
+        normalize("https://youtu.be/abc123"),
+        normalize("https://www.youtube.com/watch?v=abc123&t=10")
+    );
+    assert_ne!(
+        normalize("https://www.youtube.com/watch?v=abc123"),
+        normalize("https://www.youtube.com/watch?v=xyz789")
+    );
+    assert_eq!(
+        normalize("https://twitter.com/rustlang/status/1"),
+        normalize("https://x.com/rustlang/status/1")
+    );
+    assert_eq!(
+        normalize("https://github.com/rust-lang/rust/tree/master"),
+        normalize("https://github.com/rust-lang/rust")
+    );
+
+    let generic_only = BookmarkDeduplicator::new(DeduplicationConfig {
+        sites: SiteRules::none(),
+        ..Default::default()
+    });
+    assert_eq!(
+        generic_only
+            .normalize_url("https://www.youtube.com/watch?v=abc123")
+            .unwrap(),
+        "http://youtube.com/watch"
+    );
+}
+
 #[test]
 fn test_deduplication() {
     let config = DeduplicationConfig::default();
@@ -33,7 +68,7 @@ fn test_deduplication() {
             url: Some("https://www.example.com".to_string()),
             folder: Some("folder1".to_string()),
             date_added: None,
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "2".to_string(),
@@ -41,7 +76,7 @@ fn test_deduplication() {
             url: Some("http://example.com".to_string()),
             folder: Some("folder2".to_string()),
             date_added: None,
-            children: None,
+            ..Default::default()
         },
     ];
 
@@ -117,7 +152,7 @@ fn test_multiple_duplicates() {
             url: Some("https://www.example.com".to_string()),
             folder: None,
             date_added: None,
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "2".to_string(),
@@ -125,7 +160,7 @@ fn test_multiple_duplicates() {
             url: Some("http://example.com".to_string()),
             folder: None,
             date_added: None,
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "3".to_string(),
@@ -133,7 +168,7 @@ fn test_multiple_duplicates() {
             url: Some("https://example.com/".to_string()),
             folder: None,
             date_added: None,
-            children: None,
+            ..Default::default()
         },
     ];
 
@@ -155,7 +190,7 @@ fn test_no_duplicates() {
             url: Some("https://github.com".to_string()),
             folder: None,
             date_added: None,
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "2".to_string(),
@@ -163,7 +198,7 @@ fn test_no_duplicates() {
             url: Some("https://rust-lang.org".to_string()),
             folder: None,
             date_added: None,
-            children: None,
+            ..Default::default()
         },
     ];
 
@@ -185,7 +220,7 @@ fn test_bookmark_without_url() {
             url: Some("https://example.com".to_string()),
             folder: None,
             date_added: None,
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "2".to_string(),
@@ -193,7 +228,7 @@ fn test_bookmark_without_url() {
             url: None,
             folder: None,
             date_added: None,
-            children: None,
+            ..Default::default()
         },
     ];
 
@@ -213,7 +248,7 @@ fn test_merge_strategies() {
             url: Some("https://example.com".to_string()),
             folder: Some("folder1".to_string()),
             date_added: None,
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "2".to_string(),
@@ -221,7 +256,7 @@ fn test_merge_strategies() {
             url: Some("http://example.com".to_string()),
             folder: Some("folder2".to_string()),
             date_added: Some(Utc::now()),
-            children: None,
+            ..Default::default()
         },
     ];
 
@@ -244,6 +279,26 @@ fn test_merge_strategies() {
     let result = deduplicator.deduplicate(&bookmarks).unwrap();
     assert_eq!(result.unique_bookmarks[0].title, "Last");
     assert_eq!(result.unique_bookmarks[0].id, "2");
+
+    // Test KeepMostVisited: without history ties keep the first bookmark
+    let config = DeduplicationConfig {
+        merge_strategy: MergeStrategy::KeepMostVisited,
+        ..Default::default()
+    };
+    let result = BookmarkDeduplicator::new(config.clone())
+        .deduplicate(&bookmarks)
+        .unwrap();
+    assert_eq!(result.unique_bookmarks[0].id, "1");
+
+    let visits = HashMap::from([
+        ("https://example.com".to_string(), 3),
+        ("http://example.com".to_string(), 12),
+    ]);
+    let result = BookmarkDeduplicator::new(config)
+        .with_visit_counts(visits)
+        .deduplicate(&bookmarks)
+        .unwrap();
+    assert_eq!(result.unique_bookmarks[0].id, "2");
 }
 
 #[test]
@@ -272,3 +327,121 @@ fn test_complex_url_normalization() {
         assert_eq!(result, expected, "Failed for input: {}", input);
     }
 }
+
+#[test]
+fn test_redirects_resolved_before_grouping() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    // `/short` redirects to `/article`; every other path answers directly
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let location = format!("{}/article", base_url);
+    let server = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let status = if request_line.contains("/short") {
+                format!("301 Moved Permanently\r\nLocation: {}", location)
+            } else {
+                "200 OK".to_string()
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            )
+            .unwrap();
+            requests.push(request_line);
+        }
+        requests
+    });
+
+    let bookmark = |id: &str, path: &str| Bookmark {
+        id: id.to_string(),
+        title: "Article".to_string(),
+        url: Some(format!("{}{}", base_url, path)),
+        folder: None,
+        date_added: None,
+        ..Default::default()
+    };
+    let bookmarks = vec![bookmark("1", "/short"), bookmark("2", "/article")];
+
+    let deduplicator = BookmarkDeduplicator::new(DeduplicationConfig {
+        resolve_redirects: true,
+        ..Default::default()
+    });
+    let result = deduplicator.deduplicate(&bookmarks).unwrap();
+    assert_eq!(result.unique_bookmarks.len(), 1);
+    assert_eq!(result.duplicates_removed, 1);
+
+    // The redirect target was cached on the way, so it isn't requested twice
+    let requests = server.join().unwrap();
+    assert!(requests[0].starts_with("HEAD /short "));
+    assert!(requests[1].starts_with("HEAD /article "));
+}
+
+#[test]
+fn test_duplicate_groups_largest_first() {
+    let bookmark = |id: &str, url: &str| Bookmark {
+        id: id.to_string(),
+        title: id.to_string(),
+        url: Some(url.to_string()),
+        folder: None,
+        date_added: None,
+        ..Default::default()
+    };
+    let bookmarks = vec![
+        bookmark("1", "https://example.com/a"),
+        bookmark("2", "https://rust-lang.org/"),
+        bookmark("3", "https://www.example.com/a#top"),
+        bookmark("4", "https://rust-lang.org"),
+        bookmark("5", "https://example.com/a?ref=feed"),
+        bookmark("6", "https://crates.io/"),
+    ];
+
+    let deduplicator = BookmarkDeduplicator::new(DeduplicationConfig::default());
+    let groups = deduplicator.duplicate_groups(&bookmarks).unwrap();
+    let ids: Vec<Vec<&str>> = groups
+        .iter()
+        .map(|(_, group)| group.iter().map(|b| b.id.as_str()).collect())
+        .collect();
+    assert_eq!(ids, [vec!["1", "3", "5"], vec!["2", "4"]]);
+    assert_eq!(groups[0].0, "http://example.com/a");
+}
+
+#[test]
+fn test_potential_duplicates_on_the_same_host() {
+    let bookmark = |id: &str, url: &str| Bookmark {
+        id: id.to_string(),
+        title: id.to_string(),
+        url: Some(url.to_string()),
+        folder: None,
+        date_added: None,
+        ..Default::default()
+    };
+    let bookmarks = vec![
+        bookmark("1", "https://example.com/docs/intro"),
+        bookmark("2", "https://other.org/docs/intro"),
+        bookmark("3", "https://www.example.com/docs/intro/"),
+        bookmark("4", "https://example.com/blog?page=2"),
+        bookmark("5", "https://example.com/docs/setup"),
+    ];
+
+    let pairs = find_potential_duplicates(&bookmarks).unwrap();
+    let ids: Vec<(&str, &str)> = pairs
+        .iter()
+        .map(|(a, b, _)| (a.id.as_str(), b.id.as_str()))
+        .collect();
+    assert_eq!(ids, [("1", "3"), ("1", "5"), ("3", "5")]);
+    assert_eq!(pairs[0].2, 1.0);
+    // Same host and no query, with half the path segments shared
+    assert!((pairs[1].2 - 0.85).abs() < 1e-9);
+}
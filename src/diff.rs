@@ -0,0 +1,198 @@
+//! Differences between two bookmark collections.
+//!
+//! Bookmarks are matched by URL, the way `sync --between` matches them, and
+//! folders are compared with their roots normalized (see
+//! [`reconcile::canonical_folder`]), so a Chrome export and a Firefox one
+//! only differ where the bookmarks do. A bookmark both renamed and moved is
+//! listed under both.
+
+use serde::Serialize;
+use std::fmt::Write;
+
+use crate::exporter::Bookmark;
+use crate::reconcile;
+
+/// A bookmark only one side has
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffEntry {
+    pub url: String,
+    pub title: String,
+    pub folder: String,
+}
+
+/// A bookmark whose title changed
+#[derive(Debug, Clone, Serialize)]
+pub struct Retitled {
+    pub url: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// A bookmark filed in another folder
+#[derive(Debug, Clone, Serialize)]
+pub struct Moved {
+    pub url: String,
+    pub title: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// What changed from `old` to `new`, each list ordered by URL
+#[derive(Debug, Default, Serialize)]
+pub struct BookmarkDiff {
+    pub added: Vec<DiffEntry>,
+    pub removed: Vec<DiffEntry>,
+    pub retitled: Vec<Retitled>,
+    pub moved: Vec<Moved>,
+}
+
+impl BookmarkDiff {
+    /// Compare two collections, nested or flat. A URL bookmarked more than
+    /// once on a side is compared by its first copy.
+    pub fn between(old: &[Bookmark], new: &[Bookmark]) -> Self {
+        let old = reconcile::entries(old);
+        let mut new = reconcile::entries(new);
+        let mut diff = Self::default();
+
+        for (url, before) in old {
+            let Some(after) = new.remove(&url) else {
+                diff.removed.push(DiffEntry {
+                    url,
+                    title: before.title,
+                    folder: before.folder,
+                });
+                continue;
+            };
+            if before.title != after.title {
+                diff.retitled.push(Retitled {
+                    url: url.clone(),
+                    old: before.title,
+                    new: after.title.clone(),
+                });
+            }
+            if before.folder != after.folder {
+                diff.moved.push(Moved {
+                    url,
+                    title: after.title,
+                    from: before.folder,
+                    to: after.folder,
+                });
+            }
+        }
+        diff.added = new
+            .into_iter()
+            .map(|(url, entry)| DiffEntry {
+                url,
+                title: entry.title,
+                folder: entry.folder,
+            })
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.retitled.is_empty()
+            && self.moved.is_empty()
+    }
+
+    /// A count line followed by each change, for people
+    pub fn to_text(&self) -> String {
+        if self.is_empty() {
+            return "No differences\n".to_string();
+        }
+        let mut out = format!(
+            "{} added, {} removed, {} retitled, {} moved\n",
+            self.added.len(),
+            self.removed.len(),
+            self.retitled.len(),
+            self.moved.len()
+        );
+        for (heading, sign, entries) in
+            [("Added", '+', &self.added), ("Removed", '-', &self.removed)]
+        {
+            if !entries.is_empty() {
+                let _ = writeln!(out, "\n{}:", heading);
+            }
+            for entry in entries {
+                let _ = writeln!(
+                    out,
+                    "  {} {} ({}) in {}",
+                    sign, entry.title, entry.url, entry.folder
+                );
+            }
+        }
+        if !self.retitled.is_empty() {
+            out.push_str("\nRetitled:\n");
+        }
+        for change in &self.retitled {
+            let _ = writeln!(
+                out,
+                "  ~ \"{}\" → \"{}\" ({})",
+                change.old, change.new, change.url
+            );
+        }
+        if !self.moved.is_empty() {
+            out.push_str("\nMoved:\n");
+        }
+        for change in &self.moved {
+            let _ = writeln!(
+                out,
+                "  > {}: {} → {} ({})",
+                change.title, change.from, change.to, change.url
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(title: &str, url: &str, folder: &str) -> Bookmark {
+        Bookmark {
+            id: title.to_string(),
+            title: title.to_string(),
+            url: Some(url.to_string()),
+            folder: Some(folder.to_string()),
+            date_added: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_reports_each_kind_of_change() {
+        let old = vec![
+            bookmark("Rust", "https://rust-lang.org/", "bookmark_bar/Dev"),
+            bookmark("Tokio", "https://tokio.rs/", "bookmark_bar/Dev"),
+            bookmark("News", "https://news.example/", "other"),
+            bookmark("Same", "https://same.example/", "bookmark_bar"),
+        ];
+        let new = vec![
+            bookmark("Rust Lang", "https://rust-lang.org/", "toolbar/Dev"),
+            bookmark("Tokio", "https://tokio.rs/", "toolbar/Async"),
+            bookmark("Same", "https://same.example/", "toolbar"),
+            bookmark("Serde", "https://serde.rs/", "unfiled"),
+        ];
+        let diff = BookmarkDiff::between(&old, &new);
+
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].folder, "other");
+        assert_eq!(diff.removed[0].url, "https://news.example/");
+        assert_eq!(diff.retitled.len(), 1);
+        assert_eq!(diff.retitled[0].new, "Rust Lang");
+        assert_eq!(diff.moved.len(), 1);
+        assert_eq!(
+            (diff.moved[0].from.as_str(), diff.moved[0].to.as_str()),
+            ("toolbar/Dev", "toolbar/Async")
+        );
+
+        let text = diff.to_text();
+        assert!(text.starts_with("1 added, 1 removed, 1 retitled, 1 moved\n"));
+        assert!(text.contains("  + Serde (https://serde.rs/) in other\n"));
+        assert!(text.contains("  ~ \"Rust\" → \"Rust Lang\" (https://rust-lang.org/)\n"));
+        assert!(BookmarkDiff::between(&new, &new).is_empty());
+    }
+}
@@ -0,0 +1,277 @@
+//! Digests of recently added bookmarks.
+//!
+//! A [`Digest`] groups the bookmarks added in a period (say the last 7 days)
+//! by the same categories the knowledge graph uses, newest first, and counts
+//! them against the period before, so a team can see what is being collected
+//! and where. It renders as Markdown for chat webhooks or as an HTML page.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+use crate::exporter::{Bookmark, flatten_bookmarks};
+use crate::graph;
+
+/// Longest description shown under a bookmark, in characters
+const DESCRIPTION_LENGTH: usize = 160;
+
+/// New bookmarks of one category
+#[derive(Debug, Clone)]
+pub struct CategoryDigest {
+    pub name: String,
+    /// Newest first
+    pub bookmarks: Vec<Bookmark>,
+    /// Bookmarks in this category added in the period before
+    pub previous: usize,
+}
+
+/// Bookmarks added between `start` and `end`
+#[derive(Debug, Clone)]
+pub struct Digest {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Categories with new bookmarks, largest first
+    pub categories: Vec<CategoryDigest>,
+    /// Bookmarks added in the period of the same length before `start`
+    pub previous_total: usize,
+}
+
+impl Digest {
+    /// Collect bookmarks added in the `period` up to `end`, nested ones
+    /// included. A URL held by several profiles counts once.
+    pub fn build(bookmarks: &[Bookmark], period: Duration, end: DateTime<Utc>) -> Self {
+        let start = end - period;
+        let previous_start = start - period;
+        let mut seen = HashSet::new();
+        let mut categories: BTreeMap<String, CategoryDigest> = BTreeMap::new();
+        let mut previous_total = 0;
+
+        for bookmark in flatten_bookmarks(bookmarks) {
+            let (Some(url), Some(added)) = (bookmark.url.as_deref(), bookmark.date_added) else {
+                continue;
+            };
+            if added < previous_start || added > end || !seen.insert(url.to_string()) {
+                continue;
+            }
+            let domain = graph::extract_domain(url);
+            let name = graph::categorize(
+                &bookmark.title,
+                Some(url),
+                domain.as_deref(),
+                bookmark.page.as_ref(),
+            );
+            let category = categories
+                .entry(name.clone())
+                .or_insert_with(|| CategoryDigest {
+                    name,
+                    bookmarks: Vec::new(),
+                    previous: 0,
+                });
+            if added < start {
+                category.previous += 1;
+                previous_total += 1;
+            } else {
+                category.bookmarks.push(bookmark);
+            }
+        }
+
+        let mut categories: Vec<CategoryDigest> = categories
+            .into_values()
+            .filter(|c| !c.bookmarks.is_empty())
+            .collect();
+        for category in &mut categories {
+            category
+                .bookmarks
+                .sort_by_key(|b| std::cmp::Reverse(b.date_added));
+        }
+        categories.sort_by(|a, b| {
+            b.bookmarks
+                .len()
+                .cmp(&a.bookmarks.len())
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        Self {
+            start,
+            end,
+            categories,
+            previous_total,
+        }
+    }
+
+    /// Bookmarks added in the period
+    pub fn total(&self) -> usize {
+        self.categories.iter().map(|c| c.bookmarks.len()).sum()
+    }
+
+    fn title(&self) -> String {
+        format!(
+            "Bookmarks digest: {} to {}",
+            self.start.format("%Y-%m-%d"),
+            self.end.format("%Y-%m-%d")
+        )
+    }
+
+    fn summary(&self) -> String {
+        let period = self.end - self.start;
+        let period = match period.num_days() {
+            0 => format!("{} hours", period.num_hours()),
+            days => format!("{} days", days),
+        };
+        format!(
+            "{} new bookmarks ({} vs the previous {})",
+            self.total(),
+            change(self.total(), self.previous_total),
+            period
+        )
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n{}\n", self.title(), self.summary());
+        for category in &self.categories {
+            let _ = write!(
+                out,
+                "\n## {} ({}, {})\n\n",
+                category.name,
+                category.bookmarks.len(),
+                change(category.bookmarks.len(), category.previous)
+            );
+            for bookmark in &category.bookmarks {
+                let _ = writeln!(
+                    out,
+                    "- [{}]({})",
+                    bookmark.title.replace(['[', ']'], ""),
+                    bookmark.url.as_deref().unwrap_or_default()
+                );
+                if let Some(description) = short_description(bookmark) {
+                    let _ = writeln!(out, "  {}", description);
+                }
+            }
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>\n<p>{1}</p>\n",
+            escape_html(&self.title()),
+            escape_html(&self.summary())
+        );
+        for category in &self.categories {
+            let _ = write!(
+                out,
+                "<h2>{} ({}, {})</h2>\n<ul>\n",
+                escape_html(&category.name),
+                category.bookmarks.len(),
+                change(category.bookmarks.len(), category.previous)
+            );
+            for bookmark in &category.bookmarks {
+                let _ = write!(
+                    out,
+                    "<li><a href=\"{}\">{}</a>",
+                    escape_html(bookmark.url.as_deref().unwrap_or_default()),
+                    escape_html(&bookmark.title)
+                );
+                if let Some(description) = short_description(bookmark) {
+                    let _ = write!(out, "<br><small>{}</small>", escape_html(&description));
+                }
+                out.push_str("</li>\n");
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+/// `+3`, `-2` or `no change`
+fn change(now: usize, before: usize) -> String {
+    match now as i64 - before as i64 {
+        0 => "no change".to_string(),
+        diff => format!("{:+}", diff),
+    }
+}
+
+/// The enriched description on one line, cut at [`DESCRIPTION_LENGTH`]
+fn short_description(bookmark: &Bookmark) -> Option<String> {
+    let description = bookmark.description.as_deref()?;
+    let words: Vec<&str> = description.split_whitespace().collect();
+    if words.is_empty() {
+        return None;
+    }
+    let line = words.join(" ");
+    Some(match line.char_indices().nth(DESCRIPTION_LENGTH) {
+        Some((cut, _)) => format!("{}…", line[..cut].trim_end()),
+        None => line,
+    })
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Post `text` to a chat webhook as `{"text": ...}`, the payload Slack,
+/// Mattermost and Teams incoming webhooks accept
+pub fn post_webhook(url: &str, text: &str) -> Result<()> {
+    ureq::AgentBuilder::new()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .post(url)
+        .send_json(serde_json::json!({ "text": text }))
+        .with_context(|| format!("Could not post the digest to {}", url))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(title: &str, url: &str, days_ago: i64, description: Option<&str>) -> Bookmark {
+        Bookmark {
+            id: title.to_string(),
+            title: title.to_string(),
+            url: Some(url.to_string()),
+            folder: None,
+            date_added: Some(Utc::now() - Duration::days(days_ago)),
+            description: description.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_digest_groups_new_bookmarks() {
+        let long = "word ".repeat(100);
+        let bookmarks = vec![
+            bookmark("Tokio", "https://github.com/tokio-rs/tokio", 1, Some(&long)),
+            bookmark("Serde", "https://github.com/serde-rs/serde", 3, None),
+            bookmark("Serde again", "https://github.com/serde-rs/serde", 2, None),
+            bookmark("Kettle <deal>", "https://www.amazon.com/kettle", 2, None),
+            bookmark("Old repo", "https://github.com/old/repo", 10, None),
+            bookmark("Older shop", "https://www.ebay.com/x", 12, None),
+            bookmark("Ancient", "https://github.com/ancient/repo", 30, None),
+        ];
+        let digest = Digest::build(&bookmarks, Duration::days(7), Utc::now());
+
+        assert_eq!(digest.total(), 3);
+        assert_eq!(digest.previous_total, 2);
+        let names: Vec<_> = digest.categories.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Development", "Shopping"]);
+        let development = &digest.categories[0];
+        assert_eq!(development.bookmarks[0].title, "Tokio");
+        assert_eq!(development.previous, 1);
+
+        let markdown = digest.to_markdown();
+        assert!(markdown.contains("3 new bookmarks (+1 vs the previous 7 days)"));
+        assert!(markdown.contains("## Development (2, +1)"));
+        assert!(markdown.contains("## Shopping (1, no change)"));
+        assert!(markdown.contains("- [Tokio](https://github.com/tokio-rs/tokio)\n  word"));
+        assert!(markdown.contains("…"));
+
+        let html = digest.to_html();
+        assert!(html.contains("<a href=\"https://www.amazon.com/kettle\">Kettle &lt;deal&gt;</a>"));
+    }
+}
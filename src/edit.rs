@@ -0,0 +1,329 @@
+//! Bulk edits.
+//!
+//! A [`Filter`] picks bookmarks with search-like terms (`rust
+//! domain:medium.com tag:temp`), and an [`Edit`] sets fields and adds or
+//! removes tags on every one of them. Applying an edit returns the
+//! [`EditChange`]s it made, so callers can show a diff before writing.
+
+use anyhow::{Result, anyhow};
+use std::str::FromStr;
+use url::Url;
+
+use crate::exporter::{Bookmark, FOLDER_PATH_SEPARATOR};
+use crate::media::MediaType;
+use crate::search::matches_media;
+
+/// One filter term
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    /// Title or URL contains the text
+    Text(String),
+    Title(String),
+    Url(String),
+    /// Host is the domain or one of its subdomains
+    Domain(String),
+    /// Folder is this path or below it
+    Folder(String),
+    Tag(String),
+    Media(MediaType),
+}
+
+/// Bookmarks matching every term of a query. Bare words match the title or
+/// URL; `title:`, `url:`, `domain:`, `folder:`, `tag:` and `media:` match
+/// that field only. Quote values with spaces: `folder:"Read Later"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    terms: Vec<Term>,
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut terms = Vec::new();
+        for word in split_quoted(s)? {
+            let term = match word.split_once(':') {
+                Some(("title", value)) => Term::Title(value.to_lowercase()),
+                Some(("url", value)) => Term::Url(value.to_lowercase()),
+                Some(("domain", value)) => {
+                    Term::Domain(value.trim_start_matches("www.").to_lowercase())
+                }
+                Some(("folder", value)) => Term::Folder(value.trim_matches('/').to_lowercase()),
+                Some(("tag", value)) => Term::Tag(value.to_lowercase()),
+                Some(("media", value)) => Term::Media(value.parse()?),
+                // Anything else, URLs included, is plain text
+                _ => Term::Text(word.to_lowercase()),
+            };
+            terms.push(term);
+        }
+        if terms.is_empty() {
+            return Err(anyhow!(
+                "Empty filter; use a word or a field such as domain:example.com"
+            ));
+        }
+        Ok(Filter { terms })
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, bookmark: &Bookmark) -> bool {
+        let title = bookmark.title.to_lowercase();
+        let url = bookmark.url.as_deref().unwrap_or_default().to_lowercase();
+        self.terms.iter().all(|term| match term {
+            Term::Text(text) => title.contains(text) || url.contains(text),
+            Term::Title(text) => title.contains(text),
+            Term::Url(text) => url.contains(text),
+            Term::Domain(domain) => Url::parse(&url)
+                .ok()
+                .and_then(|u| u.host_str().map(str::to_string))
+                .is_some_and(|host| host == *domain || host.ends_with(&format!(".{}", domain))),
+            Term::Folder(folder) => in_folder(bookmark.folder.as_deref(), folder),
+            Term::Tag(tag) => bookmark
+                .tags
+                .iter()
+                .flatten()
+                .any(|t| t.to_lowercase() == *tag),
+            Term::Media(media) => matches_media(bookmark, Some(*media)),
+        })
+    }
+}
+
+/// Whether `folder` is `parent` or one of its subfolders, ignoring case and
+/// leading or trailing slashes
+pub fn in_folder(folder: Option<&str>, parent: &str) -> bool {
+    let parent = parent.trim_matches('/').to_lowercase();
+    folder.is_some_and(|f| {
+        let f = f.trim_matches('/').to_lowercase();
+        f == parent || f.starts_with(&format!("{}{}", parent, FOLDER_PATH_SEPARATOR))
+    })
+}
+
+/// Split on whitespace, keeping double-quoted runs together
+fn split_quoted(s: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in s.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if quoted {
+        return Err(anyhow!("Unclosed quote in filter: {}", s));
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// A field `--set` can change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditField {
+    Title,
+    Folder,
+    Description,
+    Keyword,
+}
+
+impl EditField {
+    fn name(self) -> &'static str {
+        match self {
+            EditField::Title => "title",
+            EditField::Folder => "folder",
+            EditField::Description => "description",
+            EditField::Keyword => "keyword",
+        }
+    }
+}
+
+/// Changes to make to every matching bookmark
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Edit {
+    /// Fields to overwrite; an empty value clears all but the title
+    pub set: Vec<(EditField, String)>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+}
+
+/// One field of one bookmark changed by an edit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditChange {
+    pub id: String,
+    pub url: String,
+    /// `title`, `folder`, `description`, `keyword` or `tags`
+    pub field: &'static str,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl Edit {
+    /// Parse a `--set` argument such as `folder=Reading/Blogs`
+    pub fn parse_assignment(assignment: &str) -> Result<(EditField, String)> {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected FIELD=VALUE, got: {}", assignment))?;
+        let field = match name.trim() {
+            "title" => EditField::Title,
+            "folder" => EditField::Folder,
+            "description" => EditField::Description,
+            "keyword" => EditField::Keyword,
+            other => {
+                return Err(anyhow!(
+                    "Cannot set {} (expected title, folder, description or keyword)",
+                    other
+                ));
+            }
+        };
+        if field == EditField::Title && value.trim().is_empty() {
+            return Err(anyhow!("A bookmark's title cannot be cleared"));
+        }
+        Ok((field, value.trim().to_string()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.add_tags.is_empty() && self.remove_tags.is_empty()
+    }
+
+    /// Edit `bookmark`, returning what actually changed
+    pub fn apply(&self, bookmark: &mut Bookmark) -> Vec<EditChange> {
+        let mut changes = Vec::new();
+        let mut record = |bookmark: &Bookmark, field, old: Option<String>, new: Option<String>| {
+            if old != new {
+                changes.push(EditChange {
+                    id: bookmark.id.clone(),
+                    url: bookmark.url.clone().unwrap_or_default(),
+                    field,
+                    old,
+                    new,
+                });
+            }
+        };
+
+        for (field, value) in &self.set {
+            let value = (!value.is_empty()).then(|| value.clone());
+            let old = match field {
+                EditField::Title => Some(std::mem::replace(
+                    &mut bookmark.title,
+                    value.clone().unwrap_or_default(),
+                )),
+                EditField::Folder => std::mem::replace(&mut bookmark.folder, value.clone()),
+                EditField::Description => {
+                    std::mem::replace(&mut bookmark.description, value.clone())
+                }
+                EditField::Keyword => std::mem::replace(&mut bookmark.keyword, value.clone()),
+            };
+            record(bookmark, field.name(), old, value);
+        }
+
+        if !self.add_tags.is_empty() || !self.remove_tags.is_empty() {
+            let old = bookmark.tags.clone().unwrap_or_default();
+            let mut tags: Vec<String> = old
+                .iter()
+                .filter(|t| !self.remove_tags.iter().any(|r| r.eq_ignore_ascii_case(t)))
+                .cloned()
+                .collect();
+            for tag in &self.add_tags {
+                if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                    tags.push(tag.clone());
+                }
+            }
+            bookmark.tags = (!tags.is_empty()).then(|| tags.clone());
+            let list = |tags: &[String]| (!tags.is_empty()).then(|| tags.join(", "));
+            record(bookmark, "tags", list(&old), list(&tags));
+        }
+        changes
+    }
+
+    /// Apply the edit to every bookmark `filter` matches, including those in
+    /// nested folders
+    pub fn apply_matching(&self, filter: &Filter, bookmarks: &mut [Bookmark]) -> Vec<EditChange> {
+        let mut changes = Vec::new();
+        for bookmark in bookmarks {
+            if let Some(children) = &mut bookmark.children {
+                changes.extend(self.apply_matching(filter, children));
+            }
+            if !bookmark.is_folder() && filter.matches(bookmark) {
+                changes.extend(self.apply(bookmark));
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(title: &str, url: &str, folder: &str, tags: &[&str]) -> Bookmark {
+        Bookmark {
+            id: title.to_string(),
+            title: title.to_string(),
+            url: Some(url.to_string()),
+            folder: Some(folder.to_string()),
+            date_added: None,
+            tags: (!tags.is_empty()).then(|| tags.iter().map(|t| t.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_filter_terms() {
+        let post = bookmark(
+            "Async Rust",
+            "https://blog.medium.com/async-rust",
+            "Read Later/Rust",
+            &["Temp"],
+        );
+        let matches = |query: &str| query.parse::<Filter>().unwrap().matches(&post);
+
+        assert!(matches("domain:medium.com"));
+        assert!(matches("rust domain:medium.com tag:temp"));
+        assert!(matches("folder:\"read later\""));
+        assert!(matches("https://blog.medium.com"));
+        assert!(!matches("domain:dium.com"));
+        assert!(!matches("folder:Read"));
+        assert!(in_folder(Some("/Read Later/Rust/"), "read later"));
+        assert!(!matches("title:medium"));
+        assert!(!matches("media:pdf"));
+        assert!("folder:\"Read".parse::<Filter>().is_err());
+        assert!("  ".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn test_edit_reports_changes() {
+        let edit = Edit {
+            set: vec![
+                Edit::parse_assignment("folder=Reading/Blogs").unwrap(),
+                Edit::parse_assignment("description=").unwrap(),
+            ],
+            add_tags: vec!["blog".to_string(), "Rust".to_string()],
+            remove_tags: vec!["temp".to_string()],
+        };
+        assert!(Edit::parse_assignment("url=x").is_err());
+        assert!(Edit::parse_assignment("title=").is_err());
+
+        let filter: Filter = "domain:medium.com".parse().unwrap();
+        let mut bookmarks = vec![
+            bookmark("Post", "https://medium.com/p/1", "Misc", &["temp", "rust"]),
+            bookmark("Rust", "https://www.rust-lang.org/", "Misc", &["temp"]),
+        ];
+        let changes = edit.apply_matching(&filter, &mut bookmarks);
+
+        let fields: Vec<_> = changes.iter().map(|c| c.field).collect();
+        assert_eq!(fields, ["folder", "tags"]);
+        assert_eq!(changes[1].old.as_deref(), Some("temp, rust"));
+        assert_eq!(changes[1].new.as_deref(), Some("rust, blog"));
+        assert_eq!(bookmarks[0].folder.as_deref(), Some("Reading/Blogs"));
+        assert_eq!(bookmarks[1].folder.as_deref(), Some("Misc"));
+
+        // Applying again changes nothing
+        assert!(edit.apply_matching(&filter, &mut bookmarks).is_empty());
+    }
+}
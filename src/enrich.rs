@@ -0,0 +1,400 @@
+//! Page metadata enrichment.
+//!
+//! Fetches each bookmarked page and reads its `<title>`, meta description
+//! (falling back to the Open Graph tags) and `<link rel="canonical">`. Empty
+//! titles and descriptions are filled in, and with `overwrite` stale ones are
+//! replaced by what the page says now. The site name, Open Graph type and
+//! keywords are kept in the bookmark's `page` field, where the graph picks
+//! them up as tags. Pages are fetched concurrently, once per URL, and only
+//! the head of each response is read.
+
+use anyhow::{Result, anyhow};
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::io::Read;
+use std::time::Duration;
+use url::Url;
+
+use crate::exporter::Bookmark;
+use crate::importer::{ATTRIBUTE_PATTERN, decode_entities, parse_attributes};
+
+/// Bytes read from each page; metadata lives in the `<head>`
+const MAX_PAGE_BYTES: u64 = 512 * 1024;
+
+/// What a page says about itself
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Absolute canonical URL
+    pub canonical_url: Option<String>,
+    pub info: PageInfo,
+}
+
+/// How a page classifies itself, from `og:site_name`, `og:type`, the
+/// `keywords` meta tag and `article:tag`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+    /// e.g. `article`, `video.movie`; `website` says nothing and is dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub og_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+}
+
+impl PageInfo {
+    pub fn is_empty(&self) -> bool {
+        self.site_name.is_none() && self.og_type.is_none() && self.keywords.is_empty()
+    }
+
+    /// One-line summary for change listings
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(site_name) = &self.site_name {
+            parts.push(format!("site {}", site_name));
+        }
+        if let Some(og_type) = &self.og_type {
+            parts.push(format!("type {}", og_type));
+        }
+        if !self.keywords.is_empty() {
+            parts.push(format!("keywords {}", self.keywords.join(", ")));
+        }
+        parts.join("; ")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EnrichOptions {
+    /// Pages fetched at once
+    pub concurrency: usize,
+    /// Replace existing titles and descriptions, not just empty ones
+    pub overwrite: bool,
+}
+
+impl Default for EnrichOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            overwrite: false,
+        }
+    }
+}
+
+/// One field changed on one bookmark
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnrichChange {
+    pub id: String,
+    pub url: String,
+    /// `title`, `description`, `canonical_url` or `page`
+    pub field: &'static str,
+    pub old: Option<String>,
+    pub new: String,
+}
+
+/// Outcome of enriching a set of bookmarks
+#[derive(Debug, Default)]
+pub struct EnrichReport {
+    /// Distinct pages fetched successfully
+    pub fetched: usize,
+    pub changes: Vec<EnrichChange>,
+    /// Pages that could not be fetched, with the reason
+    pub failures: Vec<String>,
+}
+
+pub struct Enricher {
+    agent: ureq::Agent,
+    options: EnrichOptions,
+}
+
+impl Enricher {
+    pub fn new(options: EnrichOptions) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(15))
+                .user_agent(concat!("bookmark/", env!("CARGO_PKG_VERSION")))
+                .build(),
+            options,
+        }
+    }
+
+    /// Fetch `url` and read its metadata
+    pub fn fetch(&self, url: &str) -> Result<PageMetadata> {
+        // ureq's errors already name the URL
+        let response = self.agent.get(url).call()?;
+        if !response.content_type().contains("html") {
+            return Err(anyhow!("{}: not an HTML page", url));
+        }
+        let final_url = response.get_url().to_string();
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .take(MAX_PAGE_BYTES)
+            .read_to_end(&mut bytes)?;
+        Ok(parse_metadata(&String::from_utf8_lossy(&bytes), &final_url))
+    }
+
+    /// Fetch every distinct HTTP URL among `bookmarks` (folders included)
+    /// and update them in place, recording each change
+    pub fn enrich(&self, bookmarks: &mut [Bookmark]) -> Result<EnrichReport> {
+        let mut leaves = Vec::new();
+        collect_leaves(bookmarks, &mut leaves);
+        let urls: BTreeSet<String> = leaves
+            .iter()
+            .filter_map(|b| b.url.clone())
+            .filter(|u| u.starts_with("http://") || u.starts_with("https://"))
+            .collect();
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.options.concurrency.max(1))
+            .build()?;
+        let fetched: Vec<(String, Result<PageMetadata>)> = pool.install(|| {
+            urls.into_par_iter()
+                .map(|url| {
+                    let metadata = self.fetch(&url);
+                    (url, metadata)
+                })
+                .collect()
+        });
+
+        let mut report = EnrichReport::default();
+        let mut pages = HashMap::new();
+        for (url, metadata) in fetched {
+            match metadata {
+                Ok(metadata) => {
+                    report.fetched += 1;
+                    pages.insert(url, metadata);
+                }
+                Err(e) => report.failures.push(e.to_string()),
+            }
+        }
+
+        for bookmark in leaves {
+            let Some(url) = bookmark.url.clone() else {
+                continue;
+            };
+            let Some(page) = pages.get(&url) else {
+                continue;
+            };
+            let mut change = |field, old: Option<String>, new: &String| {
+                report.changes.push(EnrichChange {
+                    id: bookmark.id.clone(),
+                    url: url.clone(),
+                    field,
+                    old,
+                    new: new.clone(),
+                })
+            };
+
+            if let Some(title) = &page.title
+                && *title != bookmark.title
+                && (self.options.overwrite || is_placeholder_title(&bookmark.title, &url))
+            {
+                change("title", Some(bookmark.title.clone()), title);
+                bookmark.title = title.clone();
+            }
+            if let Some(description) = &page.description
+                && bookmark.description.as_ref() != Some(description)
+                && (self.options.overwrite
+                    || bookmark.description.as_deref().is_none_or(str::is_empty))
+            {
+                change("description", bookmark.description.clone(), description);
+                bookmark.description = Some(description.clone());
+            }
+            if let Some(canonical) = &page.canonical_url
+                && *canonical != url
+                && bookmark.canonical_url.as_ref() != Some(canonical)
+            {
+                change("canonical_url", bookmark.canonical_url.clone(), canonical);
+                bookmark.canonical_url = Some(canonical.clone());
+            }
+            if !page.info.is_empty() && bookmark.page.as_ref() != Some(&page.info) {
+                let old = bookmark.page.as_ref().map(PageInfo::describe);
+                change("page", old, &page.info.describe());
+                bookmark.page = Some(page.info.clone());
+            }
+        }
+        Ok(report)
+    }
+}
+
+fn collect_leaves<'a>(bookmarks: &'a mut [Bookmark], out: &mut Vec<&'a mut Bookmark>) {
+    for bookmark in bookmarks {
+        if bookmark.url.is_some() {
+            out.push(bookmark);
+        } else if let Some(children) = &mut bookmark.children {
+            collect_leaves(children, out);
+        }
+    }
+}
+
+/// Titles that say nothing about the page: empty, or just its URL
+fn is_placeholder_title(title: &str, url: &str) -> bool {
+    let title = title.trim().trim_end_matches('/');
+    let bare = url.split_once("://").map_or(url, |(_, rest)| rest);
+    title.is_empty() || title == url.trim_end_matches('/') || title == bare.trim_end_matches('/')
+}
+
+/// Read the title, description and canonical URL from an HTML document
+/// served at `page_url`
+pub fn parse_metadata(html: &str, page_url: &str) -> PageMetadata {
+    let title_tag = Regex::new(r"(?is)<title\b[^>]*>(.*?)</title\s*>").unwrap();
+    let meta_tag = Regex::new(r"(?is)<meta\b([^>]*)>").unwrap();
+    let link_tag = Regex::new(r"(?is)<link\b([^>]*)>").unwrap();
+    let attribute = Regex::new(ATTRIBUTE_PATTERN).unwrap();
+
+    let mut meta: HashMap<String, String> = HashMap::new();
+    let mut keywords: Vec<String> = Vec::new();
+    for caps in meta_tag.captures_iter(html) {
+        let attrs = parse_attributes(&attribute, &caps[1]);
+        let key = attrs.get("NAME").or_else(|| attrs.get("PROPERTY"));
+        if let (Some(key), Some(content)) = (key, attrs.get("CONTENT")) {
+            let key = key.to_lowercase();
+            // `keywords` is a comma-separated list; `article:tag` repeats
+            if key == "keywords" || key == "article:tag" {
+                for keyword in content.split(',').map(clean) {
+                    if !keyword.is_empty() && !keywords.contains(&keyword) {
+                        keywords.push(keyword);
+                    }
+                }
+            }
+            meta.entry(key).or_insert_with(|| clean(content));
+        }
+    }
+    let meta = |key: &str| meta.get(key).filter(|v| !v.is_empty()).cloned();
+
+    let canonical_url = link_tag
+        .captures_iter(html)
+        .map(|caps| parse_attributes(&attribute, &caps[1]))
+        .find(|attrs| {
+            attrs
+                .get("REL")
+                .is_some_and(|rel| rel.eq_ignore_ascii_case("canonical"))
+        })
+        .and_then(|attrs| attrs.get("HREF").cloned())
+        .and_then(|href| Url::parse(page_url).ok()?.join(&href).ok())
+        .map(|u| u.to_string());
+
+    PageMetadata {
+        title: title_tag
+            .captures(html)
+            .map(|caps| clean(&decode_entities(&caps[1])))
+            .filter(|t| !t.is_empty())
+            .or_else(|| meta("og:title")),
+        description: meta("description").or_else(|| meta("og:description")),
+        canonical_url,
+        info: PageInfo {
+            site_name: meta("og:site_name"),
+            og_type: meta("og:type").filter(|t| !t.eq_ignore_ascii_case("website")),
+            keywords,
+        },
+    }
+}
+
+/// Collapse runs of whitespace, as browsers do when displaying a title
+fn clean(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    const PAGE: &str = r#"<!doctype html><html><head>
+        <title>
+          Rust &amp; Cargo
+        </title>
+        <meta property="og:description" content="Open Graph text">
+        <meta name="description" content="The Rust   book">
+        <meta property="og:site_name" content="Rust Docs">
+        <meta property="og:type" content="book">
+        <meta name="keywords" content="rust, Systems Programming,">
+        <meta property="article:tag" content="cargo">
+        <meta property="article:tag" content="rust">
+        <link rel="canonical" href="/book/">
+        </head><body><title>Not this one</title></body></html>"#;
+
+    #[test]
+    fn test_parse_metadata() {
+        let metadata = parse_metadata(PAGE, "https://example.com/book/index.html?ref=x");
+        assert_eq!(metadata.title.as_deref(), Some("Rust & Cargo"));
+        assert_eq!(metadata.description.as_deref(), Some("The Rust book"));
+        assert_eq!(
+            metadata.canonical_url.as_deref(),
+            Some("https://example.com/book/")
+        );
+        assert_eq!(metadata.info.site_name.as_deref(), Some("Rust Docs"));
+        assert_eq!(metadata.info.og_type.as_deref(), Some("book"));
+        assert_eq!(
+            metadata.info.keywords,
+            ["rust", "Systems Programming", "cargo"]
+        );
+
+        let bare = parse_metadata(
+            r#"<meta property="og:title" content="Fallback">"#,
+            "https://example.com/",
+        );
+        assert_eq!(bare.title.as_deref(), Some("Fallback"));
+        assert_eq!(bare.description, None);
+        assert!(bare.info.is_empty());
+    }
+
+    #[test]
+    fn test_enrich_fills_placeholders_only() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                PAGE.len(),
+                PAGE
+            )
+            .unwrap();
+        });
+
+        let url = format!("{}/book/index.html", base_url);
+        let bookmark = |id: &str, title: &str| Bookmark {
+            id: id.to_string(),
+            title: title.to_string(),
+            url: Some(url.clone()),
+            folder: None,
+            date_added: None,
+            ..Default::default()
+        };
+        let mut bookmarks = vec![bookmark("1", ""), bookmark("2", "My notes")];
+        let report = Enricher::new(EnrichOptions::default())
+            .enrich(&mut bookmarks)
+            .unwrap();
+        server.join().unwrap();
+
+        assert_eq!(report.fetched, 1, "each URL is fetched once");
+        assert!(report.failures.is_empty());
+        assert_eq!(bookmarks[0].title, "Rust & Cargo");
+        assert_eq!(bookmarks[1].title, "My notes");
+        assert_eq!(bookmarks[1].description.as_deref(), Some("The Rust book"));
+        assert_eq!(
+            bookmarks[0].canonical_url,
+            Some(format!("{}/book/", base_url))
+        );
+        // title, description, canonical URL and page info for the first; no
+        // title for the second
+        assert_eq!(report.changes.len(), 7);
+        assert_eq!(
+            bookmarks[1].page.as_ref().map(|p| p.keywords.len()),
+            Some(3)
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Expiration policies for throwaway folders.
+//!
+//! A policy such as `{folder: "Temp", after: 30d, action: archive}` in the
+//! config expires bookmarks in `Temp` (and its subfolders) once they are
+//! older than 30 days. Every expired bookmark is recorded as an
+//! [`Expiration`] so the processing report shows what happened to it.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::exporter::Bookmark;
+
+/// What to do with an expired bookmark
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpireAction {
+    /// Drop the bookmark
+    Delete,
+    /// Move the bookmark to the policy's `to` folder
+    Move,
+    /// Save a copy of the page to the archives directory, then drop the bookmark
+    Archive,
+}
+
+/// One folder's expiration policy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpirePolicy {
+    /// Folder name or path; subfolders are included
+    pub folder: String,
+    /// Age after which bookmarks expire, e.g. `12h`, `30d`, `2w`, `1y`
+    pub after: String,
+    pub action: ExpireAction,
+    /// Destination folder for `move`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+}
+
+impl ExpirePolicy {
+    /// The `after` age as a duration
+    pub fn max_age(&self) -> Result<Duration> {
+        parse_age(&self.after)
+    }
+
+    /// Check the policy can be applied
+    pub fn validate(&self) -> Result<()> {
+        if self.folder.trim().is_empty() {
+            return Err(anyhow!("Expire policy has empty folder"));
+        }
+        self.max_age()
+            .map_err(|e| anyhow!("Expire policy for '{}': {}", self.folder, e))?;
+        if self.action == ExpireAction::Move && self.to.as_deref().is_none_or(str::is_empty) {
+            return Err(anyhow!(
+                "Expire policy for '{}' moves bookmarks but has no `to` folder",
+                self.folder
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether `folder` is the policy's folder or one of its subfolders.
+    /// The policy folder may match anywhere in the path, so `Temp` covers
+    /// `Bookmarks Bar/Temp/Reading`.
+    pub fn covers(&self, folder: &str, separator: &str) -> bool {
+        let wanted: Vec<&str> = segments(&self.folder, separator);
+        let path: Vec<&str> = segments(folder, separator);
+        !wanted.is_empty() && path.windows(wanted.len()).any(|w| w == wanted.as_slice())
+    }
+}
+
+fn segments<'a>(path: &'a str, separator: &str) -> Vec<&'a str> {
+    path.split(separator)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Parse an age like `30d`: a whole number followed by `h`, `d`, `w` or `y`
+pub fn parse_age(age: &str) -> Result<Duration> {
+    let age = age.trim();
+    let split = age
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("Age '{}' needs a unit (h, d, w, y)", age))?;
+    let (number, unit) = age.split_at(split);
+    let n: i64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid age: '{}'", age))?;
+    match unit {
+        "h" => Ok(Duration::hours(n)),
+        "d" => Ok(Duration::days(n)),
+        "w" => Ok(Duration::weeks(n)),
+        "y" => Ok(Duration::days(n * 365)),
+        _ => Err(anyhow!(
+            "Unknown age unit '{}' in '{}' (use h, d, w, y)",
+            unit,
+            age
+        )),
+    }
+}
+
+/// Audit record for one expired bookmark
+#[derive(Debug, Clone, Serialize)]
+pub struct Expiration {
+    pub title: String,
+    pub url: Option<String>,
+    pub folder: String,
+    pub date_added: DateTime<Utc>,
+    /// The policy folder that matched
+    pub policy: String,
+    pub action: ExpireAction,
+    /// What was done, e.g. `deleted`, `moved to Archive` or the archive path
+    pub outcome: String,
+    /// False when the action failed and the bookmark was kept
+    pub applied: bool,
+}
+
+/// Save the page at `url` under `dir`, named by a hash of the URL
+pub fn archive_page(url: &str, dir: &Path) -> Result<PathBuf> {
+    let response = ureq::AgentBuilder::new()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .get(url)
+        .call()
+        .map_err(|e| anyhow!("fetch failed: {}", e))?;
+    let body = response.into_string()?;
+
+    fs::create_dir_all(dir)?;
+    let digest: String = Sha256::digest(url.as_bytes())
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let path = dir.join(format!("{}.html", digest));
+    fs::write(&path, body)?;
+    Ok(path)
+}
+
+/// The first policy that expires `bookmark` at `now`, if any
+pub fn matching_policy<'a>(
+    policies: &'a [(ExpirePolicy, Duration)],
+    bookmark: &Bookmark,
+    separator: &str,
+    now: DateTime<Utc>,
+) -> Option<&'a ExpirePolicy> {
+    bookmark.url.as_ref()?;
+    let folder = bookmark.folder.as_deref()?;
+    let added = bookmark.date_added?;
+    policies
+        .iter()
+        .find(|(policy, max_age)| policy.covers(folder, separator) && now - added > *max_age)
+        .map(|(policy, _)| policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30d").unwrap(), Duration::days(30));
+        assert_eq!(parse_age(" 2w ").unwrap(), Duration::weeks(2));
+        assert_eq!(parse_age("12h").unwrap(), Duration::hours(12));
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("3 months").is_err());
+    }
+
+    #[test]
+    fn test_policy_covers_subfolders() {
+        let policy = ExpirePolicy {
+            folder: "Temp".to_string(),
+            after: "30d".to_string(),
+            action: ExpireAction::Delete,
+            to: None,
+        };
+        assert!(policy.covers("Temp", "/"));
+        assert!(policy.covers("Bookmarks Bar/Temp/Reading", "/"));
+        assert!(!policy.covers("Templates", "/"));
+        assert!(!policy.covers("Work", "/"));
+
+        let moving = ExpirePolicy {
+            action: ExpireAction::Move,
+            ..policy
+        };
+        assert!(moving.validate().is_err());
+    }
+}
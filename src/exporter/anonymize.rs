@@ -0,0 +1,214 @@
+//! Anonymized exports for sharing datasets in bug reports.
+//!
+//! Every title, folder segment, tag and URL component is replaced by a salted
+//! hash. Equal inputs map to equal tokens, so duplicates still collide, and
+//! URLs are hashed per host label and path segment, so pages on the same
+//! domain (or subdomains of it) still group together in graphs. Profile
+//! names, emails and saved passwords are dropped.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+use super::checksum::sha256_hex;
+use super::{Bookmark, BrowserData};
+
+#[derive(Debug, Clone)]
+pub struct Anonymizer {
+    salt: String,
+}
+
+impl Anonymizer {
+    /// Reuse `salt` to keep tokens consistent across several exports; without
+    /// one a fresh salt is drawn, so tokens cannot be matched to guessed URLs
+    pub fn new(salt: Option<&str>) -> Self {
+        let salt = match salt {
+            Some(salt) => salt.to_string(),
+            None => {
+                let nanos = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos())
+                    .unwrap_or_default();
+                sha256_hex(format!("{}:{}", nanos, std::process::id()).as_bytes())
+            }
+        };
+        Self { salt }
+    }
+
+    fn token(&self, text: &str) -> String {
+        sha256_hex(format!("{}\0{}", self.salt, text).as_bytes())[..10].to_string()
+    }
+
+    /// Hash free text; empty text stays empty so "untitled" is still visible
+    pub fn text(&self, text: &str) -> String {
+        if text.is_empty() {
+            String::new()
+        } else {
+            self.token(text)
+        }
+    }
+
+    /// Hash each `/`-separated segment of a folder path
+    pub fn folder(&self, path: &str) -> String {
+        path.split('/')
+            .map(|segment| self.text(segment))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Hash a URL component-wise: the scheme, port, `www.` prefix and URL
+    /// shape are kept; host labels, path segments, query keys and values,
+    /// and the fragment are hashed individually
+    pub fn url(&self, raw: &str) -> String {
+        let Ok(url) = Url::parse(raw) else {
+            return format!("opaque:{}", self.token(raw));
+        };
+        let Some(host) = url.host_str() else {
+            return format!("{}:{}", url.scheme(), self.token(raw));
+        };
+
+        let (www, host) = match host.strip_prefix("www.") {
+            Some(rest) => ("www.", rest),
+            None => ("", host),
+        };
+        let host: Vec<String> = host.split('.').map(|label| self.token(label)).collect();
+        let mut out = format!("{}://{}{}", url.scheme(), www, host.join("."));
+        if let Some(port) = url.port() {
+            out.push_str(&format!(":{}", port));
+        }
+        let path: Vec<String> = url.path().split('/').map(|s| self.text(s)).collect();
+        out.push_str(&path.join("/"));
+        if let Some(query) = url.query() {
+            let pairs: Vec<String> = query
+                .split('&')
+                .map(|pair| match pair.split_once('=') {
+                    Some((key, value)) => format!("{}={}", self.text(key), self.text(value)),
+                    None => self.text(pair),
+                })
+                .collect();
+            out.push('?');
+            out.push_str(&pairs.join("&"));
+        }
+        if let Some(fragment) = url.fragment() {
+            out.push('#');
+            out.push_str(&self.text(fragment));
+        }
+        out
+    }
+
+    pub fn bookmark(&self, bookmark: &Bookmark) -> Bookmark {
+        Bookmark {
+            id: bookmark.id.clone(),
+            title: self.text(&bookmark.title),
+            url: bookmark.url.as_deref().map(|u| self.url(u)),
+            folder: bookmark.folder.as_deref().map(|f| self.folder(f)),
+            date_added: bookmark.date_added,
+            children: bookmark
+                .children
+                .as_ref()
+                .map(|c| c.iter().map(|b| self.bookmark(b)).collect()),
+            keyword: bookmark.keyword.as_deref().map(|k| self.text(k)),
+            tags: bookmark
+                .tags
+                .as_ref()
+                .map(|t| t.iter().map(|tag| self.text(tag)).collect()),
+            description: bookmark.description.as_deref().map(|d| self.text(d)),
+            media_type: bookmark.media_type,
+            // Snapshot URLs embed the original URL
+            // Repository names identify the page too
+            canonical_url: bookmark.canonical_url.as_deref().map(|u| self.url(u)),
+            // Citations identify the page
+            // Images show the page
+            // Site names and keywords identify the page too
+            ..Default::default()
+        }
+    }
+
+    /// Anonymize exported data in place
+    pub fn apply(&self, data: &mut BrowserData) {
+        data.profile = self.text(&data.profile);
+        data.profile_name = None;
+        data.profile_email = None;
+        data.passwords = None;
+        if let Some(bookmarks) = &mut data.bookmarks {
+            *bookmarks = bookmarks.iter().map(|b| self.bookmark(b)).collect();
+        }
+        if let Some(history) = &mut data.history {
+            for entry in &mut history.urls {
+                entry.url = self.url(&entry.url);
+                entry.title = self.text(&entry.title);
+            }
+        }
+        if let Some(aliases) = &mut data.aliases {
+            *aliases = aliases
+                .iter()
+                .map(|(keyword, url)| (self.text(keyword), self.url(url)))
+                .collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    #[test]
+    fn test_url_keeps_domain_relationships() {
+        let anon = Anonymizer::new(Some("salt"));
+        let a = anon.url("https://www.docs.example.com/guide/intro?lang=en#top");
+        let b = anon.url("https://docs.example.com/guide/");
+        let c = anon.url("https://api.example.com/guide/");
+
+        assert!(a.starts_with("https://www."));
+        assert!(!a.contains("example") && !a.contains("guide") && !a.contains("top"));
+        let host = |u: &str| Url::parse(u).unwrap().host_str().unwrap().to_string();
+        assert_eq!(host(&a).strip_prefix("www."), Some(host(&b).as_str()));
+        // Sibling subdomains share the hashed parent domain
+        let parent = |h: String| h.split_once('.').unwrap().1.to_string();
+        assert_eq!(parent(host(&b)), parent(host(&c)));
+        assert!(b.ends_with('/'));
+        assert_eq!(a.matches('?').count(), 1);
+        assert_eq!(anon.url("https://docs.example.com/guide/"), b);
+    }
+
+    #[test]
+    fn test_salt_changes_tokens() {
+        let url = "https://example.com/a";
+        assert_eq!(
+            Anonymizer::new(Some("one")).url(url),
+            Anonymizer::new(Some("one")).url(url)
+        );
+        assert_ne!(
+            Anonymizer::new(Some("one")).url(url),
+            Anonymizer::new(Some("two")).url(url)
+        );
+        assert!(
+            Anonymizer::new(None)
+                .url("not a url")
+                .starts_with("opaque:")
+        );
+    }
+
+    #[test]
+    fn test_bookmark_and_folder() {
+        let anon = Anonymizer::new(Some("salt"));
+        let bookmark = Bookmark {
+            id: "42".to_string(),
+            title: "Secret".to_string(),
+            url: Some("https://example.com/".to_string()),
+            folder: Some("Work/Secret".to_string()),
+            date_added: None,
+            tags: Some(vec!["Secret".to_string()]),
+            ..Default::default()
+        };
+        let out = anon.bookmark(&bookmark);
+        assert_eq!(out.id, "42");
+        assert_ne!(out.title, "Secret");
+        let folder = out.folder.unwrap();
+        let (work, secret) = folder.split_once('/').unwrap();
+        assert_eq!(secret, out.title);
+        assert_ne!(work, secret);
+        assert_eq!(out.tags, Some(vec![out.title.clone()]));
+        assert_eq!(anon.text(""), "");
+    }
+}
@@ -0,0 +1,233 @@
+//! BibTeX export of bookmarked papers.
+//!
+//! Only bookmarks that point at a paper are written: those with `paper`
+//! metadata from `enrich --papers`, and any other arXiv, DOI or IEEE URL as a
+//! bare `@misc` entry with its bookmark title. Journal papers become
+//! `@article`, the rest `@misc` with their arXiv eprint. Citation keys are
+//! `<family name><year><first title word>`, suffixed `b`, `c`, ... on clashes.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use super::{BrowserData, flatten_bookmarks};
+use crate::integrations::papers::{PaperId, PaperMetadata};
+
+/// Render the papers among the exported bookmarks as a `.bib` file
+pub fn to_bibtex(data: &[BrowserData]) -> String {
+    let mut out = String::new();
+    let mut keys = HashSet::new();
+    let mut seen = HashSet::new();
+    for browser_data in data {
+        for bookmark in flatten_bookmarks(browser_data.bookmarks.as_deref().unwrap_or_default()) {
+            let Some(url) = bookmark.url.as_deref() else {
+                continue;
+            };
+            let id = PaperId::from_url(url);
+            let paper = match (&bookmark.paper, id) {
+                (Some(paper), _) => paper.clone(),
+                (None, Some(id)) => PaperMetadata {
+                    title: bookmark.title.clone(),
+                    authors: Vec::new(),
+                    year: None,
+                    venue: None,
+                    doi: match &id {
+                        PaperId::Doi(doi) => Some(doi.clone()),
+                        _ => None,
+                    },
+                    arxiv: match id {
+                        PaperId::Arxiv(arxiv) => Some(arxiv),
+                        _ => None,
+                    },
+                },
+                (None, None) => continue,
+            };
+            // The same paper bookmarked twice (e.g. abstract and PDF) is cited once
+            let identity = paper
+                .doi
+                .clone()
+                .or_else(|| paper.arxiv.clone())
+                .unwrap_or_else(|| url.to_string());
+            if !seen.insert(identity.to_lowercase()) {
+                continue;
+            }
+
+            let key = unique_key(citation_key(&paper), &mut keys);
+            write_entry(&mut out, &key, &paper, url);
+        }
+    }
+    out
+}
+
+fn write_entry(out: &mut String, key: &str, paper: &PaperMetadata, url: &str) {
+    let kind = if paper.venue.is_some() && paper.arxiv.is_none() {
+        "article"
+    } else {
+        "misc"
+    };
+    let _ = writeln!(out, "@{}{{{},", kind, key);
+    let mut field = |name: &str, value: String| {
+        let _ = writeln!(out, "  {} = {{{}}},", name, value);
+    };
+    field("title", format!("{{{}}}", escape(&paper.title)));
+    if !paper.authors.is_empty() {
+        let authors: Vec<String> = paper.authors.iter().map(|a| bibtex_name(a)).collect();
+        field("author", escape(&authors.join(" and ")));
+    }
+    if let Some(year) = paper.year {
+        field("year", year.to_string());
+    }
+    if let Some(venue) = &paper.venue {
+        field(
+            if kind == "article" {
+                "journal"
+            } else {
+                "howpublished"
+            },
+            escape(venue),
+        );
+    }
+    if let Some(doi) = &paper.doi {
+        field("doi", doi.clone());
+    }
+    if let Some(arxiv) = &paper.arxiv {
+        field("eprint", arxiv.clone());
+        field("archivePrefix", "arXiv".to_string());
+    }
+    field("url", url.to_string());
+    out.push_str("}\n\n");
+}
+
+/// `Given Family` as `Family, Given`, so BibTeX splits it correctly
+fn bibtex_name(name: &str) -> String {
+    match name.rsplit_once(' ') {
+        Some((given, family)) if !name.contains(',') => format!("{}, {}", family, given),
+        _ => name.to_string(),
+    }
+}
+
+fn citation_key(paper: &PaperMetadata) -> String {
+    let family = paper
+        .authors
+        .first()
+        .map(|a| a.rsplit(' ').next().unwrap_or(a))
+        .unwrap_or("paper");
+    let word = paper
+        .title
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .find(|w| {
+            w.len() > 3
+                && !["with", "from", "into", "over", "their"].contains(&w.to_lowercase().as_str())
+        })
+        .unwrap_or("");
+    let year = paper.year.map(|y| y.to_string()).unwrap_or_default();
+    format!("{}{}{}", family, year, word)
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn unique_key(key: String, keys: &mut HashSet<String>) -> String {
+    let key = if key.is_empty() {
+        "paper".to_string()
+    } else {
+        key
+    };
+    let unique = std::iter::once(key.clone())
+        .chain(('b'..='z').map(|suffix| format!("{}{}", key, suffix)))
+        .chain((2..).map(|n| format!("{}_{}", key, n)))
+        .find(|candidate| !keys.contains(candidate))
+        .unwrap();
+    keys.insert(unique.clone());
+    unique
+}
+
+/// Escape LaTeX's special characters in free text
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '{' | '}' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::Bookmark;
+    use chrono::Utc;
+
+    fn bookmark(id: &str, title: &str, url: &str, paper: Option<PaperMetadata>) -> Bookmark {
+        Bookmark {
+            id: id.to_string(),
+            title: title.to_string(),
+            url: Some(url.to_string()),
+            folder: Some("Papers".to_string()),
+            date_added: None,
+            paper,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bibtex_entries() {
+        let attention = PaperMetadata {
+            title: "Attention Is All You Need".to_string(),
+            authors: vec!["Ashish Vaswani".to_string(), "Noam Shazeer".to_string()],
+            year: Some(2017),
+            venue: None,
+            doi: None,
+            arxiv: Some("1706.03762".to_string()),
+        };
+        let data = BrowserData {
+            browser: "firefox".to_string(),
+            profile: "default".to_string(),
+            export_date: Utc::now(),
+            bookmarks: Some(vec![
+                bookmark(
+                    "1",
+                    "Transformer",
+                    "https://arxiv.org/abs/1706.03762",
+                    Some(attention.clone()),
+                ),
+                bookmark(
+                    "2",
+                    "Transformer PDF",
+                    "https://arxiv.org/pdf/1706.03762",
+                    Some(attention),
+                ),
+                bookmark(
+                    "3",
+                    "R&D in Nature",
+                    "https://doi.org/10.1038/nature14539",
+                    None,
+                ),
+                bookmark("4", "Rust", "https://www.rust-lang.org/", None),
+            ]),
+            history: None,
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        };
+
+        let bib = to_bibtex(&[data]);
+        assert_eq!(bib.matches('@').count(), 2, "{}", bib);
+        assert!(bib.contains("@misc{vaswani2017attention,"));
+        assert!(bib.contains("  author = {Vaswani, Ashish and Shazeer, Noam},"));
+        assert!(bib.contains("  title = {{Attention Is All You Need}},"));
+        assert!(bib.contains("  eprint = {1706.03762},"));
+        assert!(bib.contains("  title = {{R\\&D in Nature}},"));
+        assert!(bib.contains("  doi = {10.1038/nature14539},"));
+        assert!(!bib.contains("rust-lang"));
+    }
+}
@@ -0,0 +1,301 @@
+//! Content checksums for export files.
+//!
+//! YAML exports end with a `# sha256: <hex>` comment and JSON Lines exports
+//! with a `{"type":"checksum","sha256":"<hex>"}` record, each covering every
+//! byte before it. Both forms stay readable by ordinary parsers; JSON has no
+//! place for a trailer and is verified by structure only, and CSV, org and
+//! OPML files are meant for other tools and get neither.
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use super::{Bookmark, BrowserData, ExportFormat, RowCounts, flatten_bookmarks};
+
+const YAML_TRAILER: &str = "# sha256: ";
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Append the format's checksum trailer, if it has one
+pub fn with_checksum(mut content: String, format: ExportFormat) -> String {
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    let digest = sha256_hex(content.as_bytes());
+    match format {
+        ExportFormat::Yaml => content.push_str(&format!("{}{}\n", YAML_TRAILER, digest)),
+        ExportFormat::Jsonl => content.push_str(&format!(
+            "{{\"type\":\"checksum\",\"sha256\":\"{}\"}}\n",
+            digest
+        )),
+        ExportFormat::Json
+        | ExportFormat::Csv
+        | ExportFormat::Org
+        | ExportFormat::Opml
+        | ExportFormat::Sqlite
+        | ExportFormat::Bibtex => {}
+    }
+    content
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumStatus {
+    Valid,
+    /// The file has no trailer (older export, or a JSON/CSV file)
+    Missing,
+}
+
+/// What `verify_export` found in a structurally valid file
+#[derive(Debug)]
+pub struct Verification {
+    pub format: ExportFormat,
+    pub profiles: usize,
+    pub counts: RowCounts,
+    pub checksum: ChecksumStatus,
+}
+
+/// Check an export's checksum trailer and structure.
+///
+/// Fails if the checksum does not match, the content does not parse as
+/// export data, or a profile's row counts disagree with its provenance, which
+/// is how truncated or half-synced files show up.
+pub fn verify_export(content: &str) -> Result<Verification> {
+    let (body, checksum, format_hint) = split_trailer(content)?;
+    let checksum = match checksum {
+        Some(expected) => {
+            let actual = sha256_hex(body.as_bytes());
+            if actual != expected {
+                return Err(anyhow!(
+                    "Checksum mismatch: file says {}, content hashes to {}",
+                    expected,
+                    actual
+                ));
+            }
+            ChecksumStatus::Valid
+        }
+        None => ChecksumStatus::Missing,
+    };
+
+    let trimmed = body.trim_start();
+    let (format, data) = match format_hint {
+        Some(ExportFormat::Jsonl) => (ExportFormat::Jsonl, parse_json_lines(body)?),
+        _ if trimmed.starts_with('[') => (ExportFormat::Json, serde_json::from_str(body)?),
+        _ if trimmed.starts_with('{') => (ExportFormat::Jsonl, parse_json_lines(body)?),
+        _ => (
+            ExportFormat::Yaml,
+            serde_yaml::from_str::<Vec<BrowserData>>(body)
+                .map_err(|e| anyhow!("Not a valid export: {}", e))?,
+        ),
+    };
+
+    let mut total = RowCounts::default();
+    for (index, browser_data) in data.iter().enumerate() {
+        let counts = RowCounts {
+            bookmarks: browser_data
+                .bookmarks
+                .as_deref()
+                .map(|b| flatten_bookmarks(b).len())
+                .unwrap_or(0),
+            history: browser_data
+                .history
+                .as_ref()
+                .map(|h| h.urls.len())
+                .unwrap_or(0),
+            passwords: browser_data.passwords.as_ref().map(Vec::len).unwrap_or(0),
+        };
+        if let Some(provenance) = &browser_data.provenance
+            && provenance.counts != counts
+        {
+            return Err(anyhow!(
+                "Profile {} ({} {}) is incomplete: provenance records {:?}, file contains {:?}",
+                index + 1,
+                browser_data.browser,
+                browser_data.profile,
+                provenance.counts,
+                counts
+            ));
+        }
+        total.bookmarks += counts.bookmarks;
+        total.history += counts.history;
+        total.passwords += counts.passwords;
+    }
+
+    Ok(Verification {
+        format,
+        profiles: data.len(),
+        counts: total,
+        checksum,
+    })
+}
+
+/// Split off a checksum trailer, returning the covered body and digest
+fn split_trailer(content: &str) -> Result<(&str, Option<String>, Option<ExportFormat>)> {
+    let without_newline = content.strip_suffix('\n').unwrap_or(content);
+    let (body, last) = match without_newline.rfind('\n') {
+        Some(pos) => (&content[..pos + 1], &without_newline[pos + 1..]),
+        None => ("", without_newline),
+    };
+
+    if let Some(digest) = last.strip_prefix(YAML_TRAILER) {
+        return Ok((
+            body,
+            Some(digest.trim().to_string()),
+            Some(ExportFormat::Yaml),
+        ));
+    }
+
+    #[derive(Deserialize)]
+    struct Trailer {
+        r#type: String,
+        sha256: String,
+    }
+    if last.starts_with('{')
+        && let Ok(trailer) = serde_json::from_str::<Trailer>(last)
+        && trailer.r#type == "checksum"
+    {
+        return Ok((body, Some(trailer.sha256), Some(ExportFormat::Jsonl)));
+    }
+
+    Ok((content, None, None))
+}
+
+/// Rebuild per-profile data from JSON Lines records
+fn parse_json_lines(body: &str) -> Result<Vec<BrowserData>> {
+    let mut data: Vec<BrowserData> = Vec::new();
+    for (number, line) in body
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+    {
+        let record: serde_json::Value = serde_json::from_str(line)
+            .map_err(|e| anyhow!("Line {} is not valid JSON: {}", number + 1, e))?;
+        let field = |name: &str| record[name].as_str().unwrap_or_default().to_string();
+        let (browser, profile) = (field("browser"), field("profile"));
+
+        let starts_profile = data
+            .last()
+            .is_none_or(|d| d.browser != browser || d.profile != profile);
+        if starts_profile || record["type"] == "provenance" {
+            data.push(BrowserData {
+                browser,
+                profile,
+                export_date: chrono::Utc::now(),
+                bookmarks: None,
+                history: None,
+                passwords: None,
+                aliases: None,
+                profile_name: None,
+                profile_email: None,
+                provenance: None,
+            });
+        }
+        let current = data.last_mut().expect("pushed above");
+
+        let invalid = |e: serde_json::Error| anyhow!("Line {} is malformed: {}", number + 1, e);
+        match record["type"].as_str() {
+            Some("provenance") => {
+                current.provenance = Some(serde_json::from_value(record).map_err(invalid)?)
+            }
+            Some("bookmark") => current
+                .bookmarks
+                .get_or_insert_with(Vec::new)
+                .push(serde_json::from_value::<Bookmark>(record).map_err(invalid)?),
+            Some("history") => current
+                .history
+                .get_or_insert_with(|| super::HistoryEntry { urls: Vec::new() })
+                .urls
+                .push(serde_json::from_value(record).map_err(invalid)?),
+            Some("password") => current
+                .passwords
+                .get_or_insert_with(Vec::new)
+                .push(serde_json::from_value(record).map_err(invalid)?),
+            other => {
+                return Err(anyhow!(
+                    "Line {} has unknown record type {:?}",
+                    number + 1,
+                    other
+                ));
+            }
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::{Provenance, render_export};
+    use std::collections::BTreeMap;
+
+    fn sample() -> Vec<BrowserData> {
+        let mut data = BrowserData {
+            browser: "chrome".to_string(),
+            profile: "Default".to_string(),
+            export_date: chrono::Utc::now(),
+            bookmarks: Some(vec![Bookmark {
+                id: "1".to_string(),
+                title: "Rust".to_string(),
+                url: Some("https://www.rust-lang.org/".to_string()),
+                folder: Some("Dev".to_string()),
+                date_added: None,
+                ..Default::default()
+            }]),
+            history: None,
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        };
+        data.provenance = Some(Provenance::new("export", BTreeMap::new()).describe(&data));
+        vec![data]
+    }
+
+    #[test]
+    fn test_verify_round_trip() {
+        for format in [ExportFormat::Yaml, ExportFormat::Jsonl, ExportFormat::Json] {
+            let content = render_export(&sample(), format).unwrap();
+            let verification = verify_export(&content).unwrap();
+            assert_eq!(verification.format, format);
+            assert_eq!(verification.profiles, 1);
+            assert_eq!(verification.counts.bookmarks, 1);
+            let expected = if format == ExportFormat::Json {
+                ChecksumStatus::Missing
+            } else {
+                ChecksumStatus::Valid
+            };
+            assert_eq!(verification.checksum, expected);
+        }
+    }
+
+    #[test]
+    fn test_verify_detects_tampering() {
+        let content = render_export(&sample(), ExportFormat::Yaml).unwrap();
+        let tampered = content.replace("Rust", "Rusty");
+        let err = verify_export(&tampered).unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"));
+    }
+
+    #[test]
+    fn test_verify_detects_truncation_without_checksum() {
+        let content = render_export(&sample(), ExportFormat::Jsonl).unwrap();
+        // Drop the bookmark and the trailer, as a partial sync would
+        let truncated: String = content
+            .lines()
+            .take(1)
+            .map(|l| format!("{}\n", l))
+            .collect();
+        let err = verify_export(&truncated).unwrap_err();
+        assert!(err.to_string().contains("incomplete"));
+    }
+
+    #[test]
+    fn test_verify_rejects_non_export() {
+        assert!(verify_export("just: a mapping\n").is_err());
+    }
+}
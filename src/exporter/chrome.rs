@@ -1,8 +1,12 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 use std::path::Path;
 
-use super::{Bookmark, HistoryEntry, UrlEntry};
+use super::holdout::{self, FolderHoldout};
+use super::os_crypt::{self, MasterKey};
+use super::snapshot::Snapshot;
+use super::{Bookmark, HISTORY_LIMIT, HistoryEntry, Password, UrlEntry};
 
 pub fn extract_bookmarks(profile_path: &Path) -> Result<Option<Vec<Bookmark>>> {
     let bookmarks_path = profile_path.join("Bookmarks");
@@ -13,25 +17,34 @@ pub fn extract_bookmarks(profile_path: &Path) -> Result<Option<Vec<Bookmark>>> {
     let content = std::fs::read_to_string(bookmarks_path)?;
     let json: serde_json::Value = serde_json::from_str(&content)?;
 
-    Ok(Some(parse_chrome_bookmarks(&json)?))
+    Ok(Some(parse_chrome_bookmarks(&json, holdout::get()?)?))
 }
 
-pub fn extract_history(profile_path: &Path) -> Result<Option<HistoryEntry>> {
+/// Extract history, most recent first; with `since`, only URLs last visited after it
+pub fn extract_history(
+    profile_path: &Path,
+    since: Option<DateTime<Utc>>,
+) -> Result<Option<HistoryEntry>> {
     let history_path = profile_path.join("History");
     if !history_path.exists() {
         return Ok(None);
     }
 
-    let conn = rusqlite::Connection::open(&history_path)?;
+    let snapshot = Snapshot::create(&history_path)?;
+    let conn = snapshot.open()?;
 
-    let mut stmt = conn.prepare(
+    let mut stmt = conn.prepare(&format!(
         "SELECT url, title, visit_count, last_visit_time 
          FROM urls 
+         WHERE ?1 IS NULL OR last_visit_time > ?1
          ORDER BY last_visit_time DESC 
-         LIMIT 10000",
-    )?;
+         LIMIT {}",
+        HISTORY_LIMIT
+    ))?;
 
-    let rows = stmt.query_map([], |row| {
+    // Chrome timestamps count microseconds from 1601-01-01
+    let since = since.map(|t| t.timestamp_micros() + 11644473600000000);
+    let rows = stmt.query_map([since], |row| {
         Ok(UrlEntry {
             url: row.get(0)?,
             title: row.get(1)?,
@@ -51,7 +64,210 @@ pub fn extract_history(profile_path: &Path) -> Result<Option<HistoryEntry>> {
     Ok(Some(HistoryEntry { urls }))
 }
 
-fn parse_chrome_bookmarks(json: &serde_json::Value) -> Result<Vec<Bookmark>> {
+/// Extract saved logins from `Login Data`, decrypting each password with `key`.
+///
+/// Entries that fail to decrypt are skipped with a warning rather than aborting.
+pub fn extract_passwords(profile_path: &Path, key: &MasterKey) -> Result<Option<Vec<Password>>> {
+    let login_data_path = profile_path.join("Login Data");
+    if !login_data_path.exists() {
+        return Ok(None);
+    }
+
+    let snapshot = Snapshot::create(&login_data_path)?;
+    let conn = snapshot.open()?;
+    let mut stmt = conn.prepare(
+        "SELECT origin_url, username_value, password_value
+         FROM logins
+         WHERE blacklisted_by_user = 0",
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Vec<u8>>(2)?,
+        ))
+    })?;
+
+    let mut passwords = Vec::new();
+    for row in rows {
+        let (url, username, encrypted) = row?;
+        match os_crypt::decrypt(&encrypted, key) {
+            Ok(password) => passwords.push(Password {
+                url,
+                username,
+                password,
+                form_data: None,
+            }),
+            Err(e) => log::warn!("Skipping saved login for {}: {}", url, e),
+        }
+    }
+
+    Ok(Some(passwords))
+}
+
+/// Folder name assigned to reading-list entries
+pub const READING_LIST_FOLDER: &str = "Reading List";
+
+/// Extract Chrome reading-list entries from the profile's sync LevelDB.
+///
+/// Entries are stored under `reading_list-dt-<id>` keys as serialized
+/// `ReadingListSpecifics` protobufs. Only the uncompressed write-ahead logs
+/// (`*.log`) are scanned; entries already compacted into `*.ldb` tables are skipped.
+pub fn extract_reading_list(profile_path: &Path) -> Result<Option<Vec<Bookmark>>> {
+    let leveldb_dir = profile_path.join("Sync Data").join("LevelDB");
+    if !leveldb_dir.is_dir() {
+        return Ok(None);
+    }
+
+    if holdout::get()?.holds(Some(READING_LIST_FOLDER)) {
+        return Ok(Some(Vec::new()));
+    }
+
+    let mut entries: HashMap<String, Bookmark> = HashMap::new();
+    let mut logs: Vec<_> = std::fs::read_dir(&leveldb_dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("log"))
+        .collect();
+    logs.sort();
+
+    for log in logs {
+        let data = std::fs::read(&log)?;
+        // Later records overwrite earlier ones, matching LevelDB semantics
+        for bookmark in parse_reading_list_records(&data) {
+            entries.insert(bookmark.id.clone(), bookmark);
+        }
+    }
+
+    let mut bookmarks: Vec<Bookmark> = entries.into_values().collect();
+    bookmarks.sort_by_key(|b| std::cmp::Reverse(b.date_added));
+    Ok(Some(bookmarks))
+}
+
+const READING_LIST_KEY_PREFIX: &[u8] = b"reading_list-dt-";
+
+fn parse_reading_list_records(data: &[u8]) -> Vec<Bookmark> {
+    let mut bookmarks = Vec::new();
+    let mut pos = 0;
+
+    while let Some(offset) = find_bytes(&data[pos..], READING_LIST_KEY_PREFIX) {
+        let key_start = pos + offset;
+        pos = key_start + READING_LIST_KEY_PREFIX.len();
+
+        // WriteBatch put record: tag(1) varint(key_len) key varint(value_len) value
+        let Some(key_len) = preceding_key_len(data, key_start) else {
+            continue;
+        };
+        let key_end = key_start + key_len;
+        let Some((value_len, value_start)) = data.get(key_end..).and_then(read_varint) else {
+            continue;
+        };
+        let value_start = key_end + value_start;
+        // A corrupt or truncated log can claim any length
+        let Some(value_end) = usize::try_from(value_len)
+            .ok()
+            .and_then(|len| value_start.checked_add(len))
+        else {
+            continue;
+        };
+        let Some(value) = data.get(value_start..value_end) else {
+            continue;
+        };
+
+        if let Some(bookmark) = parse_reading_list_specifics(value) {
+            bookmarks.push(bookmark);
+            pos = value_end;
+        }
+    }
+
+    bookmarks
+}
+
+/// Recover the key length from the varint that precedes the key in a put record
+fn preceding_key_len(data: &[u8], key_start: usize) -> Option<usize> {
+    for width in 1..=2 {
+        let tag_pos = key_start.checked_sub(width + 1)?;
+        if data[tag_pos] != 1 {
+            continue;
+        }
+        if let Some((len, used)) = read_varint(&data[tag_pos + 1..key_start])
+            && used == width
+            && len as usize > READING_LIST_KEY_PREFIX.len()
+        {
+            return Some(len as usize);
+        }
+    }
+    None
+}
+
+fn parse_reading_list_specifics(value: &[u8]) -> Option<Bookmark> {
+    let mut entry_id = None;
+    let mut title = None;
+    let mut url = None;
+    let mut creation_time_us = None;
+    let mut pos = 0;
+
+    while pos < value.len() {
+        let (tag, used) = read_varint(&value[pos..])?;
+        pos += used;
+        match tag & 0x7 {
+            0 => {
+                let (v, used) = read_varint(&value[pos..])?;
+                pos += used;
+                if tag >> 3 == 4 {
+                    creation_time_us = Some(v as i64);
+                }
+            }
+            2 => {
+                let (len, used) = read_varint(&value[pos..])?;
+                pos += used;
+                let end = pos.checked_add(usize::try_from(len).ok()?)?;
+                let bytes = value.get(pos..end)?;
+                pos = end;
+                let text = String::from_utf8(bytes.to_vec()).ok();
+                match tag >> 3 {
+                    1 => entry_id = text,
+                    2 => title = text,
+                    3 => url = text,
+                    _ => {}
+                }
+            }
+            1 => pos += 8,
+            5 => pos += 4,
+            _ => return None,
+        }
+    }
+
+    let url = url?;
+    Some(Bookmark {
+        id: entry_id.unwrap_or_else(|| url.clone()),
+        title: title.unwrap_or_default(),
+        url: Some(url),
+        folder: Some(READING_LIST_FOLDER.to_string()),
+        date_added: creation_time_us.and_then(|us| DateTime::from_timestamp(us / 1_000_000, 0)),
+        ..Default::default()
+    })
+}
+
+fn read_varint(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &byte) in data.iter().enumerate().take(10) {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn parse_chrome_bookmarks(
+    json: &serde_json::Value,
+    holdout: &FolderHoldout,
+) -> Result<Vec<Bookmark>> {
     let mut bookmarks = Vec::new();
 
     if let Some(roots) = json.get("roots").and_then(|r| r.as_object()) {
@@ -59,6 +275,7 @@ fn parse_chrome_bookmarks(json: &serde_json::Value) -> Result<Vec<Bookmark>> {
             bookmarks.extend(parse_bookmark_folder(
                 folder_data,
                 Some(folder_name.clone()),
+                holdout,
             )?);
         }
     }
@@ -69,8 +286,12 @@ fn parse_chrome_bookmarks(json: &serde_json::Value) -> Result<Vec<Bookmark>> {
 fn parse_bookmark_folder(
     folder: &serde_json::Value,
     folder_name: Option<String>,
+    holdout: &FolderHoldout,
 ) -> Result<Vec<Bookmark>> {
     let mut bookmarks = Vec::new();
+    if holdout.holds(folder_name.as_deref()) {
+        return Ok(bookmarks);
+    }
 
     if let Some(children) = folder.get("children").and_then(|c| c.as_array()) {
         for child in children {
@@ -100,7 +321,7 @@ fn parse_bookmark_folder(
                                 DateTime::from_timestamp((ts - 11644473600000000) / 1000000, 0)
                                     .unwrap_or_else(Utc::now)
                             }),
-                        children: None,
+                        ..Default::default()
                     };
                     bookmarks.push(bookmark);
                 } else if obj.get("type").and_then(|t| t.as_str()) == Some("folder") {
@@ -113,7 +334,11 @@ fn parse_bookmark_folder(
                         Some(ref parent) => format!("{}/{}", parent, subfolder_name),
                         None => subfolder_name,
                     };
-                    bookmarks.extend(parse_bookmark_folder(child, Some(full_folder_name))?);
+                    bookmarks.extend(parse_bookmark_folder(
+                        child,
+                        Some(full_folder_name),
+                        holdout,
+                    )?);
                 }
             }
         }
@@ -121,3 +346,164 @@ fn parse_bookmark_folder(
 
     Ok(bookmarks)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::os_crypt::tests::encrypt_v10;
+    use tempfile::tempdir;
+
+    fn varint(mut v: u64, out: &mut Vec<u8>) {
+        while v >= 0x80 {
+            out.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        out.push(v as u8);
+    }
+
+    fn string_field(field: u64, s: &str, out: &mut Vec<u8>) {
+        varint(field << 3 | 2, out);
+        varint(s.len() as u64, out);
+        out.extend_from_slice(s.as_bytes());
+    }
+
+    fn put_record(url: &str, title: &str, created_us: u64) -> Vec<u8> {
+        let mut value = Vec::new();
+        string_field(1, url, &mut value);
+        string_field(2, title, &mut value);
+        string_field(3, url, &mut value);
+        varint(4 << 3, &mut value);
+        varint(created_us, &mut value);
+
+        let key = format!("reading_list-dt-{}", url);
+        let mut record = vec![1];
+        varint(key.len() as u64, &mut record);
+        record.extend_from_slice(key.as_bytes());
+        varint(value.len() as u64, &mut record);
+        record.extend_from_slice(&value);
+        record
+    }
+
+    #[test]
+    fn test_extract_reading_list() {
+        let dir = tempdir().unwrap();
+        let leveldb = dir.path().join("Sync Data").join("LevelDB");
+        std::fs::create_dir_all(&leveldb).unwrap();
+
+        let long_url = format!("https://example.com/{}", "a".repeat(150));
+        let mut log = b"\x00garbage".to_vec();
+        log.extend(put_record(
+            "https://rust-lang.org/",
+            "Rust",
+            1_700_000_000_000_000,
+        ));
+        log.extend(put_record(&long_url, "Long", 1_600_000_000_000_000));
+        log.extend(put_record(
+            "https://rust-lang.org/",
+            "Rust (updated)",
+            1_700_000_000_000_000,
+        ));
+        std::fs::write(leveldb.join("000003.log"), log).unwrap();
+
+        let entries = extract_reading_list(dir.path()).unwrap().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].title, "Rust (updated)");
+        assert_eq!(entries[0].folder.as_deref(), Some(READING_LIST_FOLDER));
+        assert!(entries[0].date_added.is_some());
+        assert_eq!(entries[1].url.as_deref(), Some(long_url.as_str()));
+    }
+
+    #[test]
+    fn test_extract_passwords() {
+        let dir = tempdir().unwrap();
+        let v10 = os_crypt::derive_cbc_key(b"peanuts", 1);
+        let conn = rusqlite::Connection::open(dir.path().join("Login Data")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE logins (origin_url TEXT, username_value TEXT,
+             password_value BLOB, blacklisted_by_user INTEGER);",
+        )
+        .unwrap();
+        for (url, user, blob, blacklisted) in [
+            (
+                "https://a.example",
+                "alice",
+                encrypt_v10(&v10, "hunter2"),
+                0,
+            ),
+            ("https://b.example", "", Vec::new(), 1),
+            ("https://c.example", "carol", b"v20garbage".to_vec(), 0),
+        ] {
+            conn.execute(
+                "INSERT INTO logins VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![url, user, blob, blacklisted],
+            )
+            .unwrap();
+        }
+        drop(conn);
+
+        let key = MasterKey::Cbc { v10, v11: None };
+        let passwords = extract_passwords(dir.path(), &key).unwrap().unwrap();
+        assert_eq!(passwords.len(), 1);
+        assert_eq!(passwords[0].username, "alice");
+        assert_eq!(passwords[0].password, "hunter2");
+    }
+
+    #[test]
+    fn test_reading_list_huge_lengths() {
+        // A value length of u64::MAX
+        let key = b"reading_list-dt-https://bad.example/";
+        let mut log = vec![1];
+        varint(key.len() as u64, &mut log);
+        log.extend_from_slice(key);
+        varint(u64::MAX, &mut log);
+        // A string field claiming u64::MAX bytes inside a well-framed value
+        let mut value = Vec::new();
+        varint(3 << 3 | 2, &mut value);
+        varint(u64::MAX, &mut value);
+        log.push(1);
+        varint(key.len() as u64, &mut log);
+        log.extend_from_slice(key);
+        varint(value.len() as u64, &mut log);
+        log.extend_from_slice(&value);
+        log.extend(put_record("https://rust-lang.org/", "Rust", 0));
+
+        let entries = parse_reading_list_records(&log);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].title, "Rust");
+    }
+
+    #[test]
+    fn test_reading_list_missing() {
+        let dir = tempdir().unwrap();
+        assert!(extract_reading_list(dir.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_held_folders_are_skipped() {
+        let json = serde_json::json!({
+            "roots": {
+                "bookmark_bar": {
+                    "children": [
+                        {"type": "url", "id": "1", "name": "News", "url": "https://news.example"},
+                        {"type": "folder", "name": "Banking", "children": [
+                            {"type": "url", "id": "2", "name": "Bank", "url": "https://bank.example"},
+                            {"type": "folder", "name": "Cards", "children": [
+                                {"type": "url", "id": "3", "name": "Card", "url": "https://card.example"}
+                            ]}
+                        ]},
+                        {"type": "folder", "name": "Online Banking", "children": [
+                            {"type": "url", "id": "4", "name": "Review", "url": "https://review.example"}
+                        ]}
+                    ]
+                }
+            }
+        });
+        let holdout = FolderHoldout::new(&["banking".to_string()]);
+
+        let bookmarks = parse_chrome_bookmarks(&json, &holdout).unwrap();
+        let ids: Vec<_> = bookmarks.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(ids, ["1", "4"]);
+        let all = parse_chrome_bookmarks(&json, &FolderHoldout::default()).unwrap();
+        assert_eq!(all.len(), 4);
+    }
+}
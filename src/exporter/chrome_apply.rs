@@ -0,0 +1,531 @@
+//! Write bookmarks back into a Chrome or Edge profile's `Bookmarks` file.
+//!
+//! The file is JSON with one tree per root (`bookmark_bar`, `other`,
+//! `synced`). Bookmarks are matched to existing entries by id, then by URL,
+//! and renamed or moved in place; new ones get fresh ids and guids. The
+//! stored checksum is dropped rather than recomputed: Chrome accepts a file
+//! without one and writes a fresh one on its next save. The browser must be
+//! closed, and a copy of the file is always taken before it is replaced.
+
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use serde_json::{Map, Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::holdout;
+use super::{ApplyOptions, ApplySummary, Bookmark, FOLDER_PATH_SEPARATOR, flatten_bookmarks};
+
+/// Folder names used for the roots by other browsers and in exports, mapped
+/// to Chrome's root keys. Firefox's menu has no Chrome counterpart and lands
+/// in Other bookmarks.
+pub(super) const ROOT_ALIASES: &[(&str, &str)] = &[
+    ("bookmark_bar", "bookmark_bar"),
+    ("bookmarks bar", "bookmark_bar"),
+    ("favorites bar", "bookmark_bar"),
+    ("toolbar", "bookmark_bar"),
+    ("bookmarks toolbar", "bookmark_bar"),
+    ("other", "other"),
+    ("other bookmarks", "other"),
+    ("other favorites", "other"),
+    ("unfiled", "other"),
+    ("menu", "other"),
+    ("bookmarks menu", "other"),
+    ("synced", "synced"),
+    ("mobile", "synced"),
+    ("mobile bookmarks", "synced"),
+];
+
+const CHROME_RUNNING: &str =
+    "The browser appears to be running on this profile; close it and try again";
+
+/// Chrome timestamps count microseconds from 1601-01-01
+const WINDOWS_EPOCH_OFFSET_MICROS: i64 = 11_644_473_600_000_000;
+
+/// Apply `bookmarks` to the Chrome or Edge profile at `profile_path`
+pub fn apply_to_chrome(
+    profile_path: &Path,
+    bookmarks: &[Bookmark],
+    options: &ApplyOptions,
+) -> Result<ApplySummary> {
+    let bookmarks_path = profile_path.join("Bookmarks");
+    if !bookmarks_path.exists() {
+        return Err(anyhow!("No Bookmarks file in {}", profile_path.display()));
+    }
+    // Chrome rewrites the file from memory on exit, so edits made while it
+    // runs would be lost. It keeps a `SingletonLock` symlink (Unix) or a
+    // `lockfile` (Windows) in the user data directory while running.
+    if !options.dry_run
+        && let Some(user_data) = profile_path.parent()
+        && (user_data.join("SingletonLock").symlink_metadata().is_ok()
+            || user_data.join("lockfile").exists())
+    {
+        return Err(anyhow!(CHROME_RUNNING));
+    }
+
+    let content = fs::read_to_string(&bookmarks_path)?;
+    let mut file: Value = serde_json::from_str(&content)?;
+    let mut summary = apply_bookmarks(&mut file, bookmarks, options)?;
+    if options.dry_run {
+        return Ok(summary);
+    }
+
+    let backup_dir = options
+        .backup_dir
+        .clone()
+        .unwrap_or_else(|| crate::paths::get().backups.clone());
+    summary.backup = Some(backup_bookmarks(&content, profile_path, &backup_dir)?);
+
+    // Write beside the original and rename, so a crash never leaves half a file
+    let temp_path = profile_path.join("Bookmarks.bookmark-manager.tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(&file)?)?;
+    fs::rename(&temp_path, &bookmarks_path)?;
+    Ok(summary)
+}
+
+/// Copy the file to `chrome-<profile>-Bookmarks-<timestamp>.json` in `backup_dir`
+fn backup_bookmarks(content: &str, profile_path: &Path, backup_dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(backup_dir)?;
+    let backup = backup_dir.join(format!(
+        "chrome-{}-Bookmarks-{}.json",
+        profile_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy(),
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::write(&backup, content)?;
+    Ok(backup)
+}
+
+/// Root key plus folder names below it
+type FolderKey = (String, Vec<String>);
+
+/// An existing URL entry
+struct Existing {
+    id: String,
+    url: String,
+    title: String,
+    folder: FolderKey,
+}
+
+/// Where a bookmark ends up once the tree has been edited
+enum Placement {
+    /// An existing entry taken out of its old folder
+    Move(String, FolderKey),
+    Insert(Value, FolderKey),
+}
+
+fn apply_bookmarks(
+    file: &mut Value,
+    bookmarks: &[Bookmark],
+    options: &ApplyOptions,
+) -> Result<ApplySummary> {
+    let roots = file
+        .get_mut("roots")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| anyhow!("Bookmarks file has no roots; is this a Chrome profile?"))?;
+
+    let mut existing = Vec::new();
+    let mut max_id = 0;
+    for (key, root) in roots.iter() {
+        collect_existing(root, &(key.clone(), Vec::new()), &mut existing, &mut max_id);
+    }
+    let mut by_id: HashMap<&str, usize> = HashMap::new();
+    let mut by_url: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, entry) in existing.iter().enumerate() {
+        by_id.insert(entry.id.as_str(), index);
+        by_url.entry(entry.url.as_str()).or_default().push(index);
+    }
+    let mut claimed = vec![false; existing.len()];
+
+    let mut ids = IdSource::new(max_id);
+    let mut summary = ApplySummary::default();
+    let mut renames: HashMap<String, String> = HashMap::new();
+    let mut placements = Vec::new();
+    for bookmark in flatten_bookmarks(bookmarks) {
+        let Some(url) = bookmark.url.as_deref().filter(|u| !u.is_empty()) else {
+            summary.skipped += 1;
+            continue;
+        };
+        let target = resolve_folder(roots, bookmark.folder.as_deref());
+
+        // Prefer the entry this bookmark was exported from, then any entry for the URL
+        let matched = by_id
+            .get(bookmark.id.as_str())
+            .copied()
+            .filter(|&index| !claimed[index] && existing[index].url == url)
+            .or_else(|| {
+                by_url
+                    .get(url)
+                    .and_then(|entries| entries.iter().copied().find(|&index| !claimed[index]))
+            });
+
+        match matched {
+            Some(index) => {
+                claimed[index] = true;
+                let entry = &existing[index];
+                if entry.folder == target && entry.title == bookmark.title {
+                    summary.unchanged += 1;
+                    continue;
+                }
+                if entry.title != bookmark.title {
+                    renames.insert(entry.id.clone(), bookmark.title.clone());
+                }
+                if entry.folder != target {
+                    placements.push(Placement::Move(entry.id.clone(), target));
+                }
+                summary.updated += 1;
+            }
+            None => {
+                let added = bookmark
+                    .date_added
+                    .unwrap_or_else(Utc::now)
+                    .timestamp_micros()
+                    + WINDOWS_EPOCH_OFFSET_MICROS;
+                let id = ids.next_id();
+                let node = json!({
+                    "date_added": added.to_string(),
+                    "guid": ids.guid(url),
+                    "id": id,
+                    "name": bookmark.title,
+                    "type": "url",
+                    "url": url,
+                });
+                placements.push(Placement::Insert(node, target));
+                summary.inserted += 1;
+            }
+        }
+    }
+
+    let mut removed: HashSet<String> = HashSet::new();
+    if options.prune {
+        let holdout = holdout::get()?;
+        for (entry, _) in existing.iter().zip(&claimed).filter(|(_, c)| !**c) {
+            let (root, path) = &entry.folder;
+            let folder = std::iter::once(root.as_str())
+                .chain(path.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(FOLDER_PATH_SEPARATOR);
+            // Held-back folders are never exported, so their absence means nothing
+            if holdout.holds(Some(&folder)) {
+                continue;
+            }
+            removed.insert(entry.id.clone());
+            summary.removed += 1;
+            summary.pruned.push(Bookmark {
+                id: entry.id.clone(),
+                title: entry.title.clone(),
+                url: Some(entry.url.clone()),
+                folder: Some(folder),
+                date_added: None,
+                ..Default::default()
+            });
+        }
+    }
+    let moving: HashSet<String> = placements
+        .iter()
+        .filter_map(|p| match p {
+            Placement::Move(id, _) => Some(id.clone()),
+            Placement::Insert(..) => None,
+        })
+        .collect();
+
+    let mut detached: HashMap<String, Value> = HashMap::new();
+    for root in roots.values_mut() {
+        edit_tree(root, &renames, &removed, &moving, &mut detached);
+    }
+    for placement in placements {
+        let (node, (root, names)) = match placement {
+            Placement::Move(id, target) => match detached.remove(&id) {
+                Some(node) => (node, target),
+                None => continue,
+            },
+            Placement::Insert(node, target) => (node, target),
+        };
+        let children = folder_children(roots, &root, &names, &mut ids, &mut summary)?;
+        children.push(node);
+    }
+
+    if let Some(object) = file.as_object_mut() {
+        object.remove("checksum");
+    }
+    Ok(summary)
+}
+
+/// Record every URL entry under `node` along with the largest id in use
+fn collect_existing(node: &Value, folder: &FolderKey, out: &mut Vec<Existing>, max_id: &mut u64) {
+    if let Some(id) = node.get("id").and_then(Value::as_str)
+        && let Ok(id) = id.parse::<u64>()
+    {
+        *max_id = (*max_id).max(id);
+    }
+    for child in node
+        .get("children")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let text = |key: &str| child.get(key).and_then(Value::as_str).unwrap_or_default();
+        match text("type") {
+            "url" => {
+                if let Ok(id) = text("id").parse::<u64>() {
+                    *max_id = (*max_id).max(id);
+                }
+                out.push(Existing {
+                    id: text("id").to_string(),
+                    url: text("url").to_string(),
+                    title: text("name").to_string(),
+                    folder: folder.clone(),
+                });
+            }
+            "folder" => {
+                let mut names = folder.1.clone();
+                names.push(text("name").to_string());
+                collect_existing(child, &(folder.0.clone(), names), out, max_id);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Map an exported folder path to a Chrome root and the folders below it.
+/// Paths that don't start with a known root go under Other bookmarks whole.
+fn resolve_folder(roots: &Map<String, Value>, path: Option<&str>) -> FolderKey {
+    let segments: Vec<String> = path
+        .unwrap_or_default()
+        .split(FOLDER_PATH_SEPARATOR)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    let root = segments.first().and_then(|first| {
+        let first = first.to_lowercase();
+        ROOT_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == first)
+            .map(|(_, key)| *key)
+    });
+    match root {
+        Some(key) if roots.contains_key(key) => (key.to_string(), segments[1..].to_vec()),
+        Some(_) => ("other".to_string(), segments[1..].to_vec()),
+        None => ("other".to_string(), segments),
+    }
+}
+
+/// Rename, drop and take out (for moving) URL entries throughout `node`
+fn edit_tree(
+    node: &mut Value,
+    renames: &HashMap<String, String>,
+    removed: &HashSet<String>,
+    moving: &HashSet<String>,
+    detached: &mut HashMap<String, Value>,
+) {
+    let Some(children) = node.get_mut("children").and_then(Value::as_array_mut) else {
+        return;
+    };
+    let mut kept = Vec::with_capacity(children.len());
+    for mut child in children.drain(..) {
+        let id = child
+            .get("id")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        match child.get("type").and_then(Value::as_str) {
+            Some("url") => {
+                if let Some(title) = renames.get(&id) {
+                    child["name"] = Value::String(title.clone());
+                }
+                if removed.contains(&id) {
+                    continue;
+                }
+                if moving.contains(&id) {
+                    detached.insert(id, child);
+                    continue;
+                }
+            }
+            Some("folder") => edit_tree(&mut child, renames, removed, moving, detached),
+            _ => {}
+        }
+        kept.push(child);
+    }
+    *children = kept;
+}
+
+/// The children of folder `names` under `root`, creating missing folders
+fn folder_children<'a>(
+    roots: &'a mut Map<String, Value>,
+    root: &str,
+    names: &[String],
+    ids: &mut IdSource,
+    summary: &mut ApplySummary,
+) -> Result<&'a mut Vec<Value>> {
+    let mut node = roots
+        .get_mut(root)
+        .ok_or_else(|| anyhow!("Bookmarks file has no {} root", root))?;
+    for name in names {
+        let children = children_of(node)?;
+        let position = children.iter().position(|child| {
+            child.get("type").and_then(Value::as_str) == Some("folder")
+                && child.get("name").and_then(Value::as_str) == Some(name.as_str())
+        });
+        let position = match position {
+            Some(position) => position,
+            None => {
+                let now = (Utc::now().timestamp_micros() + WINDOWS_EPOCH_OFFSET_MICROS).to_string();
+                children.push(json!({
+                    "children": [],
+                    "date_added": now,
+                    "date_modified": now,
+                    "guid": ids.guid(name),
+                    "id": ids.next_id(),
+                    "name": name,
+                    "type": "folder",
+                }));
+                summary.folders_created += 1;
+                children.len() - 1
+            }
+        };
+        node = &mut children_of(node)?[position];
+    }
+    children_of(node)
+}
+
+fn children_of(node: &mut Value) -> Result<&mut Vec<Value>> {
+    node.as_object_mut()
+        .ok_or_else(|| anyhow!("Malformed bookmark folder"))?
+        .entry("children")
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or_else(|| anyhow!("Malformed bookmark folder"))
+}
+
+/// Fresh node ids (decimal strings above every id in the file) and guids
+struct IdSource {
+    last_id: u64,
+    counter: u64,
+    now: i64,
+}
+
+impl IdSource {
+    fn new(max_id: u64) -> Self {
+        Self {
+            last_id: max_id,
+            counter: 0,
+            now: Utc::now().timestamp_micros(),
+        }
+    }
+
+    fn next_id(&mut self) -> String {
+        self.last_id += 1;
+        self.last_id.to_string()
+    }
+
+    /// A random-looking version 4 UUID, the format Chrome uses for node guids
+    fn guid(&mut self, seed: &str) -> String {
+        self.counter += 1;
+        let mut bytes: [u8; 16] = Sha256::new()
+            .chain_update(seed.as_bytes())
+            .chain_update(self.now.to_le_bytes())
+            .chain_update(self.counter.to_le_bytes())
+            .finalize()[..16]
+            .try_into()
+            .expect("SHA-256 digests are 32 bytes");
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const BOOKMARKS: &str = r#"{
+        "checksum": "0123456789abcdef",
+        "roots": {
+            "bookmark_bar": {"children": [
+                {"id": "4", "name": "Rust", "type": "url", "url": "https://www.rust-lang.org/"},
+                {"id": "5", "name": "Dev", "type": "folder", "children": [
+                    {"id": "6", "name": "GitHub", "type": "url", "url": "https://github.com/"}
+                ]}
+            ], "id": "1", "name": "Bookmarks bar", "type": "folder"},
+            "other": {"children": [
+                {"id": "7", "name": "Old", "type": "url", "url": "https://old.example/"}
+            ], "id": "2", "name": "Other bookmarks", "type": "folder"},
+            "synced": {"children": [], "id": "3", "name": "Mobile bookmarks", "type": "folder"}
+        },
+        "version": 1
+    }"#;
+
+    fn bookmark(id: &str, title: &str, url: &str, folder: &str) -> Bookmark {
+        Bookmark {
+            id: id.to_string(),
+            title: title.to_string(),
+            url: Some(url.to_string()),
+            folder: Some(folder.to_string()),
+            date_added: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_apply_moves_renames_inserts_and_prunes() {
+        let dir = tempdir().unwrap();
+        let profile = dir.path().join("Default");
+        fs::create_dir(&profile).unwrap();
+        fs::write(profile.join("Bookmarks"), BOOKMARKS).unwrap();
+
+        let bookmarks = vec![
+            bookmark("4", "Rust", "https://www.rust-lang.org/", "bookmark_bar"),
+            // Moved out of Dev and renamed; the Firefox root name is understood
+            bookmark("x", "GitHub Home", "https://github.com/", "toolbar/Code"),
+            bookmark("y", "Docs", "https://docs.rs/", "Reading"),
+        ];
+        let options = ApplyOptions {
+            prune: true,
+            backup_dir: Some(dir.path().join("backups")),
+            ..Default::default()
+        };
+        let summary = apply_to_chrome(&profile, &bookmarks, &options).unwrap();
+        assert_eq!(
+            (
+                summary.inserted,
+                summary.updated,
+                summary.unchanged,
+                summary.removed,
+                summary.folders_created
+            ),
+            (1, 1, 1, 1, 2)
+        );
+        assert!(summary.backup.unwrap().exists());
+
+        let file: Value =
+            serde_json::from_str(&fs::read_to_string(profile.join("Bookmarks")).unwrap()).unwrap();
+        assert!(file.get("checksum").is_none());
+        let bar = &file["roots"]["bookmark_bar"]["children"];
+        assert_eq!(bar[1]["children"].as_array().unwrap().len(), 0);
+        assert_eq!(bar[2]["name"], "Code");
+        assert_eq!(bar[2]["children"][0]["id"], "6");
+        assert_eq!(bar[2]["children"][0]["name"], "GitHub Home");
+        let other = &file["roots"]["other"]["children"];
+        assert_eq!(other[0]["name"], "Reading");
+        assert_eq!(other[0]["children"][0]["url"], "https://docs.rs/");
+        assert_eq!(other[0]["children"][0]["id"], "8");
+        assert_eq!(other.as_array().unwrap().len(), 1);
+
+        // Applying the same bookmarks again changes nothing
+        let again = apply_to_chrome(&profile, &bookmarks, &options).unwrap();
+        assert_eq!((again.inserted, again.updated, again.unchanged), (0, 0, 3));
+    }
+}
@@ -0,0 +1,181 @@
+//! Selecting which bookmark fields an export carries.
+//!
+//! Dropping dates, children and the like keeps dumps of huge collections
+//! small for consumers (or LLM contexts) that only need a few columns.
+//! `id` and `title` deserialize with defaults, so projected YAML/JSON exports
+//! can still be read back.
+
+use anyhow::{Result, anyhow};
+use std::str::FromStr;
+
+use super::{Bookmark, BrowserData, flatten_bookmarks};
+
+/// Bookmark fields that can be selected, in output order
+pub const BOOKMARK_FIELDS: &[&str] = &[
+    "id",
+    "title",
+    "url",
+    "folder",
+    "date_added",
+    "children",
+    "keyword",
+    "tags",
+    "description",
+    "media_type",
+    "archive_url",
+    "github",
+    "canonical_url",
+    "paper",
+    "screenshot",
+    "page",
+];
+
+/// A validated subset of [`BOOKMARK_FIELDS`], parsed from e.g. `title,url,folder`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSelection {
+    fields: Vec<&'static str>,
+}
+
+impl FromStr for FieldSelection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let requested: Vec<&str> = s
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .collect();
+        if let Some(unknown) = requested.iter().find(|f| !BOOKMARK_FIELDS.contains(f)) {
+            return Err(anyhow!(
+                "Unknown bookmark field: {}. Choose from {}",
+                unknown,
+                BOOKMARK_FIELDS.join(", ")
+            ));
+        }
+        if requested.is_empty() {
+            return Err(anyhow!("No fields selected"));
+        }
+        Ok(Self {
+            fields: BOOKMARK_FIELDS
+                .iter()
+                .copied()
+                .filter(|f| requested.contains(f))
+                .collect(),
+        })
+    }
+}
+
+impl FieldSelection {
+    pub fn contains(&self, field: &str) -> bool {
+        self.fields.contains(&field)
+    }
+
+    pub fn names(&self) -> &[&'static str] {
+        &self.fields
+    }
+
+    /// The profile's bookmarks, flattened first when `children` is not kept
+    /// so nested bookmarks are not lost with it
+    pub(crate) fn bookmarks(&self, data: &BrowserData) -> Vec<Bookmark> {
+        let bookmarks = data.bookmarks.as_deref().unwrap_or_default();
+        if self.contains("children") {
+            bookmarks.to_vec()
+        } else {
+            flatten_bookmarks(bookmarks)
+        }
+    }
+
+    pub(crate) fn project_bookmark(&self, bookmark: &Bookmark) -> Result<serde_json::Value> {
+        let mut value = serde_json::to_value(bookmark)?;
+        if let Some(object) = value.as_object_mut() {
+            object.retain(|key, _| self.contains(key));
+            if let Some(children) = object.get_mut("children")
+                && let Some(nested) = &bookmark.children
+            {
+                *children = nested
+                    .iter()
+                    .map(|b| self.project_bookmark(b))
+                    .collect::<Result<_>>()?;
+            }
+        }
+        Ok(value)
+    }
+
+    /// Export documents with each bookmark reduced to the selected fields
+    pub(crate) fn project(&self, data: &[BrowserData]) -> Result<serde_json::Value> {
+        data.iter()
+            .map(|browser_data| {
+                let mut value = serde_json::to_value(browser_data)?;
+                if browser_data.bookmarks.is_some() {
+                    value["bookmarks"] = self
+                        .bookmarks(browser_data)
+                        .iter()
+                        .map(|b| self.project_bookmark(b))
+                        .collect::<Result<_>>()?;
+                }
+                Ok(value)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_parse_field_selection() {
+        let fields: FieldSelection = "url, title,folder".parse().unwrap();
+        assert_eq!(fields.names(), &["title", "url", "folder"]);
+        assert!("title,visits".parse::<FieldSelection>().is_err());
+        assert!(" , ".parse::<FieldSelection>().is_err());
+    }
+
+    #[test]
+    fn test_project_flattens_without_children() {
+        let leaf = Bookmark {
+            id: "1".to_string(),
+            title: "Rust".to_string(),
+            url: Some("https://www.rust-lang.org/".to_string()),
+            folder: None,
+            date_added: Some(Utc::now()),
+            ..Default::default()
+        };
+        let folder = Bookmark {
+            id: "f".to_string(),
+            title: "Dev".to_string(),
+            url: None,
+            date_added: None,
+            children: Some(vec![leaf.clone()]),
+            ..leaf
+        };
+        let data = BrowserData {
+            browser: "chrome".to_string(),
+            profile: "Default".to_string(),
+            export_date: Utc::now(),
+            bookmarks: Some(vec![folder]),
+            history: None,
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        };
+
+        let fields: FieldSelection = "title,url,folder".parse().unwrap();
+        let projected = fields.project(&[data]).unwrap();
+        assert_eq!(
+            projected[0]["bookmarks"],
+            serde_json::json!([{
+                "title": "Rust",
+                "url": "https://www.rust-lang.org/",
+                "folder": "Dev"
+            }])
+        );
+        assert_eq!(projected[0]["browser"], "chrome");
+
+        let back: Vec<BrowserData> = serde_json::from_value(projected).unwrap();
+        assert_eq!(back[0].bookmarks.as_ref().unwrap()[0].id, "");
+    }
+}
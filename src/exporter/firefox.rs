@@ -1,9 +1,13 @@
-use anyhow::{anyhow, Result};
+use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
-use super::{Bookmark, HistoryEntry, UrlEntry};
+use super::holdout;
+use super::nss;
+use super::snapshot::Snapshot;
+use super::{Bookmark, HISTORY_LIMIT, HistoryEntry, Password, UrlEntry};
 
 pub fn extract_bookmarks(profile_path: &Path) -> Result<Option<Vec<Bookmark>>> {
     let places_path = if profile_path.extension().and_then(|s| s.to_str()) == Some("sqlite") {
@@ -19,42 +23,95 @@ pub fn extract_bookmarks(profile_path: &Path) -> Result<Option<Vec<Bookmark>>> {
     extract_firefox_bookmarks(&places_path)
 }
 
-pub fn extract_history(profile_path: &Path) -> Result<Option<HistoryEntry>> {
+/// Extract history, most recent first; with `since`, only URLs last visited after it
+pub fn extract_history(
+    profile_path: &Path,
+    since: Option<DateTime<Utc>>,
+) -> Result<Option<HistoryEntry>> {
     let places_path = profile_path.join("places.sqlite");
     if !places_path.exists() {
         return Ok(None);
     }
 
-    extract_firefox_history(&places_path)
+    extract_firefox_history(&places_path, since)
 }
 
-fn extract_firefox_bookmarks(places_path: &Path) -> Result<Option<Vec<Bookmark>>> {
-    // Try to copy the database first to avoid lock issues
-    let temp_path = PathBuf::from("/tmp/places_copy.sqlite");
-
-    if let Err(e) = fs::copy(places_path, &temp_path) {
-        if e.to_string().contains("permission") || e.to_string().contains("locked") {
-            return Err(anyhow!(
-                "Firefox is running. Please close Firefox and try again. {}",
-                e
-            ));
-        }
-        return Err(e.into());
+/// Extract saved logins from `logins.json`, decrypted with the key in `key4.db`.
+///
+/// Returns `nss::MasterPasswordRequired` when the profile has a primary password
+/// and `master_password` is missing or wrong.
+pub fn extract_passwords(
+    profile_path: &Path,
+    master_password: Option<&str>,
+) -> Result<Option<Vec<Password>>> {
+    let logins_path = profile_path.join("logins.json");
+    let key4_path = profile_path.join("key4.db");
+    if !logins_path.exists() || !key4_path.exists() {
+        return Ok(None);
     }
 
-    let conn = rusqlite::Connection::open_with_flags(
-        &temp_path,
-        rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
-    )?;
+    let master_key = nss::load_master_key(&key4_path, master_password.unwrap_or(""))?;
+    let logins: serde_json::Value = serde_json::from_str(&fs::read_to_string(&logins_path)?)?;
+
+    let mut passwords = Vec::new();
+    for login in logins["logins"].as_array().into_iter().flatten() {
+        let url = login["hostname"].as_str().unwrap_or_default().to_string();
+        let decrypt = |field: &str| {
+            login[field]
+                .as_str()
+                .map(|v| nss::decrypt_login_field(&master_key, v))
+                .unwrap_or_else(|| Err(anyhow!("missing {}", field)))
+        };
+        let (username, password) =
+            match (decrypt("encryptedUsername"), decrypt("encryptedPassword")) {
+                (Ok(username), Ok(password)) => (username, password),
+                (Err(e), _) | (_, Err(e)) => {
+                    log::warn!("Skipping saved login for {}: {}", url, e);
+                    continue;
+                }
+            };
+
+        let form_data: HashMap<String, String> =
+            ["formSubmitURL", "usernameField", "passwordField"]
+                .into_iter()
+                .filter_map(|key| {
+                    let value = login[key].as_str().filter(|v| !v.is_empty())?;
+                    Some((key.to_string(), value.to_string()))
+                })
+                .collect();
 
-    let mut stmt = conn.prepare(
-        "SELECT b.id, b.title, p.url, b.dateAdded, p2.title as folder_title
+        passwords.push(Password {
+            url,
+            username,
+            password,
+            form_data: (!form_data.is_empty()).then_some(form_data),
+        });
+    }
+
+    Ok(Some(passwords))
+}
+
+fn extract_firefox_bookmarks(places_path: &Path) -> Result<Option<Vec<Bookmark>>> {
+    let snapshot = Snapshot::create(places_path)?;
+    let conn = snapshot.open()?;
+
+    // Keyword shortcuts live in moz_keywords (Firefox 39+); older profiles lack the table
+    let keyword_column = if table_exists(&conn, "moz_keywords")? {
+        "(SELECT k.keyword FROM moz_keywords k WHERE k.place_id = b.fk LIMIT 1)"
+    } else {
+        "NULL"
+    };
+
+    let folders = load_folders(&conn)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT b.id, b.title, p.url, b.dateAdded, b.parent, {} as keyword
          FROM moz_bookmarks b
          LEFT JOIN moz_places p ON b.fk = p.id
-         LEFT JOIN moz_bookmarks p2 ON b.parent = p2.id
          WHERE b.type = 1 AND p.url IS NOT NULL
          ORDER BY b.dateAdded DESC",
-    )?;
+        keyword_column
+    ))?;
 
     let rows = stmt.query_map([], |row| {
         Ok(Bookmark {
@@ -63,60 +120,95 @@ fn extract_firefox_bookmarks(places_path: &Path) -> Result<Option<Vec<Bookmark>>
                 .get::<_, Option<String>>(1)?
                 .unwrap_or_else(|| "".to_string()),
             url: row.get(2)?,
-            folder: match row.get::<_, Option<String>>(3) {
-                Ok(folder) => folder,
-                Err(_) => None,
-            },
-            date_added: match row.get::<_, i64>(4) {
+            folder: row
+                .get::<_, Option<i64>>(4)?
+                .and_then(|parent| folder_path(&folders, parent)),
+            date_added: match row.get::<_, i64>(3) {
                 Ok(ts) => Some(DateTime::from_timestamp(ts / 1000000, 0).unwrap_or_else(Utc::now)),
-                Err(_) => match row.get::<_, f64>(4) {
+                Err(_) => match row.get::<_, f64>(3) {
                     Ok(ts) => Some(
-                        DateTime::from_timestamp((ts as i64) / 1000000, 0)
-                            .unwrap_or_else(Utc::now),
+                        DateTime::from_timestamp((ts as i64) / 1000000, 0).unwrap_or_else(Utc::now),
                     ),
                     Err(_) => None,
                 },
             },
-            children: None,
+            keyword: row.get::<_, Option<String>>(5)?,
+            ..Default::default()
         })
     })?;
 
+    let holdout = holdout::get()?;
     let mut bookmarks = Vec::new();
     for row in rows {
-        bookmarks.push(row?);
+        let bookmark = row?;
+        if !holdout.holds(bookmark.folder.as_deref()) {
+            bookmarks.push(bookmark);
+        }
     }
 
     Ok(Some(bookmarks))
 }
 
-fn extract_firefox_history(places_path: &Path) -> Result<Option<HistoryEntry>> {
-    // Try to copy the database first to avoid lock issues
-    let temp_path = PathBuf::from("/tmp/places_copy_history.sqlite");
+/// Folder id -> (parent id, title) for every folder in `moz_bookmarks`
+pub(super) fn load_folders(conn: &rusqlite::Connection) -> Result<HashMap<i64, (i64, String)>> {
+    let mut stmt = conn.prepare("SELECT id, parent, title FROM moz_bookmarks WHERE type = 2")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            (
+                row.get::<_, Option<i64>>(1)?.unwrap_or(0),
+                row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            ),
+        ))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
 
-    if let Err(e) = fs::copy(places_path, &temp_path) {
-        if e.to_string().contains("permission") || e.to_string().contains("locked") {
-            return Err(anyhow!(
-                "Firefox is running. Please close Firefox and try again. {}",
-                e
-            ));
+/// Full `/`-joined path of a folder, e.g. `menu/Development/Rust`, matching the
+/// paths Chrome extraction produces. The untitled places root is left out.
+fn folder_path(folders: &HashMap<i64, (i64, String)>, folder_id: i64) -> Option<String> {
+    let mut names = Vec::new();
+    let mut id = folder_id;
+    // Bounded by the folder count in case of a corrupt parent cycle
+    for _ in 0..folders.len() {
+        let Some((parent, title)) = folders.get(&id) else {
+            break;
+        };
+        if !title.is_empty() {
+            names.push(title.as_str());
         }
-        return Err(e.into());
+        id = *parent;
     }
+    names.reverse();
+    (!names.is_empty()).then(|| names.join("/"))
+}
 
-    let conn = rusqlite::Connection::open_with_flags(
-        &temp_path,
-        rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+fn table_exists(conn: &rusqlite::Connection, name: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [name],
+        |row| row.get(0),
     )?;
+    Ok(count > 0)
+}
 
-    let mut stmt = conn.prepare(
+fn extract_firefox_history(
+    places_path: &Path,
+    since: Option<DateTime<Utc>>,
+) -> Result<Option<HistoryEntry>> {
+    let snapshot = Snapshot::create(places_path)?;
+    let conn = snapshot.open()?;
+
+    let mut stmt = conn.prepare(&format!(
         "SELECT p.url, p.title, p.visit_count, p.last_visit_date 
          FROM moz_places p
-         WHERE p.url IS NOT NULL
+         WHERE p.url IS NOT NULL AND (?1 IS NULL OR p.last_visit_date > ?1)
          ORDER BY p.last_visit_date DESC 
-         LIMIT 10000",
-    )?;
+         LIMIT {}",
+        HISTORY_LIMIT
+    ))?;
 
-    let rows = stmt.query_map([], |row| {
+    let rows = stmt.query_map([since.map(|t| t.timestamp_micros())], |row| {
         Ok(UrlEntry {
             url: row.get(0)?,
             title: row
@@ -136,3 +228,84 @@ fn extract_firefox_history(places_path: &Path) -> Result<Option<HistoryEntry>> {
 
     Ok(Some(HistoryEntry { urls }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn create_places_db(path: &Path) {
+        let conn = rusqlite::Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT, title TEXT,
+                 visit_count INTEGER, last_visit_date INTEGER);
+             CREATE TABLE moz_bookmarks (id INTEGER PRIMARY KEY, type INTEGER, fk INTEGER,
+                 parent INTEGER, title TEXT, dateAdded INTEGER);
+             CREATE TABLE moz_keywords (id INTEGER PRIMARY KEY, keyword TEXT UNIQUE,
+                 place_id INTEGER, post_data TEXT);
+             INSERT INTO moz_places VALUES (1, 'https://en.wikipedia.org/wiki/%s', 'Wikipedia', 3, NULL);
+             INSERT INTO moz_places VALUES (2, 'https://github.com', 'GitHub', 1, NULL);
+             INSERT INTO moz_bookmarks VALUES (1, 2, NULL, 0, '', 0);
+             INSERT INTO moz_bookmarks VALUES (2, 2, NULL, 1, 'menu', 0);
+             INSERT INTO moz_bookmarks VALUES (3, 2, NULL, 2, 'Development', 0);
+             INSERT INTO moz_bookmarks VALUES (4, 2, NULL, 3, 'Rust', 0);
+             INSERT INTO moz_bookmarks VALUES (10, 2, NULL, 1, 'toolbar', 0);
+             INSERT INTO moz_bookmarks VALUES (11, 1, 1, 10, 'Wikipedia', 1700000000000000);
+             INSERT INTO moz_bookmarks VALUES (12, 1, 2, 4, 'GitHub', 1700000000000000);
+             INSERT INTO moz_keywords VALUES (1, 'wp', 1, NULL);",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_extract_keyword_bookmarks() {
+        let dir = tempdir().unwrap();
+        create_places_db(&dir.path().join("places.sqlite"));
+
+        let bookmarks = extract_bookmarks(dir.path()).unwrap().unwrap();
+        assert_eq!(bookmarks.len(), 2);
+
+        let wiki = bookmarks.iter().find(|b| b.title == "Wikipedia").unwrap();
+        assert_eq!(wiki.keyword.as_deref(), Some("wp"));
+        let github = bookmarks.iter().find(|b| b.title == "GitHub").unwrap();
+        assert_eq!(github.keyword, None);
+
+        assert_eq!(wiki.folder.as_deref(), Some("toolbar"));
+        assert_eq!(github.folder.as_deref(), Some("menu/Development/Rust"));
+        assert_eq!(wiki.date_added, DateTime::from_timestamp(1700000000, 0));
+
+        let aliases = super::super::collect_aliases(&bookmarks);
+        assert_eq!(
+            aliases.get("wp").map(String::as_str),
+            Some("https://en.wikipedia.org/wiki/%s")
+        );
+    }
+
+    #[test]
+    fn test_extract_passwords() {
+        use crate::exporter::nss::tests::{create_key4_db, login_field};
+
+        let dir = tempdir().unwrap();
+        let master_key = [7u8; 32];
+        create_key4_db(&dir.path().join("key4.db"), "", &master_key);
+        let logins = serde_json::json!({
+            "logins": [{
+                "hostname": "https://example.com",
+                "formSubmitURL": "https://example.com/login",
+                "usernameField": "user",
+                "passwordField": "pass",
+                "encryptedUsername": login_field(&master_key, "alice"),
+                "encryptedPassword": login_field(&master_key, "hunter2"),
+            }]
+        });
+        fs::write(dir.path().join("logins.json"), logins.to_string()).unwrap();
+
+        let passwords = extract_passwords(dir.path(), None).unwrap().unwrap();
+        assert_eq!(passwords.len(), 1);
+        assert_eq!(passwords[0].url, "https://example.com");
+        assert_eq!(passwords[0].username, "alice");
+        assert_eq!(passwords[0].password, "hunter2");
+        let form_data = passwords[0].form_data.as_ref().unwrap();
+        assert_eq!(form_data["usernameField"], "user");
+    }
+}
@@ -0,0 +1,756 @@
+//! Write bookmarks back into a Firefox profile's `places.sqlite`.
+//!
+//! This closes the export → process → apply loop: bookmarks from an export
+//! file are matched to existing rows (by id, then by URL), retitled and moved
+//! in place, and anything new is inserted along with its `moz_places` row.
+//! Firefox maintains `foreign_count`, sibling positions and sync change
+//! counters with temporary triggers that only exist inside the browser, so
+//! they are kept up to date here by hand. Firefox must be closed, and a copy
+//! of the database is always taken before anything is written.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Transaction, TransactionBehavior, params};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::firefox::load_folders;
+use super::holdout;
+use super::snapshot::Snapshot;
+use super::{Bookmark, FOLDER_PATH_SEPARATOR, flatten_bookmarks};
+
+const TYPE_BOOKMARK: i64 = 1;
+const TYPE_FOLDER: i64 = 2;
+
+const ROOT_GUID: &str = "root________";
+const UNFILED_GUID: &str = "unfiled_____";
+const TAGS_GUID: &str = "tags________";
+
+/// `moz_bookmarks.syncStatus`: not yet uploaded / known to the server
+const SYNC_STATUS_NEW: i64 = 1;
+const SYNC_STATUS_NORMAL: i64 = 2;
+
+/// Root folder names used by other browsers, mapped to Firefox root guids
+const ROOT_ALIASES: &[(&str, &str)] = &[
+    ("bookmark_bar", "toolbar_____"),
+    ("bookmarks bar", "toolbar_____"),
+    ("bookmarks toolbar", "toolbar_____"),
+    ("favorites bar", "toolbar_____"),
+    ("bookmarks menu", "menu________"),
+    ("other", UNFILED_GUID),
+    ("other bookmarks", UNFILED_GUID),
+    ("other favorites", UNFILED_GUID),
+    ("synced", "mobile______"),
+    ("mobile bookmarks", "mobile______"),
+];
+
+const FIREFOX_RUNNING: &str =
+    "Firefox appears to be running on this profile; close it and try again";
+
+/// Options for [`apply_to_firefox`] and [`super::apply_to_chrome`]
+#[derive(Debug, Clone, Default)]
+pub struct ApplyOptions {
+    /// Work on a throwaway copy and only report what would change
+    pub dry_run: bool,
+    /// Delete bookmarks that are not in the applied file
+    pub prune: bool,
+    /// Where to keep the pre-apply copy of the bookmarks database; defaults
+    /// to the backups path (see [`crate::paths`])
+    pub backup_dir: Option<PathBuf>,
+}
+
+/// What an apply changed
+#[derive(Debug, Default)]
+pub struct ApplySummary {
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    pub folders_created: usize,
+    /// Bookmarks without a URL and Firefox tag entries, which are left alone
+    pub skipped: usize,
+    /// Copy of the bookmarks database taken before writing (`None` for dry runs)
+    pub backup: Option<PathBuf>,
+    /// The bookmarks `prune` deleted, for the store's trash
+    pub pruned: Vec<Bookmark>,
+}
+
+/// Apply `bookmarks` to the Firefox profile at `profile_path`
+pub fn apply_to_firefox(
+    profile_path: &Path,
+    bookmarks: &[Bookmark],
+    options: &ApplyOptions,
+) -> Result<ApplySummary> {
+    let places_path = profile_path.join("places.sqlite");
+    if !places_path.exists() {
+        return Err(anyhow!("No places.sqlite in {}", profile_path.display()));
+    }
+
+    if options.dry_run {
+        // A private copy keeps dry runs safe even while Firefox is open
+        let snapshot = Snapshot::create(&places_path)?;
+        let mut conn = snapshot.open()?;
+        return apply_bookmarks(&mut conn, bookmarks, options);
+    }
+
+    // Firefox keeps a `lock` symlink in the profile on Unix while running
+    if profile_path.join("lock").symlink_metadata().is_ok() {
+        return Err(anyhow!(FIREFOX_RUNNING));
+    }
+    let mut conn = Connection::open(&places_path)?;
+    // ...and holds places.sqlite in exclusive locking mode on every platform
+    conn.busy_timeout(std::time::Duration::from_millis(500))?;
+    conn.execute_batch("BEGIN EXCLUSIVE; COMMIT;")
+        .map_err(|e| anyhow!("{} ({})", FIREFOX_RUNNING, e))?;
+
+    let backup_dir = options
+        .backup_dir
+        .clone()
+        .unwrap_or_else(|| crate::paths::get().backups.clone());
+    let backup = backup_places(&conn, profile_path, &backup_dir)?;
+    let mut summary = apply_bookmarks(&mut conn, bookmarks, options)?;
+    summary.backup = Some(backup);
+    Ok(summary)
+}
+
+/// Copy the database to `firefox-<profile>-places-<timestamp>.sqlite` in `backup_dir`
+fn backup_places(conn: &Connection, profile_path: &Path, backup_dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(backup_dir)?;
+    let backup = backup_dir.join(format!(
+        "firefox-{}-places-{}.sqlite",
+        profile_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy(),
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+    // Unlike a file copy, VACUUM INTO also captures anything still in the WAL
+    conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])?;
+    Ok(backup)
+}
+
+/// An existing bookmark row outside the tags root
+struct Existing {
+    id: i64,
+    fk: i64,
+    parent: i64,
+    title: String,
+    url: String,
+    guid: String,
+    sync_status: i64,
+}
+
+fn apply_bookmarks(
+    conn: &mut Connection,
+    bookmarks: &[Bookmark],
+    options: &ApplyOptions,
+) -> Result<ApplySummary> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Exclusive)?;
+    let mut writer = PlacesWriter::load(&tx)?;
+    let existing = writer.existing_bookmarks()?;
+
+    let mut by_id: HashMap<i64, usize> = HashMap::new();
+    let mut by_url: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, row) in existing.iter().enumerate() {
+        by_id.insert(row.id, index);
+        by_url.entry(row.url.as_str()).or_default().push(index);
+    }
+    let mut claimed = vec![false; existing.len()];
+
+    let mut summary = ApplySummary::default();
+    for bookmark in flatten_bookmarks(bookmarks) {
+        let Some(url) = bookmark.url.as_deref().filter(|u| !u.is_empty()) else {
+            summary.skipped += 1;
+            continue;
+        };
+        let Some(parent) = writer.resolve_folder(bookmark.folder.as_deref(), &mut summary)? else {
+            summary.skipped += 1;
+            continue;
+        };
+
+        // Prefer the row this bookmark was exported from, then any row for the URL
+        let matched = bookmark
+            .id
+            .parse::<i64>()
+            .ok()
+            .and_then(|id| by_id.get(&id).copied())
+            .filter(|&index| !claimed[index] && existing[index].url == url)
+            .or_else(|| {
+                by_url
+                    .get(url)
+                    .and_then(|rows| rows.iter().copied().find(|&index| !claimed[index]))
+            });
+
+        match matched {
+            Some(index) => {
+                claimed[index] = true;
+                let row = &existing[index];
+                if row.parent == parent && row.title == bookmark.title {
+                    summary.unchanged += 1;
+                } else {
+                    writer.update_bookmark(row, parent, &bookmark.title)?;
+                    summary.updated += 1;
+                }
+            }
+            None => {
+                writer.insert_bookmark(parent, url, &bookmark.title, bookmark.date_added)?;
+                summary.inserted += 1;
+            }
+        }
+    }
+
+    if options.prune {
+        let folder_paths = writer.folder_paths();
+        let holdout = holdout::get()?;
+        for (row, _) in existing
+            .iter()
+            .zip(&claimed)
+            .filter(|(_, claimed)| !**claimed)
+        {
+            let folder = folder_paths.get(&row.parent).cloned();
+            // Held-back folders are never exported, so their absence means nothing
+            if holdout.holds(folder.as_deref()) {
+                continue;
+            }
+            writer.remove_bookmark(row)?;
+            summary.removed += 1;
+            summary.pruned.push(Bookmark {
+                id: row.id.to_string(),
+                title: row.title.clone(),
+                url: Some(row.url.clone()),
+                folder,
+                date_added: None,
+                ..Default::default()
+            });
+        }
+    }
+
+    writer.renumber_positions()?;
+    tx.commit()?;
+    Ok(summary)
+}
+
+struct PlacesWriter<'a> {
+    tx: &'a Transaction<'a>,
+    /// (parent id, title) -> folder id
+    children: HashMap<(i64, String), i64>,
+    /// Lower-cased top-level folder names and aliases -> root folder id
+    roots: HashMap<String, i64>,
+    unfiled: i64,
+    tags: Option<i64>,
+    /// Parents whose children gained, lost or reordered entries
+    touched: BTreeSet<i64>,
+    now: i64,
+    guid_counter: u64,
+}
+
+impl<'a> PlacesWriter<'a> {
+    fn load(tx: &'a Transaction<'a>) -> Result<Self> {
+        let guid_id = |guid: &str| -> Result<Option<i64>> {
+            Ok(tx
+                .query_row(
+                    "SELECT id FROM moz_bookmarks WHERE guid = ?1",
+                    [guid],
+                    |row| row.get(0),
+                )
+                .optional()?)
+        };
+        let root = guid_id(ROOT_GUID)?.ok_or_else(|| {
+            anyhow!("places.sqlite has no bookmarks root; is this a Firefox profile?")
+        })?;
+        let unfiled = guid_id(UNFILED_GUID)?
+            .ok_or_else(|| anyhow!("places.sqlite has no \"Other Bookmarks\" folder"))?;
+
+        let folders = load_folders(tx)?;
+        let children = folders
+            .iter()
+            .map(|(&id, (parent, title))| ((*parent, title.clone()), id))
+            .collect();
+        let mut roots: HashMap<String, i64> = folders
+            .iter()
+            .filter(|(_, (parent, title))| *parent == root && !title.is_empty())
+            .map(|(&id, (_, title))| (title.to_lowercase(), id))
+            .collect();
+        for (alias, guid) in ROOT_ALIASES {
+            if let Some(id) = guid_id(guid)? {
+                roots.entry(alias.to_string()).or_insert(id);
+            }
+        }
+
+        Ok(Self {
+            tx,
+            children,
+            roots,
+            unfiled,
+            tags: guid_id(TAGS_GUID)?,
+            touched: BTreeSet::new(),
+            now: Utc::now().timestamp_micros(),
+            guid_counter: 0,
+        })
+    }
+
+    /// The exported path (`toolbar/Dev`) of every folder, by id
+    fn folder_paths(&self) -> HashMap<i64, String> {
+        let parents: HashMap<i64, (i64, &str)> = self
+            .children
+            .iter()
+            .map(|((parent, title), &id)| (id, (*parent, title.as_str())))
+            .collect();
+        parents
+            .keys()
+            .map(|&id| {
+                let mut segments = Vec::new();
+                let mut current = id;
+                while let Some((parent, title)) = parents.get(&current) {
+                    if !title.is_empty() {
+                        segments.push(*title);
+                    }
+                    current = *parent;
+                }
+                segments.reverse();
+                (id, segments.join(FOLDER_PATH_SEPARATOR))
+            })
+            .collect()
+    }
+
+    /// Bookmark rows, leaving out the entries Firefox uses to store tags
+    fn existing_bookmarks(&self) -> Result<Vec<Existing>> {
+        let mut stmt = self.tx.prepare(
+            "SELECT b.id, b.fk, b.parent, b.title, p.url, b.guid, b.syncStatus
+             FROM moz_bookmarks b
+             JOIN moz_places p ON p.id = b.fk
+             LEFT JOIN moz_bookmarks f ON f.id = b.parent
+             WHERE b.type = ?1 AND (?2 IS NULL OR f.parent IS NOT ?2)",
+        )?;
+        let rows = stmt.query_map(params![TYPE_BOOKMARK, self.tags], |row| {
+            Ok(Existing {
+                id: row.get(0)?,
+                fk: row.get(1)?,
+                parent: row.get(2)?,
+                title: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                url: row.get(4)?,
+                guid: row.get(5)?,
+                sync_status: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Folder id for an exported folder path, creating missing folders.
+    /// Paths under a Firefox root (`toolbar/Dev`) or another browser's root
+    /// (`Bookmarks bar/Dev`) land under that root; anything else goes to
+    /// Other Bookmarks. Returns `None` for paths inside the tags root.
+    fn resolve_folder(
+        &mut self,
+        path: Option<&str>,
+        summary: &mut ApplySummary,
+    ) -> Result<Option<i64>> {
+        let segments: Vec<&str> = path
+            .unwrap_or_default()
+            .split(FOLDER_PATH_SEPARATOR)
+            .filter(|s| !s.is_empty())
+            .collect();
+        let (mut parent, rest) = match segments.first() {
+            Some(first) => match self.roots.get(&first.to_lowercase()) {
+                Some(&root) => (root, &segments[1..]),
+                None => (self.unfiled, &segments[..]),
+            },
+            None => (self.unfiled, &segments[..]),
+        };
+        if Some(parent) == self.tags {
+            return Ok(None);
+        }
+
+        for name in rest {
+            let key = (parent, name.to_string());
+            parent = match self.children.get(&key) {
+                Some(&id) => id,
+                None => {
+                    let id = self.insert_folder(parent, name)?;
+                    self.children.insert(key, id);
+                    summary.folders_created += 1;
+                    id
+                }
+            };
+        }
+        Ok(Some(parent))
+    }
+
+    fn insert_folder(&mut self, parent: i64, title: &str) -> Result<i64> {
+        let guid = self.new_guid(title);
+        self.tx.execute(
+            "INSERT INTO moz_bookmarks
+                 (type, parent, position, title, dateAdded, lastModified, guid,
+                  syncStatus, syncChangeCounter)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5, ?6, ?7, 1)",
+            params![
+                TYPE_FOLDER,
+                parent,
+                self.next_position(parent)?,
+                title,
+                self.now,
+                guid,
+                SYNC_STATUS_NEW
+            ],
+        )?;
+        self.touched.insert(parent);
+        Ok(self.tx.last_insert_rowid())
+    }
+
+    fn insert_bookmark(
+        &mut self,
+        parent: i64,
+        url: &str,
+        title: &str,
+        date_added: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        let place = self.place_id(url, title)?;
+        let guid = self.new_guid(url);
+        self.tx.execute(
+            "INSERT INTO moz_bookmarks
+                 (type, fk, parent, position, title, dateAdded, lastModified, guid,
+                  syncStatus, syncChangeCounter)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 1)",
+            params![
+                TYPE_BOOKMARK,
+                place,
+                parent,
+                self.next_position(parent)?,
+                title,
+                date_added.map_or(self.now, |d| d.timestamp_micros()),
+                self.now,
+                guid,
+                SYNC_STATUS_NEW
+            ],
+        )?;
+        self.tx.execute(
+            "UPDATE moz_places SET foreign_count = foreign_count + 1 WHERE id = ?1",
+            [place],
+        )?;
+        self.touched.insert(parent);
+        Ok(())
+    }
+
+    fn update_bookmark(&mut self, row: &Existing, parent: i64, title: &str) -> Result<()> {
+        if row.parent != parent {
+            self.tx.execute(
+                "UPDATE moz_bookmarks SET parent = ?1, position = ?2 WHERE id = ?3",
+                params![parent, self.next_position(parent)?, row.id],
+            )?;
+            self.touched.insert(row.parent);
+            self.touched.insert(parent);
+        }
+        self.tx.execute(
+            "UPDATE moz_bookmarks
+             SET title = ?1, lastModified = ?2, syncChangeCounter = syncChangeCounter + 1
+             WHERE id = ?3",
+            params![title, self.now, row.id],
+        )?;
+        Ok(())
+    }
+
+    fn remove_bookmark(&mut self, row: &Existing) -> Result<()> {
+        self.tx
+            .execute("DELETE FROM moz_bookmarks WHERE id = ?1", [row.id])?;
+        self.tx.execute(
+            "UPDATE moz_places SET foreign_count = MAX(foreign_count - 1, 0) WHERE id = ?1",
+            [row.fk],
+        )?;
+        // Tombstone for Sync, so the deletion reaches other devices
+        if row.sync_status == SYNC_STATUS_NORMAL && self.has_table("moz_bookmarks_deleted")? {
+            self.tx.execute(
+                "INSERT OR REPLACE INTO moz_bookmarks_deleted (guid, dateRemoved) VALUES (?1, ?2)",
+                params![row.guid, self.now],
+            )?;
+        }
+        self.touched.insert(row.parent);
+        Ok(())
+    }
+
+    /// Existing `moz_places` id for the URL, or a freshly inserted one
+    fn place_id(&mut self, url: &str, title: &str) -> Result<i64> {
+        let hash = url_hash(url);
+        let existing = self
+            .tx
+            .query_row(
+                "SELECT id FROM moz_places WHERE url_hash = ?1 AND url = ?2",
+                params![hash, url],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(id) = existing {
+            return Ok(id);
+        }
+
+        let guid = self.new_guid(url);
+        self.tx.execute(
+            "INSERT INTO moz_places (url, title, rev_host, guid, url_hash, foreign_count)
+             VALUES (?1, ?2, ?3, ?4, ?5, 0)",
+            params![url, title, rev_host(url), guid, hash],
+        )?;
+        Ok(self.tx.last_insert_rowid())
+    }
+
+    fn next_position(&self, parent: i64) -> Result<i64> {
+        Ok(self.tx.query_row(
+            "SELECT COALESCE(MAX(position) + 1, 0) FROM moz_bookmarks WHERE parent = ?1",
+            [parent],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Firefox expects each folder's children at positions 0..n with no gaps
+    fn renumber_positions(&self) -> Result<()> {
+        for &parent in &self.touched {
+            let ids: Vec<i64> = self
+                .tx
+                .prepare("SELECT id FROM moz_bookmarks WHERE parent = ?1 ORDER BY position, id")?
+                .query_map([parent], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            for (position, id) in ids.iter().enumerate() {
+                self.tx.execute(
+                    "UPDATE moz_bookmarks SET position = ?1 WHERE id = ?2",
+                    params![position as i64, id],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn has_table(&self, name: &str) -> Result<bool> {
+        let count: i64 = self.tx.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [name],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// A 12-character base64url guid, the format Places uses for every row
+    fn new_guid(&mut self, seed: &str) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+        self.guid_counter += 1;
+        let digest = Sha256::new()
+            .chain_update(seed.as_bytes())
+            .chain_update(self.now.to_le_bytes())
+            .chain_update(self.guid_counter.to_le_bytes())
+            .finalize();
+        digest[..12]
+            .iter()
+            .map(|b| ALPHABET[(b % 64) as usize] as char)
+            .collect()
+    }
+}
+
+/// Firefox's `hash()` SQL function, used to index `moz_places.url_hash`:
+/// the low 16 bits of the scheme's hash in bits 32-47, plus the URL's hash
+fn url_hash(url: &str) -> i64 {
+    // Only the first 1500 bytes are hashed; the scheme must end within 50
+    const MAX_CHARS_TO_HASH: usize = 1500;
+    const MAX_PREFIX_LENGTH: usize = 50;
+
+    let bytes = url.as_bytes();
+    let string_hash = hash_string(&bytes[..bytes.len().min(MAX_CHARS_TO_HASH)]) as i64;
+    match bytes
+        .iter()
+        .take(MAX_PREFIX_LENGTH)
+        .position(|&b| b == b':')
+    {
+        Some(colon) => (((hash_string(&bytes[..colon]) & 0xFFFF) as i64) << 32) + string_hash,
+        None => string_hash,
+    }
+}
+
+/// `mozilla::HashString`: the golden-ratio multiplicative hash from mfbt
+fn hash_string(bytes: &[u8]) -> u32 {
+    const GOLDEN_RATIO_U32: u32 = 0x9E37_79B9;
+    bytes.iter().fold(0u32, |hash, &b| {
+        GOLDEN_RATIO_U32.wrapping_mul(hash.rotate_left(5) ^ b as u32)
+    })
+}
+
+/// `moz_places.rev_host`: the lower-cased host reversed, with a trailing dot
+fn rev_host(url: &str) -> String {
+    let host = url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_lowercase))
+        .unwrap_or_default();
+    let mut reversed: String = host.chars().rev().collect();
+    reversed.push('.');
+    reversed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::firefox::extract_bookmarks;
+    use tempfile::tempdir;
+
+    fn create_places_db(path: &Path) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT, title TEXT,
+                 rev_host TEXT, visit_count INTEGER DEFAULT 0, last_visit_date INTEGER,
+                 guid TEXT UNIQUE, foreign_count INTEGER DEFAULT 0 NOT NULL,
+                 url_hash INTEGER DEFAULT 0 NOT NULL);
+             CREATE TABLE moz_bookmarks (id INTEGER PRIMARY KEY, type INTEGER, fk INTEGER,
+                 parent INTEGER, position INTEGER, title TEXT, dateAdded INTEGER,
+                 lastModified INTEGER, guid TEXT UNIQUE, syncStatus INTEGER NOT NULL DEFAULT 0,
+                 syncChangeCounter INTEGER NOT NULL DEFAULT 1);
+             CREATE TABLE moz_bookmarks_deleted (guid TEXT PRIMARY KEY, dateRemoved INTEGER);
+             INSERT INTO moz_bookmarks VALUES (1, 2, NULL, 0, 0, '', 0, 0, 'root________', 2, 0);
+             INSERT INTO moz_bookmarks VALUES (2, 2, NULL, 1, 0, 'menu', 0, 0, 'menu________', 2, 0);
+             INSERT INTO moz_bookmarks VALUES (3, 2, NULL, 1, 1, 'toolbar', 0, 0, 'toolbar_____', 2, 0);
+             INSERT INTO moz_bookmarks VALUES (4, 2, NULL, 1, 2, 'tags', 0, 0, 'tags________', 2, 0);
+             INSERT INTO moz_bookmarks VALUES (5, 2, NULL, 1, 3, 'unfiled', 0, 0, 'unfiled_____', 2, 0);
+             INSERT INTO moz_bookmarks VALUES (6, 2, NULL, 4, 0, 'rust', 0, 0, 'tagrust00000', 2, 0);",
+        )
+        .unwrap();
+        for (id, url) in [(1, "https://github.com/"), (2, "https://old.example.com/")] {
+            conn.execute(
+                "INSERT INTO moz_places (id, url, title, rev_host, guid, foreign_count, url_hash)
+                 VALUES (?1, ?2, '', ?3, ?4, 1, ?5)",
+                params![
+                    id,
+                    url,
+                    rev_host(url),
+                    format!("place{:07}", id),
+                    url_hash(url)
+                ],
+            )
+            .unwrap();
+        }
+        conn.execute_batch(
+            "INSERT INTO moz_bookmarks VALUES (10, 1, 1, 3, 0, 'GitHub', 0, 0, 'bookmark0010', 2, 0);
+             INSERT INTO moz_bookmarks VALUES (11, 1, 2, 2, 0, 'Old', 0, 0, 'bookmark0011', 2, 0);
+             INSERT INTO moz_bookmarks VALUES (12, 1, 1, 6, 0, NULL, 0, 0, 'tagentry0012', 2, 0);
+             UPDATE moz_places SET foreign_count = 2 WHERE id = 1;",
+        )
+        .unwrap();
+    }
+
+    fn bookmark(id: &str, title: &str, url: &str, folder: &str) -> Bookmark {
+        Bookmark {
+            id: id.to_string(),
+            title: title.to_string(),
+            url: Some(url.to_string()),
+            folder: Some(folder.to_string()),
+            date_added: DateTime::from_timestamp(1_700_000_000, 0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_apply_inserts_updates_and_prunes() {
+        let dir = tempdir().unwrap();
+        let places = dir.path().join("places.sqlite");
+        create_places_db(&places);
+
+        let bookmarks = vec![
+            // Moved from the toolbar and renamed
+            bookmark("10", "GitHub Home", "https://github.com/", "menu/Dev"),
+            // New, under a Chrome-style root
+            bookmark(
+                "x1",
+                "Rust",
+                "https://www.rust-lang.org/",
+                "Bookmarks bar/Lang",
+            ),
+            // A tag entry from a Firefox export: left alone
+            bookmark("12", "", "https://github.com/", "tags/rust"),
+        ];
+        let options = ApplyOptions {
+            dry_run: false,
+            prune: true,
+            backup_dir: Some(dir.path().join("backups")),
+        };
+        let summary = apply_to_firefox(dir.path(), &bookmarks, &options).unwrap();
+        assert_eq!(summary.updated, 1);
+        assert_eq!(summary.inserted, 1);
+        assert_eq!(summary.removed, 1);
+        assert_eq!(
+            summary.pruned[0].url.as_deref(),
+            Some("https://old.example.com/")
+        );
+        assert_eq!(summary.pruned[0].folder.as_deref(), Some("menu"));
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(summary.folders_created, 2);
+        assert!(summary.backup.as_ref().unwrap().exists());
+
+        let mut applied = extract_bookmarks(dir.path()).unwrap().unwrap();
+        applied.retain(|b| !b.folder.as_deref().unwrap_or_default().starts_with("tags"));
+        applied.sort_by(|a, b| a.title.cmp(&b.title));
+        let found: Vec<(&str, &str)> = applied
+            .iter()
+            .map(|b| (b.title.as_str(), b.folder.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            found,
+            [("GitHub Home", "menu/Dev"), ("Rust", "toolbar/Lang")]
+        );
+
+        let conn = Connection::open(&places).unwrap();
+        let count = |sql: &str| -> i64 { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+        // GitHub keeps its bookmark and tag entry; the pruned place is unreferenced
+        assert_eq!(
+            count("SELECT foreign_count FROM moz_places WHERE id = 1"),
+            2
+        );
+        assert_eq!(
+            count("SELECT foreign_count FROM moz_places WHERE id = 2"),
+            0
+        );
+        assert_eq!(
+            count("SELECT url_hash FROM moz_places WHERE url = 'https://www.rust-lang.org/'"),
+            url_hash("https://www.rust-lang.org/")
+        );
+        assert_eq!(count("SELECT COUNT(*) FROM moz_bookmarks_deleted"), 1);
+        // Toolbar lost GitHub and gained Lang: positions stay contiguous
+        assert_eq!(
+            count("SELECT MAX(position) FROM moz_bookmarks WHERE parent = 3"),
+            0
+        );
+    }
+
+    #[test]
+    fn test_dry_run_and_running_firefox_leave_profile_untouched() {
+        let dir = tempdir().unwrap();
+        create_places_db(&dir.path().join("places.sqlite"));
+        let bookmarks = vec![bookmark("n", "New", "https://example.com/", "menu")];
+
+        let options = ApplyOptions {
+            dry_run: true,
+            prune: false,
+            backup_dir: None,
+        };
+        let summary = apply_to_firefox(dir.path(), &bookmarks, &options).unwrap();
+        assert_eq!(summary.inserted, 1);
+        assert!(summary.backup.is_none());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("127.0.0.1:+1234", dir.path().join("lock")).unwrap();
+            let options = ApplyOptions {
+                dry_run: false,
+                prune: false,
+                backup_dir: Some(dir.path().join("backups")),
+            };
+            assert!(apply_to_firefox(dir.path(), &bookmarks, &options).is_err());
+        }
+        assert_eq!(extract_bookmarks(dir.path()).unwrap().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_url_hash_layout() {
+        let hash = url_hash("https://example.com/");
+        assert_eq!(hash >> 32, (hash_string(b"https") & 0xFFFF) as i64);
+        assert_eq!(
+            hash & 0xFFFF_FFFF,
+            hash_string(b"https://example.com/") as i64
+        );
+        assert_eq!(url_hash("no-scheme"), hash_string(b"no-scheme") as i64);
+        assert_eq!(rev_host("https://WWW.Example.com/a"), "moc.elpmaxe.www.");
+    }
+}
@@ -0,0 +1,99 @@
+//! Folders that never leave the browser.
+//!
+//! `never_export_folders` in the config lists folders such as `Banking` or
+//! `Private`. The Chrome, Firefox and Safari extractors drop those folders
+//! and everything under them while parsing, so their bookmarks never reach an
+//! export, the store, a graph or an MCP response. Only Firefox's database
+//! snapshot still holds them, and that is a private copy of the browser's
+//! own file, deleted once read. `apply --prune` leaves them in place, since
+//! they are missing from every export by design.
+
+use anyhow::{Result, anyhow};
+use std::sync::OnceLock;
+
+use super::FOLDER_PATH_SEPARATOR;
+use crate::config::AppConfig;
+
+/// The configured folders, as lowercase path segments
+#[derive(Debug, Clone, Default)]
+pub struct FolderHoldout {
+    folders: Vec<Vec<String>>,
+}
+
+impl FolderHoldout {
+    /// Folders are names or paths (`Work/Payroll`), matched without regard
+    /// to case
+    pub fn new(folders: &[String]) -> Self {
+        Self {
+            folders: folders
+                .iter()
+                .map(|folder| segments(folder))
+                .filter(|segments| !segments.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.folders.is_empty()
+    }
+
+    /// Whether a bookmark in `folder` is held back: some run of the path's
+    /// segments equals a configured folder, so `Banking` holds
+    /// `bookmark_bar/Banking/Cards` but not `bookmark_bar/Online Banking`
+    pub fn holds(&self, folder: Option<&str>) -> bool {
+        let Some(folder) = folder else {
+            return false;
+        };
+        let path = segments(folder);
+        self.folders
+            .iter()
+            .any(|held| path.windows(held.len()).any(|run| run == held.as_slice()))
+    }
+}
+
+fn segments(path: &str) -> Vec<String> {
+    path.split(FOLDER_PATH_SEPARATOR)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+static HOLDOUT: OnceLock<Result<FolderHoldout, String>> = OnceLock::new();
+
+/// The holdout from the config file in effect, read once per process. An
+/// unreadable config is an error rather than an empty holdout, so a typo
+/// can't quietly export the folders it was meant to protect.
+pub fn get() -> Result<&'static FolderHoldout> {
+    HOLDOUT
+        .get_or_init(|| {
+            let path = &crate::paths::get().config;
+            if !path.exists() {
+                return Ok(FolderHoldout::default());
+            }
+            AppConfig::load_from_file(path)
+                .map(|config| FolderHoldout::new(&config.never_export_folders))
+                .map_err(|e| format!("{:#}", e))
+        })
+        .as_ref()
+        .map_err(|e| anyhow!("Could not read never_export_folders from the config: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_holds_folders_and_subfolders() {
+        let holdout = FolderHoldout::new(&["Banking".to_string(), "Work/Payroll".to_string()]);
+
+        assert!(holdout.holds(Some("bookmark_bar/Banking")));
+        assert!(holdout.holds(Some("bookmark_bar/banking/Cards")));
+        assert!(holdout.holds(Some("Banking")));
+        assert!(holdout.holds(Some("menu/Work/Payroll/2024")));
+        assert!(!holdout.holds(Some("bookmark_bar/Online Banking")));
+        assert!(!holdout.holds(Some("menu/Payroll")));
+        assert!(!holdout.holds(None));
+        assert!(FolderHoldout::new(&[]).is_empty());
+    }
+}
@@ -1,15 +1,52 @@
+mod anonymize;
+mod bibtex;
+mod checksum;
 mod chrome;
+mod chrome_apply;
+mod fields;
 mod firefox;
+mod firefox_apply;
+mod holdout;
+mod nss;
+mod opml;
+mod org;
+mod os_crypt;
+mod provenance;
 mod safari;
+mod snapshot;
+mod sqlite;
 
-use anyhow::{anyhow, Result};
+use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use crate::browser::Browser;
+use crate::enrich::PageInfo;
+use crate::integrations::github::RepoInfo;
+use crate::integrations::papers::PaperMetadata;
+use crate::media::MediaType;
+use crate::progress::ProgressEvent;
+use crate::utils;
+
+pub use anonymize::Anonymizer;
+pub use bibtex::to_bibtex;
+pub use checksum::{ChecksumStatus, Verification, verify_export, with_checksum};
+pub use chrome_apply::apply_to_chrome;
+pub use fields::{BOOKMARK_FIELDS, FieldSelection};
+pub use firefox_apply::{ApplyOptions, ApplySummary, apply_to_firefox};
+pub use holdout::FolderHoldout;
+pub use nss::MasterPasswordRequired;
+pub use provenance::{Provenance, RowCounts};
+pub use sqlite::write_sqlite;
+
+/// Most recent history entries read per profile; exports at this size are
+/// flagged as truncated in their provenance
+pub const HISTORY_LIMIT: usize = 10_000;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BrowserData {
@@ -19,16 +56,61 @@ pub struct BrowserData {
     pub bookmarks: Option<Vec<Bookmark>>,
     pub history: Option<HistoryEntry>,
     pub passwords: Option<Vec<Password>>,
+    /// Keyword shortcuts (keyword -> URL), only present when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aliases: Option<BTreeMap<String, String>>,
+    /// Profile name from the browser's profile picker, when it differs from `profile`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile_name: Option<String>,
+    /// Account email signed in to the profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile_email: Option<String>,
+    /// How this data was produced (tool version, settings, row counts)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Bookmark {
+    #[serde(default)]
     pub id: String,
+    #[serde(default)]
     pub title: String,
     pub url: Option<String>,
     pub folder: Option<String>,
     pub date_added: Option<DateTime<Utc>>,
     pub children: Option<Vec<Bookmark>>,
+    /// Browser keyword shortcut (e.g. Firefox `moz_keywords`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyword: Option<String>,
+    /// User-assigned tags (e.g. from an imported `bookmarks.html`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// Free-form notes (e.g. Pinboard's extended description)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// PDF, video, podcast or image, as detected by the enrich stage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<MediaType>,
+    /// Nearest Wayback Machine snapshot of the URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_url: Option<String>,
+    /// Stars, language and archived/moved status of a GitHub repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github: Option<RepoInfo>,
+    /// `<link rel="canonical">` of the page, as fetched by `enrich`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<String>,
+    /// Title, authors and year of a bookmarked paper (arXiv, DOI, IEEE)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paper: Option<PaperMetadata>,
+    /// Path of a thumbnail of the page, from `enrich --screenshots`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screenshot: Option<String>,
+    /// Site name, Open Graph type and keywords the page declares, from
+    /// `enrich`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<PageInfo>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -52,6 +134,129 @@ pub struct Password {
     pub form_data: Option<HashMap<String, String>>,
 }
 
+/// File format written by an export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Full `BrowserData` documents, readable by `process` and `graph`
+    #[default]
+    Yaml,
+    /// One row per bookmark, for spreadsheets and BI tools
+    Csv,
+    /// The same documents as YAML, as a pretty-printed JSON array
+    Json,
+    /// One JSON object per bookmark, history entry or password, for streaming
+    Jsonl,
+    /// Emacs org-mode outline of folders and bookmarks
+    Org,
+    /// OPML outline of folders with bookmark links
+    Opml,
+    /// Normalized SQLite database of profiles, folders, bookmarks and history;
+    /// written straight to a file
+    Sqlite,
+    /// BibTeX entries for the bookmarks that point at papers
+    Bibtex,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Yaml => "yaml",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Org => "org",
+            ExportFormat::Opml => "opml",
+            ExportFormat::Sqlite => "sqlite",
+            ExportFormat::Bibtex => "bib",
+        }
+    }
+
+    /// Whether the format only carries bookmarks (plus, for org, visit counts)
+    fn bookmarks_only(&self) -> bool {
+        matches!(
+            self,
+            ExportFormat::Csv | ExportFormat::Org | ExportFormat::Opml | ExportFormat::Bibtex
+        )
+    }
+
+    /// Whether the format has room for saved passwords
+    fn holds_passwords(&self) -> bool {
+        matches!(
+            self,
+            ExportFormat::Yaml | ExportFormat::Json | ExportFormat::Jsonl
+        )
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "yaml" | "yml" => Ok(ExportFormat::Yaml),
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "jsonl" | "ndjson" => Ok(ExportFormat::Jsonl),
+            "org" => Ok(ExportFormat::Org),
+            "opml" => Ok(ExportFormat::Opml),
+            "sqlite" | "db" => Ok(ExportFormat::Sqlite),
+            "bibtex" | "bib" => Ok(ExportFormat::Bibtex),
+            _ => Err(anyhow!(
+                "Unsupported export format: {}. Use yaml, json, jsonl, csv, org, opml, sqlite or bibtex",
+                s
+            )),
+        }
+    }
+}
+
+/// Options controlling what goes into an export beyond the raw data type
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Output file format
+    pub format: ExportFormat,
+    /// Bookmark fields to keep; `None` keeps them all
+    pub fields: Option<FieldSelection>,
+    /// Hash titles, URLs and folders so the export can be shared
+    pub anonymize: Option<Anonymizer>,
+    /// Emit an `aliases` section mapping bookmark keywords to URLs
+    pub include_aliases: bool,
+    /// Append Chrome/Edge reading-list entries to the exported bookmarks
+    pub include_reading_list: bool,
+    /// Decrypt and export saved passwords in plaintext (explicit opt-in)
+    pub include_passwords: bool,
+    /// Firefox primary password for `key4.db`, if the profile has one
+    pub master_password: Option<String>,
+    /// Copy the rendered export to the clipboard instead of writing it out
+    pub clipboard: bool,
+}
+
+impl ExportOptions {
+    /// Settings recorded in an export's provenance (the master password is left out)
+    pub fn settings(&self, data_type: &str) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("data_type".to_string(), data_type.to_string()),
+            ("format".to_string(), self.format.extension().to_string()),
+            ("aliases".to_string(), self.include_aliases.to_string()),
+            (
+                "reading_list".to_string(),
+                self.include_reading_list.to_string(),
+            ),
+            ("passwords".to_string(), self.include_passwords.to_string()),
+            (
+                "fields".to_string(),
+                self.fields
+                    .as_ref()
+                    .map(|f| f.names().join(","))
+                    .unwrap_or_else(|| "all".to_string()),
+            ),
+            (
+                "anonymized".to_string(),
+                self.anonymize.is_some().to_string(),
+            ),
+        ])
+    }
+}
+
 /// Load bookmark and history data directly from browser databases (in-memory, no file I/O)
 pub fn load_browser_data(
     browser_name: &str,
@@ -91,7 +296,7 @@ pub fn load_browser_data(
                 }
             }
             if want_history {
-                match extract_history(&browser, profile_path) {
+                match extract_history(&browser, profile_path, None) {
                     Ok(Some(h)) => all_history.extend(h.urls),
                     Ok(None) => {}
                     Err(e) => log::debug!("Failed to extract history from {}: {}", name, e),
@@ -109,8 +314,125 @@ pub fn export_data(
     output_file: Option<PathBuf>,
     profile_dir: Option<PathBuf>,
 ) -> Result<()> {
+    export_data_with_options(
+        browser_name,
+        data_type,
+        output_file,
+        profile_dir,
+        &ExportOptions::default(),
+    )
+}
+
+pub fn export_data_with_options(
+    browser_name: &str,
+    data_type: &str,
+    output_file: Option<PathBuf>,
+    profile_dir: Option<PathBuf>,
+    options: &ExportOptions,
+) -> Result<()> {
+    if options.format.bookmarks_only() && !matches!(data_type, "bookmarks" | "all") {
+        return Err(anyhow!(
+            "{:?} export only supports bookmarks, not {}",
+            options.format,
+            data_type
+        ));
+    }
+
+    if options.include_passwords && data_type == "all" && !options.format.holds_passwords() {
+        return Err(anyhow!(
+            "{:?} export cannot hold passwords; use yaml, json or jsonl, or leave out \
+             --i-understand-the-risk",
+            options.format
+        ));
+    }
+
+    if options.format == ExportFormat::Sqlite {
+        if data_type == "passwords" {
+            return Err(anyhow!("SQLite export does not store passwords"));
+        }
+        let path = output_file
+            .filter(|path| !utils::is_stdout(path) && !options.clipboard)
+            .ok_or_else(|| anyhow!("SQLite export needs an output file (--output)"))?;
+        let all_data =
+            collect_browser_data(browser_name, data_type, profile_dir.as_deref(), options)?;
+        write_sqlite(&all_data, &path)?;
+        println!("Data exported to {}", path.display());
+        return Ok(());
+    }
+
+    let all_data = collect_browser_data(browser_name, data_type, profile_dir.as_deref(), options)?;
+    let content = render_export_fields(&all_data, options.format, options.fields.as_ref())?;
+    let has_passwords = all_data
+        .iter()
+        .any(|data| data.passwords.as_ref().is_some_and(|p| !p.is_empty()));
+
+    match output_file {
+        _ if options.clipboard => {
+            utils::copy_to_clipboard(&content)?;
+            eprintln!("Copied {} bytes to the clipboard", content.len());
+        }
+        Some(path) if !utils::is_stdout(&path) => {
+            if has_passwords {
+                write_private(&path, &content)?;
+                eprintln!(
+                    "Warning: {} holds passwords in cleartext; keep it private and delete it \
+                     when done",
+                    path.display()
+                );
+            } else {
+                fs::write(&path, content)?;
+            }
+            println!("Data exported to {}", path.display());
+        }
+        _ => {
+            println!("{}", content);
+        }
+    }
+
+    Ok(())
+}
+
+/// Write `content` so only its owner can read it, for exports holding
+/// cleartext passwords
+fn write_private(path: &Path, content: &str) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // `mode` only applies to a new file; an existing one is tightened too
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+/// Extract per-profile browser data in memory, without writing an export file
+pub fn collect_browser_data(
+    browser_name: &str,
+    data_type: &str,
+    profile_dir: Option<&std::path::Path>,
+    options: &ExportOptions,
+) -> Result<Vec<BrowserData>> {
+    collect_browser_data_with_progress(browser_name, data_type, profile_dir, options, |_| {})
+}
+
+/// [`collect_browser_data`], reporting each profile as it is read
+pub fn collect_browser_data_with_progress(
+    browser_name: &str,
+    data_type: &str,
+    profile_dir: Option<&std::path::Path>,
+    options: &ExportOptions,
+    on_progress: impl Fn(ProgressEvent),
+) -> Result<Vec<BrowserData>> {
     let browser = Browser::from_str(browser_name)?;
-    let profiles = browser.find_profiles(profile_dir.as_deref())?;
+    let profiles = browser.find_profiles(profile_dir)?;
 
     if profiles.is_empty() {
         return Err(anyhow!("No profiles found for {}", browser_name));
@@ -119,55 +441,425 @@ pub fn export_data(
     let mut all_data = Vec::new();
 
     for profile_path in profiles {
-        let profile_name = profile_path
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-
-        let mut browser_data = BrowserData {
-            browser: browser_name.to_string(),
-            profile: profile_name,
-            export_date: Utc::now(),
-            bookmarks: None,
-            history: None,
-            passwords: None,
-        };
+        let mut browser_data = empty_profile_data(&browser, browser_name, &profile_path);
+        let source = format!("{}/{}", browser_name, browser_data.profile);
+        on_progress(ProgressEvent::SourceStarted {
+            source: source.clone(),
+        });
 
         match data_type {
             "bookmarks" => {
                 browser_data.bookmarks = extract_bookmarks(&browser, &profile_path)?;
             }
             "history" => {
-                browser_data.history = extract_history(&browser, &profile_path)?;
+                browser_data.history = extract_history(&browser, &profile_path, None)?;
             }
             "passwords" => {
-                browser_data.passwords = extract_passwords(&browser, &profile_path)?;
+                if !options.include_passwords {
+                    return Err(anyhow!(
+                        "Password export writes plaintext credentials; \
+                         re-run with --i-understand-the-risk to proceed"
+                    ));
+                }
+                browser_data.passwords = extract_passwords(&browser, &profile_path, options)?;
             }
             "all" => {
                 browser_data.bookmarks = extract_bookmarks(&browser, &profile_path)?;
-                browser_data.history = extract_history(&browser, &profile_path)?;
-                browser_data.passwords = extract_passwords(&browser, &profile_path)?;
+                browser_data.history = extract_history(&browser, &profile_path, None)?;
+                if options.include_passwords {
+                    browser_data.passwords = extract_passwords(&browser, &profile_path, options)?;
+                }
             }
             _ => return Err(anyhow!("Invalid data type: {}", data_type)),
         }
 
+        if options.include_reading_list
+            && browser_data.bookmarks.is_some()
+            && let Some(entries) = extract_reading_list(&browser, &profile_path)?
+        {
+            browser_data
+                .bookmarks
+                .get_or_insert_with(Vec::new)
+                .extend(entries);
+        }
+
+        let rows = [
+            (
+                "bookmarks",
+                browser_data.bookmarks.as_deref().map(count_urls),
+            ),
+            (
+                "history",
+                browser_data.history.as_ref().map(|h| h.urls.len()),
+            ),
+            ("passwords", browser_data.passwords.as_ref().map(Vec::len)),
+        ];
+        for (kind, count) in rows {
+            if let Some(count) = count {
+                on_progress(ProgressEvent::RowsRead {
+                    source: source.clone(),
+                    kind,
+                    count,
+                });
+            }
+        }
+
+        if options.include_aliases {
+            browser_data.aliases = browser_data.bookmarks.as_deref().map(collect_aliases);
+        }
+
+        if let Some(anonymizer) = &options.anonymize {
+            anonymizer.apply(&mut browser_data);
+        }
+        browser_data.provenance = Some(
+            Provenance::new("export", options.settings(data_type))
+                .with_browser_version(browser.version(&profile_path))
+                .describe(&browser_data),
+        );
         all_data.push(browser_data);
     }
 
-    let yaml_content = serde_yaml::to_string(&all_data)?;
+    Ok(all_data)
+}
 
-    match output_file {
-        Some(path) => {
-            fs::write(&path, yaml_content)?;
-            println!("Data exported to {}", path.display());
+/// Bookmarks with a URL, nested ones included
+fn count_urls(bookmarks: &[Bookmark]) -> usize {
+    bookmarks
+        .iter()
+        .map(|b| usize::from(b.url.is_some()) + b.children.as_deref().map_or(0, count_urls))
+        .sum()
+}
+
+/// Write `bookmarks` into a browser profile (see [`apply_to_firefox`] and
+/// [`apply_to_chrome`]); Safari profiles are read-only
+pub fn apply_to_browser(
+    browser: &Browser,
+    profile_path: &std::path::Path,
+    bookmarks: &[Bookmark],
+    options: &ApplyOptions,
+) -> Result<ApplySummary> {
+    match browser {
+        Browser::Firefox => apply_to_firefox(profile_path, bookmarks, options),
+        Browser::Chrome | Browser::Edge => apply_to_chrome(profile_path, bookmarks, options),
+        Browser::Safari => Err(anyhow!("Writing bookmarks to Safari is not supported")),
+    }
+}
+
+/// Read one profile's bookmarks and/or history (`data_type` bookmarks,
+/// history or both) for the store. With `history_since`, only URLs visited
+/// after it are read, so repeat syncs skip history that hasn't changed.
+pub fn read_profile(
+    browser_name: &str,
+    profile_path: &std::path::Path,
+    data_type: &str,
+    history_since: Option<DateTime<Utc>>,
+) -> Result<BrowserData> {
+    let browser = Browser::from_str(browser_name)?;
+    let mut browser_data = empty_profile_data(&browser, browser_name, profile_path);
+    if matches!(data_type, "bookmarks" | "both") {
+        browser_data.bookmarks = extract_bookmarks(&browser, profile_path)?;
+    }
+    if matches!(data_type, "history" | "both") {
+        browser_data.history = extract_history(&browser, profile_path, history_since)?;
+    }
+
+    let mut settings = BTreeMap::from([("data_type".to_string(), data_type.to_string())]);
+    if let Some(since) = history_since {
+        settings.insert("history_since".to_string(), since.to_rfc3339());
+    }
+    browser_data.provenance = Some(
+        Provenance::new("sync", settings)
+            .with_browser_version(browser.version(profile_path))
+            .describe(&browser_data),
+    );
+    Ok(browser_data)
+}
+
+/// A profile's `BrowserData` with identity filled in and no sections yet
+fn empty_profile_data(
+    browser: &Browser,
+    browser_name: &str,
+    profile_path: &std::path::Path,
+) -> BrowserData {
+    let profile_name = profile_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let info = browser.profile_info(profile_path);
+    BrowserData {
+        browser: browser_name.to_string(),
+        profile_name: info.display_name.filter(|name| *name != profile_name),
+        profile: profile_name,
+        export_date: Utc::now(),
+        bookmarks: None,
+        history: None,
+        passwords: None,
+        aliases: None,
+        profile_email: info.email,
+        provenance: None,
+    }
+}
+
+/// Serialize exported data in the given format, with its checksum trailer
+pub fn render_export(data: &[BrowserData], format: ExportFormat) -> Result<String> {
+    render_export_fields(data, format, None)
+}
+
+/// Like [`render_export`], keeping only the selected bookmark fields
+pub fn render_export_fields(
+    data: &[BrowserData],
+    format: ExportFormat,
+    fields: Option<&FieldSelection>,
+) -> Result<String> {
+    let content = match (format, fields) {
+        (ExportFormat::Yaml, None) => serde_yaml::to_string(data)?,
+        (ExportFormat::Yaml, Some(fields)) => serde_yaml::to_string(&fields.project(data)?)?,
+        (ExportFormat::Json, None) => serde_json::to_string_pretty(data)?,
+        (ExportFormat::Json, Some(fields)) => serde_json::to_string_pretty(&fields.project(data)?)?,
+        (ExportFormat::Csv, _) => bookmarks_to_csv(data, fields)?,
+        (ExportFormat::Jsonl, _) => to_json_lines(data, fields)?,
+        (
+            ExportFormat::Org | ExportFormat::Opml | ExportFormat::Sqlite | ExportFormat::Bibtex,
+            Some(_),
+        ) => {
+            return Err(anyhow!("{:?} export does not support --fields", format));
+        }
+        (ExportFormat::Org, None) => org::to_org(data),
+        (ExportFormat::Opml, None) => opml::to_opml(data),
+        (ExportFormat::Bibtex, None) => bibtex::to_bibtex(data),
+        (ExportFormat::Sqlite, None) => {
+            return Err(anyhow!(
+                "SQLite exports are written to a file; use write_sqlite"
+            ));
+        }
+    };
+    Ok(with_checksum(content, format))
+}
+
+/// One JSON Lines record: an exported item tagged with its `type` and source
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    browser: &'a str,
+    profile: &'a str,
+    #[serde(flatten)]
+    item: JsonLineItem<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonLineItem<'a> {
+    Provenance(&'a Provenance),
+    Bookmark(serde_json::Value),
+    History(&'a UrlEntry),
+    Password(&'a Password),
+}
+
+/// Render one JSON object per line so large exports can be read incrementally.
+/// Each profile starts with its `provenance` line, if any, and nested
+/// bookmarks are flattened so every line is a single bookmark. `fields`, when
+/// given, limits the keys written for each bookmark.
+pub fn to_json_lines(data: &[BrowserData], fields: Option<&FieldSelection>) -> Result<String> {
+    let mut out = String::new();
+    let mut push = |browser_data: &BrowserData, item: JsonLineItem| -> Result<()> {
+        out.push_str(&serde_json::to_string(&JsonLine {
+            browser: &browser_data.browser,
+            profile: &browser_data.profile,
+            item,
+        })?);
+        out.push('\n');
+        Ok(())
+    };
+    for browser_data in data {
+        if let Some(provenance) = &browser_data.provenance {
+            push(browser_data, JsonLineItem::Provenance(provenance))?;
         }
-        None => {
-            println!("{}", yaml_content);
+        let bookmarks = flatten_bookmarks(browser_data.bookmarks.as_deref().unwrap_or_default());
+        for bookmark in &bookmarks {
+            let value = match fields {
+                Some(fields) => fields.project_bookmark(bookmark)?,
+                None => serde_json::to_value(bookmark)?,
+            };
+            push(browser_data, JsonLineItem::Bookmark(value))?;
+        }
+        for entry in browser_data.history.iter().flat_map(|h| &h.urls) {
+            push(browser_data, JsonLineItem::History(entry))?;
+        }
+        for password in browser_data.passwords.iter().flatten() {
+            push(browser_data, JsonLineItem::Password(password))?;
         }
     }
+    Ok(out)
+}
 
-    Ok(())
+/// Render bookmarks as CSV with `id,title,url,folder,date_added,browser,profile`
+/// columns; dates are RFC 3339 and missing values are empty cells. `fields`,
+/// when given, limits the bookmark columns (`browser`/`profile` always stay).
+pub fn bookmarks_to_csv(data: &[BrowserData], fields: Option<&FieldSelection>) -> Result<String> {
+    const BOOKMARK_COLUMNS: [&str; 5] = ["id", "title", "url", "folder", "date_added"];
+    let keep: Vec<bool> = BOOKMARK_COLUMNS
+        .iter()
+        .map(|c| fields.is_none_or(|f| f.contains(c)))
+        .collect();
+    let select = |row: [String; 5]| {
+        row.into_iter()
+            .zip(&keep)
+            .filter(|(_, keep)| **keep)
+            .map(|(cell, _)| cell)
+    };
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(
+        select(BOOKMARK_COLUMNS.map(str::to_string)).chain(["browser".into(), "profile".into()]),
+    )?;
+    for browser_data in data {
+        for bookmark in flatten_bookmarks(browser_data.bookmarks.as_deref().unwrap_or_default()) {
+            let row = [
+                bookmark.id,
+                bookmark.title,
+                bookmark.url.unwrap_or_default(),
+                bookmark.folder.unwrap_or_default(),
+                bookmark
+                    .date_added
+                    .map(|d| d.to_rfc3339())
+                    .unwrap_or_default(),
+            ];
+            writer.write_record(
+                select(row).chain([browser_data.browser.clone(), browser_data.profile.clone()]),
+            )?;
+        }
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// Build a keyword -> URL map from bookmarks that carry a keyword shortcut
+pub fn collect_aliases(bookmarks: &[Bookmark]) -> BTreeMap<String, String> {
+    bookmarks
+        .iter()
+        .filter_map(|b| Some((b.keyword.clone()?, b.url.clone()?)))
+        .collect()
+}
+
+/// Separator used for folder paths when converting between flat and nested bookmarks
+pub const FOLDER_PATH_SEPARATOR: &str = "/";
+
+impl Bookmark {
+    /// Folder nodes carry children and no URL
+    pub fn is_folder(&self) -> bool {
+        self.url.is_none() && self.children.is_some()
+    }
+}
+
+/// Whether `name` is a browser's root folder (toolbar, menu, Other
+/// bookmarks, ...) rather than one the user made
+pub fn is_root_folder(name: &str) -> bool {
+    let name = name.to_lowercase();
+    chrome_apply::ROOT_ALIASES
+        .iter()
+        .any(|(alias, _)| *alias == name)
+}
+
+/// Flatten nested bookmarks into a list of leaves whose `folder` is the
+/// `/`-joined path of the folder nodes above them.
+///
+/// Folder nodes themselves are dropped. Top-level entries keep their own
+/// `folder`, so flat input passes through unchanged.
+pub fn flatten_bookmarks(bookmarks: &[Bookmark]) -> Vec<Bookmark> {
+    fn walk(nodes: &[Bookmark], path: Option<&str>, out: &mut Vec<Bookmark>) {
+        for node in nodes {
+            let folder = path.map(str::to_string).or_else(|| node.folder.clone());
+            if let Some(children) = &node.children {
+                let child_path = match &folder {
+                    Some(parent) => format!("{}{}{}", parent, FOLDER_PATH_SEPARATOR, node.title),
+                    None => node.title.clone(),
+                };
+                walk(children, Some(&child_path), out);
+            }
+            if !node.is_folder() {
+                out.push(Bookmark {
+                    folder,
+                    children: None,
+                    ..node.clone()
+                });
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(bookmarks, None, &mut out);
+    out
+}
+
+/// Rebuild a folder tree from flat bookmarks, the inverse of [`flatten_bookmarks`].
+///
+/// Each distinct path segment becomes a folder node (id `folder:<path>`, no
+/// URL) whose `folder` is its parent path. Folders and bookmarks keep their
+/// first-seen order; bookmarks without a folder stay at the top level.
+pub fn bookmarks_to_tree(bookmarks: &[Bookmark]) -> Vec<Bookmark> {
+    #[derive(Default)]
+    struct Node {
+        entries: Vec<Entry>,
+    }
+    enum Entry {
+        Folder(String, Node),
+        Bookmark(Box<Bookmark>),
+    }
+
+    fn folder_mut<'a>(node: &'a mut Node, name: &str) -> &'a mut Node {
+        let index = node
+            .entries
+            .iter()
+            .position(|e| matches!(e, Entry::Folder(n, _) if n == name))
+            .unwrap_or_else(|| {
+                node.entries
+                    .push(Entry::Folder(name.to_string(), Node::default()));
+                node.entries.len() - 1
+            });
+        match &mut node.entries[index] {
+            Entry::Folder(_, child) => child,
+            Entry::Bookmark(_) => unreachable!(),
+        }
+    }
+
+    fn build(node: Node, path: Option<&str>) -> Vec<Bookmark> {
+        node.entries
+            .into_iter()
+            .map(|entry| match entry {
+                Entry::Bookmark(bookmark) => *bookmark,
+                Entry::Folder(name, child) => {
+                    let child_path = match path {
+                        Some(parent) => format!("{}{}{}", parent, FOLDER_PATH_SEPARATOR, name),
+                        None => name.clone(),
+                    };
+                    Bookmark {
+                        id: format!("folder:{}", child_path),
+                        title: name,
+                        url: None,
+                        folder: path.map(str::to_string),
+                        date_added: None,
+                        children: Some(build(child, Some(&child_path))),
+                        ..Default::default()
+                    }
+                }
+            })
+            .collect()
+    }
+
+    let mut root = Node::default();
+    for bookmark in flatten_bookmarks(bookmarks) {
+        let mut node = &mut root;
+        for segment in bookmark
+            .folder
+            .iter()
+            .flat_map(|f| f.split(FOLDER_PATH_SEPARATOR))
+            .filter(|s| !s.is_empty())
+        {
+            node = folder_mut(node, segment);
+        }
+        node.entries.push(Entry::Bookmark(Box::new(bookmark)));
+    }
+    build(root, None)
 }
 
 // --- Browser dispatch ---
@@ -183,13 +875,24 @@ fn extract_bookmarks(
     }
 }
 
+fn extract_reading_list(
+    browser: &Browser,
+    profile_path: &std::path::Path,
+) -> Result<Option<Vec<Bookmark>>> {
+    match browser {
+        Browser::Chrome | Browser::Edge => chrome::extract_reading_list(profile_path),
+        Browser::Firefox | Browser::Safari => Ok(None),
+    }
+}
+
 fn extract_history(
     browser: &Browser,
     profile_path: &std::path::Path,
+    since: Option<DateTime<Utc>>,
 ) -> Result<Option<HistoryEntry>> {
     match browser {
-        Browser::Chrome | Browser::Edge => chrome::extract_history(profile_path),
-        Browser::Firefox => firefox::extract_history(profile_path),
+        Browser::Chrome | Browser::Edge => chrome::extract_history(profile_path, since),
+        Browser::Firefox => firefox::extract_history(profile_path, since),
         Browser::Safari => safari::extract_history(profile_path),
     }
 }
@@ -197,26 +900,194 @@ fn extract_history(
 fn extract_passwords(
     browser: &Browser,
     profile_path: &std::path::Path,
+    options: &ExportOptions,
 ) -> Result<Option<Vec<Password>>> {
     match browser {
         Browser::Chrome | Browser::Edge => {
-            let login_data_path = profile_path.join("Login Data");
-            if !login_data_path.exists() {
+            if !profile_path.join("Login Data").exists() {
                 return Ok(None);
             }
-            Ok(None)
+            let safe_storage = match browser {
+                Browser::Edge => "Microsoft Edge",
+                _ => "Chrome",
+            };
+            let key = os_crypt::load_master_key(safe_storage, profile_path)?;
+            chrome::extract_passwords(profile_path, &key)
         }
         Browser::Firefox => {
-            let signons_path = profile_path.join("signons.sqlite");
-            if !signons_path.exists() {
-                let key4_path = profile_path.join("key4.db");
-                if !key4_path.exists() {
-                    return Ok(None);
-                }
-                return Ok(None);
-            }
-            Ok(None)
+            firefox::extract_passwords(profile_path, options.master_password.as_deref())
         }
         Browser::Safari => Ok(None),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(id: &str, folder: Option<&str>) -> Bookmark {
+        Bookmark {
+            id: id.to_string(),
+            title: format!("Bookmark {}", id),
+            url: Some(format!("https://example.com/{}", id)),
+            folder: folder.map(str::to_string),
+            date_added: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bookmarks_to_csv() {
+        let data = BrowserData {
+            browser: "chrome".to_string(),
+            profile: "Default".to_string(),
+            export_date: Utc::now(),
+            bookmarks: Some(vec![
+                Bookmark {
+                    title: "Hello, \"world\"".to_string(),
+                    date_added: DateTime::from_timestamp(1609459200, 0),
+                    ..bookmark("1", Some("Dev"))
+                },
+                bookmark("2", None),
+            ]),
+            history: None,
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        };
+        let fields: FieldSelection = "title,url".parse().unwrap();
+        let narrow = bookmarks_to_csv(std::slice::from_ref(&data), Some(&fields)).unwrap();
+        assert_eq!(
+            narrow.lines().nth(2),
+            Some("Bookmark 2,https://example.com/2,chrome,Default")
+        );
+
+        let csv = bookmarks_to_csv(&[data], None).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "id,title,url,folder,date_added,browser,profile");
+        assert_eq!(
+            lines[1],
+            "1,\"Hello, \"\"world\"\"\",https://example.com/1,Dev,2021-01-01T00:00:00+00:00,chrome,Default"
+        );
+        assert_eq!(
+            lines[2],
+            "2,Bookmark 2,https://example.com/2,,,chrome,Default"
+        );
+    }
+
+    #[test]
+    fn test_to_json_lines() {
+        let data = BrowserData {
+            browser: "firefox".to_string(),
+            profile: "default".to_string(),
+            export_date: Utc::now(),
+            bookmarks: Some(vec![bookmark("1", Some("Dev")), bookmark("2", None)]),
+            history: Some(HistoryEntry {
+                urls: vec![UrlEntry {
+                    url: "https://example.com/h".to_string(),
+                    title: "Visited".to_string(),
+                    visit_count: 3,
+                    last_visit: None,
+                }],
+            }),
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        };
+        let jsonl = to_json_lines(&[data], None).unwrap();
+        let lines: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["type"], "bookmark");
+        assert_eq!(lines[0]["browser"], "firefox");
+        assert_eq!(lines[0]["folder"], "Dev");
+        assert_eq!(lines[1]["id"], "2");
+        assert_eq!(lines[2]["type"], "history");
+        assert_eq!(lines[2]["visit_count"], 3);
+    }
+
+    #[test]
+    fn test_tree_round_trip() {
+        let flat = vec![
+            bookmark("1", Some("Dev/Rust")),
+            bookmark("2", None),
+            bookmark("3", Some("Dev")),
+            bookmark("4", Some("Dev/Rust")),
+        ];
+
+        let tree = bookmarks_to_tree(&flat);
+        assert_eq!(tree.len(), 2);
+        assert!(tree[0].is_folder());
+        assert_eq!(tree[0].title, "Dev");
+        let dev = tree[0].children.as_ref().unwrap();
+        assert_eq!(dev[0].id, "folder:Dev/Rust");
+        assert_eq!(dev[0].folder.as_deref(), Some("Dev"));
+        assert_eq!(dev[0].children.as_ref().unwrap().len(), 2);
+        assert_eq!(dev[1].id, "3");
+        assert_eq!(tree[1].id, "2");
+
+        let ids: Vec<_> = flatten_bookmarks(&tree)
+            .into_iter()
+            .map(|b| (b.id, b.folder))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("1".to_string(), Some("Dev/Rust".to_string())),
+                ("4".to_string(), Some("Dev/Rust".to_string())),
+                ("3".to_string(), Some("Dev".to_string())),
+                ("2".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flatten_nested_under_existing_folder() {
+        let nested = Bookmark {
+            id: "f".to_string(),
+            title: "Reading".to_string(),
+            url: None,
+            folder: Some("Bar".to_string()),
+            date_added: None,
+            children: Some(vec![bookmark("1", Some("ignored"))]),
+            ..Default::default()
+        };
+        let flat = flatten_bookmarks(&[nested]);
+        assert_eq!(flat.len(), 1);
+        assert_eq!(flat[0].folder.as_deref(), Some("Bar/Reading"));
+        assert!(flat[0].children.is_none());
+    }
+
+    #[test]
+    fn test_passwords_need_a_format_that_holds_them() {
+        let options = ExportOptions {
+            format: ExportFormat::Csv,
+            include_passwords: true,
+            ..Default::default()
+        };
+        let err = export_data_with_options("chrome", "all", None, None, &options).unwrap_err();
+        assert!(err.to_string().contains("cannot hold passwords"), "{}", err);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("passwords.yaml");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private(&path, "secret").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "secret");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,414 @@
+//! Firefox (NSS) saved-login decryption using `key4.db`.
+//!
+//! `key4.db` holds a global salt plus a PBE-encrypted "password-check" value in
+//! `metaData`, and the PBE-encrypted master key in `nssPrivate.a11`. Both use
+//! either PBES2 (PBKDF2-SHA256 + AES-256-CBC, Firefox 75+) or the legacy
+//! `pbeWithSha1AndTripleDES-CBC` scheme. The master key then decrypts the
+//! base64 DER blobs in `logins.json` with 3DES-CBC or AES-256-CBC.
+
+use aes::cipher::{BlockDecryptMut, KeyIvInit, block_padding::Pkcs7};
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha1::{Digest, Sha1};
+use std::path::Path;
+
+use super::snapshot::Snapshot;
+
+/// Returned when `key4.db` is protected by a primary (master) password that
+/// was not supplied or did not match
+#[derive(Debug, thiserror::Error)]
+#[error("Firefox profile is protected by a primary password")]
+pub struct MasterPasswordRequired;
+
+const OID_PBES2: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0d];
+const OID_PBE_SHA1_3DES: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x05, 0x01, 0x03,
+];
+const OID_HMAC_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x09];
+const OID_DES_EDE3_CBC: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x03, 0x07];
+const OID_AES256_CBC: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a];
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+
+const PASSWORD_CHECK: &[u8] = b"password-check";
+
+/// Unlock `key4.db` and return the raw master key used for `logins.json`
+pub fn load_master_key(key4_path: &Path, master_password: &str) -> Result<Vec<u8>> {
+    let snapshot = Snapshot::create(key4_path)?;
+    let conn = snapshot.open()?;
+
+    let (global_salt, check): (Vec<u8>, Vec<u8>) = conn.query_row(
+        "SELECT item1, item2 FROM metaData WHERE id = 'password'",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let checked = decrypt_pbe(&check, &global_salt, master_password.as_bytes())
+        .map_err(|_| MasterPasswordRequired)?;
+    if !checked.starts_with(PASSWORD_CHECK) {
+        return Err(MasterPasswordRequired.into());
+    }
+
+    let mut stmt = conn.prepare("SELECT a11 FROM nssPrivate WHERE a11 IS NOT NULL")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let a11: Vec<u8> = row.get(0)?;
+        if let Ok(key) = decrypt_pbe(&a11, &global_salt, master_password.as_bytes()) {
+            return Ok(key);
+        }
+    }
+
+    Err(anyhow!(
+        "No usable master key found in {}",
+        key4_path.display()
+    ))
+}
+
+/// Decrypt one base64 `encryptedUsername`/`encryptedPassword` value from `logins.json`
+pub fn decrypt_login_field(master_key: &[u8], encoded: &str) -> Result<String> {
+    let blob = base64::engine::general_purpose::STANDARD.decode(encoded)?;
+
+    // SEQUENCE { OCTET STRING keyId, SEQUENCE { OID cipher, OCTET STRING iv }, OCTET STRING data }
+    let fields = sequence(&blob)?;
+    let [
+        (_, _key_id),
+        (TAG_SEQUENCE, algorithm),
+        (TAG_OCTET_STRING, ciphertext),
+    ] = fields[..]
+    else {
+        return Err(anyhow!("Unexpected login field structure"));
+    };
+    let algorithm = children(algorithm)?;
+    let [(TAG_OID, oid), (TAG_OCTET_STRING, iv)] = algorithm[..] else {
+        return Err(anyhow!("Unexpected login cipher parameters"));
+    };
+
+    let plaintext = match oid {
+        OID_DES_EDE3_CBC => decrypt_3des(key_prefix(master_key, 24)?, iv, ciphertext)?,
+        OID_AES256_CBC => decrypt_aes256(key_prefix(master_key, 32)?, iv, ciphertext)?,
+        _ => return Err(anyhow!("Unsupported login cipher")),
+    };
+    Ok(String::from_utf8(plaintext)?)
+}
+
+/// Decrypt an NSS PBE blob: SEQUENCE { AlgorithmIdentifier, OCTET STRING ciphertext }
+fn decrypt_pbe(blob: &[u8], global_salt: &[u8], master_password: &[u8]) -> Result<Vec<u8>> {
+    let fields = sequence(blob)?;
+    let [(TAG_SEQUENCE, algorithm), (TAG_OCTET_STRING, ciphertext)] = fields[..] else {
+        return Err(anyhow!("Unexpected PBE structure"));
+    };
+    let algorithm = children(algorithm)?;
+    let [(TAG_OID, oid), (TAG_SEQUENCE, params)] = algorithm[..] else {
+        return Err(anyhow!("Unexpected PBE algorithm"));
+    };
+
+    match oid {
+        OID_PBES2 => decrypt_pbes2(params, global_salt, master_password, ciphertext),
+        OID_PBE_SHA1_3DES => {
+            decrypt_pbe_sha1_3des(params, global_salt, master_password, ciphertext)
+        }
+        _ => Err(anyhow!("Unsupported PBE scheme")),
+    }
+}
+
+fn decrypt_pbes2(
+    params: &[u8],
+    global_salt: &[u8],
+    master_password: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>> {
+    // SEQUENCE { SEQUENCE { OID pbkdf2, SEQUENCE { salt, iterations, keyLength, prf } },
+    //            SEQUENCE { OID aes256-cbc, OCTET STRING iv } }
+    let params = children(params)?;
+    let [(TAG_SEQUENCE, kdf), (TAG_SEQUENCE, cipher)] = params[..] else {
+        return Err(anyhow!("Unexpected PBES2 parameters"));
+    };
+    let kdf = children(kdf)?;
+    let [_, (TAG_SEQUENCE, kdf_params)] = kdf[..] else {
+        return Err(anyhow!("Unexpected PBKDF2 parameters"));
+    };
+    let kdf_params = children(kdf_params)?;
+    let (salt, iterations, prf) = match kdf_params[..] {
+        [(TAG_OCTET_STRING, salt), (TAG_INTEGER, iterations), ..] => {
+            let prf = kdf_params
+                .iter()
+                .find(|(tag, _)| *tag == TAG_SEQUENCE)
+                .map(|(_, prf)| children(prf))
+                .transpose()?;
+            (salt, integer(iterations)?, prf)
+        }
+        _ => return Err(anyhow!("Unexpected PBKDF2 parameters")),
+    };
+    let cipher = children(cipher)?;
+    let [(TAG_OID, OID_AES256_CBC), (TAG_OCTET_STRING, iv)] = cipher[..] else {
+        return Err(anyhow!("Unsupported PBES2 cipher"));
+    };
+
+    let password = Sha1::new()
+        .chain_update(global_salt)
+        .chain_update(master_password)
+        .finalize();
+    let mut key = [0u8; 32];
+    match prf.as_deref() {
+        Some([(TAG_OID, OID_HMAC_SHA256), ..]) => {
+            pbkdf2::pbkdf2_hmac::<sha2::Sha256>(&password, salt, iterations, &mut key)
+        }
+        Some(_) => return Err(anyhow!("Unsupported PBKDF2 PRF")),
+        None => pbkdf2::pbkdf2_hmac::<Sha1>(&password, salt, iterations, &mut key),
+    }
+
+    // NSS stores only the last 14 bytes of the IV; the DER header bytes complete it
+    let iv = if iv.len() == 14 {
+        [&[TAG_OCTET_STRING, 14][..], iv].concat()
+    } else {
+        iv.to_vec()
+    };
+    decrypt_aes256(&key, &iv, ciphertext)
+}
+
+fn decrypt_pbe_sha1_3des(
+    params: &[u8],
+    global_salt: &[u8],
+    master_password: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>> {
+    let params = children(params)?;
+    let [(TAG_OCTET_STRING, entry_salt), ..] = params[..] else {
+        return Err(anyhow!("Unexpected 3DES PBE parameters"));
+    };
+
+    let hp = Sha1::new()
+        .chain_update(global_salt)
+        .chain_update(master_password)
+        .finalize();
+    let mut pes = entry_salt.to_vec();
+    pes.resize(pes.len().max(20), 0);
+    let chp = Sha1::new()
+        .chain_update(hp)
+        .chain_update(entry_salt)
+        .finalize();
+
+    let hmac = |parts: &[&[u8]]| {
+        let mut mac = Hmac::<Sha1>::new_from_slice(&chp).expect("HMAC accepts any key length");
+        for part in parts {
+            mac.update(part);
+        }
+        mac.finalize().into_bytes()
+    };
+    let k1 = hmac(&[&pes, entry_salt]);
+    let tk = hmac(&[&pes]);
+    let k2 = hmac(&[&tk, entry_salt]);
+    let k = [k1.as_slice(), k2.as_slice()].concat();
+
+    decrypt_3des(&k[..24], &k[k.len() - 8..], ciphertext)
+}
+
+fn decrypt_3des(key: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    cbc::Decryptor::<des::TdesEde3>::new_from_slices(key, iv)
+        .map_err(|_| anyhow!("Invalid 3DES key or IV length"))?
+        .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+        .map_err(|_| anyhow!("Decryption failed (wrong key?)"))
+}
+
+fn decrypt_aes256(key: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    cbc::Decryptor::<aes::Aes256>::new_from_slices(key, iv)
+        .map_err(|_| anyhow!("Invalid AES key or IV length"))?
+        .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+        .map_err(|_| anyhow!("Decryption failed (wrong key?)"))
+}
+
+fn key_prefix(key: &[u8], len: usize) -> Result<&[u8]> {
+    key.get(..len)
+        .ok_or_else(|| anyhow!("Master key too short for login cipher"))
+}
+
+// --- Minimal DER reader ---
+
+/// Split one TLV off the front of `data`: (tag, content, rest)
+fn read_tlv(data: &[u8]) -> Result<(u8, &[u8], &[u8])> {
+    let truncated = || anyhow!("Truncated DER value");
+    let (&tag, data) = data.split_first().ok_or_else(truncated)?;
+    let (&first, mut data) = data.split_first().ok_or_else(truncated)?;
+    let len = if first & 0x80 == 0 {
+        first as usize
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || data.len() < n {
+            return Err(anyhow!("Unsupported DER length"));
+        }
+        let len = data[..n]
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        data = &data[n..];
+        len
+    };
+    if data.len() < len {
+        return Err(truncated());
+    }
+    Ok((tag, &data[..len], &data[len..]))
+}
+
+/// All TLVs in a run of concatenated DER values
+fn children(mut data: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    let mut items = Vec::new();
+    while !data.is_empty() {
+        let (tag, content, rest) = read_tlv(data)?;
+        items.push((tag, content));
+        data = rest;
+    }
+    Ok(items)
+}
+
+/// Children of the top-level SEQUENCE in `data`
+fn sequence(data: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    match read_tlv(data)? {
+        (TAG_SEQUENCE, content, _) => children(content),
+        _ => Err(anyhow!("Expected DER SEQUENCE")),
+    }
+}
+
+fn integer(content: &[u8]) -> Result<u32> {
+    if content.is_empty() || content.len() > 5 {
+        return Err(anyhow!("Unsupported DER integer"));
+    }
+    let value = content.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+    u32::try_from(value).map_err(|_| anyhow!("DER integer out of range"))
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use aes::cipher::BlockEncryptMut;
+
+    fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        if content.len() < 0x80 {
+            out.push(content.len() as u8);
+        } else {
+            out.extend([0x82, (content.len() >> 8) as u8, content.len() as u8]);
+        }
+        out.extend(content);
+        out
+    }
+
+    fn seq(parts: &[Vec<u8>]) -> Vec<u8> {
+        tlv(TAG_SEQUENCE, &parts.concat())
+    }
+
+    fn aes256_encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        cbc::Encryptor::<aes::Aes256>::new_from_slices(key, iv)
+            .unwrap()
+            .encrypt_padded_vec_mut::<Pkcs7>(plaintext)
+    }
+
+    /// Build a PBES2 blob the way NSS does (PBKDF2-SHA256, AES-256-CBC, 14-byte IV)
+    fn pbes2_blob(global_salt: &[u8], master_password: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let salt = [9u8; 32];
+        let iv14 = [3u8; 14];
+        let iterations = 1000u16;
+
+        let password = Sha1::new()
+            .chain_update(global_salt)
+            .chain_update(master_password)
+            .finalize();
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<sha2::Sha256>(&password, &salt, iterations as u32, &mut key);
+        let iv = [&[TAG_OCTET_STRING, 14][..], &iv14].concat();
+
+        let kdf = seq(&[
+            tlv(
+                TAG_OID,
+                &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0c],
+            ),
+            seq(&[
+                tlv(TAG_OCTET_STRING, &salt),
+                tlv(TAG_INTEGER, &iterations.to_be_bytes()),
+                tlv(TAG_INTEGER, &[32]),
+                seq(&[tlv(TAG_OID, OID_HMAC_SHA256)]),
+            ]),
+        ]);
+        let cipher = seq(&[tlv(TAG_OID, OID_AES256_CBC), tlv(TAG_OCTET_STRING, &iv14)]);
+        seq(&[
+            seq(&[tlv(TAG_OID, OID_PBES2), seq(&[kdf, cipher])]),
+            tlv(TAG_OCTET_STRING, &aes256_encrypt(&key, &iv, plaintext)),
+        ])
+    }
+
+    /// Base64 `logins.json` field encrypted with an AES-256 master key
+    pub(crate) fn login_field(master_key: &[u8], value: &str) -> String {
+        let iv = [5u8; 16];
+        let blob = seq(&[
+            tlv(TAG_OCTET_STRING, &[0xf8; 16]),
+            seq(&[tlv(TAG_OID, OID_AES256_CBC), tlv(TAG_OCTET_STRING, &iv)]),
+            tlv(
+                TAG_OCTET_STRING,
+                &aes256_encrypt(master_key, &iv, value.as_bytes()),
+            ),
+        ]);
+        base64::engine::general_purpose::STANDARD.encode(blob)
+    }
+
+    /// Write a key4.db protected by `master_password` holding `master_key`
+    pub(crate) fn create_key4_db(path: &Path, master_password: &str, master_key: &[u8]) {
+        let global_salt = [1u8; 20];
+        let conn = rusqlite::Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE metaData (id PRIMARY KEY UNIQUE ON CONFLICT REPLACE, item1, item2);
+             CREATE TABLE nssPrivate (id PRIMARY KEY UNIQUE ON CONFLICT ABORT, a11, a102);",
+        )
+        .unwrap();
+        let check = pbes2_blob(
+            &global_salt,
+            master_password.as_bytes(),
+            b"password-check\x02\x02",
+        );
+        conn.execute(
+            "INSERT INTO metaData VALUES ('password', ?1, ?2)",
+            rusqlite::params![global_salt.to_vec(), check],
+        )
+        .unwrap();
+        let a11 = pbes2_blob(&global_salt, master_password.as_bytes(), master_key);
+        conn.execute(
+            "INSERT INTO nssPrivate VALUES (1, ?1, ?2)",
+            rusqlite::params![a11, vec![0xf8u8; 16]],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_master_key_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let key4 = dir.path().join("key4.db");
+        let master_key = [42u8; 32];
+        create_key4_db(&key4, "", &master_key);
+
+        let key = load_master_key(&key4, "").unwrap();
+        assert_eq!(key, master_key);
+
+        let field = login_field(&key, "alice");
+        assert_eq!(decrypt_login_field(&key, &field).unwrap(), "alice");
+    }
+
+    #[test]
+    fn test_primary_password_required() {
+        let dir = tempfile::tempdir().unwrap();
+        let key4 = dir.path().join("key4.db");
+        create_key4_db(&key4, "s3cret", &[42u8; 32]);
+
+        let err = load_master_key(&key4, "").unwrap_err();
+        assert!(err.downcast_ref::<MasterPasswordRequired>().is_some());
+        assert!(load_master_key(&key4, "s3cret").is_ok());
+    }
+
+    #[test]
+    fn test_der_length_forms() {
+        let long = tlv(TAG_OCTET_STRING, &[0u8; 300]);
+        let (tag, content, rest) = read_tlv(&long).unwrap();
+        assert_eq!((tag, content.len(), rest.len()), (TAG_OCTET_STRING, 300, 0));
+        assert!(read_tlv(&[TAG_SEQUENCE, 5, 0]).is_err());
+    }
+}
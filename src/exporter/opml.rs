@@ -0,0 +1,131 @@
+//! OPML export.
+//!
+//! Produces an OPML 2.0 outline: one outline per profile, nested outlines per
+//! folder, and `type="link"` outlines carrying each bookmark's `url` (and
+//! `created` date), which outliners and RSS readers can import.
+
+use chrono::{DateTime, Utc};
+use quick_xml::escape::escape;
+use std::fmt::Write;
+
+use super::{Bookmark, BrowserData, bookmarks_to_tree};
+
+/// Render the folder tree of each profile as an OPML document
+pub fn to_opml(data: &[BrowserData]) -> String {
+    let mut out =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    let _ = writeln!(
+        out,
+        "  <head>\n    <title>Bookmarks</title>\n    <dateCreated>{}</dateCreated>\n  </head>\n  <body>",
+        rfc822(Utc::now())
+    );
+    for browser_data in data {
+        let profile = browser_data
+            .profile_name
+            .as_deref()
+            .unwrap_or(&browser_data.profile);
+        let _ = writeln!(
+            out,
+            "    <outline text=\"{}\">",
+            escape(format!("{} ({})", browser_data.browser, profile).as_str())
+        );
+        let tree = bookmarks_to_tree(browser_data.bookmarks.as_deref().unwrap_or_default());
+        write_outlines(&mut out, &tree, 3);
+        out.push_str("    </outline>\n");
+    }
+    out.push_str("  </body>\n</opml>\n");
+    out
+}
+
+fn write_outlines(out: &mut String, nodes: &[Bookmark], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for node in nodes {
+        if let Some(children) = node.children.as_ref().filter(|_| node.is_folder()) {
+            let _ = writeln!(
+                out,
+                "{}<outline text=\"{}\">",
+                indent,
+                escape(node.title.as_str())
+            );
+            write_outlines(out, children, depth + 1);
+            let _ = writeln!(out, "{}</outline>", indent);
+            continue;
+        }
+
+        let Some(url) = &node.url else {
+            continue;
+        };
+        let title = if node.title.trim().is_empty() {
+            url
+        } else {
+            &node.title
+        };
+        let _ = write!(
+            out,
+            "{}<outline text=\"{}\" type=\"link\" url=\"{}\"",
+            indent,
+            escape(title.as_str()),
+            escape(url.as_str())
+        );
+        if let Some(date) = node.date_added {
+            let _ = write!(out, " created=\"{}\"", rfc822(date));
+        }
+        out.push_str("/>\n");
+    }
+}
+
+/// OPML dates use RFC 822
+fn rfc822(date: DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_opml() {
+        let bookmark = |id: &str, title: &str, folder: Option<&str>| Bookmark {
+            id: id.to_string(),
+            title: title.to_string(),
+            url: Some(format!("https://example.com/?id={}&x=1", id)),
+            folder: folder.map(str::to_string),
+            date_added: DateTime::from_timestamp(1609459200, 0),
+            ..Default::default()
+        };
+        let data = BrowserData {
+            browser: "chrome".to_string(),
+            profile: "Default".to_string(),
+            export_date: Utc::now(),
+            bookmarks: Some(vec![
+                bookmark("1", "Rust & \"Friends\"", Some("bar/Dev")),
+                bookmark("2", "Top", None),
+            ]),
+            history: None,
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        };
+
+        let opml = to_opml(&[data]);
+        assert!(
+            opml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">")
+        );
+        let body = opml.split_once("<body>\n").unwrap().1;
+        assert_eq!(
+            body,
+            "    <outline text=\"chrome (Default)\">\n\
+             \x20     <outline text=\"bar\">\n\
+             \x20       <outline text=\"Dev\">\n\
+             \x20         <outline text=\"Rust &amp; &quot;Friends&quot;\" type=\"link\" url=\"https://example.com/?id=1&amp;x=1\" created=\"Fri, 01 Jan 2021 00:00:00 GMT\"/>\n\
+             \x20       </outline>\n\
+             \x20     </outline>\n\
+             \x20     <outline text=\"Top\" type=\"link\" url=\"https://example.com/?id=2&amp;x=1\" created=\"Fri, 01 Jan 2021 00:00:00 GMT\"/>\n\
+             \x20   </outline>\n\
+             \x20 </body>\n\
+             </opml>\n"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Org-mode export.
+//!
+//! Each profile becomes a top-level headline, folders become nested
+//! headlines, and bookmarks are `[[url][title]]` headlines with a
+//! `:PROPERTIES:` drawer holding the date added and, when the export includes
+//! history, the URL's visit count.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::{Bookmark, BrowserData, bookmarks_to_tree};
+
+/// Render bookmarks as an org document
+pub fn to_org(data: &[BrowserData]) -> String {
+    let mut out = String::from("#+TITLE: Bookmarks\n");
+    for browser_data in data {
+        let visits: HashMap<&str, i64> = browser_data
+            .history
+            .iter()
+            .flat_map(|h| &h.urls)
+            .map(|entry| (entry.url.as_str(), entry.visit_count))
+            .collect();
+        let _ = writeln!(
+            out,
+            "* {} ({})",
+            browser_data.browser,
+            browser_data
+                .profile_name
+                .as_deref()
+                .unwrap_or(&browser_data.profile)
+        );
+        let tree = bookmarks_to_tree(browser_data.bookmarks.as_deref().unwrap_or_default());
+        write_nodes(&mut out, &tree, 2, &visits);
+    }
+    out
+}
+
+fn write_nodes(out: &mut String, nodes: &[Bookmark], depth: usize, visits: &HashMap<&str, i64>) {
+    let stars = "*".repeat(depth);
+    for node in nodes {
+        if let Some(children) = node.children.as_ref().filter(|_| node.is_folder()) {
+            let _ = writeln!(out, "{} {}", stars, escape_description(&node.title));
+            write_nodes(out, children, depth + 1, visits);
+            continue;
+        }
+
+        let Some(url) = &node.url else {
+            continue;
+        };
+        let title = if node.title.trim().is_empty() {
+            url
+        } else {
+            &node.title
+        };
+        let _ = write!(
+            out,
+            "{} [[{}][{}]]",
+            stars,
+            escape_link(url),
+            escape_description(title)
+        );
+        let tags: Vec<String> = node
+            .tags
+            .iter()
+            .flatten()
+            .map(|t| org_tag(t))
+            .filter(|t| !t.is_empty())
+            .collect();
+        if !tags.is_empty() {
+            let _ = write!(out, " :{}:", tags.join(":"));
+        }
+        out.push('\n');
+
+        let mut properties = Vec::new();
+        if let Some(date) = node.date_added {
+            properties.push(("DATE_ADDED", date.format("[%Y-%m-%d %a %H:%M]").to_string()));
+        }
+        if let Some(count) = visits.get(url.as_str()) {
+            properties.push(("VISIT_COUNT", count.to_string()));
+        }
+        if !properties.is_empty() {
+            out.push_str(":PROPERTIES:\n");
+            for (key, value) in properties {
+                let _ = writeln!(out, ":{}: {}", key, value);
+            }
+            out.push_str(":END:\n");
+        }
+    }
+}
+
+/// Brackets would end the link early; org has no escape for them in the URL part
+fn escape_link(url: &str) -> String {
+    url.replace('[', "%5B").replace(']', "%5D")
+}
+
+fn escape_description(text: &str) -> String {
+    text.replace('[', "{").replace(']', "}")
+}
+
+/// Org tags allow letters, digits, `_`, `@`, `#` and `%`
+fn org_tag(tag: &str) -> String {
+    tag.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "_@#%".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::{HistoryEntry, UrlEntry};
+    use chrono::{DateTime, Utc};
+
+    #[test]
+    fn test_to_org() {
+        let bookmark = |id: &str, title: &str, url: &str, folder: Option<&str>| Bookmark {
+            id: id.to_string(),
+            title: title.to_string(),
+            url: Some(url.to_string()),
+            folder: folder.map(str::to_string),
+            date_added: None,
+            ..Default::default()
+        };
+        let data = BrowserData {
+            browser: "firefox".to_string(),
+            profile: "abcd.default".to_string(),
+            export_date: Utc::now(),
+            bookmarks: Some(vec![
+                Bookmark {
+                    date_added: DateTime::from_timestamp(1609459200, 0),
+                    tags: Some(vec!["rust".to_string(), "web dev".to_string()]),
+                    ..bookmark(
+                        "1",
+                        "Rust [lang]",
+                        "https://www.rust-lang.org/",
+                        Some("menu/Dev"),
+                    )
+                },
+                bookmark("2", "", "https://example.com/a]b", None),
+            ]),
+            history: Some(HistoryEntry {
+                urls: vec![UrlEntry {
+                    url: "https://www.rust-lang.org/".to_string(),
+                    title: "Rust".to_string(),
+                    visit_count: 42,
+                    last_visit: None,
+                }],
+            }),
+            passwords: None,
+            aliases: None,
+            profile_name: Some("Personal".to_string()),
+            profile_email: None,
+            provenance: None,
+        };
+
+        assert_eq!(
+            to_org(&[data]),
+            "#+TITLE: Bookmarks\n\
+             * firefox (Personal)\n\
+             ** menu\n\
+             *** Dev\n\
+             **** [[https://www.rust-lang.org/][Rust {lang}]] :rust:web_dev:\n\
+             :PROPERTIES:\n\
+             :DATE_ADDED: [2021-01-01 Fri 00:00]\n\
+             :VISIT_COUNT: 42\n\
+             :END:\n\
+             ** [[https://example.com/a%5Db][https://example.com/a}b]]\n"
+        );
+    }
+}
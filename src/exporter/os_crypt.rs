@@ -0,0 +1,253 @@
+//! Decryption of Chromium `os_crypt` blobs (saved passwords).
+//!
+//! Chromium prefixes encrypted values with a version tag:
+//! - macOS/Linux `v10`/`v11`: AES-128-CBC, key derived with PBKDF2-SHA1 from a
+//!   password kept in the Keychain (macOS) or libsecret (Linux, `v11`; `v10`
+//!   uses the hard-coded `peanuts` password).
+//! - Windows `v10`: AES-256-GCM with a key stored DPAPI-protected in `Local State`.
+//! - Windows without a prefix: the value itself is a DPAPI blob.
+//!
+//! Windows app-bound encryption (`v20`, Chrome 127+) is not supported.
+
+use aes::cipher::{BlockDecryptMut, KeyIvInit, block_padding::Pkcs7};
+use aes_gcm::aead::{Aead, KeyInit};
+use anyhow::{Result, anyhow};
+use std::path::Path;
+
+type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+
+/// Key material needed to decrypt one browser's saved values
+pub enum MasterKey {
+    /// macOS/Linux: AES-128-CBC keys for the `v10` and (Linux) `v11` prefixes
+    Cbc {
+        v10: [u8; 16],
+        v11: Option<[u8; 16]>,
+    },
+    /// Windows: AES-256-GCM key unwrapped from `Local State`
+    #[cfg_attr(not(windows), allow(dead_code))]
+    Gcm([u8; 32]),
+}
+
+/// Derive a CBC key the way Chromium does on macOS (1003 rounds) and Linux (1 round)
+pub fn derive_cbc_key(password: &[u8], iterations: u32) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    pbkdf2::pbkdf2_hmac::<sha1::Sha1>(password, b"saltysalt", iterations, &mut key);
+    key
+}
+
+/// Load the master key for a Chromium browser profile from the OS keystore.
+///
+/// `safe_storage` is the browser's keystore name, e.g. `Chrome` or `Microsoft Edge`.
+#[cfg(target_os = "macos")]
+pub fn load_master_key(safe_storage: &str, _profile_path: &Path) -> Result<MasterKey> {
+    let output = std::process::Command::new("security")
+        .args([
+            "find-generic-password",
+            "-w",
+            "-s",
+            &format!("{} Safe Storage", safe_storage),
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Keychain access to '{} Safe Storage' was denied or the item is missing",
+            safe_storage
+        ));
+    }
+    let password = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(MasterKey::Cbc {
+        v10: derive_cbc_key(password.as_bytes(), 1003),
+        v11: None,
+    })
+}
+
+/// Load the master key for a Chromium browser profile from the OS keystore.
+///
+/// `safe_storage` is the browser's keystore name, e.g. `Chrome` or `Microsoft Edge`.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn load_master_key(safe_storage: &str, _profile_path: &Path) -> Result<MasterKey> {
+    // libsecret stores the password under the lowercase application name
+    let application = match safe_storage {
+        "Chrome" => "chrome",
+        "Microsoft Edge" => "microsoft-edge",
+        other => other,
+    };
+    let v11 = std::process::Command::new("secret-tool")
+        .args(["lookup", "application", application])
+        .output()
+        .ok()
+        .filter(|o| o.status.success() && !o.stdout.is_empty())
+        .map(|o| derive_cbc_key(String::from_utf8_lossy(&o.stdout).trim().as_bytes(), 1));
+    if v11.is_none() {
+        log::debug!(
+            "No libsecret entry for {}; only v10 values can be decrypted",
+            application
+        );
+    }
+    Ok(MasterKey::Cbc {
+        v10: derive_cbc_key(b"peanuts", 1),
+        v11,
+    })
+}
+
+/// Load the master key for a Chromium browser profile from the OS keystore.
+///
+/// `safe_storage` is unused on Windows; the key lives in the `Local State` file
+/// next to the profile directory.
+#[cfg(windows)]
+pub fn load_master_key(_safe_storage: &str, profile_path: &Path) -> Result<MasterKey> {
+    use base64::Engine;
+
+    let local_state_path = profile_path
+        .parent()
+        .map(|p| p.join("Local State"))
+        .ok_or_else(|| anyhow!("Cannot locate Local State for {}", profile_path.display()))?;
+    let local_state: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&local_state_path)?)?;
+    let encoded = local_state["os_crypt"]["encrypted_key"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Local State has no os_crypt.encrypted_key"))?;
+    let wrapped = base64::engine::general_purpose::STANDARD.decode(encoded)?;
+    let wrapped = wrapped
+        .strip_prefix(b"DPAPI")
+        .ok_or_else(|| anyhow!("Unexpected os_crypt key format"))?;
+    let key = dpapi_unprotect(wrapped)?;
+    let key: [u8; 32] = key
+        .try_into()
+        .map_err(|_| anyhow!("Unexpected os_crypt key length"))?;
+    Ok(MasterKey::Gcm(key))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn load_master_key(_safe_storage: &str, _profile_path: &Path) -> Result<MasterKey> {
+    Err(anyhow!(
+        "Password decryption is not supported on this platform"
+    ))
+}
+
+/// Decrypt a single `os_crypt` value into its plaintext string
+pub fn decrypt(value: &[u8], key: &MasterKey) -> Result<String> {
+    let plaintext = match (key, value) {
+        (_, []) => Vec::new(),
+        (_, [b'v', b'2', b'0', ..]) => {
+            return Err(anyhow!("App-bound (v20) encryption is not supported"));
+        }
+        (MasterKey::Cbc { v10, .. }, [b'v', b'1', b'0', rest @ ..]) => decrypt_cbc(v10, rest)?,
+        (MasterKey::Cbc { v11, .. }, [b'v', b'1', b'1', rest @ ..]) => {
+            let v11 = v11
+                .as_ref()
+                .ok_or_else(|| anyhow!("v11 value but no keyring password available"))?;
+            decrypt_cbc(v11, rest)?
+        }
+        (MasterKey::Gcm(k), [b'v', b'1', b'0', rest @ ..]) => decrypt_gcm(k, rest)?,
+        #[cfg(windows)]
+        (MasterKey::Gcm(_), legacy) => dpapi_unprotect(legacy)?,
+        _ => return Err(anyhow!("Unrecognized encrypted value format")),
+    };
+    Ok(String::from_utf8(plaintext)?)
+}
+
+fn decrypt_cbc(key: &[u8; 16], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    let iv = [b' '; 16];
+    Aes128CbcDec::new(key.into(), &iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+        .map_err(|_| anyhow!("Decryption failed (wrong key?)"))
+}
+
+fn decrypt_gcm(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < 12 {
+        return Err(anyhow!("Encrypted value too short"));
+    }
+    let (nonce, ciphertext) = data.split_at(12);
+    aes_gcm::Aes256Gcm::new(key.into())
+        .decrypt(nonce.into(), ciphertext)
+        .map_err(|_| anyhow!("Decryption failed (wrong key?)"))
+}
+
+#[cfg(windows)]
+fn dpapi_unprotect(data: &[u8]) -> Result<Vec<u8>> {
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Cryptography::{CRYPT_INTEGER_BLOB, CryptUnprotectData};
+
+    let input = CRYPT_INTEGER_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr() as *mut u8,
+    };
+    let mut output = CRYPT_INTEGER_BLOB {
+        cbData: 0,
+        pbData: std::ptr::null_mut(),
+    };
+    // SAFETY: input points to a live slice; on success output is allocated by
+    // the system and released with LocalFree after copying.
+    unsafe {
+        if CryptUnprotectData(
+            &input,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            std::ptr::null(),
+            std::ptr::null(),
+            0,
+            &mut output,
+        ) == 0
+        {
+            return Err(anyhow!("DPAPI decryption failed"));
+        }
+        let result = std::slice::from_raw_parts(output.pbData, output.cbData as usize).to_vec();
+        LocalFree(output.pbData as _);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use aes::cipher::BlockEncryptMut;
+
+    pub(crate) fn encrypt_v10(key: &[u8; 16], plaintext: &str) -> Vec<u8> {
+        let iv = [b' '; 16];
+        let mut out = b"v10".to_vec();
+        out.extend(
+            cbc::Encryptor::<aes::Aes128>::new(key.into(), &iv.into())
+                .encrypt_padded_vec_mut::<Pkcs7>(plaintext.as_bytes()),
+        );
+        out
+    }
+
+    #[test]
+    fn test_decrypt_cbc_roundtrip() {
+        let key = MasterKey::Cbc {
+            v10: derive_cbc_key(b"peanuts", 1),
+            v11: None,
+        };
+        let MasterKey::Cbc { v10, .. } = &key else {
+            unreachable!()
+        };
+        let blob = encrypt_v10(v10, "hunter2");
+        assert_eq!(decrypt(&blob, &key).unwrap(), "hunter2");
+        assert_eq!(decrypt(b"", &key).unwrap(), "");
+    }
+
+    #[test]
+    fn test_decrypt_gcm_roundtrip() {
+        let raw = [7u8; 32];
+        let nonce = [1u8; 12];
+        let mut blob = b"v10".to_vec();
+        blob.extend(nonce);
+        blob.extend(
+            aes_gcm::Aes256Gcm::new(&raw.into())
+                .encrypt(&nonce.into(), b"s3cret".as_ref())
+                .unwrap(),
+        );
+        assert_eq!(decrypt(&blob, &MasterKey::Gcm(raw)).unwrap(), "s3cret");
+    }
+
+    #[test]
+    fn test_unsupported_formats() {
+        let key = MasterKey::Cbc {
+            v10: derive_cbc_key(b"peanuts", 1),
+            v11: None,
+        };
+        assert!(decrypt(b"v20abcdef", &key).is_err());
+        assert!(decrypt(b"v11abcdef", &key).is_err());
+    }
+}
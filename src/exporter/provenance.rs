@@ -0,0 +1,129 @@
+//! Provenance recorded with every export, so a file can be interpreted later
+//! (and attached to bug reports) without knowing how it was produced.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::checksum::sha256_hex;
+use super::{BrowserData, HISTORY_LIMIT, flatten_bookmarks};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Version of this crate that wrote the file
+    pub tool_version: String,
+    /// Command that produced the data (`export`, `import`, `process`, ...)
+    pub generated_by: String,
+    /// Options the command ran with; never contains secrets
+    pub settings: BTreeMap<String, String>,
+    /// Short SHA-256 of `settings`, for spotting files made with the same setup
+    pub settings_hash: String,
+    /// Version of the browser that last opened the profile, when detectable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub browser_version: Option<String>,
+    pub counts: RowCounts,
+    /// Sections cut off at an extraction limit (e.g. `history`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub truncated: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RowCounts {
+    pub bookmarks: usize,
+    pub history: usize,
+    pub passwords: usize,
+}
+
+impl Provenance {
+    pub fn new(generated_by: &str, settings: BTreeMap<String, String>) -> Self {
+        Self {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_by: generated_by.to_string(),
+            settings_hash: settings_hash(&settings),
+            settings,
+            browser_version: None,
+            counts: RowCounts::default(),
+            truncated: Vec::new(),
+        }
+    }
+
+    pub fn with_browser_version(mut self, version: Option<String>) -> Self {
+        self.browser_version = version;
+        self
+    }
+
+    /// Fill in row counts and truncation flags from the data this block describes
+    pub fn describe(mut self, data: &BrowserData) -> Self {
+        self.counts = RowCounts {
+            bookmarks: data
+                .bookmarks
+                .as_deref()
+                .map(|b| flatten_bookmarks(b).len())
+                .unwrap_or(0),
+            history: data.history.as_ref().map(|h| h.urls.len()).unwrap_or(0),
+            passwords: data.passwords.as_ref().map(Vec::len).unwrap_or(0),
+        };
+        self.truncated.clear();
+        if self.counts.history >= HISTORY_LIMIT {
+            self.truncated.push("history".to_string());
+        }
+        self
+    }
+}
+
+fn settings_hash(settings: &BTreeMap<String, String>) -> String {
+    let lines: String = settings
+        .iter()
+        .map(|(key, value)| format!("{}={}\n", key, value))
+        .collect();
+    sha256_hex(lines.as_bytes())[..16].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::{HistoryEntry, UrlEntry};
+    use chrono::Utc;
+
+    fn settings(data_type: &str) -> BTreeMap<String, String> {
+        BTreeMap::from([("data_type".to_string(), data_type.to_string())])
+    }
+
+    #[test]
+    fn test_settings_hash_is_stable() {
+        let a = Provenance::new("export", settings("bookmarks"));
+        let b = Provenance::new("export", settings("bookmarks"));
+        let c = Provenance::new("export", settings("history"));
+        assert_eq!(a.settings_hash, b.settings_hash);
+        assert_ne!(a.settings_hash, c.settings_hash);
+        assert_eq!(a.settings_hash.len(), 16);
+        assert_eq!(a.tool_version, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn test_describe_counts_and_truncation() {
+        let entry = || UrlEntry {
+            url: "https://example.com".to_string(),
+            title: String::new(),
+            visit_count: 1,
+            last_visit: None,
+        };
+        let data = BrowserData {
+            browser: "chrome".to_string(),
+            profile: "Default".to_string(),
+            export_date: Utc::now(),
+            bookmarks: Some(Vec::new()),
+            history: Some(HistoryEntry {
+                urls: (0..HISTORY_LIMIT).map(|_| entry()).collect(),
+            }),
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        };
+        let provenance = Provenance::new("export", settings("all")).describe(&data);
+        assert_eq!(provenance.counts.history, HISTORY_LIMIT);
+        assert_eq!(provenance.counts.bookmarks, 0);
+        assert_eq!(provenance.truncated, vec!["history".to_string()]);
+    }
+}
@@ -1,8 +1,15 @@
-use anyhow::{anyhow, Result};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
 use std::path::Path;
+use std::time::SystemTime;
 
+use super::chrome::READING_LIST_FOLDER;
+use super::holdout::{self, FolderHoldout};
 use super::{Bookmark, HistoryEntry};
 
+/// Title of the list holding Safari's Reading List
+const READING_LIST_TITLE: &str = "com.apple.ReadingList";
+
 pub fn extract_bookmarks(profile_path: &Path) -> Result<Option<Vec<Bookmark>>> {
     // First try the default location
     let bookmarks_path = profile_path.join("Bookmarks.plist");
@@ -48,42 +55,175 @@ fn extract_safari_bookmarks(bookmarks_path: &Path) -> Result<Option<Vec<Bookmark
     let content = std::fs::read(bookmarks_path)?;
     let plist: plist::Value = plist::from_bytes(&content)?;
 
+    let holdout = holdout::get()?;
     let mut bookmarks = Vec::new();
+    if let Some(dict) = plist.as_dictionary() {
+        collect_bookmarks(dict, None, holdout, &mut bookmarks);
+    }
 
-    if let Some(dict) = plist.into_dictionary() {
-        if let Some(children) = dict.get("Children").and_then(|c| c.as_array()) {
-            for item in children {
-                if let Some(bookmark_dict) = item.as_dictionary() {
-                    if bookmark_dict
-                        .get("WebBookmarkType")
-                        .and_then(|t| t.as_string())
-                        == Some("WebBookmarkTypeLeaf")
-                    {
-                        let title = bookmark_dict
-                            .get("URIDictionary")
-                            .and_then(|d| d.as_dictionary())
-                            .and_then(|d| d.get("title"))
-                            .and_then(|t| t.as_string())
-                            .unwrap_or("")
-                            .to_string();
-
-                        let bookmark = Bookmark {
-                            id: title.clone(),
-                            title: title.clone(),
-                            url: bookmark_dict
-                                .get("URLString")
-                                .and_then(|u| u.as_string())
-                                .map(|s| s.to_string()),
-                            folder: None,
-                            date_added: None,
-                            children: None,
-                        };
-                        bookmarks.push(bookmark);
-                    }
-                }
+    Ok(Some(bookmarks))
+}
+
+/// Walk a `WebBookmarkTypeList` node, naming each leaf's folder by the `/`-joined
+/// titles of the lists above it. The top-level lists (`BookmarksBar`,
+/// `BookmarksMenu`) become the first path segment, like Chrome's root names.
+/// Lists the holdout holds are skipped whole.
+fn collect_bookmarks(
+    list: &plist::Dictionary,
+    folder: Option<&str>,
+    holdout: &FolderHoldout,
+    out: &mut Vec<Bookmark>,
+) {
+    if holdout.holds(folder) {
+        return;
+    }
+    let Some(children) = list.get("Children").and_then(|c| c.as_array()) else {
+        return;
+    };
+
+    for item in children.iter().filter_map(|c| c.as_dictionary()) {
+        match item.get("WebBookmarkType").and_then(|t| t.as_string()) {
+            Some("WebBookmarkTypeList") => {
+                let title = item.get("Title").and_then(|t| t.as_string()).unwrap_or("");
+                let name = if title == READING_LIST_TITLE {
+                    READING_LIST_FOLDER
+                } else {
+                    title
+                };
+                let path = match (folder, name.is_empty()) {
+                    (_, true) => folder.map(str::to_string),
+                    (Some(parent), false) => Some(format!("{}/{}", parent, name)),
+                    (None, false) => Some(name.to_string()),
+                };
+                collect_bookmarks(item, path.as_deref(), holdout, out);
             }
+            Some("WebBookmarkTypeLeaf") => out.push(leaf_to_bookmark(item, folder)),
+            _ => {}
         }
     }
+}
 
-    Ok(Some(bookmarks))
+fn leaf_to_bookmark(leaf: &plist::Dictionary, folder: Option<&str>) -> Bookmark {
+    let title = leaf
+        .get("URIDictionary")
+        .and_then(|d| d.as_dictionary())
+        .and_then(|d| d.get("title"))
+        .and_then(|t| t.as_string())
+        .unwrap_or("")
+        .to_string();
+    // Reading-list items carry a ReadingList dict with dates and preview text
+    let reading_list = leaf.get("ReadingList").and_then(|r| r.as_dictionary());
+    let date = |key: &str| {
+        reading_list
+            .and_then(|r| r.get(key))
+            .and_then(|d| d.as_date())
+            .map(|d| DateTime::<Utc>::from(SystemTime::from(d)))
+    };
+    let status = reading_list.map(|_| {
+        let status = if date("DateLastViewed").is_some() {
+            "read"
+        } else {
+            "unread"
+        };
+        vec![status.to_string()]
+    });
+
+    Bookmark {
+        id: leaf
+            .get("WebBookmarkUUID")
+            .and_then(|u| u.as_string())
+            .map(str::to_string)
+            .unwrap_or_else(|| title.clone()),
+        title,
+        url: leaf
+            .get("URLString")
+            .and_then(|u| u.as_string())
+            .map(|s| s.to_string()),
+        folder: folder.map(str::to_string),
+        date_added: date("DateAdded"),
+        tags: status,
+        description: reading_list
+            .and_then(|r| r.get("PreviewText"))
+            .and_then(|t| t.as_string())
+            .filter(|t| !t.is_empty())
+            .map(str::to_string),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plist::{Dictionary, Value};
+    use tempfile::tempdir;
+
+    fn leaf(uuid: &str, url: &str, title: &str) -> Dictionary {
+        let mut uri = Dictionary::new();
+        uri.insert("title".into(), title.into());
+        let mut leaf = Dictionary::new();
+        leaf.insert("WebBookmarkType".into(), "WebBookmarkTypeLeaf".into());
+        leaf.insert("WebBookmarkUUID".into(), uuid.into());
+        leaf.insert("URLString".into(), url.into());
+        leaf.insert("URIDictionary".into(), uri.into());
+        leaf
+    }
+
+    fn list(title: &str, children: Vec<Dictionary>) -> Dictionary {
+        let mut list = Dictionary::new();
+        list.insert("WebBookmarkType".into(), "WebBookmarkTypeList".into());
+        list.insert("Title".into(), title.into());
+        list.insert(
+            "Children".into(),
+            Value::Array(children.into_iter().map(Value::Dictionary).collect()),
+        );
+        list
+    }
+
+    #[test]
+    fn test_extract_folders_and_reading_list() {
+        let added = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1609459200);
+        let mut reading = Dictionary::new();
+        reading.insert("DateAdded".into(), Value::Date(added.into()));
+        reading.insert("PreviewText".into(), "A short preview".into());
+        let mut unread = leaf("u2", "https://example.com/article", "Article");
+        unread.insert("ReadingList".into(), reading.clone().into());
+        reading.insert("DateLastViewed".into(), Value::Date(added.into()));
+        let mut read = leaf("u3", "https://example.com/done", "Done");
+        read.insert("ReadingList".into(), reading.into());
+
+        let root = list(
+            "",
+            vec![
+                list(
+                    "BookmarksBar",
+                    vec![list(
+                        "Development",
+                        vec![leaf("u1", "https://www.rust-lang.org/", "Rust")],
+                    )],
+                ),
+                list(READING_LIST_TITLE, vec![unread, read]),
+            ],
+        );
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Bookmarks.plist");
+        Value::Dictionary(root).to_file_binary(&path).unwrap();
+
+        let bookmarks = extract_bookmarks(&path).unwrap().unwrap();
+        assert_eq!(bookmarks.len(), 3);
+        assert_eq!(bookmarks[0].id, "u1");
+        assert_eq!(
+            bookmarks[0].folder.as_deref(),
+            Some("BookmarksBar/Development")
+        );
+        assert_eq!(bookmarks[0].tags, None);
+
+        assert_eq!(bookmarks[1].folder.as_deref(), Some(READING_LIST_FOLDER));
+        assert_eq!(
+            bookmarks[1].date_added,
+            DateTime::from_timestamp(1609459200, 0)
+        );
+        assert_eq!(bookmarks[1].description.as_deref(), Some("A short preview"));
+        assert_eq!(bookmarks[1].tags, Some(vec!["unread".to_string()]));
+        assert_eq!(bookmarks[2].tags, Some(vec!["read".to_string()]));
+    }
 }
@@ -0,0 +1,140 @@
+//! Point-in-time copies of browser SQLite databases.
+//!
+//! Browsers keep their databases open (and often locked) while running, and
+//! recent writes may still live in the `-wal` file. Reading a private copy of
+//! the database together with its `-wal`/`-shm` siblings avoids lock errors and
+//! sees the same data the browser does. Each snapshot gets its own directory
+//! under the snapshots path (see [`crate::paths`]), which is removed when the
+//! snapshot is dropped.
+
+use anyhow::{Context, Result, anyhow};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+const COPY_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// SQLite sidecar files that must travel with the main database
+const SIDECAR_SUFFIXES: [&str; 2] = ["-wal", "-shm"];
+
+/// A private copy of a SQLite database, deleted on drop
+pub struct Snapshot {
+    // Held for its Drop impl, which removes the directory
+    _dir: tempfile::TempDir,
+    db_path: PathBuf,
+}
+
+impl Snapshot {
+    /// Copy `source` (and any `-wal`/`-shm` files) into a fresh directory,
+    /// retrying briefly if the browser is mid-write
+    pub fn create(source: &Path) -> Result<Self> {
+        let snapshots = &crate::paths::get().snapshots;
+        std::fs::create_dir_all(snapshots)
+            .with_context(|| format!("Could not create {}", snapshots.display()))?;
+        let dir = tempfile::Builder::new()
+            .prefix(crate::cleanup::SNAPSHOT_PREFIX)
+            .tempdir_in(snapshots)?;
+        let file_name = source
+            .file_name()
+            .ok_or_else(|| anyhow!("Not a database file: {}", source.display()))?;
+        let db_path = dir.path().join(file_name);
+
+        copy_with_retry(source, &db_path).with_context(|| {
+            format!(
+                "Could not snapshot {}. If the browser is running, close it and try again",
+                source.display()
+            )
+        })?;
+
+        for suffix in SIDECAR_SUFFIXES {
+            let sidecar = with_suffix(source, suffix);
+            if sidecar.exists() {
+                // A missing sidecar just means the browser checkpointed meanwhile
+                if let Err(e) = copy_with_retry(&sidecar, &with_suffix(&db_path, suffix)) {
+                    log::debug!("Skipping {}: {}", sidecar.display(), e);
+                }
+            }
+        }
+
+        Ok(Self { _dir: dir, db_path })
+    }
+
+    /// Open the copy. It is opened read-write so SQLite can replay the WAL;
+    /// the browser's original files are never touched.
+    pub fn open(&self) -> Result<rusqlite::Connection> {
+        Ok(rusqlite::Connection::open(&self.db_path)?)
+    }
+}
+
+fn copy_with_retry(from: &Path, to: &Path) -> Result<()> {
+    let mut attempt = 1;
+    loop {
+        match std::fs::copy(from, to) {
+            Ok(_) => return Ok(()),
+            Err(e) if attempt < COPY_ATTEMPTS => {
+                log::debug!(
+                    "Copy of {} failed (attempt {}): {}",
+                    from.display(),
+                    attempt,
+                    e
+                );
+                thread::sleep(RETRY_DELAY * attempt);
+                attempt += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_includes_wal_and_cleans_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("History");
+
+        // Keep the writer open so the committed row stays in the WAL
+        let writer = rusqlite::Connection::open(&source).unwrap();
+        writer
+            .execute_batch(
+                "PRAGMA journal_mode = WAL;
+                 PRAGMA wal_autocheckpoint = 0;
+                 CREATE TABLE urls (url TEXT);
+                 INSERT INTO urls VALUES ('https://example.com');",
+            )
+            .unwrap();
+        assert!(with_suffix(&source, "-wal").exists());
+
+        let snapshot = Snapshot::create(&source).unwrap();
+        let snapshot_dir = snapshot.db_path.parent().unwrap().to_path_buf();
+        let count: i64 = snapshot
+            .open()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM urls", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+
+        drop(snapshot);
+        assert!(!snapshot_dir.exists());
+        drop(writer);
+    }
+
+    #[test]
+    fn test_snapshot_missing_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = Snapshot::create(&dir.path().join("places.sqlite"))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("close it and try again"));
+    }
+}
@@ -0,0 +1,243 @@
+//! SQLite export backend.
+//!
+//! Writes profiles, folders, bookmarks (with a tag table) and history into a
+//! normalized database so large collections can be queried with SQL. Folder
+//! rows form a tree through `parent_id`; saved passwords are never written.
+
+use anyhow::Result;
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+use std::fs;
+use std::path::Path;
+
+use super::{BrowserData, flatten_bookmarks};
+
+const SCHEMA: &str = "
+CREATE TABLE profiles (
+    id INTEGER PRIMARY KEY,
+    browser TEXT NOT NULL,
+    profile TEXT NOT NULL,
+    profile_name TEXT,
+    profile_email TEXT,
+    export_date TEXT NOT NULL
+);
+CREATE TABLE folders (
+    id INTEGER PRIMARY KEY,
+    profile_id INTEGER NOT NULL REFERENCES profiles(id),
+    parent_id INTEGER REFERENCES folders(id),
+    name TEXT NOT NULL,
+    path TEXT NOT NULL,
+    UNIQUE (profile_id, path)
+);
+CREATE TABLE bookmarks (
+    id INTEGER PRIMARY KEY,
+    profile_id INTEGER NOT NULL REFERENCES profiles(id),
+    folder_id INTEGER REFERENCES folders(id),
+    source_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT,
+    date_added TEXT,
+    keyword TEXT,
+    description TEXT
+);
+CREATE TABLE bookmark_tags (
+    bookmark_id INTEGER NOT NULL REFERENCES bookmarks(id),
+    tag TEXT NOT NULL,
+    PRIMARY KEY (bookmark_id, tag)
+);
+CREATE TABLE history (
+    id INTEGER PRIMARY KEY,
+    profile_id INTEGER NOT NULL REFERENCES profiles(id),
+    url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    visit_count INTEGER NOT NULL,
+    last_visit TEXT
+);
+CREATE INDEX idx_bookmarks_url ON bookmarks(url);
+CREATE INDEX idx_bookmarks_folder ON bookmarks(folder_id);
+CREATE INDEX idx_bookmark_tags_tag ON bookmark_tags(tag);
+CREATE INDEX idx_history_url ON history(url);
+CREATE INDEX idx_history_last_visit ON history(last_visit);
+";
+
+/// Write exported data to a fresh SQLite database at `path`, replacing any
+/// existing file
+pub fn write_sqlite(data: &[BrowserData], path: &Path) -> Result<()> {
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    let mut conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    for browser_data in data {
+        write_profile(&tx, browser_data)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn write_profile(tx: &Transaction, data: &BrowserData) -> Result<()> {
+    tx.execute(
+        "INSERT INTO profiles (browser, profile, profile_name, profile_email, export_date)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            data.browser,
+            data.profile,
+            data.profile_name,
+            data.profile_email,
+            data.export_date.to_rfc3339()
+        ],
+    )?;
+    let profile_id = tx.last_insert_rowid();
+
+    for bookmark in flatten_bookmarks(data.bookmarks.as_deref().unwrap_or_default()) {
+        let folder_id = match bookmark.folder.as_deref() {
+            Some(folder) => folder_id(tx, profile_id, folder)?,
+            None => None,
+        };
+        tx.execute(
+            "INSERT INTO bookmarks
+                 (profile_id, folder_id, source_id, title, url, date_added, keyword, description)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                profile_id,
+                folder_id,
+                bookmark.id,
+                bookmark.title,
+                bookmark.url,
+                bookmark.date_added.map(|d| d.to_rfc3339()),
+                bookmark.keyword,
+                bookmark.description
+            ],
+        )?;
+        let bookmark_id = tx.last_insert_rowid();
+        for tag in bookmark.tags.iter().flatten() {
+            tx.execute(
+                "INSERT OR IGNORE INTO bookmark_tags (bookmark_id, tag) VALUES (?1, ?2)",
+                params![bookmark_id, tag],
+            )?;
+        }
+    }
+
+    for entry in data.history.iter().flat_map(|h| &h.urls) {
+        tx.execute(
+            "INSERT INTO history (profile_id, url, title, visit_count, last_visit)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                profile_id,
+                entry.url,
+                entry.title,
+                entry.visit_count,
+                entry.last_visit.map(|d| d.to_rfc3339())
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Id of the folder row for a `/`-separated path, creating it and any
+/// missing ancestors
+fn folder_id(tx: &Transaction, profile_id: i64, path: &str) -> Result<Option<i64>> {
+    let mut parent_id = None;
+    let mut current = String::new();
+    for name in path.split('/').filter(|s| !s.is_empty()) {
+        if !current.is_empty() {
+            current.push('/');
+        }
+        current.push_str(name);
+
+        let existing = tx
+            .query_row(
+                "SELECT id FROM folders WHERE profile_id = ?1 AND path = ?2",
+                params![profile_id, current],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?;
+        parent_id = Some(match existing {
+            Some(id) => id,
+            None => {
+                tx.execute(
+                    "INSERT INTO folders (profile_id, parent_id, name, path) VALUES (?1, ?2, ?3, ?4)",
+                    params![profile_id, parent_id, name, current],
+                )?;
+                tx.last_insert_rowid()
+            }
+        });
+    }
+    Ok(parent_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::{Bookmark, HistoryEntry, UrlEntry};
+    use chrono::Utc;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_sqlite() {
+        let bookmark = |id: &str, folder: &str| Bookmark {
+            id: id.to_string(),
+            title: format!("Bookmark {}", id),
+            url: Some(format!("https://example.com/{}", id)),
+            folder: Some(folder.to_string()),
+            date_added: None,
+            tags: Some(vec!["rust".to_string()]),
+            ..Default::default()
+        };
+        let data = BrowserData {
+            browser: "chrome".to_string(),
+            profile: "Default".to_string(),
+            export_date: Utc::now(),
+            bookmarks: Some(vec![
+                bookmark("1", "bar/Dev/Rust"),
+                bookmark("2", "bar/Dev"),
+            ]),
+            history: Some(HistoryEntry {
+                urls: vec![UrlEntry {
+                    url: "https://example.com/1".to_string(),
+                    title: "Bookmark 1".to_string(),
+                    visit_count: 5,
+                    last_visit: None,
+                }],
+            }),
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        };
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("export.sqlite");
+        fs::write(&path, "stale").unwrap();
+        write_sqlite(&[data], &path).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let count = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM folders"), 3);
+        assert_eq!(count("SELECT COUNT(*) FROM bookmarks"), 2);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM bookmark_tags WHERE tag = 'rust'"),
+            2
+        );
+        assert_eq!(
+            count(
+                "SELECT h.visit_count FROM bookmarks b
+                 JOIN folders f ON f.id = b.folder_id
+                 JOIN history h ON h.url = b.url
+                 WHERE f.path = 'bar/Dev/Rust'"
+            ),
+            5
+        );
+        let parent: String = conn
+            .query_row(
+                "SELECT p.path FROM folders f JOIN folders p ON p.id = f.parent_id
+                 WHERE f.name = 'Rust'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(parent, "bar/Dev");
+    }
+}
@@ -1,7 +1,10 @@
 use std::collections::HashSet;
 
-/// Extract meaningful tags from title and URL
-pub fn extract_tags(title: &str, url: Option<&str>) -> Vec<String> {
+use crate::enrich::PageInfo;
+
+/// Extract meaningful tags from title and URL, plus the keywords, site name
+/// and Open Graph type the page declares about itself when enriched
+pub fn extract_tags(title: &str, url: Option<&str>, page: Option<&PageInfo>) -> Vec<String> {
     let stop_words: HashSet<&str> = [
         "the", "a", "an", "and", "or", "but", "in", "on", "at", "to", "for",
         "of", "with", "by", "from", "is", "it", "this", "that", "are", "was",
@@ -30,30 +33,63 @@ pub fn extract_tags(title: &str, url: Option<&str>) -> Vec<String> {
     }
 
     // Extract path segments from URL
-    if let Some(url_str) = url {
-        if let Ok(parsed) = url::Url::parse(url_str) {
-            for segment in parsed.path_segments().into_iter().flatten() {
-                let seg = segment.to_lowercase();
-                if seg.len() >= 3 && !stop_words.contains(seg.as_str()) {
-                    // Remove file extensions
-                    let clean = seg.split('.').next().unwrap_or(&seg);
-                    if clean.len() >= 3 {
-                        tags.insert(clean.to_string());
-                    }
+    if let Some(url_str) = url
+        && let Ok(parsed) = url::Url::parse(url_str)
+    {
+        for segment in parsed.path_segments().into_iter().flatten() {
+            let seg = segment.to_lowercase();
+            if seg.len() >= 3 && !stop_words.contains(seg.as_str()) {
+                // Remove file extensions
+                let clean = seg.split('.').next().unwrap_or(&seg);
+                if clean.len() >= 3 {
+                    tags.insert(clean.to_string());
                 }
             }
         }
     }
 
+    // Declared metadata is kept whole, as a slug: "Machine Learning" -> machine-learning
+    if let Some(page) = page {
+        let declared = page
+            .keywords
+            .iter()
+            .chain(&page.site_name)
+            .chain(&page.og_type);
+        for value in declared {
+            let slug = slugify(value);
+            if slug.len() >= 2 && !stop_words.contains(slug.as_str()) {
+                tags.insert(slug);
+            }
+        }
+    }
+
     tags.into_iter().collect()
 }
 
-/// Categorize a bookmark based on title, URL, and domain
-pub fn categorize(title: &str, url: Option<&str>, domain: Option<&str>) -> String {
+/// Lowercase words joined by `-`; `video.movie` becomes `video-movie`
+fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Categorize a bookmark based on title, URL, domain and declared keywords
+pub fn categorize(
+    title: &str,
+    url: Option<&str>,
+    domain: Option<&str>,
+    page: Option<&PageInfo>,
+) -> String {
+    let keywords = page
+        .map(|p| p.keywords.iter().map(|k| slugify(k)).collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
     let text = format!(
-        "{} {}",
+        "{} {} {}",
         title.to_lowercase(),
-        url.unwrap_or("").to_lowercase()
+        url.unwrap_or("").to_lowercase(),
+        keywords
     );
     let domain_lower = domain.unwrap_or("").to_lowercase();
 
@@ -1,13 +1,16 @@
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use std::collections::{HashMap, HashSet};
+use std::time::Instant;
 
 use super::analyzer;
 use super::{
-    DetailLevel, EdgeType, GraphConfig, GraphEdge, GraphMetadata, GraphNode, KnowledgeGraph,
-    NodeType,
+    DetailLevel, EdgeType, GraphConfig, GraphEdge, GraphMetadata, GraphNode, GraphSizeError,
+    KnowledgeGraph, NodeMetadata, NodeType,
 };
-use crate::exporter::{Bookmark, UrlEntry};
+use crate::enrich::PageInfo;
+use crate::exporter::{Bookmark, UrlEntry, flatten_bookmarks};
+use crate::progress::ProgressEvent;
 
 /// A single item to ingest into the graph (unified representation)
 struct IngestItem<'a> {
@@ -16,12 +19,22 @@ struct IngestItem<'a> {
     url: Option<&'a str>,
     folder: Option<&'a str>,
     size: usize,
+    date_added: Option<DateTime<Utc>>,
+    visit_count: Option<i64>,
+    last_visit: Option<DateTime<Utc>>,
+    /// 1 for bookmarks, 0 for history-only entries
+    bookmark_count: usize,
+    /// Primary language of the GitHub repository the bookmark points at
+    language: Option<&'a str>,
+    /// What the page declares about itself, from `enrich`
+    page: Option<&'a PageInfo>,
 }
 
 /// Builder for creating knowledge graphs
 pub struct GraphBuilder {
     config: GraphConfig,
     domain_counts: HashMap<String, usize>,
+    domain_metadata: HashMap<String, NodeMetadata>,
     folder_counts: HashMap<String, usize>,
     tag_counts: HashMap<String, usize>,
     category_counts: HashMap<String, usize>,
@@ -30,6 +43,7 @@ pub struct GraphBuilder {
     tag_to_bookmarks: HashMap<String, Vec<String>>,
     category_to_bookmarks: HashMap<String, Vec<String>>,
     bookmark_tags: HashMap<String, HashSet<String>>,
+    on_progress: Option<Box<dyn Fn(ProgressEvent)>>,
 }
 
 impl GraphBuilder {
@@ -37,6 +51,7 @@ impl GraphBuilder {
         Self {
             config,
             domain_counts: HashMap::new(),
+            domain_metadata: HashMap::new(),
             folder_counts: HashMap::new(),
             tag_counts: HashMap::new(),
             category_counts: HashMap::new(),
@@ -45,12 +60,31 @@ impl GraphBuilder {
             tag_to_bookmarks: HashMap::new(),
             category_to_bookmarks: HashMap::new(),
             bookmark_tags: HashMap::new(),
+            on_progress: None,
+        }
+    }
+
+    /// Report the `ingest` and `finalize` stages to `on_progress`
+    pub fn with_progress(mut self, on_progress: impl Fn(ProgressEvent) + 'static) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    fn stage_finished(&self, stage: &str, input: usize, output: usize, start: Instant) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(ProgressEvent::StageFinished {
+                stage: stage.to_string(),
+                input,
+                output,
+                duration: start.elapsed(),
+            });
         }
     }
 
     /// Build a graph from bookmarks
     pub fn from_bookmarks(&mut self, bookmarks: &[Bookmark]) -> Result<KnowledgeGraph> {
-        let filtered = self.filter_bookmarks(bookmarks);
+        let bookmarks = flatten_bookmarks(bookmarks);
+        let filtered = self.filter_bookmarks(&bookmarks);
         let items: Vec<IngestItem> = filtered
             .iter()
             .map(|b| IngestItem {
@@ -59,6 +93,12 @@ impl GraphBuilder {
                 url: b.url.as_deref(),
                 folder: b.folder.as_deref(),
                 size: 1,
+                date_added: b.date_added,
+                visit_count: None,
+                last_visit: None,
+                bookmark_count: 1,
+                language: b.github.as_ref().and_then(|g| g.language.as_deref()),
+                page: b.page.as_ref(),
             })
             .collect();
 
@@ -80,6 +120,12 @@ impl GraphBuilder {
                 url: Some(e.url.as_str()),
                 folder: None,
                 size: e.visit_count as usize,
+                date_added: None,
+                visit_count: Some(e.visit_count),
+                last_visit: e.last_visit,
+                bookmark_count: 0,
+                language: None,
+                page: None,
             })
             .collect();
 
@@ -93,6 +139,12 @@ impl GraphBuilder {
         bookmarks: &[Bookmark],
         history: &[UrlEntry],
     ) -> Result<KnowledgeGraph> {
+        // Join visit counts and latest visits onto bookmarks by URL
+        let visits: HashMap<&str, &UrlEntry> = history
+            .iter()
+            .map(|e| (e.url.as_str(), e))
+            .collect();
+
         let mut items: Vec<IngestItem> = bookmarks
             .iter()
             .map(|b| IngestItem {
@@ -101,6 +153,12 @@ impl GraphBuilder {
                 url: b.url.as_deref(),
                 folder: b.folder.as_deref(),
                 size: 1,
+                date_added: b.date_added,
+                visit_count: b.url.as_deref().and_then(|u| visits.get(u)).map(|e| e.visit_count),
+                last_visit: b.url.as_deref().and_then(|u| visits.get(u)).and_then(|e| e.last_visit),
+                bookmark_count: 1,
+                language: b.github.as_ref().and_then(|g| g.language.as_deref()),
+                page: b.page.as_ref(),
             })
             .collect();
 
@@ -110,6 +168,12 @@ impl GraphBuilder {
             url: Some(e.url.as_str()),
             folder: None,
             size: e.visit_count as usize,
+            date_added: None,
+            visit_count: Some(e.visit_count),
+            last_visit: e.last_visit,
+            bookmark_count: 0,
+            language: None,
+            page: None,
         }));
 
         let nodes = self.ingest_items(&items, true);
@@ -118,10 +182,11 @@ impl GraphBuilder {
 
     /// Unified ingestion: track stats and optionally create bookmark nodes
     fn ingest_items(&mut self, items: &[IngestItem], create_nodes: bool) -> Vec<GraphNode> {
+        let start = Instant::now();
         let mut nodes = Vec::new();
 
         for item in items {
-            let domain = item.url.and_then(analyzer::extract_domain);
+            let domain = item.url.and_then(|u| self.config.sites.canonical_host(u));
 
             // Track domain
             if let Some(ref d) = domain {
@@ -130,6 +195,18 @@ impl GraphBuilder {
                     .entry(d.clone())
                     .or_default()
                     .push(item.id.clone());
+
+                let meta = self.domain_metadata.entry(d.clone()).or_default();
+                meta.bookmark_count += item.bookmark_count;
+                if let Some(date) = item.date_added {
+                    meta.date_added = Some(meta.date_added.map_or(date, |d| d.min(date)));
+                }
+                // Bookmarks carry visits joined from history; count each visit once
+                if item.bookmark_count == 0
+                    && let Some(visits) = item.visit_count
+                {
+                    meta.visit_count = Some(meta.visit_count.unwrap_or(0) + visits);
+                }
             }
 
             // Track folder
@@ -141,8 +218,13 @@ impl GraphBuilder {
                     .push(item.id.clone());
             }
 
-            // Extract tags
-            let tags = analyzer::extract_tags(item.title, item.url);
+            // Extract tags; repositories also group under their language
+            let mut tags = analyzer::extract_tags(item.title, item.url, item.page);
+            if let Some(language) = item.language.map(str::to_lowercase)
+                && !tags.contains(&language)
+            {
+                tags.push(language);
+            }
             for tag in &tags {
                 *self.tag_counts.entry(tag.clone()).or_insert(0) += 1;
                 self.tag_to_bookmarks
@@ -154,7 +236,8 @@ impl GraphBuilder {
                 .insert(item.id.clone(), tags.into_iter().collect());
 
             // Assign category
-            let category = analyzer::categorize(item.title, item.url, domain.as_deref());
+            let category =
+                analyzer::categorize(item.title, item.url, domain.as_deref(), item.page);
             *self.category_counts.entry(category.clone()).or_insert(0) += 1;
             self.category_to_bookmarks
                 .entry(category.clone())
@@ -170,10 +253,19 @@ impl GraphBuilder {
                     domain: domain.clone(),
                     folder: item.folder.map(|s| s.to_string()),
                     size: item.size,
+                    metadata: NodeMetadata {
+                        date_added: item.date_added,
+                        visit_count: item.visit_count,
+                        bookmark_count: item.bookmark_count,
+                        last_visit: item.last_visit,
+                    },
+                    color: None,
+                    position: None,
                 });
             }
         }
 
+        self.stage_finished("ingest", items.len(), nodes.len(), start);
         nodes
     }
 
@@ -204,17 +296,17 @@ impl GraphBuilder {
                     let domain = bookmark
                         .url
                         .as_ref()
-                        .and_then(|u| analyzer::extract_domain(u))
+                        .and_then(|u| self.config.sites.canonical_host(u))
                         .unwrap_or_else(|| "other".to_string());
                     let count = domain_counts.entry(domain).or_insert(0);
                     let under_per_domain = self
                         .config
                         .max_bookmarks_per_domain
-                        .map_or(true, |max| *count < max);
+                        .is_none_or(|max| *count < max);
                     let under_total = self
                         .config
                         .max_total_bookmarks
-                        .map_or(true, |max| result.len() < max);
+                        .is_none_or(|max| result.len() < max);
                     if under_per_domain && under_total {
                         result.push(bookmark);
                         *count += 1;
@@ -238,6 +330,10 @@ impl GraphBuilder {
         mut nodes: Vec<GraphNode>,
         bookmark_count: usize,
     ) -> Result<KnowledgeGraph> {
+        let start = Instant::now();
+        let input = nodes.len();
+        self.check_size_limits(nodes.len())?;
+
         let mut edges = Vec::new();
 
         // Create aggregate nodes
@@ -251,6 +347,10 @@ impl GraphBuilder {
 
         nodes.extend(self.create_tag_nodes());
         nodes.extend(self.create_category_nodes());
+        self.config.theme.apply(&mut nodes);
+        if let Some(heatmap) = self.config.heatmap {
+            heatmap.apply(&mut nodes, Utc::now());
+        }
 
         // Create edges
         if self.config.include_domain_edges {
@@ -279,7 +379,9 @@ impl GraphBuilder {
             domain_count,
             folder_count,
             generated_at: Utc::now(),
+            config: self.config.summary(),
         };
+        self.stage_finished("finalize", input, nodes.len(), start);
 
         Ok(KnowledgeGraph {
             nodes,
@@ -288,6 +390,120 @@ impl GraphBuilder {
         })
     }
 
+    /// Estimate the final graph size from ingestion stats and fail before
+    /// materializing anything that would blow past the configured limits.
+    /// Similarity edges are data-dependent and not included in the estimate.
+    fn check_size_limits(&self, bookmark_nodes: usize) -> Result<(), GraphSizeError> {
+        let threshold = self.config.min_domain_threshold;
+        let domain_nodes = self
+            .domain_counts
+            .values()
+            .filter(|&&c| c >= threshold)
+            .count();
+        let tag_nodes = self
+            .tag_counts
+            .values()
+            .filter(|&&c| c >= self.config.min_tag_threshold)
+            .count();
+        let estimated_nodes = bookmark_nodes
+            + domain_nodes
+            + self.folder_counts.len()
+            + tag_nodes
+            + self.category_counts.len();
+
+        let mut contributions: Vec<(usize, String)> = Vec::new();
+        if self.config.include_domain_edges {
+            let n = self
+                .domain_counts
+                .values()
+                .filter(|&&c| c >= threshold)
+                .sum();
+            contributions.push((
+                n,
+                "--domain-only (collapse bookmarks into their domains)".to_string(),
+            ));
+        }
+        if self.config.include_folder_edges {
+            let n = self.folder_counts.values().sum();
+            contributions.push((n, "--detail overview (domains and folders only)".to_string()));
+        }
+        if self.config.include_same_domain_edges {
+            let n = self
+                .domain_to_bookmarks
+                .iter()
+                .map(|(domain, ids)| {
+                    let n = ids.len();
+                    if self.config.aggregate_edges && n > 1 && self.has_domain_node(domain) {
+                        if self.config.include_domain_edges { 0 } else { n }
+                    } else {
+                        n * n.saturating_sub(1) / 2
+                    }
+                })
+                .sum();
+            let hint = if self.config.aggregate_edges {
+                "--domain-only (collapse bookmarks into their domains)"
+            } else {
+                "--aggregate-edges (count same-domain links on the domain edges)"
+            };
+            contributions.push((n, hint.to_string()));
+        }
+        if self.config.include_tag_edges {
+            let n = self
+                .tag_counts
+                .values()
+                .filter(|&&c| c >= self.config.min_tag_threshold)
+                .sum();
+            contributions.push((n, "leave out --tags".to_string()));
+        }
+        if self.config.include_category_edges {
+            let n = self.category_counts.values().sum();
+            contributions.push((n, "--no-categories".to_string()));
+        }
+        let estimated_edges: usize = contributions.iter().map(|(n, _)| n).sum();
+
+        let (exceeded, limit) = match (self.config.max_nodes, self.config.max_edges) {
+            (Some(max), _) if estimated_nodes > max => ("node", max),
+            (_, Some(max)) if estimated_edges > max => ("edge", max),
+            _ => return Ok(()),
+        };
+
+        let mut suggestions = Vec::new();
+        if exceeded == "edge" {
+            contributions.sort_by_key(|(n, _)| std::cmp::Reverse(*n));
+            suggestions.extend(
+                contributions
+                    .into_iter()
+                    .filter(|(n, _)| *n > 0)
+                    .map(|(_, s)| s),
+            );
+        }
+        if bookmark_nodes > 0 {
+            suggestions.push("--domain-only (collapse bookmarks into their domains)".to_string());
+            suggestions.push(match self.config.max_total_bookmarks {
+                Some(max) => format!("a lower --max-total (currently {})", max),
+                None => "--max-total N".to_string(),
+            });
+        }
+        suggestions.push(format!(
+            "a higher --min-threshold (currently {})",
+            threshold
+        ));
+        suggestions.push(format!(
+            "a higher --max-{}s if the machine can handle it",
+            exceeded
+        ));
+        let mut seen = HashSet::new();
+        suggestions.retain(|s| seen.insert(s.clone()));
+
+        Err(GraphSizeError {
+            estimated_nodes,
+            estimated_edges,
+            exceeded,
+            limit,
+            suggestions,
+        })
+    }
+
     // --- Node creators ---
 
     fn create_domain_nodes(&self) -> Vec<GraphNode> {
@@ -302,6 +518,13 @@ impl GraphBuilder {
                 domain: Some(domain.clone()),
                 folder: None,
                 size: count,
+                metadata: self
+                    .domain_metadata
+                    .get(domain)
+                    .cloned()
+                    .unwrap_or_default(),
+                color: None,
+                position: None,
             })
             .collect()
     }
@@ -317,6 +540,12 @@ impl GraphBuilder {
                 domain: None,
                 folder: Some(folder.clone()),
                 size: count,
+                metadata: NodeMetadata {
+                    bookmark_count: count,
+                    ..Default::default()
+                },
+                color: None,
+                position: None,
             })
             .collect()
     }
@@ -333,6 +562,12 @@ impl GraphBuilder {
                 domain: None,
                 folder: None,
                 size: count,
+                metadata: NodeMetadata {
+                    bookmark_count: count,
+                    ..Default::default()
+                },
+                color: None,
+                position: None,
             })
             .collect()
     }
@@ -348,6 +583,12 @@ impl GraphBuilder {
                 domain: None,
                 folder: None,
                 size: count,
+                metadata: NodeMetadata {
+                    bookmark_count: count,
+                    ..Default::default()
+                },
+                color: None,
+                position: None,
             })
             .collect()
     }
@@ -356,20 +597,30 @@ impl GraphBuilder {
 
     fn create_domain_edges(&self, edges: &mut Vec<GraphEdge>) {
         for (domain, bookmark_ids) in &self.domain_to_bookmarks {
-            if *self.domain_counts.get(domain).unwrap_or(&0) >= self.config.min_domain_threshold {
-                let domain_id = format!("domain_{}", domain);
-                for bookmark_id in bookmark_ids {
-                    edges.push(GraphEdge {
-                        source: bookmark_id.clone(),
-                        target: domain_id.clone(),
-                        edge_type: EdgeType::BelongsToDomain,
-                        weight: 1.0,
-                    });
-                }
+            if self.has_domain_node(domain) {
+                self.push_domain_edges(domain, bookmark_ids, edges);
             }
         }
     }
 
+    /// Link each bookmark to its domain node. When same-domain edges are
+    /// aggregated, these edges carry the size of the clique they replace.
+    fn push_domain_edges(&self, domain: &str, bookmark_ids: &[String], edges: &mut Vec<GraphEdge>) {
+        let n = bookmark_ids.len();
+        let count = (self.config.include_same_domain_edges && self.config.aggregate_edges && n > 1)
+            .then(|| n - 1);
+        let domain_id = format!("domain_{}", domain);
+        for bookmark_id in bookmark_ids {
+            edges.push(GraphEdge {
+                source: bookmark_id.clone(),
+                target: domain_id.clone(),
+                edge_type: EdgeType::BelongsToDomain,
+                weight: 1.0,
+                count,
+            });
+        }
+    }
+
     fn create_folder_edges(&self, edges: &mut Vec<GraphEdge>) {
         for (folder, bookmark_ids) in &self.folder_to_bookmarks {
             let folder_id = format!("folder_{}", folder.replace('/', "_"));
@@ -379,14 +630,21 @@ impl GraphBuilder {
                     target: folder_id.clone(),
                     edge_type: EdgeType::InFolder,
                     weight: 1.0,
+                    count: None,
                 });
             }
         }
     }
 
     fn create_same_domain_edges(&self, edges: &mut Vec<GraphEdge>) {
-        for bookmark_ids in self.domain_to_bookmarks.values() {
-            if bookmark_ids.len() > 1 {
+        for (domain, bookmark_ids) in &self.domain_to_bookmarks {
+            let n = bookmark_ids.len();
+            if self.config.aggregate_edges && n > 1 && self.has_domain_node(domain) {
+                // The bookmarks' domain edges stand in for the n*(n-1)/2 clique
+                if !self.config.include_domain_edges {
+                    self.push_domain_edges(domain, bookmark_ids, edges);
+                }
+            } else if n > 1 {
                 for i in 0..bookmark_ids.len() {
                     for j in (i + 1)..bookmark_ids.len() {
                         edges.push(GraphEdge {
@@ -394,6 +652,7 @@ impl GraphBuilder {
                             target: bookmark_ids[j].clone(),
                             edge_type: EdgeType::SameDomain,
                             weight: 0.5,
+                            count: None,
                         });
                     }
                 }
@@ -411,6 +670,7 @@ impl GraphBuilder {
                         target: tag_id.clone(),
                         edge_type: EdgeType::HasTag,
                         weight: 0.8,
+                        count: None,
                     });
                 }
             }
@@ -426,12 +686,26 @@ impl GraphBuilder {
                     target: cat_id.clone(),
                     edge_type: EdgeType::InCategory,
                     weight: 0.7,
+                    count: None,
                 });
             }
         }
     }
 
     fn create_similarity_edges(&self, edges: &mut Vec<GraphEdge>) {
+        // Domain of each bookmark, used to roll similarity up when aggregating
+        let bookmark_domain: HashMap<&str, &str> = if self.config.aggregate_edges {
+            self.domain_to_bookmarks
+                .iter()
+                .filter(|(domain, _)| self.has_domain_node(domain))
+                .flat_map(|(domain, ids)| ids.iter().map(move |id| (id.as_str(), domain.as_str())))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        // (domain_a, domain_b) -> (summed weight, pair count)
+        let mut domain_pairs: HashMap<(&str, &str), (f64, usize)> = HashMap::new();
+
         let bookmark_ids: Vec<&String> = self.bookmark_tags.keys().collect();
         for i in 0..bookmark_ids.len() {
             for j in (i + 1)..bookmark_ids.len() {
@@ -439,15 +713,40 @@ impl GraphBuilder {
                 let tags_b = &self.bookmark_tags[bookmark_ids[j]];
                 let jaccard = analyzer::jaccard_similarity(tags_a, tags_b);
                 if jaccard >= self.config.similarity_threshold {
+                    if let (Some(&da), Some(&db)) = (
+                        bookmark_domain.get(bookmark_ids[i].as_str()),
+                        bookmark_domain.get(bookmark_ids[j].as_str()),
+                    ) {
+                        let key = if da <= db { (da, db) } else { (db, da) };
+                        let entry = domain_pairs.entry(key).or_insert((0.0, 0));
+                        entry.0 += jaccard;
+                        entry.1 += 1;
+                        continue;
+                    }
                     edges.push(GraphEdge {
                         source: bookmark_ids[i].clone(),
                         target: bookmark_ids[j].clone(),
                         edge_type: EdgeType::SimilarContent,
                         weight: jaccard,
+                        count: None,
                     });
                 }
             }
         }
+
+        for ((da, db), (total, count)) in domain_pairs {
+            edges.push(GraphEdge {
+                source: format!("domain_{}", da),
+                target: format!("domain_{}", db),
+                edge_type: EdgeType::SimilarContent,
+                weight: total / count as f64,
+                count: Some(count),
+            });
+        }
+    }
+
+    fn has_domain_node(&self, domain: &str) -> bool {
+        self.domain_counts.get(domain).copied().unwrap_or(0) >= self.config.min_domain_threshold
     }
 
     // --- Public helpers for backward compatibility ---
@@ -457,10 +756,10 @@ impl GraphBuilder {
     }
 
     pub fn extract_tags(&self, title: &str, url: Option<&str>) -> Vec<String> {
-        analyzer::extract_tags(title, url)
+        analyzer::extract_tags(title, url, None)
     }
 
     pub fn categorize(&self, title: &str, url: Option<&str>, domain: Option<&str>) -> String {
-        analyzer::categorize(title, url, domain)
+        analyzer::categorize(title, url, domain, None)
     }
 }
@@ -0,0 +1,214 @@
+use chrono::Utc;
+use std::collections::{BTreeMap, HashMap};
+
+use super::{
+    EdgeType, GraphEdge, GraphMetadata, GraphNode, KnowledgeGraph, NodeMetadata, NodeType,
+    extract_domain,
+};
+use crate::exporter::{Bookmark, flatten_bookmarks};
+
+/// Fill of a bookmark the run dropped, as a duplicate or otherwise
+pub const REMOVED_COLOR: &str = "#ef5350";
+/// Fill of a bookmark the run filed in another folder
+pub const MOVED_COLOR: &str = "#ffb74d";
+/// Fill of a folder the run left without bookmarks
+pub const EMPTIED_COLOR: &str = "#9e9e9e";
+
+/// What a run did to one input bookmark
+enum Fate {
+    /// Kept, as the output bookmark at this index
+    Kept(usize),
+    /// Dropped as a copy of the output bookmark at this index
+    Duplicate(usize),
+    /// Dropped without a copy left, e.g. by expiration
+    Dropped,
+}
+
+impl KnowledgeGraph {
+    /// A before/after picture of a `process` run over `before`, which gave
+    /// `after`. Every output bookmark hangs off its folder; one filed
+    /// elsewhere is drawn in [`MOVED_COLOR`] with a `movedfrom` edge to its
+    /// old folder too. Input bookmarks the run dropped stay in their old
+    /// folder in [`REMOVED_COLOR`], with a `duplicateof` edge to the copy
+    /// that was kept. Bookmarks are matched on `key`, the normalized URL
+    /// deduplication compares, preferring an identical URL.
+    pub fn visual_diff(
+        before: &[Bookmark],
+        after: &[Bookmark],
+        key: impl Fn(&str) -> String,
+    ) -> KnowledgeGraph {
+        let before: Vec<Bookmark> = flatten_bookmarks(before)
+            .into_iter()
+            .filter(|b| b.url.as_deref().is_some_and(|u| !u.is_empty()))
+            .collect();
+        let after: Vec<Bookmark> = flatten_bookmarks(after)
+            .into_iter()
+            .filter(|b| b.url.as_deref().is_some_and(|u| !u.is_empty()))
+            .collect();
+
+        let mut by_key: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, bookmark) in after.iter().enumerate() {
+            by_key
+                .entry(key(bookmark.url.as_deref().unwrap_or_default()))
+                .or_default()
+                .push(i);
+        }
+        let mut claimed = vec![false; after.len()];
+        let fates: Vec<Fate> = before
+            .iter()
+            .map(|bookmark| {
+                let url = bookmark.url.as_deref().unwrap_or_default();
+                let Some(candidates) = by_key.get(&key(url)) else {
+                    return Fate::Dropped;
+                };
+                let unclaimed = |&&i: &&usize| !claimed[i];
+                let pick = candidates
+                    .iter()
+                    .filter(unclaimed)
+                    .find(|&&i| after[i].url.as_deref() == Some(url))
+                    .or_else(|| candidates.iter().find(unclaimed))
+                    .copied();
+                match pick {
+                    Some(i) => {
+                        claimed[i] = true;
+                        Fate::Kept(i)
+                    }
+                    None => Fate::Duplicate(candidates[0]),
+                }
+            })
+            .collect();
+
+        let mut old_folder: Vec<Option<&str>> = vec![None; after.len()];
+        for (bookmark, fate) in before.iter().zip(&fates) {
+            if let Fate::Kept(i) = fate {
+                old_folder[*i] = Some(folder_of(bookmark));
+            }
+        }
+
+        let mut folder_counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for bookmark in &before {
+            folder_counts.entry(folder_of(bookmark)).or_default();
+        }
+        for bookmark in &after {
+            *folder_counts.entry(folder_of(bookmark)).or_default() += 1;
+        }
+
+        let mut nodes: Vec<GraphNode> = folder_counts
+            .iter()
+            .map(|(&folder, &count)| GraphNode {
+                id: folder_id(folder),
+                title: folder_title(folder).to_string(),
+                node_type: NodeType::Folder,
+                url: None,
+                domain: None,
+                folder: Some(folder.to_string()),
+                size: count.max(1),
+                metadata: NodeMetadata {
+                    bookmark_count: count,
+                    ..Default::default()
+                },
+                color: (count == 0).then(|| EMPTIED_COLOR.to_string()),
+                position: None,
+            })
+            .collect();
+        let mut edges = Vec::new();
+        let edge = |source: String, target: String, edge_type, weight| GraphEdge {
+            source,
+            target,
+            edge_type,
+            weight,
+            count: None,
+        };
+
+        let mut moved = 0;
+        for (i, bookmark) in after.iter().enumerate() {
+            let id = format!("after_{}", i);
+            let folder = folder_of(bookmark);
+            let from = old_folder[i].filter(|&old| old != folder);
+            nodes.push(bookmark_node(&id, bookmark, from.map(|_| MOVED_COLOR)));
+            edges.push(edge(id.clone(), folder_id(folder), EdgeType::InFolder, 1.0));
+            if let Some(from) = from {
+                moved += 1;
+                edges.push(edge(id, folder_id(from), EdgeType::MovedFrom, 0.5));
+            }
+        }
+
+        let mut removed = 0;
+        for (i, (bookmark, fate)) in before.iter().zip(&fates).enumerate() {
+            if let Fate::Kept(_) = fate {
+                continue;
+            }
+            removed += 1;
+            let id = format!("before_{}", i);
+            nodes.push(bookmark_node(&id, bookmark, Some(REMOVED_COLOR)));
+            edges.push(edge(
+                id.clone(),
+                folder_id(folder_of(bookmark)),
+                EdgeType::InFolder,
+                0.5,
+            ));
+            if let Fate::Duplicate(kept) = fate {
+                edges.push(edge(
+                    id,
+                    format!("after_{}", kept),
+                    EdgeType::DuplicateOf,
+                    0.5,
+                ));
+            }
+        }
+
+        let config = BTreeMap::from([
+            ("diff".to_string(), "process".to_string()),
+            ("removed".to_string(), removed.to_string()),
+            ("moved".to_string(), moved.to_string()),
+            ("unchanged".to_string(), (after.len() - moved).to_string()),
+        ]);
+        KnowledgeGraph {
+            metadata: GraphMetadata {
+                total_nodes: nodes.len(),
+                total_edges: edges.len(),
+                bookmark_count: after.len() + removed,
+                domain_count: 0,
+                folder_count: folder_counts.len(),
+                generated_at: Utc::now(),
+                config,
+            },
+            nodes,
+            edges,
+        }
+    }
+}
+
+fn folder_of(bookmark: &Bookmark) -> &str {
+    bookmark.folder.as_deref().unwrap_or_default()
+}
+
+fn folder_id(folder: &str) -> String {
+    format!("folder_{}", folder)
+}
+
+fn folder_title(folder: &str) -> &str {
+    match folder {
+        "" => "(no folder)",
+        folder => folder,
+    }
+}
+
+fn bookmark_node(id: &str, bookmark: &Bookmark, color: Option<&str>) -> GraphNode {
+    let url = bookmark.url.clone().unwrap_or_default();
+    GraphNode {
+        id: id.to_string(),
+        title: bookmark.title.clone(),
+        node_type: NodeType::Bookmark,
+        domain: extract_domain(&url),
+        url: Some(url),
+        folder: bookmark.folder.clone(),
+        size: 1,
+        metadata: NodeMetadata {
+            date_added: bookmark.date_added,
+            ..Default::default()
+        },
+        color: color.map(str::to_string),
+        position: None,
+    }
+}
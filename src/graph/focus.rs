@@ -0,0 +1,71 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::{KnowledgeGraph, NodeType};
+
+impl KnowledgeGraph {
+    /// Extract the neighborhood reachable from `seeds` within `depth` hops.
+    ///
+    /// Edges are followed in both directions. Only edges whose endpoints are
+    /// both kept survive, and the metadata counts are recomputed for the subgraph.
+    pub fn neighborhood(&self, seeds: &[String], depth: usize) -> KnowledgeGraph {
+        let mut adjacency: HashMap<&str, Vec<&str>> = HashMap::new();
+        for edge in &self.edges {
+            adjacency
+                .entry(edge.source.as_str())
+                .or_default()
+                .push(edge.target.as_str());
+            adjacency
+                .entry(edge.target.as_str())
+                .or_default()
+                .push(edge.source.as_str());
+        }
+
+        let known: HashSet<&str> = self.nodes.iter().map(|n| n.id.as_str()).collect();
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut queue: VecDeque<(&str, usize)> = VecDeque::new();
+
+        for seed in seeds {
+            if known.contains(seed.as_str()) && visited.insert(seed.as_str()) {
+                queue.push_back((seed.as_str(), 0));
+            }
+        }
+
+        while let Some((id, dist)) = queue.pop_front() {
+            if dist >= depth {
+                continue;
+            }
+            for &next in adjacency.get(id).into_iter().flatten() {
+                if visited.insert(next) {
+                    queue.push_back((next, dist + 1));
+                }
+            }
+        }
+
+        let nodes: Vec<_> = self
+            .nodes
+            .iter()
+            .filter(|n| visited.contains(n.id.as_str()))
+            .cloned()
+            .collect();
+        let edges: Vec<_> = self
+            .edges
+            .iter()
+            .filter(|e| visited.contains(e.source.as_str()) && visited.contains(e.target.as_str()))
+            .cloned()
+            .collect();
+
+        let count = |t: NodeType| nodes.iter().filter(|n| n.node_type == t).count();
+        let mut metadata = self.metadata.clone();
+        metadata.total_nodes = nodes.len();
+        metadata.total_edges = edges.len();
+        metadata.bookmark_count = count(NodeType::Bookmark);
+        metadata.domain_count = count(NodeType::Domain);
+        metadata.folder_count = count(NodeType::Folder);
+
+        KnowledgeGraph {
+            nodes,
+            edges,
+            metadata,
+        }
+    }
+}
@@ -1,21 +1,27 @@
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 
-use super::{EdgeType, KnowledgeGraph, NodeType};
+use super::{EdgeType, KnowledgeGraph, NodeType, theme};
 
 /// Export graph to DOT format (Graphviz)
+///
+/// The graph label carries the metadata block and a `cluster_legend` subgraph
+/// shows the node and edge styles present, so rendered images are self-describing.
 pub fn to_dot(graph: &KnowledgeGraph) -> String {
     let mut dot = String::from("digraph BookmarkKnowledgeGraph {\n");
     dot.push_str("    rankdir=LR;\n");
-    dot.push_str("    node [shape=box];\n\n");
+    dot.push_str("    node [shape=box];\n");
+    dot.push_str(&format!(
+        "    label=\"{}\";\n    labelloc=b;\n    labeljust=l;\n    fontsize=10;\n\n",
+        dot_metadata_label(graph)
+    ));
 
     for node in &graph.nodes {
-        let (color, shape) = match node.node_type {
-            NodeType::Bookmark => ("lightblue", "box"),
-            NodeType::Domain => ("lightgreen", "ellipse"),
-            NodeType::Folder => ("lightyellow", "folder"),
-            NodeType::Tag => ("lightsalmon", "diamond"),
-            NodeType::Category => ("plum", "octagon"),
+        let (default_color, shape) = dot_node_style(node.node_type);
+        let color = match &node.color {
+            Some(color) => format!("\"{}\"", color),
+            None => default_color.to_string(),
         };
         dot.push_str(&format!(
             "    \"{}\" [label=\"{}\", fillcolor={}, style=filled, shape={}];\n",
@@ -26,16 +32,13 @@ pub fn to_dot(graph: &KnowledgeGraph) -> String {
         ));
     }
 
-    dot.push_str("\n");
+    dot.push('\n');
 
     for edge in &graph.edges {
-        let style = match edge.edge_type {
-            EdgeType::BelongsToDomain => "[color=blue, penwidth=2]",
-            EdgeType::InFolder => "[color=green, penwidth=1]",
-            EdgeType::SameDomain => "[color=gray, penwidth=0.5, style=dashed]",
-            EdgeType::HasTag => "[color=orange, penwidth=1, style=dotted]",
-            EdgeType::InCategory => "[color=purple, penwidth=1.5]",
-            EdgeType::SimilarContent => "[color=red, penwidth=0.5, style=dashed]",
+        let style = dot_edge_style(edge.edge_type);
+        let style = match edge.count {
+            Some(count) => style.replacen(']', &format!(", label=\"x{}\"]", count), 1),
+            None => style.to_string(),
         };
         dot.push_str(&format!(
             "    \"{}\" -> \"{}\" {};\n",
@@ -45,48 +48,169 @@ pub fn to_dot(graph: &KnowledgeGraph) -> String {
         ));
     }
 
+    dot.push_str(&dot_legend(graph));
     dot.push_str("}\n");
     dot
 }
 
-/// Export graph to JSON format
-pub fn to_json(graph: &KnowledgeGraph) -> String {
-    #[derive(Serialize)]
-    struct JsonGraph {
-        nodes: Vec<JsonNode>,
-        edges: Vec<JsonEdge>,
-        metadata: JsonMetadata,
+fn dot_node_style(node_type: NodeType) -> (&'static str, &'static str) {
+    match node_type {
+        NodeType::Bookmark => ("lightblue", "box"),
+        NodeType::Domain => ("lightgreen", "ellipse"),
+        NodeType::Folder => ("lightyellow", "folder"),
+        NodeType::Tag => ("lightsalmon", "diamond"),
+        NodeType::Category => ("plum", "octagon"),
     }
+}
 
-    #[derive(Serialize)]
-    struct JsonNode {
-        id: String,
-        title: String,
-        node_type: String,
-        url: Option<String>,
-        domain: Option<String>,
-        folder: Option<String>,
-        size: usize,
+fn dot_edge_style(edge_type: EdgeType) -> &'static str {
+    match edge_type {
+        EdgeType::BelongsToDomain => "[color=blue, penwidth=2]",
+        EdgeType::InFolder => "[color=green, penwidth=1]",
+        EdgeType::SameDomain => "[color=gray, penwidth=0.5, style=dashed]",
+        EdgeType::HasTag => "[color=orange, penwidth=1, style=dotted]",
+        EdgeType::InCategory => "[color=purple, penwidth=1.5]",
+        EdgeType::SimilarContent => "[color=red, penwidth=0.5, style=dashed]",
+        EdgeType::MovedFrom => "[color=orange, penwidth=1, style=dashed]",
+        EdgeType::DuplicateOf => "[color=red, penwidth=1, style=dotted]",
     }
+}
 
-    #[derive(Serialize)]
-    struct JsonEdge {
-        source: String,
-        target: String,
-        edge_type: String,
-        weight: f64,
+/// Left-justified (`\l`) lines: title, generation time, counts, then config
+fn dot_metadata_label(graph: &KnowledgeGraph) -> String {
+    let meta = &graph.metadata;
+    let mut lines = vec![
+        "Bookmark Knowledge Graph".to_string(),
+        format!(
+            "Generated: {}",
+            meta.generated_at.format("%Y-%m-%d %H:%M UTC")
+        ),
+        format!(
+            "Nodes: {}  Edges: {}  Bookmarks: {}  Domains: {}  Folders: {}",
+            meta.total_nodes,
+            meta.total_edges,
+            meta.bookmark_count,
+            meta.domain_count,
+            meta.folder_count
+        ),
+    ];
+    lines.extend(meta.config.iter().map(|(k, v)| format!("{}: {}", k, v)));
+    lines
+        .iter()
+        .map(|l| format!("{}\\l", escape_dot_label(l)))
+        .collect()
+}
+
+/// Legend cluster listing only the node and edge types that appear in the graph.
+/// A node type is drawn in its theme color when all its nodes share one.
+fn dot_legend(graph: &KnowledgeGraph) -> String {
+    let node_types: BTreeSet<NodeType> = graph.nodes.iter().map(|n| n.node_type).collect();
+    let edge_types: BTreeSet<EdgeType> = graph.edges.iter().map(|e| e.edge_type).collect();
+    if node_types.is_empty() && edge_types.is_empty() {
+        return String::new();
     }
 
-    #[derive(Serialize)]
-    struct JsonMetadata {
-        total_nodes: usize,
-        total_edges: usize,
-        bookmark_count: usize,
-        domain_count: usize,
-        folder_count: usize,
-        generated_at: DateTime<Utc>,
+    let mut legend = String::from(
+        "\n    subgraph cluster_legend {\n        label=\"Legend\";\n        style=dashed;\n        fontsize=10;\n",
+    );
+    for node_type in node_types {
+        let (default_color, shape) = dot_node_style(node_type);
+        let mut colors = graph
+            .nodes
+            .iter()
+            .filter(|n| n.node_type == node_type)
+            .map(|n| n.color.as_deref());
+        let first = colors.next().flatten();
+        let color = match first {
+            Some(color) if colors.all(|c| c == Some(color)) => format!("\"{}\"", color),
+            _ => default_color.to_string(),
+        };
+        legend.push_str(&format!(
+            "        \"legend_{:?}\" [label=\"{:?}\", fillcolor={}, style=filled, shape={}];\n",
+            node_type, node_type, color, shape
+        ));
+    }
+    if let Some(by) = graph.metadata.config.get("heatmap") {
+        legend.push_str(&format!(
+            "        \"legend_heatmap\" [label=\"Bookmark {}: old to recent\", fillcolor=\"{}:{}\", style=filled, shape=box];\n",
+            by,
+            theme::HEATMAP_STOPS[0],
+            theme::HEATMAP_STOPS[theme::HEATMAP_STOPS.len() - 1]
+        ));
+    }
+    for edge_type in edge_types {
+        let style = dot_edge_style(edge_type).replacen(
+            ']',
+            &format!(", label=\"{:?}\", fontsize=9]", edge_type),
+            1,
+        );
+        legend.push_str(&format!(
+            "        \"legend_{0:?}_from\" [shape=point, width=0.05];\n        \"legend_{0:?}_to\" [shape=point, width=0.05];\n        \"legend_{0:?}_from\" -> \"legend_{0:?}_to\" {1};\n",
+            edge_type, style
+        ));
     }
+    legend.push_str("    }\n");
+    legend
+}
+
+/// Export graph to JSON format
+pub fn to_json(graph: &KnowledgeGraph) -> String {
+    serde_json::to_string_pretty(&json_graph(graph)).unwrap_or_default()
+}
+
+#[derive(Serialize)]
+struct JsonGraph {
+    nodes: Vec<JsonNode>,
+    edges: Vec<JsonEdge>,
+    metadata: JsonMetadata,
+}
+
+#[derive(Serialize)]
+struct JsonNode {
+    id: String,
+    title: String,
+    node_type: String,
+    url: Option<String>,
+    domain: Option<String>,
+    folder: Option<String>,
+    size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_added: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    visit_count: Option<i64>,
+    bookmark_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    /// The laid-out position, which the HTML view starts from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    y: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct JsonEdge {
+    source: String,
+    target: String,
+    edge_type: String,
+    weight: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    count: Option<usize>,
+}
 
+#[derive(Serialize)]
+struct JsonMetadata {
+    total_nodes: usize,
+    total_edges: usize,
+    bookmark_count: usize,
+    domain_count: usize,
+    folder_count: usize,
+    generated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    config: BTreeMap<String, String>,
+}
+
+fn json_graph(graph: &KnowledgeGraph) -> JsonGraph {
     let json_nodes: Vec<JsonNode> = graph
         .nodes
         .iter()
@@ -98,6 +222,12 @@ pub fn to_json(graph: &KnowledgeGraph) -> String {
             domain: n.domain.clone(),
             folder: n.folder.clone(),
             size: n.size,
+            date_added: n.metadata.date_added,
+            visit_count: n.metadata.visit_count,
+            bookmark_count: n.metadata.bookmark_count,
+            color: n.color.clone(),
+            x: n.position.map(|(x, _)| round_position(x)),
+            y: n.position.map(|(_, y)| round_position(y)),
         })
         .collect();
 
@@ -109,10 +239,11 @@ pub fn to_json(graph: &KnowledgeGraph) -> String {
             target: e.target.clone(),
             edge_type: format!("{:?}", e.edge_type).to_lowercase(),
             weight: e.weight,
+            count: e.count,
         })
         .collect();
 
-    let json_graph = JsonGraph {
+    JsonGraph {
         nodes: json_nodes,
         edges: json_edges,
         metadata: JsonMetadata {
@@ -122,17 +253,21 @@ pub fn to_json(graph: &KnowledgeGraph) -> String {
             domain_count: graph.metadata.domain_count,
             folder_count: graph.metadata.folder_count,
             generated_at: graph.metadata.generated_at,
+            config: graph.metadata.config.clone(),
         },
-    };
-
-    serde_json::to_string_pretty(&json_graph).unwrap_or_default()
+    }
 }
 
 /// Export graph to GEXF format (Gephi)
+///
+/// Themed nodes carry a `viz:color`, which Gephi shows as the node color.
+///
+/// Nodes placed by [`KnowledgeGraph::apply_layout`] also get a
+/// `viz:position`, so Gephi opens the graph already spread out.
 pub fn to_gexf(graph: &KnowledgeGraph) -> String {
     let mut gexf = String::from(
         r#"<?xml version="1.0" encoding="UTF-8"?>
-<gexf xmlns="http://www.gexf.net/1.2draft" version="1.2">
+<gexf xmlns="http://www.gexf.net/1.2draft" xmlns:viz="http://www.gexf.net/1.2draft/viz" version="1.2">
     <graph mode="static" defaultedgetype="directed">
 "#,
     );
@@ -143,6 +278,12 @@ pub fn to_gexf(graph: &KnowledgeGraph) -> String {
             <attribute id="1" title="url" type="string"/>
             <attribute id="2" title="domain" type="string"/>
             <attribute id="3" title="folder" type="string"/>
+            <attribute id="4" title="date_added" type="string"/>
+            <attribute id="5" title="visit_count" type="long"/>
+            <attribute id="6" title="bookmark_count" type="integer"/>
+        </attributes>
+        <attributes class="edge">
+            <attribute id="0" title="count" type="integer"/>
         </attributes>
 "#,
     );
@@ -183,9 +324,59 @@ pub fn to_gexf(graph: &KnowledgeGraph) -> String {
             ));
         }
 
+        if let Some(date_added) = node.metadata.date_added {
+            gexf.push_str(&format!(
+                r#"
+                    <attvalue for="4" value="{}"/>"#,
+                date_added.to_rfc3339()
+            ));
+        }
+
+        if let Some(visit_count) = node.metadata.visit_count {
+            gexf.push_str(&format!(
+                r#"
+                    <attvalue for="5" value="{}"/>"#,
+                visit_count
+            ));
+        }
+
+        gexf.push_str(&format!(
+            r#"
+                    <attvalue for="6" value="{}"/>"#,
+            node.metadata.bookmark_count
+        ));
+
+        gexf.push_str(
+            r#"
+                </attvalues>"#,
+        );
+
+        let color = node
+            .color
+            .as_deref()
+            .unwrap_or(theme::default_color(node.node_type));
+        if let Some((r, g, b)) = theme::rgb(color) {
+            gexf.push_str(&format!(
+                r#"
+                <viz:color r="{}" g="{}" b="{}"/>"#,
+                r, g, b
+            ));
+        }
+        gexf.push_str(&format!(
+            r#"
+                <viz:size value="{:.1}"/>"#,
+            gexf_size(node.size)
+        ));
+        if let Some((x, y)) = node.position {
+            gexf.push_str(&format!(
+                r#"
+                <viz:position x="{:.2}" y="{:.2}" z="0.0"/>"#,
+                x, y
+            ));
+        }
+
         gexf.push_str(
             r#"
-                </attvalues>
             </node>"#,
         );
         gexf.push('\n');
@@ -195,13 +386,25 @@ pub fn to_gexf(graph: &KnowledgeGraph) -> String {
     gexf.push_str("        <edges>\n");
     for (i, edge) in graph.edges.iter().enumerate() {
         let edge_type_str = format!("{:?}", edge.edge_type).to_lowercase();
+        let close = match edge.count {
+            Some(count) => format!(
+                r#">
+                <attvalues>
+                    <attvalue for="0" value="{}"/>
+                </attvalues>
+            </edge>"#,
+                count
+            ),
+            None => "/>".to_string(),
+        };
         gexf.push_str(&format!(
-            r#"            <edge id="{}" source="{}" target="{}" weight="{}" label="{}"/>"#,
+            r#"            <edge id="{}" source="{}" target="{}" weight="{}" label="{}"{}"#,
             i,
             escape_xml(&edge.source),
             escape_xml(&edge.target),
             edge.weight,
-            escape_xml(&edge_type_str)
+            escape_xml(&edge_type_str),
+            close
         ));
         gexf.push('\n');
     }
@@ -211,19 +414,122 @@ pub fn to_gexf(graph: &KnowledgeGraph) -> String {
     gexf
 }
 
+/// Export graph to a Mermaid flowchart, for pasting into Markdown
+///
+/// Node ids are positional (`n0`, `n1`, ...) because Mermaid ids cannot carry
+/// the URLs and paths used as graph ids. Loose relations (same domain,
+/// similar content) are drawn as dotted edges.
+pub fn to_mermaid(graph: &KnowledgeGraph) -> String {
+    let ids: BTreeMap<&str, usize> = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id.as_str(), i))
+        .collect();
+
+    let mut mermaid = String::from("flowchart LR\n");
+    for (i, node) in graph.nodes.iter().enumerate() {
+        let label = escape_mermaid_label(&node.title);
+        let shape = match node.node_type {
+            NodeType::Bookmark => format!("[\"{}\"]", label),
+            NodeType::Domain => format!("([\"{}\"])", label),
+            NodeType::Folder => format!("[/\"{}\"/]", label),
+            NodeType::Tag => format!("{{\"{}\"}}", label),
+            NodeType::Category => format!("{{{{\"{}\"}}}}", label),
+        };
+        mermaid.push_str(&format!("    n{}{}\n", i, shape));
+    }
+
+    for edge in &graph.edges {
+        let (Some(source), Some(target)) =
+            (ids.get(edge.source.as_str()), ids.get(edge.target.as_str()))
+        else {
+            continue;
+        };
+        let arrow = match edge.edge_type {
+            EdgeType::SameDomain
+            | EdgeType::SimilarContent
+            | EdgeType::MovedFrom
+            | EdgeType::DuplicateOf => "-.->",
+            _ => "-->",
+        };
+        let label = edge
+            .count
+            .map(|count| format!("|x{}|", count))
+            .unwrap_or_default();
+        mermaid.push_str(&format!("    n{} {}{} n{}\n", source, arrow, label, target));
+    }
+    mermaid
+}
+
+/// Starting color scheme of the HTML visualization; the page's toggle button
+/// still switches between the two
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl ThemeMode {
+    fn body_class(self) -> &'static str {
+        match self {
+            ThemeMode::Dark => "dark",
+            ThemeMode::Light => "light",
+        }
+    }
+}
+
+/// Look of the HTML visualization
+#[derive(Debug, Clone, Default)]
+pub struct HtmlTheme {
+    pub mode: ThemeMode,
+    /// Stylesheet injected after the built-in one, so its rules win
+    pub custom_css: Option<String>,
+}
+
+impl HtmlTheme {
+    fn css(&self) -> String {
+        match &self.custom_css {
+            Some(css) => format!("{}\n<style>\n{}\n</style>", HTML_CSS, css),
+            None => HTML_CSS.to_string(),
+        }
+    }
+}
+
 /// Export graph to interactive HTML visualization using D3.js
-pub fn to_html(graph: &KnowledgeGraph) -> String {
-    let graph_json = to_json(graph);
+pub fn to_html(graph: &KnowledgeGraph, theme: &HtmlTheme) -> String {
     format!(
-        "{preamble}{graph_json}{postamble}",
-        preamble = HTML_PREAMBLE,
-        graph_json = graph_json,
-        postamble = HTML_POSTAMBLE
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="UTF-8">
+<meta name="viewport" content="width=device-width, initial-scale=1.0">
+<title>Bookmark Knowledge Graph</title>
+{css}
+</head>
+<body class="{body_class}">
+{controls}
+<svg id="graph" role="group" aria-label="Bookmark knowledge graph" aria-describedby="graph-help"></svg>
+<section id="list-view" aria-label="Graph as a list" hidden></section>
+<script src="https://d3js.org/d3.v7.min.js"></script>
+<script>
+const graphData = {graph_json};
+{d3_script}
+</script>
+</body>
+</html>"#,
+        css = theme.css(),
+        body_class = theme.mode.body_class(),
+        controls = HTML_CONTROLS,
+        graph_json = to_json(graph),
+        d3_script = D3_GRAPH_SCRIPT,
     )
 }
 
 /// Export graph to interactive HTML that loads data dynamically from JS file
-pub fn to_html_dynamic(data_path: &std::path::Path) -> String {
+pub fn to_html_dynamic(data_path: &std::path::Path, theme: &HtmlTheme) -> String {
     let data_filename = data_path
         .file_name()
         .and_then(|s| s.to_str())
@@ -238,34 +544,80 @@ pub fn to_html_dynamic(data_path: &std::path::Path) -> String {
 <title>Bookmark Knowledge Graph</title>
 {css}
 </head>
-<body class="dark">
-<div id="loading">
+<body class="{body_class}">
+<div id="loading" role="status">
   <div class="spinner"></div>
-  <div>Loading graph data...</div>
+  <div id="loading-text">Loading graph data...</div>
+  <progress id="loading-progress" value="0" hidden></progress>
 </div>
 {controls}
-<svg id="graph"></svg>
+<svg id="graph" role="group" aria-label="Bookmark knowledge graph" aria-describedby="graph-help"></svg>
+<section id="list-view" aria-label="Graph as a list" hidden></section>
 <script src="https://d3js.org/d3.v7.min.js"></script>
 <script src="{data_filename}"></script>
 <script>
-function initGraph() {{
-  if (typeof window.graphData === 'undefined') {{
-    document.getElementById('loading').innerHTML = '<div style="color:#ef5350">Error: Graph data not loaded. Make sure {data_filename} is in the same directory.</div>';
-    return;
+function loadError(file) {{
+  document.getElementById('loading').innerHTML = '<div style="color:#ef5350">Error: Graph data not loaded. Make sure ' + file + ' is in the same directory.</div>';
+}}
+
+// Chunked data: the manifest lists the files, loaded one after another so
+// the progress bar can update and no single file is too big to parse
+function loadChunks(manifest, done) {{
+  const data = {{ nodes: [], edges: [], metadata: manifest.metadata }};
+  const progress = document.getElementById('loading-progress');
+  const text = document.getElementById('loading-text');
+  progress.max = manifest.chunks.length;
+  progress.hidden = false;
+  window.graphChunks = window.graphChunks || [];
+  let loaded = 0;
+  function next() {{
+    if (loaded === manifest.chunks.length) {{
+      done(data);
+      return;
+    }}
+    const script = document.createElement('script');
+    script.src = manifest.chunks[loaded];
+    script.onload = () => {{
+      for (const chunk of window.graphChunks.splice(0)) {{
+        for (const n of chunk.nodes) data.nodes.push(n);
+        for (const e of chunk.edges) data.edges.push(e);
+      }}
+      script.remove();
+      loaded += 1;
+      progress.value = loaded;
+      text.textContent = `Loading graph data... ${{data.nodes.length}} of ${{manifest.total_nodes}} nodes, ${{data.edges.length}} of ${{manifest.total_edges}} edges`;
+      next();
+    }};
+    script.onerror = () => loadError(manifest.chunks[loaded]);
+    document.body.appendChild(script);
   }}
+  next();
+}}
+
+function initGraph(graphData) {{
   document.getElementById('loading').style.display = 'none';
-  const graphData = window.graphData;
   {d3_script}
 }}
+
+function start() {{
+  if (window.graphManifest) {{
+    loadChunks(window.graphManifest, initGraph);
+  }} else if (window.graphData) {{
+    initGraph(window.graphData);
+  }} else {{
+    loadError('{data_filename}');
+  }}
+}}
 if (document.readyState === 'loading') {{
-  document.addEventListener('DOMContentLoaded', initGraph);
+  document.addEventListener('DOMContentLoaded', start);
 }} else {{
-  initGraph();
+  start();
 }}
 </script>
 </body>
 </html>"#,
-        css = HTML_CSS,
+        css = theme.css(),
+        body_class = theme.mode.body_class(),
         controls = HTML_CONTROLS,
         data_filename = data_filename,
         d3_script = D3_GRAPH_SCRIPT,
@@ -281,11 +633,87 @@ pub fn to_js_data(graph: &KnowledgeGraph) -> String {
     )
 }
 
+/// Graph data split across several JavaScript files
+pub struct JsDataChunks {
+    /// Manifest for the data file `to_html_dynamic` references: metadata,
+    /// totals and the chunk file names in load order
+    pub manifest: String,
+    /// `(file name, contents)` of each chunk, relative to the manifest
+    pub chunks: Vec<(String, String)>,
+}
+
+/// Export graph data as a manifest and chunks of at most `chunk_size` nodes
+/// or edges each, named `<stem>.<n>.js` after the manifest's `data_filename`.
+/// Nodes come first, so every edge's endpoints exist once edges arrive.
+pub fn to_js_chunks(
+    graph: &KnowledgeGraph,
+    data_filename: &str,
+    chunk_size: usize,
+) -> JsDataChunks {
+    #[derive(Serialize)]
+    struct Chunk<'a> {
+        nodes: &'a [JsonNode],
+        edges: &'a [JsonEdge],
+    }
+
+    #[derive(Serialize)]
+    struct Manifest<'a> {
+        total_nodes: usize,
+        total_edges: usize,
+        metadata: &'a JsonMetadata,
+        chunks: Vec<String>,
+    }
+
+    let json = json_graph(graph);
+    let chunk_size = chunk_size.max(1);
+    let stem = data_filename.strip_suffix(".js").unwrap_or(data_filename);
+    let parts = json
+        .nodes
+        .chunks(chunk_size)
+        .map(|nodes| Chunk { nodes, edges: &[] })
+        .chain(
+            json.edges
+                .chunks(chunk_size)
+                .map(|edges| Chunk { nodes: &[], edges }),
+        );
+
+    let chunks: Vec<(String, String)> = parts
+        .enumerate()
+        .map(|(i, chunk)| {
+            (
+                format!("{}.{}.js", stem, i + 1),
+                format!(
+                    "(window.graphChunks = window.graphChunks || []).push({});\n",
+                    serde_json::to_string(&chunk).unwrap_or_default()
+                ),
+            )
+        })
+        .collect();
+
+    let manifest = Manifest {
+        total_nodes: json.nodes.len(),
+        total_edges: json.edges.len(),
+        metadata: &json.metadata,
+        chunks: chunks.iter().map(|(name, _)| name.clone()).collect(),
+    };
+    JsDataChunks {
+        manifest: format!(
+            "// Bookmark Knowledge Graph Data (chunked)\n// Generated by bookmark tool\nwindow.graphManifest = {};\n",
+            serde_json::to_string_pretty(&manifest).unwrap_or_default()
+        ),
+        chunks,
+    }
+}
+
+/// Hundredths of a pixel are plenty, and keep the data file small
+fn round_position(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
 // --- Escape helpers ---
 
 fn escape_dot_id(s: &str) -> String {
-    s.replace('"', "_")
-        .replace('\\', "_")
+    s.replace(['"', '\\'], "_")
         .replace(|c: char| c.is_whitespace(), "_")
 }
 
@@ -299,6 +727,17 @@ fn escape_dot_label(s: &str) -> String {
         .replace('>', "\\>")
 }
 
+/// Mermaid labels are quoted; quotes become entity codes and line breaks spaces
+fn escape_mermaid_label(s: &str) -> String {
+    s.replace('"', "#quot;").replace(['\n', '\r'], " ")
+}
+
+/// Gephi node size for a node's `size` (visits or bookmarks); logarithmic,
+/// so a few busy domains don't dwarf everything else
+fn gexf_size(size: usize) -> f64 {
+    4.0 + 4.0 * (1.0 + size as f64).ln()
+}
+
 fn escape_xml(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
@@ -319,6 +758,7 @@ const HTML_CSS: &str = r#"<style>
     position: fixed; top: 50%; left: 50%; transform: translate(-50%, -50%);
     font-size: 18px; z-index: 100; text-align: center;
   }
+  #loading progress { width: 280px; margin-top: 12px; }
   #controls {
     position: fixed; top: 16px; left: 16px; z-index: 10;
     padding: 16px; border-radius: 8px; min-width: 240px;
@@ -342,13 +782,26 @@ const HTML_CSS: &str = r#"<style>
   #stats {
     position: fixed; bottom: 16px; left: 16px; font-size: 11px; opacity: 0.7;
   }
+  #view-buttons { position: fixed; top: 16px; right: 16px; z-index: 10; display: flex; gap: 8px; }
   button.theme-btn {
-    position: fixed; top: 16px; right: 16px; z-index: 10;
     padding: 6px 14px; border-radius: 6px; cursor: pointer; border: 1px solid #555;
     font-size: 12px;
   }
   body.dark button.theme-btn { background: #333; color: #eee; }
   body.light button.theme-btn { background: #fff; color: #333; }
+  :focus-visible { outline: 3px solid #ffbf47; outline-offset: 2px; }
+  #graph circle:focus { outline: none; }
+  #graph circle:focus-visible { stroke: #ffbf47; stroke-width: 4px; }
+  .hint { margin-top: 8px; font-size: 11px; opacity: 0.8; }
+  #list-view {
+    position: fixed; top: 60px; right: 16px; bottom: 16px; left: 300px;
+    overflow: auto; padding: 0 16px; font-size: 14px; line-height: 1.5;
+  }
+  #list-view h2 { font-size: 16px; margin: 16px 0 6px; }
+  #list-view ul { list-style: disc; margin-left: 20px; }
+  #list-view .related { font-size: 12px; opacity: 0.8; }
+  body.dark #list-view a { color: #80d8ff; }
+  body.light #list-view a { color: #0b57d0; }
   .filter-group { margin-top: 8px; }
   .filter-group label { font-size: 11px; cursor: pointer; }
   .filter-group input { margin-right: 4px; }
@@ -358,33 +811,108 @@ const HTML_CSS: &str = r#"<style>
     animation: spin 1s linear infinite; margin: 0 auto 16px;
   }
   @keyframes spin { 0% { transform: rotate(0deg); } 100% { transform: rotate(360deg); } }
+  .badge circle { fill: #e53935; stroke: #fff; stroke-width: 1; }
+  .badge text { fill: #fff; font-size: 8px; font-weight: 600; text-anchor: middle; }
+  .badge, .badge * { pointer-events: none; }
+  .legend-line { width: 16px; height: 0; border-top: 2px solid; }
+  .legend-gradient { width: 48px; height: 8px; border-radius: 4px; background: linear-gradient(to right, #2c7bb6, #ffffbf, #d7191c); }
+  #graph-info { margin-top: 10px; font-size: 11px; }
+  #graph-info summary { cursor: pointer; font-weight: 600; }
+  #graph-info table { margin-top: 6px; border-collapse: collapse; }
+  #graph-info td { padding: 1px 6px 1px 0; vertical-align: top; }
+  #graph-info td:first-child { opacity: 0.7; white-space: nowrap; }
 </style>"#;
 
-const HTML_CONTROLS: &str = r#"<div id="controls">
+const HTML_CONTROLS: &str = r#"<div id="view-buttons">
+  <button type="button" class="theme-btn" id="view-toggle" aria-pressed="false" aria-controls="list-view">List view</button>
+  <button type="button" class="theme-btn" id="collapse-all" hidden>Collapse all</button>
+  <button type="button" class="theme-btn" id="expand-all" hidden>Expand all</button>
+  <button type="button" class="theme-btn" id="relayout">Re-run layout</button>
+  <button type="button" class="theme-btn" id="theme-toggle">Toggle Theme</button>
+</div>
+<div id="controls" role="region" aria-label="Graph controls">
   <h3>Knowledge Graph</h3>
-  <div class="ctrl-row"><label>Charge</label><input type="range" id="charge" min="-500" max="-10" value="-120"></div>
-  <div class="ctrl-row"><label>Distance</label><input type="range" id="distance" min="20" max="300" value="80"></div>
-  <div class="filter-group">
+  <div class="ctrl-row"><label for="charge">Charge</label><input type="range" id="charge" min="-500" max="-10" value="-120"></div>
+  <div class="ctrl-row"><label for="distance">Distance</label><input type="range" id="distance" min="20" max="300" value="80"></div>
+  <div class="filter-group" role="group" aria-label="Node types shown">
     <div><label><input type="checkbox" data-type="bookmark" checked> Bookmarks</label></div>
     <div><label><input type="checkbox" data-type="domain" checked> Domains</label></div>
     <div><label><input type="checkbox" data-type="folder" checked> Folders</label></div>
     <div><label><input type="checkbox" data-type="tag" checked> Tags</label></div>
     <div><label><input type="checkbox" data-type="category" checked> Categories</label></div>
   </div>
-  <div class="legend">
+  <div class="legend" aria-hidden="true">
     <div class="legend-item"><div class="legend-dot" style="background:#4fc3f7"></div>Bookmark</div>
     <div class="legend-item"><div class="legend-dot" style="background:#81c784"></div>Domain</div>
     <div class="legend-item"><div class="legend-dot" style="background:#fff176"></div>Folder</div>
     <div class="legend-item"><div class="legend-dot" style="background:#ff8a65"></div>Tag</div>
     <div class="legend-item"><div class="legend-dot" style="background:#ce93d8"></div>Category</div>
   </div>
+  <div class="legend" id="edge-legend" aria-hidden="true"></div>
+  <p class="hint" id="graph-help">Keyboard: Tab into the graph, move between nodes with the arrow keys, open a bookmark with Enter. List view shows the same nodes as text.</p>
+  <details id="graph-info">
+    <summary>Graph info</summary>
+    <table id="graph-meta"></table>
+  </details>
 </div>
-<button class="theme-btn" onclick="toggleTheme()">Toggle Theme</button>
-<div id="tooltip"></div>
+<div id="tooltip" role="tooltip"></div>
 <div id="stats"></div>"#;
 
 const D3_GRAPH_SCRIPT: &str = r#"const colorMap = { bookmark:'#4fc3f7', domain:'#81c784', folder:'#fff176', tag:'#ff8a65', category:'#ce93d8' };
 const radiusMap = { bookmark:5, domain:10, folder:8, tag:7, category:12 };
+const edgeColorMap = {
+  belongstodomain:'#42a5f5', infolder:'#66bb6a', samedomain:'#78909c',
+  hastag:'#ffa726', incategory:'#ab47bc', similarcontent:'#ef5350',
+  movedfrom:'#ffb74d', duplicateof:'#ef5350'
+};
+// In a process diff, bookmarks the run dropped are drawn faded
+const diffMode = Boolean((graphData.metadata.config || {}).diff);
+const removedColor = '#ef5350';
+
+// Adjacency by node id, taken before d3 swaps edge ends for node objects
+const neighbors = new Map(graphData.nodes.map(n => [n.id, new Set()]));
+graphData.edges.forEach(e => {
+  if (neighbors.has(e.source) && neighbors.has(e.target)) {
+    neighbors.get(e.source).add(e.target);
+    neighbors.get(e.target).add(e.source);
+  }
+});
+const titleOf = new Map(graphData.nodes.map(n => [n.id, n.title]));
+// The one node in the tab order; arrow keys move it
+let activeId = null;
+
+// Double-clicking a domain, folder, tag or category node hides or shows
+// the bookmarks attached to it. Big graphs start with all of them hidden,
+// so the first layout stays fast, and track the opened nodes instead.
+// Either set is kept in localStorage, so a reload looks the same.
+const PROGRESSIVE_BOOKMARKS = 1500;
+const progressive = graphData.nodes.filter(n => n.node_type === 'bookmark').length > PROGRESSIVE_BOOKMARKS;
+const CLUSTER_STATE_KEY = 'bookmark-graph-clusters';
+const clusterState = loadClusterState();
+const expanded = new Set(clusterState.expanded);
+const collapsed = new Set(clusterState.collapsed);
+const nodeById = new Map(graphData.nodes.map(n => [n.id, n]));
+const withBookmarks = new Set(graphData.nodes.filter(n => n.node_type !== 'bookmark'
+  && [...neighbors.get(n.id)].some(id => nodeById.get(id).node_type === 'bookmark')).map(n => n.id));
+const expandable = d => withBookmarks.has(d.id);
+const isOpen = d => progressive ? expanded.has(d.id) : !collapsed.has(d.id);
+
+function loadClusterState() {
+  try {
+    const saved = JSON.parse(localStorage.getItem(CLUSTER_STATE_KEY)) || {};
+    return { expanded: saved.expanded || [], collapsed: saved.collapsed || [] };
+  } catch (e) {
+    return { expanded: [], collapsed: [] };
+  }
+}
+
+function saveClusterState() {
+  try {
+    localStorage.setItem(CLUSTER_STATE_KEY, JSON.stringify({ expanded: [...expanded], collapsed: [...collapsed] }));
+  } catch (e) {
+    // Storage may be disabled, e.g. for file:// pages; the state just isn't kept
+  }
+}
 
 let visibleTypes = new Set(['bookmark','domain','folder','tag','category']);
 const svg = d3.select('#graph');
@@ -394,10 +922,41 @@ svg.attr('width', width).attr('height', height);
 const g = svg.append('g');
 svg.call(d3.zoom().scaleExtent([0.1, 8]).on('zoom', (e) => g.attr('transform', e.transform)));
 
-let simulation, linkSel, nodeSel, labelSel;
+// Positions from `graph --layout` are fitted to the window and drawn as
+// they are; the simulation only runs when asked to re-run the layout
+const precomputed = graphData.nodes.length > 0 && graphData.nodes.every(n => n.x !== undefined);
+if (precomputed) {
+  const bounds = graphData.nodes.reduce((b, n) => [
+    Math.min(b[0], n.x), Math.min(b[1], n.y), Math.max(b[2], n.x), Math.max(b[3], n.y)
+  ], [Infinity, Infinity, -Infinity, -Infinity]);
+  const scale = Math.min((width - 80) / ((bounds[2] - bounds[0]) || 1), (height - 80) / ((bounds[3] - bounds[1]) || 1));
+  graphData.nodes.forEach(n => {
+    n.x = (n.x - (bounds[0] + bounds[2]) / 2) * scale + width / 2;
+    n.y = (n.y - (bounds[1] + bounds[3]) / 2) * scale + height / 2;
+  });
+}
+
+let simulation, linkSel, nodeSel, labelSel, badgeSel;
+
+// A bookmark shows when, in a big graph, any node it is attached to was
+// opened, and otherwise when none of them was collapsed
+function isShown(n) {
+  if (!visibleTypes.has(n.node_type)) return false;
+  if (n.node_type !== 'bookmark') return true;
+  const around = neighbors.get(n.id) || [];
+  if (progressive) {
+    for (const id of around) if (expanded.has(id)) return true;
+    return false;
+  }
+  for (const id of around) if (collapsed.has(id)) return false;
+  return true;
+}
+
+const radiusOf = d => Math.max(radiusMap[d.node_type] || 5, Math.sqrt(d.size) * 3);
+const badgeText = count => count >= 1000 ? (count / 1000).toFixed(count >= 10000 ? 0 : 1) + 'k' : String(count);
 
 function filterData() {
-  const nodes = graphData.nodes.filter(n => visibleTypes.has(n.node_type));
+  const nodes = graphData.nodes.filter(isShown);
   const nodeIds = new Set(nodes.map(n => n.id));
   const edges = graphData.edges.filter(e => {
     const sourceId = typeof e.source === 'object' ? e.source.id : e.source;
@@ -409,248 +968,214 @@ function filterData() {
 
 function render() {
   if (graphData.nodes.length === 0) return;
+  if (simulation) simulation.stop();
   const data = filterData();
   g.selectAll('*').remove();
 
-  const edgeColorMap = {
-    belongstodomain:'#42a5f5', infolder:'#66bb6a', samedomain:'#78909c',
-    hastag:'#ffa726', incategory:'#ab47bc', similarcontent:'#ef5350'
-  };
-
   linkSel = g.append('g').selectAll('line').data(data.edges).join('line')
     .attr('stroke', d => edgeColorMap[d.edge_type] || '#555')
     .attr('stroke-opacity', 0.4)
     .attr('stroke-width', d => Math.max(0.5, d.weight * 2));
 
+  if (!data.nodes.some(n => n.id === activeId)) activeId = data.nodes.length ? data.nodes[0].id : null;
   nodeSel = g.append('g').selectAll('circle').data(data.nodes).join('circle')
-    .attr('r', d => Math.max(radiusMap[d.node_type] || 5, Math.sqrt(d.size) * 3))
-    .attr('fill', d => colorMap[d.node_type] || '#999')
-    .attr('stroke', '#fff').attr('stroke-width', 0.5)
+    .attr('r', radiusOf)
+    .attr('fill', d => d.color || colorMap[d.node_type] || '#999')
+    .attr('fill-opacity', d => diffMode && d.color === removedColor ? 0.45 : 1)
+    .attr('stroke', '#fff').attr('stroke-width', d => expandable(d) && !isOpen(d) ? 2 : 0.5)
+    .attr('stroke-dasharray', d => expandable(d) && !isOpen(d) ? '3,2' : null)
+    .attr('tabindex', d => d.id === activeId ? 0 : -1)
+    .attr('role', d => d.url ? 'link' : expandable(d) ? 'button' : 'img')
+    .attr('aria-label', nodeLabel)
+    .attr('aria-expanded', d => expandable(d) ? String(isOpen(d)) : null)
     .style('cursor', 'pointer')
     .call(d3.drag().on('start', dragStart).on('drag', dragging).on('end', dragEnd))
     .on('mouseover', showTooltip).on('mouseout', hideTooltip)
-    .on('click', (e, d) => { if (d.url) window.open(d.url, '_blank'); });
+    .on('focus', (e, d) => {
+      const box = e.target.getBoundingClientRect();
+      placeTooltip(d, box.right, box.top);
+    })
+    .on('blur', hideTooltip)
+    .on('keydown', onNodeKey)
+    .on('click', (e, d) => {
+      if (d.url) window.open(d.url, '_blank', 'noopener');
+    })
+    .on('dblclick', (e, d) => {
+      // Not a zoom: the svg's zoom behaviour also listens for double clicks
+      e.stopPropagation();
+      if (expandable(d)) toggleCluster(d);
+    });
 
   labelSel = g.append('g').selectAll('text').data(data.nodes.filter(n => n.node_type !== 'bookmark')).join('text')
     .text(d => d.title.length > 20 ? d.title.slice(0, 20) + '...' : d.title)
     .attr('font-size', 9).attr('dx', 12).attr('dy', 3)
     .attr('fill', document.body.classList.contains('dark') ? '#ccc' : '#555');
 
+  badgeSel = g.append('g').selectAll('g').data(data.nodes.filter(n => n.node_type !== 'bookmark' && n.bookmark_count > 0)).join('g')
+    .attr('class', 'badge').attr('aria-hidden', 'true');
+  badgeSel.append('circle').attr('r', d => badgeText(d.bookmark_count).length > 2 ? 9 : 7);
+  badgeSel.append('text').attr('dy', 3).text(d => badgeText(d.bookmark_count));
+
   simulation = d3.forceSimulation(data.nodes)
     .force('link', d3.forceLink(data.edges).id(d => d.id).distance(+document.getElementById('distance').value))
     .force('charge', d3.forceManyBody().strength(+document.getElementById('charge').value))
     .force('center', d3.forceCenter(width / 2, height / 2))
     .force('collision', d3.forceCollide().radius(d => (radiusMap[d.node_type] || 5) + 2))
-    .on('tick', () => {
-      linkSel.attr('x1', d => d.source.x).attr('y1', d => d.source.y)
-             .attr('x2', d => d.target.x).attr('y2', d => d.target.y);
-      nodeSel.attr('cx', d => d.x).attr('cy', d => d.y);
-      labelSel.attr('x', d => d.x).attr('y', d => d.y);
-    });
+    .on('tick', draw);
+  if (precomputed) {
+    simulation.stop();
+    draw();
+  }
 
   document.getElementById('stats').textContent =
-    `Nodes: ${data.nodes.length} | Edges: ${data.edges.length} | Bookmarks: ${graphData.metadata.bookmark_count} | Domains: ${graphData.metadata.domain_count}`;
+    `Nodes: ${data.nodes.length} | Edges: ${data.edges.length} | Bookmarks: ${graphData.metadata.bookmark_count} | Domains: ${graphData.metadata.domain_count}` +
+    (withBookmarks.size ? ` | Double-click a domain, folder or category to ${progressive ? 'show' : 'hide'} its bookmarks` : '');
+}
+
+function draw() {
+  linkSel.attr('x1', d => d.source.x).attr('y1', d => d.source.y)
+         .attr('x2', d => d.target.x).attr('y2', d => d.target.y);
+  nodeSel.attr('cx', d => d.x).attr('cy', d => d.y);
+  labelSel.attr('x', d => d.x).attr('y', d => d.y);
+  badgeSel.attr('transform', d => {
+    const offset = radiusOf(d) * 0.75;
+    return `translate(${d.x + offset},${d.y - offset})`;
+  });
 }
 
-function showTooltip(e, d) {
+// Show or hide the bookmarks around an aggregate node, starting new ones
+// next to it rather than at the origin
+function toggleCluster(d) {
+  const opening = !isOpen(d);
+  const set = progressive ? expanded : collapsed;
+  if (opening === progressive) set.add(d.id); else set.delete(d.id);
+  if (opening) {
+    (neighbors.get(d.id) || new Set()).forEach(id => {
+      const n = nodeById.get(id);
+      if (n && n.x === undefined) {
+        n.x = d.x + (Math.random() - 0.5) * 40;
+        n.y = d.y + (Math.random() - 0.5) * 40;
+      }
+    });
+  }
+  saveClusterState();
+  updateClusterButtons();
+  activeId = d.id;
+  render();
+}
+
+function showTooltip(e, d) { placeTooltip(d, e.clientX, e.clientY); }
+function placeTooltip(d, x, y) {
   const tip = document.getElementById('tooltip');
   let html = `<strong>${d.title}</strong><br>Type: ${d.node_type}`;
   if (d.url) html += `<br>URL: ${d.url}`;
   if (d.domain) html += `<br>Domain: ${d.domain}`;
   if (d.folder) html += `<br>Folder: ${d.folder}`;
   html += `<br>Size: ${d.size}`;
+  if (d.node_type !== 'bookmark' && d.bookmark_count > 0) html += `<br>Bookmarks: ${d.bookmark_count}`;
   tip.innerHTML = html;
   tip.style.display = 'block';
-  tip.style.left = (e.clientX + 12) + 'px';
-  tip.style.top = (e.clientY - 12) + 'px';
+  tip.style.left = (x + 12) + 'px';
+  tip.style.top = (y - 12) + 'px';
 }
 function hideTooltip() { document.getElementById('tooltip').style.display = 'none'; }
 
-function dragStart(e, d) { if (!e.active) simulation.alphaTarget(0.3).restart(); d.fx = d.x; d.fy = d.y; }
-function dragging(e, d) { d.fx = e.x; d.fy = e.y; }
-function dragEnd(e, d) { if (!e.active) simulation.alphaTarget(0); d.fx = null; d.fy = null; }
+function nodeLabel(d) {
+  const degree = (neighbors.get(d.id) || new Set()).size;
+  let label = `${d.title}, ${d.node_type}, ${degree} connection${degree === 1 ? '' : 's'}`;
+  if (d.domain && d.node_type === 'bookmark') label += `, ${d.domain}`;
+  if (d.node_type !== 'bookmark' && d.bookmark_count > 0) label += `, ${d.bookmark_count} bookmark${d.bookmark_count === 1 ? '' : 's'}`;
+  return label;
+}
 
-function toggleTheme() {
-  document.body.classList.toggle('dark');
-  document.body.classList.toggle('light');
-  if (labelSel) {
-    labelSel.attr('fill', document.body.classList.contains('dark') ? '#ccc' : '#555');
-  }
+function focusNode(d) {
+  activeId = d.id;
+  nodeSel.attr('tabindex', n => n.id === activeId ? 0 : -1);
+  nodeSel.filter(n => n.id === activeId).node().focus();
 }
 
-document.getElementById('charge').addEventListener('input', () => {
-  if (simulation) simulation.force('charge', d3.forceManyBody().strength(+document.getElementById('charge').value)).alpha(0.3).restart();
-});
-document.getElementById('distance').addEventListener('input', () => {
-  if (simulation) { simulation.force('link').distance(+document.getElementById('distance').value); simulation.alpha(0.3).restart(); }
-});
-document.querySelectorAll('.filter-group input').forEach(cb => {
-  cb.addEventListener('change', () => {
-    if (cb.checked) visibleTypes.add(cb.dataset.type); else visibleTypes.delete(cb.dataset.type);
-    render();
+// Closest node roughly in direction (dx, dy), favouring ones straight ahead
+function nearestNode(from, dx, dy) {
+  let best = null, bestScore = Infinity;
+  nodeSel.each(d => {
+    if (d === from) return;
+    const vx = d.x - from.x, vy = d.y - from.y;
+    const ahead = vx * dx + vy * dy;
+    if (ahead <= 0) return;
+    const score = ahead + 2 * Math.abs(vx * dy - vy * dx);
+    if (score < bestScore) { bestScore = score; best = d; }
   });
-});
-
-render();"#;
+  return best;
+}
 
-const HTML_PREAMBLE: &str = r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-<meta charset="UTF-8">
-<meta name="viewport" content="width=device-width, initial-scale=1.0">
-<title>Bookmark Knowledge Graph</title>
-<style>
-  * { margin: 0; padding: 0; box-sizing: border-box; }
-  body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif; overflow: hidden; }
-  body.dark { background: #1a1a2e; color: #e0e0e0; }
-  body.light { background: #f5f5f5; color: #333; }
-  #graph { width: 100vw; height: 100vh; }
-  #controls {
-    position: fixed; top: 16px; left: 16px; z-index: 10;
-    padding: 16px; border-radius: 8px; min-width: 240px;
-    font-size: 13px; backdrop-filter: blur(12px);
-  }
-  body.dark #controls { background: rgba(30,30,60,0.9); border: 1px solid #333; }
-  body.light #controls { background: rgba(255,255,255,0.95); border: 1px solid #ddd; }
-  #controls h3 { margin-bottom: 12px; font-size: 15px; }
-  .ctrl-row { margin-bottom: 8px; display: flex; align-items: center; gap: 8px; }
-  .ctrl-row label { min-width: 60px; }
-  .ctrl-row input[type=range] { flex: 1; }
-  .legend { display: flex; flex-wrap: wrap; gap: 6px; margin-top: 10px; }
-  .legend-item { display: flex; align-items: center; gap: 4px; font-size: 11px; }
-  .legend-dot { width: 10px; height: 10px; border-radius: 50%; }
-  #tooltip {
-    position: fixed; padding: 8px 12px; border-radius: 6px; font-size: 12px;
-    pointer-events: none; display: none; max-width: 320px; z-index: 20;
-  }
-  body.dark #tooltip { background: rgba(0,0,0,0.85); color: #fff; }
-  body.light #tooltip { background: rgba(255,255,255,0.95); color: #333; border: 1px solid #ccc; }
-  #stats {
-    position: fixed; bottom: 16px; left: 16px; font-size: 11px; opacity: 0.7;
-  }
-  button.theme-btn {
-    position: fixed; top: 16px; right: 16px; z-index: 10;
-    padding: 6px 14px; border-radius: 6px; cursor: pointer; border: 1px solid #555;
-    font-size: 12px;
+function onNodeKey(e, d) {
+  const directions = { ArrowRight: [1, 0], ArrowLeft: [-1, 0], ArrowDown: [0, 1], ArrowUp: [0, -1] };
+  if (directions[e.key]) {
+    const next = nearestNode(d, ...directions[e.key]);
+    if (next) focusNode(next);
+  } else if ((e.key === 'Enter' || e.key === ' ') && d.url) {
+    window.open(d.url, '_blank', 'noopener');
+  } else if ((e.key === 'Enter' || e.key === ' ') && expandable(d)) {
+    toggleCluster(d);
+    focusNode(d);
+  } else if (e.key === 'Escape') {
+    hideTooltip();
+    e.target.blur();
+  } else {
+    return;
   }
-  body.dark button.theme-btn { background: #333; color: #eee; }
-  body.light button.theme-btn { background: #fff; color: #333; }
-  .filter-group { margin-top: 8px; }
-  .filter-group label { font-size: 11px; cursor: pointer; }
-  .filter-group input { margin-right: 4px; }
-</style>
-</head>
-<body class="dark">
-<div id="controls">
-  <h3>Knowledge Graph</h3>
-  <div class="ctrl-row"><label>Charge</label><input type="range" id="charge" min="-500" max="-10" value="-120"></div>
-  <div class="ctrl-row"><label>Distance</label><input type="range" id="distance" min="20" max="300" value="80"></div>
-  <div class="filter-group">
-    <div><label><input type="checkbox" data-type="bookmark" checked> Bookmarks</label></div>
-    <div><label><input type="checkbox" data-type="domain" checked> Domains</label></div>
-    <div><label><input type="checkbox" data-type="folder" checked> Folders</label></div>
-    <div><label><input type="checkbox" data-type="tag" checked> Tags</label></div>
-    <div><label><input type="checkbox" data-type="category" checked> Categories</label></div>
-  </div>
-  <div class="legend">
-    <div class="legend-item"><div class="legend-dot" style="background:#4fc3f7"></div>Bookmark</div>
-    <div class="legend-item"><div class="legend-dot" style="background:#81c784"></div>Domain</div>
-    <div class="legend-item"><div class="legend-dot" style="background:#fff176"></div>Folder</div>
-    <div class="legend-item"><div class="legend-dot" style="background:#ff8a65"></div>Tag</div>
-    <div class="legend-item"><div class="legend-dot" style="background:#ce93d8"></div>Category</div>
-  </div>
-</div>
-<button class="theme-btn" onclick="toggleTheme()">Toggle Theme</button>
-<div id="tooltip"></div>
-<div id="stats"></div>
-<svg id="graph"></svg>
-<script src="https://d3js.org/d3.v7.min.js"></script>
-<script>
-const graphData = "#;
-
-const HTML_POSTAMBLE: &str = r#";
-const colorMap = { bookmark:'#4fc3f7', domain:'#81c784', folder:'#fff176', tag:'#ff8a65', category:'#ce93d8' };
-const radiusMap = { bookmark:5, domain:10, folder:8, tag:7, category:12 };
-
-let visibleTypes = new Set(['bookmark','domain','folder','tag','category']);
-const svg = d3.select('#graph');
-const width = window.innerWidth, height = window.innerHeight;
-svg.attr('width', width).attr('height', height);
-
-const g = svg.append('g');
-svg.call(d3.zoom().scaleExtent([0.1, 8]).on('zoom', (e) => g.attr('transform', e.transform)));
-
-let simulation, linkSel, nodeSel, labelSel;
-
-function filterData() {
-  const nodes = graphData.nodes.filter(n => visibleTypes.has(n.node_type));
-  const nodeIds = new Set(nodes.map(n => n.id));
-  const edges = graphData.edges.filter(e => {
-    const sourceId = typeof e.source === 'object' ? e.source.id : e.source;
-    const targetId = typeof e.target === 'object' ? e.target.id : e.target;
-    return nodeIds.has(sourceId) && nodeIds.has(targetId);
-  });
-  return { nodes, edges };
+  e.preventDefault();
 }
 
-function render() {
-  if (graphData.nodes.length === 0) return;
-  const data = filterData();
-  g.selectAll('*').remove();
-
-  const edgeColorMap = {
-    belongstodomain:'#42a5f5', infolder:'#66bb6a', samedomain:'#78909c',
-    hastag:'#ffa726', incategory:'#ab47bc', similarcontent:'#ef5350'
-  };
-
-  linkSel = g.append('g').selectAll('line').data(data.edges).join('line')
-    .attr('stroke', d => edgeColorMap[d.edge_type] || '#555')
-    .attr('stroke-opacity', 0.4)
-    .attr('stroke-width', d => Math.max(0.5, d.weight * 2));
-
-  nodeSel = g.append('g').selectAll('circle').data(data.nodes).join('circle')
-    .attr('r', d => Math.max(radiusMap[d.node_type] || 5, Math.sqrt(d.size) * 3))
-    .attr('fill', d => colorMap[d.node_type] || '#999')
-    .attr('stroke', '#fff').attr('stroke-width', 0.5)
-    .style('cursor', 'pointer')
-    .call(d3.drag().on('start', dragStart).on('drag', dragging).on('end', dragEnd))
-    .on('mouseover', showTooltip).on('mouseout', hideTooltip)
-    .on('click', (e, d) => { if (d.url) window.open(d.url, '_blank'); });
-
-  labelSel = g.append('g').selectAll('text').data(data.nodes.filter(n => n.node_type !== 'bookmark')).join('text')
-    .text(d => d.title.length > 20 ? d.title.slice(0, 20) + '...' : d.title)
-    .attr('font-size', 9).attr('dx', 12).attr('dy', 3)
-    .attr('fill', document.body.classList.contains('dark') ? '#ccc' : '#555');
-
-  simulation = d3.forceSimulation(data.nodes)
-    .force('link', d3.forceLink(data.edges).id(d => d.id).distance(+document.getElementById('distance').value))
-    .force('charge', d3.forceManyBody().strength(+document.getElementById('charge').value))
-    .force('center', d3.forceCenter(width / 2, height / 2))
-    .force('collision', d3.forceCollide().radius(d => (radiusMap[d.node_type] || 5) + 2))
-    .on('tick', () => {
-      linkSel.attr('x1', d => d.source.x).attr('y1', d => d.source.y)
-             .attr('x2', d => d.target.x).attr('y2', d => d.target.y);
-      nodeSel.attr('cx', d => d.x).attr('cy', d => d.y);
-      labelSel.attr('x', d => d.x).attr('y', d => d.y);
+// The visible nodes as headed lists of links, for screen readers and
+// keyboard users who would rather not walk the graph
+function renderList() {
+  const headings = { category: 'Categories', domain: 'Domains', folder: 'Folders', tag: 'Tags', bookmark: 'Bookmarks' };
+  const list = document.getElementById('list-view');
+  list.replaceChildren();
+  // Every node, expanded or not: the list is the way to reach them all
+  const nodes = graphData.nodes.filter(n => visibleTypes.has(n.node_type));
+  Object.entries(headings).forEach(([type, heading]) => {
+    const ofType = nodes.filter(n => n.node_type === type).sort((a, b) => a.title.localeCompare(b.title));
+    if (ofType.length === 0) return;
+    const h2 = document.createElement('h2');
+    h2.textContent = `${heading} (${ofType.length})`;
+    const ul = document.createElement('ul');
+    ofType.forEach(n => {
+      const li = document.createElement('li');
+      if (n.url) {
+        const a = document.createElement('a');
+        a.href = n.url;
+        a.target = '_blank';
+        a.rel = 'noopener';
+        a.textContent = n.title || n.url;
+        li.appendChild(a);
+      } else {
+        li.append(n.title);
+      }
+      const related = [...(neighbors.get(n.id) || [])].map(id => titleOf.get(id));
+      if (related.length > 0) {
+        const div = document.createElement('div');
+        div.className = 'related';
+        const shown = related.slice(0, 20).join(', ');
+        div.textContent = `Connected to: ${shown}${related.length > 20 ? `, and ${related.length - 20} more` : ''}`;
+        li.appendChild(div);
+      }
+      ul.appendChild(li);
     });
-
-  document.getElementById('stats').textContent =
-    `Nodes: ${data.nodes.length} | Edges: ${data.edges.length} | Bookmarks: ${graphData.metadata.bookmark_count} | Domains: ${graphData.metadata.domain_count}`;
+    list.append(h2, ul);
+  });
 }
 
-function showTooltip(e, d) {
-  const tip = document.getElementById('tooltip');
-  let html = `<strong>${d.title}</strong><br>Type: ${d.node_type}`;
-  if (d.url) html += `<br>URL: ${d.url}`;
-  if (d.domain) html += `<br>Domain: ${d.domain}`;
-  if (d.folder) html += `<br>Folder: ${d.folder}`;
-  html += `<br>Size: ${d.size}`;
-  tip.innerHTML = html;
-  tip.style.display = 'block';
-  tip.style.left = (e.clientX + 12) + 'px';
-  tip.style.top = (e.clientY - 12) + 'px';
+function toggleListView() {
+  const button = document.getElementById('view-toggle');
+  const showList = button.getAttribute('aria-pressed') !== 'true';
+  button.setAttribute('aria-pressed', String(showList));
+  document.getElementById('list-view').hidden = !showList;
+  document.getElementById('graph').style.display = showList ? 'none' : '';
+  hideTooltip();
+  if (showList) renderList();
 }
-function hideTooltip() { document.getElementById('tooltip').style.display = 'none'; }
 
 function dragStart(e, d) { if (!e.active) simulation.alphaTarget(0.3).restart(); d.fx = d.x; d.fy = d.y; }
 function dragging(e, d) { d.fx = e.x; d.fy = e.y; }
@@ -674,10 +1199,84 @@ document.querySelectorAll('.filter-group input').forEach(cb => {
   cb.addEventListener('change', () => {
     if (cb.checked) visibleTypes.add(cb.dataset.type); else visibleTypes.delete(cb.dataset.type);
     render();
+    if (!document.getElementById('list-view').hidden) renderList();
   });
 });
+document.getElementById('view-toggle').addEventListener('click', toggleListView);
+document.getElementById('theme-toggle').addEventListener('click', toggleTheme);
+document.getElementById('relayout').addEventListener('click', () => {
+  if (simulation) simulation.alpha(1).restart();
+});
+function updateClusterButtons() {
+  document.getElementById('collapse-all').hidden = !progressive || expanded.size === 0;
+  document.getElementById('expand-all').hidden = progressive || collapsed.size === 0;
+}
+document.getElementById('collapse-all').addEventListener('click', () => {
+  expanded.clear();
+  saveClusterState();
+  updateClusterButtons();
+  render();
+});
+document.getElementById('expand-all').addEventListener('click', () => {
+  collapsed.clear();
+  saveClusterState();
+  updateClusterButtons();
+  render();
+});
 
-render();
-</script>
-</body>
-</html>"#;
+function renderInfo() {
+  const edgeLabels = {
+    belongstodomain:'Belongs to domain', infolder:'In folder', samedomain:'Same domain',
+    hastag:'Has tag', incategory:'In category', similarcontent:'Similar content',
+    movedfrom:'Moved from', duplicateof:'Duplicate of'
+  };
+  const present = new Set(graphData.edges.map(e => e.edge_type));
+  const legend = document.getElementById('edge-legend');
+  Object.entries(edgeLabels).filter(([t]) => present.has(t)).forEach(([t, label]) => {
+    const item = document.createElement('div');
+    item.className = 'legend-item';
+    const line = document.createElement('div');
+    line.className = 'legend-line';
+    line.style.borderColor = edgeColorMap[t];
+    item.append(line, label);
+    legend.appendChild(item);
+  });
+  const heatmap = (graphData.metadata.config || {}).heatmap;
+  if (heatmap) {
+    const item = document.createElement('div');
+    item.className = 'legend-item';
+    const bar = document.createElement('div');
+    bar.className = 'legend-gradient';
+    item.append(heatmap === 'visited' ? 'Visited long ago' : 'Added long ago', bar, 'Recently');
+    legend.appendChild(item);
+  }
+  if (diffMode) {
+    [[removedColor, 'Removed'], ['#ffb74d', 'Moved'], ['#9e9e9e', 'Emptied folder']].forEach(([color, label]) => {
+      const item = document.createElement('div');
+      item.className = 'legend-item';
+      const dot = document.createElement('div');
+      dot.className = 'legend-dot';
+      dot.style.background = color;
+      item.append(dot, label);
+      legend.appendChild(item);
+    });
+  }
+
+  const m = graphData.metadata;
+  const rows = [
+    ['Generated', new Date(m.generated_at).toLocaleString()],
+    ['Nodes', m.total_nodes], ['Edges', m.total_edges],
+    ['Bookmarks', m.bookmark_count], ['Domains', m.domain_count], ['Folders', m.folder_count],
+    ...Object.entries(m.config || {})
+  ];
+  const table = document.getElementById('graph-meta');
+  rows.forEach(([k, v]) => {
+    const tr = table.insertRow();
+    tr.insertCell().textContent = k;
+    tr.insertCell().textContent = v;
+  });
+}
+
+renderInfo();
+updateClusterButtons();
+render();"#;
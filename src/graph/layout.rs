@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use super::KnowledgeGraph;
+
+/// Pairwise repulsions computed over a whole layout; larger graphs get
+/// fewer iterations so a layout stays within a few seconds
+const LAYOUT_BUDGET: usize = 200_000_000;
+const MAX_ITERATIONS: usize = 300;
+const MIN_ITERATIONS: usize = 20;
+
+const REPULSION: f64 = 10.0;
+const GRAVITY: f64 = 1.0;
+
+impl KnowledgeGraph {
+    /// Node positions from a small ForceAtlas2-style pass: nodes repel each
+    /// other in proportion to their degree, edges pull their ends together
+    /// by weight, and gravity keeps unconnected parts near the center.
+    ///
+    /// Nodes start on a spiral in graph order, so the same graph always gets
+    /// the same layout. Each step is capped per node, and the cap shrinks as
+    /// the layout settles.
+    pub fn layout(&self) -> HashMap<String, (f64, f64)> {
+        let n = self.nodes.len();
+        let index: HashMap<&str, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id.as_str(), i))
+            .collect();
+        let edges: Vec<(usize, usize, f64)> = self
+            .edges
+            .iter()
+            .filter_map(|edge| {
+                let source = *index.get(edge.source.as_str())?;
+                let target = *index.get(edge.target.as_str())?;
+                (source != target).then_some((source, target, edge.weight.max(0.0)))
+            })
+            .collect();
+        let mut mass = vec![1.0; n];
+        for &(source, target, _) in &edges {
+            mass[source] += 1.0;
+            mass[target] += 1.0;
+        }
+
+        // Golden-angle spiral, spread so the start is not too crowded
+        let golden_angle = std::f64::consts::PI * (3.0 - 5f64.sqrt());
+        let mut positions: Vec<(f64, f64)> = (0..n)
+            .map(|i| {
+                let radius = 10.0 * (i as f64 + 0.5).sqrt();
+                let angle = i as f64 * golden_angle;
+                (radius * angle.cos(), radius * angle.sin())
+            })
+            .collect();
+
+        let iterations = (LAYOUT_BUDGET / (n * n).max(1)).clamp(MIN_ITERATIONS, MAX_ITERATIONS);
+        let mut max_step = 10.0 * (n as f64).sqrt().max(1.0);
+        let mut forces = vec![(0.0, 0.0); n];
+        for _ in 0..iterations {
+            forces.iter_mut().for_each(|f| *f = (0.0, 0.0));
+            for i in 0..n {
+                for j in i + 1..n {
+                    let dx = positions[i].0 - positions[j].0;
+                    let dy = positions[i].1 - positions[j].1;
+                    let distance_sq = (dx * dx + dy * dy).max(0.01);
+                    let push = REPULSION * mass[i] * mass[j] / distance_sq;
+                    forces[i].0 += dx * push;
+                    forces[i].1 += dy * push;
+                    forces[j].0 -= dx * push;
+                    forces[j].1 -= dy * push;
+                }
+            }
+            for &(source, target, weight) in &edges {
+                let dx = positions[target].0 - positions[source].0;
+                let dy = positions[target].1 - positions[source].1;
+                forces[source].0 += dx * weight;
+                forces[source].1 += dy * weight;
+                forces[target].0 -= dx * weight;
+                forces[target].1 -= dy * weight;
+            }
+            for (i, (x, y)) in positions.iter_mut().enumerate() {
+                let distance = (*x * *x + *y * *y).sqrt().max(0.01);
+                let pull = GRAVITY * mass[i] / distance;
+                let (fx, fy) = (forces[i].0 - *x * pull, forces[i].1 - *y * pull);
+                let step = (fx * fx + fy * fy).sqrt() / mass[i];
+                let scale = match step > max_step {
+                    true => max_step / step,
+                    false => 1.0,
+                } / mass[i];
+                *x += fx * scale;
+                *y += fy * scale;
+            }
+            max_step = (max_step * 0.97).max(0.5);
+        }
+
+        self.nodes
+            .iter()
+            .zip(positions)
+            .map(|(node, position)| (node.id.clone(), position))
+            .collect()
+    }
+
+    /// Store [`layout`](Self::layout)'s positions on the nodes, so every
+    /// format that can carry them does: GEXF as `viz:position`, JSON and
+    /// the HTML view's data as `x` and `y`
+    pub fn apply_layout(&mut self) {
+        let mut positions = self.layout();
+        for node in &mut self.nodes {
+            node.position = positions.remove(&node.id);
+        }
+    }
+}
@@ -1,17 +1,26 @@
 mod analyzer;
 mod builder;
+mod diff;
+mod focus;
 pub mod formats;
+mod layout;
 #[cfg(test)]
 mod tests;
+mod theme;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::canonical::SiteRules;
 
 // Re-export public API
+pub use analyzer::{categorize, extract_domain};
 pub use builder::GraphBuilder;
+pub use theme::{GraphTheme, Heatmap};
 
 /// Node types in the knowledge graph
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum NodeType {
     Bookmark,
@@ -22,7 +31,7 @@ pub enum NodeType {
 }
 
 /// Edge types representing different relationships
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum EdgeType {
     BelongsToDomain,
@@ -31,14 +40,24 @@ pub enum EdgeType {
     HasTag,
     InCategory,
     SimilarContent,
+    /// From a bookmark to the folder a `process` run moved it out of
+    MovedFrom,
+    /// From a bookmark a `process` run dropped to the copy it kept
+    DuplicateOf,
 }
 
 /// Metadata for a graph node
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// For bookmark nodes these describe the bookmark itself; for domain nodes the
+/// date is the earliest bookmark and visits are summed across the domain.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct NodeMetadata {
     pub date_added: Option<DateTime<Utc>>,
     pub visit_count: Option<i64>,
     pub bookmark_count: usize,
+    /// Latest visit, for bookmark nodes whose page is in the history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_visit: Option<DateTime<Utc>>,
 }
 
 /// A node in the knowledge graph
@@ -51,6 +70,15 @@ pub struct GraphNode {
     pub domain: Option<String>,
     pub folder: Option<String>,
     pub size: usize,
+    #[serde(default)]
+    pub metadata: NodeMetadata,
+    /// `#rrggbb` fill from the configured theme; unset nodes use their
+    /// type's default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Where [`KnowledgeGraph::apply_layout`] placed the node
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<(f64, f64)>,
 }
 
 /// An edge in the knowledge graph
@@ -60,6 +88,9 @@ pub struct GraphEdge {
     pub target: String,
     pub edge_type: EdgeType,
     pub weight: f64,
+    /// Number of original edges this edge stands for when edges are aggregated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
 }
 
 /// Metadata for the knowledge graph
@@ -71,6 +102,9 @@ pub struct GraphMetadata {
     pub domain_count: usize,
     pub folder_count: usize,
     pub generated_at: DateTime<Utc>,
+    /// Settings the graph was built with, for self-describing outputs
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub config: BTreeMap<String, String>,
 }
 
 /// The main knowledge graph structure
@@ -81,6 +115,23 @@ pub struct KnowledgeGraph {
     pub metadata: GraphMetadata,
 }
 
+/// Returned when a graph is estimated to exceed the configured size limits
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Graph too large: estimated {estimated_nodes} nodes / {estimated_edges} edges \
+     exceeds the {exceeded} limit of {limit}.\nTry one of:\n{}",
+    suggestions.iter().map(|s| format!("  - {}", s)).collect::<Vec<_>>().join("\n")
+)]
+pub struct GraphSizeError {
+    pub estimated_nodes: usize,
+    pub estimated_edges: usize,
+    /// Which limit was hit ("node" or "edge")
+    pub exceeded: &'static str,
+    pub limit: usize,
+    /// `graph` options that would shrink the graph, most effective first
+    pub suggestions: Vec<String>,
+}
+
 /// Detail level for graph generation
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DetailLevel {
@@ -114,6 +165,84 @@ pub struct GraphConfig {
     pub min_date: Option<chrono::DateTime<chrono::Utc>>,
     /// Domain-only mode (collapse all bookmarks into domains)
     pub domain_only: bool,
+    /// Replace same-domain cliques with counts on the domain edges and roll similarity edges up to domains
+    pub aggregate_edges: bool,
+    /// Refuse to build graphs estimated to exceed this many nodes (None = no limit)
+    pub max_nodes: Option<usize>,
+    /// Refuse to build graphs estimated to exceed this many edges (None = no limit)
+    pub max_edges: Option<usize>,
+    /// Site-specific URL rewrites used when grouping bookmarks by domain
+    pub sites: SiteRules,
+    /// Node colors shared by every output format
+    pub theme: GraphTheme,
+    /// Color bookmark nodes by age instead of by theme
+    pub heatmap: Option<Heatmap>,
+}
+
+impl GraphConfig {
+    /// Human-readable summary of the settings, recorded in graph metadata
+    pub fn summary(&self) -> BTreeMap<String, String> {
+        let limit = |v: Option<usize>| v.map_or("none".to_string(), |n| n.to_string());
+        let edges: Vec<&str> = [
+            (self.include_domain_edges, "domain"),
+            (self.include_folder_edges, "folder"),
+            (self.include_same_domain_edges, "same_domain"),
+            (self.include_tag_edges, "tag"),
+            (self.include_category_edges, "category"),
+            (self.include_similarity_edges, "similarity"),
+        ]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect();
+
+        let min_date = self.min_date.map(|d| d.format("%Y-%m-%d").to_string());
+
+        // (include?, key, value)
+        [
+            (
+                true,
+                "detail_level",
+                format!("{:?}", self.detail_level).to_lowercase(),
+            ),
+            (true, "edges", edges.join(", ")),
+            (
+                true,
+                "min_domain_threshold",
+                self.min_domain_threshold.to_string(),
+            ),
+            (
+                true,
+                "max_bookmarks_per_domain",
+                limit(self.max_bookmarks_per_domain),
+            ),
+            (true, "max_total_bookmarks", limit(self.max_total_bookmarks)),
+            (
+                self.include_tag_edges,
+                "min_tag_threshold",
+                self.min_tag_threshold.to_string(),
+            ),
+            (
+                self.include_similarity_edges,
+                "similarity_threshold",
+                self.similarity_threshold.to_string(),
+            ),
+            (min_date.is_some(), "min_date", min_date.unwrap_or_default()),
+            (self.domain_only, "domain_only", "true".to_string()),
+            (self.aggregate_edges, "aggregate_edges", "true".to_string()),
+            (
+                self.heatmap.is_some(),
+                "heatmap",
+                self.heatmap
+                    .map(Heatmap::name)
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+        ]
+        .into_iter()
+        .filter(|(include, _, _)| *include)
+        .map(|(_, key, value)| (key.to_string(), value))
+        .collect()
+    }
 }
 
 impl Default for GraphConfig {
@@ -133,6 +262,12 @@ impl Default for GraphConfig {
             max_total_bookmarks: Some(5000),
             min_date: None,
             domain_only: false,
+            aggregate_edges: false,
+            max_nodes: None,
+            max_edges: None,
+            sites: SiteRules::default(),
+            theme: GraphTheme::default(),
+            heatmap: None,
         }
     }
 }
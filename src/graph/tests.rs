@@ -10,7 +10,7 @@ fn create_test_bookmarks() -> Vec<Bookmark> {
             url: Some("https://github.com".to_string()),
             folder: Some("Development".to_string()),
             date_added: Some(Utc::now()),
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "2".to_string(),
@@ -18,7 +18,7 @@ fn create_test_bookmarks() -> Vec<Bookmark> {
             url: Some("https://github.com/user/repo".to_string()),
             folder: Some("Development".to_string()),
             date_added: Some(Utc::now()),
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "3".to_string(),
@@ -26,7 +26,7 @@ fn create_test_bookmarks() -> Vec<Bookmark> {
             url: Some("https://doc.rust-lang.org".to_string()),
             folder: Some("Development".to_string()),
             date_added: Some(Utc::now()),
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "4".to_string(),
@@ -34,7 +34,7 @@ fn create_test_bookmarks() -> Vec<Bookmark> {
             url: Some("https://www.amazon.com".to_string()),
             folder: Some("Shopping".to_string()),
             date_added: Some(Utc::now()),
-            children: None,
+            ..Default::default()
         },
     ]
 }
@@ -87,6 +87,27 @@ fn test_graph_from_bookmarks() {
     assert_eq!(folder_nodes.len(), 2);
 }
 
+#[test]
+fn test_graph_progress_events() {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut builder = GraphBuilder::new(GraphConfig::default())
+        .with_progress(move |event| sender.send(event).unwrap());
+    let graph = builder.from_bookmarks(&create_test_bookmarks()).unwrap();
+
+    let stages: Vec<(String, usize, usize)> = receiver
+        .try_iter()
+        .map(|event| match event {
+            crate::progress::ProgressEvent::StageFinished { stage, input, output, .. } => {
+                (stage, input, output)
+            }
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect();
+    assert_eq!(stages.len(), 2);
+    assert_eq!((stages[0].0.as_str(), stages[0].1), ("ingest", 4));
+    assert_eq!((stages[1].0.as_str(), stages[1].2), ("finalize", graph.nodes.len()));
+}
+
 #[test]
 fn test_graph_from_history() {
     let history = create_test_history();
@@ -139,21 +160,45 @@ fn test_edge_creation() {
     let mut builder = GraphBuilder::new(config);
     let graph = builder.from_bookmarks(&bookmarks).unwrap();
 
-    assert!(graph.edges.len() > 0);
+    assert!(!graph.edges.is_empty());
 
     let domain_edges: Vec<_> = graph
         .edges
         .iter()
         .filter(|e| e.edge_type == EdgeType::BelongsToDomain)
         .collect();
-    assert!(domain_edges.len() > 0);
+    assert!(!domain_edges.is_empty());
 
     let folder_edges: Vec<_> = graph
         .edges
         .iter()
         .filter(|e| e.edge_type == EdgeType::InFolder)
         .collect();
-    assert!(folder_edges.len() > 0);
+    assert!(!folder_edges.is_empty());
+}
+
+#[test]
+fn test_mermaid_export() {
+    let bookmarks = create_test_bookmarks();
+    let config = GraphConfig {
+        min_domain_threshold: 2,
+        detail_level: super::DetailLevel::Detailed,
+        ..Default::default()
+    };
+    let mut builder = GraphBuilder::new(config);
+    let graph = builder.from_bookmarks(&bookmarks).unwrap();
+
+    let mermaid = formats::to_mermaid(&graph);
+    assert!(mermaid.starts_with("flowchart LR\n"));
+    assert_eq!(
+        mermaid
+            .lines()
+            .filter(|l| l.contains("-->") || l.contains("-.->"))
+            .count(),
+        graph.edges.len()
+    );
+    assert!(mermaid.contains("([\"github.com\"])"));
+    assert!(!mermaid.contains("https://"));
 }
 
 #[test]
@@ -176,6 +221,14 @@ fn test_dot_export() {
     assert!(dot.contains("fillcolor"));
     assert!(dot.contains("node"));
     assert!(dot.contains("->"));
+
+    // Self-describing: metadata label and a legend for the types present
+    assert!(dot.contains("labelloc=b"));
+    assert!(dot.contains("detail_level: detailed\\l"));
+    assert!(dot.contains("subgraph cluster_legend"));
+    assert!(dot.contains("\"legend_Domain\""));
+    assert!(dot.contains("\"legend_BelongsToDomain_from\" -> \"legend_BelongsToDomain_to\""));
+    assert!(!dot.contains("legend_Tag"));
 }
 
 #[test]
@@ -225,6 +278,103 @@ fn test_gexf_export() {
     assert!(gexf.contains("</gexf>"));
 }
 
+#[test]
+fn test_gexf_viz_attributes_and_layout() {
+    let bookmarks = create_test_bookmarks();
+    let config = GraphConfig {
+        min_domain_threshold: 1,
+        detail_level: super::DetailLevel::Detailed,
+        ..Default::default()
+    };
+    let mut builder = GraphBuilder::new(config);
+    let mut graph = builder.from_bookmarks(&bookmarks).unwrap();
+
+    // Unthemed nodes get their type's color, and every node a size
+    let gexf = formats::to_gexf(&graph);
+    assert!(gexf.contains(r#"<viz:color r="79" g="195" b="247"/>"#));
+    assert_eq!(gexf.matches("<viz:size").count(), graph.nodes.len());
+    assert!(!gexf.contains("<viz:position"));
+
+    let layout = graph.layout();
+    assert_eq!(layout.len(), graph.nodes.len());
+    assert!(layout.values().all(|(x, y)| x.is_finite() && y.is_finite()));
+    assert_eq!(layout, graph.layout());
+    assert!(!formats::to_json(&graph).contains("\"x\""));
+
+    // Applied positions travel with the graph into GEXF and the HTML data
+    graph.apply_layout();
+    assert_eq!(graph.nodes[0].position, layout.get(&graph.nodes[0].id).copied());
+    let laid_out = formats::to_gexf(&graph);
+    assert_eq!(laid_out.matches("<viz:position").count(), graph.nodes.len());
+    let json: serde_json::Value = serde_json::from_str(&formats::to_json(&graph)).unwrap();
+    let (x, y) = layout[json["nodes"][0]["id"].as_str().unwrap()];
+    assert!((json["nodes"][0]["x"].as_f64().unwrap() - x).abs() <= 0.005);
+    assert!((json["nodes"][0]["y"].as_f64().unwrap() - y).abs() <= 0.005);
+    assert!(formats::to_js_data(&graph).contains("\"x\": "));
+}
+
+#[test]
+fn test_theme_colors_all_formats() {
+    let bookmarks = create_test_bookmarks();
+    let theme = GraphTheme {
+        node_types: [(NodeType::Domain, "#445566".to_string())].into(),
+        folders: [("Development".to_string(), "#112233".to_string())].into(),
+        domains: [("amazon.com".to_string(), "#aabbcc".to_string())].into(),
+        ..Default::default()
+    };
+    theme.validate().unwrap();
+    let config = GraphConfig {
+        min_domain_threshold: 1,
+        detail_level: super::DetailLevel::Detailed,
+        theme,
+        ..Default::default()
+    };
+    let mut builder = GraphBuilder::new(config);
+    let graph = builder.from_bookmarks(&bookmarks).unwrap();
+
+    let color = |id: &str| {
+        graph
+            .nodes
+            .iter()
+            .find(|n| n.id == id)
+            .and_then(|n| n.color.clone())
+    };
+    // Folder beats domain for bookmarks; the domain entry beats the type default
+    assert_eq!(color("1").as_deref(), Some("#112233"));
+    assert_eq!(color("4").as_deref(), Some("#aabbcc"));
+    assert_eq!(color("domain_github.com").as_deref(), Some("#445566"));
+    assert_eq!(color("domain_amazon.com").as_deref(), Some("#aabbcc"));
+    assert_eq!(color("folder_Shopping"), None);
+
+    let dot = formats::to_dot(&graph);
+    assert!(dot.contains("fillcolor=\"#112233\""));
+    assert!(dot.contains("fillcolor=lightyellow"));
+
+    let json: serde_json::Value = serde_json::from_str(&formats::to_json(&graph)).unwrap();
+    let node = |id: &str| {
+        json["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|n| n["id"] == id)
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(node("4")["color"], "#aabbcc");
+    assert!(node("folder_Shopping").get("color").is_none());
+
+    let gexf = formats::to_gexf(&graph);
+    assert!(gexf.contains("<viz:color r=\"17\" g=\"34\" b=\"51\"/>"));
+
+    assert!(formats::to_html(&graph, &formats::HtmlTheme::default()).contains("\"color\": \"#112233\""));
+
+    let invalid = GraphTheme {
+        tags: [("rust".to_string(), "orange".to_string())].into(),
+        ..Default::default()
+    };
+    assert!(invalid.validate().is_err());
+}
+
 #[test]
 fn test_edge_type_toggles() {
     let bookmarks = create_test_bookmarks();
@@ -260,7 +410,7 @@ fn test_tag_nodes_created() {
             url: Some("https://rust-lang.org/learn".to_string()),
             folder: Some("Dev".to_string()),
             date_added: Some(Utc::now()),
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "2".to_string(),
@@ -268,7 +418,7 @@ fn test_tag_nodes_created() {
             url: Some("https://rust-lang.org/async".to_string()),
             folder: Some("Dev".to_string()),
             date_added: Some(Utc::now()),
-            children: None,
+            ..Default::default()
         },
     ];
     let config = GraphConfig {
@@ -300,6 +450,96 @@ fn test_tag_nodes_created() {
     assert!(!tag_edges.is_empty(), "Should create HasTag edges");
 }
 
+#[test]
+fn test_github_language_groups_as_tag() {
+    let repo = |id: &str, title: &str, url: &str| Bookmark {
+        id: id.to_string(),
+        title: title.to_string(),
+        url: Some(url.to_string()),
+        folder: None,
+        date_added: Some(Utc::now()),
+        github: Some(crate::integrations::github::RepoInfo {
+            full_name: id.to_string(),
+            stars: 1,
+            archived: false,
+            language: Some("Zig".to_string()),
+            moved_to: None,
+        }),
+        ..Default::default()
+    };
+    let bookmarks = vec![
+        repo("1", "Bun runtime", "https://github.com/oven-sh/bun"),
+        repo("2", "Ghostty terminal", "https://github.com/ghostty-org/ghostty"),
+    ];
+    let config = GraphConfig {
+        min_tag_threshold: 2,
+        include_tag_edges: true,
+        detail_level: super::DetailLevel::Detailed,
+        max_bookmarks_per_domain: None,
+        max_total_bookmarks: None,
+        ..Default::default()
+    };
+    let graph = GraphBuilder::new(config).from_bookmarks(&bookmarks).unwrap();
+
+    let zig_edges = graph
+        .edges
+        .iter()
+        .filter(|e| e.edge_type == EdgeType::HasTag && e.target == "tag_zig")
+        .count();
+    assert!(graph.nodes.iter().any(|n| n.id == "tag_zig"));
+    assert_eq!(zig_edges, 2);
+}
+
+#[test]
+fn test_page_metadata_tags_and_category() {
+    let page = |site_name: &str, keywords: &[&str]| crate::enrich::PageInfo {
+        site_name: Some(site_name.to_string()),
+        og_type: Some("article".to_string()),
+        keywords: keywords.iter().map(|k| k.to_string()).collect(),
+    };
+    let bookmark = |id: &str, url: &str, page| Bookmark {
+        id: id.to_string(),
+        title: "Untitled".to_string(),
+        url: Some(url.to_string()),
+        folder: None,
+        date_added: Some(Utc::now()),
+        page: Some(page),
+        ..Default::default()
+    };
+    let bookmarks = vec![
+        bookmark(
+            "1",
+            "https://example.com/a",
+            page("The Gradient", &["Machine Learning", "transformers"]),
+        ),
+        bookmark(
+            "2",
+            "https://example.org/b",
+            page("The Gradient", &["machine learning"]),
+        ),
+    ];
+    let config = GraphConfig {
+        min_tag_threshold: 2,
+        include_tag_edges: true,
+        detail_level: super::DetailLevel::Detailed,
+        ..Default::default()
+    };
+    let graph = GraphBuilder::new(config).from_bookmarks(&bookmarks).unwrap();
+
+    let tagged = |tag: &str| {
+        graph
+            .edges
+            .iter()
+            .filter(|e| e.edge_type == EdgeType::HasTag && e.target == format!("tag_{}", tag))
+            .count()
+    };
+    assert_eq!(tagged("machine-learning"), 2);
+    assert_eq!(tagged("the-gradient"), 2);
+    assert_eq!(tagged("article"), 2);
+    // Nothing in the title or URL says so, but the keywords do
+    assert!(graph.nodes.iter().any(|n| n.id == "cat_AI & ML"));
+}
+
 #[test]
 fn test_category_nodes_created() {
     let bookmarks = create_test_bookmarks();
@@ -336,7 +576,7 @@ fn test_similarity_edges() {
             url: Some("https://rust-lang.org".to_string()),
             folder: None,
             date_added: None,
-            children: None,
+            ..Default::default()
         },
         Bookmark {
             id: "2".to_string(),
@@ -344,7 +584,7 @@ fn test_similarity_edges() {
             url: Some("https://example.com/rust".to_string()),
             folder: None,
             date_added: None,
-            children: None,
+            ..Default::default()
         },
     ];
     let config = GraphConfig {
@@ -382,12 +622,84 @@ fn test_html_export() {
     let mut builder = GraphBuilder::new(config);
     let graph = builder.from_bookmarks(&bookmarks).unwrap();
 
-    let html = formats::to_html(&graph);
+    let html = formats::to_html(&graph, &formats::HtmlTheme::default());
     assert!(html.contains("<!DOCTYPE html>"));
     assert!(html.contains("d3.v7.min.js"));
     assert!(html.contains("Knowledge Graph"));
     assert!(html.contains("\"nodes\""));
     assert!(html.contains("\"edges\""));
+    assert!(html.contains("<details id=\"graph-info\">"));
+    assert!(html.contains("\"config\""));
+    assert!(html.contains("<body class=\"dark\">"));
+
+    // Usable without a mouse: labelled controls, focusable nodes, list view
+    assert!(html.contains("<label for=\"charge\">"));
+    assert!(html.contains("aria-describedby=\"graph-help\""));
+    assert!(html.contains(".attr('aria-label', nodeLabel)"));
+    assert!(html.contains("<section id=\"list-view\" aria-label=\"Graph as a list\" hidden>"));
+    assert!(!html.contains("onclick="));
+
+    // Big graphs open with only the aggregate nodes; any graph's clusters
+    // fold on double-click, with counts as badges and the state remembered
+    assert!(html.contains("const PROGRESSIVE_BOOKMARKS = 1500;"));
+    assert!(html.contains("id=\"collapse-all\" hidden"));
+    assert!(html.contains("id=\"expand-all\" hidden"));
+    assert!(html.contains(".on('dblclick', "));
+    assert!(html.contains("localStorage.setItem(CLUSTER_STATE_KEY"));
+    assert!(html.contains("badgeText(d.bookmark_count)"));
+
+    let theme = formats::HtmlTheme {
+        mode: formats::ThemeMode::Light,
+        custom_css: Some("body.light { background: #fffff0; }".to_string()),
+    };
+    let html = formats::to_html(&graph, &theme);
+    assert!(html.contains("<body class=\"light\">"));
+    let custom = html.find("#fffff0").unwrap();
+    assert!(custom > html.find("body.light { background: #f5f5f5").unwrap());
+    assert!(custom < html.find("<body").unwrap());
+}
+
+#[test]
+fn test_js_chunks() {
+    let bookmarks = create_test_bookmarks();
+    let mut builder = GraphBuilder::new(GraphConfig {
+        min_domain_threshold: 1,
+        ..Default::default()
+    });
+    let graph = builder.from_bookmarks(&bookmarks).unwrap();
+    assert!(graph.nodes.len() > 2 && !graph.edges.is_empty());
+
+    let data = formats::to_js_chunks(&graph, "graph_1.data.js", 2);
+    let expected = graph.nodes.len().div_ceil(2) + graph.edges.len().div_ceil(2);
+    assert_eq!(data.chunks.len(), expected);
+    assert_eq!(data.chunks[0].0, "graph_1.data.1.js");
+    assert!(data.manifest.contains("window.graphManifest = {"));
+    assert!(data.manifest.contains("\"graph_1.data.1.js\""));
+    assert!(data.manifest.contains(&format!("\"total_nodes\": {}", graph.nodes.len())));
+
+    // Every node and edge lands in exactly one chunk, nodes first
+    let mut nodes = 0;
+    let mut edges = 0;
+    for (_, content) in &data.chunks {
+        let json = content
+            .strip_prefix("(window.graphChunks = window.graphChunks || []).push(")
+            .and_then(|c| c.strip_suffix(");\n"))
+            .unwrap();
+        let chunk: serde_json::Value = serde_json::from_str(json).unwrap();
+        let chunk_nodes = chunk["nodes"].as_array().unwrap().len();
+        assert!(chunk_nodes == 0 || edges == 0);
+        nodes += chunk_nodes;
+        edges += chunk["edges"].as_array().unwrap().len();
+    }
+    assert_eq!((nodes, edges), (graph.nodes.len(), graph.edges.len()));
+
+    let html = formats::to_html_dynamic(
+        std::path::Path::new("graph_1.data.js"),
+        &formats::HtmlTheme::default(),
+    );
+    assert!(html.contains("<script src=\"graph_1.data.js\"></script>"));
+    assert!(html.contains("loadChunks(window.graphManifest, initGraph)"));
+    assert!(html.contains("<progress id=\"loading-progress\""));
 }
 
 #[test]
@@ -458,7 +770,7 @@ fn test_bookmark_without_url() {
         url: None,
         folder: Some("Misc".to_string()),
         date_added: Some(Utc::now()),
-        children: None,
+        ..Default::default()
     }];
 
     let config = GraphConfig {
@@ -524,3 +836,288 @@ fn test_analyzer_jaccard_similarity() {
     let empty: HashSet<String> = HashSet::new();
     assert_eq!(analyzer::jaccard_similarity(&a, &empty), 0.0);
 }
+
+#[test]
+fn test_aggregate_same_domain_edges() {
+    let bookmarks: Vec<Bookmark> = (0..20)
+        .map(|i| Bookmark {
+            id: i.to_string(),
+            title: format!("Page {}", i),
+            url: Some(format!("https://example.com/page{}", i)),
+            folder: None,
+            date_added: None,
+            ..Default::default()
+        })
+        .collect();
+    let config = GraphConfig {
+        min_domain_threshold: 2,
+        detail_level: super::DetailLevel::Detailed,
+        max_bookmarks_per_domain: None,
+        max_total_bookmarks: None,
+        include_same_domain_edges: true,
+        ..Default::default()
+    };
+
+    let edges_of = |graph: &KnowledgeGraph, edge_type: EdgeType| -> Vec<GraphEdge> {
+        graph
+            .edges
+            .iter()
+            .filter(|e| e.edge_type == edge_type)
+            .cloned()
+            .collect()
+    };
+
+    let clique = GraphBuilder::new(config.clone())
+        .from_bookmarks(&bookmarks)
+        .unwrap();
+    assert_eq!(edges_of(&clique, EdgeType::SameDomain).len(), 190);
+    assert!(edges_of(&clique, EdgeType::BelongsToDomain).iter().all(|e| e.count.is_none()));
+
+    let aggregated = GraphBuilder::new(GraphConfig {
+        aggregate_edges: true,
+        ..config.clone()
+    })
+    .from_bookmarks(&bookmarks)
+    .unwrap();
+    // The counts ride on the domain edges rather than a second set of edges
+    assert!(edges_of(&aggregated, EdgeType::SameDomain).is_empty());
+    let edges = edges_of(&aggregated, EdgeType::BelongsToDomain);
+    assert_eq!(edges.len(), 20);
+    assert!(edges.iter().all(|e| e.target == "domain_example.com"));
+    assert!(edges.iter().all(|e| e.count == Some(19)));
+
+    let gexf = formats::to_gexf(&aggregated);
+    assert!(gexf.contains(r#"<attribute id="0" title="count" type="integer"/>"#));
+    assert!(gexf.contains(r#"<attvalue for="0" value="19"/>"#));
+    assert!(formats::to_json(&aggregated).contains("\"count\": 19"));
+
+    // Without domain edges, aggregating still links bookmarks to their domain
+    let hubs_only = GraphBuilder::new(GraphConfig {
+        aggregate_edges: true,
+        include_domain_edges: false,
+        ..config
+    })
+    .from_bookmarks(&bookmarks)
+    .unwrap();
+    let edges = edges_of(&hubs_only, EdgeType::BelongsToDomain);
+    assert_eq!(edges.len(), 20);
+    assert!(edges.iter().all(|e| e.count == Some(19)));
+}
+
+#[test]
+fn test_neighborhood_depth() {
+    use std::collections::HashSet;
+
+    let bookmarks = create_test_bookmarks();
+    let config = GraphConfig {
+        min_domain_threshold: 2,
+        detail_level: super::DetailLevel::Detailed,
+        max_bookmarks_per_domain: None,
+        max_total_bookmarks: None,
+        include_category_edges: false,
+        ..Default::default()
+    };
+    let mut builder = GraphBuilder::new(config);
+    let graph = builder.from_bookmarks(&bookmarks).unwrap();
+
+    // Depth 0 keeps only the seed
+    let focused = graph.neighborhood(&["1".to_string()], 0);
+    assert_eq!(focused.nodes.len(), 1);
+    assert_eq!(focused.metadata.total_edges, 0);
+
+    // Depth 1 reaches the github domain and the Development folder
+    let focused = graph.neighborhood(&["1".to_string()], 1);
+    let ids: HashSet<&str> = focused.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert!(ids.contains("domain_github.com"));
+    assert!(ids.contains("folder_Development"));
+    assert!(!ids.contains("4"));
+    assert_eq!(focused.metadata.total_nodes, focused.nodes.len());
+
+    // Depth 2 pulls in the other bookmarks in the same folder
+    let focused = graph.neighborhood(&["1".to_string()], 2);
+    let ids: HashSet<&str> = focused.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert!(ids.contains("3"));
+    assert!(!ids.contains("4"));
+    assert_eq!(focused.metadata.bookmark_count, 3);
+
+    // Unknown seeds produce an empty graph
+    let empty = graph.neighborhood(&["missing".to_string()], 3);
+    assert!(empty.nodes.is_empty());
+}
+
+#[test]
+fn test_size_limit_fails_fast_with_suggestions() {
+    let bookmarks: Vec<Bookmark> = (0..200)
+        .map(|i| Bookmark {
+            id: i.to_string(),
+            title: format!("Doc {}", i),
+            url: Some(format!("https://docs.example.com/{}", i)),
+            folder: None,
+            date_added: None,
+            ..Default::default()
+        })
+        .collect();
+    let config = GraphConfig {
+        detail_level: super::DetailLevel::Detailed,
+        max_total_bookmarks: None,
+        include_same_domain_edges: true,
+        max_edges: Some(5_000),
+        ..Default::default()
+    };
+
+    let err = GraphBuilder::new(config.clone())
+        .from_bookmarks(&bookmarks)
+        .unwrap_err();
+    let size_err = err.downcast_ref::<GraphSizeError>().unwrap();
+    assert_eq!(size_err.exceeded, "edge");
+    assert!(size_err.estimated_edges >= 19_900);
+    assert!(size_err.suggestions[0].contains("--aggregate-edges"));
+    assert!(err.to_string().contains("--max-edges"));
+
+    // Aggregating the clique brings it back under the limit
+    let graph = GraphBuilder::new(GraphConfig {
+        aggregate_edges: true,
+        ..config
+    })
+    .from_bookmarks(&bookmarks)
+    .unwrap();
+    assert!(graph.metadata.total_edges <= 5_000);
+}
+
+#[test]
+fn test_node_metadata_joined_from_history() {
+    let bookmarks = create_test_bookmarks();
+    let history = create_test_history();
+    let config = GraphConfig {
+        min_domain_threshold: 2,
+        detail_level: super::DetailLevel::Detailed,
+        max_bookmarks_per_domain: None,
+        max_total_bookmarks: None,
+        ..Default::default()
+    };
+    let mut builder = GraphBuilder::new(config);
+    let graph = builder.from_both(&bookmarks, &history).unwrap();
+
+    let github = graph.nodes.iter().find(|n| n.id == "1").unwrap();
+    assert_eq!(github.metadata.visit_count, Some(10));
+    assert_eq!(github.metadata.bookmark_count, 1);
+    assert!(github.metadata.date_added.is_some());
+
+    let domain = graph
+        .nodes
+        .iter()
+        .find(|n| n.id == "domain_github.com")
+        .unwrap();
+    assert_eq!(domain.metadata.bookmark_count, 2);
+    assert_eq!(domain.metadata.visit_count, Some(10));
+
+    let json: serde_json::Value = serde_json::from_str(&formats::to_json(&graph)).unwrap();
+    let node = json["nodes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|n| n["id"] == "1")
+        .unwrap();
+    assert_eq!(node["visit_count"], 10);
+    assert!(node["date_added"].is_string());
+
+    let gexf = formats::to_gexf(&graph);
+    assert!(gexf.contains(r#"title="visit_count""#));
+    assert!(gexf.contains(r#"<attvalue for="5" value="10"/>"#));
+}
+
+#[test]
+fn test_heatmap_colors_bookmarks_by_age() {
+    let mut bookmarks = create_test_bookmarks();
+    for (bookmark, days) in bookmarks.iter_mut().zip([1000, 30, 0, 1000]) {
+        bookmark.date_added = Some(Utc::now() - chrono::Duration::days(days));
+    }
+    let history = create_test_history();
+    let build = |heatmap| {
+        let config = GraphConfig {
+            min_domain_threshold: 1,
+            detail_level: super::DetailLevel::Detailed,
+            heatmap: Some(heatmap),
+            ..Default::default()
+        };
+        GraphBuilder::new(config).from_both(&bookmarks, &history).unwrap()
+    };
+    let color = |graph: &KnowledgeGraph, id: &str| {
+        graph
+            .nodes
+            .iter()
+            .find(|n| n.id == id)
+            .and_then(|n| n.color.clone())
+    };
+
+    let added = build(Heatmap::Added);
+    assert_eq!(color(&added, "1").as_deref(), Some("#2c7bb6"));
+    assert_eq!(color(&added, "3").as_deref(), Some("#d7191c"));
+    let middle = color(&added, "2").unwrap();
+    assert!(middle != "#2c7bb6" && middle != "#d7191c");
+    assert_eq!(color(&added, "domain_github.com"), None);
+    assert!(formats::to_dot(&added).contains("\"legend_heatmap\""));
+    assert_eq!(added.metadata.config["heatmap"], "added");
+
+    // GitHub was visited just now, so it is fresh despite its age
+    let visited = build(Heatmap::Visited);
+    assert_eq!(color(&visited, "1").as_deref(), Some("#d7191c"));
+    assert_eq!(color(&visited, "4").as_deref(), Some("#2c7bb6"));
+
+    assert!("visited".parse::<Heatmap>().is_ok());
+    assert!("recent".parse::<Heatmap>().is_err());
+}
+
+#[test]
+fn test_visual_diff_marks_removed_and_moved() {
+    let mut before = create_test_bookmarks();
+    let mut copy = before[0].clone();
+    copy.id = "5".to_string();
+    copy.url = Some("https://github.com/#top".to_string());
+    before.push(copy);
+
+    // The run kept one GitHub home, expired the repo and refiled the rest
+    let mut after = vec![before[0].clone(), before[2].clone(), before[3].clone()];
+    after[1].folder = Some("Reference".to_string());
+    after[2].folder = Some("Reference".to_string());
+    let key = |url: &str| url.split('#').next().unwrap().trim_end_matches('/').to_string();
+    let graph = KnowledgeGraph::visual_diff(&before, &after, key);
+
+    let config = &graph.metadata.config;
+    assert_eq!(
+        (&config["removed"][..], &config["moved"][..], &config["unchanged"][..]),
+        ("2", "2", "1")
+    );
+    let node = |url: &str| {
+        graph
+            .nodes
+            .iter()
+            .find(|n| n.url.as_deref() == Some(url))
+            .unwrap()
+    };
+    assert_eq!(node("https://github.com").color, None);
+    assert_eq!(node("https://github.com/#top").color.as_deref(), Some("#ef5350"));
+    assert_eq!(node("https://github.com/user/repo").color.as_deref(), Some("#ef5350"));
+    assert_eq!(node("https://www.amazon.com").color.as_deref(), Some("#ffb74d"));
+
+    let edge = |source: &str, edge_type: EdgeType| {
+        graph
+            .edges
+            .iter()
+            .find(|e| e.source == source && e.edge_type == edge_type)
+            .map(|e| e.target.clone())
+    };
+    let amazon = &node("https://www.amazon.com").id;
+    assert_eq!(edge(amazon, EdgeType::InFolder).as_deref(), Some("folder_Reference"));
+    assert_eq!(edge(amazon, EdgeType::MovedFrom).as_deref(), Some("folder_Shopping"));
+    assert_eq!(
+        edge(&node("https://github.com/#top").id, EdgeType::DuplicateOf),
+        Some(node("https://github.com").id.clone())
+    );
+    assert_eq!(edge(&node("https://github.com/user/repo").id, EdgeType::DuplicateOf), None);
+
+    let shopping = graph.nodes.iter().find(|n| n.id == "folder_Shopping").unwrap();
+    assert_eq!((shopping.metadata.bookmark_count, shopping.color.as_deref()), (0, Some("#9e9e9e")));
+    let html = formats::to_html(&graph, &Default::default());
+    assert!(html.contains("duplicateof"));
+}
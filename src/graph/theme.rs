@@ -0,0 +1,222 @@
+//! Node colors from the config's `theme` section.
+//!
+//! A theme maps categories, folders, domains and tags to `#rrggbb` colors, with
+//! per-node-type defaults underneath. The builder resolves one color per node
+//! and stores it on the node, so DOT, GEXF, JSON and HTML output all draw the
+//! same palette. A [`Heatmap`] recolors bookmark nodes by age on top of it.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use super::{GraphNode, NodeType};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphTheme {
+    /// Default color per node type (`bookmark`, `domain`, `folder`, `tag`,
+    /// `category`)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub node_types: BTreeMap<NodeType, String>,
+    /// Category name to color
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub categories: BTreeMap<String, String>,
+    /// Folder path to color; subfolders inherit it unless they have their own
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub folders: BTreeMap<String, String>,
+    /// Domain to color; subdomains inherit it unless they have their own
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub domains: BTreeMap<String, String>,
+    /// Tag (without `#`) to color
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl GraphTheme {
+    pub fn is_empty(&self) -> bool {
+        self.node_types.is_empty()
+            && self.categories.is_empty()
+            && self.folders.is_empty()
+            && self.domains.is_empty()
+            && self.tags.is_empty()
+    }
+
+    /// Reject colors that are not `#rrggbb`; every output format understands
+    /// those, and GEXF needs the components
+    pub fn validate(&self) -> Result<()> {
+        let colors = self
+            .node_types
+            .values()
+            .chain(self.categories.values())
+            .chain(self.folders.values())
+            .chain(self.domains.values())
+            .chain(self.tags.values());
+        for color in colors {
+            if rgb(color).is_none() {
+                return Err(anyhow!("Invalid theme color: {} (expected #rrggbb)", color));
+            }
+        }
+        Ok(())
+    }
+
+    /// The color for `node`: its own category, folder, domain or tag entry,
+    /// then the node type default. Bookmarks take their folder's color, else
+    /// their domain's.
+    pub fn color(&self, node: &GraphNode) -> Option<&str> {
+        let specific = match node.node_type {
+            NodeType::Category => self.categories.get(&node.title).map(String::as_str),
+            NodeType::Tag => self
+                .tags
+                .get(node.title.trim_start_matches('#'))
+                .map(String::as_str),
+            NodeType::Folder => node.folder.as_deref().and_then(|f| self.folder_color(f)),
+            NodeType::Domain => node.domain.as_deref().and_then(|d| self.domain_color(d)),
+            NodeType::Bookmark => node
+                .folder
+                .as_deref()
+                .and_then(|f| self.folder_color(f))
+                .or_else(|| node.domain.as_deref().and_then(|d| self.domain_color(d))),
+        };
+        specific.or_else(|| self.node_types.get(&node.node_type).map(String::as_str))
+    }
+
+    /// Color every node the theme covers
+    pub fn apply(&self, nodes: &mut [GraphNode]) {
+        for node in nodes {
+            if let Some(color) = self.color(node) {
+                node.color = Some(color.to_string());
+            }
+        }
+    }
+
+    /// The entry for `folder` or its nearest themed parent
+    fn folder_color(&self, folder: &str) -> Option<&str> {
+        let mut path = folder.trim_matches('/');
+        loop {
+            if let Some(color) = self.folders.get(path) {
+                return Some(color);
+            }
+            path = path.rsplit_once('/')?.0;
+        }
+    }
+
+    /// The entry for `domain` or its nearest themed parent domain
+    fn domain_color(&self, domain: &str) -> Option<&str> {
+        let mut host = domain.trim_start_matches("www.");
+        loop {
+            if let Some(color) = self.domains.get(host) {
+                return Some(color);
+            }
+            host = host.split_once('.')?.1;
+        }
+    }
+}
+
+/// Which date places a bookmark on the heatmap gradient
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heatmap {
+    /// When it was added
+    Added,
+    /// When its page was last visited, else when it was added
+    Visited,
+}
+
+impl Heatmap {
+    pub fn name(self) -> &'static str {
+        match self {
+            Heatmap::Added => "added",
+            Heatmap::Visited => "visited",
+        }
+    }
+
+    fn date(self, node: &GraphNode) -> Option<DateTime<Utc>> {
+        match self {
+            Heatmap::Added => node.metadata.date_added,
+            Heatmap::Visited => node.metadata.last_visit.or(node.metadata.date_added),
+        }
+    }
+
+    /// Color bookmark nodes from blue (oldest) through yellow to red
+    /// (newest). Ages are spread on a log scale, so last month isn't
+    /// squeezed into one end by a few decade-old bookmarks. Nodes without
+    /// the date keep their theme color.
+    pub fn apply(self, nodes: &mut [GraphNode], now: DateTime<Utc>) {
+        let age = |node: &GraphNode| {
+            self.date(node)
+                .filter(|_| node.node_type == NodeType::Bookmark)
+                .map(|date| ((now - date).num_days().max(0) as f64).ln_1p())
+        };
+        let ages: Vec<f64> = nodes.iter().filter_map(age).collect();
+        let Some(oldest) = ages.iter().copied().reduce(f64::max) else {
+            return;
+        };
+        let newest = ages.iter().copied().fold(oldest, f64::min);
+        for node in nodes {
+            if let Some(age) = age(node) {
+                let freshness = match oldest - newest {
+                    span if span > 0.0 => (oldest - age) / span,
+                    _ => 1.0,
+                };
+                node.color = Some(gradient(freshness));
+            }
+        }
+    }
+}
+
+impl FromStr for Heatmap {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "added" => Ok(Heatmap::Added),
+            "visited" => Ok(Heatmap::Visited),
+            _ => Err(anyhow!(
+                "Unknown heatmap: {} (expected added or visited)",
+                s
+            )),
+        }
+    }
+}
+
+/// Fill for a node the theme leaves unset, the same as the HTML legend's
+pub fn default_color(node_type: NodeType) -> &'static str {
+    match node_type {
+        NodeType::Bookmark => "#4fc3f7",
+        NodeType::Domain => "#81c784",
+        NodeType::Folder => "#fff176",
+        NodeType::Tag => "#ff8a65",
+        NodeType::Category => "#ce93d8",
+    }
+}
+
+/// Heatmap stops, stale to fresh
+pub const HEATMAP_STOPS: [&str; 3] = ["#2c7bb6", "#ffffbf", "#d7191c"];
+
+/// The heatmap color at `t` in 0..=1, stale to fresh
+fn gradient(t: f64) -> String {
+    let t = t.clamp(0.0, 1.0) * (HEATMAP_STOPS.len() - 1) as f64;
+    let i = (t.floor() as usize).min(HEATMAP_STOPS.len() - 2);
+    let (from, to) = (
+        rgb(HEATMAP_STOPS[i]).unwrap(),
+        rgb(HEATMAP_STOPS[i + 1]).unwrap(),
+    );
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * (t - i as f64)).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        mix(from.0, to.0),
+        mix(from.1, to.1),
+        mix(from.2, to.2)
+    )
+}
+
+/// The components of a `#rrggbb` color
+pub fn rgb(color: &str) -> Option<(u8, u8, u8)> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((component(0)?, component(2)?, component(4)?))
+}
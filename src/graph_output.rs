@@ -1,79 +1,94 @@
 //! Graph output handling for different formats
 
 use anyhow::Result;
-use chrono::Utc;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use crate::graph;
-use crate::utils;
-
-/// Configuration for graph output
-pub struct OutputConfig {
-    /// Temp directory for data files
-    pub temp_dir: PathBuf,
-    /// HTML filename
-    pub html_filename: String,
-    /// Data filename
-    pub data_filename: String,
-}
-
-impl OutputConfig {
-    /// Create a new output config with timestamp-based filenames
-    pub fn new() -> Self {
-        let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
-        let temp_dir = std::env::temp_dir().join("bookmark-graph");
-        fs::create_dir_all(&temp_dir).ok(); // Ignore errors, will handle later
-
-        Self {
-            temp_dir,
-            html_filename: format!("graph_{}.html", timestamp),
-            data_filename: format!("graph_{}.data.js", timestamp),
-        }
-    }
-
-    /// Get the full path for the HTML file
-    pub fn html_path(&self) -> PathBuf {
-        self.temp_dir.join(&self.html_filename)
-    }
-
-    /// Get the full path for the data file
-    pub fn data_path(&self) -> PathBuf {
-        self.temp_dir.join(&self.data_filename)
-    }
-}
 
 /// Write graph output for HTML format
+///
+/// The page goes to `output` and its data to `<stem>.data.js` beside it, so
+/// the two can be moved or shared together. Graphs with more than
+/// `chunk_size` nodes or edges get their data split into chunk files next to
+/// a manifest; `0` always writes a single file.
 pub fn write_html_output(
     graph: &graph::KnowledgeGraph,
     output: &Path,
+    theme: &graph::formats::HtmlTheme,
+    chunk_size: usize,
 ) -> Result<(PathBuf, PathBuf)> {
-    let config = OutputConfig::new();
-    let html_path = config.html_path();
-    let data_path = config.data_path();
+    let dir = output
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+    let stem = output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("graph");
+    let data_filename = format!("{}.data.js", stem);
+    let data_path = dir.join(&data_filename);
 
     // Write data file
-    let js_content = graph::formats::to_js_data(graph);
-    fs::write(&data_path, js_content)?;
+    if chunk_size > 0 && graph.nodes.len().max(graph.edges.len()) > chunk_size {
+        let data = graph::formats::to_js_chunks(graph, &data_filename, chunk_size);
+        for (name, content) in &data.chunks {
+            fs::write(dir.join(name), content)?;
+        }
+        fs::write(&data_path, data.manifest)?;
+        eprintln!("  Graph data split into {} chunks", data.chunks.len());
+    } else {
+        let js_content = graph::formats::to_js_data(graph);
+        fs::write(&data_path, js_content)?;
+    }
 
     // Write HTML file (references data file by name only, for same-directory loading)
-    let html_content = graph::formats::to_html_dynamic(&data_path);
-    fs::write(&html_path, html_content)?;
-
-    // Create redirect at requested output location if different from temp
-    if output != &html_path {
-        let target_url = html_path.display().to_string();
-        utils::create_redirect_html(output, &target_url)?;
-    }
+    let html_content = graph::formats::to_html_dynamic(&data_path, theme);
+    fs::write(output, html_content)?;
 
-    Ok((html_path, data_path))
+    Ok((output.to_path_buf(), data_path))
 }
 
 /// Print summary of graph output
-pub fn print_output_summary(html_path: &Path, data_path: &Path, graph: &graph::KnowledgeGraph) {
-    println!("  Graph files created in temp directory:");
-    println!("    HTML: {}", html_path.display());
-    println!("    Data: {}", data_path.display());
-    println!("  Opening {}", html_path.display());
-    println!("  Note: Temp files can be safely deleted after viewing");
+pub fn print_output_summary(html_path: &Path, data_path: &Path, _graph: &graph::KnowledgeGraph) {
+    eprintln!("  Graph files written:");
+    eprintln!("    HTML: {}", html_path.display());
+    eprintln!("    Data: {}", data_path.display());
+    eprintln!("  Opening {}", html_path.display());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::Bookmark;
+    use crate::graph::{GraphBuilder, GraphConfig};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_html_written_next_to_output() {
+        let bookmarks: Vec<Bookmark> = (0..3)
+            .map(|i| Bookmark {
+                id: i.to_string(),
+                title: format!("Page {}", i),
+                url: Some(format!("https://example.com/{}", i)),
+                ..Default::default()
+            })
+            .collect();
+        let graph = GraphBuilder::new(GraphConfig {
+            min_domain_threshold: 1,
+            ..Default::default()
+        })
+        .from_bookmarks(&bookmarks)
+        .unwrap();
+        let dir = tempdir().unwrap();
+        let output = dir.path().join("out/graph.html");
+
+        let (html, data) = write_html_output(&graph, &output, &Default::default(), 2).unwrap();
+        assert_eq!(html, output);
+        assert_eq!(data, dir.path().join("out/graph.data.js"));
+        assert!(fs::read_to_string(&html).unwrap().contains("graph.data.js"));
+        assert!(data.exists());
+        assert!(dir.path().join("out/graph.data.1.js").exists());
+    }
 }
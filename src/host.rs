@@ -0,0 +1,356 @@
+//! Native messaging host for a companion browser extension.
+//!
+//! Chrome and Firefox start `bookmark-host` when an extension connects and
+//! talk to it over stdin and stdout. Each message is a 32-bit length in
+//! native byte order followed by that many bytes of UTF-8 JSON. A request
+//! names an `action` and may carry an `id`, which the response repeats:
+//!
+//! - `ping` answers with the version
+//! - `search` ranks bookmarks for `query` the way `search` does
+//! - `add` puts `url`, with an optional `title`, `folder` and `tags`, into
+//!   the bookmark store
+//! - `organize` says where the organizer would file `url`, or each of
+//!   `bookmarks`, under the config's rules
+//!
+//! Responses are `{"id", "ok": true, "result"}` or `{"id", "ok": false,
+//! "error"}`. Browsers drop a message from the host over 1 MB, so a larger
+//! result is answered with an error asking for a smaller `limit`.
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::config::AppConfig;
+use crate::deduplication::{BookmarkDeduplicator, DeduplicationConfig};
+use crate::exporter::{Bookmark, BrowserData};
+use crate::organization::{BookmarkOrganizer, OrganizationConfig};
+use crate::search::{SearchOptions, search_bookmarks_internal};
+use crate::store::Store;
+
+/// The name extensions connect to, and the manifest's file stem
+pub const HOST_NAME: &str = "com.github.yingkitw.bookmark";
+
+/// Largest message a browser accepts from the host
+const MAX_OUTGOING: usize = 1024 * 1024;
+
+/// Largest message read from the browser, so one can't exhaust memory
+const MAX_INCOMING: usize = 64 * 1024 * 1024;
+
+/// Read one message; `None` when the browser has closed the pipe
+pub fn read_message(reader: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let length = u32::from_ne_bytes(length) as usize;
+    if length > MAX_INCOMING {
+        return Err(anyhow!("Message of {} bytes is too large", length));
+    }
+    let mut message = vec![0u8; length];
+    reader.read_exact(&mut message)?;
+    Ok(Some(message))
+}
+
+/// Write one message with its length prefix
+pub fn write_message(writer: &mut impl Write, message: &[u8]) -> Result<()> {
+    let length = u32::try_from(message.len())?;
+    writer.write_all(&length.to_ne_bytes())?;
+    writer.write_all(message)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// The manifest registering the host with `browser` (chrome or firefox)
+/// for one extension, with `path` the `bookmark-host` executable
+pub fn manifest(browser: &str, extension_id: &str, path: &Path) -> Result<Value> {
+    let mut manifest = json!({
+        "name": HOST_NAME,
+        "description": "Bookmark manager",
+        "path": path,
+        "type": "stdio",
+    });
+    match browser {
+        "chrome" | "chromium" | "edge" | "brave" => {
+            manifest["allowed_origins"] = json!([format!("chrome-extension://{}/", extension_id)]);
+        }
+        "firefox" => manifest["allowed_extensions"] = json!([extension_id]),
+        other => {
+            return Err(anyhow!(
+                "Unknown browser: {}. Use chrome, chromium, edge, brave or firefox",
+                other
+            ));
+        }
+    }
+    Ok(manifest)
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum Request {
+    Ping,
+    Search {
+        query: String,
+        #[serde(default = "default_limit")]
+        limit: usize,
+        #[serde(default)]
+        title_only: bool,
+        #[serde(default)]
+        url_only: bool,
+    },
+    Add {
+        url: String,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        folder: Option<String>,
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+    Organize {
+        #[serde(default)]
+        url: Option<String>,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        bookmarks: Vec<Bookmark>,
+    },
+}
+
+fn default_limit() -> usize {
+    20
+}
+
+/// Answers an extension's messages until it disconnects
+pub struct NativeHost {
+    config: AppConfig,
+}
+
+impl NativeHost {
+    /// A host using the config file in effect
+    pub fn new() -> Result<Self> {
+        let path = &crate::paths::get().config;
+        let config = match path.exists() {
+            true => AppConfig::load_from_file(path)?,
+            false => AppConfig::default(),
+        };
+        Ok(Self { config })
+    }
+
+    /// Answer messages on stdin until the browser closes it
+    pub fn run(&self) -> Result<()> {
+        let mut stdin = io::stdin().lock();
+        let mut stdout = io::stdout().lock();
+        while let Some(message) = read_message(&mut stdin)? {
+            let response = match serde_json::from_slice::<Value>(&message) {
+                Ok(request) => self.handle(&request),
+                Err(e) => failure(&Value::Null, format!("Invalid JSON: {}", e)),
+            };
+            let mut bytes = serde_json::to_vec(&response)?;
+            if bytes.len() > MAX_OUTGOING {
+                let error = format!(
+                    "Response of {} bytes is over the browser's 1 MB limit; ask for fewer results",
+                    bytes.len()
+                );
+                bytes = serde_json::to_vec(&failure(&response["id"], error))?;
+            }
+            write_message(&mut stdout, &bytes)?;
+        }
+        Ok(())
+    }
+
+    /// The response to one request
+    pub fn handle(&self, request: &Value) -> Value {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let result = serde_json::from_value::<Request>(request.clone())
+            .map_err(|e| anyhow!("Invalid request: {}", e))
+            .and_then(|request| self.dispatch(request));
+        match result {
+            Ok(result) => json!({ "id": id, "ok": true, "result": result }),
+            Err(e) => failure(&id, format!("{:#}", e)),
+        }
+    }
+
+    fn dispatch(&self, request: Request) -> Result<Value> {
+        match request {
+            Request::Ping => Ok(json!({ "name": HOST_NAME, "version": env!("CARGO_PKG_VERSION") })),
+            Request::Search {
+                query,
+                limit,
+                title_only,
+                url_only,
+            } => {
+                let options = SearchOptions {
+                    title_only,
+                    url_only,
+                    limit,
+                    media_type: None,
+                    sort: Default::default(),
+                };
+                Ok(serde_json::to_value(search_bookmarks_internal(
+                    &query, &options,
+                )?)?)
+            }
+            Request::Add {
+                url,
+                title,
+                folder,
+                tags,
+            } => {
+                let bookmark = new_bookmark(url, title, folder, tags);
+                let summary = self.add(bookmark, &mut Store::open_default()?)?;
+                Ok(json!({ "added": summary.added == 1 }))
+            }
+            Request::Organize {
+                url,
+                title,
+                mut bookmarks,
+            } => {
+                if let Some(url) = url {
+                    bookmarks.push(new_bookmark(url, title, None, Vec::new()));
+                }
+                if bookmarks.is_empty() {
+                    return Err(anyhow!("Give a url or bookmarks to organize"));
+                }
+                Ok(json!(self.organize(&bookmarks)))
+            }
+        }
+    }
+
+    /// Store `bookmark` unless a bookmark with the same normalized URL is
+    /// there already. This is an import rather than a sync, so until the
+    /// user syncs, searches keep reading the browsers.
+    fn add(&self, bookmark: Bookmark, store: &mut Store) -> Result<crate::store::ImportSummary> {
+        let deduplicator = BookmarkDeduplicator::new(DeduplicationConfig {
+            sites: self.config.sites.clone(),
+            resolve_redirects: false,
+            ..self.config.deduplication.clone()
+        });
+        let key = |url: &str| {
+            deduplicator
+                .normalize_url(url)
+                .unwrap_or_else(|_| url.to_string())
+        };
+        let data = BrowserData {
+            browser: "extension".to_string(),
+            profile: HOST_NAME.to_string(),
+            export_date: chrono::Utc::now(),
+            bookmarks: Some(vec![bookmark]),
+            history: None,
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        };
+        store.import(data, key, false)
+    }
+
+    /// Each bookmark's URL with where the organizer would file it
+    fn organize(&self, bookmarks: &[Bookmark]) -> Vec<Value> {
+        let organizer = BookmarkOrganizer::new(OrganizationConfig {
+            sites: self.config.sites.clone(),
+            ..self.config.organization.clone()
+        });
+        bookmarks
+            .iter()
+            .map(|bookmark| {
+                let mut placement = json!(organizer.explain(bookmark));
+                placement["url"] = json!(bookmark.url);
+                placement
+            })
+            .collect()
+    }
+}
+
+fn failure(id: &Value, error: String) -> Value {
+    json!({ "id": id, "ok": false, "error": error })
+}
+
+fn new_bookmark(
+    url: String,
+    title: Option<String>,
+    folder: Option<String>,
+    tags: Vec<String>,
+) -> Bookmark {
+    Bookmark {
+        id: String::new(),
+        title: title.unwrap_or_else(|| url.clone()),
+        url: Some(url),
+        folder: folder.filter(|f| !f.is_empty()),
+        date_added: Some(chrono::Utc::now()),
+        tags: (!tags.is_empty()).then_some(tags),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_messages_and_requests() {
+        let mut pipe = Vec::new();
+        write_message(&mut pipe, br#"{"action":"ping"}"#).unwrap();
+        assert_eq!(&pipe[..4], &17u32.to_ne_bytes());
+        let mut reader = pipe.as_slice();
+        assert_eq!(
+            read_message(&mut reader).unwrap().unwrap(),
+            br#"{"action":"ping"}"#
+        );
+        assert!(read_message(&mut reader).unwrap().is_none());
+
+        let host = NativeHost {
+            config: AppConfig::default(),
+        };
+        let pong = host.handle(&json!({ "id": 7, "action": "ping" }));
+        assert_eq!((&pong["id"], &pong["ok"]), (&json!(7), &json!(true)));
+        let unknown = host.handle(&json!({ "id": 8, "action": "delete" }));
+        assert_eq!(unknown["ok"], false);
+        assert!(unknown["error"].as_str().unwrap().contains("delete"));
+
+        let organized = host.handle(&json!({
+            "action": "organize",
+            "url": "https://github.com/rust-lang/rust",
+        }));
+        assert_eq!(
+            organized["result"][0]["url"],
+            "https://github.com/rust-lang/rust"
+        );
+        assert!(organized["result"][0]["folder"].is_string());
+
+        let dir = tempdir().unwrap();
+        let mut store = Store::open(&dir.path().join("store.db")).unwrap();
+        let bookmark = |url: &str| {
+            new_bookmark(
+                url.to_string(),
+                None,
+                Some("Inbox".to_string()),
+                vec!["later".to_string()],
+            )
+        };
+        let first = host
+            .add(bookmark("https://example.com/a"), &mut store)
+            .unwrap();
+        assert_eq!(first.added, 1);
+        let again = host
+            .add(bookmark("https://www.example.com/a#top"), &mut store)
+            .unwrap();
+        assert_eq!((again.added, again.skipped), (0, 1));
+        let stored = store.bookmarks().unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].folder.as_deref(), Some("Inbox"));
+
+        let path = Path::new("/usr/bin/bookmark-host");
+        let chrome = manifest("chrome", "abc", path).unwrap();
+        assert_eq!(chrome["allowed_origins"][0], "chrome-extension://abc/");
+        assert_eq!(chrome["path"], "/usr/bin/bookmark-host");
+        let firefox = manifest("firefox", "host@example.org", path).unwrap();
+        assert_eq!(firefox["allowed_extensions"][0], "host@example.org");
+        assert!(manifest("lynx", "abc", path).is_err());
+    }
+}
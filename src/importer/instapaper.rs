@@ -0,0 +1,104 @@
+//! Instapaper CSV export parser.
+//!
+//! The export has `URL,Title,Selection,Folder` columns, plus a `Timestamp`
+//! (Unix seconds) in newer exports. `Folder` is `Unread`, `Archive`,
+//! `Starred` or a user folder name; it is kept under an `Instapaper` folder,
+//! and the highlighted `Selection` becomes the bookmark's description.
+
+use anyhow::{Result, anyhow};
+use chrono::DateTime;
+use serde::Deserialize;
+
+use crate::exporter::Bookmark;
+
+/// Folder marking items that came from Instapaper
+const INSTAPAPER_FOLDER: &str = "Instapaper";
+
+#[derive(Debug, Deserialize)]
+struct InstapaperRow {
+    #[serde(rename = "URL")]
+    url: String,
+    #[serde(rename = "Title", default)]
+    title: String,
+    #[serde(rename = "Selection", default)]
+    selection: String,
+    #[serde(rename = "Folder", default)]
+    folder: String,
+    #[serde(rename = "Timestamp", default)]
+    timestamp: String,
+}
+
+pub fn parse(content: &str) -> Result<Vec<Bookmark>> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    if !reader.headers()?.iter().any(|h| h == "URL") {
+        return Err(anyhow!("Not an Instapaper export: missing URL column"));
+    }
+
+    let mut bookmarks = Vec::new();
+    for row in reader.deserialize::<InstapaperRow>() {
+        let row = row?;
+        if row.url.is_empty() {
+            continue;
+        }
+        let folder = match row.folder.trim() {
+            "" => INSTAPAPER_FOLDER.to_string(),
+            name => format!("{}/{}", INSTAPAPER_FOLDER, name),
+        };
+        let selection = row.selection.trim();
+        bookmarks.push(Bookmark {
+            id: format!("instapaper-{}", bookmarks.len() + 1),
+            title: row.title,
+            url: Some(row.url),
+            folder: Some(folder),
+            date_added: row
+                .timestamp
+                .trim()
+                .parse::<i64>()
+                .ok()
+                .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+            description: (!selection.is_empty()).then(|| selection.to_string()),
+            ..Default::default()
+        });
+    }
+
+    Ok(bookmarks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_export() {
+        let csv = "URL,Title,Selection,Folder,Timestamp\n\
+                   https://example.com/a,\"Hello, world\",,Unread,1609459200\n\
+                   https://example.com/b,Quoted,\"A \"\"good\"\" bit\",Rust Reading,\n";
+        let bookmarks = parse(csv).unwrap();
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks[0].title, "Hello, world");
+        assert_eq!(bookmarks[0].folder.as_deref(), Some("Instapaper/Unread"));
+        assert_eq!(bookmarks[0].description, None);
+        assert_eq!(
+            bookmarks[0].date_added,
+            DateTime::from_timestamp(1609459200, 0)
+        );
+        assert_eq!(
+            bookmarks[1].folder.as_deref(),
+            Some("Instapaper/Rust Reading")
+        );
+        assert_eq!(bookmarks[1].description.as_deref(), Some("A \"good\" bit"));
+        assert_eq!(bookmarks[1].date_added, None);
+    }
+
+    #[test]
+    fn test_parse_export_without_timestamp() {
+        let csv = "URL,Title,Selection,Folder\nhttps://example.com/a,A,,Archive\n";
+        let bookmarks = parse(csv).unwrap();
+        assert_eq!(bookmarks[0].folder.as_deref(), Some("Instapaper/Archive"));
+    }
+
+    #[test]
+    fn test_rejects_unrelated_csv() {
+        assert!(parse("title,url\nA,https://example.com\n").is_err());
+    }
+}
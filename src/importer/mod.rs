@@ -0,0 +1,133 @@
+//! Import bookmarks from files produced by browsers and other services
+
+mod instapaper;
+mod netscape;
+mod onetab;
+mod pocket;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::exporter::Bookmark;
+
+/// Supported import file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// Netscape bookmark file (`bookmarks.html`), exported by every major browser
+    Html,
+    /// Pocket export (`ril_export.html` or CSV)
+    Pocket,
+    /// Instapaper CSV export
+    Instapaper,
+    /// OneTab "Export URLs" text
+    OneTab,
+}
+
+impl FromStr for ImportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "html" | "netscape" => Ok(ImportFormat::Html),
+            "pocket" => Ok(ImportFormat::Pocket),
+            "instapaper" => Ok(ImportFormat::Instapaper),
+            "onetab" => Ok(ImportFormat::OneTab),
+            _ => Err(anyhow!("Unsupported import format: {}", s)),
+        }
+    }
+}
+
+/// Parse an import file into bookmarks
+pub fn import_file(path: &Path, format: ImportFormat) -> Result<Vec<Bookmark>> {
+    let content = fs::read_to_string(path)?;
+    if format == ImportFormat::OneTab {
+        // OneTab exports are undated; the file's modification time is the best
+        // record of when the dump was taken
+        let exported_at = fs::metadata(path)?
+            .modified()
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+        return onetab::parse(&content, exported_at);
+    }
+    import_str(&content, format)
+}
+
+/// Parse already-loaded import content into bookmarks. Undated formats
+/// (OneTab) are stamped with the current time.
+pub fn import_str(content: &str, format: ImportFormat) -> Result<Vec<Bookmark>> {
+    match format {
+        ImportFormat::Html => netscape::parse(content),
+        ImportFormat::Pocket => pocket::parse(content),
+        ImportFormat::Instapaper => instapaper::parse(content),
+        ImportFormat::OneTab => onetab::parse(content, Utc::now()),
+    }
+}
+
+/// Matches one `name="value"` pair inside an HTML start tag
+pub(crate) const ATTRIBUTE_PATTERN: &str = r#"(?s)([A-Za-z_]+)\s*=\s*"([^"]*)""#;
+
+/// Upper-cased attribute names mapped to their entity-decoded values
+pub(crate) fn parse_attributes(attribute: &Regex, attrs: &str) -> HashMap<String, String> {
+    attribute
+        .captures_iter(attrs)
+        .map(|c| (c[1].to_uppercase(), decode_entities(&c[2])))
+        .collect()
+}
+
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let entity = &rest[1..end];
+            let ch = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse))
+                    .and_then(|n| n.ok())
+                    .and_then(char::from_u32),
+            };
+            ch.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(
+            decode_entities("a &lt;b&gt; &#39;c&#x27; &bogus; &"),
+            "a <b> 'c' &bogus; &"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Netscape bookmark file (`bookmarks.html`) parser.
+//!
+//! The format is loose HTML: folders are `<DT><H3>` headings followed by a
+//! nested `<DL>` list, and bookmarks are `<DT><A HREF=...>` links carrying
+//! `ADD_DATE` (Unix seconds), optional `TAGS` (comma separated) and Firefox's
+//! `SHORTCUTURL` keyword. Closing `</DT>`/`<p>` tags are usually omitted, so
+//! only the headings, links and list boundaries are matched.
+
+use anyhow::{Result, anyhow};
+use chrono::DateTime;
+use regex::Regex;
+
+use super::{ATTRIBUTE_PATTERN, decode_entities, parse_attributes};
+use crate::exporter::Bookmark;
+
+pub fn parse(html: &str) -> Result<Vec<Bookmark>> {
+    let element =
+        Regex::new(r"(?is)<h3\b([^>]*)>(.*?)</h3\s*>|<a\b([^>]*)>(.*?)</a\s*>|<(/?)dl\b[^>]*>")?;
+    let attribute = Regex::new(ATTRIBUTE_PATTERN)?;
+
+    if !html.to_uppercase().contains("<DL") {
+        return Err(anyhow!("Not a Netscape bookmark file: no <DL> list found"));
+    }
+
+    let mut bookmarks = Vec::new();
+    // One entry per open <DL>; `None` for lists that are not a named folder
+    let mut folders: Vec<Option<String>> = Vec::new();
+    let mut pending_folder = None;
+
+    for caps in element.captures_iter(html) {
+        if let Some(name) = caps.get(2) {
+            pending_folder = Some(decode_entities(name.as_str().trim()));
+        } else if let Some(attrs) = caps.get(3) {
+            let attrs = parse_attributes(&attribute, attrs.as_str());
+            let Some(url) = attrs.get("HREF").filter(|u| !u.is_empty()) else {
+                continue;
+            };
+            let path: Vec<&str> = folders.iter().flatten().map(String::as_str).collect();
+            bookmarks.push(Bookmark {
+                id: (bookmarks.len() + 1).to_string(),
+                title: decode_entities(caps[4].trim()),
+                url: Some(url.clone()),
+                folder: (!path.is_empty()).then(|| path.join("/")),
+                date_added: attrs
+                    .get("ADD_DATE")
+                    .and_then(|d| d.parse::<i64>().ok())
+                    .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+                keyword: attrs.get("SHORTCUTURL").filter(|k| !k.is_empty()).cloned(),
+                tags: attrs
+                    .get("TAGS")
+                    .map(|t| split_tags(t))
+                    .filter(|t| !t.is_empty()),
+                ..Default::default()
+            });
+        } else if caps.get(5).is_some_and(|m| m.as_str() == "/") {
+            folders.pop();
+        } else {
+            folders.push(pending_folder.take());
+        }
+    }
+
+    Ok(bookmarks)
+}
+
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1600000000" PERSONAL_TOOLBAR_FOLDER="true">Bookmarks bar</H3>
+    <DL><p>
+        <DT><A HREF="https://www.rust-lang.org/" ADD_DATE="1609459200" TAGS="rust, lang">Rust &amp; Friends</A>
+        <DT><H3>Dev</H3>
+        <DL><p>
+            <DT><A HREF="https://github.com/" SHORTCUTURL="gh">GitHub</A>
+            <DD>Where the code lives
+        </DL><p>
+    </DL><p>
+    <DT><a href="https://example.com/?a=1&amp;b=2">Top level</a>
+</DL><p>
+"#;
+
+    #[test]
+    fn test_parse_nested_folders() {
+        let bookmarks = parse(SAMPLE).unwrap();
+        assert_eq!(bookmarks.len(), 3);
+
+        assert_eq!(bookmarks[0].title, "Rust & Friends");
+        assert_eq!(bookmarks[0].folder.as_deref(), Some("Bookmarks bar"));
+        assert_eq!(
+            bookmarks[0].date_added,
+            DateTime::from_timestamp(1609459200, 0)
+        );
+        assert_eq!(
+            bookmarks[0].tags,
+            Some(vec!["rust".to_string(), "lang".to_string()])
+        );
+
+        assert_eq!(bookmarks[1].folder.as_deref(), Some("Bookmarks bar/Dev"));
+        assert_eq!(bookmarks[1].keyword.as_deref(), Some("gh"));
+        assert_eq!(bookmarks[1].tags, None);
+
+        assert_eq!(
+            bookmarks[2].url.as_deref(),
+            Some("https://example.com/?a=1&b=2")
+        );
+        assert_eq!(bookmarks[2].folder, None);
+    }
+
+    #[test]
+    fn test_rejects_non_bookmark_file() {
+        assert!(parse("<html><body>hello</body></html>").is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! OneTab export parser.
+//!
+//! OneTab's "Export URLs" page is plain text: one `URL | Title` line per tab,
+//! with tab groups (sessions) separated by blank lines, newest first. The
+//! export carries no dates, so every session is filed under
+//! `OneTab/<export date> #<n>` using the date the export was taken.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+
+use crate::exporter::Bookmark;
+
+/// Folder marking items that came from OneTab
+const ONETAB_FOLDER: &str = "OneTab";
+
+pub fn parse(content: &str, exported_at: DateTime<Utc>) -> Result<Vec<Bookmark>> {
+    let date = exported_at.format("%Y-%m-%d");
+    let mut bookmarks = Vec::new();
+    let mut session = 0;
+    let mut in_session = false;
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() {
+            in_session = false;
+            continue;
+        }
+        let (url, title) = match line.split_once(" | ") {
+            Some((url, title)) => (url.trim(), title.trim()),
+            None => (line, ""),
+        };
+        if !url.contains("://") {
+            continue;
+        }
+        if !in_session {
+            session += 1;
+            in_session = true;
+        }
+        bookmarks.push(Bookmark {
+            id: format!("onetab-{}", bookmarks.len() + 1),
+            title: if title.is_empty() { url } else { title }.to_string(),
+            url: Some(url.to_string()),
+            folder: Some(format!("{}/{} #{}", ONETAB_FOLDER, date, session)),
+            date_added: Some(exported_at),
+            ..Default::default()
+        });
+    }
+
+    if bookmarks.is_empty() && !content.trim().is_empty() {
+        return Err(anyhow!("Not a OneTab export: no `URL | Title` lines found"));
+    }
+    Ok(bookmarks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sessions() {
+        let export = "https://www.rust-lang.org/ | Rust | Programming Language\n\
+                      https://docs.rs/\n\
+                      \n\
+                      \n\
+                      https://github.com/ | GitHub\n";
+        let exported_at = DateTime::from_timestamp(1609459200, 0).unwrap();
+        let bookmarks = parse(export, exported_at).unwrap();
+
+        assert_eq!(bookmarks.len(), 3);
+        assert_eq!(bookmarks[0].title, "Rust | Programming Language");
+        assert_eq!(bookmarks[0].folder.as_deref(), Some("OneTab/2021-01-01 #1"));
+        assert_eq!(bookmarks[1].title, "https://docs.rs/");
+        assert_eq!(bookmarks[1].folder.as_deref(), Some("OneTab/2021-01-01 #1"));
+        assert_eq!(bookmarks[2].folder.as_deref(), Some("OneTab/2021-01-01 #2"));
+        assert_eq!(bookmarks[2].date_added, Some(exported_at));
+    }
+
+    #[test]
+    fn test_rejects_non_onetab_text() {
+        assert!(parse("just some notes\nnothing else", Utc::now()).is_err());
+    }
+}
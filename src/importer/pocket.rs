@@ -0,0 +1,182 @@
+//! Pocket export parser.
+//!
+//! Pocket has shipped two export layouts: the older `ril_export.html`, with
+//! `<h1>Unread</h1>`/`<h1>Read Archive</h1>` sections of
+//! `<a href time_added tags>` links, and a CSV with
+//! `title,url,time_added,tags,status` columns where tags are `|`-separated.
+//! Items land in a `Pocket` folder (`Pocket/Archive` for read items) so they
+//! stay distinguishable after merging with browser bookmarks.
+
+use anyhow::{Result, anyhow};
+use chrono::DateTime;
+use regex::Regex;
+use serde::Deserialize;
+
+use super::{ATTRIBUTE_PATTERN, decode_entities, parse_attributes};
+use crate::exporter::Bookmark;
+
+/// Folder marking items that came from Pocket
+const POCKET_FOLDER: &str = "Pocket";
+const ARCHIVE_FOLDER: &str = "Pocket/Archive";
+
+pub fn parse(content: &str) -> Result<Vec<Bookmark>> {
+    if content.trim_start().starts_with('<') {
+        parse_html(content)
+    } else {
+        parse_csv(content)
+    }
+}
+
+fn parse_html(html: &str) -> Result<Vec<Bookmark>> {
+    let element = Regex::new(r"(?is)<h1\b[^>]*>(.*?)</h1\s*>|<a\b([^>]*)>(.*?)</a\s*>")?;
+    let attribute = Regex::new(ATTRIBUTE_PATTERN)?;
+
+    let mut bookmarks = Vec::new();
+    let mut archived = false;
+
+    for caps in element.captures_iter(html) {
+        if let Some(heading) = caps.get(1) {
+            archived = heading.as_str().to_lowercase().contains("archive");
+            continue;
+        }
+        let attrs = parse_attributes(&attribute, &caps[2]);
+        let Some(url) = attrs.get("HREF").filter(|u| !u.is_empty()) else {
+            continue;
+        };
+        bookmarks.push(pocket_bookmark(
+            bookmarks.len(),
+            decode_entities(caps[3].trim()),
+            url.clone(),
+            attrs.get("TIME_ADDED").map(String::as_str),
+            attrs.get("TAGS").map(|t| t.split(',')),
+            archived,
+        ));
+    }
+
+    Ok(bookmarks)
+}
+
+#[derive(Debug, Deserialize)]
+struct PocketRow {
+    #[serde(default)]
+    title: String,
+    url: String,
+    #[serde(default)]
+    time_added: String,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    status: String,
+}
+
+fn parse_csv(content: &str) -> Result<Vec<Bookmark>> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    if !reader.headers()?.iter().any(|h| h == "url") {
+        return Err(anyhow!("Not a Pocket export: missing url column"));
+    }
+
+    let mut bookmarks = Vec::new();
+    for row in reader.deserialize::<PocketRow>() {
+        let row = row?;
+        if row.url.is_empty() {
+            continue;
+        }
+        bookmarks.push(pocket_bookmark(
+            bookmarks.len(),
+            row.title,
+            row.url,
+            Some(&row.time_added),
+            Some(row.tags.split('|')),
+            row.status == "archive",
+        ));
+    }
+
+    Ok(bookmarks)
+}
+
+fn pocket_bookmark<'a>(
+    index: usize,
+    title: String,
+    url: String,
+    time_added: Option<&str>,
+    tags: Option<impl Iterator<Item = &'a str>>,
+    archived: bool,
+) -> Bookmark {
+    let tags: Vec<String> = tags
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect();
+    let folder = if archived {
+        ARCHIVE_FOLDER
+    } else {
+        POCKET_FOLDER
+    };
+    Bookmark {
+        id: format!("pocket-{}", index + 1),
+        title,
+        url: Some(url),
+        folder: Some(folder.to_string()),
+        date_added: time_added
+            .and_then(|t| t.trim().parse::<i64>().ok())
+            .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+        tags: (!tags.is_empty()).then_some(tags),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_html_export() {
+        let html = r#"<!DOCTYPE html>
+<html><head><title>Pocket Export</title></head><body>
+<h1>Unread</h1>
+<ul>
+<li><a href="https://example.com/a" time_added="1609459200" tags="rust,async">A &amp; B</a></li>
+</ul>
+<h1>Read Archive</h1>
+<ul>
+<li><a href="https://example.com/b" time_added="1609459300" tags="">Read one</a></li>
+</ul>
+</body></html>"#;
+        let bookmarks = parse(html).unwrap();
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks[0].title, "A & B");
+        assert_eq!(bookmarks[0].folder.as_deref(), Some("Pocket"));
+        assert_eq!(
+            bookmarks[0].tags,
+            Some(vec!["rust".to_string(), "async".to_string()])
+        );
+        assert_eq!(
+            bookmarks[0].date_added,
+            DateTime::from_timestamp(1609459200, 0)
+        );
+        assert_eq!(bookmarks[1].folder.as_deref(), Some("Pocket/Archive"));
+        assert_eq!(bookmarks[1].tags, None);
+    }
+
+    #[test]
+    fn test_parse_csv_export() {
+        let csv = "title,url,time_added,tags,status\n\
+                   \"Hello, world\",https://example.com/a,1609459200,rust|cli,unread\n\
+                   Done,https://example.com/b,1609459300,,archive\n";
+        let bookmarks = parse(csv).unwrap();
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks[0].title, "Hello, world");
+        assert_eq!(
+            bookmarks[0].tags,
+            Some(vec!["rust".to_string(), "cli".to_string()])
+        );
+        assert_eq!(bookmarks[1].folder.as_deref(), Some("Pocket/Archive"));
+    }
+
+    #[test]
+    fn test_rejects_unrelated_csv() {
+        assert!(parse("name,email\nbob,bob@example.com\n").is_err());
+    }
+}
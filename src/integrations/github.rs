@@ -0,0 +1,321 @@
+//! GitHub repository lookups.
+//!
+//! Bookmarks pointing at a repository can be enriched from the REST API with
+//! its stars, primary language and whether it was archived or moved. Without
+//! a token the API allows 60 requests an hour, so set `github.api_token` in
+//! the config (or `GITHUB_TOKEN`) for larger collections. Each repository is
+//! looked up once per run however many bookmarks point at it.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use url::Url;
+
+use crate::exporter::Bookmark;
+
+const API_BASE: &str = "https://api.github.com";
+
+/// First path segments on github.com that are not repository owners
+const RESERVED_OWNERS: &[&str] = &[
+    "about",
+    "apps",
+    "collections",
+    "enterprise",
+    "explore",
+    "features",
+    "login",
+    "marketplace",
+    "notifications",
+    "orgs",
+    "pricing",
+    "pulls",
+    "search",
+    "settings",
+    "sponsors",
+    "topics",
+    "trending",
+];
+
+/// Tag added to bookmarks of archived repositories
+pub const ARCHIVED_TAG: &str = "repo-archived";
+/// Tag added to bookmarks of repositories that were renamed or transferred
+pub const MOVED_TAG: &str = "repo-moved";
+
+/// What the API reports about a bookmarked repository
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoInfo {
+    /// `owner/name` as GitHub knows it now
+    pub full_name: String,
+    pub stars: u64,
+    pub archived: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// The repository's current URL, when the bookmarked one redirects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moved_to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiRepo {
+    full_name: String,
+    html_url: String,
+    stargazers_count: u64,
+    archived: bool,
+    language: Option<String>,
+}
+
+/// Outcome of enriching a set of bookmarks
+#[derive(Debug, Default)]
+pub struct GithubSummary {
+    /// Bookmarks that point at a repository
+    pub repos: usize,
+    pub enriched: usize,
+    pub archived: usize,
+    pub moved: usize,
+    /// Repositories that no longer exist (or are private)
+    pub missing: usize,
+    /// Why lookups stopped early, e.g. the rate limit
+    pub error: Option<String>,
+}
+
+pub struct GithubClient {
+    token: Option<String>,
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl GithubClient {
+    pub fn new(token: Option<&str>) -> Self {
+        Self {
+            token: token.map(str::to_string),
+            base_url: API_BASE.to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    /// Point the client at another API root (e.g. a local mock)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Look up `owner/repo`; `None` when it doesn't exist. Renamed and
+    /// transferred repositories are followed and reported in `moved_to`.
+    pub fn repo(&self, owner: &str, repo: &str) -> Result<Option<RepoInfo>> {
+        let mut request = self
+            .agent
+            .get(&format!("{}/repos/{}/{}", self.base_url, owner, repo))
+            .set("Accept", "application/vnd.github+json")
+            .set(
+                "User-Agent",
+                concat!("bookmark/", env!("CARGO_PKG_VERSION")),
+            );
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+
+        let api: ApiRepo = match request.call() {
+            Ok(response) => response
+                .into_json()
+                .map_err(|e| anyhow!("Unexpected GitHub response: {}", e))?,
+            Err(ureq::Error::Status(404, _)) => return Ok(None),
+            Err(ureq::Error::Status(401, _)) => {
+                return Err(anyhow!(
+                    "GitHub rejected the API token; check github.api_token in the config"
+                ));
+            }
+            Err(ureq::Error::Status(403 | 429, _)) => {
+                return Err(anyhow!(
+                    "GitHub rate limit hit; set github.api_token in the config or try later"
+                ));
+            }
+            Err(e) => return Err(anyhow!("GitHub lookup of {}/{} failed: {}", owner, repo, e)),
+        };
+
+        let requested = format!("{}/{}", owner, repo);
+        let moved = !api.full_name.eq_ignore_ascii_case(&requested);
+        Ok(Some(RepoInfo {
+            full_name: api.full_name,
+            stars: api.stargazers_count,
+            archived: api.archived,
+            language: api.language,
+            moved_to: moved.then_some(api.html_url),
+        }))
+    }
+
+    /// Record repository details on every bookmark pointing at a GitHub
+    /// repository, tagging archived and moved ones and adding the primary
+    /// language as a tag. Stops at the first error other than a missing
+    /// repository, keeping what was gathered so far.
+    pub fn enrich(&self, bookmarks: &mut [Bookmark]) -> GithubSummary {
+        let mut summary = GithubSummary::default();
+        let mut seen: HashMap<(String, String), Option<RepoInfo>> = HashMap::new();
+        for bookmark in bookmarks {
+            let Some((owner, repo)) = bookmark.url.as_deref().and_then(repo_from_url) else {
+                continue;
+            };
+            summary.repos += 1;
+            if summary.error.is_some() {
+                continue;
+            }
+
+            let key = (owner.to_lowercase(), repo.to_lowercase());
+            let info = match seen.get(&key) {
+                Some(info) => info.clone(),
+                None => match self.repo(&owner, &repo) {
+                    Ok(info) => seen.entry(key).or_insert(info).clone(),
+                    Err(e) => {
+                        summary.error = Some(e.to_string());
+                        continue;
+                    }
+                },
+            };
+            let Some(info) = info else {
+                summary.missing += 1;
+                continue;
+            };
+
+            let tags = bookmark.tags.get_or_insert_with(Vec::new);
+            let mut tag = |tag: String| {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            };
+            if let Some(language) = &info.language {
+                tag(language.to_lowercase());
+            }
+            if info.archived {
+                tag(ARCHIVED_TAG.to_string());
+                summary.archived += 1;
+            }
+            if info.moved_to.is_some() {
+                tag(MOVED_TAG.to_string());
+                summary.moved += 1;
+            }
+            bookmark.github = Some(info);
+            summary.enriched += 1;
+        }
+        summary
+    }
+}
+
+/// `(owner, repo)` for a github.com URL inside a repository
+pub fn repo_from_url(url: &str) -> Option<(String, String)> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_lowercase();
+    if host != "github.com" && host != "www.github.com" {
+        return None;
+    }
+    let mut segments = parsed.path_segments()?.filter(|s| !s.is_empty());
+    let owner = segments.next()?;
+    let repo = segments.next()?;
+    if RESERVED_OWNERS.contains(&owner.to_lowercase().as_str()) {
+        return None;
+    }
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+    Some((owner.to_string(), repo.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serve one JSON response per request line received, in order
+    fn serve(responses: Vec<&'static str>) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let (status, body) = response.split_once('\n').unwrap();
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+                requests.push(request_line);
+            }
+            requests
+        });
+        (base_url, handle)
+    }
+
+    fn bookmark(url: &str) -> Bookmark {
+        Bookmark {
+            id: url.to_string(),
+            title: String::new(),
+            url: Some(url.to_string()),
+            folder: None,
+            date_added: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_repo_from_url() {
+        assert_eq!(
+            repo_from_url("https://github.com/rust-lang/rust/issues/1"),
+            Some(("rust-lang".to_string(), "rust".to_string()))
+        );
+        assert_eq!(
+            repo_from_url("https://github.com/serde-rs/serde.git"),
+            Some(("serde-rs".to_string(), "serde".to_string()))
+        );
+        assert_eq!(repo_from_url("https://github.com/rust-lang"), None);
+        assert_eq!(repo_from_url("https://github.com/topics/rust"), None);
+        assert_eq!(repo_from_url("https://gitlab.com/a/b"), None);
+    }
+
+    #[test]
+    fn test_enrich_flags_archived_and_moved() {
+        let (base_url, server) = serve(vec![
+            "200 OK\n{\"full_name\":\"new-owner/tool\",\"html_url\":\"https://github.com/new-owner/tool\",\"stargazers_count\":42,\"archived\":true,\"language\":\"Rust\"}",
+            "404 Not Found\n{\"message\":\"Not Found\"}",
+        ]);
+        let mut bookmarks = vec![
+            bookmark("https://github.com/old-owner/tool"),
+            bookmark("https://github.com/old-owner/tool/issues/3"),
+            bookmark("https://github.com/gone/repo"),
+            bookmark("https://example.com/"),
+        ];
+        let summary = GithubClient::new(Some("TOKEN"))
+            .with_base_url(&base_url)
+            .enrich(&mut bookmarks);
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 2, "one request per repository");
+        assert!(requests[0].starts_with("GET /repos/old-owner/tool "));
+        assert_eq!(summary.repos, 3);
+        assert_eq!(summary.enriched, 2);
+        assert_eq!(summary.missing, 1);
+
+        let info = bookmarks[1].github.as_ref().unwrap();
+        assert_eq!(info.stars, 42);
+        assert_eq!(
+            info.moved_to.as_deref(),
+            Some("https://github.com/new-owner/tool")
+        );
+        assert_eq!(
+            bookmarks[0].tags.as_deref().unwrap(),
+            ["rust", ARCHIVED_TAG, MOVED_TAG]
+        );
+        assert!(bookmarks[3].tags.is_none());
+    }
+}
@@ -0,0 +1,9 @@
+//! Sync with third-party bookmarking services, lookups against the Wayback
+//! Machine, GitHub and paper indexes, and page screenshots from headless Chrome
+
+pub mod github;
+pub mod papers;
+pub mod pinboard;
+#[cfg(feature = "screenshots")]
+pub mod screenshots;
+pub mod wayback;
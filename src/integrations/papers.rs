@@ -0,0 +1,390 @@
+//! Paper detection and citation metadata.
+//!
+//! Bookmarks of arXiv abstracts, DOIs (including ACM Digital Library links)
+//! and IEEE Xplore documents are recognised from their URL. Their title,
+//! authors and year come from the arXiv API and Crossref; IEEE documents are
+//! resolved to their DOI first. The result is stored in the bookmark's
+//! `paper` field and feeds the BibTeX export.
+
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+use url::Url;
+
+use crate::exporter::Bookmark;
+use crate::importer::decode_entities;
+
+const ARXIV_API: &str = "https://export.arxiv.org/api/query";
+const CROSSREF_API: &str = "https://api.crossref.org/works";
+const IEEE_DOCUMENT: &str = "https://ieeexplore.ieee.org/document";
+/// arXiv asks API clients to wait 3 seconds between requests
+const DEFAULT_REQUEST_DELAY: Duration = Duration::from_secs(3);
+
+/// How a paper is identified by its URL
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PaperId {
+    /// arXiv identifier without version, e.g. `1706.03762`
+    Arxiv(String),
+    Doi(String),
+    /// IEEE Xplore document number
+    Ieee(String),
+}
+
+impl PaperId {
+    pub fn from_url(url: &str) -> Option<Self> {
+        let parsed = Url::parse(url).ok()?;
+        let host = parsed.host_str()?.to_lowercase();
+        let host = host.strip_prefix("www.").unwrap_or(&host);
+        let path = parsed.path().trim_matches('/');
+
+        match host {
+            "arxiv.org" | "export.arxiv.org" => {
+                let id = path
+                    .strip_prefix("abs/")
+                    .or_else(|| path.strip_prefix("pdf/"))?;
+                let id = id.strip_suffix(".pdf").unwrap_or(id);
+                let id = match id.rsplit_once('v') {
+                    Some((base, version)) if version.chars().all(|c| c.is_ascii_digit()) => base,
+                    _ => id,
+                };
+                (!id.is_empty()).then(|| PaperId::Arxiv(id.to_string()))
+            }
+            "doi.org" | "dx.doi.org" => doi_in(path).map(PaperId::Doi),
+            "ieeexplore.ieee.org" => {
+                let mut segments = path.split('/').skip_while(|s| *s != "document");
+                segments.next()?;
+                let number = segments.next()?;
+                (!number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+                    .then(|| PaperId::Ieee(number.to_string()))
+            }
+            // ACM, Wiley, Taylor & Francis, SAGE, ... all use /doi/<doi>
+            _ if path.starts_with("doi/") || path.contains("/doi/") => {
+                let after = &path[path.find("doi/")? + 4..];
+                let after = ["abs/", "pdf/", "full/", "epdf/", "fullHtml/"]
+                    .iter()
+                    .find_map(|prefix| after.strip_prefix(prefix))
+                    .unwrap_or(after);
+                doi_in(after).map(PaperId::Doi)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PaperId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaperId::Arxiv(id) => write!(f, "arXiv:{}", id),
+            PaperId::Doi(doi) => write!(f, "doi:{}", doi),
+            PaperId::Ieee(number) => write!(f, "IEEE Xplore {}", number),
+        }
+    }
+}
+
+/// A DOI at the start of `path`, e.g. `10.1145/3292500.3330701`
+fn doi_in(path: &str) -> Option<String> {
+    let doi = path.replace("%2F", "/").replace("%2f", "/");
+    (doi.starts_with("10.") && doi.contains('/')).then_some(doi)
+}
+
+/// Citation metadata for a bookmarked paper
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaperMetadata {
+    pub title: String,
+    /// Names as `Given Family`
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+    /// Journal or proceedings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub venue: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doi: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arxiv: Option<String>,
+}
+
+/// Outcome of looking up the papers among a set of bookmarks
+#[derive(Debug, Default)]
+pub struct PaperSummary {
+    /// Bookmarks recognised as papers
+    pub papers: usize,
+    /// Bookmarks given metadata
+    pub found: usize,
+    /// Lookups that failed, with the reason
+    pub failures: Vec<String>,
+}
+
+pub struct PaperClient {
+    arxiv_url: String,
+    crossref_url: String,
+    ieee_url: String,
+    agent: ureq::Agent,
+    request_delay: Duration,
+}
+
+impl Default for PaperClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PaperClient {
+    pub fn new() -> Self {
+        Self {
+            arxiv_url: ARXIV_API.to_string(),
+            crossref_url: CROSSREF_API.to_string(),
+            ieee_url: IEEE_DOCUMENT.to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .user_agent(concat!("bookmark/", env!("CARGO_PKG_VERSION")))
+                .build(),
+            request_delay: DEFAULT_REQUEST_DELAY,
+        }
+    }
+
+    /// Point the client at another host (e.g. a local mock) serving
+    /// `/api/query`, `/works` and `/document`
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        self.arxiv_url = format!("{}/api/query", base_url);
+        self.crossref_url = format!("{}/works", base_url);
+        self.ieee_url = format!("{}/document", base_url);
+        self
+    }
+
+    /// Pause between lookups
+    pub fn with_request_delay(mut self, delay: Duration) -> Self {
+        self.request_delay = delay;
+        self
+    }
+
+    /// Metadata for `id`, or `None` when the service doesn't know it
+    pub fn lookup(&self, id: &PaperId) -> Result<Option<PaperMetadata>> {
+        match id {
+            PaperId::Arxiv(id) => self.arxiv(id),
+            PaperId::Doi(doi) => self.crossref(doi),
+            PaperId::Ieee(number) => match self.ieee_doi(number)? {
+                Some(doi) => self.crossref(&doi),
+                None => Ok(None),
+            },
+        }
+    }
+
+    fn arxiv(&self, id: &str) -> Result<Option<PaperMetadata>> {
+        let feed = self
+            .agent
+            .get(&self.arxiv_url)
+            .query("id_list", id)
+            .call()
+            .map_err(|e| anyhow!("arXiv lookup of {} failed: {}", id, e))?
+            .into_string()?;
+        Ok(parse_arxiv_entry(&feed).map(|mut paper| {
+            paper.arxiv = Some(id.to_string());
+            paper
+        }))
+    }
+
+    fn crossref(&self, doi: &str) -> Result<Option<PaperMetadata>> {
+        let work: serde_json::Value = match self
+            .agent
+            .get(&format!("{}/{}", self.crossref_url, doi))
+            .call()
+        {
+            Ok(response) => response.into_json()?,
+            Err(ureq::Error::Status(404, _)) => return Ok(None),
+            Err(e) => return Err(anyhow!("Crossref lookup of {} failed: {}", doi, e)),
+        };
+        Ok(parse_crossref_work(&work["message"]))
+    }
+
+    /// IEEE Xplore has no open API; document pages embed their DOI
+    fn ieee_doi(&self, number: &str) -> Result<Option<String>> {
+        let page = self
+            .agent
+            .get(&format!("{}/{}", self.ieee_url, number))
+            .call()
+            .map_err(|e| anyhow!("IEEE Xplore lookup of {} failed: {}", number, e))?
+            .into_string()?;
+        let doi = Regex::new(r#""doi"\s*:\s*"(10\.[^"]+)""#)?;
+        Ok(doi.captures(&page).map(|caps| caps[1].to_string()))
+    }
+
+    /// Look up every bookmark (folders included) that points at a paper and
+    /// lacks metadata, once per paper
+    pub fn enrich(&self, bookmarks: &mut [Bookmark]) -> PaperSummary {
+        let mut summary = PaperSummary::default();
+        self.enrich_into(bookmarks, &mut HashMap::new(), &mut summary);
+        summary
+    }
+
+    fn enrich_into(
+        &self,
+        bookmarks: &mut [Bookmark],
+        seen: &mut HashMap<PaperId, Option<PaperMetadata>>,
+        summary: &mut PaperSummary,
+    ) {
+        for bookmark in bookmarks {
+            if let Some(children) = &mut bookmark.children {
+                self.enrich_into(children, seen, summary);
+            }
+            let Some(id) = bookmark.url.as_deref().and_then(PaperId::from_url) else {
+                continue;
+            };
+            summary.papers += 1;
+            if bookmark.paper.is_some() {
+                continue;
+            }
+
+            if !seen.contains_key(&id) {
+                if !seen.is_empty() {
+                    thread::sleep(self.request_delay);
+                }
+                let paper = self.lookup(&id).unwrap_or_else(|e| {
+                    summary.failures.push(e.to_string());
+                    None
+                });
+                seen.insert(id.clone(), paper);
+            }
+            if let Some(paper) = &seen[&id] {
+                bookmark.paper = Some(paper.clone());
+                summary.found += 1;
+            }
+        }
+    }
+}
+
+/// The first entry of an arXiv Atom feed
+fn parse_arxiv_entry(feed: &str) -> Option<PaperMetadata> {
+    let entry = Regex::new(r"(?s)<entry>(.*?)</entry>").ok()?;
+    let field = |name: &str, text: &str| {
+        Regex::new(&format!(r"(?s)<{0}\b[^>]*>(.*?)</{0}>", name))
+            .ok()?
+            .captures(text)
+            .map(|caps| clean(&caps[1]))
+    };
+    let entry = entry.captures(feed)?.get(1)?.as_str();
+    // An unknown id answers with an entry titled "Error"
+    let title = field("title", entry).filter(|t| t != "Error")?;
+    let author = Regex::new(r"(?s)<author>\s*<name>(.*?)</name>").ok()?;
+    Some(PaperMetadata {
+        title,
+        authors: author
+            .captures_iter(entry)
+            .map(|caps| clean(&caps[1]))
+            .collect(),
+        year: field("published", entry).and_then(|d| d.get(..4)?.parse().ok()),
+        venue: field("arxiv:journal_ref", entry),
+        doi: field("arxiv:doi", entry),
+        arxiv: None,
+    })
+}
+
+/// A Crossref `work` message
+fn parse_crossref_work(work: &serde_json::Value) -> Option<PaperMetadata> {
+    let first = |key: &str| work[key][0].as_str().map(clean).filter(|s| !s.is_empty());
+    let authors = work["author"]
+        .as_array()
+        .map(|authors| {
+            authors
+                .iter()
+                .filter_map(|a| {
+                    let family = a["family"].as_str().or_else(|| a["name"].as_str())?;
+                    Some(match a["given"].as_str() {
+                        Some(given) => format!("{} {}", given, family),
+                        None => family.to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let year = ["published", "issued", "created"]
+        .iter()
+        .find_map(|key| work[key]["date-parts"][0][0].as_i64())
+        .map(|y| y as i32);
+    Some(PaperMetadata {
+        title: first("title")?,
+        authors,
+        year,
+        venue: first("container-title"),
+        doi: work["DOI"].as_str().map(str::to_string),
+        arxiv: None,
+    })
+}
+
+/// Decode entities and collapse the line breaks feeds wrap titles with
+fn clean(text: &str) -> String {
+    decode_entities(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paper_id_from_url() {
+        let id = |url| PaperId::from_url(url);
+        assert_eq!(
+            id("https://arxiv.org/abs/1706.03762v7"),
+            Some(PaperId::Arxiv("1706.03762".to_string()))
+        );
+        assert_eq!(
+            id("https://arxiv.org/pdf/1706.03762.pdf"),
+            Some(PaperId::Arxiv("1706.03762".to_string()))
+        );
+        assert_eq!(
+            id("https://doi.org/10.1038/nature14539"),
+            Some(PaperId::Doi("10.1038/nature14539".to_string()))
+        );
+        assert_eq!(
+            id("https://dl.acm.org/doi/abs/10.1145/3292500.3330701"),
+            Some(PaperId::Doi("10.1145/3292500.3330701".to_string()))
+        );
+        assert_eq!(
+            id("https://ieeexplore.ieee.org/abstract/document/7780459"),
+            Some(PaperId::Ieee("7780459".to_string()))
+        );
+        assert_eq!(id("https://arxiv.org/list/cs.LG/recent"), None);
+        assert_eq!(id("https://example.com/doi/"), None);
+    }
+
+    #[test]
+    fn test_parse_arxiv_and_crossref() {
+        let feed = r#"<feed><title>ArXiv Query</title><entry>
+            <title>Attention Is All
+              You Need</title>
+            <published>2017-06-12T17:57:34Z</published>
+            <author><name>Ashish Vaswani</name></author>
+            <author><name>Noam Shazeer</name></author>
+            </entry></feed>"#;
+        let paper = parse_arxiv_entry(feed).unwrap();
+        assert_eq!(paper.title, "Attention Is All You Need");
+        assert_eq!(paper.authors, ["Ashish Vaswani", "Noam Shazeer"]);
+        assert_eq!(paper.year, Some(2017));
+        assert_eq!(
+            parse_arxiv_entry("<feed><entry><title>Error</title></entry></feed>"),
+            None
+        );
+
+        let work = serde_json::json!({
+            "DOI": "10.1038/nature14539",
+            "title": ["Deep learning"],
+            "container-title": ["Nature"],
+            "author": [{"given": "Yann", "family": "LeCun"}, {"name": "Consortium"}],
+            "issued": {"date-parts": [[2015, 5, 27]]}
+        });
+        let paper = parse_crossref_work(&work).unwrap();
+        assert_eq!(paper.authors, ["Yann LeCun", "Consortium"]);
+        assert_eq!(paper.year, Some(2015));
+        assert_eq!(paper.venue.as_deref(), Some("Nature"));
+        assert_eq!(paper.doi.as_deref(), Some("10.1038/nature14539"));
+    }
+}
@@ -0,0 +1,272 @@
+//! Pinboard v1 API client.
+//!
+//! `posts/all` is rate limited to one call every five minutes and other
+//! endpoints to one call every three seconds, so pushes are paced with
+//! [`PinboardClient::with_request_delay`].
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+use std::thread;
+use std::time::Duration;
+
+use crate::exporter::Bookmark;
+
+const API_BASE: &str = "https://api.pinboard.in/v1";
+const DEFAULT_REQUEST_DELAY: Duration = Duration::from_secs(3);
+
+/// A post as returned by `posts/all?format=json`
+#[derive(Debug, Deserialize)]
+pub struct Post {
+    pub href: String,
+    /// Pinboard calls the title "description"
+    #[serde(default)]
+    pub description: String,
+    /// Free-form notes
+    #[serde(default)]
+    pub extended: String,
+    #[serde(default)]
+    pub hash: String,
+    #[serde(default)]
+    pub time: Option<DateTime<Utc>>,
+    /// Space-separated tags
+    #[serde(default)]
+    pub tags: String,
+}
+
+/// Outcome of pushing bookmarks to Pinboard
+#[derive(Debug, Default)]
+pub struct PushSummary {
+    pub pushed: usize,
+    /// Bookmarks without a URL, which Pinboard cannot store
+    pub skipped: usize,
+}
+
+pub struct PinboardClient {
+    token: String,
+    base_url: String,
+    agent: ureq::Agent,
+    request_delay: Duration,
+}
+
+impl PinboardClient {
+    /// `token` is the `username:TOKEN` string from Pinboard's settings page
+    pub fn new(token: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            base_url: API_BASE.to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+            request_delay: DEFAULT_REQUEST_DELAY,
+        }
+    }
+
+    /// Point the client at another API root (e.g. a local mock)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Pause between write requests
+    pub fn with_request_delay(mut self, delay: Duration) -> Self {
+        self.request_delay = delay;
+        self
+    }
+
+    /// Fetch every post in the account
+    pub fn fetch_all(&self) -> Result<Vec<Bookmark>> {
+        let posts: Vec<Post> = self
+            .get("posts/all", &[])?
+            .into_json()
+            .map_err(|e| anyhow!("Unexpected Pinboard response: {}", e))?;
+        Ok(posts.iter().map(post_to_bookmark).collect())
+    }
+
+    /// Add or replace each bookmark on Pinboard
+    pub fn push(&self, bookmarks: &[Bookmark]) -> Result<PushSummary> {
+        let mut summary = PushSummary::default();
+        for bookmark in bookmarks {
+            let Some(params) = bookmark_to_params(bookmark) else {
+                summary.skipped += 1;
+                continue;
+            };
+            if summary.pushed > 0 {
+                thread::sleep(self.request_delay);
+            }
+
+            let params: Vec<(&str, &str)> = params.iter().map(|(k, v)| (*k, v.as_str())).collect();
+            let response: serde_json::Value = self.get("posts/add", &params)?.into_json()?;
+            match response["result_code"].as_str() {
+                Some("done") => summary.pushed += 1,
+                other => {
+                    return Err(anyhow!(
+                        "Pinboard refused {}: {}",
+                        bookmark.url.as_deref().unwrap_or_default(),
+                        other.unwrap_or("no result code")
+                    ));
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    fn get(&self, endpoint: &str, params: &[(&str, &str)]) -> Result<ureq::Response> {
+        let mut request = self
+            .agent
+            .get(&format!("{}/{}", self.base_url, endpoint))
+            .query("auth_token", &self.token)
+            .query("format", "json");
+        for (key, value) in params {
+            request = request.query(key, value);
+        }
+
+        match request.call() {
+            Ok(response) => Ok(response),
+            Err(ureq::Error::Status(401, _)) => Err(anyhow!(
+                "Pinboard rejected the API token; check pinboard.api_token in the config"
+            )),
+            Err(ureq::Error::Status(429, _)) => Err(anyhow!(
+                "Pinboard rate limit hit; wait a few minutes and try again"
+            )),
+            Err(e) => Err(anyhow!("Pinboard request to {} failed: {}", endpoint, e)),
+        }
+    }
+}
+
+/// Map a Pinboard post onto a bookmark
+pub fn post_to_bookmark(post: &Post) -> Bookmark {
+    let tags: Vec<String> = post.tags.split_whitespace().map(str::to_string).collect();
+    Bookmark {
+        id: if post.hash.is_empty() {
+            post.href.clone()
+        } else {
+            post.hash.clone()
+        },
+        title: post.description.clone(),
+        url: Some(post.href.clone()),
+        folder: None,
+        date_added: post.time,
+        tags: (!tags.is_empty()).then_some(tags),
+        description: (!post.extended.is_empty()).then(|| post.extended.clone()),
+        ..Default::default()
+    }
+}
+
+/// Query parameters for `posts/add`, or `None` if the bookmark has no URL
+pub fn bookmark_to_params(bookmark: &Bookmark) -> Option<Vec<(&'static str, String)>> {
+    let url = bookmark.url.clone()?;
+    // Pinboard requires a title and separates tags with spaces
+    let title = if bookmark.title.trim().is_empty() {
+        url.clone()
+    } else {
+        bookmark.title.clone()
+    };
+    let mut params = vec![
+        ("url", url),
+        ("description", title),
+        ("replace", "yes".to_string()),
+    ];
+    if let Some(description) = &bookmark.description {
+        params.push(("extended", description.clone()));
+    }
+    if let Some(tags) = &bookmark.tags {
+        let tags: Vec<String> = tags.iter().map(|t| t.replace(' ', "_")).collect();
+        params.push(("tags", tags.join(" ")));
+    }
+    if let Some(date) = bookmark.date_added {
+        params.push(("dt", date.to_rfc3339_opts(SecondsFormat::Secs, true)));
+    }
+    Some(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Serve a single HTTP response and return the request line that was received
+    fn serve_once(body: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            request_line
+        });
+        (base_url, handle)
+    }
+
+    #[test]
+    fn test_fetch_all_maps_posts() {
+        let (base_url, server) = serve_once(
+            r#"[{"href":"https://www.rust-lang.org/","description":"Rust","extended":"Systems language","meta":"m","hash":"abc123","time":"2021-01-01T00:00:00Z","shared":"yes","toread":"no","tags":"rust lang"}]"#,
+        );
+        let bookmarks = PinboardClient::new("user:TOKEN")
+            .with_base_url(&base_url)
+            .fetch_all()
+            .unwrap();
+
+        let request_line = server.join().unwrap();
+        assert!(request_line.starts_with("GET /posts/all?auth_token=user%3ATOKEN&format=json"));
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].id, "abc123");
+        assert_eq!(bookmarks[0].title, "Rust");
+        assert_eq!(
+            bookmarks[0].tags,
+            Some(vec!["rust".to_string(), "lang".to_string()])
+        );
+        assert_eq!(
+            bookmarks[0].description.as_deref(),
+            Some("Systems language")
+        );
+        assert_eq!(
+            bookmarks[0].date_added,
+            DateTime::from_timestamp(1609459200, 0)
+        );
+    }
+
+    #[test]
+    fn test_bookmark_to_params() {
+        let bookmark = Bookmark {
+            id: "1".to_string(),
+            title: String::new(),
+            url: Some("https://example.com".to_string()),
+            folder: Some("Dev".to_string()),
+            date_added: DateTime::from_timestamp(1609459200, 0),
+            tags: Some(vec!["web dev".to_string(), "rust".to_string()]),
+            ..Default::default()
+        };
+        let params = bookmark_to_params(&bookmark).unwrap();
+        let get = |key: &str| {
+            params
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(get("description"), Some("https://example.com"));
+        assert_eq!(get("tags"), Some("web_dev rust"));
+        assert_eq!(get("dt"), Some("2021-01-01T00:00:00Z"));
+        assert_eq!(get("extended"), None);
+
+        let no_url = Bookmark {
+            url: None,
+            ..bookmark
+        };
+        assert!(bookmark_to_params(&no_url).is_none());
+    }
+}
@@ -0,0 +1,521 @@
+//! Page thumbnails from a headless Chrome, driven over the DevTools protocol.
+//!
+//! Chrome is started once with a throwaway profile and a DevTools port of its
+//! own choosing, which it announces in the profile's `DevToolsActivePort`
+//! file. Each page then gets a fresh tab: navigate, wait for the load event,
+//! capture the viewport scaled down to a thumbnail and close the tab.
+//! Screenshots are PNG files named by a hash of the URL, and the bookmark's
+//! `screenshot` field records where.
+
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::{Message, WebSocket};
+use url::Url;
+
+use crate::exporter::Bookmark;
+
+/// Browser binaries tried on `PATH` when `CHROME` is not set
+const CHROME_NAMES: &[&str] = &[
+    "google-chrome",
+    "google-chrome-stable",
+    "chromium",
+    "chromium-browser",
+    "chrome",
+    "msedge",
+];
+
+/// Install locations that are not on `PATH`
+const CHROME_PATHS: &[&str] = &[
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+    r"C:\Program Files\Google\Chrome\Application\chrome.exe",
+    r"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe",
+];
+
+#[derive(Debug, Clone)]
+pub struct ScreenshotOptions {
+    /// Viewport the page is laid out in, in CSS pixels
+    pub width: u32,
+    pub height: u32,
+    /// Thumbnail size relative to the viewport
+    pub scale: f64,
+    /// How long a page may take to load
+    pub timeout: Duration,
+    /// Only bookmarks in this folder or its subfolders
+    pub folder: Option<String>,
+    /// Capture again even when the screenshot file exists
+    pub overwrite: bool,
+}
+
+impl Default for ScreenshotOptions {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 800,
+            scale: 0.25,
+            timeout: Duration::from_secs(30),
+            folder: None,
+            overwrite: false,
+        }
+    }
+}
+
+/// Outcome of capturing screenshots for a set of bookmarks
+#[derive(Debug, Default)]
+pub struct ScreenshotSummary {
+    pub captured: usize,
+    /// Bookmarks whose screenshot already existed
+    pub existing: usize,
+    /// Pages that could not be captured, with the reason
+    pub failures: Vec<String>,
+}
+
+/// A DevTools connection to Chrome, and the process when we started it
+pub struct Chrome {
+    socket: WebSocket<TcpStream>,
+    next_id: u64,
+    /// Events that arrived while waiting for a command's result
+    events: Vec<Value>,
+    process: Option<Child>,
+    /// Removed when Chrome is dropped
+    _profile: Option<tempfile::TempDir>,
+}
+
+impl Chrome {
+    /// Start a headless Chrome: `binary`, else `CHROME`, else the first
+    /// browser found on `PATH` or in its usual install location
+    pub fn launch(binary: Option<&Path>) -> Result<Self> {
+        let binary = match binary {
+            Some(binary) => binary.to_path_buf(),
+            None => find_chrome().ok_or_else(|| {
+                anyhow!("Chrome not found; install Chrome or Chromium, or set CHROME to its path")
+            })?,
+        };
+        let profile = tempfile::Builder::new()
+            .prefix(crate::cleanup::CHROME_PROFILE_PREFIX)
+            .tempdir()?;
+        let mut process = Command::new(&binary)
+            .args([
+                "--headless=new",
+                "--remote-debugging-port=0",
+                "--no-first-run",
+                "--no-default-browser-check",
+                "--hide-scrollbars",
+                "--mute-audio",
+            ])
+            .arg(format!("--user-data-dir={}", profile.path().display()))
+            .arg("about:blank")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow!("Could not start {}: {}", binary.display(), e))?;
+
+        // Chrome writes the port and browser target path once it listens
+        let port_file = profile.path().join("DevToolsActivePort");
+        let deadline = Instant::now() + Duration::from_secs(20);
+        let endpoint = loop {
+            if let Ok(content) = fs::read_to_string(&port_file)
+                && let Some((port, path)) = content.split_once('\n')
+                && !path.trim().is_empty()
+            {
+                break format!("ws://127.0.0.1:{}{}", port.trim(), path.trim());
+            }
+            if let Some(status) = process.try_wait()? {
+                return Err(anyhow!("{} exited early ({})", binary.display(), status));
+            }
+            if Instant::now() > deadline {
+                let _ = process.kill();
+                return Err(anyhow!("{} did not open a DevTools port", binary.display()));
+            }
+            thread::sleep(Duration::from_millis(100));
+        };
+
+        let mut chrome = Self::connect(&endpoint)?;
+        chrome.process = Some(process);
+        chrome._profile = Some(profile);
+        Ok(chrome)
+    }
+
+    /// Attach to a running browser's DevTools endpoint, e.g.
+    /// `ws://127.0.0.1:9222/devtools/browser/<id>`
+    pub fn connect(endpoint: &str) -> Result<Self> {
+        let url = Url::parse(endpoint)?;
+        let address = url
+            .socket_addrs(|| Some(9222))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Cannot resolve {}", endpoint))?;
+        let stream = TcpStream::connect(address)?;
+        let (socket, _) = tungstenite::client(endpoint, stream)
+            .map_err(|e| anyhow!("DevTools handshake with {} failed: {}", endpoint, e))?;
+        Ok(Self {
+            socket,
+            next_id: 0,
+            events: Vec::new(),
+            process: None,
+            _profile: None,
+        })
+    }
+
+    /// PNG thumbnail of the page at `url`
+    pub fn screenshot(&mut self, url: &str, options: &ScreenshotOptions) -> Result<Vec<u8>> {
+        self.set_timeout(options.timeout)?;
+        self.events.clear();
+        let target = self.call("Target.createTarget", json!({"url": "about:blank"}), None)?;
+        let target_id = target["targetId"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Chrome did not open a tab"))?
+            .to_string();
+        let result = self.capture_in(&target_id, url, options);
+        let _ = self.call("Target.closeTarget", json!({"targetId": target_id}), None);
+        result
+    }
+
+    fn capture_in(
+        &mut self,
+        target_id: &str,
+        url: &str,
+        options: &ScreenshotOptions,
+    ) -> Result<Vec<u8>> {
+        let attached = self.call(
+            "Target.attachToTarget",
+            json!({"targetId": target_id, "flatten": true}),
+            None,
+        )?;
+        let session = attached["sessionId"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Chrome did not attach to the tab"))?
+            .to_string();
+        let session = Some(session.as_str());
+
+        self.call("Page.enable", json!({}), session)?;
+        self.call(
+            "Emulation.setDeviceMetricsOverride",
+            json!({
+                "width": options.width,
+                "height": options.height,
+                "deviceScaleFactor": 1,
+                "mobile": false,
+            }),
+            session,
+        )?;
+        let navigation = self.call("Page.navigate", json!({"url": url}), session)?;
+        if let Some(error) = navigation["errorText"].as_str() {
+            return Err(anyhow!("{}", error));
+        }
+        self.wait_for("Page.loadEventFired", session)?;
+
+        let shot = self.call(
+            "Page.captureScreenshot",
+            json!({
+                "format": "png",
+                "clip": {
+                    "x": 0,
+                    "y": 0,
+                    "width": options.width,
+                    "height": options.height,
+                    "scale": options.scale,
+                },
+            }),
+            session,
+        )?;
+        let data = shot["data"]
+            .as_str()
+            .ok_or_else(|| anyhow!("no screenshot data"))?;
+        Ok(base64::engine::general_purpose::STANDARD.decode(data)?)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<()> {
+        self.socket.get_mut().set_read_timeout(Some(timeout))?;
+        Ok(())
+    }
+
+    /// Send a command and wait for its result, skipping events meanwhile
+    fn call(&mut self, method: &str, params: Value, session: Option<&str>) -> Result<Value> {
+        self.next_id += 1;
+        let id = self.next_id;
+        let mut command = json!({"id": id, "method": method, "params": params});
+        if let Some(session) = session {
+            command["sessionId"] = json!(session);
+        }
+        self.socket.send(Message::text(command.to_string()))?;
+
+        loop {
+            let message = self.read()?;
+            if message["id"].as_u64() != Some(id) {
+                if message.get("method").is_some() {
+                    self.events.push(message);
+                }
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                return Err(anyhow!(
+                    "{} failed: {}",
+                    method,
+                    error["message"].as_str().unwrap_or("unknown error")
+                ));
+            }
+            return Ok(message["result"].clone());
+        }
+    }
+
+    /// Block until `event` arrives for `session`
+    fn wait_for(&mut self, event: &str, session: Option<&str>) -> Result<()> {
+        let matches = |message: &Value| {
+            message["method"].as_str() == Some(event) && message["sessionId"].as_str() == session
+        };
+        if self.events.iter().any(matches) {
+            return Ok(());
+        }
+        while !matches(&self.read()?) {}
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Value> {
+        loop {
+            match self.socket.read() {
+                Ok(Message::Text(text)) => return Ok(serde_json::from_str(text.as_str())?),
+                Ok(Message::Close(_)) => return Err(anyhow!("Chrome closed the connection")),
+                Ok(_) => continue,
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Err(anyhow!("timed out waiting for Chrome"));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Capture every HTTP bookmark among `bookmarks` (folders included) that
+    /// has no screenshot yet, saving PNGs under `dir`
+    pub fn capture(
+        &mut self,
+        bookmarks: &mut [Bookmark],
+        dir: &Path,
+        options: &ScreenshotOptions,
+    ) -> ScreenshotSummary {
+        let mut summary = ScreenshotSummary::default();
+        self.capture_into(bookmarks, dir, options, &mut summary);
+        summary
+    }
+
+    fn capture_into(
+        &mut self,
+        bookmarks: &mut [Bookmark],
+        dir: &Path,
+        options: &ScreenshotOptions,
+        summary: &mut ScreenshotSummary,
+    ) {
+        for bookmark in bookmarks {
+            if let Some(children) = &mut bookmark.children {
+                self.capture_into(children, dir, options, summary);
+            }
+            let Some(url) = bookmark.url.clone() else {
+                continue;
+            };
+            if !(url.starts_with("http://") || url.starts_with("https://"))
+                || !in_folder(bookmark.folder.as_deref(), options.folder.as_deref())
+            {
+                continue;
+            }
+
+            let path = screenshot_path(&url, dir);
+            if path.exists() && !options.overwrite {
+                summary.existing += 1;
+                bookmark.screenshot = Some(path.display().to_string());
+                continue;
+            }
+            let saved = self.screenshot(&url, options).and_then(|png| {
+                fs::create_dir_all(dir)?;
+                fs::write(&path, png)?;
+                Ok(())
+            });
+            match saved {
+                Ok(()) => {
+                    summary.captured += 1;
+                    bookmark.screenshot = Some(path.display().to_string());
+                }
+                Err(e) => summary.failures.push(format!("{}: {}", url, e)),
+            }
+        }
+    }
+}
+
+impl Drop for Chrome {
+    fn drop(&mut self) {
+        let _ = self.socket.close(None);
+        if let Some(process) = &mut self.process {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+/// Where the screenshot of `url` is kept under `dir`
+pub fn screenshot_path(url: &str, dir: &Path) -> PathBuf {
+    let digest: String = Sha256::digest(url.as_bytes())
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    dir.join(format!("{}.png", digest))
+}
+
+/// `CHROME` if set, else the first known browser binary that exists
+pub fn find_chrome() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("CHROME").filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let on_path = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+    CHROME_NAMES
+        .iter()
+        .flat_map(|name| {
+            on_path
+                .iter()
+                .flat_map(move |dir| [dir.join(name), dir.join(format!("{}.exe", name))])
+        })
+        .chain(CHROME_PATHS.iter().map(PathBuf::from))
+        .find(|path| path.is_file())
+}
+
+fn in_folder(folder: Option<&str>, wanted: Option<&str>) -> bool {
+    let Some(wanted) = wanted else {
+        return true;
+    };
+    folder.is_some_and(|folder| {
+        folder == wanted
+            || folder
+                .strip_prefix(wanted)
+                .is_some_and(|rest| rest.starts_with('/'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// Answers the DevTools commands a screenshot takes, like Chrome would
+    fn fake_chrome(listener: TcpListener, png: &'static [u8]) -> thread::JoinHandle<Vec<String>> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut socket = tungstenite::accept(stream).unwrap();
+            let mut methods = Vec::new();
+            loop {
+                let text = match socket.read() {
+                    Ok(Message::Text(text)) => text,
+                    Ok(_) => continue,
+                    Err(_) => break,
+                };
+                let command: Value = serde_json::from_str(text.as_str()).unwrap();
+                let method = command["method"].as_str().unwrap().to_string();
+                let result = match method.as_str() {
+                    "Target.createTarget" => json!({"targetId": "T1"}),
+                    "Target.attachToTarget" => json!({"sessionId": "S1"}),
+                    "Page.navigate" => {
+                        // An event for another tab must not end the wait
+                        for session in ["S2", "S1"] {
+                            let event = json!({"method": "Page.loadEventFired", "sessionId": session, "params": {}});
+                            socket.send(Message::text(event.to_string())).unwrap();
+                        }
+                        json!({"frameId": "F1"})
+                    }
+                    "Page.captureScreenshot" => {
+                        assert_eq!(command["params"]["clip"]["scale"], 0.25);
+                        json!({"data": base64::engine::general_purpose::STANDARD.encode(png)})
+                    }
+                    _ => json!({}),
+                };
+                let mut reply = json!({"id": command["id"], "result": result});
+                if let Some(session) = command.get("sessionId") {
+                    reply["sessionId"] = session.clone();
+                }
+                socket.send(Message::text(reply.to_string())).unwrap();
+                methods.push(method);
+            }
+            methods
+        })
+    }
+
+    fn bookmark(id: &str, url: &str, folder: &str) -> Bookmark {
+        Bookmark {
+            id: id.to_string(),
+            title: id.to_string(),
+            url: Some(url.to_string()),
+            folder: Some(folder.to_string()),
+            date_added: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_capture_over_devtools() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!(
+            "ws://{}/devtools/browser/abc",
+            listener.local_addr().unwrap()
+        );
+        let server = fake_chrome(listener, b"\x89PNG fake");
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut bookmarks = vec![
+            bookmark("1", "https://example.com/", "Reading/Web"),
+            bookmark("2", "https://example.org/", "Work"),
+            bookmark("3", "file:///etc/hosts", "Reading"),
+        ];
+        let options = ScreenshotOptions {
+            folder: Some("Reading".to_string()),
+            ..Default::default()
+        };
+        let mut chrome = Chrome::connect(&endpoint).unwrap();
+        let summary = chrome.capture(&mut bookmarks, dir.path(), &options);
+        drop(chrome);
+        let methods = server.join().unwrap();
+
+        assert_eq!(summary.captured, 1, "{:?}", summary.failures);
+        assert!(summary.failures.is_empty());
+        let path = screenshot_path("https://example.com/", dir.path());
+        assert_eq!(fs::read(&path).unwrap(), b"\x89PNG fake");
+        assert_eq!(bookmarks[0].screenshot, Some(path.display().to_string()));
+        assert_eq!(bookmarks[1].screenshot, None);
+        assert_eq!(bookmarks[2].screenshot, None);
+        assert_eq!(
+            methods,
+            [
+                "Target.createTarget",
+                "Target.attachToTarget",
+                "Page.enable",
+                "Emulation.setDeviceMetricsOverride",
+                "Page.navigate",
+                "Page.captureScreenshot",
+                "Target.closeTarget",
+            ]
+        );
+
+        // Existing files are reused without asking Chrome
+        bookmarks[0].screenshot = None;
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("ws://{}/", listener.local_addr().unwrap());
+        let server = fake_chrome(listener, b"");
+        let mut chrome = Chrome::connect(&endpoint).unwrap();
+        let summary = chrome.capture(&mut bookmarks, dir.path(), &options);
+        drop(chrome);
+        assert!(server.join().unwrap().is_empty());
+        assert_eq!((summary.captured, summary.existing), (0, 1));
+        assert!(bookmarks[0].screenshot.is_some());
+    }
+}
@@ -0,0 +1,318 @@
+//! Internet Archive Wayback Machine client.
+//!
+//! Looks up the snapshot closest to when a page was bookmarked through the
+//! availability API, and can ask Save Page Now to capture pages that have
+//! none. Save Page Now is rate limited, so captures are paced with
+//! [`WaybackClient::with_request_delay`].
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::thread;
+use std::time::Duration;
+
+use crate::exporter::Bookmark;
+use crate::progress::ProgressEvent;
+
+const AVAILABILITY_BASE: &str = "https://archive.org/wayback/available";
+const SAVE_BASE: &str = "https://web.archive.org/save";
+const DEFAULT_REQUEST_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+struct Availability {
+    #[serde(default)]
+    archived_snapshots: ArchivedSnapshots,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ArchivedSnapshots {
+    closest: Option<Snapshot>,
+}
+
+/// An archived copy of a page
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct Snapshot {
+    pub url: String,
+    /// Capture time as `YYYYMMDDhhmmss`
+    pub timestamp: String,
+    #[serde(default)]
+    pub available: bool,
+}
+
+pub struct WaybackClient {
+    availability_url: String,
+    save_url: String,
+    agent: ureq::Agent,
+    request_delay: Duration,
+}
+
+/// Which bookmarks [`WaybackClient::archive`] handles
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArchiveOptions {
+    /// Only bookmarks whose page no longer loads
+    pub dead_only: bool,
+    /// Submit pages without a snapshot to Save Page Now
+    pub save_missing: bool,
+}
+
+/// Outcome of archiving a set of bookmarks
+#[derive(Debug, Default)]
+pub struct ArchiveSummary {
+    /// Bookmarks looked up
+    pub checked: usize,
+    /// Existing snapshots found
+    pub found: usize,
+    /// Pages captured by Save Page Now
+    pub saved: usize,
+    /// Pages without a snapshot, left as they were
+    pub missing: usize,
+    /// Lookups or captures that failed, with the reason
+    pub failures: Vec<String>,
+}
+
+impl Default for WaybackClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WaybackClient {
+    pub fn new() -> Self {
+        Self {
+            availability_url: AVAILABILITY_BASE.to_string(),
+            save_url: SAVE_BASE.to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(60))
+                .build(),
+            request_delay: DEFAULT_REQUEST_DELAY,
+        }
+    }
+
+    /// Point the client at another host (e.g. a local mock) serving
+    /// `/wayback/available` and `/save/`
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        self.availability_url = format!("{}/wayback/available", base_url);
+        self.save_url = format!("{}/save", base_url);
+        self
+    }
+
+    /// Pause between Save Page Now requests
+    pub fn with_request_delay(mut self, delay: Duration) -> Self {
+        self.request_delay = delay;
+        self
+    }
+
+    /// The snapshot of `url` closest to `near` (the latest when `None`)
+    pub fn nearest_snapshot(
+        &self,
+        url: &str,
+        near: Option<DateTime<Utc>>,
+    ) -> Result<Option<Snapshot>> {
+        let mut request = self.agent.get(&self.availability_url).query("url", url);
+        let timestamp = near.map(|d| d.format("%Y%m%d%H%M%S").to_string());
+        if let Some(timestamp) = &timestamp {
+            request = request.query("timestamp", timestamp);
+        }
+        let availability: Availability = request
+            .call()
+            .map_err(|e| anyhow!("Wayback lookup for {} failed: {}", url, e))?
+            .into_json()
+            .map_err(|e| anyhow!("Unexpected Wayback response: {}", e))?;
+        Ok(availability
+            .archived_snapshots
+            .closest
+            .filter(|snapshot| snapshot.available))
+    }
+
+    /// Set `archive_url` on bookmarks that lack one, from the snapshot
+    /// nearest to when each was added
+    pub fn archive(&self, bookmarks: &mut [Bookmark], options: ArchiveOptions) -> ArchiveSummary {
+        self.archive_with_progress(bookmarks, options, |_| {})
+    }
+
+    /// [`Self::archive`], reporting each bookmark as it is checked
+    pub fn archive_with_progress(
+        &self,
+        bookmarks: &mut [Bookmark],
+        options: ArchiveOptions,
+        on_progress: impl Fn(ProgressEvent),
+    ) -> ArchiveSummary {
+        let mut summary = ArchiveSummary::default();
+        let mut submitted = 0;
+        let candidates = |b: &&mut Bookmark| {
+            b.archive_url.is_none() && b.url.as_deref().is_some_and(|u| u.starts_with("http"))
+        };
+        let total = bookmarks.iter_mut().filter(candidates).count();
+        for (done, bookmark) in bookmarks.iter_mut().filter(candidates).enumerate() {
+            on_progress(ProgressEvent::ItemsDone {
+                stage: "wayback".to_string(),
+                done,
+                total,
+            });
+            let url = bookmark.url.clone().unwrap_or_default();
+            if options.dead_only && !is_dead(&url) {
+                continue;
+            }
+            summary.checked += 1;
+
+            match self.nearest_snapshot(&url, bookmark.date_added) {
+                Ok(Some(snapshot)) => {
+                    bookmark.archive_url = Some(snapshot.url);
+                    summary.found += 1;
+                }
+                Ok(None) if options.save_missing => {
+                    if submitted > 0 {
+                        thread::sleep(self.request_delay);
+                    }
+                    submitted += 1;
+                    match self.save(&url) {
+                        Ok(snapshot_url) => {
+                            bookmark.archive_url = Some(snapshot_url);
+                            summary.saved += 1;
+                        }
+                        Err(e) => summary.failures.push(e.to_string()),
+                    }
+                }
+                Ok(None) => summary.missing += 1,
+                Err(e) => summary.failures.push(e.to_string()),
+            }
+        }
+        on_progress(ProgressEvent::ItemsDone {
+            stage: "wayback".to_string(),
+            done: total,
+            total,
+        });
+        summary
+    }
+
+    /// Ask Save Page Now to capture `url`, returning the snapshot URL
+    pub fn save(&self, url: &str) -> Result<String> {
+        let response = match self.agent.get(&format!("{}/{}", self.save_url, url)).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(429, _)) => {
+                return Err(anyhow!(
+                    "Save Page Now rate limit hit; wait a few minutes and try again"
+                ));
+            }
+            Err(e) => return Err(anyhow!("Save Page Now failed for {}: {}", url, e)),
+        };
+        // The capture is announced in Content-Location, or reached by redirect
+        let location = response
+            .header("Content-Location")
+            .map(str::to_string)
+            .unwrap_or_else(|| response.get_url().to_string());
+        let path = location
+            .find("/web/")
+            .map(|i| &location[i..])
+            .ok_or_else(|| anyhow!("Save Page Now returned no snapshot for {}", url))?;
+        Ok(format!("https://web.archive.org{}", path))
+    }
+}
+
+/// Whether `url` looks dead: unreachable, or answering with an error status
+pub fn is_dead(url: &str) -> bool {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(15))
+        .build();
+    match agent.head(url).call() {
+        Ok(_) => false,
+        // Some servers refuse HEAD; ask again with GET before calling it dead
+        Err(ureq::Error::Status(405 | 501, _)) => agent.get(url).call().is_err(),
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Serve a single HTTP response and return the request line that was received
+    fn serve_once(
+        headers: &'static str,
+        body: &'static str,
+    ) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\n{}Content-Length: {}\r\n\r\n{}",
+                headers,
+                body.len(),
+                body
+            )
+            .unwrap();
+            request_line
+        });
+        (base_url, handle)
+    }
+
+    #[test]
+    fn test_nearest_snapshot() {
+        let (base_url, server) = serve_once(
+            "Content-Type: application/json\r\n",
+            r#"{"url":"example.com","archived_snapshots":{"closest":{"status":"200","available":true,"url":"http://web.archive.org/web/20210101000000/https://example.com/","timestamp":"20210101000000"}}}"#,
+        );
+        let snapshot = WaybackClient::new()
+            .with_base_url(&base_url)
+            .nearest_snapshot(
+                "https://example.com/",
+                DateTime::from_timestamp(1609459200, 0),
+            )
+            .unwrap()
+            .unwrap();
+
+        let request_line = server.join().unwrap();
+        assert!(request_line.starts_with(
+            "GET /wayback/available?url=https%3A%2F%2Fexample.com%2F&timestamp=20210101000000"
+        ));
+        assert_eq!(snapshot.timestamp, "20210101000000");
+        assert!(snapshot.url.contains("/web/20210101000000/"));
+    }
+
+    #[test]
+    fn test_missing_snapshot_and_save() {
+        let (base_url, server) = serve_once(
+            "Content-Type: application/json\r\n",
+            r#"{"url":"example.com/gone","archived_snapshots":{}}"#,
+        );
+        let client = WaybackClient::new().with_base_url(&base_url);
+        assert_eq!(
+            client
+                .nearest_snapshot("https://example.com/gone", None)
+                .unwrap(),
+            None
+        );
+        server.join().unwrap();
+
+        let (base_url, server) = serve_once(
+            "Content-Location: /web/20240501120000/https://example.com/gone\r\n",
+            "",
+        );
+        let saved = WaybackClient::new()
+            .with_base_url(&base_url)
+            .save("https://example.com/gone")
+            .unwrap();
+        assert!(
+            server
+                .join()
+                .unwrap()
+                .starts_with("GET /save/https://example.com/gone")
+        );
+        assert_eq!(
+            saved,
+            "https://web.archive.org/web/20240501120000/https://example.com/gone"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! A record of what `process` wrote, so a run can be undone.
+//!
+//! Each run that writes its output file adds a [`JournalEntry`] to the
+//! store's `journal` table: the input bookmarks, the settings used, the
+//! output file as it was before (or that there was none) and what was
+//! written over it. [`undo`] puts the earlier file back, or removes the file
+//! when the run created it, and refuses when the file has changed since,
+//! so a later edit isn't lost by accident. Only the most recent
+//! [`JOURNAL_KEEP`] runs are kept.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::exporter::Bookmark;
+
+/// Runs kept in the journal; older ones are dropped as new ones are added
+pub const JOURNAL_KEEP: usize = 20;
+
+/// One recorded run
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    /// Journal id, used to undo it
+    pub id: i64,
+    /// The command that wrote the file, e.g. `process`
+    pub operation: String,
+    pub at: DateTime<Utc>,
+    pub output: PathBuf,
+    /// The file before the run; `None` when the run created it
+    pub previous: Option<String>,
+    /// What the run wrote
+    pub written: String,
+    /// The bookmarks the run read
+    pub input: Vec<Bookmark>,
+    /// Stages, strategy and other options it ran with
+    pub settings: BTreeMap<String, String>,
+    pub undone_at: Option<DateTime<Utc>>,
+}
+
+/// What [`undo`] did to the output file
+#[derive(Debug, PartialEq, Eq)]
+pub enum Undone {
+    /// The earlier content was written back
+    Restored,
+    /// The run had created the file, so it was removed
+    Removed,
+}
+
+/// Put `entry`'s output file back as it was before the run. Fails if the
+/// run was already undone, or, unless `force`, if the file no longer holds
+/// what the run wrote.
+pub fn undo(entry: &JournalEntry, force: bool) -> Result<Undone> {
+    if let Some(at) = entry.undone_at {
+        return Err(anyhow!(
+            "{} run {} was already undone on {}",
+            entry.operation,
+            entry.id,
+            at.format("%Y-%m-%d %H:%M")
+        ));
+    }
+    let current = fs::read_to_string(&entry.output).ok();
+    if !force && current.as_deref() != Some(entry.written.as_str()) {
+        return Err(anyhow!(
+            "{} has changed since {} wrote it; pass --force to undo anyway",
+            entry.output.display(),
+            entry.operation
+        ));
+    }
+    match &entry.previous {
+        Some(previous) => {
+            fs::write(&entry.output, previous)?;
+            Ok(Undone::Restored)
+        }
+        None => {
+            if current.is_some() {
+                fs::remove_file(&entry.output)?;
+            }
+            Ok(Undone::Removed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_undo_restores_or_removes() {
+        let dir = tempdir().unwrap();
+        let output = dir.path().join("processed.yaml");
+        fs::write(&output, "after").unwrap();
+        let mut entry = JournalEntry {
+            id: 1,
+            operation: "process".to_string(),
+            at: Utc::now(),
+            output: output.clone(),
+            previous: Some("before".to_string()),
+            written: "after".to_string(),
+            input: Vec::new(),
+            settings: BTreeMap::new(),
+            undone_at: None,
+        };
+
+        assert_eq!(undo(&entry, false).unwrap(), Undone::Restored);
+        assert_eq!(fs::read_to_string(&output).unwrap(), "before");
+        // The file no longer holds what was written
+        assert!(undo(&entry, false).is_err());
+
+        entry.previous = None;
+        assert_eq!(undo(&entry, true).unwrap(), Undone::Removed);
+        assert!(!output.exists());
+        entry.undone_at = Some(Utc::now());
+        assert!(undo(&entry, true).is_err());
+    }
+}
@@ -5,6 +5,7 @@
 //! - Multi-browser support: Chrome, Firefox, Safari, Edge
 //! - Search and open bookmarks
 //! - Export bookmarks and history
+//! - Import Netscape `bookmarks.html` files
 //! - Remove duplicates and organize into folders
 //! - Generate knowledge graphs (DOT, JSON, GEXF)
 //!
@@ -55,25 +56,57 @@
 //! bookmark-mcp
 //! ```
 
+pub mod backup;
 pub mod browser;
+pub mod canonical;
+pub mod cleanup;
 pub mod config;
+pub mod convert;
 pub mod deduplication;
+pub mod diff;
+pub mod digest;
+pub mod edit;
+pub mod enrich;
+pub mod expiration;
 pub mod exporter;
 pub mod graph;
 pub mod graph_output;
+pub mod importer;
+pub mod journal;
+pub mod integrations;
+pub mod media;
+pub mod merge;
 pub mod organization;
+pub mod paths;
+pub mod pipeline;
 pub mod processor;
+pub mod progress;
+pub mod quota;
+pub mod reconcile;
 pub mod search;
+pub mod snapshot;
+pub mod store;
+pub mod suggest;
+pub mod synthetic;
+pub mod tree;
 pub mod utils;
 
+#[cfg(feature = "host")]
+pub mod host;
 #[cfg(feature = "mcp")]
 pub mod mcp;
 
+#[cfg(feature = "serve")]
+pub mod serve;
+#[cfg(feature = "tui")]
+pub mod tui;
+
 use std::path::PathBuf;
 
 /// Re-export commonly used types
-pub use crate::exporter::{Bookmark, UrlEntry};
+pub use crate::exporter::{Bookmark, ExportFormat, ExportOptions, UrlEntry, bookmarks_to_tree, flatten_bookmarks};
 pub use crate::graph::{GraphConfig, GraphBuilder, KnowledgeGraph};
+pub use crate::search::{MatchedField, SearchResult};
 
 /// Main bookmark manager API
 pub struct BookmarkManager {
@@ -100,14 +133,37 @@ impl BookmarkManager {
         Ok(bookmarks)
     }
 
-    /// Search bookmarks by query
-    pub fn search(&self, query: &str) -> Result<Vec<Bookmark>, Box<dyn std::error::Error>> {
+    /// Export bookmarks from a browser (or "all") serialized as YAML, JSON,
+    /// JSON Lines or CSV, with the browser and profile they came from
+    pub fn export_bookmarks_as(&self, browser: &str, format: ExportFormat) -> Result<String, Box<dyn std::error::Error>> {
+        let browsers: Vec<&str> = if browser == "all" {
+            vec!["chrome", "firefox", "safari", "edge"]
+        } else {
+            vec![browser]
+        };
+
+        let mut data = Vec::new();
+        for name in browsers {
+            match crate::exporter::collect_browser_data(name, "bookmarks", None, &ExportOptions::default()) {
+                Ok(d) => data.extend(d),
+                Err(e) if browser == "all" => log::debug!("Skipping {}: {}", name, e),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(crate::exporter::render_export(&data, format)?)
+    }
+
+    /// Search bookmarks by query, best match first, each with its score and
+    /// where it matched
+    pub fn search(&self, query: &str) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
         use crate::search::{search_bookmarks_internal, SearchOptions};
 
         let options = SearchOptions {
             title_only: false,
             url_only: false,
             limit: 100,
+            media_type: None,
+            sort: Default::default(),
         };
 
         Ok(search_bookmarks_internal(query, &options)?)
@@ -1,28 +1,52 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
+use std::time::Duration;
 
-mod browser;
 mod cli;
-mod config;
-mod deduplication;
-mod exporter;
-mod graph;
-mod graph_output;
-mod organization;
-mod processor;
-mod search;
-mod utils;
-
-use exporter::export_data;
-use search::{open_bookmark, search_bookmarks};
+
+use bookmark::enrich::EnrichOptions;
+use bookmark::exporter::{Anonymizer, ApplyOptions, ExportOptions};
+#[cfg(feature = "screenshots")]
+use bookmark::integrations::screenshots::ScreenshotOptions;
+use bookmark::paths::{self, PathOverrides};
+use bookmark::search::{
+    OpenOptions, SearchOptions, open_bookmark, refresh_store, search_bookmarks,
+};
+use bookmark::synthetic::SyntheticConfig;
+use bookmark::{
+    backup, browser, cleanup, config, convert, deduplication, diff, digest, edit, enrich,
+    expiration, exporter, graph, graph_output, importer, integrations, journal, merge,
+    organization, processor, reconcile, search, snapshot, store, synthetic, tree, utils,
+};
 
 #[derive(Parser)]
 #[command(name = "bookmark")]
-#[command(about = "Import, search, and manage bookmarks from all browsers", version = "0.1.1")]
+#[command(
+    about = "Import, search, and manage bookmarks from all browsers",
+    version = "0.1.1"
+)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Config file [env: BOOKMARK_CONFIG]
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Bookmark store database [env: BOOKMARK_STORE]
+    #[arg(long, global = true)]
+    store: Option<PathBuf>,
+    /// Cache directory [env: BOOKMARK_CACHE_DIR]
+    #[arg(long, global = true)]
+    cache_dir: Option<PathBuf>,
+    /// Backup directory [env: BOOKMARK_BACKUP_DIR]
+    #[arg(long, global = true)]
+    backup_dir: Option<PathBuf>,
+    /// Page archive directory [env: BOOKMARK_ARCHIVE_DIR]
+    #[arg(long, global = true)]
+    archive_dir: Option<PathBuf>,
+    /// Browser database snapshot directory [env: BOOKMARK_SNAPSHOT_DIR]
+    #[arg(long, global = true)]
+    snapshot_dir: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -32,15 +56,97 @@ enum Commands {
         /// Browser (chrome, firefox, safari, edge, all)
         #[arg(short, long, default_value = "all")]
         browser: String,
-        /// Data type (bookmarks, history, both)
+        /// Data type (bookmarks, history, both, passwords, all)
         #[arg(short, long, default_value = "bookmarks")]
         data_type: String,
-        /// Output directory
+        /// Output file, or directory with --browser all; `-` or omitted prints to stdout
         #[arg(short, long)]
         output: Option<PathBuf>,
+        /// Copy the export to the clipboard instead (small exports only)
+        #[arg(long, conflicts_with = "output")]
+        clipboard: bool,
+        /// Output format (yaml, json, jsonl, csv, org, opml, sqlite, bibtex); csv, org and opml
+        /// carry bookmarks only, bibtex only those pointing at papers
+        #[arg(short, long, default_value = "yaml")]
+        format: String,
+        /// Bookmark fields to keep, e.g. title,url,folder (default: all)
+        #[arg(long)]
+        fields: Option<String>,
+        /// Hash titles, URLs and folders so the export can be shared in bug reports
+        #[arg(long)]
+        anonymize: bool,
+        /// Salt for --anonymize; reuse it to keep tokens consistent across exports
+        #[arg(long, requires = "anonymize")]
+        anonymize_salt: Option<String>,
         /// Custom browser data directory
         #[arg(long)]
         profile_dir: Option<PathBuf>,
+        /// Add an aliases section mapping keyword shortcuts to URLs
+        #[arg(long)]
+        aliases: bool,
+        /// Include Chrome/Edge reading-list entries (foldered as "Reading List")
+        #[arg(long)]
+        include_reading_list: bool,
+        /// Allow decrypting saved passwords into the export in plaintext
+        #[arg(long = "i-understand-the-risk")]
+        i_understand_the_risk: bool,
+        /// With --browser all, seconds to wait for each browser before moving on (0: no limit)
+        #[arg(long, default_value = "120")]
+        timeout: u64,
+    },
+
+    /// Import bookmarks from a file exported by a browser or service
+    Import {
+        /// File to import
+        file: PathBuf,
+        /// Input format (html, pocket, instapaper, onetab)
+        #[arg(short, long, default_value = "html")]
+        format: String,
+        /// Output file (prints to stdout if omitted, unless --to-store)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Add the bookmarks to the store, skipping URLs it already has
+        /// (compared after dedupe's URL normalization). Named apart from the
+        /// global `--store <PATH>`, which picks the store database.
+        #[arg(long)]
+        to_store: bool,
+        /// With --to-store, overwrite known bookmarks' title and folder and add tags
+        #[arg(long, requires = "to_store")]
+        update: bool,
+    },
+
+    /// Generate a synthetic collection for benchmarking and trying settings
+    Generate {
+        /// Number of bookmarks
+        #[arg(long, default_value = "10000")]
+        count: usize,
+        /// Number of distinct domains (popularity follows a power law)
+        #[arg(long, default_value = "500")]
+        domains: usize,
+        /// Fraction of bookmarks that duplicate an earlier URL
+        #[arg(long, default_value = "0.05")]
+        duplicates: f64,
+        /// Also generate browsing history
+        #[arg(long)]
+        history: bool,
+        /// Random seed; the same seed always produces the same file
+        #[arg(long, default_value = "42")]
+        seed: u64,
+        /// Output file (prints to stdout if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Check an export file's checksum and structure
+    Verify {
+        /// Export file (YAML, JSON or JSON Lines)
+        file: PathBuf,
+    },
+
+    /// Pull from or push to Pinboard (token from pinboard.api_token in the config)
+    Pinboard {
+        #[command(subcommand)]
+        action: PinboardAction,
     },
 
     /// List available browsers
@@ -62,15 +168,105 @@ enum Commands {
         /// Limit results
         #[arg(short, long, default_value = "20")]
         limit: usize,
+        /// Only bookmarks of this media type (pdf, video, podcast, image)
+        #[arg(long)]
+        media: Option<String>,
+        /// Result order: relevance (title and URL match, visits, recency),
+        /// date, visits or title
+        #[arg(long, default_value = "relevance")]
+        sort: String,
+        /// Output format: text, or json, csv or yaml with id, title, url,
+        /// folder, browser and score for scripts
+        #[arg(short, long, default_value = "text")]
+        output: String,
+        /// Read browsers directly instead of the synced store
+        #[arg(long)]
+        live: bool,
+        /// Sync the store with new bookmarks and history before searching
+        #[arg(long, conflicts_with = "live")]
+        refresh: bool,
+        /// Use the store as it stood at this date (YYYY-MM-DD, through the
+        /// end of the day, or RFC 3339), including bookmarks deleted since
+        #[arg(long, value_name = "DATE", conflicts_with = "live")]
+        as_of: Option<String>,
     },
 
     /// Open bookmark in browser
     Open {
-        /// Search query
+        /// Search query; empty matches everything
+        #[arg(default_value = "")]
         query: String,
         /// Open first match without asking
-        #[arg(short, long)]
+        #[arg(short, long, conflicts_with = "all")]
         first: bool,
+        /// Open every match in its own tab, asking first above 10
+        #[arg(short, long)]
+        all: bool,
+        /// With --all, open at most this many matches
+        #[arg(short, long, requires = "all")]
+        limit: Option<usize>,
+        /// Only bookmarks in this folder or its subfolders
+        #[arg(long)]
+        folder: Option<String>,
+        /// Read browsers directly instead of the synced store
+        #[arg(long)]
+        live: bool,
+        /// Sync the store with new bookmarks and history before searching
+        #[arg(long, conflicts_with = "live")]
+        refresh: bool,
+    },
+
+    /// Browse, search, tag, move and delete stored bookmarks in the terminal
+    #[cfg(feature = "tui")]
+    Tui,
+
+    /// Serve bookmarks, search, processing and graphs as a JSON HTTP API
+    #[cfg(feature = "serve")]
+    Serve {
+        /// Address to listen on; anything but localhost is reachable without
+        /// authentication
+        #[arg(long, default_value = "127.0.0.1:8787")]
+        addr: String,
+        /// Allow a web page from this origin (or `*`) to call the API
+        #[arg(long)]
+        allow_origin: Option<String>,
+        /// Requests answered at once
+        #[arg(long, default_value = "4")]
+        workers: usize,
+    },
+
+    /// Refresh the bookmark store from browsers
+    #[command(visible_alias = "index")]
+    Sync {
+        /// Browser to sync (chrome, firefox, safari, edge, all)
+        #[arg(short, long, default_value = "all")]
+        browser: String,
+        /// Data type (bookmarks, history, both)
+        #[arg(short, long, default_value = "both")]
+        data_type: String,
+        /// Re-read everything instead of only what is new since the last
+        /// sync; also picks up deleted and renamed bookmarks
+        #[arg(long)]
+        full: bool,
+        /// Sync two browsers with each other instead of into the store,
+        /// e.g. `chrome,firefox` or `chrome:Default,firefox`
+        #[arg(long)]
+        between: Option<String>,
+        /// How --between settles bookmarks changed in both browsers
+        /// (newest, union, interactive)
+        #[arg(long, default_value = "newest")]
+        conflict: String,
+        /// With --between, report what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Merge a store copied from another machine into this one instead
+        /// of reading browsers; URLs filed or tagged differently go to a
+        /// conflicts file for `resolve`
+        #[arg(long, value_name = "STORE", conflicts_with = "between")]
+        from: Option<PathBuf>,
+        /// Where --from writes conflicts
+        #[arg(long, default_value = "conflicts.yaml", requires = "from")]
+        conflicts_file: PathBuf,
     },
 
     /// Process bookmarks (deduplicate, organize, or both)
@@ -84,18 +280,243 @@ enum Commands {
         /// Processing mode (dedupe, organize, both)
         #[arg(short, long, default_value = "both")]
         mode: String,
-        /// Merge strategy (first, last, recent, merge)
+        /// Merge strategy (first, last, recent, frequent, visited, merge).
+        /// `frequent` keeps the most common title; `visited` keeps the most
+        /// visited URL according to history
         #[arg(long, default_value = "merge")]
         strategy: String,
-        /// Organization strategy (domain, category, custom)
+        /// History export (YAML) supplying visit counts for --strategy visited
+        #[arg(long)]
+        history: Option<PathBuf>,
+        /// Follow redirects and dedupe on the URLs they end at (one request
+        /// per URL, cached between runs)
+        #[arg(long)]
+        resolve_redirects: bool,
+        /// Organization strategy (domain, category, media, custom).
+        /// `media` files PDFs, videos, podcasts and images into Papers,
+        /// Videos, Podcasts and Images, categorizing the rest
         #[arg(long, default_value = "custom")]
         org_strategy: String,
+        /// Detect media types from the Content-Type of a HEAD request when
+        /// the URL alone doesn't tell
+        #[arg(long)]
+        probe_media: bool,
+        /// Record each bookmark's nearest Wayback Machine snapshot in
+        /// `archive_url`; `dead` limits this to pages that no longer load
+        #[arg(long, value_name = "all|dead", num_args = 0..=1, default_missing_value = "all")]
+        archive: Option<String>,
+        /// With --archive, submit pages that have no snapshot to Save Page Now
+        #[arg(long, requires = "archive")]
+        save_missing: bool,
+        /// Look up bookmarked GitHub repositories (token from the config's
+        /// `github.api_token` or GITHUB_TOKEN): record stars and language,
+        /// tag them with the language, and flag archived or moved ones
+        #[arg(long)]
+        github: bool,
         /// Preview without applying
         #[arg(long)]
         preview: bool,
         /// Create backup
         #[arg(long)]
         backup: bool,
+        /// Comma-separated pipeline stages to run, in order
+        /// (expire, normalize, dedupe, enrich, github, organize, cleanup, wayback, quota)
+        #[arg(long)]
+        stages: Option<String>,
+        /// Write a processing report (.json for JSON, otherwise Markdown)
+        #[arg(long)]
+        report: Option<PathBuf>,
+        /// Write each bookmark's organizer folder with the rule or keyword
+        /// behind it and a confidence (.json for JSON, otherwise CSV)
+        #[arg(long)]
+        audit: Option<PathBuf>,
+        /// Write an HTML graph of what the run did: removed bookmarks in red,
+        /// moved ones linked to their old and new folders
+        #[arg(long, value_name = "PATH")]
+        visual_diff: Option<PathBuf>,
+    },
+
+    /// Fetch pages to fill in titles, descriptions and canonical URLs
+    Enrich {
+        /// Input file (YAML export)
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output file
+        #[arg(short, long, required_unless_present = "dry_run")]
+        output: Option<PathBuf>,
+        /// Pages fetched at once
+        #[arg(long, default_value = "8")]
+        concurrency: usize,
+        /// Replace existing titles and descriptions with the page's current
+        /// ones, not just empty or URL-only ones
+        #[arg(long)]
+        overwrite: bool,
+        /// Also look up arXiv, DOI and IEEE papers' title, authors and year
+        #[arg(long)]
+        papers: bool,
+        /// Write the bookmarked papers as BibTeX to this file
+        #[arg(long)]
+        bibtex: Option<PathBuf>,
+        /// Capture page thumbnails with headless Chrome (set CHROME to pick
+        /// the browser binary)
+        #[cfg(feature = "screenshots")]
+        #[arg(long)]
+        screenshots: bool,
+        /// Only capture bookmarks in this folder or its subfolders
+        #[cfg(feature = "screenshots")]
+        #[arg(long, requires = "screenshots")]
+        screenshot_folder: Option<String>,
+        /// List the changes without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Change the folder, title, description, keyword or tags of every
+    /// bookmark matching a filter, in the store or an export file
+    Edit {
+        /// Which bookmarks: words match the title or URL, and `domain:`,
+        /// `folder:`, `tag:`, `title:`, `url:` and `media:` match one field.
+        /// Every term must match
+        #[arg(long)]
+        filter: String,
+        /// Field to overwrite, e.g. folder="Reading/Blogs" (title, folder,
+        /// description or keyword; an empty value clears it)
+        #[arg(long, value_name = "FIELD=VALUE")]
+        set: Vec<String>,
+        /// Tag to add
+        #[arg(long)]
+        add_tag: Vec<String>,
+        /// Tag to remove
+        #[arg(long)]
+        remove_tag: Vec<String>,
+        /// Export file (YAML) to edit instead of the store
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+        /// Where to write the edited file; defaults to --input
+        #[arg(short, long, requires = "input")]
+        output: Option<PathBuf>,
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Turn folders into tags or tags into folders, e.g. to move between a
+    /// browser and Pinboard or Raindrop
+    Convert {
+        /// Input file (YAML export)
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Tag each bookmark with its folder path's segments and flatten the tree
+        #[arg(
+            long,
+            required_unless_present = "tags_to_folders",
+            conflicts_with = "tags_to_folders"
+        )]
+        folders_to_tags: bool,
+        /// File each tagged bookmark in a folder named after its primary tag
+        #[arg(long)]
+        tags_to_folders: bool,
+        /// Comma-separated tags to prefer as the folder, most important
+        /// first; otherwise a bookmark's first tag is used
+        #[arg(long, requires = "tags_to_folders", value_delimiter = ',')]
+        primary_tag_order: Vec<String>,
+    },
+
+    /// Write an export file's bookmarks back into a browser profile (Firefox, Chrome or Edge)
+    Apply {
+        /// Export file (YAML), e.g. the output of `process`
+        file: PathBuf,
+        /// Browser to write to
+        #[arg(short, long, default_value = "firefox")]
+        browser: String,
+        /// Profile directory, or the directory holding several profiles
+        #[arg(long)]
+        profile_dir: Option<PathBuf>,
+        /// Delete bookmarks that are not in the file
+        #[arg(long)]
+        prune: bool,
+        /// Report what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Summarize recently added bookmarks by category, for sharing
+    Digest {
+        /// Browser source
+        #[arg(short, long, default_value = "all")]
+        browser: String,
+        /// Period to cover (e.g. 7d, 2w, 24h), compared with the one before
+        #[arg(long, default_value = "7d")]
+        since: String,
+        /// Output format (markdown, html)
+        #[arg(short, long, default_value = "markdown")]
+        format: String,
+        /// Output file; prints to stdout when omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Also post the Markdown digest to this chat webhook (Slack,
+        /// Mattermost, Teams)
+        #[arg(long)]
+        webhook: Option<String>,
+        /// Read browsers directly instead of the synced store
+        #[arg(long)]
+        live: bool,
+    },
+
+    /// Compare two export files (YAML or JSON) or browsers: bookmarks added,
+    /// removed, retitled and moved
+    Diff {
+        /// Older export file, or a browser to read (chrome, firefox, ...)
+        old: String,
+        /// Newer export file or browser
+        new: String,
+        /// Output format: text, or json for scripts
+        #[arg(short, long, default_value = "text")]
+        output: String,
+    },
+
+    /// Merge export files (YAML or JSON), e.g. from several machines, into
+    /// one export without duplicates
+    Merge {
+        /// Export files, in order: `first` and `last` strategies follow it
+        #[arg(required = true, num_args = 2..)]
+        inputs: Vec<PathBuf>,
+        /// Merged export file (YAML)
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Merge strategy (first, last, recent, frequent, visited, merge);
+        /// defaults to the config's deduplication.merge_strategy
+        #[arg(long)]
+        strategy: Option<String>,
+    },
+
+    /// Show the folder hierarchy with bookmark counts
+    Tree {
+        /// Only the folder at this path (e.g. bookmark_bar/Dev), matched
+        /// without regard to case
+        path: Option<String>,
+        /// Export file (YAML) to read instead of the store or browsers
+        #[arg(short, long, conflicts_with_all = ["browser", "live"])]
+        input: Option<PathBuf>,
+        /// Browser source
+        #[arg(short, long, default_value = "all")]
+        browser: String,
+        /// Levels of subfolders to show
+        #[arg(short, long)]
+        depth: Option<usize>,
+        /// Draw with plain ASCII instead of box-drawing characters
+        #[arg(long)]
+        ascii: bool,
+        /// Read browsers directly instead of the synced store
+        #[arg(long)]
+        live: bool,
+        /// Use the store as it stood at this date (YYYY-MM-DD, through the
+        /// end of the day, or RFC 3339), including bookmarks deleted since
+        #[arg(long, value_name = "DATE", conflicts_with_all = ["live", "input"])]
+        as_of: Option<String>,
     },
 
     /// Generate knowledge graph
@@ -106,12 +527,15 @@ enum Commands {
         /// Data type (bookmarks, history, both)
         #[arg(short, long, default_value = "both")]
         data_type: String,
-        /// Output format (dot, json, gexf, html)
+        /// Output format (dot, json, gexf, mermaid, html)
         #[arg(short, long, default_value = "html")]
         format: String,
-        /// Output file
-        #[arg(short, long)]
-        output: PathBuf,
+        /// Output file; `-` prints to stdout with status messages on stderr
+        #[arg(short, long, required_unless_present = "clipboard")]
+        output: Option<PathBuf>,
+        /// Copy the graph to the clipboard instead (small graphs only)
+        #[arg(long, conflicts_with = "output")]
+        clipboard: bool,
         /// Minimum bookmarks for domain node (default: 5)
         #[arg(long, default_value = "5")]
         min_threshold: usize,
@@ -127,11 +551,126 @@ enum Commands {
         /// Domain-only mode (no individual bookmark nodes)
         #[arg(long)]
         domain_only: bool,
-        /// Only include bookmarks newer than this date (ISO 8601 format)
+        /// Only include bookmarks newer than this date (YYYY-MM-DD or RFC 3339)
         #[arg(long)]
         since: Option<String>,
+        /// Add tag edges between bookmarks and their extracted tags
+        #[arg(long, overrides_with = "no_tags")]
+        tags: bool,
+        /// Disable tag edges (default)
+        #[arg(long, overrides_with = "tags")]
+        no_tags: bool,
+        /// Disable category edges
+        #[arg(long)]
+        no_categories: bool,
+        /// Add similarity edges between bookmarks above this Jaccard score (0.0-1.0)
+        #[arg(long)]
+        similarity_threshold: Option<f64>,
+        /// Collapse same-domain cliques into counts on the domain edges
+        #[arg(long)]
+        aggregate_edges: bool,
+        /// Stop before building a graph estimated to have more nodes than
+        /// this, with suggestions for shrinking it
+        #[arg(long)]
+        max_nodes: Option<usize>,
+        /// Stop before building a graph estimated to have more edges than
+        /// this, with suggestions for shrinking it
+        #[arg(long)]
+        max_edges: Option<usize>,
+        /// Only export the neighborhood of bookmarks matching this search query
+        #[arg(long)]
+        focus: Option<String>,
+        /// Hops to expand from focus matches
+        #[arg(long, default_value = "2")]
+        depth: usize,
+        /// Look up bookmarked GitHub repositories first, so they group under
+        /// their primary language as a tag
+        #[arg(long)]
+        github: bool,
+        /// HTML color scheme (dark, light) or a CSS file to inject; defaults
+        /// to the config's `html` section
+        #[arg(long)]
+        theme: Option<String>,
+        /// Split HTML graph data into files of at most this many nodes or
+        /// edges, loaded progressively (0 writes a single file)
+        #[arg(long, default_value = "10000")]
+        chunk_size: usize,
+        /// Color bookmarks from blue (old) to red (recent) by date added or
+        /// last visit (added, visited)
+        #[arg(long, value_name = "DATE")]
+        heatmap: Option<String>,
+        /// Place nodes with a force-directed pass and save the positions in
+        /// the output: Gephi and the HTML view open the graph already spread
+        /// out (gexf, json, html)
+        #[arg(long)]
+        layout: bool,
+        /// Read browsers directly instead of the synced store
+        #[arg(long)]
+        live: bool,
+        /// Use the store as it stood at this date (YYYY-MM-DD, through the
+        /// end of the day, or RFC 3339), including bookmarks deleted since
+        #[arg(long, value_name = "DATE", conflicts_with = "live")]
+        as_of: Option<String>,
+    },
+
+    /// Apply the choices made in a conflicts file written by `sync --from`
+    Resolve {
+        /// Conflicts file
+        file: PathBuf,
+    },
+
+    /// List, restore or empty the store's deleted bookmarks
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+
+    /// Revert the output file of the last `process` run (or of run ID)
+    Undo {
+        /// Run id, from `undo --list`
+        id: Option<i64>,
+        /// Undo even if the file changed after the run wrote it
+        #[arg(long)]
+        force: bool,
+        /// Show recorded runs instead
+        #[arg(long, conflicts_with_all = ["id", "force"])]
+        list: bool,
     },
 
+    /// Save, compare and restore versions of the bookmark set
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+
+    /// Save every browser's bookmarks to a dated, compressed file in the
+    /// backups directory, if backup.interval has passed, and delete all but
+    /// the newest backup.keep
+    Backup {
+        /// Back up even if the last backup is recent
+        #[arg(long)]
+        force: bool,
+        /// Show the backups instead
+        #[arg(long, conflicts_with = "force")]
+        list: bool,
+    },
+
+    /// Delete graph pages, database snapshots and other temporary files
+    Clean {
+        /// Only files made more than this many days ago (default: cleanup.max_age_days)
+        #[arg(long, conflicts_with = "all")]
+        older_than: Option<u32>,
+        /// Delete them all, however recent
+        #[arg(long)]
+        all: bool,
+        /// List what would be deleted without deleting it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Show where config, store, cache, backups, archives and snapshots live
+    Paths,
+
     /// Manage configuration
     Config {
         /// Show current config
@@ -146,24 +685,178 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum PinboardAction {
+    /// Fetch all posts as an export file
+    Pull {
+        /// Output file (prints to stdout if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add or update posts from an export file
+    Push {
+        /// Export file whose bookmarks are pushed
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Show what would be pushed without calling the API
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashAction {
+    /// Show trashed bookmarks with their ids
+    List,
+    /// Take bookmarks out of the trash into an export file to `apply`
+    Restore {
+        /// Trash ids, from `trash list`
+        #[arg(required = true)]
+        ids: Vec<i64>,
+        /// Export file to write
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Delete trashed bookmarks for good
+    Empty {
+        /// Only those deleted more than this many days ago
+        #[arg(long)]
+        older_than: Option<u32>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SnapshotAction {
+    /// Save the current bookmarks; an unchanged set reuses its snapshot
+    Create {
+        /// Snapshot this export file (YAML or JSON) instead of the store
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+        /// A note to recognize it by, e.g. "before cleanup"
+        #[arg(short, long)]
+        label: Option<String>,
+    },
+    /// Show snapshots, most recent first
+    List,
+    /// Compare two snapshots by id (or a unique prefix); `current` is the
+    /// store as it is now
+    Diff {
+        /// Older snapshot
+        old: String,
+        /// Newer snapshot
+        #[arg(default_value = "current")]
+        new: String,
+        /// Output format: text, or json for scripts
+        #[arg(short, long, default_value = "text")]
+        output: String,
+    },
+    /// Write a snapshot to an export file to `apply`
+    Restore {
+        /// Snapshot id, or a unique prefix of it
+        id: String,
+        /// Export file to write
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
 fn main() -> Result<()> {
     env_logger::init();
     let args = Cli::parse();
+    paths::init(&PathOverrides {
+        config: args.config,
+        store: args.store,
+        cache: args.cache_dir,
+        backups: args.backup_dir,
+        archives: args.archive_dir,
+        snapshots: args.snapshot_dir,
+    });
+    if !matches!(args.command, Commands::Clean { .. }) {
+        cli::auto_clean();
+    }
 
     match args.command {
         Commands::Export {
             browser,
             data_type,
             output,
+            clipboard,
+            format,
+            fields,
+            anonymize,
+            anonymize_salt,
             profile_dir,
+            aliases,
+            include_reading_list,
+            i_understand_the_risk,
+            timeout,
         } => {
+            let options = ExportOptions {
+                format: format.parse()?,
+                fields: fields.as_deref().map(str::parse).transpose()?,
+                anonymize: anonymize.then(|| Anonymizer::new(anonymize_salt.as_deref())),
+                include_aliases: aliases,
+                include_reading_list,
+                include_passwords: i_understand_the_risk,
+                master_password: None,
+                clipboard,
+            };
             if browser == "all" {
-                cli::export_all_browsers(&data_type, output, profile_dir)?;
+                if clipboard || output.as_deref().is_some_and(utils::is_stdout) {
+                    return Err(anyhow::anyhow!(
+                        "--browser all writes one file per profile; pick a browser to print or copy the export"
+                    ));
+                }
+                let timeout = (timeout > 0).then(|| Duration::from_secs(timeout));
+                cli::export_all_browsers(&data_type, output, profile_dir, &options, timeout)?;
             } else {
-                export_data(&browser, &data_type, output, profile_dir)?;
+                cli::export_with_password_prompt(
+                    &browser,
+                    &data_type,
+                    output,
+                    profile_dir,
+                    &options,
+                )?;
             }
         }
 
+        Commands::Import {
+            file,
+            format,
+            output,
+            to_store,
+            update,
+        } => {
+            cli::import_bookmarks(&file, &format, output, to_store, update)?;
+        }
+
+        Commands::Generate {
+            count,
+            domains,
+            duplicates,
+            history,
+            seed,
+            output,
+        } => {
+            let config = SyntheticConfig {
+                count,
+                domains,
+                duplicate_ratio: duplicates.clamp(0.0, 1.0),
+                history,
+                seed,
+            };
+            cli::generate_synthetic(&config, output)?;
+        }
+
+        Commands::Verify { file } => {
+            cli::verify_export_file(&file)?;
+        }
+
+        Commands::Pinboard { action } => match action {
+            PinboardAction::Pull { output } => cli::pinboard_pull(output)?,
+            PinboardAction::Push { input, dry_run } => cli::pinboard_push(&input, dry_run)?,
+        },
+
         Commands::List { browser } => {
             if let Some(b) = browser {
                 cli::list_browser_profiles(&b)?;
@@ -177,14 +870,74 @@ fn main() -> Result<()> {
             title_only,
             url_only,
             limit,
+            media,
+            sort,
+            output,
+            live,
+            refresh,
+            as_of,
         } => {
-            search_bookmarks(&query, title_only, url_only, limit)?;
+            if refresh {
+                refresh_store()?;
+            }
+            let as_of = as_of.as_deref().map(cli::parse_as_of).transpose()?;
+            let options = SearchOptions {
+                title_only,
+                url_only,
+                limit,
+                media_type: media.as_deref().map(str::parse).transpose()?,
+                sort: sort.parse()?,
+            };
+            search_bookmarks(&query, options, output.parse()?, live, as_of)?;
         }
 
-        Commands::Open { query, first } => {
-            open_bookmark(&query, first)?;
+        Commands::Open {
+            query,
+            first,
+            all,
+            limit,
+            folder,
+            live,
+            refresh,
+        } => {
+            if refresh {
+                refresh_store()?;
+            }
+            let options = OpenOptions {
+                first,
+                all,
+                limit,
+                folder,
+                live,
+            };
+            open_bookmark(&query, &options)?;
         }
 
+        #[cfg(feature = "tui")]
+        Commands::Tui => bookmark::tui::run()?,
+
+        #[cfg(feature = "serve")]
+        Commands::Serve {
+            addr,
+            allow_origin,
+            workers,
+        } => bookmark::serve::ApiServer::new(allow_origin)?.run(&addr, workers)?,
+
+        Commands::Sync {
+            browser,
+            data_type,
+            full,
+            between,
+            conflict,
+            dry_run,
+            from,
+            conflicts_file,
+        } => match (between, from) {
+            (Some(between), _) => cli::sync_between(&between, &conflict, dry_run)?,
+            (None, Some(other)) => cli::merge_store(&other, &conflicts_file)?,
+            (None, None) => cli::sync_store(&browser, &data_type, full)?,
+        },
+
         Commands::Process {
             input,
             output,
@@ -193,8 +946,126 @@ fn main() -> Result<()> {
             org_strategy,
             preview,
             backup,
+            stages,
+            report,
+            history,
+            probe_media,
+            archive,
+            save_missing,
+            github,
+            resolve_redirects,
+            audit,
+            visual_diff,
         } => {
-            cli::process_bookmarks(&input, &output, &mode, &strategy, &org_strategy, preview, backup)?;
+            let params = cli::ProcessParams {
+                mode,
+                strategy,
+                org_strategy,
+                preview,
+                backup,
+                stages,
+                report,
+                history,
+                probe_media,
+                archive,
+                save_missing,
+                github,
+                resolve_redirects,
+                audit,
+                visual_diff,
+            };
+            cli::process_bookmarks(&input, &output, params)?;
+        }
+
+        Commands::Enrich {
+            input,
+            output,
+            concurrency,
+            overwrite,
+            papers,
+            bibtex,
+            #[cfg(feature = "screenshots")]
+            screenshots,
+            #[cfg(feature = "screenshots")]
+            screenshot_folder,
+            dry_run,
+        } => {
+            let options = EnrichOptions {
+                concurrency,
+                overwrite,
+            };
+            let output = output.filter(|_| !dry_run);
+            let bibtex = bibtex.filter(|_| !dry_run);
+            #[cfg(feature = "screenshots")]
+            let screenshots = (screenshots && !dry_run).then(|| ScreenshotOptions {
+                folder: screenshot_folder,
+                ..Default::default()
+            });
+            cli::enrich_bookmarks(
+                &input,
+                output.as_deref(),
+                options,
+                papers,
+                bibtex.as_deref(),
+                #[cfg(feature = "screenshots")]
+                screenshots,
+            )?;
+        }
+
+        Commands::Edit {
+            filter,
+            set,
+            add_tag,
+            remove_tag,
+            input,
+            output,
+            dry_run,
+        } => {
+            let changes = edit::Edit {
+                set: set
+                    .iter()
+                    .map(|s| edit::Edit::parse_assignment(s))
+                    .collect::<Result<_>>()?,
+                add_tags: add_tag,
+                remove_tags: remove_tag,
+            };
+            cli::edit_bookmarks(
+                &filter.parse()?,
+                &changes,
+                input.as_deref(),
+                output.as_deref(),
+                dry_run,
+            )?;
+        }
+
+        Commands::Convert {
+            input,
+            output,
+            folders_to_tags: _,
+            tags_to_folders,
+            primary_tag_order,
+        } => {
+            let conversion = if tags_to_folders {
+                convert::Conversion::TagsToFolders(primary_tag_order)
+            } else {
+                convert::Conversion::FoldersToTags
+            };
+            cli::convert_bookmarks(&input, &output, &conversion)?;
+        }
+
+        Commands::Apply {
+            file,
+            browser,
+            profile_dir,
+            prune,
+            dry_run,
+        } => {
+            let options = ApplyOptions {
+                dry_run,
+                prune,
+                backup_dir: None,
+            };
+            cli::apply_export(&file, &browser, profile_dir.as_deref(), &options)?;
         }
 
         Commands::Graph {
@@ -202,12 +1073,29 @@ fn main() -> Result<()> {
             data_type,
             format,
             output,
+            clipboard,
             min_threshold,
             detail,
             max_per_domain,
             max_total,
             domain_only,
             since,
+            tags,
+            no_tags: _,
+            no_categories,
+            similarity_threshold,
+            aggregate_edges,
+            max_nodes,
+            max_edges,
+            focus,
+            depth,
+            github,
+            theme,
+            chunk_size,
+            heatmap,
+            layout,
+            live,
+            as_of,
         } => {
             let params = cli::GraphParams {
                 min_threshold,
@@ -216,10 +1104,94 @@ fn main() -> Result<()> {
                 max_total,
                 domain_only,
                 since,
+                tags,
+                categories: !no_categories,
+                similarity_threshold,
+                aggregate_edges,
+                focus,
+                depth,
+                github,
+                theme,
+                chunk_size,
+                heatmap,
+                layout,
+                max_nodes,
+                max_edges,
+            };
+            let target = match output {
+                _ if clipboard => cli::GraphTarget::Clipboard,
+                Some(path) if utils::is_stdout(&path) => cli::GraphTarget::Stdout,
+                Some(path) => cli::GraphTarget::File(path),
+                None => unreachable!("clap requires --output unless --clipboard"),
             };
-            cli::generate_graph(&browser, &data_type, &format, output, params)?;
+            let as_of = as_of.as_deref().map(cli::parse_as_of).transpose()?;
+            cli::generate_graph(&browser, &data_type, &format, target, params, live, as_of)?;
         }
 
+        Commands::Digest {
+            browser,
+            since,
+            format,
+            output,
+            webhook,
+            live,
+        } => cli::digest(&browser, &since, &format, output, webhook.as_deref(), live)?,
+
+        Commands::Diff { old, new, output } => cli::diff_bookmarks(&old, &new, &output)?,
+
+        Commands::Merge {
+            inputs,
+            output,
+            strategy,
+        } => cli::merge_exports(&inputs, &output, strategy.as_deref())?,
+
+        Commands::Tree {
+            path,
+            input,
+            browser,
+            depth,
+            ascii,
+            live,
+            as_of,
+        } => cli::print_tree(
+            input.as_deref(),
+            &browser,
+            path.as_deref(),
+            depth,
+            ascii,
+            live,
+            as_of.as_deref().map(cli::parse_as_of).transpose()?,
+        )?,
+
+        Commands::Clean {
+            older_than,
+            all,
+            dry_run,
+        } => cli::clean(older_than, all, dry_run)?,
+
+        Commands::Paths => cli::print_paths(),
+
+        Commands::Resolve { file } => cli::resolve_conflicts(&file)?,
+
+        Commands::Trash { action } => match action {
+            TrashAction::List => cli::trash_list()?,
+            TrashAction::Restore { ids, output } => cli::trash_restore(&ids, &output)?,
+            TrashAction::Empty { older_than } => cli::trash_empty(older_than)?,
+        },
+
+        Commands::Undo { id, force, list } => cli::undo(id, force, list)?,
+
+        Commands::Snapshot { action } => match action {
+            SnapshotAction::Create { input, label } => {
+                cli::snapshot_create(input.as_deref(), label)?
+            }
+            SnapshotAction::List => cli::snapshot_list()?,
+            SnapshotAction::Diff { old, new, output } => cli::snapshot_diff(&old, &new, &output)?,
+            SnapshotAction::Restore { id, output } => cli::snapshot_restore(&id, &output)?,
+        },
+
+        Commands::Backup { force, list } => cli::backup(force, list)?,
+
         Commands::Config {
             show,
             create_sample,
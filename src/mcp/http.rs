@@ -0,0 +1,385 @@
+//! The MCP streamable HTTP transport, for clients that can't start the
+//! server as a subprocess, such as remote or containerized agents.
+//!
+//! Everything goes through one endpoint, `/mcp`:
+//!
+//! - `POST` takes one JSON-RPC message. A request is answered with its
+//!   response as JSON, or as a one-event SSE stream when the client only
+//!   accepts `text/event-stream`; a notification or response gets 202.
+//! - `GET` is refused with 405, as the server never starts a conversation
+//! - `DELETE` ends the session named by `Mcp-Session-Id`
+//!
+//! A successful `initialize` returns an `Mcp-Session-Id` header, which every
+//! later request must send back. Requests from a browser page are refused
+//! unless their `Origin` is on this machine, so a web site can't reach the
+//! server through DNS rebinding. There is no other authentication.
+
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::{McpServer, PROTOCOL_VERSIONS};
+use crate::utils::is_local_origin;
+
+/// Largest request body read, so one request can't exhaust memory
+const MAX_BODY: u64 = 64 * 1024 * 1024;
+
+const ENDPOINT: &str = "/mcp";
+const WORKERS: usize = 4;
+
+const JSON: &str = "application/json";
+const EVENT_STREAM: &str = "text/event-stream";
+
+/// A response ready to send
+#[derive(Debug)]
+pub struct Reply {
+    pub status: u16,
+    /// None when there is no body
+    pub content_type: Option<&'static str>,
+    /// Sent as `Mcp-Session-Id`, when `initialize` opened a session
+    pub session_id: Option<String>,
+    pub body: String,
+}
+
+impl Reply {
+    fn empty(status: u16) -> Self {
+        Self {
+            status,
+            content_type: None,
+            session_id: None,
+            body: String::new(),
+        }
+    }
+
+    fn error(status: u16, message: impl std::fmt::Display) -> Self {
+        Self {
+            status,
+            content_type: Some(JSON),
+            session_id: None,
+            body: json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32600, "message": message.to_string() },
+            })
+            .to_string(),
+        }
+    }
+}
+
+/// Serves an [`McpServer`] over HTTP; cheap to share between worker threads
+pub struct HttpTransport {
+    server: McpServer,
+    /// Ids of the sessions `initialize` opened and `DELETE` hasn't ended
+    sessions: Mutex<HashSet<String>>,
+}
+
+impl HttpTransport {
+    pub fn new(server: McpServer) -> Self {
+        Self {
+            server,
+            sessions: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Listen on `addr` and answer requests until the process is stopped
+    pub fn run(self, addr: &str) -> Result<()> {
+        let server = tiny_http::Server::http(addr)
+            .map_err(|e| anyhow!("Could not listen on {}: {}", addr, e))?;
+        if let Some(ip) = server.server_addr().to_ip()
+            && !ip.ip().is_loopback()
+        {
+            eprintln!(
+                "Warning: listening on {} without authentication; anyone who can reach it can read your bookmarks",
+                ip
+            );
+        }
+        eprintln!("Serving MCP on http://{}{}", addr, ENDPOINT);
+
+        let server = Arc::new(server);
+        let transport = Arc::new(self);
+        let handles: Vec<_> = (0..WORKERS)
+            .map(|_| {
+                let server = Arc::clone(&server);
+                let transport = Arc::clone(&transport);
+                thread::spawn(move || {
+                    for request in server.incoming_requests() {
+                        transport.respond(request);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            let _ = handle.join();
+        }
+        Ok(())
+    }
+
+    fn respond(&self, mut request: tiny_http::Request) {
+        let headers: Vec<(String, String)> = request
+            .headers()
+            .iter()
+            .map(|h| (h.field.as_str().to_string(), h.value.as_str().to_string()))
+            .collect();
+        let mut body = String::new();
+        let reply = match request.as_reader().take(MAX_BODY).read_to_string(&mut body) {
+            Ok(_) => self.handle(request.method().as_str(), request.url(), &headers, &body),
+            Err(e) => Reply::error(400, format!("Could not read the request body: {}", e)),
+        };
+        log::info!("{} {} {}", request.method(), request.url(), reply.status);
+
+        let mut response =
+            tiny_http::Response::from_string(reply.body).with_status_code(reply.status);
+        if let Some(content_type) = reply.content_type {
+            response = response.with_header(header("Content-Type", content_type));
+        }
+        if let Some(id) = &reply.session_id {
+            response = response.with_header(header("Mcp-Session-Id", id));
+        }
+        if reply.status == 405 {
+            response = response.with_header(header("Allow", "POST, DELETE"));
+        }
+        if let Err(e) = request.respond(response) {
+            log::warn!("Could not send the response: {}", e);
+        }
+    }
+
+    /// Answer one request: `url` is the path with any query string, and
+    /// header names are matched case-insensitively
+    pub fn handle(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(String, String)],
+        body: &str,
+    ) -> Reply {
+        let path = url.split_once('?').map_or(url, |(path, _)| path);
+        if path.trim_end_matches('/') != ENDPOINT {
+            return Reply::error(
+                404,
+                format!("No endpoint {}; MCP is served on {}", path, ENDPOINT),
+            );
+        }
+        if let Some(origin) = header_value(headers, "Origin")
+            && !is_local_origin(origin)
+        {
+            return Reply::error(403, format!("Requests from {} are not allowed", origin));
+        }
+        if let Some(version) = header_value(headers, "MCP-Protocol-Version")
+            && !PROTOCOL_VERSIONS.contains(&version)
+        {
+            return Reply::error(
+                400,
+                format!(
+                    "Unsupported MCP-Protocol-Version {}; this server supports {}",
+                    version,
+                    PROTOCOL_VERSIONS.join(", ")
+                ),
+            );
+        }
+
+        match method {
+            "POST" => self.post(headers, body),
+            "DELETE" => match self.session(headers) {
+                Ok(id) => {
+                    self.sessions.lock().unwrap().remove(&id);
+                    Reply::empty(204)
+                }
+                Err(reply) => reply,
+            },
+            _ => Reply::error(405, format!("{} is not allowed on {}", method, ENDPOINT)),
+        }
+    }
+
+    fn post(&self, headers: &[(String, String)], body: &str) -> Reply {
+        let accept = header_value(headers, "Accept").unwrap_or("*/*");
+        let accepts = |media_type: &str| accept.contains(media_type) || accept.contains("*/*");
+        if !accepts(JSON) && !accepts(EVENT_STREAM) {
+            return Reply::error(406, format!("Responses are {} or {}", JSON, EVENT_STREAM));
+        }
+
+        let Ok(message) = serde_json::from_str::<Value>(body) else {
+            // `handle_line` words the parse error
+            let response = self.server.handle_line(body);
+            return Reply {
+                status: 400,
+                content_type: Some(JSON),
+                session_id: None,
+                body: serde_json::to_string(&response).unwrap_or_default(),
+            };
+        };
+        let initialize = message["method"] == "initialize";
+        if !initialize && let Err(reply) = self.session(headers) {
+            return reply;
+        }
+        // A client's answer to a server request; this server sends none
+        if message.get("method").is_none() {
+            return Reply::empty(202);
+        }
+
+        let Some(response) = self.server.handle_line(body) else {
+            return Reply::empty(202);
+        };
+        let session_id = (initialize && response.error.is_none()).then(|| {
+            let id = session_id();
+            self.sessions.lock().unwrap().insert(id.clone());
+            id
+        });
+        let json = serde_json::to_string(&response).unwrap_or_default();
+        let (content_type, body) = match accepts(JSON) {
+            true => (JSON, json),
+            false => (EVENT_STREAM, format!("event: message\ndata: {}\n\n", json)),
+        };
+        Reply {
+            status: 200,
+            content_type: Some(content_type),
+            session_id,
+            body,
+        }
+    }
+
+    /// The open session the request names, or the reply refusing it
+    fn session(&self, headers: &[(String, String)]) -> std::result::Result<String, Reply> {
+        let Some(id) = header_value(headers, "Mcp-Session-Id") else {
+            return Err(Reply::error(
+                400,
+                "Missing Mcp-Session-Id; call initialize first",
+            ));
+        };
+        match self.sessions.lock().unwrap().contains(id) {
+            true => Ok(id.to_string()),
+            false => Err(Reply::error(
+                404,
+                format!("Unknown or ended session {}", id),
+            )),
+        }
+    }
+}
+
+fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(field, _)| field.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn header(name: &str, value: &str) -> tiny_http::Header {
+    tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes())
+        .expect("header names and values are ASCII")
+}
+
+/// 128 random bits, hex-encoded, so one client can't guess another's session
+fn session_id() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("the OS random number generator is available");
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_session_lifecycle() {
+        let transport = HttpTransport::new(McpServer::new());
+        let accept = ("Accept", "application/json, text/event-stream");
+        let initialize = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-06-18"}}"#;
+        let list = r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#;
+
+        let reply = transport.handle("POST", "/mcp", &headers(&[accept]), list);
+        assert_eq!(reply.status, 400);
+
+        let reply = transport.handle("POST", "/mcp", &headers(&[accept]), initialize);
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.content_type, Some(JSON));
+        let id = reply.session_id.unwrap();
+        assert_eq!(id.len(), 32);
+
+        let session = ("mcp-session-id", id.as_str());
+        let initialized = r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#;
+        let reply = transport.handle("POST", "/mcp", &headers(&[accept, session]), initialized);
+        assert_eq!(reply.status, 202);
+        assert!(reply.body.is_empty());
+
+        let reply = transport.handle("POST", "/mcp", &headers(&[accept, session]), list);
+        assert_eq!(reply.status, 200);
+        let response: Value = serde_json::from_str(&reply.body).unwrap();
+        assert!(
+            response["result"]["tools"]
+                .as_array()
+                .is_some_and(|t| !t.is_empty())
+        );
+
+        let stream_only = ("Accept", "text/event-stream");
+        let reply = transport.handle("POST", "/mcp", &headers(&[stream_only, session]), list);
+        assert_eq!(reply.content_type, Some(EVENT_STREAM));
+        assert!(reply.body.starts_with("event: message\ndata: {"));
+
+        assert_eq!(
+            transport
+                .handle("GET", "/mcp", &headers(&[session]), "")
+                .status,
+            405
+        );
+        assert_eq!(
+            transport
+                .handle("DELETE", "/mcp", &headers(&[session]), "")
+                .status,
+            204
+        );
+        let reply = transport.handle("POST", "/mcp", &headers(&[accept, session]), list);
+        assert_eq!(reply.status, 404);
+    }
+
+    #[test]
+    fn test_rejects_foreign_origins_and_versions() {
+        let transport = HttpTransport::new(McpServer::new());
+        let initialize = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+
+        let reply = transport.handle(
+            "POST",
+            "/mcp",
+            &headers(&[("Origin", "https://evil.example")]),
+            initialize,
+        );
+        assert_eq!(reply.status, 403);
+        let reply = transport.handle(
+            "POST",
+            "/mcp",
+            &headers(&[("Origin", "http://localhost:3000")]),
+            initialize,
+        );
+        assert_eq!(reply.status, 200);
+        let reply = transport.handle(
+            "POST",
+            "/mcp",
+            &headers(&[("MCP-Protocol-Version", "1.0")]),
+            initialize,
+        );
+        assert_eq!(reply.status, 400);
+        let reply = transport.handle(
+            "POST",
+            "/mcp",
+            &headers(&[("Accept", "text/html")]),
+            initialize,
+        );
+        assert_eq!(reply.status, 406);
+        assert_eq!(
+            transport.handle("POST", "/other", &[], initialize).status,
+            404
+        );
+        assert_eq!(
+            transport.handle("POST", "/mcp", &[], "{not json").status,
+            400
+        );
+    }
+}
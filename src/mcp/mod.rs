@@ -1,3 +1,6 @@
+mod http;
+mod resources;
+mod self_test;
 mod tools;
 
 use anyhow::Result;
@@ -5,6 +8,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::io::{self, BufRead, Write};
 
+pub use http::HttpTransport;
+pub use self_test::SelfTestReport;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct McpRequest {
     jsonrpc: String,
@@ -31,6 +37,56 @@ struct McpError {
     data: Option<Value>,
 }
 
+/// Protocol versions this server speaks, newest first
+pub const PROTOCOL_VERSIONS: [&str; 2] = ["2025-06-18", "2024-11-05"];
+
+/// A failure with its own JSON-RPC error code; any other error is reported
+/// as an internal error (-32603)
+#[derive(Debug)]
+struct RpcError {
+    code: i32,
+    message: String,
+    data: Option<Value>,
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+/// The version to answer a client asking for `requested` with: the newest
+/// supported one no newer than it. Versions are dates, so a client newer
+/// than this server gets the newest version here and can decide whether
+/// to continue. A client that names none gets the newest as well.
+fn negotiate_version(requested: Option<&str>) -> Result<&'static str> {
+    let Some(requested) = requested else {
+        log::warn!("initialize without a protocolVersion; answering {}", PROTOCOL_VERSIONS[0]);
+        return Ok(PROTOCOL_VERSIONS[0]);
+    };
+    let is_date = chrono::NaiveDate::parse_from_str(requested, "%Y-%m-%d").is_ok();
+    PROTOCOL_VERSIONS
+        .into_iter()
+        .find(|&version| is_date && version <= requested)
+        .ok_or_else(|| {
+            RpcError {
+                code: -32602,
+                message: format!(
+                    "Unsupported protocol version {}; this server supports {}",
+                    requested,
+                    PROTOCOL_VERSIONS.join(", ")
+                ),
+                data: Some(json!({
+                    "supported": PROTOCOL_VERSIONS,
+                    "requested": requested,
+                })),
+            }
+            .into()
+        })
+}
+
 pub struct McpServer {
     name: String,
     version: String,
@@ -56,20 +112,10 @@ impl McpServer {
                 continue;
             }
 
-            let response = match serde_json::from_str::<McpRequest>(&line) {
-                Ok(request) => self.handle_request(request),
-                Err(e) => McpResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: None,
-                    result: None,
-                    error: Some(McpError {
-                        code: -32700,
-                        message: format!("Parse error: {}", e),
-                        data: None,
-                    }),
-                },
+            // Notifications, such as `notifications/initialized`, get no answer
+            let Some(response) = self.handle_line(&line) else {
+                continue;
             };
-
             let response_json = serde_json::to_string(&response)?;
             writeln!(stdout, "{}", response_json)?;
             stdout.flush()?;
@@ -78,6 +124,32 @@ impl McpServer {
         Ok(())
     }
 
+    /// Answer one JSON-RPC message the way `run` does; notifications get `None`
+    pub fn respond(&self, message: &str) -> Option<Value> {
+        self.handle_line(message)
+            .map(|response| serde_json::to_value(response).expect("responses serialize to JSON"))
+    }
+
+    fn handle_line(&self, line: &str) -> Option<McpResponse> {
+        match serde_json::from_str::<McpRequest>(line) {
+            Ok(request) if request.id.is_none() && request.method.starts_with("notifications/") => {
+                log::debug!("Notification: {}", request.method);
+                None
+            }
+            Ok(request) => Some(self.handle_request(request)),
+            Err(e) => Some(McpResponse {
+                jsonrpc: "2.0".to_string(),
+                id: None,
+                result: None,
+                error: Some(McpError {
+                    code: -32700,
+                    message: format!("Parse error: {}", e),
+                    data: None,
+                }),
+            }),
+        }
+    }
+
     fn handle_request(&self, request: McpRequest) -> McpResponse {
         log::debug!("Handling request: {:?}", request.method);
 
@@ -85,6 +157,8 @@ impl McpServer {
             "initialize" => self.handle_initialize(request.params),
             "tools/list" => self.handle_list_tools(),
             "tools/call" => self.handle_tool_call(request.params),
+            "resources/list" => self.handle_list_resources(),
+            "resources/read" => self.handle_read_resource(request.params),
             _ => Err(anyhow::anyhow!("Method not found: {}", request.method)),
         };
 
@@ -99,21 +173,29 @@ impl McpServer {
                 jsonrpc: "2.0".to_string(),
                 id: request.id,
                 result: None,
-                error: Some(McpError {
-                    code: -32603,
-                    message: e.to_string(),
-                    data: None,
+                error: Some(match e.downcast::<RpcError>() {
+                    Ok(error) => McpError {
+                        code: error.code,
+                        message: error.message,
+                        data: error.data,
+                    },
+                    Err(e) => McpError {
+                        code: -32603,
+                        message: e.to_string(),
+                        data: None,
+                    },
                 }),
             },
         }
     }
 
-    fn handle_initialize(&self, _params: Option<Value>) -> Result<Value> {
+    fn handle_initialize(&self, params: Option<Value>) -> Result<Value> {
+        let requested = params
+            .as_ref()
+            .and_then(|p| p["protocolVersion"].as_str());
         Ok(json!({
-            "protocolVersion": "2024-11-05",
-            "capabilities": {
-                "tools": {}
-            },
+            "protocolVersion": negotiate_version(requested)?,
+            "capabilities": self.capabilities(),
             "serverInfo": {
                 "name": self.name,
                 "version": self.version
@@ -121,6 +203,17 @@ impl McpServer {
         }))
     }
 
+    /// What the server offers, by the methods it answers: `tools/*` with a
+    /// fixed list, and `resources/list` and `resources/read` without
+    /// subscriptions. Prompts and logging are not implemented, so they are
+    /// not advertised.
+    fn capabilities(&self) -> Value {
+        json!({
+            "tools": { "listChanged": false },
+            "resources": { "subscribe": false, "listChanged": false }
+        })
+    }
+
     fn handle_list_tools(&self) -> Result<Value> {
         Ok(json!({
             "tools": [
@@ -140,6 +233,14 @@ impl McpServer {
                                 "description": "Data type to export",
                                 "enum": ["bookmarks", "history", "both"],
                                 "default": "bookmarks"
+                            },
+                            "fields": {
+                                "type": "array",
+                                "description": "Bookmark fields to keep (default: all)",
+                                "items": {
+                                    "type": "string",
+                                    "enum": ["id", "title", "url", "folder", "date_added", "children", "keyword", "tags", "description"]
+                                }
                             }
                         },
                         "required": ["browser"]
@@ -169,6 +270,17 @@ impl McpServer {
                                 "type": "integer",
                                 "description": "Maximum number of results",
                                 "default": 20
+                            },
+                            "media_type": {
+                                "type": "string",
+                                "description": "Only bookmarks of this media type",
+                                "enum": ["pdf", "video", "podcast", "image"]
+                            },
+                            "sort": {
+                                "type": "string",
+                                "description": "Result order",
+                                "enum": ["relevance", "date", "visits", "title"],
+                                "default": "relevance"
                             }
                         },
                         "required": ["query"]
@@ -239,6 +351,104 @@ impl McpServer {
                             }
                         }
                     }
+                },
+                {
+                    "name": "preview_duplicates",
+                    "description": "List pairs of bookmarks that look like duplicates, with a similarity score and the normalized URL deduplication would merge them on, so merges can be confirmed before processing",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "bookmarks": {
+                                "type": "array",
+                                "description": "Bookmarks to check (default: the browser's bookmarks)"
+                            },
+                            "browser": {
+                                "type": "string",
+                                "description": "Browser source when no bookmarks are given",
+                                "enum": ["chrome", "firefox", "safari", "edge", "all"],
+                                "default": "all"
+                            },
+                            "limit": {
+                                "type": "integer",
+                                "description": "Maximum number of pairs, most similar first",
+                                "default": 50
+                            }
+                        }
+                    }
+                },
+                {
+                    "name": "organize_bookmarks",
+                    "description": "File bookmarks into folders using rules given inline, without changing the config file, and return the reorganized bookmarks",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "bookmarks": {
+                                "type": "array",
+                                "description": "Bookmarks to organize"
+                            },
+                            "rules": {
+                                "type": "array",
+                                "description": "Rules tried before the built-in ones unless given a lower priority",
+                                "items": {
+                                    "type": "object",
+                                    "properties": {
+                                        "pattern": {
+                                            "type": "string",
+                                            "description": "Regular expression matched against the URL"
+                                        },
+                                        "folder": {
+                                            "type": "string",
+                                            "description": "Folder for matching bookmarks, e.g. Development/Rust"
+                                        },
+                                        "priority": {
+                                            "type": "integer",
+                                            "description": "Higher priorities are tried first; built-in rules go up to 10",
+                                            "default": 100
+                                        },
+                                        "name": {
+                                            "type": "string",
+                                            "description": "Rule name for error messages (default: the folder)"
+                                        }
+                                    },
+                                    "required": ["pattern", "folder"]
+                                }
+                            },
+                            "builtin_rules": {
+                                "type": "boolean",
+                                "description": "Also apply the built-in rules (social, development, news, ...)",
+                                "default": true
+                            },
+                            "preserve_existing": {
+                                "type": "boolean",
+                                "description": "Keep each bookmark's current folder as a subfolder of its new one",
+                                "default": false
+                            }
+                        },
+                        "required": ["bookmarks"]
+                    }
+                },
+                {
+                    "name": "open_bookmark",
+                    "description": "Open the bookmark that best matches a query in the default browser, as the CLI's `open --first` does",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "query": {
+                                "type": "string",
+                                "description": "Search query"
+                            },
+                            "folder": {
+                                "type": "string",
+                                "description": "Only bookmarks in this folder or its subfolders"
+                            },
+                            "dry_run": {
+                                "type": "boolean",
+                                "description": "Only say what would be opened",
+                                "default": false
+                            }
+                        },
+                        "required": ["query"]
+                    }
                 }
             ]
         }))
@@ -257,6 +467,9 @@ impl McpServer {
             "list_browsers" => self.tool_list_browsers(arguments),
             "process_bookmarks" => self.tool_process_bookmarks(arguments),
             "generate_graph" => self.tool_generate_graph(arguments),
+            "preview_duplicates" => self.tool_preview_duplicates(arguments),
+            "organize_bookmarks" => self.tool_organize_bookmarks(arguments),
+            "open_bookmark" => self.tool_open_bookmark(arguments),
             _ => Err(anyhow::anyhow!("Unknown tool: {}", tool_name)),
         }
     }
@@ -0,0 +1,207 @@
+//! MCP resources: bookmark data a client can list and read by URI instead
+//! of getting it back as tool text.
+//!
+//! - `bookmark://<browser>/bookmarks`: a browser's bookmarks, read live
+//! - `bookmark://store/bookmarks`: the synced bookmark store
+//! - `bookmark://processed/<run>`: the file a `process` run wrote, for runs
+//!   still in the undo journal
+//! - `bookmark://backups/<file>`: dated exports written by `backup`
+//! - `bookmark://graphs/<file>`: graphs saved to the cache by `graph`
+//!
+//! Only resources that exist are listed, and `resources/read` only serves a
+//! URI that `resources/list` would list, so no other file can be reached.
+
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use serde_json::{Value, json};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::{McpServer, RpcError};
+use crate::browser::Browser;
+use crate::exporter::{ExportOptions, collect_browser_data};
+use crate::store::Store;
+
+/// Code MCP uses for a URI that names no resource
+const RESOURCE_NOT_FOUND: i32 = -32002;
+
+const BROWSERS: [&str; 4] = ["chrome", "firefox", "safari", "edge"];
+
+/// A listed resource and where its content comes from
+struct Resource {
+    uri: String,
+    name: String,
+    description: String,
+    mime_type: &'static str,
+    source: Source,
+}
+
+enum Source {
+    Browser(&'static str),
+    Store,
+    Journal(i64),
+    /// A file, gzipped when the flag is set
+    File(PathBuf, bool),
+}
+
+impl McpServer {
+    pub(super) fn handle_list_resources(&self) -> Result<Value> {
+        let resources: Vec<Value> = self
+            .resources()?
+            .into_iter()
+            .map(|resource| {
+                json!({
+                    "uri": resource.uri,
+                    "name": resource.name,
+                    "description": resource.description,
+                    "mimeType": resource.mime_type,
+                })
+            })
+            .collect();
+        Ok(json!({ "resources": resources }))
+    }
+
+    pub(super) fn handle_read_resource(&self, params: Option<Value>) -> Result<Value> {
+        let uri = params
+            .as_ref()
+            .and_then(|p| p["uri"].as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing uri"))?;
+        let resource = self
+            .resources()?
+            .into_iter()
+            .find(|resource| resource.uri == uri)
+            .ok_or_else(|| RpcError {
+                code: RESOURCE_NOT_FOUND,
+                message: format!("Resource not found: {}", uri),
+                data: Some(json!({ "uri": uri })),
+            })?;
+
+        let text = match &resource.source {
+            Source::Browser(browser) => serde_json::to_string_pretty(&collect_browser_data(
+                browser,
+                "bookmarks",
+                None,
+                &ExportOptions::default(),
+            )?)?,
+            Source::Store => serde_json::to_string_pretty(&Store::open_default()?.bookmarks()?)?,
+            Source::Journal(id) => Store::open_default()?
+                .journal()?
+                .into_iter()
+                .find(|entry| entry.id == *id)
+                .map(|entry| entry.written)
+                .ok_or_else(|| anyhow::anyhow!("Run {} is no longer in the journal", id))?,
+            Source::File(path, false) => fs::read_to_string(path)?,
+            Source::File(path, true) => {
+                let mut text = String::new();
+                GzDecoder::new(fs::File::open(path)?).read_to_string(&mut text)?;
+                text
+            }
+        };
+        Ok(json!({
+            "contents": [{
+                "uri": resource.uri,
+                "mimeType": resource.mime_type,
+                "text": text,
+            }]
+        }))
+    }
+
+    /// Every resource that currently exists
+    fn resources(&self) -> Result<Vec<Resource>> {
+        let mut resources = Vec::new();
+        for browser in BROWSERS {
+            let has_profiles = Browser::from_str(browser)
+                .and_then(|b| b.find_profiles(None))
+                .is_ok_and(|profiles| !profiles.is_empty());
+            if has_profiles {
+                resources.push(Resource {
+                    uri: format!("bookmark://{}/bookmarks", browser),
+                    name: format!("{} bookmarks", browser),
+                    description: format!("Bookmarks of every {} profile, read live", browser),
+                    mime_type: "application/json",
+                    source: Source::Browser(browser),
+                });
+            }
+        }
+
+        if let Some(store) = Store::open_if_synced()? {
+            resources.push(Resource {
+                uri: "bookmark://store/bookmarks".to_string(),
+                name: "Stored bookmarks".to_string(),
+                description: "Bookmarks in the synced store".to_string(),
+                mime_type: "application/json",
+                source: Source::Store,
+            });
+            for entry in store.journal()? {
+                if entry.operation != "process" || entry.undone_at.is_some() {
+                    continue;
+                }
+                resources.push(Resource {
+                    uri: format!("bookmark://processed/{}", entry.id),
+                    name: format!("Processed bookmarks (run {})", entry.id),
+                    description: format!(
+                        "{} as `process` wrote it at {}",
+                        entry.output.display(),
+                        entry.at.format("%Y-%m-%d %H:%M UTC")
+                    ),
+                    mime_type: mime_type(&entry.output),
+                    source: Source::Journal(entry.id),
+                });
+            }
+        }
+
+        let paths = crate::paths::get();
+        for backup in crate::backup::list(&paths.backups)? {
+            let file = file_name(&backup.path);
+            resources.push(Resource {
+                uri: format!("bookmark://backups/{}", file),
+                name: format!("Backup {}", backup.taken_at.format("%Y-%m-%d %H:%M")),
+                description: "Every browser's bookmarks as exported by `backup`".to_string(),
+                mime_type: "application/yaml",
+                source: Source::File(backup.path, true),
+            });
+        }
+
+        let mut graphs: Vec<PathBuf> = match fs::read_dir(paths.cache.join("graphs")) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        // Names carry their timestamp, so this puts the newest first
+        graphs.sort_by(|a, b| b.cmp(a));
+        for path in graphs {
+            let file = file_name(&path);
+            resources.push(Resource {
+                uri: format!("bookmark://graphs/{}", file),
+                name: format!("Graph {}", file),
+                description: "A knowledge graph saved by `graph`".to_string(),
+                mime_type: mime_type(&path),
+                source: Source::File(path, false),
+            });
+        }
+        Ok(resources)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => "application/json",
+        Some("html") => "text/html",
+        Some("js") => "text/javascript",
+        Some("csv") => "text/csv",
+        Some("md") => "text/markdown",
+        Some("yaml" | "yml") => "application/yaml",
+        _ => "text/plain",
+    }
+}
@@ -0,0 +1,494 @@
+//! `bookmark-mcp --self-test`: every tool called in-process, through the
+//! same JSON-RPC handling as stdin, with valid and invalid arguments.
+//!
+//! Search and `open_bookmark`, always as a dry run, use a bookmark store
+//! seeded with fixture bookmarks; `process_bookmarks`, `preview_duplicates`
+//! and `organize_bookmarks` are given them directly.
+//! `export_bookmarks`, `list_browsers` and `generate_graph` read the
+//! installed browsers, read-only, so a machine without any still passes.
+//! Resources are read from the fixture store and a recorded `process` run.
+
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use serde_json::{Value, json};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::Path;
+
+use super::{McpResponse, McpServer, PROTOCOL_VERSIONS};
+use crate::exporter::BrowserData;
+use crate::journal::JournalEntry;
+use crate::paths::{self, PathOverrides};
+use crate::store::Store;
+
+/// Code of a JSON-RPC request that is not valid JSON
+const PARSE_ERROR: i32 = -32700;
+/// Code of a request whose parameters are unacceptable
+const INVALID_PARAMS: i32 = -32602;
+/// Code of a URI that names no resource
+const RESOURCE_NOT_FOUND: i32 = -32002;
+/// Code of every method or tool failure
+const INTERNAL_ERROR: i32 = -32603;
+
+/// One check of a self-test
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    /// Why it failed; `None` when it passed
+    pub failure: Option<String>,
+}
+
+/// The outcome of [`McpServer::self_test`]
+#[derive(Debug, Clone, Default)]
+pub struct SelfTestReport {
+    pub checks: Vec<Check>,
+}
+
+impl SelfTestReport {
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.failure.is_none())
+    }
+
+    fn record(&mut self, name: &str, outcome: Result<(), String>) {
+        self.checks.push(Check {
+            name: name.to_string(),
+            failure: outcome.err(),
+        });
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            match &check.failure {
+                None => writeln!(f, "PASS {}", check.name)?,
+                Some(failure) => writeln!(f, "FAIL {}: {}", check.name, failure)?,
+            }
+        }
+        let failed = self.checks.iter().filter(|c| c.failure.is_some()).count();
+        write!(
+            f,
+            "{} passed, {} failed",
+            self.checks.len() - failed,
+            failed
+        )
+    }
+}
+
+impl McpServer {
+    /// Exercise every tool, keeping the fixture store and any other files
+    /// in `dir`. This fixes the process's paths there, so it must run
+    /// before anything else reads them.
+    pub fn self_test(&self, dir: &Path) -> Result<SelfTestReport> {
+        let store = dir.join("store.db");
+        let paths = paths::init(&PathOverrides {
+            config: Some(dir.join("config.toml")),
+            store: Some(store.clone()),
+            cache: Some(dir.join("cache")),
+            backups: Some(dir.join("backups")),
+            archives: Some(dir.join("archives")),
+            snapshots: Some(dir.join("snapshots")),
+        });
+        if paths.store != store {
+            return Err(anyhow!(
+                "The bookmark store is already open at {}; run the self-test in a fresh process",
+                paths.store.display()
+            ));
+        }
+        let mut fixture_store = Store::open_default()?;
+        fixture_store.ingest(&BrowserData {
+            browser: "fixture".to_string(),
+            profile: "self-test".to_string(),
+            export_date: Utc::now(),
+            bookmarks: Some(serde_json::from_value(fixture())?),
+            history: None,
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        })?;
+        let run = fixture_store.record_journal(&JournalEntry {
+            id: 0,
+            operation: "process".to_string(),
+            at: Utc::now(),
+            output: dir.join("processed.yaml"),
+            previous: None,
+            written: "- browser: fixture\n".to_string(),
+            input: Vec::new(),
+            settings: Default::default(),
+            undone_at: None,
+        })?;
+        drop(fixture_store);
+
+        let mut report = SelfTestReport::default();
+        let mut called = BTreeSet::new();
+        let mut call = |tool: &str, arguments: Value| {
+            called.insert(tool.to_string());
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": { "name": tool, "arguments": arguments },
+            });
+            self.handle_line(&request.to_string())
+        };
+
+        let initialize = |version: &str| {
+            self.handle_line(
+                &json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "initialize",
+                    "params": { "protocolVersion": version, "capabilities": {} },
+                })
+                .to_string(),
+            )
+        };
+        report.record(
+            "initialize with the oldest supported version",
+            succeeds(initialize("2024-11-05"), |r| {
+                r["protocolVersion"] == "2024-11-05"
+                    && r["serverInfo"]["name"] == "bookmark-mcp"
+                    && r["capabilities"]["tools"].is_object()
+            }),
+        );
+        report.record(
+            "initialize from a newer client",
+            succeeds(initialize("2099-01-01"), |r| {
+                r["protocolVersion"] == PROTOCOL_VERSIONS[0]
+            }),
+        );
+        report.record(
+            "initialize with an unsupported version",
+            fails(initialize("1.0"), INVALID_PARAMS),
+        );
+        report.record(
+            "notifications/initialized gets no response",
+            match self.handle_line(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#) {
+                None => Ok(()),
+                Some(_) => Err("answered a notification".to_string()),
+            },
+        );
+        report.record(
+            "malformed JSON is a parse error",
+            fails(self.handle_line("{\"jsonrpc\":"), PARSE_ERROR),
+        );
+        report.record(
+            "unknown method",
+            fails(
+                self.handle_line(r#"{"jsonrpc":"2.0","id":1,"method":"prompts/list"}"#),
+                INTERNAL_ERROR,
+            ),
+        );
+        report.record(
+            "unknown tool",
+            fails(call("delete_everything", json!({})), INTERNAL_ERROR),
+        );
+
+        report.record(
+            "export_bookmarks from all browsers",
+            succeeds(call("export_bookmarks", json!({ "browser": "all" })), |r| {
+                serde_json::from_str::<Vec<BrowserData>>(&text(r)).is_ok()
+            }),
+        );
+        report.record(
+            "export_bookmarks without a browser",
+            fails(call("export_bookmarks", json!({})), INTERNAL_ERROR),
+        );
+        report.record(
+            "export_bookmarks with an unknown field",
+            fails(
+                call(
+                    "export_bookmarks",
+                    json!({ "browser": "all", "fields": ["colour"] }),
+                ),
+                INTERNAL_ERROR,
+            ),
+        );
+
+        report.record(
+            "search_bookmarks finds a fixture bookmark",
+            succeeds(call("search_bookmarks", json!({ "query": "rust" })), |r| {
+                text(r).contains("The Rust Programming Language")
+            }),
+        );
+        report.record(
+            "search_bookmarks without a query",
+            fails(call("search_bookmarks", json!({})), INTERNAL_ERROR),
+        );
+        report.record(
+            "search_bookmarks with an unknown media type",
+            fails(
+                call(
+                    "search_bookmarks",
+                    json!({ "query": "rust", "media_type": "hologram" }),
+                ),
+                INTERNAL_ERROR,
+            ),
+        );
+
+        report.record(
+            "list_browsers",
+            succeeds(call("list_browsers", json!({})), |r| {
+                r["content"].is_array()
+            }),
+        );
+        report.record(
+            "list_browsers with an unknown browser",
+            fails(
+                call("list_browsers", json!({ "browser": "netscape" })),
+                INTERNAL_ERROR,
+            ),
+        );
+
+        report.record(
+            "process_bookmarks merges the fixture duplicate",
+            succeeds(
+                call("process_bookmarks", json!({ "bookmarks": fixture() })),
+                |r| r["processed_bookmarks"].as_array().map(Vec::len) == Some(3),
+            ),
+        );
+        report.record(
+            "process_bookmarks without bookmarks",
+            fails(call("process_bookmarks", json!({})), INTERNAL_ERROR),
+        );
+        report.record(
+            "process_bookmarks with a malformed bookmark",
+            fails(
+                call(
+                    "process_bookmarks",
+                    json!({ "bookmarks": [{ "title": 1 }] }),
+                ),
+                INTERNAL_ERROR,
+            ),
+        );
+
+        report.record(
+            "generate_graph",
+            succeeds(call("generate_graph", json!({ "format": "json" })), |r| {
+                serde_json::from_str::<Value>(&text(r)).is_ok()
+            }),
+        );
+        report.record(
+            "generate_graph with an unknown format",
+            fails(
+                call("generate_graph", json!({ "format": "png" })),
+                INTERNAL_ERROR,
+            ),
+        );
+
+        report.record(
+            "preview_duplicates pairs the fixture duplicate",
+            succeeds(
+                call("preview_duplicates", json!({ "bookmarks": fixture() })),
+                |r| r["candidates"][0]["normalized_url"] == "http://doc.rust-lang.org/book",
+            ),
+        );
+        report.record(
+            "preview_duplicates with a malformed bookmark",
+            fails(
+                call("preview_duplicates", json!({ "bookmarks": [{ "url": 5 }] })),
+                INTERNAL_ERROR,
+            ),
+        );
+
+        let rules = json!([{ "pattern": r"rust-lang\.org|crates\.io", "folder": "Rust" }]);
+        report.record(
+            "organize_bookmarks with an inline rule",
+            succeeds(
+                call(
+                    "organize_bookmarks",
+                    json!({ "bookmarks": fixture(), "rules": rules }),
+                ),
+                |r| {
+                    let folders: Vec<&str> = r["organized_bookmarks"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|b| b["folder"].as_str())
+                        .collect();
+                    folders.len() == 4 && folders.iter().filter(|&&f| f == "Rust").count() == 3
+                },
+            ),
+        );
+        report.record(
+            "organize_bookmarks with an invalid pattern",
+            fails(
+                call(
+                    "organize_bookmarks",
+                    json!({ "bookmarks": fixture(), "rules": [{ "pattern": "(", "folder": "X" }] }),
+                ),
+                INTERNAL_ERROR,
+            ),
+        );
+        report.record(
+            "organize_bookmarks with a rule missing its folder",
+            fails(
+                call(
+                    "organize_bookmarks",
+                    json!({ "bookmarks": fixture(), "rules": [{ "pattern": "rust" }] }),
+                ),
+                INTERNAL_ERROR,
+            ),
+        );
+
+        // Never without dry_run: a self-test must not open tabs
+        report.record(
+            "open_bookmark picks the best fixture match",
+            succeeds(
+                call(
+                    "open_bookmark",
+                    json!({ "query": "crates", "dry_run": true }),
+                ),
+                |r| r["opened"] == false && r["bookmark"]["url"] == "https://crates.io/",
+            ),
+        );
+        report.record(
+            "open_bookmark without a query",
+            fails(
+                call("open_bookmark", json!({ "dry_run": true })),
+                INTERNAL_ERROR,
+            ),
+        );
+
+        let request = |method: &str, params: Value| {
+            self.handle_line(
+                &json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params })
+                    .to_string(),
+            )
+        };
+        report.record(
+            "resources/list includes the store and the process run",
+            succeeds(request("resources/list", json!({})), |r| {
+                let uris: Vec<&str> = r["resources"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|resource| resource["uri"].as_str())
+                    .collect();
+                uris.contains(&"bookmark://store/bookmarks")
+                    && uris.contains(&format!("bookmark://processed/{}", run).as_str())
+            }),
+        );
+        report.record(
+            "resources/read of the store",
+            succeeds(
+                request(
+                    "resources/read",
+                    json!({ "uri": "bookmark://store/bookmarks" }),
+                ),
+                |r| {
+                    r["contents"][0]["text"]
+                        .as_str()
+                        .is_some_and(|text| text.contains("https://crates.io/"))
+                },
+            ),
+        );
+        report.record(
+            "resources/read of a process run",
+            succeeds(
+                request(
+                    "resources/read",
+                    json!({ "uri": format!("bookmark://processed/{}", run) }),
+                ),
+                |r| r["contents"][0]["mimeType"] == "application/yaml",
+            ),
+        );
+        report.record(
+            "resources/read of a file outside the resources",
+            fails(
+                request(
+                    "resources/read",
+                    json!({ "uri": "bookmark://graphs/../../config.toml" }),
+                ),
+                RESOURCE_NOT_FOUND,
+            ),
+        );
+
+        let tools = self.handle_line(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#);
+        let listed: BTreeSet<String> = tools
+            .and_then(|response| response.result)
+            .iter()
+            .flat_map(|r| r["tools"].as_array().cloned().unwrap_or_default())
+            .filter_map(|tool| tool["name"].as_str().map(str::to_string))
+            .collect();
+        called.remove("delete_everything");
+        let untested: Vec<&String> = listed.difference(&called).collect();
+        report.record(
+            "tools/list names only tested tools",
+            match untested.is_empty() && !listed.is_empty() {
+                true => Ok(()),
+                false => Err(format!("untested: {:?}", untested)),
+            },
+        );
+
+        Ok(report)
+    }
+}
+
+/// Four bookmarks, two of them the same page
+fn fixture() -> Value {
+    json!([
+        {
+            "id": "1",
+            "title": "The Rust Programming Language",
+            "url": "https://doc.rust-lang.org/book/",
+            "folder": "Development/Rust",
+        },
+        {
+            "id": "2",
+            "title": "The Book",
+            "url": "https://www.doc.rust-lang.org/book#intro",
+            "folder": "Reading",
+        },
+        {
+            "id": "3",
+            "title": "crates.io",
+            "url": "https://crates.io/",
+            "folder": "Development/Rust",
+        },
+        {
+            "id": "4",
+            "title": "Hacker News",
+            "url": "https://news.ycombinator.com/",
+            "folder": "News",
+        },
+    ])
+}
+
+fn text(result: &Value) -> String {
+    result["content"][0]["text"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn succeeds(
+    response: Option<McpResponse>,
+    expected: impl Fn(&Value) -> bool,
+) -> Result<(), String> {
+    let response = response.ok_or("no response")?;
+    match (response.result, response.error) {
+        (_, Some(error)) => Err(format!("error {}: {}", error.code, error.message)),
+        (Some(result), None) if expected(&result) => Ok(()),
+        (Some(result), None) => {
+            let result = result.to_string();
+            let shown: String = result.chars().take(200).collect();
+            Err(format!("unexpected result {}", shown))
+        }
+        (None, None) => Err("empty response".to_string()),
+    }
+}
+
+fn fails(response: Option<McpResponse>, code: i32) -> Result<(), String> {
+    match response.ok_or("no response")?.error {
+        Some(error) if error.code == code => Ok(()),
+        Some(error) => Err(format!(
+            "error code {} instead of {}: {}",
+            error.code, code, error.message
+        )),
+        None => Err("succeeded; expected an error".to_string()),
+    }
+}
@@ -1,35 +1,84 @@
 use anyhow::Result;
-use serde_json::{json, Value};
-use std::path::PathBuf;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use url::Url;
 
 use crate::browser::Browser;
-use crate::exporter::{export_data, Bookmark};
+use crate::deduplication::{BookmarkDeduplicator, DeduplicationConfig, find_potential_duplicates};
+use crate::exporter::{Bookmark, ExportOptions, FieldSelection, collect_browser_data};
 use crate::graph::{GraphBuilder, GraphConfig};
+use crate::media::MediaType;
+use crate::organization::{BookmarkOrganizer, OrganizationConfig, OrganizationRule};
 use crate::processor::{BookmarkProcessor, ProcessingConfig};
-use crate::search::{search_bookmarks_internal, SearchOptions};
+use crate::search::{
+    MatchedField, OpenOptions, SearchOptions, SortOrder, open_matches, search_bookmarks_internal,
+    suggestions_for,
+};
 
 use super::McpServer;
 
+/// An `organize_bookmarks` rule; unnamed rules are named after their folder
+#[derive(Deserialize)]
+struct InlineRule {
+    #[serde(default)]
+    name: Option<String>,
+    pattern: String,
+    folder: String,
+    #[serde(default = "inline_rule_priority")]
+    priority: i32,
+}
+
+/// Above every built-in rule, so a rule given inline wins by default
+fn inline_rule_priority() -> i32 {
+    100
+}
+
 impl McpServer {
     pub(super) fn tool_export_bookmarks(&self, args: Value) -> Result<Value> {
         let browser = args["browser"]
             .as_str()
             .ok_or_else(|| anyhow::anyhow!("Missing browser"))?;
-        let data_type = args["data_type"].as_str().unwrap_or("bookmarks");
-
-        let temp_dir = PathBuf::from("/tmp/bookmark_mcp");
-        std::fs::create_dir_all(&temp_dir)?;
-
-        let output_file = temp_dir.join(format!("{}-{}.yaml", browser, data_type));
-        export_data(browser, data_type, Some(output_file.clone()), None)?;
+        // The exporter's `all` is bookmarks and history; passwords stay off
+        let data_type = match args["data_type"].as_str().unwrap_or("bookmarks") {
+            "both" => "all",
+            data_type @ ("bookmarks" | "history") => data_type,
+            other => return Err(anyhow::anyhow!("Invalid data_type: {}", other)),
+        };
+        let fields = match &args["fields"] {
+            Value::String(spec) => Some(spec.parse::<FieldSelection>()?),
+            Value::Array(names) => Some(
+                names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(",")
+                    .parse::<FieldSelection>()?,
+            ),
+            _ => None,
+        };
 
-        let content = std::fs::read_to_string(&output_file)?;
-        let data: Vec<crate::exporter::BrowserData> = serde_yaml::from_str(&content)?;
+        let data = match browser {
+            // Browsers without profiles are skipped, as `list_browsers` does
+            "all" => ["chrome", "firefox", "safari", "edge"]
+                .into_iter()
+                .filter_map(|name| {
+                    collect_browser_data(name, data_type, None, &ExportOptions::default()).ok()
+                })
+                .flatten()
+                .collect(),
+            browser => collect_browser_data(browser, data_type, None, &ExportOptions::default())?,
+        };
+        let text = match &fields {
+            Some(fields) => serde_json::to_string_pretty(&fields.project(&data)?)?,
+            None => serde_json::to_string_pretty(&data)?,
+        };
 
         Ok(json!({
             "content": [{
                 "type": "text",
-                "text": serde_json::to_string_pretty(&data)?
+                "text": text
             }]
         }))
     }
@@ -41,18 +90,56 @@ impl McpServer {
         let title_only = args["title_only"].as_bool().unwrap_or(false);
         let url_only = args["url_only"].as_bool().unwrap_or(false);
         let limit = args["limit"].as_u64().unwrap_or(20) as usize;
+        let media_type = args["media_type"]
+            .as_str()
+            .map(MediaType::from_str)
+            .transpose()?;
+        let sort = args["sort"]
+            .as_str()
+            .map(SortOrder::from_str)
+            .transpose()?
+            .unwrap_or_default();
 
         let options = SearchOptions {
             title_only,
             url_only,
             limit,
+            media_type,
+            sort,
         };
 
         let results = search_bookmarks_internal(query, &options)?;
+        if results.is_empty() {
+            let text = match suggestions_for(query).to_text() {
+                Some(text) => format!("No bookmarks found matching '{}'.\n{}", query, text),
+                None => format!("No bookmarks found matching '{}'.", query),
+            };
+            return Ok(json!({
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }]
+            }));
+        }
 
         let formatted_results: Vec<String> = results
             .iter()
-            .map(|b| format!("{} - {}", b.title, b.url.as_deref().unwrap_or("N/A")))
+            .map(|r| {
+                format!(
+                    "{} - {} [{}; {} match: {}]",
+                    r.bookmark.title,
+                    r.bookmark.url.as_deref().unwrap_or("N/A"),
+                    r.source_browser,
+                    match r.matched_field {
+                        MatchedField::Title => "title",
+                        MatchedField::Url => "url",
+                        MatchedField::Tags => "tag",
+                        MatchedField::Folder => "folder",
+                        MatchedField::Description => "description",
+                    },
+                    r.snippet
+                )
+            })
             .collect();
 
         Ok(json!({
@@ -67,20 +154,17 @@ impl McpServer {
         let mut output = Vec::new();
 
         let browsers = if let Some(browser_name) = args["browser"].as_str() {
+            Browser::from_str(browser_name)?;
             vec![browser_name]
         } else {
             vec!["chrome", "firefox", "safari", "edge"]
         };
 
         for browser_name in browsers {
-            if let Ok(browser) = Browser::from_str(browser_name) {
-                if let Ok(profiles) = browser.find_profiles(None) {
-                    output.push(format!(
-                        "{}: {} profile(s)",
-                        browser_name,
-                        profiles.len()
-                    ));
-                }
+            if let Ok(browser) = Browser::from_str(browser_name)
+                && let Ok(profiles) = browser.find_profiles(None)
+            {
+                output.push(format!("{}: {} profile(s)", browser_name, profiles.len()));
             }
         }
 
@@ -117,6 +201,7 @@ impl McpServer {
             organization_config: crate::organization::OrganizationConfig::default(),
             dry_run: false,
             backup_original: false,
+            ..Default::default()
         };
 
         let processor = BookmarkProcessor::new(config);
@@ -156,8 +241,13 @@ impl McpServer {
             "dot" => crate::graph::formats::to_dot(&graph),
             "json" => crate::graph::formats::to_json(&graph),
             "gexf" => crate::graph::formats::to_gexf(&graph),
-            "html" => crate::graph::formats::to_html(&graph),
-            _ => return Err(anyhow::anyhow!("Invalid format: {}. Use dot, json, gexf, or html", format)),
+            "html" => crate::graph::formats::to_html(&graph, &Default::default()),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Invalid format: {}. Use dot, json, gexf, or html",
+                    format
+                ));
+            }
         };
 
         Ok(json!({
@@ -173,4 +263,185 @@ impl McpServer {
             }
         }))
     }
+
+    pub(super) fn tool_preview_duplicates(&self, args: Value) -> Result<Value> {
+        let limit = args["limit"].as_u64().unwrap_or(50) as usize;
+        let bookmarks: Vec<Bookmark> = match args["bookmarks"].as_array() {
+            Some(bookmarks) => serde_json::from_value(json!(bookmarks))?,
+            None => {
+                let browser = args["browser"].as_str().unwrap_or("all");
+                crate::exporter::load_browser_data(browser, "bookmarks")?.0
+            }
+        };
+        // Similarity needs a URL that parses; folders and broken URLs are skipped
+        let bookmarks: Vec<Bookmark> = crate::exporter::flatten_bookmarks(&bookmarks)
+            .into_iter()
+            .filter(|b| b.url.as_deref().is_some_and(|u| Url::parse(u).is_ok()))
+            .collect();
+
+        let mut pairs = find_potential_duplicates(&bookmarks)?;
+        pairs.sort_by(|a, b| b.2.total_cmp(&a.2));
+        let total = pairs.len();
+        pairs.truncate(limit);
+
+        let deduplicator = BookmarkDeduplicator::new(DeduplicationConfig::default());
+        let normalize = |bookmark: &Bookmark| {
+            let url = bookmark.url.as_deref().unwrap_or_default();
+            deduplicator.normalize_url(url).ok()
+        };
+        let summary = |bookmark: &Bookmark| {
+            json!({
+                "id": bookmark.id,
+                "title": bookmark.title,
+                "url": bookmark.url,
+                "folder": bookmark.folder
+            })
+        };
+        let mut lines = vec![format!(
+            "{} potential duplicate pair(s) among {} bookmarks",
+            total,
+            bookmarks.len()
+        )];
+        let candidates: Vec<Value> = pairs
+            .iter()
+            .map(|(first, second, similarity)| {
+                let (key1, key2) = (normalize(first), normalize(second));
+                // Only a shared key is one `process` would merge on
+                let normalized_url = key1.filter(|key| Some(key) == key2.as_ref());
+                lines.push(format!(
+                    "{:.0}% {} | {} -> {}",
+                    similarity * 100.0,
+                    first.url.as_deref().unwrap_or_default(),
+                    second.url.as_deref().unwrap_or_default(),
+                    normalized_url.as_deref().unwrap_or("(kept apart)")
+                ));
+                json!({
+                    "first": summary(first),
+                    "second": summary(second),
+                    "similarity": similarity,
+                    "normalized_url": normalized_url,
+                })
+            })
+            .collect();
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": lines.join("\n")
+            }],
+            "candidates": candidates,
+            "total": total
+        }))
+    }
+
+    pub(super) fn tool_organize_bookmarks(&self, args: Value) -> Result<Value> {
+        let bookmarks: Vec<Bookmark> = serde_json::from_value(
+            args.get("bookmarks")
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Missing bookmarks array"))?,
+        )?;
+        let rules: Vec<InlineRule> = match args.get("rules") {
+            Some(rules) => serde_json::from_value(rules.clone())?,
+            None => Vec::new(),
+        };
+        let builtin_rules = args["builtin_rules"].as_bool().unwrap_or(true);
+        let preserve_existing = args["preserve_existing"].as_bool().unwrap_or(false);
+
+        let defaults = OrganizationConfig::default();
+        let mut custom_rules = match builtin_rules {
+            true => defaults.custom_rules.clone(),
+            false => Vec::new(),
+        };
+        custom_rules.extend(rules.into_iter().map(|rule| OrganizationRule {
+            name: rule.name.unwrap_or_else(|| rule.folder.clone()),
+            pattern: rule.pattern,
+            folder: rule.folder,
+            priority: rule.priority,
+        }));
+        let organizer = BookmarkOrganizer::new(OrganizationConfig {
+            custom_rules,
+            preserve_existing,
+            ..defaults
+        });
+        // A rule the caller wrote should be fixed, not silently skipped
+        if !organizer.rule_errors().is_empty() {
+            return Err(anyhow::anyhow!(
+                "Invalid rules: {}",
+                organizer.rule_errors().join("; ")
+            ));
+        }
+
+        let organized = organizer.organize(crate::exporter::flatten_bookmarks(&bookmarks))?;
+        let mut folders: BTreeMap<&str, usize> = BTreeMap::new();
+        for bookmark in &organized {
+            *folders
+                .entry(bookmark.folder.as_deref().unwrap_or_default())
+                .or_default() += 1;
+        }
+        let mut lines = vec![format!(
+            "Organized {} bookmarks into {} folders:",
+            organized.len(),
+            folders.len()
+        )];
+        lines.extend(
+            folders
+                .iter()
+                .map(|(folder, count)| format!("- {} ({})", folder, count)),
+        );
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": lines.join("\n")
+            }],
+            "organized_bookmarks": organized
+        }))
+    }
+
+    pub(super) fn tool_open_bookmark(&self, args: Value) -> Result<Value> {
+        let query = args["query"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing query"))?;
+        let dry_run = args["dry_run"].as_bool().unwrap_or(false);
+        let options = OpenOptions {
+            first: true,
+            folder: args["folder"].as_str().map(str::to_string),
+            ..Default::default()
+        };
+
+        let matches = open_matches(query, &options);
+        let Some((bookmark, browser)) = matches.first() else {
+            let text = match suggestions_for(query).to_text() {
+                Some(text) => format!("No bookmarks found matching '{}'.\n{}", query, text),
+                None => format!("No bookmarks found matching '{}'.", query),
+            };
+            return Ok(json!({
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "opened": false
+            }));
+        };
+        let url = bookmark
+            .url
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("Best match has no URL"))?;
+
+        let text = match dry_run {
+            true => format!("Would open: {} - {} [{}]", bookmark.title, url, browser),
+            false => {
+                open::that(url).map_err(|e| anyhow::anyhow!("Failed to open {}: {}", url, e))?;
+                format!("Opened: {} - {} [{}]", bookmark.title, url, browser)
+            }
+        };
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!("{} (best of {} matches)", text, matches.len())
+            }],
+            "bookmark": bookmark,
+            "opened": !dry_run
+        }))
+    }
 }
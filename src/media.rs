@@ -0,0 +1,185 @@
+//! Media type detection.
+//!
+//! The enrich stage tags bookmarks as PDFs, videos, podcasts or images from
+//! their URL (file extension or a known host such as YouTube), and optionally
+//! from the `Content-Type` a HEAD request returns. The `media` organization
+//! strategy and `search --media` key on the result.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    Pdf,
+    Video,
+    Podcast,
+    Image,
+}
+
+const VIDEO_HOSTS: &[&str] = &[
+    "youtube.com",
+    "youtu.be",
+    "vimeo.com",
+    "dailymotion.com",
+    "twitch.tv",
+];
+const PODCAST_HOSTS: &[&str] = &[
+    "podcasts.apple.com",
+    "overcast.fm",
+    "pca.st",
+    "podbean.com",
+    "anchor.fm",
+    "castbox.fm",
+];
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm", "mov", "mkv", "avi", "m4v"];
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "m4a", "ogg", "opus", "wav", "flac"];
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "avif", "bmp"];
+
+impl MediaType {
+    /// Folder the `media` organization strategy files this type under
+    pub fn folder(self) -> &'static str {
+        match self {
+            MediaType::Pdf => "Papers",
+            MediaType::Video => "Videos",
+            MediaType::Podcast => "Podcasts",
+            MediaType::Image => "Images",
+        }
+    }
+
+    /// Detect from the URL alone: file extension, then known hosts
+    pub fn from_url(url: &str) -> Option<Self> {
+        let parsed = Url::parse(url).ok()?;
+        let path = parsed.path().to_lowercase();
+        if let Some((_, extension)) = path.rsplit_once('.') {
+            if extension == "pdf" {
+                return Some(MediaType::Pdf);
+            }
+            if VIDEO_EXTENSIONS.contains(&extension) {
+                return Some(MediaType::Video);
+            }
+            if AUDIO_EXTENSIONS.contains(&extension) {
+                return Some(MediaType::Podcast);
+            }
+            if IMAGE_EXTENSIONS.contains(&extension) {
+                return Some(MediaType::Image);
+            }
+        }
+
+        let host = parsed.host_str()?.to_lowercase();
+        let on = |hosts: &[&str]| {
+            hosts
+                .iter()
+                .any(|h| host == *h || host.ends_with(&format!(".{}", h)))
+        };
+        if on(VIDEO_HOSTS) {
+            Some(MediaType::Video)
+        } else if on(PODCAST_HOSTS) || (host == "open.spotify.com" && path.starts_with("/episode"))
+        {
+            Some(MediaType::Podcast)
+        } else {
+            None
+        }
+    }
+
+    /// Detect from a `Content-Type` header value
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        match mime.split_once('/')? {
+            (_, "pdf") => Some(MediaType::Pdf),
+            ("video", _) => Some(MediaType::Video),
+            ("audio", _) => Some(MediaType::Podcast),
+            ("image", _) => Some(MediaType::Image),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MediaType::Pdf => "pdf",
+            MediaType::Video => "video",
+            MediaType::Podcast => "podcast",
+            MediaType::Image => "image",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for MediaType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "pdf" => Ok(MediaType::Pdf),
+            "video" => Ok(MediaType::Video),
+            "podcast" | "audio" => Ok(MediaType::Podcast),
+            "image" => Ok(MediaType::Image),
+            _ => Err(anyhow!(
+                "Unknown media type: {} (expected pdf, video, podcast or image)",
+                s
+            )),
+        }
+    }
+}
+
+/// Ask the server what `url` serves with a HEAD request
+pub fn probe_content_type(url: &str) -> Result<Option<MediaType>> {
+    let response = ureq::AgentBuilder::new()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .head(url)
+        .call()
+        .map_err(|e| anyhow!("HEAD {} failed: {}", url, e))?;
+    Ok(response
+        .header("Content-Type")
+        .and_then(MediaType::from_content_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_from_url() {
+        let detect = MediaType::from_url;
+        assert_eq!(
+            detect("https://arxiv.org/pdf/1706.03762.PDF"),
+            Some(MediaType::Pdf)
+        );
+        assert_eq!(
+            detect("https://www.youtube.com/watch?v=abc"),
+            Some(MediaType::Video)
+        );
+        assert_eq!(detect("https://vimeo.com/12345"), Some(MediaType::Video));
+        assert_eq!(
+            detect("https://open.spotify.com/episode/xyz"),
+            Some(MediaType::Podcast)
+        );
+        assert_eq!(detect("https://open.spotify.com/album/xyz"), None);
+        assert_eq!(
+            detect("https://example.com/cat.webp"),
+            Some(MediaType::Image)
+        );
+        assert_eq!(detect("https://notyoutube.com/"), None);
+        assert_eq!(detect("https://docs.rs/serde"), None);
+    }
+
+    #[test]
+    fn test_detect_from_content_type() {
+        let detect = MediaType::from_content_type;
+        assert_eq!(detect("application/pdf"), Some(MediaType::Pdf));
+        assert_eq!(detect("audio/mpeg"), Some(MediaType::Podcast));
+        assert_eq!(detect("image/png; charset=binary"), Some(MediaType::Image));
+        assert_eq!(detect("text/html; charset=utf-8"), None);
+    }
+}
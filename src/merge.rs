@@ -0,0 +1,474 @@
+//! Merging bookmarks from several machines.
+//!
+//! Stores moved between machines (by hand, on a shared drive or through
+//! git) are merged by URL with `sync --from`. [`merge`] adds the bookmarks
+//! the other store has and this one lacks, and leaves those both agree on
+//! alone. A URL filed in
+//! a different folder, or tagged differently, on each machine is a
+//! [`Conflict`]; conflicts go to a YAML file where a person picks a side (or
+//! writes the folder and tags to use), and [`resolve`] applies the choices.
+//! The outcome depends only on the two stores, so merging the same pair
+//! again writes the same file. Folders are compared with their roots
+//! normalized, as `sync --between` does, so Chrome on one machine and
+//! Firefox on the other don't conflict over root names. Titles differing is
+//! not a conflict (this store's title stays), and deletions don't carry
+//! over: a store can't tell a bookmark deleted on one machine from one never
+//! synced there.
+//!
+//! Export files need no store: [`merge_exports`] concatenates them and
+//! removes duplicates with the configured merge strategy, for the `merge`
+//! command.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::deduplication::{BookmarkDeduplicator, DeduplicationConfig};
+use crate::exporter::{
+    Bookmark, BrowserData, HistoryEntry, Provenance, UrlEntry, flatten_bookmarks,
+};
+use crate::reconcile::canonical_folder;
+use crate::store::Store;
+
+/// Comment written at the top of a conflicts file
+const HEADER: &str = "\
+# Bookmarks filed or tagged differently in the two stores.
+# For each, set `keep` to `local` or `other`, or add `folder` and/or `tags`
+# to use instead (the rest comes from `keep`, else from local), then run
+# `bookmark resolve` on this file. Entries left as they are stay unresolved.
+";
+
+/// Which store's version of a conflict to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Keep {
+    Local,
+    Other,
+}
+
+/// One store's folder and tags for a URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Version {
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A URL both stores hold with a different folder or tags
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conflict {
+    pub url: String,
+    pub title: String,
+    pub local: Version,
+    pub other: Version,
+    /// Filled in by hand
+    #[serde(default)]
+    pub keep: Option<Keep>,
+    /// A folder to use instead of either side's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
+    /// Tags to use instead of either side's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
+
+impl Conflict {
+    /// The folder and tags decided on, or `None` while undecided
+    pub fn decision(&self) -> Option<Version> {
+        let base = match self.keep {
+            Some(Keep::Local) => &self.local,
+            Some(Keep::Other) => &self.other,
+            None if self.folder.is_some() || self.tags.is_some() => &self.local,
+            None => return None,
+        };
+        Some(Version {
+            folder: self.folder.clone().or_else(|| base.folder.clone()),
+            tags: self.tags.clone().unwrap_or_else(|| base.tags.clone()),
+        })
+    }
+}
+
+/// The conflicts of one merge, as written for editing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictsFile {
+    /// The store that was merged in
+    pub other: PathBuf,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl ConflictsFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        serde_yaml::from_str(&content)
+            .with_context(|| format!("Could not parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = format!("{}{}", HEADER, serde_yaml::to_string(self)?);
+        fs::write(path, content).with_context(|| format!("Could not write {}", path.display()))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MergeSummary {
+    /// Bookmarks only the other store had, now added
+    pub added: usize,
+    /// Bookmarks both stores agree on
+    pub unchanged: usize,
+    /// Ordered by URL
+    pub conflicts: Vec<Conflict>,
+}
+
+#[derive(Debug, Default)]
+pub struct ResolveSummary {
+    pub applied: usize,
+    /// Conflicts with no decision yet
+    pub unresolved: usize,
+    /// Decided conflicts whose URL is no longer in the store
+    pub missing: usize,
+}
+
+/// Merge the store at `other_path` into `local`. The added bookmarks are
+/// recorded with the other store's file name as their source, like an
+/// import, so browser syncs leave them alone.
+pub fn merge(local: &mut Store, other_path: &Path) -> Result<MergeSummary> {
+    if !other_path.exists() {
+        return Err(anyhow::anyhow!("No store at {}", other_path.display()));
+    }
+    let other = Store::open(other_path)?.bookmarks()?;
+    let conflicts = find_conflicts(&local.bookmarks()?, &other);
+
+    let data = BrowserData {
+        browser: "store".to_string(),
+        profile: other_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        export_date: chrono::Utc::now(),
+        bookmarks: Some(other),
+        history: None,
+        passwords: None,
+        aliases: None,
+        profile_name: None,
+        profile_email: None,
+        provenance: None,
+    };
+    let imported = local.import(data, str::to_string, false)?;
+    Ok(MergeSummary {
+        added: imported.added,
+        unchanged: imported.skipped - conflicts.len(),
+        conflicts,
+    })
+}
+
+/// URLs in both collections whose folder (root normalized) or tag set differ
+pub fn find_conflicts(local: &[Bookmark], other: &[Bookmark]) -> Vec<Conflict> {
+    let version = |bookmark: &Bookmark| Version {
+        folder: bookmark.folder.clone(),
+        tags: bookmark
+            .tags
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect(),
+    };
+    let local: HashMap<&str, &Bookmark> = local
+        .iter()
+        .filter_map(|b| b.url.as_deref().map(|url| (url, b)))
+        .collect();
+
+    let mut conflicts: Vec<Conflict> = other
+        .iter()
+        .filter_map(|theirs| {
+            let url = theirs.url.as_deref()?;
+            let ours = local.get(url)?;
+            let (mine, other) = (version(ours), version(theirs));
+            let same_folder = canonical_folder(mine.folder.as_deref())
+                == canonical_folder(other.folder.as_deref());
+            (!same_folder || mine.tags != other.tags).then(|| Conflict {
+                url: url.to_string(),
+                title: ours.title.clone(),
+                local: mine,
+                other,
+                keep: None,
+                folder: None,
+                tags: None,
+            })
+        })
+        .collect();
+    conflicts.sort_by(|a, b| a.url.cmp(&b.url));
+    conflicts.dedup_by(|a, b| a.url == b.url);
+    conflicts
+}
+
+/// Write the decided folder and tags of `conflicts` to the store. The next
+/// sync of a browser holding one of them files it by that browser again.
+pub fn resolve(store: &mut Store, conflicts: &[Conflict]) -> Result<ResolveSummary> {
+    let mut stored: HashMap<String, Bookmark> = store
+        .bookmarks()?
+        .into_iter()
+        .filter_map(|b| b.url.clone().map(|url| (url, b)))
+        .collect();
+    let mut summary = ResolveSummary::default();
+    let mut updates = Vec::new();
+    for conflict in conflicts {
+        let Some(decision) = conflict.decision() else {
+            summary.unresolved += 1;
+            continue;
+        };
+        let Some(bookmark) = stored.remove(&conflict.url) else {
+            summary.missing += 1;
+            continue;
+        };
+        updates.push(Bookmark {
+            folder: decision.folder,
+            tags: (!decision.tags.is_empty()).then_some(decision.tags),
+            ..bookmark
+        });
+    }
+    summary.applied = store.update_bookmarks(&updates)?;
+    Ok(summary)
+}
+
+#[derive(Debug)]
+pub struct ExportMerge {
+    /// The single profile holding the merged bookmarks and history
+    pub data: BrowserData,
+    /// Bookmarks read from all inputs, before deduplication
+    pub read: usize,
+    pub duplicates_removed: usize,
+}
+
+/// Merge the profiles of several exports into one. Bookmarks are flattened
+/// and concatenated in input order, so `first` and `last` refer to that
+/// order, then deduplicated with `config`. History is merged by URL, adding
+/// up visit counts, and those counts feed the `visited` strategy.
+pub fn merge_exports(
+    profiles: Vec<BrowserData>,
+    sources: &[String],
+    config: DeduplicationConfig,
+) -> Result<ExportMerge> {
+    let mut bookmarks = Vec::new();
+    let mut history: BTreeMap<String, UrlEntry> = BTreeMap::new();
+    for profile in profiles {
+        bookmarks.extend(flatten_bookmarks(&profile.bookmarks.unwrap_or_default()));
+        for entry in profile.history.into_iter().flat_map(|h| h.urls) {
+            match history.get_mut(&entry.url) {
+                Some(merged) => {
+                    merged.visit_count += entry.visit_count;
+                    merged.last_visit = merged.last_visit.max(entry.last_visit);
+                }
+                None => {
+                    history.insert(entry.url.clone(), entry);
+                }
+            }
+        }
+    }
+
+    let visit_counts = history
+        .values()
+        .map(|entry| (entry.url.clone(), entry.visit_count))
+        .collect();
+    let settings = BTreeMap::from([
+        ("sources".to_string(), sources.join(",")),
+        (
+            "strategy".to_string(),
+            format!("{:?}", config.merge_strategy),
+        ),
+    ]);
+    let result = BookmarkDeduplicator::new(config)
+        .with_visit_counts(visit_counts)
+        .deduplicate(&bookmarks)?;
+
+    let mut data = BrowserData {
+        browser: "merged".to_string(),
+        profile: sources.join("+"),
+        export_date: chrono::Utc::now(),
+        bookmarks: Some(result.unique_bookmarks),
+        history: (!history.is_empty()).then(|| HistoryEntry {
+            urls: history.into_values().collect(),
+        }),
+        passwords: None,
+        aliases: None,
+        profile_name: None,
+        profile_email: None,
+        provenance: None,
+    };
+    data.provenance = Some(Provenance::new("merge", settings).describe(&data));
+    Ok(ExportMerge {
+        data,
+        read: bookmarks.len(),
+        duplicates_removed: result.duplicates_removed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deduplication::MergeStrategy;
+    use tempfile::tempdir;
+
+    fn profile(bookmarks: Vec<(&str, &str, &[&str])>) -> BrowserData {
+        BrowserData {
+            browser: "chrome".to_string(),
+            profile: "Default".to_string(),
+            export_date: chrono::Utc::now(),
+            bookmarks: Some(
+                bookmarks
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (url, folder, tags))| Bookmark {
+                        id: i.to_string(),
+                        title: url.to_string(),
+                        url: Some(url.to_string()),
+                        folder: Some(folder.to_string()),
+                        date_added: None,
+                        tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+            history: None,
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_and_resolve() {
+        let dir = tempdir().unwrap();
+        let mut local = Store::open(&dir.path().join("local.db")).unwrap();
+        local
+            .ingest(&profile(vec![
+                ("https://a.example/", "bookmark_bar/Dev", &["rust"]),
+                ("https://b.example/", "bookmark_bar/News", &[]),
+                ("https://c.example/", "bookmark_bar/Dev", &["x"]),
+            ]))
+            .unwrap();
+        let other_path = dir.path().join("laptop.db");
+        Store::open(&other_path)
+            .unwrap()
+            .ingest(&profile(vec![
+                // Same folder under Firefox's root name: no conflict
+                ("https://a.example/", "toolbar/Dev", &["rust"]),
+                ("https://b.example/", "bookmark_bar/Reading", &[]),
+                ("https://c.example/", "bookmark_bar/Dev", &["x", "y"]),
+                ("https://d.example/", "bookmark_bar/New", &[]),
+            ]))
+            .unwrap();
+
+        let summary = merge(&mut local, &other_path).unwrap();
+        assert_eq!((summary.added, summary.unchanged), (1, 1));
+        let urls: Vec<&str> = summary.conflicts.iter().map(|c| c.url.as_str()).collect();
+        assert_eq!(urls, ["https://b.example/", "https://c.example/"]);
+
+        let path = dir.path().join("conflicts.yaml");
+        let mut file = ConflictsFile {
+            other: other_path.clone(),
+            conflicts: summary.conflicts,
+        };
+        file.save(&path).unwrap();
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .starts_with("# Bookmarks")
+        );
+        file = ConflictsFile::load(&path).unwrap();
+        file.conflicts[0].keep = Some(Keep::Other);
+
+        let resolved = resolve(&mut local, &file.conflicts).unwrap();
+        assert_eq!((resolved.applied, resolved.unresolved), (1, 1));
+        let b = local
+            .bookmarks()
+            .unwrap()
+            .into_iter()
+            .find(|b| b.url.as_deref() == Some("https://b.example/"))
+            .unwrap();
+        assert_eq!(b.folder.as_deref(), Some("bookmark_bar/Reading"));
+        assert_eq!(local.bookmarks().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_merge_exports() {
+        let desktop = profile(vec![
+            ("https://a.example/", "bookmark_bar/Dev", &["rust"]),
+            ("https://b.example/", "bookmark_bar/News", &[]),
+        ]);
+        let mut laptop = profile(vec![
+            ("https://www.a.example/", "toolbar/Rust", &[]),
+            ("https://c.example/", "toolbar", &[]),
+        ]);
+        laptop.history = Some(HistoryEntry {
+            urls: vec![UrlEntry {
+                url: "https://www.a.example/".to_string(),
+                title: "A".to_string(),
+                visit_count: 3,
+                last_visit: None,
+            }],
+        });
+        let sources = ["desktop.yaml".to_string(), "laptop.yaml".to_string()];
+        let config = DeduplicationConfig {
+            merge_strategy: MergeStrategy::KeepMostVisited,
+            ..Default::default()
+        };
+
+        let merged = merge_exports(vec![desktop, laptop], &sources, config).unwrap();
+        assert_eq!((merged.read, merged.duplicates_removed), (4, 1));
+        assert_eq!(merged.data.profile, "desktop.yaml+laptop.yaml");
+        let bookmarks = merged.data.bookmarks.unwrap();
+        assert_eq!(bookmarks.len(), 3);
+        assert!(
+            bookmarks
+                .iter()
+                .any(|b| b.folder.as_deref() == Some("toolbar/Rust"))
+        );
+        assert_eq!(merged.data.history.unwrap().urls.len(), 1);
+        assert_eq!(merged.data.provenance.unwrap().generated_by, "merge");
+    }
+
+    #[test]
+    fn test_decision_overrides() {
+        let conflict = Conflict {
+            url: "https://a.example/".to_string(),
+            title: "A".to_string(),
+            local: Version {
+                folder: Some("Dev".to_string()),
+                tags: vec!["x".to_string()],
+            },
+            other: Version {
+                folder: Some("Rust".to_string()),
+                tags: vec!["y".to_string()],
+            },
+            keep: None,
+            folder: None,
+            tags: None,
+        };
+        assert_eq!(conflict.decision(), None);
+        let tags = Conflict {
+            tags: Some(vec!["x".to_string(), "y".to_string()]),
+            ..conflict.clone()
+        };
+        assert_eq!(tags.decision().unwrap().folder.as_deref(), Some("Dev"));
+        let other = Conflict {
+            keep: Some(Keep::Other),
+            folder: Some("Lang/Rust".to_string()),
+            ..conflict
+        };
+        assert_eq!(
+            other.decision(),
+            Some(Version {
+                folder: Some("Lang/Rust".to_string()),
+                tags: vec!["y".to_string()],
+            })
+        );
+    }
+}
@@ -3,20 +3,31 @@ pub mod rules;
 mod tests;
 
 use anyhow::Result;
-use regex::Regex;
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
 use std::collections::HashMap;
 use url::Url;
 
+use crate::canonical::SiteRules;
 use crate::exporter::Bookmark;
+use crate::media::MediaType;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct OrganizationConfig {
     pub organize_by_domain: bool,
     pub organize_by_category: bool,
     pub organize_by_date: bool,
+    /// File PDFs, videos, podcasts and images under their media folder
+    /// (e.g. Papers) ahead of every other rule
+    #[serde(default)]
+    pub organize_by_media: bool,
     pub custom_rules: Vec<OrganizationRule>,
     pub folder_separator: String,
     pub preserve_existing: bool,
+    /// Host aliases and site rewrites, so a rule or domain folder for a
+    /// site's new domain also takes its old URLs; set from the `sites` config
+    #[serde(skip)]
+    pub sites: SiteRules,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -29,138 +40,359 @@ pub struct OrganizationRule {
 
 impl Default for OrganizationConfig {
     fn default() -> Self {
-        let mut custom_rules = Vec::new();
-
-        // Social media
-        custom_rules.push(OrganizationRule {
-            name: "Social Media".to_string(),
-            pattern: r"(facebook|twitter|x|instagram|linkedin|reddit|youtube|tiktok|snapchat)\.com"
-                .to_string(),
-            folder: "Social".to_string(),
-            priority: 10,
-        });
-
-        // Development
-        custom_rules.push(OrganizationRule {
-            name: "Development".to_string(),
-            pattern: r"(github|gitlab|bitbucket|stackoverflow|dev\.to|medium\.com)".to_string(),
-            folder: "Development".to_string(),
-            priority: 9,
-        });
-
-        // Shopping
-        custom_rules.push(OrganizationRule {
-            name: "Shopping".to_string(),
-            pattern: r"(amazon|ebay|etsy|shopify|aliexpress|walmart|target)".to_string(),
-            folder: "Shopping".to_string(),
-            priority: 8,
-        });
-
-        // News
-        custom_rules.push(OrganizationRule {
-            name: "News".to_string(),
-            pattern:
-                r"(cnn|bbc|reuters|wikipedia|nytimes|washingtonpost|news\.|\.co\.|\.org\.|\.edu\.)"
+        let custom_rules = vec![
+            // Social media
+            OrganizationRule {
+                name: "Social Media".to_string(),
+                pattern: r"(facebook|twitter|x|instagram|linkedin|reddit|youtube|tiktok|snapchat)\.com"
                     .to_string(),
-            folder: "News & Reference".to_string(),
-            priority: 7,
-        });
-
-        // Entertainment
-        custom_rules.push(OrganizationRule {
-            name: "Entertainment".to_string(),
-            pattern: r"(netflix|hulu|disney\+|spotify|apple\.music|twitch)".to_string(),
-            folder: "Entertainment".to_string(),
-            priority: 6,
-        });
-
-        // Work/Productivity
-        custom_rules.push(OrganizationRule {
-            name: "Work".to_string(),
-            pattern: r"(office\.com|google\.com/docs|slack|teams|zoom|notion|trello|asana)"
-                .to_string(),
-            folder: "Work".to_string(),
-            priority: 5,
-        });
+                folder: "Social".to_string(),
+                priority: 10,
+            },
+            // Development
+            OrganizationRule {
+                name: "Development".to_string(),
+                pattern: r"(github|gitlab|bitbucket|stackoverflow|dev\.to|medium\.com)".to_string(),
+                folder: "Development".to_string(),
+                priority: 9,
+            },
+            // Shopping
+            OrganizationRule {
+                name: "Shopping".to_string(),
+                pattern: r"(amazon|ebay|etsy|shopify|aliexpress|walmart|target)".to_string(),
+                folder: "Shopping".to_string(),
+                priority: 8,
+            },
+            // News
+            OrganizationRule {
+                name: "News".to_string(),
+                pattern:
+                    r"(cnn|bbc|reuters|wikipedia|nytimes|washingtonpost|news\.|\.co\.|\.org\.|\.edu\.)"
+                        .to_string(),
+                folder: "News & Reference".to_string(),
+                priority: 7,
+            },
+            // Entertainment
+            OrganizationRule {
+                name: "Entertainment".to_string(),
+                pattern: r"(netflix|hulu|disney\+|spotify|apple\.music|twitch)".to_string(),
+                folder: "Entertainment".to_string(),
+                priority: 6,
+            },
+            // Work/Productivity
+            OrganizationRule {
+                name: "Work".to_string(),
+                pattern: r"(office\.com|google\.com/docs|slack|teams|zoom|notion|trello|asana)"
+                    .to_string(),
+                folder: "Work".to_string(),
+                priority: 5,
+            },
+        ];
 
         Self {
             organize_by_domain: true,
             organize_by_category: true,
             organize_by_date: false,
+            organize_by_media: false,
             custom_rules,
             folder_separator: "/".to_string(),
             preserve_existing: true,
+            sites: SiteRules::default(),
         }
     }
 }
 
+/// Upper bound on a rule's compiled program size, so a pathological pattern
+/// fails to compile instead of exhausting memory
+pub const RULE_REGEX_SIZE_LIMIT: usize = 1 << 20;
+/// Upper bound on the lazy DFA cache used while matching a rule
+pub const RULE_DFA_SIZE_LIMIT: usize = 1 << 20;
+
+/// Compile a rule pattern with the organizer's resource limits
+pub fn compile_rule_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .size_limit(RULE_REGEX_SIZE_LIMIT)
+        .dfa_size_limit(RULE_DFA_SIZE_LIMIT)
+        .build()
+}
+
+/// The first category whose keywords appear in `url` or `title`, the keyword
+/// that matched, and how many categories matched in all
+fn content_category(url: &str, title: &str) -> Option<(&'static str, &'static str, usize)> {
+    let content = format!("{} {}", url, title).to_lowercase();
+    let mut matches = CONTENT_CATEGORIES
+        .iter()
+        .filter_map(|(category, keywords)| {
+            let keyword = keywords.iter().find(|&&k| content.contains(k))?;
+            Some((*category, *keyword))
+        });
+    let (category, keyword) = matches.next()?;
+    Some((category, keyword, 1 + matches.count()))
+}
+
+/// Minimum bookmarks handed to each worker when organizing in parallel
+const ORGANIZE_CHUNK_SIZE: usize = 1024;
+
+/// A custom rule with its pattern compiled once up front
+struct CompiledRule {
+    name: String,
+    regex: Regex,
+    folder: String,
+}
+
+/// Keywords in a bookmark's URL or title that file it under a category, in
+/// the order categories are tried
+const CONTENT_CATEGORIES: &[(&str, &[&str])] = &[
+    (
+        "Development",
+        &[
+            "github",
+            "gitlab",
+            "stackoverflow",
+            "developer",
+            "api",
+            "documentation",
+            "docs",
+        ],
+    ),
+    (
+        "Social",
+        &["facebook", "twitter", "instagram", "linkedin", "social"],
+    ),
+    (
+        "Shopping",
+        &["amazon", "ebay", "shop", "store", "buy", "price"],
+    ),
+    (
+        "News & Reference",
+        &["news", "article", "blog", "post", "wikipedia"],
+    ),
+    (
+        "Entertainment",
+        &["video", "movie", "music", "game", "stream"],
+    ),
+    (
+        "Work",
+        &["work", "office", "productivity", "tool", "service"],
+    ),
+];
+
+/// Where the organizer would file a bookmark, and why
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Placement {
+    pub folder: String,
+    /// What decided it: `media`, `rule`, `domain`, `category`, `date` or
+    /// `none` for bookmarks without a URL
+    pub basis: &'static str,
+    /// The media type, rule name, host, keyword or date that matched
+    pub evidence: Option<String>,
+    /// How much to trust the placement, from 0 to 1: explicit rules and
+    /// media types rank highest, a keyword claimed by several categories
+    /// or a fallback low
+    pub confidence: f64,
+}
+
+/// One row of an organization audit
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AuditEntry {
+    pub id: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub domain: Option<String>,
+    /// Folder before organizing
+    pub current_folder: Option<String>,
+    pub folder: String,
+    pub basis: &'static str,
+    pub evidence: Option<String>,
+    pub confidence: f64,
+}
+
 pub struct BookmarkOrganizer {
     config: OrganizationConfig,
+    /// Valid custom rules, highest priority first
+    rules: Vec<CompiledRule>,
+    /// Rules whose pattern failed to compile; these never match
+    rule_errors: Vec<String>,
 }
 
 impl BookmarkOrganizer {
     pub fn new(config: OrganizationConfig) -> Self {
-        Self { config }
-    }
+        let mut sorted_rules: Vec<&OrganizationRule> = config.custom_rules.iter().collect();
+        sorted_rules.sort_by_key(|r| std::cmp::Reverse(r.priority));
+
+        let mut rules = Vec::with_capacity(sorted_rules.len());
+        let mut rule_errors = Vec::new();
+        for rule in sorted_rules {
+            match compile_rule_pattern(&rule.pattern) {
+                Ok(regex) => rules.push(CompiledRule {
+                    name: rule.name.clone(),
+                    regex,
+                    folder: rule.folder.clone(),
+                }),
+                Err(e) => {
+                    log::warn!("Skipping rule '{}': {}", rule.name, e);
+                    rule_errors.push(format!("Rule '{}': {}", rule.name, e));
+                }
+            }
+        }
 
-    pub fn organize(&self, bookmarks: Vec<Bookmark>) -> Result<Vec<Bookmark>> {
-        let mut organized_bookmarks = Vec::new();
+        Self {
+            config,
+            rules,
+            rule_errors,
+        }
+    }
 
-        for mut bookmark in bookmarks {
-            let new_folder = self.determine_folder(&bookmark);
+    /// Rules that were skipped because their pattern did not compile
+    pub fn rule_errors(&self) -> &[String] {
+        &self.rule_errors
+    }
 
-            if self.config.preserve_existing && bookmark.folder.is_some() {
-                if let Some(ref existing_folder) = bookmark.folder {
-                    bookmark.folder = Some(format!(
-                        "{}{}{}",
-                        new_folder, self.config.folder_separator, existing_folder
-                    ));
+    /// Assign folders to `bookmarks`, processing chunks in parallel while
+    /// keeping the input order
+    pub fn organize(&self, bookmarks: Vec<Bookmark>) -> Result<Vec<Bookmark>> {
+        let organized_bookmarks = bookmarks
+            .into_par_iter()
+            .with_min_len(ORGANIZE_CHUNK_SIZE)
+            .map(|mut bookmark| {
+                let new_folder = self.determine_folder(&bookmark);
+
+                if self.config.preserve_existing && bookmark.folder.is_some() {
+                    if let Some(ref existing_folder) = bookmark.folder {
+                        bookmark.folder = Some(format!(
+                            "{}{}{}",
+                            new_folder, self.config.folder_separator, existing_folder
+                        ));
+                    }
+                } else {
+                    bookmark.folder = Some(new_folder);
                 }
-            } else {
-                bookmark.folder = Some(new_folder);
-            }
 
-            organized_bookmarks.push(bookmark);
-        }
+                bookmark
+            })
+            .collect();
 
         Ok(organized_bookmarks)
     }
 
     fn determine_folder(&self, bookmark: &Bookmark) -> String {
-        // Check custom rules first (sorted by priority)
-        let mut sorted_rules = self.config.custom_rules.clone();
-        sorted_rules.sort_by(|a, b| b.priority.cmp(&a.priority));
-
-        if let Some(ref url_str) = bookmark.url {
-            for rule in &sorted_rules {
-                if let Ok(regex) = Regex::new(&rule.pattern) {
-                    if regex.is_match(url_str) {
-                        return rule.folder.clone();
-                    }
-                }
-            }
+        self.explain(bookmark).folder
+    }
 
-            // If no custom rule matches, check domain-based organization
-            if self.config.organize_by_domain {
-                if let Ok(url) = Url::parse(url_str) {
-                    if let Some(host) = url.host_str() {
-                        return self.extract_domain_folder(host);
-                    }
-                }
+    /// Where `bookmark` would be filed, with the rule or keyword that decided
+    /// it. Media types come first, then custom rules in priority order, then
+    /// the domain, content keywords and the date added, as configured.
+    pub fn explain(&self, bookmark: &Bookmark) -> Placement {
+        let placement = |folder: String, basis, evidence: Option<String>, confidence| Placement {
+            folder,
+            basis,
+            evidence,
+            confidence,
+        };
+        let Some(url_str) = bookmark.url.as_deref() else {
+            return placement("Uncategorized".to_string(), "none", None, 0.0);
+        };
+
+        if self.config.organize_by_media {
+            // A recorded type was probed or enriched; one from the URL is a guess
+            let (media_type, confidence) = match bookmark.media_type {
+                Some(media_type) => (Some(media_type), 0.95),
+                None => (MediaType::from_url(url_str), 0.9),
+            };
+            if let Some(media_type) = media_type {
+                return placement(
+                    media_type.folder().to_string(),
+                    "media",
+                    Some(media_type.to_string()),
+                    confidence,
+                );
             }
+        }
 
-            // Category-based organization as fallback
-            if self.config.organize_by_category {
-                return self.categorize_by_content(url_str, &bookmark.title);
-            }
+        // Then custom rules, already in priority order, against the URL as
+        // bookmarked or on the domain its site moved to
+        let canonical = self.config.sites.canonicalize(url_str);
+        if let Some(rule) = self
+            .rules
+            .iter()
+            .find(|r| r.regex.is_match(url_str) || r.regex.is_match(&canonical))
+        {
+            return placement(rule.folder.clone(), "rule", Some(rule.name.clone()), 0.9);
+        }
 
-            // Date-based organization as last resort
-            if self.config.organize_by_date {
-                return self.categorize_by_date(&bookmark.date_added);
-            }
+        // If no custom rule matches, check domain-based organization
+        if self.config.organize_by_domain
+            && let Ok(url) = Url::parse(&canonical)
+            && let Some(host) = url.host_str()
+        {
+            let folder = self.extract_domain_folder(host);
+            return placement(folder, "domain", Some(host.to_string()), 0.7);
         }
 
-        "Uncategorized".to_string()
+        // Category-based organization as fallback
+        if self.config.organize_by_category {
+            return match content_category(url_str, &bookmark.title) {
+                // First category wins, so a keyword shared with others is weak evidence
+                Some((category, keyword, 1)) => placement(
+                    category.to_string(),
+                    "category",
+                    Some(keyword.to_string()),
+                    0.6,
+                ),
+                Some((category, keyword, _)) => placement(
+                    category.to_string(),
+                    "category",
+                    Some(keyword.to_string()),
+                    0.4,
+                ),
+                None => placement("General".to_string(), "category", None, 0.1),
+            };
+        }
+
+        // Date-based organization as last resort
+        if self.config.organize_by_date {
+            let evidence = bookmark
+                .date_added
+                .map(|d| d.format("%Y-%m-%d").to_string());
+            return placement(
+                self.categorize_by_date(&bookmark.date_added),
+                "date",
+                evidence,
+                0.3,
+            );
+        }
+
+        placement("Uncategorized".to_string(), "none", None, 0.0)
+    }
+
+    /// [`explain`](Self::explain) every bookmark, nested ones included,
+    /// alongside the folder it is in now
+    pub fn audit(&self, bookmarks: &[Bookmark]) -> Vec<AuditEntry> {
+        let mut entries = Vec::new();
+        for bookmark in bookmarks {
+            if let Some(children) = &bookmark.children {
+                entries.extend(self.audit(children));
+            }
+            if bookmark.is_folder() {
+                continue;
+            }
+            let placement = self.explain(bookmark);
+            entries.push(AuditEntry {
+                id: bookmark.id.clone(),
+                title: bookmark.title.clone(),
+                url: bookmark.url.clone(),
+                domain: bookmark
+                    .url
+                    .as_deref()
+                    .and_then(|u| self.config.sites.canonical_host(u)),
+                current_folder: bookmark.folder.clone(),
+                folder: placement.folder,
+                basis: placement.basis,
+                evidence: placement.evidence,
+                confidence: placement.confidence,
+            });
+        }
+        entries
     }
 
     fn extract_domain_folder(&self, host: &str) -> String {
@@ -181,56 +413,6 @@ impl BookmarkOrganizer {
         }
     }
 
-    fn categorize_by_content(&self, url: &str, title: &str) -> String {
-        let content = format!("{} {}", url, title).to_lowercase();
-
-        if self.contains_any(
-            &content,
-            &[
-                "github",
-                "gitlab",
-                "stackoverflow",
-                "developer",
-                "api",
-                "documentation",
-                "docs",
-            ],
-        ) {
-            return "Development".to_string();
-        }
-
-        if self.contains_any(
-            &content,
-            &["facebook", "twitter", "instagram", "linkedin", "social"],
-        ) {
-            return "Social".to_string();
-        }
-
-        if self.contains_any(
-            &content,
-            &["amazon", "ebay", "shop", "store", "buy", "price"],
-        ) {
-            return "Shopping".to_string();
-        }
-
-        if self.contains_any(&content, &["news", "article", "blog", "post", "wikipedia"]) {
-            return "News & Reference".to_string();
-        }
-
-        if self.contains_any(&content, &["video", "movie", "music", "game", "stream"]) {
-            return "Entertainment".to_string();
-        }
-
-        if self.contains_any(
-            &content,
-            &["work", "office", "productivity", "tool", "service"],
-        ) {
-            return "Work".to_string();
-        }
-
-        "General".to_string()
-    }
-
     fn categorize_by_date(&self, date_added: &Option<chrono::DateTime<chrono::Utc>>) -> String {
         if let Some(date) = date_added {
             let year = date.format("%Y").to_string();
@@ -241,10 +423,6 @@ impl BookmarkOrganizer {
         }
     }
 
-    fn contains_any(&self, text: &str, keywords: &[&str]) -> bool {
-        keywords.iter().any(|&keyword| text.contains(keyword))
-    }
-
     pub fn create_folder_structure<'a>(
         &self,
         bookmarks: &'a [Bookmark],
@@ -257,10 +435,7 @@ impl BookmarkOrganizer {
                 .as_ref()
                 .unwrap_or(&"Uncategorized".to_string())
                 .clone();
-            folder_map
-                .entry(folder)
-                .or_insert_with(Vec::new)
-                .push(bookmark);
+            folder_map.entry(folder).or_default().push(bookmark);
         }
 
         folder_map
@@ -12,12 +12,11 @@ pub fn create_automated_rules(bookmarks: &[Bookmark]) -> Vec<OrganizationRule> {
 
     // Count domain frequencies
     for bookmark in bookmarks {
-        if let Some(url) = &bookmark.url {
-            if let Ok(parsed) = Url::parse(url) {
-                if let Some(host) = parsed.host_str() {
-                    *domain_counts.entry(host.to_string()).or_insert(0) += 1;
-                }
-            }
+        if let Some(url) = &bookmark.url
+            && let Ok(parsed) = Url::parse(url)
+            && let Some(host) = parsed.host_str()
+        {
+            *domain_counts.entry(host.to_string()).or_insert(0) += 1;
         }
 
         // Extract common title patterns
@@ -31,7 +30,7 @@ pub fn create_automated_rules(bookmarks: &[Bookmark]) -> Vec<OrganizationRule> {
         for word in title_words {
             title_patterns
                 .entry(word.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(bookmark.title.clone());
         }
     }
@@ -45,7 +44,7 @@ pub fn create_automated_rules(bookmarks: &[Bookmark]) -> Vec<OrganizationRule> {
 
             rules.push(OrganizationRule {
                 name: format!("Auto: {}", domain),
-                pattern: format!(r"{}", regex::escape(&domain)),
+                pattern: regex::escape(&domain).to_string(),
                 folder: format!("Frequent/{}", folder_name),
                 priority: 3,
             });
@@ -22,18 +22,16 @@ fn test_domain_extraction() {
 
 #[test]
 fn test_content_categorization() {
-    let config = OrganizationConfig::default();
-    let organizer = BookmarkOrganizer::new(config);
-
     assert_eq!(
-        organizer.categorize_by_content("https://github.com/user/repo", "GitHub Repo"),
-        "Development"
+        content_category("https://github.com/user/repo", "GitHub Repo"),
+        Some(("Development", "github", 1))
     );
 
     assert_eq!(
-        organizer.categorize_by_content("https://www.amazon.com/product", "Product on Amazon"),
-        "Shopping"
+        content_category("https://www.amazon.com/product", "Product on Amazon"),
+        Some(("Shopping", "amazon", 1))
     );
+    assert_eq!(content_category("https://example.com/", "Example"), None);
 }
 
 #[test]
@@ -47,7 +45,7 @@ fn test_custom_rules() {
         url: Some("https://www.facebook.com/profile".to_string()),
         folder: None,
         date_added: None,
-        children: None,
+        ..Default::default()
     };
 
     let folder = organizer.determine_folder(&bookmark);
@@ -68,7 +66,7 @@ fn test_organize_preserves_existing() {
         url: Some("https://github.com".to_string()),
         folder: Some("My Folder".to_string()),
         date_added: None,
-        children: None,
+        ..Default::default()
     }];
 
     let result = organizer.organize(bookmarks).unwrap();
@@ -90,7 +88,7 @@ fn test_organize_replaces_folder() {
         url: Some("https://github.com".to_string()),
         folder: Some("Old Folder".to_string()),
         date_added: None,
-        children: None,
+        ..Default::default()
     }];
 
     let result = organizer.organize(bookmarks).unwrap();
@@ -119,7 +117,7 @@ fn test_bookmark_without_url() {
         url: None,
         folder: None,
         date_added: None,
-        children: None,
+        ..Default::default()
     }];
 
     let result = organizer.organize(bookmarks).unwrap();
@@ -154,9 +152,165 @@ fn test_multiple_categorization_rules() {
         url: Some("https://www.reddit.com/r/rust".to_string()),
         folder: None,
         date_added: None,
-        children: None,
+        ..Default::default()
     };
 
     let folder = organizer.determine_folder(&bookmark);
     assert_eq!(folder, "Social");
 }
+
+#[test]
+fn test_invalid_and_oversized_rules_are_reported() {
+    let mut config = OrganizationConfig::default();
+    config.custom_rules.push(OrganizationRule {
+        name: "Broken".to_string(),
+        pattern: r"(github".to_string(),
+        folder: "Broken".to_string(),
+        priority: 100,
+    });
+    config.custom_rules.push(OrganizationRule {
+        name: "Huge".to_string(),
+        pattern: r"(\w{100}){100}".to_string(),
+        folder: "Huge".to_string(),
+        priority: 100,
+    });
+    let organizer = BookmarkOrganizer::new(config);
+
+    let errors = organizer.rule_errors();
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0].contains("Broken"));
+    assert!(errors[1].contains("Huge"));
+
+    // Remaining rules still apply
+    let bookmark = Bookmark {
+        id: "1".to_string(),
+        title: "GitHub".to_string(),
+        url: Some("https://github.com/rust-lang".to_string()),
+        folder: None,
+        date_added: None,
+        ..Default::default()
+    };
+    assert_eq!(organizer.determine_folder(&bookmark), "Development");
+}
+
+#[test]
+fn test_organize_large_batch_keeps_order_and_priority() {
+    let mut config = OrganizationConfig {
+        preserve_existing: false,
+        ..Default::default()
+    };
+    // Listed last but highest priority, so it must win over "Development"
+    config.custom_rules.push(OrganizationRule {
+        name: "Rust".to_string(),
+        pattern: r"github\.com/rust-lang".to_string(),
+        folder: "Rust".to_string(),
+        priority: 50,
+    });
+    let organizer = BookmarkOrganizer::new(config);
+
+    let bookmarks: Vec<Bookmark> = (0..5000)
+        .map(|i| Bookmark {
+            id: i.to_string(),
+            title: format!("Repo {}", i),
+            url: Some(if i % 2 == 0 {
+                format!("https://github.com/rust-lang/repo{}", i)
+            } else {
+                format!("https://github.com/other/repo{}", i)
+            }),
+            folder: None,
+            date_added: None,
+            ..Default::default()
+        })
+        .collect();
+
+    let result = organizer.organize(bookmarks).unwrap();
+    assert_eq!(result.len(), 5000);
+    for (i, bookmark) in result.iter().enumerate() {
+        assert_eq!(bookmark.id, i.to_string());
+        let expected = if i % 2 == 0 { "Rust" } else { "Development" };
+        assert_eq!(bookmark.folder.as_deref(), Some(expected));
+    }
+}
+
+#[test]
+fn test_organize_by_media() {
+    let config = OrganizationConfig {
+        organize_by_media: true,
+        preserve_existing: false,
+        ..Default::default()
+    };
+    let organizer = BookmarkOrganizer::new(config);
+
+    let bookmark = |url: &str| Bookmark {
+        id: url.to_string(),
+        title: String::new(),
+        url: Some(url.to_string()),
+        folder: None,
+        date_added: None,
+        ..Default::default()
+    };
+    let probed = Bookmark {
+        media_type: Some(MediaType::Podcast),
+        ..bookmark("https://example.com/feed")
+    };
+    let bookmarks = vec![
+        bookmark("https://arxiv.org/pdf/1706.03762.pdf"),
+        // Matches the Social Media rule too, but media comes first
+        bookmark("https://www.youtube.com/watch?v=abc"),
+        probed,
+        bookmark("https://github.com/rust-lang/rust"),
+    ];
+
+    let folders: Vec<String> = organizer
+        .organize(bookmarks)
+        .unwrap()
+        .into_iter()
+        .filter_map(|b| b.folder)
+        .collect();
+    assert_eq!(folders, ["Papers", "Videos", "Podcasts", "Development"]);
+}
+
+#[test]
+fn test_audit_explains_placements() {
+    let config = OrganizationConfig {
+        organize_by_domain: false,
+        ..Default::default()
+    };
+    let organizer = BookmarkOrganizer::new(config);
+
+    let bookmark = |url: Option<&str>, title: &str| Bookmark {
+        id: title.to_string(),
+        title: title.to_string(),
+        url: url.map(str::to_string),
+        folder: Some("Inbox".to_string()),
+        date_added: None,
+        ..Default::default()
+    };
+    let folder = Bookmark {
+        url: None,
+        children: Some(vec![
+            bookmark(Some("https://www.github.com/rust-lang"), "Rust"),
+            bookmark(Some("https://example.com/blog"), "Game blog"),
+            bookmark(Some("https://example.com/"), "Example"),
+        ]),
+        ..bookmark(None, "Folder")
+    };
+
+    let audit = organizer.audit(&[folder]);
+    let rows: Vec<_> = audit
+        .iter()
+        .map(|e| (e.title.as_str(), e.folder.as_str(), e.basis, e.evidence.as_deref()))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            ("Rust", "Development", "rule", Some("Development")),
+            ("Game blog", "News & Reference", "category", Some("blog")),
+            ("Example", "General", "category", None),
+        ]
+    );
+    assert_eq!(audit[0].domain.as_deref(), Some("github.com"));
+    assert_eq!(audit[0].current_folder.as_deref(), Some("Inbox"));
+    // "blog" and "game" point at different categories
+    assert!(audit[1].confidence < 0.5 && audit[2].confidence < audit[1].confidence);
+}
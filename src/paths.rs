@@ -0,0 +1,148 @@
+//! Where the tool keeps its files.
+//!
+//! Defaults follow each platform's conventions through `dirs`: the XDG base
+//! directories on Linux, `~/Library/Application Support` and
+//! `~/Library/Caches` on macOS, and `%APPDATA%`/`%LOCALAPPDATA%` on Windows.
+//! An explicitly set `XDG_*_HOME` is honoured on every platform. Each location
+//! can be moved with a command-line flag or an environment variable; flags
+//! win over the environment.
+
+use serde::Serialize;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Directory name used under the platform config, data and cache dirs
+pub const APP_DIR: &str = "bookmark-manager";
+
+/// Per-location overrides, typically from command-line flags
+#[derive(Debug, Clone, Default)]
+pub struct PathOverrides {
+    pub config: Option<PathBuf>,
+    pub store: Option<PathBuf>,
+    pub cache: Option<PathBuf>,
+    pub backups: Option<PathBuf>,
+    pub archives: Option<PathBuf>,
+    pub snapshots: Option<PathBuf>,
+}
+
+/// Resolved locations of everything the tool reads or writes on its own
+#[derive(Debug, Clone, Serialize)]
+pub struct AppPaths {
+    /// Configuration file
+    pub config: PathBuf,
+    /// Persistent bookmark store
+    pub store: PathBuf,
+    /// Regenerable files such as graph output
+    pub cache: PathBuf,
+    /// Copies of browser databases taken before writing to them
+    pub backups: PathBuf,
+    /// Archived pages
+    pub archives: PathBuf,
+    /// Short-lived copies of browser databases read during export
+    pub snapshots: PathBuf,
+}
+
+impl AppPaths {
+    /// Environment variables that relocate each path, in [`AppPaths::entries`] order
+    pub const ENV_VARS: [&'static str; 6] = [
+        "BOOKMARK_CONFIG",
+        "BOOKMARK_STORE",
+        "BOOKMARK_CACHE_DIR",
+        "BOOKMARK_BACKUP_DIR",
+        "BOOKMARK_ARCHIVE_DIR",
+        "BOOKMARK_SNAPSHOT_DIR",
+    ];
+
+    /// Resolve every path from `overrides`, then the environment, then the
+    /// platform defaults
+    pub fn resolve(overrides: &PathOverrides) -> Self {
+        Self::resolve_with(overrides, |name| std::env::var_os(name))
+    }
+
+    fn resolve_with(overrides: &PathOverrides, env: impl Fn(&str) -> Option<OsString>) -> Self {
+        let pick = |flag: &Option<PathBuf>, var: &str, default: PathBuf| {
+            flag.clone()
+                .or_else(|| env(var).filter(|v| !v.is_empty()).map(PathBuf::from))
+                .unwrap_or(default)
+        };
+        let base = |xdg_var: &str, platform: Option<PathBuf>| {
+            env(xdg_var)
+                .map(PathBuf::from)
+                .filter(|p| p.is_absolute())
+                .or(platform)
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(APP_DIR)
+        };
+        let config_dir = base("XDG_CONFIG_HOME", dirs::config_dir());
+        let data_dir = base("XDG_DATA_HOME", dirs::data_dir());
+        let cache_dir = base("XDG_CACHE_HOME", dirs::cache_dir());
+
+        let [config, store, cache, backups, archives, snapshots] = Self::ENV_VARS;
+        let cache = pick(&overrides.cache, cache, cache_dir);
+        Self {
+            config: pick(&overrides.config, config, config_dir.join("config.yaml")),
+            store: pick(&overrides.store, store, data_dir.join("store.db")),
+            backups: pick(&overrides.backups, backups, data_dir.join("backups")),
+            archives: pick(&overrides.archives, archives, data_dir.join("archives")),
+            snapshots: pick(&overrides.snapshots, snapshots, cache.join("snapshots")),
+            cache,
+        }
+    }
+
+    /// (name, path, environment variable) for each location
+    pub fn entries(&self) -> [(&'static str, &Path, &'static str); 6] {
+        let [config, store, cache, backups, archives, snapshots] = Self::ENV_VARS;
+        [
+            ("config", &self.config, config),
+            ("store", &self.store, store),
+            ("cache", &self.cache, cache),
+            ("backups", &self.backups, backups),
+            ("archives", &self.archives, archives),
+            ("snapshots", &self.snapshots, snapshots),
+        ]
+    }
+}
+
+static PATHS: OnceLock<AppPaths> = OnceLock::new();
+
+/// Fix the paths for this process. Call before anything reads [`get`];
+/// later calls return the paths already in effect.
+pub fn init(overrides: &PathOverrides) -> &'static AppPaths {
+    PATHS.get_or_init(|| AppPaths::resolve(overrides))
+}
+
+/// The paths in effect, resolved from the environment if [`init`] was not called
+pub fn get() -> &'static AppPaths {
+    PATHS.get_or_init(|| AppPaths::resolve(&PathOverrides::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_beats_env_beats_default() {
+        let env = |name: &str| match name {
+            "BOOKMARK_STORE" => Some(OsString::from("/env/store.db")),
+            "BOOKMARK_CACHE_DIR" => Some(OsString::from("/env/cache")),
+            "XDG_DATA_HOME" => Some(OsString::from("/xdg/data")),
+            _ => None,
+        };
+        let overrides = PathOverrides {
+            store: Some(PathBuf::from("/flag/store.db")),
+            ..Default::default()
+        };
+        let paths = AppPaths::resolve_with(&overrides, env);
+
+        assert_eq!(paths.store, PathBuf::from("/flag/store.db"));
+        assert_eq!(paths.cache, PathBuf::from("/env/cache"));
+        // Snapshots follow a relocated cache
+        assert_eq!(paths.snapshots, PathBuf::from("/env/cache/snapshots"));
+        assert_eq!(
+            paths.backups,
+            PathBuf::from("/xdg/data").join(APP_DIR).join("backups")
+        );
+        assert!(paths.config.ends_with("bookmark-manager/config.yaml"));
+    }
+}
@@ -0,0 +1,898 @@
+//! Bookmark processing as an ordered list of pluggable stages.
+//!
+//! The default pipeline is normalize → dedupe → enrich → organize → cleanup,
+//! preceded by expire when folder expiration policies are configured and
+//! followed by wayback when archiving is asked for and quota when folder
+//! quotas are configured; github joins after enrich when repository lookups
+//! are asked for.
+//! Each stage implements [`Stage`], so callers can reorder, skip, or insert
+//! their own stages; every run records a [`StageSummary`] for the report.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use url::Url;
+
+use crate::deduplication::{
+    BookmarkDeduplicator, DeduplicationConfig, DeduplicationResult, RedirectResolver,
+};
+use crate::expiration::{self, Expiration, ExpireAction, ExpirePolicy};
+use crate::exporter::Bookmark;
+use crate::integrations::github::GithubClient;
+use crate::integrations::wayback::{ArchiveOptions, WaybackClient};
+use crate::media::{self, MediaType};
+use crate::organization::{BookmarkOrganizer, OrganizationConfig};
+use crate::progress::ProgressEvent;
+use crate::quota::{FolderQuota, OVERFLOW_TAG, Overflow, OverflowAction};
+
+/// Names of the built-in stages, in default order
+pub const DEFAULT_STAGES: [&str; 5] = ["normalize", "dedupe", "enrich", "organize", "cleanup"];
+
+/// One step of the processing pipeline
+pub trait Stage {
+    /// Short name shown in reports
+    fn name(&self) -> &str;
+
+    /// Transform `bookmarks`, recording anything worth reporting in `ctx`
+    fn run(&self, bookmarks: Vec<Bookmark>, ctx: &mut StageContext) -> Result<Vec<Bookmark>>;
+}
+
+/// State shared across a pipeline run
+#[derive(Debug, Default)]
+pub struct StageContext {
+    /// Notes from the stage currently running; moved into its summary afterwards
+    pub notes: Vec<String>,
+    /// Set by the dedupe stage
+    pub deduplication_result: Option<DeduplicationResult>,
+    /// Set by the organize stage
+    pub rule_errors: Vec<String>,
+    /// Set by the expire stage
+    pub expirations: Vec<Expiration>,
+    /// Set by the quota stage
+    pub overflows: Vec<Overflow>,
+}
+
+/// Effect of a single stage on the bookmark list
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StageSummary {
+    pub stage: String,
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
+    pub input_count: usize,
+    pub output_count: usize,
+    /// Bookmarks dropped (e.g. merged away by dedupe)
+    pub removed: usize,
+    /// Bookmarks added by the stage
+    pub added: usize,
+    /// Surviving bookmarks whose folder changed
+    pub moved: usize,
+    /// Surviving bookmarks whose title changed
+    pub retitled: usize,
+    pub notes: Vec<String>,
+}
+
+/// Serialize a duration as fractional milliseconds
+pub fn serialize_millis<S: serde::Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+/// Run `stages` in order over `bookmarks`
+pub fn run_stages(
+    stages: &[Box<dyn Stage>],
+    bookmarks: Vec<Bookmark>,
+) -> Result<(Vec<Bookmark>, StageContext, Vec<StageSummary>)> {
+    run_stages_with_progress(stages, bookmarks, |_| {})
+}
+
+/// [`run_stages`], reporting each stage as it finishes
+pub fn run_stages_with_progress(
+    stages: &[Box<dyn Stage>],
+    bookmarks: Vec<Bookmark>,
+    on_progress: impl Fn(ProgressEvent),
+) -> Result<(Vec<Bookmark>, StageContext, Vec<StageSummary>)> {
+    let mut ctx = StageContext::default();
+    let mut summaries = Vec::with_capacity(stages.len());
+    let mut bookmarks = bookmarks;
+
+    for stage in stages {
+        let input_count = bookmarks.len();
+        // Matched by id afterwards; with duplicate ids the last one wins,
+        // so moved/retitled counts are approximate for such inputs
+        let before: HashMap<String, (String, Option<String>)> = bookmarks
+            .iter()
+            .map(|b| (b.id.clone(), (b.title.clone(), b.folder.clone())))
+            .collect();
+
+        let start = Instant::now();
+        bookmarks = stage.run(bookmarks, &mut ctx)?;
+        let duration = start.elapsed();
+
+        let (mut moved, mut retitled) = (0, 0);
+        for bookmark in &bookmarks {
+            if let Some((title, folder)) = before.get(&bookmark.id) {
+                moved += usize::from(*folder != bookmark.folder);
+                retitled += usize::from(*title != bookmark.title);
+            }
+        }
+
+        let output_count = bookmarks.len();
+        on_progress(ProgressEvent::StageFinished {
+            stage: stage.name().to_string(),
+            input: input_count,
+            output: output_count,
+            duration,
+        });
+        summaries.push(StageSummary {
+            stage: stage.name().to_string(),
+            duration,
+            input_count,
+            output_count,
+            removed: input_count.saturating_sub(output_count),
+            added: output_count.saturating_sub(input_count),
+            moved,
+            retitled,
+            notes: std::mem::take(&mut ctx.notes),
+        });
+    }
+
+    Ok((bookmarks, ctx, summaries))
+}
+
+/// Build a built-in stage by name
+pub fn stage_from_name(
+    name: &str,
+    deduplication_config: &DeduplicationConfig,
+    organization_config: &OrganizationConfig,
+) -> Result<Box<dyn Stage>> {
+    match name {
+        "normalize" => Ok(Box::new(NormalizeStage)),
+        "dedupe" => Ok(Box::new(DedupeStage::new(deduplication_config.clone()))),
+        "enrich" => Ok(Box::new(EnrichStage::default())),
+        "organize" => Ok(Box::new(OrganizeStage::new(organization_config.clone()))),
+        "cleanup" => Ok(Box::new(CleanupStage::new(
+            &organization_config.folder_separator,
+        ))),
+        _ => Err(anyhow!(
+            "Unknown stage: {} (expected one of {})",
+            name,
+            DEFAULT_STAGES.join(", ")
+        )),
+    }
+}
+
+/// Trim stray whitespace from URLs and drop empty ones
+pub struct NormalizeStage;
+
+impl Stage for NormalizeStage {
+    fn name(&self) -> &str {
+        "normalize"
+    }
+
+    fn run(&self, mut bookmarks: Vec<Bookmark>, ctx: &mut StageContext) -> Result<Vec<Bookmark>> {
+        let mut changed = 0;
+        for bookmark in &mut bookmarks {
+            if let Some(url) = &bookmark.url {
+                let trimmed = url.trim();
+                if trimmed.len() != url.len() || trimmed.is_empty() {
+                    bookmark.url = (!trimmed.is_empty()).then(|| trimmed.to_string());
+                    changed += 1;
+                }
+            }
+        }
+        if changed > 0 {
+            ctx.notes.push(format!("{} URLs normalized", changed));
+        }
+        Ok(bookmarks)
+    }
+}
+
+/// Delete, move or archive bookmarks that outlived their folder's policy
+pub struct ExpireStage {
+    policies: Vec<ExpirePolicy>,
+    folder_separator: String,
+    now: DateTime<Utc>,
+    archive_dir: Option<PathBuf>,
+}
+
+impl ExpireStage {
+    pub fn new(policies: &[ExpirePolicy], folder_separator: &str) -> Self {
+        Self {
+            policies: policies.to_vec(),
+            folder_separator: folder_separator.to_string(),
+            now: Utc::now(),
+            archive_dir: None,
+        }
+    }
+
+    /// Measure ages from `now` instead of the current time
+    pub fn at(mut self, now: DateTime<Utc>) -> Self {
+        self.now = now;
+        self
+    }
+
+    /// Save archived pages under `dir`. Without one, archive actions are
+    /// recorded but no pages are fetched (as in a preview).
+    pub fn with_archive_dir(mut self, dir: PathBuf) -> Self {
+        self.archive_dir = Some(dir);
+        self
+    }
+
+    /// Carry out `policy` on `bookmark`, returning the outcome and the
+    /// bookmark if it stays in the collection
+    fn apply(&self, policy: &ExpirePolicy, mut bookmark: Bookmark) -> (String, Option<Bookmark>) {
+        match policy.action {
+            ExpireAction::Delete => ("deleted".to_string(), None),
+            ExpireAction::Move => {
+                let to = policy.to.clone().unwrap_or_default();
+                let outcome = format!("moved to {}", to);
+                bookmark.folder = Some(to);
+                (outcome, Some(bookmark))
+            }
+            ExpireAction::Archive => {
+                let Some(dir) = &self.archive_dir else {
+                    return ("would archive (preview)".to_string(), None);
+                };
+                let url = bookmark.url.as_deref().unwrap_or_default();
+                match expiration::archive_page(url, dir) {
+                    Ok(path) => (format!("archived to {}", path.display()), None),
+                    Err(e) => (format!("kept, {}", e), Some(bookmark)),
+                }
+            }
+        }
+    }
+}
+
+impl Stage for ExpireStage {
+    fn name(&self) -> &str {
+        "expire"
+    }
+
+    fn run(&self, bookmarks: Vec<Bookmark>, ctx: &mut StageContext) -> Result<Vec<Bookmark>> {
+        let policies = self
+            .policies
+            .iter()
+            .map(|policy| {
+                policy.validate()?;
+                Ok((policy.clone(), policy.max_age()?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut kept = Vec::with_capacity(bookmarks.len());
+        let mut expired = 0;
+        for bookmark in bookmarks {
+            let Some(policy) =
+                expiration::matching_policy(&policies, &bookmark, &self.folder_separator, self.now)
+            else {
+                kept.push(bookmark);
+                continue;
+            };
+
+            let mut record = Expiration {
+                title: bookmark.title.clone(),
+                url: bookmark.url.clone(),
+                folder: bookmark.folder.clone().unwrap_or_default(),
+                date_added: bookmark.date_added.unwrap_or(self.now),
+                policy: policy.folder.clone(),
+                action: policy.action,
+                outcome: String::new(),
+                applied: true,
+            };
+            let (outcome, survivor) = self.apply(policy, bookmark);
+            record.applied = !(policy.action == ExpireAction::Archive && survivor.is_some());
+            record.outcome = outcome;
+            expired += usize::from(record.applied);
+            kept.extend(survivor);
+            ctx.expirations.push(record);
+        }
+        if expired > 0 {
+            ctx.notes.push(format!("{} bookmarks expired", expired));
+        }
+        let failed = ctx.expirations.iter().filter(|e| !e.applied).count();
+        if failed > 0 {
+            ctx.notes
+                .push(format!("{} archives failed, bookmarks kept", failed));
+        }
+        Ok(kept)
+    }
+}
+
+/// Merge bookmarks that point at the same normalized URL
+pub struct DedupeStage {
+    deduplicator: BookmarkDeduplicator,
+}
+
+impl DedupeStage {
+    pub fn new(config: DeduplicationConfig) -> Self {
+        Self {
+            deduplicator: BookmarkDeduplicator::new(config),
+        }
+    }
+
+    /// Supply history visit counts for `MergeStrategy::KeepMostVisited`
+    pub fn with_visit_counts(mut self, visit_counts: HashMap<String, i64>) -> Self {
+        self.deduplicator = self.deduplicator.with_visit_counts(visit_counts);
+        self
+    }
+
+    /// Resolve redirects through `resolver` when `resolve_redirects` is on
+    pub fn with_redirect_resolver(mut self, resolver: RedirectResolver) -> Self {
+        self.deduplicator = self.deduplicator.with_redirect_resolver(resolver);
+        self
+    }
+}
+
+impl Stage for DedupeStage {
+    fn name(&self) -> &str {
+        "dedupe"
+    }
+
+    fn run(&self, bookmarks: Vec<Bookmark>, ctx: &mut StageContext) -> Result<Vec<Bookmark>> {
+        let result = self.deduplicator.deduplicate(&bookmarks)?;
+        if result.duplicates_removed > 0 {
+            ctx.notes.push(format!(
+                "{} duplicates removed across {} URLs",
+                result.duplicates_removed,
+                result.merge_summary.len()
+            ));
+        }
+        let unique_bookmarks = result.unique_bookmarks.clone();
+        ctx.deduplication_result = Some(result);
+        Ok(unique_bookmarks)
+    }
+}
+
+/// Fill in missing titles from the bookmark URL and detect media types
+#[derive(Debug, Default)]
+pub struct EnrichStage {
+    probe_content_type: bool,
+}
+
+impl EnrichStage {
+    /// Also send a HEAD request for bookmarks whose URL doesn't reveal a
+    /// media type, and detect it from the `Content-Type`
+    pub fn with_content_probe(mut self) -> Self {
+        self.probe_content_type = true;
+        self
+    }
+}
+
+impl Stage for EnrichStage {
+    fn name(&self) -> &str {
+        "enrich"
+    }
+
+    fn run(&self, mut bookmarks: Vec<Bookmark>, ctx: &mut StageContext) -> Result<Vec<Bookmark>> {
+        let mut filled = 0;
+        let mut media: BTreeMap<String, usize> = BTreeMap::new();
+        let mut probe_failures = 0;
+        for bookmark in &mut bookmarks {
+            if bookmark.title.trim().is_empty()
+                && let Some(title) = bookmark.url.as_deref().and_then(title_from_url)
+            {
+                bookmark.title = title;
+                filled += 1;
+            }
+            if bookmark.media_type.is_none()
+                && let Some(url) = bookmark.url.as_deref()
+            {
+                bookmark.media_type = MediaType::from_url(url);
+                if bookmark.media_type.is_none() && self.probe_content_type {
+                    match media::probe_content_type(url) {
+                        Ok(detected) => bookmark.media_type = detected,
+                        Err(e) => {
+                            log::debug!("{}", e);
+                            probe_failures += 1;
+                        }
+                    }
+                }
+            }
+            if let Some(media_type) = bookmark.media_type {
+                *media.entry(media_type.to_string()).or_default() += 1;
+            }
+        }
+        if filled > 0 {
+            ctx.notes.push(format!("{} missing titles filled", filled));
+        }
+        if !media.is_empty() {
+            let counts: Vec<String> = media
+                .iter()
+                .map(|(media_type, n)| format!("{} {}", n, media_type))
+                .collect();
+            ctx.notes
+                .push(format!("media detected: {}", counts.join(", ")));
+        }
+        if probe_failures > 0 {
+            ctx.notes
+                .push(format!("{} content-type probes failed", probe_failures));
+        }
+        Ok(bookmarks)
+    }
+}
+
+fn title_from_url(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?.trim_start_matches("www.");
+    let path = parsed.path().trim_end_matches('/');
+    Some(format!("{}{}", host, path))
+}
+
+/// Assign folders using the organization rules
+pub struct OrganizeStage {
+    organizer: BookmarkOrganizer,
+}
+
+impl OrganizeStage {
+    pub fn new(config: OrganizationConfig) -> Self {
+        Self {
+            organizer: BookmarkOrganizer::new(config),
+        }
+    }
+}
+
+impl Stage for OrganizeStage {
+    fn name(&self) -> &str {
+        "organize"
+    }
+
+    fn run(&self, bookmarks: Vec<Bookmark>, ctx: &mut StageContext) -> Result<Vec<Bookmark>> {
+        let organized = self.organizer.organize(bookmarks)?;
+        let folders: HashSet<&str> = organized
+            .iter()
+            .filter_map(|b| b.folder.as_deref())
+            .collect();
+        ctx.notes
+            .push(format!("{} folders assigned", folders.len()));
+        ctx.rule_errors
+            .extend(self.organizer.rule_errors().iter().cloned());
+        Ok(organized)
+    }
+}
+
+/// Collapse whitespace in titles and empty segments in folder paths
+pub struct CleanupStage {
+    folder_separator: String,
+}
+
+impl CleanupStage {
+    pub fn new(folder_separator: &str) -> Self {
+        Self {
+            folder_separator: folder_separator.to_string(),
+        }
+    }
+}
+
+impl Stage for CleanupStage {
+    fn name(&self) -> &str {
+        "cleanup"
+    }
+
+    fn run(&self, mut bookmarks: Vec<Bookmark>, ctx: &mut StageContext) -> Result<Vec<Bookmark>> {
+        let mut titles = 0;
+        let mut folders = 0;
+        for bookmark in &mut bookmarks {
+            let title = bookmark
+                .title
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            if title != bookmark.title {
+                bookmark.title = title;
+                titles += 1;
+            }
+            if let Some(folder) = &bookmark.folder {
+                let cleaned = folder
+                    .split(self.folder_separator.as_str())
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(&self.folder_separator);
+                if &cleaned != folder {
+                    bookmark.folder = (!cleaned.is_empty()).then_some(cleaned);
+                    folders += 1;
+                }
+            }
+        }
+        if titles > 0 {
+            ctx.notes.push(format!("{} titles cleaned", titles));
+        }
+        if folders > 0 {
+            ctx.notes.push(format!("{} folder paths cleaned", folders));
+        }
+        Ok(bookmarks)
+    }
+}
+
+/// Record stars, language and archived/moved status for bookmarks of
+/// GitHub repositories, tagging them with the language and any flags
+pub struct GithubStage {
+    client: GithubClient,
+}
+
+impl GithubStage {
+    pub fn new(client: GithubClient) -> Self {
+        Self { client }
+    }
+}
+
+impl Stage for GithubStage {
+    fn name(&self) -> &str {
+        "github"
+    }
+
+    fn run(&self, mut bookmarks: Vec<Bookmark>, ctx: &mut StageContext) -> Result<Vec<Bookmark>> {
+        let summary = self.client.enrich(&mut bookmarks);
+        ctx.notes.push(format!(
+            "{} repository bookmarks: {} enriched, {} archived, {} moved, {} missing",
+            summary.repos, summary.enriched, summary.archived, summary.moved, summary.missing
+        ));
+        if let Some(error) = summary.error {
+            ctx.notes.push(format!("lookups stopped early: {}", error));
+        }
+        Ok(bookmarks)
+    }
+}
+
+/// Record Wayback Machine snapshots for bookmarks, optionally capturing
+/// pages that have none
+pub struct WaybackStage {
+    client: WaybackClient,
+    options: ArchiveOptions,
+}
+
+impl WaybackStage {
+    pub fn new(client: WaybackClient, options: ArchiveOptions) -> Self {
+        Self { client, options }
+    }
+}
+
+impl Stage for WaybackStage {
+    fn name(&self) -> &str {
+        "wayback"
+    }
+
+    fn run(&self, mut bookmarks: Vec<Bookmark>, ctx: &mut StageContext) -> Result<Vec<Bookmark>> {
+        let summary = self.client.archive(&mut bookmarks, self.options);
+        ctx.notes.push(format!(
+            "{} checked: {} snapshots found, {} saved, {} without a snapshot",
+            summary.checked, summary.found, summary.saved, summary.missing
+        ));
+        if !summary.failures.is_empty() {
+            ctx.notes.push(format!(
+                "{} lookups failed, first: {}",
+                summary.failures.len(),
+                summary.failures[0]
+            ));
+        }
+        Ok(bookmarks)
+    }
+}
+
+/// Keep folders within their quotas by moving or flagging the oldest
+/// bookmarks beyond the limit
+pub struct QuotaStage {
+    quotas: Vec<FolderQuota>,
+    folder_separator: String,
+    now: DateTime<Utc>,
+}
+
+impl QuotaStage {
+    pub fn new(quotas: &[FolderQuota], folder_separator: &str) -> Self {
+        Self {
+            quotas: quotas.to_vec(),
+            folder_separator: folder_separator.to_string(),
+            now: Utc::now(),
+        }
+    }
+
+    /// Date overflow folders with `now` instead of the current time
+    pub fn at(mut self, now: DateTime<Utc>) -> Self {
+        self.now = now;
+        self
+    }
+}
+
+impl Stage for QuotaStage {
+    fn name(&self) -> &str {
+        "quota"
+    }
+
+    fn run(&self, mut bookmarks: Vec<Bookmark>, ctx: &mut StageContext) -> Result<Vec<Bookmark>> {
+        for quota in &self.quotas {
+            quota.validate()?;
+        }
+
+        // Each folder path a quota holds is capped on its own
+        let mut folders: BTreeMap<(usize, String), Vec<usize>> = BTreeMap::new();
+        for (i, bookmark) in bookmarks.iter().enumerate() {
+            let (Some(_), Some(folder)) = (&bookmark.url, &bookmark.folder) else {
+                continue;
+            };
+            if let Some(q) = self
+                .quotas
+                .iter()
+                .position(|quota| quota.holds(folder, &self.folder_separator))
+            {
+                folders.entry((q, folder.clone())).or_default().push(i);
+            }
+        }
+
+        let mut overflowed = 0;
+        for ((q, folder), mut members) in folders {
+            let quota = &self.quotas[q];
+            if members.len() <= quota.max {
+                continue;
+            }
+            // Oldest first; undated bookmarks count as oldest
+            members.sort_by_key(|&i| bookmarks[i].date_added);
+            let excess = members.len() - quota.max;
+            for &i in &members[..excess] {
+                let bookmark = &mut bookmarks[i];
+                let outcome = match quota.overflow {
+                    OverflowAction::Move => {
+                        let to = format!(
+                            "{}{}Overflow {}",
+                            folder,
+                            self.folder_separator,
+                            self.now.format("%Y-%m-%d")
+                        );
+                        bookmark.folder = Some(to.clone());
+                        format!("moved to {}", to)
+                    }
+                    OverflowAction::Flag => {
+                        let tags = bookmark.tags.get_or_insert_with(Vec::new);
+                        if !tags.iter().any(|t| t == OVERFLOW_TAG) {
+                            tags.push(OVERFLOW_TAG.to_string());
+                        }
+                        "flagged".to_string()
+                    }
+                };
+                ctx.overflows.push(Overflow {
+                    title: bookmark.title.clone(),
+                    url: bookmark.url.clone(),
+                    folder: folder.clone(),
+                    quota: quota.folder.clone(),
+                    action: quota.overflow,
+                    outcome,
+                });
+            }
+            overflowed += excess;
+            ctx.notes
+                .push(format!("{} over by {} (max {})", folder, excess, quota.max));
+        }
+        if overflowed > 0 {
+            ctx.notes
+                .push(format!("{} bookmarks over quota", overflowed));
+        }
+        Ok(bookmarks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(id: &str, title: &str, url: &str) -> Bookmark {
+        Bookmark {
+            id: id.to_string(),
+            title: title.to_string(),
+            url: Some(url.to_string()),
+            folder: None,
+            date_added: None,
+            ..Default::default()
+        }
+    }
+
+    /// Drops every bookmark whose URL contains a marker
+    struct DropStage;
+
+    impl Stage for DropStage {
+        fn name(&self) -> &str {
+            "drop"
+        }
+
+        fn run(&self, bookmarks: Vec<Bookmark>, _ctx: &mut StageContext) -> Result<Vec<Bookmark>> {
+            Ok(bookmarks
+                .into_iter()
+                .filter(|b| !b.url.as_deref().unwrap_or("").contains("drop"))
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_default_stages_report_effects() {
+        let dedup = DeduplicationConfig::default();
+        let org = OrganizationConfig::default();
+        let stages: Vec<Box<dyn Stage>> = DEFAULT_STAGES
+            .iter()
+            .map(|name| stage_from_name(name, &dedup, &org).unwrap())
+            .collect();
+
+        let bookmarks = vec![
+            bookmark("1", "GitHub   Home", " https://github.com "),
+            bookmark("2", "GitHub", "https://www.github.com"),
+            bookmark("3", "", "https://example.com/docs/"),
+        ];
+        let (result, ctx, summaries) = run_stages(&stages, bookmarks).unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert!(ctx.deduplication_result.is_some());
+        let names: Vec<&str> = summaries.iter().map(|s| s.stage.as_str()).collect();
+        assert_eq!(names, DEFAULT_STAGES);
+        assert_eq!(summaries[0].notes, vec!["1 URLs normalized"]);
+        assert_eq!(
+            (summaries[1].input_count, summaries[1].output_count),
+            (3, 2)
+        );
+        assert_eq!(summaries[1].removed, 1);
+        assert_eq!(summaries[2].notes, vec!["1 missing titles filled"]);
+        assert_eq!(summaries[2].retitled, 1);
+        assert_eq!(summaries[3].moved, 2);
+        assert!(result.iter().any(|b| b.title == "example.com/docs"));
+
+        let json = serde_json::to_value(&summaries[1]).unwrap();
+        assert!(json["duration_ms"].is_f64());
+        assert_eq!(json["removed"], 1);
+    }
+
+    #[test]
+    fn test_stage_progress_events() {
+        let stages: Vec<Box<dyn Stage>> =
+            vec![Box::new(DropStage), Box::new(CleanupStage::new("/"))];
+        let bookmarks = vec![
+            bookmark("1", "Keep", "https://keep.example"),
+            bookmark("2", "Drop", "https://drop.example"),
+        ];
+        let events = std::cell::RefCell::new(Vec::new());
+        run_stages_with_progress(&stages, bookmarks, |event| events.borrow_mut().push(event))
+            .unwrap();
+
+        let finished: Vec<_> = events
+            .into_inner()
+            .into_iter()
+            .map(|event| match event {
+                ProgressEvent::StageFinished {
+                    stage,
+                    input,
+                    output,
+                    ..
+                } => (stage, input, output),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            finished,
+            [("drop".to_string(), 2, 1), ("cleanup".to_string(), 1, 1)]
+        );
+    }
+
+    #[test]
+    fn test_custom_stage_inserted_and_stage_skipped() {
+        let stages: Vec<Box<dyn Stage>> =
+            vec![Box::new(DropStage), Box::new(CleanupStage::new("/"))];
+        let bookmarks = vec![
+            bookmark("1", "Keep", "https://keep.example"),
+            bookmark("2", "Drop", "https://drop.example"),
+        ];
+        let (result, ctx, summaries) = run_stages(&stages, bookmarks).unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert!(ctx.deduplication_result.is_none());
+        assert_eq!(summaries[0].stage, "drop");
+        assert_eq!(summaries[0].output_count, 1);
+    }
+
+    #[test]
+    fn test_expire_stage_applies_policies() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let in_folder = |id: &str, folder: &str, age_days: i64| Bookmark {
+            folder: Some(folder.to_string()),
+            date_added: Some(now - chrono::Duration::days(age_days)),
+            ..bookmark(id, id, &format!("https://example.com/{}", id))
+        };
+        let policy = |folder: &str, action, to: Option<&str>| ExpirePolicy {
+            folder: folder.to_string(),
+            after: "30d".to_string(),
+            action,
+            to: to.map(str::to_string),
+        };
+        let stage = ExpireStage::new(
+            &[
+                policy("Temp", ExpireAction::Delete, None),
+                policy("Later", ExpireAction::Move, Some("Archive")),
+                policy("Read", ExpireAction::Archive, None),
+            ],
+            "/",
+        )
+        .at(now);
+
+        let (bookmarks, ctx, summaries) = run_stages(
+            &[Box::new(stage)],
+            vec![
+                in_folder("old", "Bar/Temp", 40),
+                in_folder("fresh", "Temp", 5),
+                in_folder("later", "Later", 90),
+                in_folder("read", "Read", 90),
+                in_folder("kept", "Work", 400),
+            ],
+        )
+        .unwrap();
+
+        let ids: Vec<&str> = bookmarks.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(ids, ["fresh", "later", "kept"]);
+        assert_eq!(bookmarks[1].folder.as_deref(), Some("Archive"));
+        let outcomes: Vec<&str> = ctx.expirations.iter().map(|e| e.outcome.as_str()).collect();
+        assert_eq!(
+            outcomes,
+            ["deleted", "moved to Archive", "would archive (preview)"]
+        );
+        assert_eq!(summaries[0].moved, 1);
+        assert_eq!(summaries[0].notes, ["3 bookmarks expired"]);
+    }
+
+    #[test]
+    fn test_quota_stage_overflows_oldest() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let in_folder = |id: &str, folder: &str, age_days: i64| Bookmark {
+            folder: Some(folder.to_string()),
+            date_added: Some(now - chrono::Duration::days(age_days)),
+            ..bookmark(id, id, &format!("https://example.com/{}", id))
+        };
+        let quota = |folder: &str, overflow| FolderQuota {
+            folder: folder.to_string(),
+            max: 2,
+            overflow,
+        };
+        let stage = QuotaStage::new(
+            &[
+                quota("Read Later", OverflowAction::Move),
+                quota("Inbox", OverflowAction::Flag),
+            ],
+            "/",
+        )
+        .at(now);
+
+        let (bookmarks, ctx, summaries) = run_stages(
+            &[Box::new(stage)],
+            vec![
+                in_folder("new", "Bar/Read Later", 1),
+                in_folder("oldest", "Bar/Read Later", 90),
+                in_folder("mid", "Bar/Read Later", 10),
+                in_folder("inbox1", "Inbox", 3),
+                in_folder("inbox2", "Inbox", 2),
+                in_folder("work", "Work", 400),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(bookmarks.len(), 6);
+        assert_eq!(
+            bookmarks[1].folder.as_deref(),
+            Some("Bar/Read Later/Overflow 2023-11-14")
+        );
+        assert_eq!(bookmarks[2].folder.as_deref(), Some("Bar/Read Later"));
+        assert!(bookmarks[3].tags.is_none());
+        assert_eq!(ctx.overflows.len(), 1);
+        assert_eq!(summaries[0].moved, 1);
+        assert_eq!(
+            summaries[0].notes,
+            ["Bar/Read Later over by 1 (max 2)", "1 bookmarks over quota"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_stage_name() {
+        let err = stage_from_name(
+            "bogus",
+            &DeduplicationConfig::default(),
+            &OrganizationConfig::default(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("normalize"));
+    }
+}
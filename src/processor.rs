@@ -1,13 +1,26 @@
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::PathBuf;
 
+use crate::config::GithubConfig;
 use crate::deduplication::{
-    find_potential_duplicates, BookmarkDeduplicator, DeduplicationConfig, DeduplicationResult,
+    BookmarkDeduplicator, DeduplicationConfig, DeduplicationResult, RedirectResolver,
+    find_potential_duplicates,
 };
-use crate::exporter::{Bookmark, BrowserData};
+use crate::expiration::{Expiration, ExpirePolicy};
+use crate::exporter::{
+    Bookmark, BrowserData, ExportFormat, Provenance, flatten_bookmarks, with_checksum,
+};
+use crate::integrations::github::GithubClient;
+use crate::integrations::wayback::{ArchiveOptions, WaybackClient};
 use crate::organization::{BookmarkOrganizer, OrganizationConfig};
+use crate::pipeline::{
+    self, CleanupStage, DedupeStage, EnrichStage, ExpireStage, GithubStage, NormalizeStage,
+    OrganizeStage, QuotaStage, Stage, StageSummary, WaybackStage,
+};
+use crate::progress::ProgressEvent;
+use crate::quota::{FolderQuota, Overflow};
 
 #[derive(Debug)]
 pub struct ProcessingConfig {
@@ -15,6 +28,88 @@ pub struct ProcessingConfig {
     pub organization_config: OrganizationConfig,
     pub dry_run: bool,
     pub backup_original: bool,
+    /// History visit counts keyed by URL, for `MergeStrategy::KeepMostVisited`
+    pub visit_counts: HashMap<String, i64>,
+    /// Folder expiration policies; the expire stage runs first when any are set
+    pub expire_policies: Vec<ExpirePolicy>,
+    /// Folder size limits; the quota stage runs last when any are set
+    pub folder_quotas: Vec<FolderQuota>,
+    /// Let the enrich stage send HEAD requests to detect media types
+    pub probe_media: bool,
+    /// Look up Wayback Machine snapshots; the wayback stage runs after
+    /// cleanup when set
+    pub archive: Option<ArchiveOptions>,
+    /// Look up GitHub repositories with these credentials; the github stage
+    /// runs after enrich when set
+    pub github: Option<GithubConfig>,
+}
+
+impl ProcessingConfig {
+    /// Build a built-in stage by name using this config
+    pub fn stage(&self, name: &str) -> Result<Box<dyn Stage>> {
+        match name {
+            "dedupe" => Ok(Box::new(self.dedupe_stage())),
+            "enrich" => Ok(Box::new(self.enrich_stage())),
+            "expire" => Ok(Box::new(self.expire_stage())),
+            "wayback" => Ok(Box::new(self.wayback_stage())),
+            "github" => Ok(Box::new(self.github_stage())),
+            "quota" => Ok(Box::new(QuotaStage::new(
+                &self.folder_quotas,
+                &self.organization_config.folder_separator,
+            ))),
+            _ => pipeline::stage_from_name(
+                name,
+                &self.deduplication_config,
+                &self.organization_config,
+            ),
+        }
+    }
+
+    /// Resolved redirects are cached across runs in the cache directory
+    fn dedupe_stage(&self) -> DedupeStage {
+        let stage = DedupeStage::new(self.deduplication_config.clone())
+            .with_visit_counts(self.visit_counts.clone());
+        if self.deduplication_config.resolve_redirects {
+            let cache_file = crate::paths::get().cache.join("redirects.json");
+            stage.with_redirect_resolver(RedirectResolver::new().with_cache_file(cache_file))
+        } else {
+            stage
+        }
+    }
+
+    fn enrich_stage(&self) -> EnrichStage {
+        let stage = EnrichStage::default();
+        if self.probe_media {
+            stage.with_content_probe()
+        } else {
+            stage
+        }
+    }
+
+    /// Dry runs look snapshots up but never submit pages to Save Page Now
+    fn wayback_stage(&self) -> WaybackStage {
+        let mut options = self.archive.unwrap_or_default();
+        options.save_missing &= !self.dry_run;
+        WaybackStage::new(WaybackClient::new(), options)
+    }
+
+    fn github_stage(&self) -> GithubStage {
+        let token = self.github.as_ref().and_then(GithubConfig::token);
+        GithubStage::new(GithubClient::new(token.as_deref()))
+    }
+
+    /// Pages are only archived when the run is not a dry run
+    fn expire_stage(&self) -> ExpireStage {
+        let stage = ExpireStage::new(
+            &self.expire_policies,
+            &self.organization_config.folder_separator,
+        );
+        if self.dry_run {
+            stage
+        } else {
+            stage.with_archive_dir(crate::paths::get().archives.clone())
+        }
+    }
 }
 
 impl Default for ProcessingConfig {
@@ -24,6 +119,12 @@ impl Default for ProcessingConfig {
             organization_config: OrganizationConfig::default(),
             dry_run: false,
             backup_original: true,
+            visit_counts: HashMap::new(),
+            expire_policies: Vec::new(),
+            folder_quotas: Vec::new(),
+            probe_media: false,
+            archive: None,
+            github: None,
         }
     }
 }
@@ -35,23 +136,108 @@ pub struct ProcessingResult {
     pub processing_summary: ProcessingSummary,
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct ProcessingSummary {
     pub original_count: usize,
     pub final_count: usize,
     pub duplicates_removed: usize,
     pub folders_created: usize,
+    #[serde(
+        rename = "processing_time_ms",
+        serialize_with = "pipeline::serialize_millis"
+    )]
     pub processing_time: std::time::Duration,
     pub folder_distribution: HashMap<String, usize>,
+    /// Organization rules skipped because their pattern failed to compile
+    pub rule_errors: Vec<String>,
+    /// Per-stage effects, in the order the stages ran
+    pub stages: Vec<StageSummary>,
+    /// Bookmarks handled by expiration policies
+    pub expirations: Vec<Expiration>,
+    /// Bookmarks moved or flagged by folder quotas
+    pub overflows: Vec<Overflow>,
 }
 
 pub struct BookmarkProcessor {
     config: ProcessingConfig,
+    stages: Vec<Box<dyn Stage>>,
 }
 
 impl BookmarkProcessor {
+    /// Create a processor running the default stages; dedupe is left out
+    /// when URL normalization is disabled, expire runs first when
+    /// expiration policies are configured, github follows enrich when
+    /// repository lookups are asked for, wayback follows cleanup when
+    /// archiving is asked for, and quota runs last when folder quotas are
     pub fn new(config: ProcessingConfig) -> Self {
-        Self { config }
+        let mut stages: Vec<Box<dyn Stage>> = Vec::new();
+        if !config.expire_policies.is_empty() {
+            stages.push(Box::new(config.expire_stage()));
+        }
+        stages.push(Box::new(NormalizeStage));
+        if config.deduplication_config.normalize_urls {
+            stages.push(Box::new(config.dedupe_stage()));
+        }
+        stages.push(Box::new(config.enrich_stage()));
+        if config.github.is_some() {
+            stages.push(Box::new(config.github_stage()));
+        }
+        stages.push(Box::new(OrganizeStage::new(
+            config.organization_config.clone(),
+        )));
+        stages.push(Box::new(CleanupStage::new(
+            &config.organization_config.folder_separator,
+        )));
+        if config.archive.is_some() {
+            stages.push(Box::new(config.wayback_stage()));
+        }
+        if !config.folder_quotas.is_empty() {
+            stages.push(Box::new(QuotaStage::new(
+                &config.folder_quotas,
+                &config.organization_config.folder_separator,
+            )));
+        }
+        Self { config, stages }
+    }
+
+    /// Replace the pipeline, e.g. to reorder, skip, or insert custom stages
+    pub fn with_stages(mut self, stages: Vec<Box<dyn Stage>>) -> Self {
+        self.stages = stages;
+        self
+    }
+
+    pub fn config(&self) -> &ProcessingConfig {
+        &self.config
+    }
+
+    /// Settings recorded in the provenance of processed exports, and in the
+    /// undo journal
+    pub fn provenance_settings(&self) -> BTreeMap<String, String> {
+        let dedupe = &self.config.deduplication_config;
+        BTreeMap::from([
+            ("stages".to_string(), self.stage_names().join(",")),
+            (
+                "merge_strategy".to_string(),
+                format!("{:?}", dedupe.merge_strategy),
+            ),
+            (
+                "normalize_urls".to_string(),
+                dedupe.normalize_urls.to_string(),
+            ),
+            (
+                "organization_rules".to_string(),
+                self.config
+                    .organization_config
+                    .custom_rules
+                    .len()
+                    .to_string(),
+            ),
+        ])
+    }
+
+    /// Names of the stages this processor runs, in order
+    pub fn stage_names(&self) -> Vec<&str> {
+        self.stages.iter().map(|s| s.name()).collect()
     }
 
     pub fn process_browser_data(
@@ -68,50 +254,54 @@ impl BookmarkProcessor {
         Ok(results)
     }
 
+    /// Run the stages over `bookmarks`; nested input is flattened first so
+    /// bookmarks inside `children` are processed rather than dropped
     pub fn process_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<ProcessingResult> {
+        self.process_bookmarks_with_progress(bookmarks, |_| {})
+    }
+
+    /// [`Self::process_bookmarks`], reporting each stage as it finishes
+    pub fn process_bookmarks_with_progress(
+        &self,
+        bookmarks: &[Bookmark],
+        on_progress: impl Fn(ProgressEvent),
+    ) -> Result<ProcessingResult> {
         let start_time = std::time::Instant::now();
+        let bookmarks = flatten_bookmarks(bookmarks);
         let original_count = bookmarks.len();
 
-        // Step 1: Deduplicate bookmarks
-        let (unique_bookmarks, deduplication_result) =
-            if self.config.deduplication_config.normalize_urls {
-                let deduplicator =
-                    BookmarkDeduplicator::new(self.config.deduplication_config.clone());
-                let result = deduplicator.deduplicate(bookmarks)?;
-                (result.unique_bookmarks.clone(), Some(result))
-            } else {
-                (bookmarks.to_vec(), None)
-            };
+        let (processed_bookmarks, ctx, stages) =
+            pipeline::run_stages_with_progress(&self.stages, bookmarks, on_progress)?;
 
-        // Step 2: Organize bookmarks into folders
-        let organizer = BookmarkOrganizer::new(self.config.organization_config.clone());
-        let organized_bookmarks = organizer.organize(unique_bookmarks)?;
-
-        // Step 3: Create processing summary
-        let folder_distribution: HashMap<String, usize> = organizer
-            .create_folder_structure(&organized_bookmarks)
-            .into_iter()
-            .map(|(folder, bookmarks)| (folder, bookmarks.len()))
-            .collect();
+        let mut folder_distribution: HashMap<String, usize> = HashMap::new();
+        for bookmark in &processed_bookmarks {
+            let folder = bookmark.folder.as_deref().unwrap_or("Uncategorized");
+            *folder_distribution.entry(folder.to_string()).or_insert(0) += 1;
+        }
 
         let processing_time = start_time.elapsed();
-        let final_count = organized_bookmarks.len();
+        let final_count = processed_bookmarks.len();
 
         let processing_summary = ProcessingSummary {
             original_count,
             final_count,
-            duplicates_removed: deduplication_result
+            duplicates_removed: ctx
+                .deduplication_result
                 .as_ref()
                 .map(|r| r.duplicates_removed)
                 .unwrap_or(0),
             folders_created: folder_distribution.len(),
             processing_time,
             folder_distribution,
+            rule_errors: ctx.rule_errors,
+            stages,
+            expirations: ctx.expirations,
+            overflows: ctx.overflows,
         };
 
         Ok(ProcessingResult {
-            processed_bookmarks: organized_bookmarks,
-            deduplication_result,
+            processed_bookmarks,
+            deduplication_result: ctx.deduplication_result,
             processing_summary,
         })
     }
@@ -124,7 +314,7 @@ impl BookmarkProcessor {
         }
 
         // Add source information to bookmarks
-        for (_source_index, _bookmark) in all_bookmarks.iter_mut().enumerate() {
+        for _bookmark in all_bookmarks.iter_mut() {
             // We could add metadata about the source here if needed
         }
 
@@ -140,16 +330,23 @@ impl BookmarkProcessor {
         output_path: &PathBuf,
     ) -> Result<()> {
         // Create BrowserData structure for the processed bookmarks
-        let browser_data = BrowserData {
+        let mut browser_data = BrowserData {
             browser: "Processed".to_string(),
             profile: "Deduplicated & Organized".to_string(),
             export_date: chrono::Utc::now(),
             bookmarks: Some(bookmarks.to_vec()),
             history: None,
             passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
         };
+        browser_data.provenance =
+            Some(Provenance::new("process", self.provenance_settings()).describe(&browser_data));
 
-        let yaml_content = serde_yaml::to_string(&[browser_data])?;
+        let yaml_content =
+            with_checksum(serde_yaml::to_string(&[browser_data])?, ExportFormat::Yaml);
 
         if self.config.dry_run {
             println!("DRY RUN: Would write to {}", output_path.display());
@@ -201,6 +398,36 @@ impl BookmarkProcessor {
             result.processing_summary.processing_time
         ));
 
+        if !result.processing_summary.rule_errors.is_empty() {
+            report.push_str("## Skipped Rules\n\n");
+            for error in &result.processing_summary.rule_errors {
+                report.push_str(&format!("- {}\n", error));
+            }
+            report.push('\n');
+        }
+
+        if !result.processing_summary.stages.is_empty() {
+            report.push_str("## Pipeline Stages\n\n");
+            for stage in &result.processing_summary.stages {
+                report.push_str(&format!(
+                    "- {}: {} → {} in {:?} (removed {}, added {}, moved {}, retitled {})",
+                    stage.stage,
+                    stage.input_count,
+                    stage.output_count,
+                    stage.duration,
+                    stage.removed,
+                    stage.added,
+                    stage.moved,
+                    stage.retitled
+                ));
+                if !stage.notes.is_empty() {
+                    report.push_str(&format!(": {}", stage.notes.join("; ")));
+                }
+                report.push('\n');
+            }
+            report.push('\n');
+        }
+
         // Deduplication details
         if let Some(ref dedup_result) = result.deduplication_result {
             report.push_str("## Deduplication Details\n\n");
@@ -222,6 +449,35 @@ impl BookmarkProcessor {
             report.push('\n');
         }
 
+        if !result.processing_summary.expirations.is_empty() {
+            report.push_str("## Expired Bookmarks\n\n");
+            for expiration in &result.processing_summary.expirations {
+                report.push_str(&format!(
+                    "- {} ({}) in {}, added {}: {}\n",
+                    expiration.title,
+                    expiration.url.as_deref().unwrap_or_default(),
+                    expiration.folder,
+                    expiration.date_added.format("%Y-%m-%d"),
+                    expiration.outcome
+                ));
+            }
+            report.push('\n');
+        }
+
+        if !result.processing_summary.overflows.is_empty() {
+            report.push_str("## Folder Quotas\n\n");
+            for overflow in &result.processing_summary.overflows {
+                report.push_str(&format!(
+                    "- {} ({}) in {}: {}\n",
+                    overflow.title,
+                    overflow.url.as_deref().unwrap_or_default(),
+                    overflow.folder,
+                    overflow.outcome
+                ));
+            }
+            report.push('\n');
+        }
+
         // Folder distribution
         report.push_str("## Folder Distribution\n\n");
         let mut sorted_folders: Vec<_> = result
@@ -281,7 +537,7 @@ impl BookmarkProcessor {
             println!("  Folder: {:?}", bookmark.folder);
 
             // Process a single bookmark to show the change
-            let processed = self.process_bookmarks(&[bookmark.clone()])?;
+            let processed = self.process_bookmarks(std::slice::from_ref(bookmark))?;
             if let Some(processed_bookmark) = processed.processed_bookmarks.first() {
                 println!("**After:**");
                 println!("  Title: {}", processed_bookmark.title);
@@ -342,7 +598,7 @@ mod tests {
                 url: Some("https://github.com".to_string()),
                 folder: None,
                 date_added: Some(Utc::now()),
-                children: None,
+                ..Default::default()
             },
             Bookmark {
                 id: "2".to_string(),
@@ -350,7 +606,7 @@ mod tests {
                 url: Some("http://www.github.com".to_string()),
                 folder: Some("Bookmarks".to_string()),
                 date_added: Some(Utc::now()),
-                children: None,
+                ..Default::default()
             },
         ];
 
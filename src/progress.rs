@@ -0,0 +1,36 @@
+//! Progress events for library callers.
+//!
+//! Long-running calls have variants taking an `on_progress` callback
+//! ([`crate::exporter::collect_browser_data_with_progress`],
+//! [`crate::processor::BookmarkProcessor::process_bookmarks_with_progress`],
+//! [`crate::integrations::wayback::WaybackClient::archive_with_progress`] and
+//! [`crate::graph::GraphBuilder::with_progress`]), so GUIs and the MCP server
+//! can show progress without parsing what the CLI prints. Events arrive on
+//! the calling thread, in order.
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent {
+    /// Reading a source began; browser profiles are named `browser/profile`
+    SourceStarted { source: String },
+    /// Rows of one kind (`bookmarks`, `history`, `passwords`) read from a source
+    RowsRead {
+        source: String,
+        kind: &'static str,
+        count: usize,
+    },
+    /// `done` of a stage's `total` items are handled, e.g. URLs checked
+    ItemsDone {
+        stage: String,
+        done: usize,
+        total: usize,
+    },
+    /// A stage turned `input` items into `output`
+    StageFinished {
+        stage: String,
+        input: usize,
+        output: usize,
+        duration: Duration,
+    },
+}
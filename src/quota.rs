@@ -0,0 +1,99 @@
+//! Folder quotas.
+//!
+//! A quota such as `{folder: "Read Later", max: 200, overflow: move}` in the
+//! config keeps a working folder at most `max` bookmarks. When `process`
+//! finds more, the oldest are moved to a dated subfolder (`Read
+//! Later/Overflow 2024-05-01`) or tagged `overflow` in place. Each one is
+//! recorded as an [`Overflow`] for the processing report.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+/// Tag added to bookmarks flagged by a quota
+pub const OVERFLOW_TAG: &str = "overflow";
+
+/// What to do with bookmarks beyond a folder's quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverflowAction {
+    /// Move them to a dated `Overflow` subfolder
+    Move,
+    /// Leave them in place, tagged `overflow`
+    Flag,
+}
+
+/// One folder's size limit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderQuota {
+    /// Folder name or path; only bookmarks directly in it count
+    pub folder: String,
+    /// Most bookmarks the folder may hold
+    pub max: usize,
+    pub overflow: OverflowAction,
+}
+
+impl FolderQuota {
+    /// Check the quota can be enforced
+    pub fn validate(&self) -> Result<()> {
+        if self.folder.trim().is_empty() {
+            return Err(anyhow!("Folder quota has empty folder"));
+        }
+        if self.max == 0 {
+            return Err(anyhow!(
+                "Folder quota for '{}' must allow at least one bookmark",
+                self.folder
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether `folder` is the quota's folder. The quota folder may be the
+    /// tail of a longer path, so `Read Later` holds `toolbar/Read Later` but
+    /// not its subfolders.
+    pub fn holds(&self, folder: &str, separator: &str) -> bool {
+        let wanted = segments(&self.folder, separator);
+        let path = segments(folder, separator);
+        !wanted.is_empty() && path.ends_with(&wanted)
+    }
+}
+
+fn segments<'a>(path: &'a str, separator: &str) -> Vec<&'a str> {
+    path.split(separator)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Audit record for one bookmark over its folder's quota
+#[derive(Debug, Clone, Serialize)]
+pub struct Overflow {
+    pub title: String,
+    pub url: Option<String>,
+    pub folder: String,
+    /// The quota folder that overflowed
+    pub quota: String,
+    pub action: OverflowAction,
+    /// What was done, e.g. `moved to Read Later/Overflow 2024-05-01` or `flagged`
+    pub outcome: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_holds_folder_but_not_subfolders() {
+        let quota = FolderQuota {
+            folder: "Read Later".to_string(),
+            max: 2,
+            overflow: OverflowAction::Move,
+        };
+        assert!(quota.holds("Read Later", "/"));
+        assert!(quota.holds("toolbar/Read Later", "/"));
+        assert!(!quota.holds("Read Later/Overflow 2024-05-01", "/"));
+        assert!(!quota.holds("Reading", "/"));
+
+        let empty = FolderQuota { max: 0, ..quota };
+        assert!(empty.validate().is_err());
+    }
+}
@@ -0,0 +1,467 @@
+//! Two-way bookmark synchronization between browsers.
+//!
+//! Both browsers' bookmarks are compared, URL by URL, with what they agreed
+//! on after the previous sync (the baseline, kept in the store). A side that
+//! differs from the baseline has changed; when only one side changed, its
+//! version is taken, and when both changed differently the
+//! [`ConflictPolicy`] decides. Folders are compared by root (toolbar, other,
+//! mobile), so Chrome's `bookmark_bar/Dev` and Firefox's `toolbar/Dev` are
+//! the same folder.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+use crate::browser::Browser;
+use crate::exporter::{Bookmark, FOLDER_PATH_SEPARATOR, flatten_bookmarks};
+
+/// Root folder names across browsers, grouped by the root they stand for.
+/// Firefox's menu has no Chrome counterpart and counts as Other.
+const ROOT_GROUPS: &[(&str, &[&str])] = &[
+    (
+        "toolbar",
+        &[
+            "toolbar",
+            "bookmark_bar",
+            "bookmarks bar",
+            "bookmarks toolbar",
+            "favorites bar",
+        ],
+    ),
+    (
+        "other",
+        &[
+            "other",
+            "unfiled",
+            "menu",
+            "other bookmarks",
+            "other favorites",
+            "bookmarks menu",
+        ],
+    ),
+    ("mobile", &["mobile", "synced", "mobile bookmarks"]),
+];
+
+/// How to settle a URL both browsers changed differently since the last sync
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep the more recently added copy; an edit beats a deletion, since
+    /// browsers don't record when a bookmark was deleted
+    Newest,
+    /// Never delete: deletions on either side are undone, and the first
+    /// browser's version wins edit conflicts
+    Union,
+    /// Ask for each conflict
+    Interactive,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "newest" => Ok(ConflictPolicy::Newest),
+            "union" => Ok(ConflictPolicy::Union),
+            "interactive" => Ok(ConflictPolicy::Interactive),
+            _ => Err(anyhow!(
+                "Unknown conflict policy: {} (expected newest, union or interactive)",
+                s
+            )),
+        }
+    }
+}
+
+/// One browser's version of a URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub title: String,
+    /// Folder path with the root normalized, e.g. `toolbar/Dev`
+    pub folder: String,
+    pub date_added: Option<DateTime<Utc>>,
+}
+
+impl Entry {
+    /// Same title and folder; when a bookmark was added doesn't make it differ
+    fn same(&self, other: &Entry) -> bool {
+        self.title == other.title && self.folder == other.folder
+    }
+}
+
+fn same(a: Option<&Entry>, b: Option<&Entry>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.same(b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Which browser's version to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// A URL both browsers changed differently; `None` means deleted
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub url: String,
+    pub left: Option<Entry>,
+    pub right: Option<Entry>,
+}
+
+/// What one browser has to change to match the merged set
+#[derive(Debug, Default)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub updated: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct Reconciliation {
+    /// The bookmark set both browsers end up with, keyed by URL
+    pub merged: BTreeMap<String, Entry>,
+    pub left_changes: Changes,
+    pub right_changes: Changes,
+    /// Conflicts and the side that won each
+    pub conflicts: Vec<(Conflict, Side)>,
+}
+
+/// Merge `left` and `right` against `baseline`. `ask` settles conflicts
+/// under [`ConflictPolicy::Interactive`] and is not called otherwise.
+pub fn reconcile(
+    baseline: &BTreeMap<String, Entry>,
+    left: &[Bookmark],
+    right: &[Bookmark],
+    policy: ConflictPolicy,
+    mut ask: impl FnMut(&Conflict) -> Result<Side>,
+) -> Result<Reconciliation> {
+    let left = entries(left);
+    let right = entries(right);
+    let urls: BTreeSet<&String> = baseline
+        .keys()
+        .chain(left.keys())
+        .chain(right.keys())
+        .collect();
+
+    let mut merged = BTreeMap::new();
+    let mut conflicts = Vec::new();
+    for url in urls {
+        let base = baseline.get(url);
+        let (l, r) = (left.get(url), right.get(url));
+        let winner = if same(l, r) || same(r, base) {
+            l
+        } else if same(l, base) {
+            r
+        } else {
+            let conflict = Conflict {
+                url: url.clone(),
+                left: l.cloned(),
+                right: r.cloned(),
+            };
+            let side = match (policy, l, r) {
+                (ConflictPolicy::Interactive, _, _) => ask(&conflict)?,
+                (_, Some(_), None) => Side::Left,
+                (_, None, Some(_)) => Side::Right,
+                (ConflictPolicy::Newest, Some(l), Some(r)) if r.date_added > l.date_added => {
+                    Side::Right
+                }
+                _ => Side::Left,
+            };
+            conflicts.push((conflict, side));
+            match side {
+                Side::Left => l,
+                Side::Right => r,
+            }
+        };
+
+        // Under union a deletion is undone with whichever copy survives
+        let winner = match policy {
+            ConflictPolicy::Union => winner.or(l).or(r),
+            _ => winner,
+        };
+        if let Some(entry) = winner {
+            merged.insert(url.clone(), entry.clone());
+        }
+    }
+
+    Ok(Reconciliation {
+        left_changes: changes(&left, &merged),
+        right_changes: changes(&right, &merged),
+        merged,
+        conflicts,
+    })
+}
+
+fn changes(side: &BTreeMap<String, Entry>, merged: &BTreeMap<String, Entry>) -> Changes {
+    let mut changes = Changes::default();
+    for (url, entry) in merged {
+        match side.get(url) {
+            None => changes.added.push(url.clone()),
+            Some(own) if !own.same(entry) => changes.updated.push(url.clone()),
+            Some(_) => {}
+        }
+    }
+    changes.removed = side
+        .keys()
+        .filter(|url| !merged.contains_key(*url))
+        .cloned()
+        .collect();
+    changes
+}
+
+/// One entry per URL (the first one, for URLs bookmarked twice)
+pub fn entries(bookmarks: &[Bookmark]) -> BTreeMap<String, Entry> {
+    let mut entries = BTreeMap::new();
+    for bookmark in flatten_bookmarks(bookmarks) {
+        let Some(url) = bookmark.url.filter(|u| !u.is_empty()) else {
+            continue;
+        };
+        entries.entry(url).or_insert_with(|| Entry {
+            title: bookmark.title,
+            folder: canonical_folder(bookmark.folder.as_deref()),
+            date_added: bookmark.date_added,
+        });
+    }
+    entries
+}
+
+/// A folder path with its root renamed to `toolbar`, `other` or `mobile`;
+/// paths outside any known root are treated as inside Other
+pub fn canonical_folder(path: Option<&str>) -> String {
+    let segments: Vec<&str> = path
+        .unwrap_or_default()
+        .split(FOLDER_PATH_SEPARATOR)
+        .filter(|s| !s.is_empty())
+        .collect();
+    let Some(first) = segments.first() else {
+        return "other".to_string();
+    };
+    let first = first.to_lowercase();
+    let (root, rest) = match ROOT_GROUPS
+        .iter()
+        .find(|(_, names)| names.contains(&first.as_str()))
+    {
+        Some((root, _)) => (*root, &segments[1..]),
+        None => ("other", &segments[..]),
+    };
+    std::iter::once(root)
+        .chain(rest.iter().copied())
+        .collect::<Vec<_>>()
+        .join(FOLDER_PATH_SEPARATOR)
+}
+
+/// A canonical folder path in `browser`'s own root names
+fn browser_folder(folder: &str, browser: &Browser) -> String {
+    let (root, rest) = folder
+        .split_once(FOLDER_PATH_SEPARATOR)
+        .unwrap_or((folder, ""));
+    let root = match (browser, root) {
+        (Browser::Firefox, "other") => "unfiled",
+        (Browser::Firefox, root) => root,
+        (_, "toolbar") => "bookmark_bar",
+        (_, "mobile") => "synced",
+        (_, _) => "other",
+    };
+    if rest.is_empty() {
+        root.to_string()
+    } else {
+        format!("{}{}{}", root, FOLDER_PATH_SEPARATOR, rest)
+    }
+}
+
+/// The bookmarks to write to a browser so it matches `merged`. Bookmarks the
+/// merge left alone are passed through untouched, so their folder naming,
+/// ids and any duplicates survive; changed ones keep their own folder when
+/// only the title changed.
+pub fn target_bookmarks(
+    own: &[Bookmark],
+    merged: &BTreeMap<String, Entry>,
+    browser: &Browser,
+) -> Vec<Bookmark> {
+    let own_entries = entries(own);
+    let mut seen = BTreeSet::new();
+    let mut target = Vec::new();
+    for bookmark in flatten_bookmarks(own) {
+        let Some(url) = bookmark.url.clone().filter(|u| !u.is_empty()) else {
+            continue;
+        };
+        let Some(entry) = merged.get(&url) else {
+            continue;
+        };
+        let first = seen.insert(url.clone());
+        if own_entries.get(&url).is_some_and(|own| own.same(entry)) {
+            target.push(bookmark);
+        } else if first {
+            let folder = if canonical_folder(bookmark.folder.as_deref()) == entry.folder {
+                bookmark.folder.clone()
+            } else {
+                Some(browser_folder(&entry.folder, browser))
+            };
+            target.push(Bookmark {
+                title: entry.title.clone(),
+                folder,
+                ..bookmark
+            });
+        }
+    }
+
+    for (url, entry) in merged {
+        if !seen.contains(url) {
+            target.push(Bookmark {
+                id: String::new(),
+                title: entry.title.clone(),
+                url: Some(url.clone()),
+                folder: Some(browser_folder(&entry.folder, browser)),
+                date_added: entry.date_added,
+                ..Default::default()
+            });
+        }
+    }
+    target
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(title: &str, url: &str, folder: &str, added: i64) -> Bookmark {
+        Bookmark {
+            id: String::new(),
+            title: title.to_string(),
+            url: Some(url.to_string()),
+            folder: Some(folder.to_string()),
+            date_added: DateTime::from_timestamp(added, 0),
+            ..Default::default()
+        }
+    }
+
+    fn never_asked(_: &Conflict) -> Result<Side> {
+        panic!("only interactive sync asks")
+    }
+
+    #[test]
+    fn test_canonical_folder() {
+        assert_eq!(canonical_folder(Some("bookmark_bar/Dev")), "toolbar/Dev");
+        assert_eq!(canonical_folder(Some("toolbar/Dev")), "toolbar/Dev");
+        assert_eq!(canonical_folder(Some("menu")), "other");
+        assert_eq!(canonical_folder(Some("Reading")), "other/Reading");
+        assert_eq!(canonical_folder(None), "other");
+        assert_eq!(
+            browser_folder("other/Reading", &Browser::Firefox),
+            "unfiled/Reading"
+        );
+        assert_eq!(browser_folder("toolbar", &Browser::Chrome), "bookmark_bar");
+    }
+
+    #[test]
+    fn test_one_sided_changes_propagate() {
+        let baseline = entries(&[
+            bookmark("Rust", "https://rust-lang.org/", "toolbar", 1),
+            bookmark("Old", "https://old.example/", "toolbar", 1),
+        ]);
+        // Chrome added docs.rs and deleted Old; Firefox renamed Rust
+        let chrome = [
+            bookmark("Rust", "https://rust-lang.org/", "bookmark_bar", 1),
+            bookmark("Docs", "https://docs.rs/", "bookmark_bar/Dev", 2),
+        ];
+        let firefox = [
+            bookmark("Rust Lang", "https://rust-lang.org/", "toolbar", 1),
+            bookmark("Old", "https://old.example/", "toolbar", 1),
+        ];
+
+        let result = reconcile(
+            &baseline,
+            &chrome,
+            &firefox,
+            ConflictPolicy::Newest,
+            never_asked,
+        )
+        .unwrap();
+        assert!(result.conflicts.is_empty());
+        assert_eq!(
+            result.merged.keys().collect::<Vec<_>>(),
+            ["https://docs.rs/", "https://rust-lang.org/"]
+        );
+        assert_eq!(result.left_changes.updated, ["https://rust-lang.org/"]);
+        assert_eq!(result.right_changes.added, ["https://docs.rs/"]);
+        assert_eq!(result.right_changes.removed, ["https://old.example/"]);
+
+        let target = target_bookmarks(&firefox, &result.merged, &Browser::Firefox);
+        assert_eq!(target.len(), 2);
+        assert_eq!(target[1].folder.as_deref(), Some("toolbar/Dev"));
+        let target = target_bookmarks(&chrome, &result.merged, &Browser::Chrome);
+        assert_eq!(target[0].title, "Rust Lang");
+        assert_eq!(target[0].folder.as_deref(), Some("bookmark_bar"));
+    }
+
+    #[test]
+    fn test_conflict_policies() {
+        let baseline = entries(&[
+            bookmark("A", "https://a.example/", "toolbar", 1),
+            bookmark("B", "https://b.example/", "toolbar", 1),
+        ]);
+        // Both retitled A; Chrome deleted B while Firefox moved it
+        let chrome = [bookmark(
+            "A chrome",
+            "https://a.example/",
+            "bookmark_bar",
+            5,
+        )];
+        let firefox = [
+            bookmark("A firefox", "https://a.example/", "toolbar", 1),
+            bookmark("B", "https://b.example/", "toolbar/Later", 1),
+        ];
+
+        let newest = reconcile(
+            &baseline,
+            &chrome,
+            &firefox,
+            ConflictPolicy::Newest,
+            never_asked,
+        )
+        .unwrap();
+        assert_eq!(newest.conflicts.len(), 2);
+        assert_eq!(newest.merged["https://a.example/"].title, "A chrome");
+        assert_eq!(newest.merged["https://b.example/"].folder, "toolbar/Later");
+
+        let mut asked = Vec::new();
+        let interactive = reconcile(
+            &baseline,
+            &chrome,
+            &firefox,
+            ConflictPolicy::Interactive,
+            |conflict| {
+                asked.push(conflict.url.clone());
+                Ok(Side::Left)
+            },
+        )
+        .unwrap();
+        assert_eq!(asked, ["https://a.example/", "https://b.example/"]);
+        assert!(!interactive.merged.contains_key("https://b.example/"));
+
+        // Union undoes a one-sided deletion too
+        let firefox = [
+            bookmark("A", "https://a.example/", "toolbar", 1),
+            bookmark("B", "https://b.example/", "toolbar", 1),
+        ];
+        let union = reconcile(
+            &baseline,
+            &chrome,
+            &firefox,
+            ConflictPolicy::Union,
+            never_asked,
+        )
+        .unwrap();
+        assert_eq!(union.merged["https://a.example/"].title, "A chrome");
+        assert_eq!(union.merged["https://b.example/"].title, "B");
+    }
+}
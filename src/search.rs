@@ -1,158 +1,739 @@
-use crate::browser::Browser;
-use crate::exporter::{export_data, BrowserData, Bookmark};
-use anyhow::{anyhow, Result};
-use dialoguer::Select;
-use serde_yaml;
-use std::fs;
-use std::path::PathBuf;
+use crate::config::AppConfig;
+use crate::edit::in_folder;
+use crate::exporter::{Bookmark, UrlEntry, load_browser_data};
+use crate::media::MediaType;
+use crate::store::Store;
+use crate::suggest::{Suggestions, suggest};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+#[cfg(feature = "cli")]
+use dialoguer::{Confirm, Select};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// How old the store may get before searches warn that it is stale
+const STALE_AFTER: Duration = Duration::days(1);
 
 pub struct SearchOptions {
     pub title_only: bool,
     pub url_only: bool,
     pub limit: usize,
+    /// Only bookmarks of this media type
+    pub media_type: Option<MediaType>,
+    pub sort: SortOrder,
 }
 
-pub fn search_bookmarks_internal(query: &str, options: &SearchOptions) -> Result<Vec<Bookmark>> {
-    let temp_file = PathBuf::from("/tmp/bookmark_search_data.yaml");
-    let browsers = ["Chrome", "Firefox", "Safari", "Edge"];
-    let mut all_bookmarks = Vec::new();
+/// Order of search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Best match first, see [`relevance`]
+    #[default]
+    Relevance,
+    /// Most recently added first
+    Date,
+    /// Most visited first
+    Visits,
+    /// Alphabetical by title
+    Title,
+}
 
-    for browser_name in browsers.iter() {
-        match Browser::from_str(browser_name) {
-            Ok(browser) => {
-                if let Ok(profiles) = browser.find_profiles(None) {
-                    if !profiles.is_empty() {
-                        if let Ok(_) = export_data(browser_name, "bookmarks", Some(temp_file.clone()), None) {
-                            if let Ok(content) = fs::read_to_string(&temp_file) {
-                                if let Ok(data) = serde_yaml::from_str::<Vec<BrowserData>>(&content) {
-                                    for browser_data in data {
-                                        if let Some(bookmarks) = browser_data.bookmarks {
-                                            for bookmark in bookmarks {
-                                                if let Some(url) = &bookmark.url {
-                                                    if !url.is_empty() {
-                                                        all_bookmarks.push(bookmark);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+impl SortOrder {
+    /// Whether ordering needs visit counts from history
+    fn uses_history(self) -> bool {
+        matches!(self, SortOrder::Relevance | SortOrder::Visits)
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "relevance" => Ok(SortOrder::Relevance),
+            "date" => Ok(SortOrder::Date),
+            "visits" => Ok(SortOrder::Visits),
+            "title" => Ok(SortOrder::Title),
+            _ => Err(anyhow!(
+                "Unknown sort order: {} (expected relevance, date, visits or title)",
+                s
+            )),
+        }
+    }
+}
+
+/// How `search` prints its results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchOutput {
+    /// Numbered list for reading
+    #[default]
+    Text,
+    Json,
+    Csv,
+    Yaml,
+}
+
+impl FromStr for SearchOutput {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(SearchOutput::Text),
+            "json" => Ok(SearchOutput::Json),
+            "csv" => Ok(SearchOutput::Csv),
+            "yaml" => Ok(SearchOutput::Yaml),
+            _ => Err(anyhow!(
+                "Unknown output format: {} (expected text, json, csv or yaml)",
+                s
+            )),
+        }
+    }
+}
+
+/// The part of a bookmark a query matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchedField {
+    Title,
+    Url,
+    Tags,
+    Folder,
+    Description,
+}
+
+/// Characters of context kept on each side of the match in a snippet
+const SNIPPET_CONTEXT: usize = 30;
+
+/// A bookmark a search found, with why and where it matched
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResult {
+    /// Its fields sit at the top level of JSON and YAML output
+    #[serde(flatten)]
+    pub bookmark: Bookmark,
+    /// [`relevance`] to the query, whatever the sort order
+    pub score: f64,
+    /// The first of the title, URL, tags, folder and description that matched
+    pub matched_field: MatchedField,
+    /// Browsers holding the bookmark, comma-separated (`deleted` for one
+    /// found by `--as-of` that no browser holds any more)
+    pub source_browser: String,
+    /// Profiles holding it, comma-separated, when read from the store
+    pub profile: Option<String>,
+    /// The matched field around the first match, trimmed with `…`
+    pub snippet: String,
+}
+
+impl SearchResult {
+    /// Describe a bookmark matching `query`, as `options` matched it
+    pub fn new(
+        bookmark: Bookmark,
+        source_browser: String,
+        query: &str,
+        options: &SearchOptions,
+        weights: &RankingWeights,
+        score: f64,
+    ) -> Self {
+        let (matched_field, text) = matched_field(&bookmark, query, options, weights).unwrap_or((
+            MatchedField::Url,
+            bookmark.url.as_deref().unwrap_or_default(),
+        ));
+        Self {
+            snippet: snippet(text, query, SNIPPET_CONTEXT),
+            matched_field,
+            source_browser,
+            profile: None,
+            score: (score * 100.0).round() / 100.0,
+            bookmark,
+        }
+    }
+}
+
+/// `text` cut to `context` characters either side of the first
+/// case-insensitive occurrence of `query`, marking the cuts with `…`
+pub fn snippet(text: &str, query: &str, context: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let fold = |c: &char| c.to_lowercase().next().unwrap_or(*c);
+    let lower: Vec<char> = chars.iter().map(fold).collect();
+    let needle: Vec<char> = query.trim().chars().map(|c| fold(&c)).collect();
+    let start = match needle.is_empty() {
+        true => 0,
+        false => lower
+            .windows(needle.len())
+            .position(|w| w == needle.as_slice())
+            .unwrap_or(0),
+    };
+    let from = start.saturating_sub(context);
+    let to = (start + needle.len() + context).min(chars.len());
+    let mut snippet: String = chars[from..to].iter().collect();
+    if from > 0 {
+        snippet = format!("…{}", snippet.trim_start());
+    }
+    if to < chars.len() {
+        snippet = format!("{}…", snippet.trim_end());
+    }
+    snippet
+}
+
+/// One row of CSV output, which has no room for the whole bookmark
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    id: &'a str,
+    title: &'a str,
+    url: Option<&'a str>,
+    folder: Option<&'a str>,
+    browser: &'a str,
+    score: f64,
+    matched_field: MatchedField,
+    snippet: &'a str,
+}
+
+/// Render results as JSON, CSV or YAML; text output is printed as it goes
+/// by [`search_bookmarks`]
+pub fn render_results(results: &[SearchResult], output: SearchOutput) -> Result<String> {
+    match output {
+        SearchOutput::Json => Ok(serde_json::to_string_pretty(results)?),
+        SearchOutput::Yaml => Ok(serde_yaml::to_string(results)?),
+        SearchOutput::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            if results.is_empty() {
+                writer.write_record([
+                    "id",
+                    "title",
+                    "url",
+                    "folder",
+                    "browser",
+                    "score",
+                    "matched_field",
+                    "snippet",
+                ])?;
             }
-            Err(_) => continue,
+            for result in results {
+                writer.serialize(CsvRow {
+                    id: &result.bookmark.id,
+                    title: &result.bookmark.title,
+                    url: result.bookmark.url.as_deref(),
+                    folder: result.bookmark.folder.as_deref(),
+                    browser: &result.source_browser,
+                    score: result.score,
+                    matched_field: result.matched_field,
+                    snippet: &result.snippet,
+                })?;
+            }
+            Ok(String::from_utf8(writer.into_inner()?)?)
         }
+        SearchOutput::Text => Err(anyhow!("Text search output is not rendered")),
     }
+}
 
-    let query_lower = query.to_lowercase();
-    let filtered_bookmarks: Vec<Bookmark> = all_bookmarks
-        .into_iter()
-        .filter(|bookmark| {
-            let title_match = bookmark.title.to_lowercase().contains(&query_lower);
-            let url_match = bookmark
-                .url
-                .as_ref()
-                .map(|u| u.to_lowercase().contains(&query_lower))
-                .unwrap_or(false);
-
-            if options.title_only {
-                title_match
-            } else if options.url_only {
-                url_match
-            } else {
-                title_match || url_match
+/// What each part of a match adds to [`relevance`], set under
+/// `search.weights` in the config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RankingWeights {
+    /// A title equal to the query; one starting with it gets 0.6 of this,
+    /// one containing it 0.4, and each query word in the title a share of 0.1
+    pub title: f64,
+    /// A URL containing the query
+    pub url: f64,
+    /// A tag equal to the query
+    pub tags: f64,
+    /// A folder path containing the query
+    pub folder: f64,
+    /// A description (notes) containing the query
+    pub description: f64,
+    /// Times the log of the visit count
+    pub visits: f64,
+    /// A visit (or, unvisited, an addition) today; halves every 90 days
+    pub recency: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            title: 10.0,
+            url: 2.0,
+            tags: 3.0,
+            folder: 1.0,
+            description: 1.0,
+            visits: 2.0,
+            recency: 3.0,
+        }
+    }
+}
+
+static WEIGHTS: OnceLock<RankingWeights> = OnceLock::new();
+
+impl RankingWeights {
+    /// The weights from the config file in effect, read once per process;
+    /// the defaults when there is none or it can't be read
+    pub fn configured() -> &'static RankingWeights {
+        WEIGHTS.get_or_init(|| {
+            let path = &crate::paths::get().config;
+            if !path.exists() {
+                return RankingWeights::default();
+            }
+            match AppConfig::load_from_file(path) {
+                Ok(config) => config.search.weights,
+                Err(e) => {
+                    log::warn!("Using default search weights: {:#}", e);
+                    RankingWeights::default()
+                }
             }
         })
-        .take(options.limit)
+    }
+}
+
+/// Score how well `bookmark` answers `query`. The title counts most: an
+/// exact match, then one starting with the query, then containing it or
+/// each of its words; matches in the URL, tags, folder and description add
+/// less. Visits add on a log scale, and a recent visit (or, unvisited, a
+/// recent addition) adds a boost that halves every 90 days. `weights` sets
+/// how much each counts.
+pub fn relevance(
+    bookmark: &Bookmark,
+    query: &str,
+    visits: Option<&UrlEntry>,
+    now: DateTime<Utc>,
+    weights: &RankingWeights,
+) -> f64 {
+    let query = query.trim().to_lowercase();
+    let title = bookmark.title.trim().to_lowercase();
+    let url = bookmark.url.as_deref().unwrap_or_default().to_lowercase();
+    let contains = |text: Option<&str>| text.is_some_and(|t| t.to_lowercase().contains(&query));
+
+    let mut score = if title == query {
+        weights.title
+    } else if title.starts_with(&query) {
+        0.6 * weights.title
+    } else if title.contains(&query) {
+        0.4 * weights.title
+    } else {
+        0.0
+    };
+    let words: Vec<&str> = query.split_whitespace().collect();
+    if words.len() > 1 {
+        score += 0.1 * weights.title * words.iter().filter(|w| title.contains(*w)).count() as f64
+            / words.len() as f64;
+    }
+    if url.contains(&query) {
+        score += weights.url;
+    }
+    if bookmark
+        .tags
+        .iter()
+        .flatten()
+        .any(|tag| tag.to_lowercase() == query)
+    {
+        score += weights.tags;
+    }
+    if contains(bookmark.folder.as_deref()) {
+        score += weights.folder;
+    }
+    if contains(bookmark.description.as_deref()) {
+        score += weights.description;
+    }
+
+    if let Some(visits) = visits {
+        score += weights.visits * (1.0 + visits.visit_count.max(0) as f64).ln();
+    }
+    let seen = visits.and_then(|v| v.last_visit).or(bookmark.date_added);
+    if let Some(seen) = seen {
+        let days = (now - seen).num_days().max(0) as f64;
+        score += weights.recency * 0.5_f64.powf(days / 90.0);
+    }
+    score
+}
+
+/// Order `results` by `sort`, keeping the original order among equals
+pub fn sort_results<T>(
+    results: Vec<T>,
+    bookmark: impl Fn(&T) -> &Bookmark,
+    query: &str,
+    sort: SortOrder,
+    history: &HashMap<String, UrlEntry>,
+    weights: &RankingWeights,
+) -> Vec<T> {
+    let now = Utc::now();
+    let mut keyed: Vec<(f64, T)> = results
+        .into_iter()
+        .map(|result| {
+            let b = bookmark(&result);
+            let visits = b.url.as_deref().and_then(|u| history.get(u));
+            let key = match sort {
+                SortOrder::Relevance => relevance(b, query, visits, now, weights),
+                SortOrder::Date => b.date_added.map_or(f64::MIN, |d| d.timestamp() as f64),
+                SortOrder::Visits => visits.map_or(0.0, |v| v.visit_count as f64),
+                SortOrder::Title => 0.0,
+            };
+            (key, result)
+        })
         .collect();
+    match sort {
+        SortOrder::Title => keyed.sort_by_cached_key(|(_, r)| bookmark(r).title.to_lowercase()),
+        _ => keyed.sort_by(|a, b| b.0.total_cmp(&a.0)),
+    }
+    keyed.into_iter().map(|(_, result)| result).collect()
+}
 
-    let _ = fs::remove_file(&temp_file);
-    Ok(filtered_bookmarks)
+/// Check a bookmark against a media type filter, detecting the type from
+/// the URL when the bookmark hasn't been enriched
+pub fn matches_media(bookmark: &Bookmark, wanted: Option<MediaType>) -> bool {
+    let Some(wanted) = wanted else {
+        return true;
+    };
+    let detected = bookmark
+        .media_type
+        .or_else(|| bookmark.url.as_deref().and_then(MediaType::from_url));
+    detected == Some(wanted)
 }
 
-pub fn search_bookmarks(query: &str, title_only: bool, url_only: bool, limit: usize) -> Result<()> {
-    // First, import all bookmarks to a temporary file
-    let temp_file = PathBuf::from("/tmp/bookmark_search_data.yaml");
+/// The first part of `bookmark` that matches `query`, with its text, or
+/// `None` if nothing does: the title or URL containing the query, then a
+/// tag equal to it, then the folder or description containing it, the way
+/// [`relevance`] scores them. Tags, folder and description are only
+/// searched when `weights` gives them weight, and not at all by a title-only
+/// or URL-only search.
+pub fn matched_field<'a>(
+    bookmark: &'a Bookmark,
+    query: &str,
+    options: &SearchOptions,
+    weights: &RankingWeights,
+) -> Option<(MatchedField, &'a str)> {
+    let query_lower = query.to_lowercase();
+    if !options.url_only && bookmark.title.to_lowercase().contains(&query_lower) {
+        return Some((MatchedField::Title, &bookmark.title));
+    }
+    if let Some(url) = bookmark.url.as_deref()
+        && !options.title_only
+        && url.to_lowercase().contains(&query_lower)
+    {
+        return Some((MatchedField::Url, url));
+    }
+    if options.title_only || options.url_only {
+        return None;
+    }
 
+    let query = query_lower.trim();
+    let contains = |text: &str| text.to_lowercase().contains(query);
+    if weights.tags > 0.0
+        && let Some(tag) = bookmark
+            .tags
+            .iter()
+            .flatten()
+            .find(|tag| tag.to_lowercase() == query)
+    {
+        return Some((MatchedField::Tags, tag));
+    }
+    if weights.folder > 0.0
+        && let Some(folder) = bookmark.folder.as_deref().filter(|f| contains(f))
+    {
+        return Some((MatchedField::Folder, folder));
+    }
+    bookmark
+        .description
+        .as_deref()
+        .filter(|d| weights.description > 0.0 && contains(d))
+        .map(|description| (MatchedField::Description, description))
+}
+
+/// Check whether a title/URL pair matches a query (case-insensitive substring)
+pub fn matches_query(title: &str, url: Option<&str>, query: &str, options: &SearchOptions) -> bool {
+    let query_lower = query.to_lowercase();
+    let title_match = title.to_lowercase().contains(&query_lower);
+    let url_match = url
+        .map(|u| u.to_lowercase().contains(&query_lower))
+        .unwrap_or(false);
+
+    if options.title_only {
+        title_match
+    } else if options.url_only {
+        url_match
+    } else {
+        title_match || url_match
+    }
+}
+
+/// Load URL bookmarks tagged with the browser(s) holding them: from the store
+/// when it has been synced, unless `live` asks to read every browser directly
+pub(crate) fn load_all_bookmarks(live: bool) -> Vec<(Bookmark, String)> {
+    if !live {
+        match load_from_store() {
+            Ok(Some(bookmarks)) => return bookmarks,
+            Ok(None) => {}
+            Err(e) => log::warn!("Could not read the bookmark store, reading browsers: {}", e),
+        }
+    }
+
+    eprintln!("Loading bookmarks from all browsers...");
+    if !live {
+        eprintln!("Run `bookmark sync` once (or pass --refresh) to search the store instead");
+    }
     let browsers = ["Chrome", "Firefox", "Safari", "Edge"];
     let mut all_bookmarks = Vec::new();
 
-    println!("Loading bookmarks from all browsers...");
-
-    for browser_name in browsers.iter() {
-        match Browser::from_str(browser_name) {
-            Ok(browser) => {
-                if let Ok(profiles) = browser.find_profiles(None) {
-                    if !profiles.is_empty() {
-                        match export_data(browser_name, "bookmarks", Some(temp_file.clone()), None)
-                        {
-                            Ok(_) => {
-                                // Read the exported data and extract bookmarks
-                                if let Ok(content) = fs::read_to_string(&temp_file) {
-                                    if let Ok(data) =
-                                        serde_yaml::from_str::<Vec<BrowserData>>(&content)
-                                    {
-                                        for browser_data in data {
-                                            if let Some(bookmarks) = browser_data.bookmarks {
-                                                for bookmark in bookmarks {
-                                                    if let Some(url) = &bookmark.url {
-                                                        if !url.is_empty() {
-                                                            all_bookmarks.push((
-                                                                bookmark,
-                                                                browser_name.to_string(),
-                                                            ));
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Err(_) => {
-                                // Continue with other browsers if one fails
-                                continue;
-                            }
-                        }
-                    }
-                }
+    for browser_name in browsers {
+        // Continue with other browsers if one fails
+        let Ok((bookmarks, _)) = load_browser_data(browser_name, "bookmarks") else {
+            continue;
+        };
+        all_bookmarks.extend(
+            bookmarks
+                .into_iter()
+                .filter(|b| b.url.as_deref().is_some_and(|u| !u.is_empty()))
+                .map(|b| (b, browser_name.to_string())),
+        );
+    }
+
+    all_bookmarks
+}
+
+fn load_from_store() -> Result<Option<Vec<(Bookmark, String)>>> {
+    let Some(store) = Store::open_if_synced()? else {
+        return Ok(None);
+    };
+    if let Some(synced) = store.last_synced()? {
+        eprintln!(
+            "Using bookmark store (last synced {}); pass --live to read browsers directly",
+            synced.format("%Y-%m-%d %H:%M UTC")
+        );
+        if let Some(warning) = stale_warning(synced, Utc::now()) {
+            eprintln!("{}", warning);
+        }
+    }
+    Ok(Some(
+        store
+            .bookmarks_with_browsers()?
+            .into_iter()
+            .map(|(bookmark, browsers)| (bookmark, browsers.join(",")))
+            .collect(),
+    ))
+}
+
+/// The store's bookmarks as they stood just before `at` (see
+/// [`Store::bookmarks_as_of`]); ones deleted since are labelled `deleted`
+fn load_as_of(at: DateTime<Utc>) -> Result<Vec<(Bookmark, String)>> {
+    let store = Store::open_if_synced()?
+        .ok_or_else(|| anyhow!("--as-of reads the bookmark store; run `bookmark sync` first"))?;
+    eprintln!(
+        "Using bookmark store as it stood before {}",
+        at.format("%Y-%m-%d %H:%M UTC")
+    );
+    Ok(store
+        .bookmarks_as_of(at)?
+        .into_iter()
+        .map(|(bookmark, browsers)| match browsers.is_empty() {
+            true => (bookmark, "deleted".to_string()),
+            false => (bookmark, browsers.join(",")),
+        })
+        .collect())
+}
+
+/// A warning when the store was last synced more than [`STALE_AFTER`] ago
+fn stale_warning(synced: DateTime<Utc>, now: DateTime<Utc>) -> Option<String> {
+    let age = now - synced;
+    (age > STALE_AFTER).then(|| {
+        format!(
+            "Warning: the store is {} days old; pass --refresh or run `bookmark sync` to pick up new bookmarks",
+            age.num_days()
+        )
+    })
+}
+
+/// Bring the store up to date before a search: an incremental sync of every
+/// browser's bookmarks and history, or a full one the first time. Progress
+/// goes to stderr, so `--output json` stays clean.
+pub fn refresh_store() -> Result<()> {
+    eprintln!("Refreshing bookmark store...");
+    let mut store = Store::open_default()?;
+    let synced = store.sync("all", "both", false)?;
+    let total = |count: fn(&crate::store::ProfileSync) -> Option<usize>| {
+        synced.iter().filter_map(count).sum::<usize>()
+    };
+    eprintln!(
+        "Read {} bookmarks and {} history URLs from {} profiles",
+        total(|p| p.bookmarks),
+        total(|p| p.history),
+        synced.len()
+    );
+    Ok(())
+}
+
+/// Visits per URL, summed over profiles with the latest visit kept: from
+/// the store when it has been synced, unless `live` asks for the browsers.
+/// With `as_of`, only the store's URLs last visited before then.
+fn load_visits(live: bool, as_of: Option<DateTime<Utc>>) -> HashMap<String, UrlEntry> {
+    let history = match (Store::open_if_synced(), as_of) {
+        (Ok(Some(store)), Some(at)) => store.history_as_of(at).unwrap_or_default(),
+        (_, Some(_)) => Vec::new(),
+        (Ok(Some(store)), None) if !live => store.history().unwrap_or_default(),
+        _ => load_browser_data("all", "history")
+            .map(|(_, history)| history)
+            .unwrap_or_default(),
+    };
+    let mut visits: HashMap<String, UrlEntry> = HashMap::new();
+    for entry in history {
+        match visits.get_mut(&entry.url) {
+            Some(seen) => {
+                seen.visit_count += entry.visit_count;
+                seen.last_visit = seen.last_visit.max(entry.last_visit);
+            }
+            None => {
+                visits.insert(entry.url.clone(), entry);
             }
-            Err(_) => continue,
         }
     }
+    visits
+}
 
-    if all_bookmarks.is_empty() {
+/// Profiles holding each URL, comma-separated, when the store has been
+/// synced; reading browsers directly doesn't keep track of profiles
+fn load_profiles(live: bool) -> HashMap<String, String> {
+    match Store::open_if_synced() {
+        Ok(Some(store)) if !live => store
+            .profiles_by_url()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(url, profiles)| (url, profiles.join(",")))
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
+
+/// Score and describe the matches left after sorting and truncating
+fn to_results(
+    matches: Vec<(Bookmark, String)>,
+    query: &str,
+    options: &SearchOptions,
+    visits: &HashMap<String, UrlEntry>,
+    profiles: &HashMap<String, String>,
+    now: DateTime<Utc>,
+) -> Vec<SearchResult> {
+    matches
+        .into_iter()
+        .map(|(bookmark, browser)| {
+            let url = bookmark.url.as_deref().unwrap_or_default();
+            let weights = RankingWeights::configured();
+            let score = relevance(&bookmark, query, visits.get(url), now, weights);
+            let profile = profiles.get(url).cloned();
+            SearchResult {
+                profile,
+                ..SearchResult::new(bookmark, browser, query, options, weights, score)
+            }
+        })
+        .collect()
+}
+
+/// Near misses for a query that found nothing, from the same bookmarks
+/// [`search_bookmarks_internal`] searches
+pub fn suggestions_for(query: &str) -> Suggestions {
+    let bookmarks = load_all_bookmarks(false);
+    suggest(query, bookmarks.iter().map(|(b, _)| b))
+}
+
+/// Search the store (or, before the first sync, every browser)
+pub fn search_bookmarks_internal(
+    query: &str,
+    options: &SearchOptions,
+) -> Result<Vec<SearchResult>> {
+    let matches: Vec<(Bookmark, String)> = load_all_bookmarks(false)
+        .into_iter()
+        .filter(|(bookmark, _)| {
+            matched_field(bookmark, query, options, RankingWeights::configured()).is_some()
+                && matches_media(bookmark, options.media_type)
+        })
+        .collect();
+    if matches.is_empty() {
+        return Ok(Vec::new());
+    }
+    let visits = load_visits(false, None);
+    let mut sorted = sort_results(
+        matches,
+        |(b, _)| b,
+        query,
+        options.sort,
+        &visits,
+        RankingWeights::configured(),
+    );
+    sorted.truncate(options.limit);
+    Ok(to_results(
+        sorted,
+        query,
+        options,
+        &visits,
+        &load_profiles(false),
+        Utc::now(),
+    ))
+}
+
+pub fn search_bookmarks(
+    query: &str,
+    options: SearchOptions,
+    output: SearchOutput,
+    live: bool,
+    as_of: Option<DateTime<Utc>>,
+) -> Result<()> {
+    let all_bookmarks = match as_of {
+        Some(at) => load_as_of(at)?,
+        None => load_all_bookmarks(live),
+    };
+
+    if all_bookmarks.is_empty() && output == SearchOutput::Text {
         println!("No bookmarks found.");
         return Ok(());
     }
 
     // Filter bookmarks based on search criteria
-    let filtered_bookmarks: Vec<_> = all_bookmarks
-        .into_iter()
+    let matches: Vec<_> = all_bookmarks
+        .iter()
         .filter(|(bookmark, _)| {
-            let query_lower = query.to_lowercase();
-            let title_match = bookmark.title.to_lowercase().contains(&query_lower);
-            let url_match = bookmark
-                .url
-                .as_ref()
-                .map(|u| u.to_lowercase().contains(&query_lower))
-                .unwrap_or(false);
-
-            if title_only {
-                title_match
-            } else if url_only {
-                url_match
-            } else {
-                title_match || url_match
-            }
+            matched_field(bookmark, query, &options, RankingWeights::configured()).is_some()
+                && matches_media(bookmark, options.media_type)
         })
-        .take(limit)
+        .cloned()
         .collect();
+    let suggestions = || suggest(query, all_bookmarks.iter().map(|(b, _)| b)).to_text();
+    let scored = output != SearchOutput::Text;
+    let visits = match (options.sort.uses_history() || scored) && !matches.is_empty() {
+        true => load_visits(live, as_of),
+        false => HashMap::new(),
+    };
+    let mut filtered_bookmarks = sort_results(
+        matches,
+        |(b, _)| b,
+        query,
+        options.sort,
+        &visits,
+        RankingWeights::configured(),
+    );
+    filtered_bookmarks.truncate(options.limit);
+
+    if scored {
+        let profiles = match filtered_bookmarks.is_empty() {
+            true => HashMap::new(),
+            false => load_profiles(live),
+        };
+        let now = as_of.unwrap_or_else(Utc::now);
+        let results = to_results(filtered_bookmarks, query, &options, &visits, &profiles, now);
+        // On stderr, so an empty list is still all stdout holds
+        if results.is_empty()
+            && let Some(text) = suggestions()
+        {
+            eprintln!("{}", text);
+        }
+        let rendered = render_results(&results, output)?;
+        print!("{}", rendered);
+        if !rendered.ends_with('\n') {
+            println!();
+        }
+        return Ok(());
+    }
 
     if filtered_bookmarks.is_empty() {
         println!("No bookmarks found matching '{}'.", query);
+        if let Some(text) = suggestions() {
+            println!("{}", text);
+        }
         return Ok(());
     }
 
@@ -175,89 +756,100 @@ pub fn search_bookmarks(query: &str, title_only: bool, url_only: bool, limit: us
         println!();
     }
 
-    // Clean up temporary file
-    let _ = fs::remove_file(&temp_file);
-
     Ok(())
 }
 
-pub fn open_bookmark(query: &str, first: bool) -> Result<()> {
-    // First, import all bookmarks to a temporary file
-    let temp_file = PathBuf::from("/tmp/bookmark_open_data.yaml");
+/// What `open` opens
+#[derive(Debug, Clone, Default)]
+pub struct OpenOptions {
+    /// Open the best match without asking
+    pub first: bool,
+    /// Open every match in its own tab, best first
+    pub all: bool,
+    /// Most matches `all` opens
+    pub limit: Option<usize>,
+    /// Only bookmarks in this folder or its subfolders
+    pub folder: Option<String>,
+    /// Read browsers directly instead of the synced store
+    pub live: bool,
+}
 
-    let browsers = ["Chrome", "Firefox", "Safari", "Edge"];
-    let mut all_bookmarks = Vec::new();
+/// Opening more tabs than this at once asks first
+#[cfg(feature = "cli")]
+const CONFIRM_TABS_ABOVE: usize = 10;
 
-    println!("Searching for bookmarks to open...");
+/// The bookmarks `open` chooses from for `query`, best match first, each
+/// with the browsers it came from
+pub fn open_matches(query: &str, open_options: &OpenOptions) -> Vec<(Bookmark, String)> {
+    rank_open_matches(&load_all_bookmarks(open_options.live), query, open_options)
+}
 
-    for browser_name in browsers.iter() {
-        match Browser::from_str(browser_name) {
-            Ok(browser) => {
-                if let Ok(profiles) = browser.find_profiles(None) {
-                    if !profiles.is_empty() {
-                        match export_data(browser_name, "bookmarks", Some(temp_file.clone()), None)
-                        {
-                            Ok(_) => {
-                                // Read the exported data and extract bookmarks
-                                if let Ok(content) = fs::read_to_string(&temp_file) {
-                                    if let Ok(data) =
-                                        serde_yaml::from_str::<Vec<BrowserData>>(&content)
-                                    {
-                                        for browser_data in data {
-                                            if let Some(bookmarks) = browser_data.bookmarks {
-                                                for bookmark in bookmarks {
-                                                    if let Some(url) = &bookmark.url {
-                                                        if !url.is_empty() {
-                                                            all_bookmarks.push((
-                                                                bookmark,
-                                                                browser_name.to_string(),
-                                                            ));
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Err(_) => {
-                                // Continue with other browsers if one fails
-                                continue;
-                            }
-                        }
-                    }
-                }
-            }
-            Err(_) => continue,
-        }
-    }
+fn rank_open_matches(
+    all_bookmarks: &[(Bookmark, String)],
+    query: &str,
+    open_options: &OpenOptions,
+) -> Vec<(Bookmark, String)> {
+    let options = SearchOptions {
+        title_only: false,
+        url_only: false,
+        limit: usize::MAX,
+        media_type: None,
+        sort: SortOrder::Relevance,
+    };
+    let matches: Vec<_> = all_bookmarks
+        .iter()
+        .filter(|(bookmark, _)| {
+            matched_field(bookmark, query, &options, RankingWeights::configured()).is_some()
+                && open_options
+                    .folder
+                    .as_deref()
+                    .is_none_or(|folder| in_folder(bookmark.folder.as_deref(), folder))
+        })
+        .cloned()
+        .collect();
+    let visits = match matches.len() > 1 {
+        true => load_visits(open_options.live, None),
+        false => HashMap::new(),
+    };
+    sort_results(
+        matches,
+        |(b, _)| b,
+        query,
+        options.sort,
+        &visits,
+        RankingWeights::configured(),
+    )
+}
+
+/// Open the best match for `query`, asking which when there are several;
+/// interactive, so only in the CLI build
+#[cfg(feature = "cli")]
+pub fn open_bookmark(query: &str, open_options: &OpenOptions) -> Result<()> {
+    println!("Searching for bookmarks to open...");
+    let all_bookmarks = load_all_bookmarks(open_options.live);
 
     if all_bookmarks.is_empty() {
         println!("No bookmarks found.");
         return Ok(());
     }
 
-    // Filter bookmarks based on search query
-    let query_lower = query.to_lowercase();
-    let filtered_bookmarks: Vec<_> = all_bookmarks
-        .into_iter()
-        .filter(|(bookmark, _)| {
-            let title_match = bookmark.title.to_lowercase().contains(&query_lower);
-            let url_match = bookmark
-                .url
-                .as_ref()
-                .map(|u| u.to_lowercase().contains(&query_lower))
-                .unwrap_or(false);
-            title_match || url_match
-        })
-        .collect();
+    // Filter bookmarks based on search query, best match first
+    let mut filtered_bookmarks = rank_open_matches(&all_bookmarks, query, open_options);
 
     if filtered_bookmarks.is_empty() {
         println!("No bookmarks found matching '{}'.", query);
+        if let Some(text) = suggest(query, all_bookmarks.iter().map(|(b, _)| b)).to_text() {
+            println!("{}", text);
+        }
         return Ok(());
     }
 
-    let bookmark_to_open = if filtered_bookmarks.len() == 1 || first {
+    if open_options.all {
+        filtered_bookmarks.truncate(open_options.limit.unwrap_or(usize::MAX));
+        return open_all(&filtered_bookmarks);
+    }
+
+    let bookmark_to_open = if filtered_bookmarks.len() == 1 || open_options.first {
         &filtered_bookmarks[0]
     } else {
         // Create selection list
@@ -287,8 +879,225 @@ pub fn open_bookmark(query: &str, first: bool) -> Result<()> {
         return Err(anyhow!("Selected bookmark has no URL"));
     }
 
-    // Clean up temporary file
-    let _ = fs::remove_file(&temp_file);
+    Ok(())
+}
 
+/// Open every bookmark in a tab, asking first when there are many. A URL the
+/// browser refuses is reported and skipped.
+#[cfg(feature = "cli")]
+fn open_all(bookmarks: &[(Bookmark, String)]) -> Result<()> {
+    if bookmarks.len() > CONFIRM_TABS_ABOVE
+        && !Confirm::new()
+            .with_prompt(format!("Open {} tabs?", bookmarks.len()))
+            .default(false)
+            .interact()?
+    {
+        println!("Nothing opened.");
+        return Ok(());
+    }
+
+    let mut opened = 0;
+    for url in bookmarks.iter().filter_map(|(b, _)| b.url.as_deref()) {
+        println!("Opening: {}", url);
+        match open::that(url) {
+            Ok(_) => opened += 1,
+            Err(e) => eprintln!("Failed to open {}: {}", url, e),
+        }
+    }
+    println!("Opened {} of {} bookmarks", opened, bookmarks.len());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn bookmark(title: &str, url: &str, days_ago: i64) -> Bookmark {
+        Bookmark {
+            id: title.to_string(),
+            title: title.to_string(),
+            url: Some(url.to_string()),
+            folder: None,
+            date_added: Some(Utc::now() - Duration::days(days_ago)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sort_results() {
+        let results = vec![
+            bookmark("Learning Rust the hard way", "https://example.com/a", 400),
+            bookmark("Rust", "https://www.rust-lang.org/", 1000),
+            bookmark("Blog", "https://example.com/rust", 10),
+            bookmark("Rust cookbook", "https://example.com/c", 5),
+        ];
+        let visits = HashMap::from([(
+            "https://example.com/a".to_string(),
+            UrlEntry {
+                url: "https://example.com/a".to_string(),
+                title: String::new(),
+                visit_count: 50,
+                last_visit: Some(Utc::now()),
+            },
+        )]);
+        let titles = |sort: SortOrder| -> Vec<String> {
+            sort_results(
+                results.clone(),
+                |b| b,
+                "rust",
+                sort,
+                &visits,
+                &RankingWeights::default(),
+            )
+            .into_iter()
+            .map(|b| b.title)
+            .collect()
+        };
+
+        // Heavy recent visits outrank an exact title, which outranks a prefix
+        assert_eq!(
+            titles(SortOrder::Relevance),
+            [
+                "Learning Rust the hard way",
+                "Rust",
+                "Rust cookbook",
+                "Blog"
+            ]
+        );
+        assert_eq!(titles(SortOrder::Date)[0], "Rust cookbook");
+        assert_eq!(titles(SortOrder::Visits)[0], "Learning Rust the hard way");
+        assert_eq!(titles(SortOrder::Title)[0], "Blog");
+        assert!("newest".parse::<SortOrder>().is_err());
+    }
+
+    #[test]
+    fn test_matched_field_follows_weights() {
+        let mut notes = bookmark("Paper", "https://example.com/p", 1000);
+        notes.description = Some("Notes on rust lifetimes".to_string());
+        notes.folder = Some("Research/Rust".to_string());
+        notes.tags = Some(vec!["Lifetimes".to_string()]);
+        let field = |query: &str, options: &SearchOptions, weights: &RankingWeights| {
+            matched_field(&notes, query, options, weights).map(|(field, _)| field)
+        };
+
+        let defaults = RankingWeights::default();
+        assert_eq!(
+            field("example", &options(), &defaults),
+            Some(MatchedField::Url)
+        );
+        assert_eq!(
+            field("lifetimes", &options(), &defaults),
+            Some(MatchedField::Tags)
+        );
+        assert_eq!(
+            field("research", &options(), &defaults),
+            Some(MatchedField::Folder)
+        );
+        assert_eq!(
+            field("notes", &options(), &defaults),
+            Some(MatchedField::Description)
+        );
+
+        let titles_and_urls: RankingWeights =
+            serde_yaml::from_str("tags: 0\nfolder: 0\ndescription: 0").unwrap();
+        assert_eq!(field("lifetimes", &options(), &titles_and_urls), None);
+        assert_eq!(field("research", &options(), &titles_and_urls), None);
+        let title_only = SearchOptions {
+            title_only: true,
+            ..options()
+        };
+        assert_eq!(field("notes", &title_only, &defaults), None);
+        assert_eq!(
+            field("paper", &title_only, &defaults),
+            Some(MatchedField::Title)
+        );
+    }
+
+    #[test]
+    fn test_stale_warning() {
+        let now = Utc::now();
+        assert!(stale_warning(now - Duration::hours(2), now).is_none());
+        let warning = stale_warning(now - Duration::days(3), now).unwrap();
+        assert!(warning.contains("3 days old"));
+    }
+
+    fn options() -> SearchOptions {
+        SearchOptions {
+            title_only: false,
+            url_only: false,
+            limit: 10,
+            media_type: None,
+            sort: SortOrder::Relevance,
+        }
+    }
+
+    #[test]
+    fn test_search_result_describes_match() {
+        let mut book = bookmark("Rust, the book", "https://doc.rust-lang.org/book/", 0);
+        book.id = "42".to_string();
+        let title = SearchResult::new(
+            book.clone(),
+            "chrome".to_string(),
+            "BOOK",
+            &options(),
+            &RankingWeights::default(),
+            1.0,
+        );
+        assert_eq!(title.matched_field, MatchedField::Title);
+        assert_eq!(title.snippet, "Rust, the book");
+
+        let url = SearchResult::new(
+            book,
+            "chrome".to_string(),
+            "doc.rust",
+            &options(),
+            &RankingWeights::default(),
+            1.0,
+        );
+        assert_eq!(url.matched_field, MatchedField::Url);
+
+        let long = "A very long title that goes on and on before the word rust shows up at last";
+        assert_eq!(snippet(long, "RUST", 10), "…the word rust shows up…");
+        assert_eq!(snippet("Café crème", "crème", 2), "…é crème");
+    }
+
+    #[test]
+    fn test_render_results() {
+        let mut book = bookmark("Rust, the book", "https://doc.rust-lang.org/book/", 0);
+        book.id = "42".to_string();
+        book.date_added = None;
+        let results = vec![SearchResult {
+            profile: Some("Default".to_string()),
+            ..SearchResult::new(
+                book,
+                "chrome,firefox".to_string(),
+                "book",
+                &options(),
+                &RankingWeights::default(),
+                12.5,
+            )
+        }];
+
+        let csv = render_results(&results, SearchOutput::Csv).unwrap();
+        assert_eq!(
+            csv,
+            "id,title,url,folder,browser,score,matched_field,snippet\n\
+             42,\"Rust, the book\",https://doc.rust-lang.org/book/,,\"chrome,firefox\",12.5,title,\"Rust, the book\"\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&render_results(&results, SearchOutput::Json).unwrap()).unwrap();
+        assert_eq!(json[0]["folder"], serde_json::Value::Null);
+        assert_eq!(json[0]["url"], "https://doc.rust-lang.org/book/");
+        assert_eq!(json[0]["score"], 12.5);
+        assert_eq!(json[0]["matched_field"], "title");
+        assert_eq!(json[0]["source_browser"], "chrome,firefox");
+        assert_eq!(json[0]["profile"], "Default");
+        assert!(
+            render_results(&[], SearchOutput::Csv)
+                .unwrap()
+                .starts_with("id,title")
+        );
+        assert!("xml".parse::<SearchOutput>().is_err());
+    }
+}
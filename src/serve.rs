@@ -0,0 +1,830 @@
+//! HTTP API over the library, for other tools and web front ends.
+//!
+//! `bookmark serve` answers JSON over plain HTTP:
+//!
+//! - `GET /bookmarks` lists the bookmarks, each with the browsers holding it
+//! - `GET /search?q=` ranks matches the way `search` does
+//! - `GET /folders` gives the folder tree with bookmark counts
+//! - `GET /duplicates` groups the bookmarks `process` would merge
+//! - `POST /process` dedupes and organizes the bookmarks posted to it
+//! - `GET /graph?format=json` builds the knowledge graph
+//!
+//! `GET /` is a dashboard page built on those endpoints: a searchable
+//! bookmark table, the folder tree, the duplicate report and the graph's
+//! HTML view. `GET /api` lists the endpoints.
+//!
+//! Reads come from the bookmark store once it has been synced, else from
+//! the browsers, and `live=true` always reads the browsers. Nothing is
+//! written: `/process` returns its result rather than saving it. There is
+//! no authentication, so the server listens on localhost unless told
+//! otherwise. So that a web page can't reach it through DNS rebinding, a
+//! request must name this machine in `Host`, and a request from a browser
+//! page must come from a local `Origin` or the one `--allow-origin` names.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::Read;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use url::{Host, Url};
+
+use crate::config::AppConfig;
+use crate::deduplication::{BookmarkDeduplicator, DeduplicationConfig, MergeStrategy};
+use crate::exporter::{Bookmark, UrlEntry, load_browser_data};
+use crate::graph::{DetailLevel, GraphBuilder, GraphConfig, formats};
+use crate::media::MediaType;
+use crate::organization::OrganizationConfig;
+use crate::processor::{BookmarkProcessor, ProcessingConfig};
+use crate::search::{SearchOptions, SortOrder, load_all_bookmarks, search_bookmarks_internal};
+use crate::store::Store;
+use crate::tree::FolderTree;
+use crate::utils::{is_local_host, is_local_origin};
+
+/// Largest request body read, so one request can't exhaust memory
+const MAX_BODY: u64 = 64 * 1024 * 1024;
+
+const JSON: &str = "application/json";
+const HTML: &str = "text/html; charset=utf-8";
+
+/// A response ready to send
+#[derive(Debug)]
+pub struct Reply {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Reply {
+    fn json(value: &impl Serialize) -> Result<Self> {
+        Ok(Self {
+            status: 200,
+            content_type: JSON,
+            body: serde_json::to_string_pretty(value)?,
+        })
+    }
+
+    fn error(status: u16, message: impl std::fmt::Display) -> Self {
+        Self {
+            status,
+            content_type: JSON,
+            body: json!({ "error": message.to_string() }).to_string(),
+        }
+    }
+}
+
+/// A request the client got wrong, answered with 400 instead of 500
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+struct BadRequest(String);
+
+fn bad_request(message: impl Into<String>) -> anyhow::Error {
+    BadRequest(message.into()).into()
+}
+
+/// A bookmark as `/bookmarks` lists it
+#[derive(Serialize)]
+struct Listed {
+    #[serde(flatten)]
+    bookmark: Bookmark,
+    /// Browser profiles holding it, comma-separated
+    browser: String,
+}
+
+/// A set of bookmarks `/duplicates` reports as the same page
+#[derive(Serialize)]
+struct DuplicateGroup {
+    /// The normalized URL they share
+    url: String,
+    bookmarks: Vec<Bookmark>,
+}
+
+/// The body of `POST /process`
+#[derive(Deserialize)]
+struct ProcessRequest {
+    bookmarks: Vec<Bookmark>,
+    /// Merge strategy for duplicates, as `process --strategy` takes it
+    #[serde(default)]
+    strategy: Option<String>,
+    /// Stages to run instead of the default pipeline
+    #[serde(default)]
+    stages: Option<Vec<String>>,
+}
+
+/// Answers API requests; cheap to share between worker threads
+pub struct ApiServer {
+    config: AppConfig,
+    /// Sent as `Access-Control-Allow-Origin`, so a web UI elsewhere can call
+    /// the API
+    allow_origin: Option<String>,
+    /// Whether to accept a `Host` that is an IP address rather than
+    /// `localhost`, set when listening beyond loopback
+    remote_hosts: bool,
+}
+
+impl ApiServer {
+    /// A server using the config file in effect
+    pub fn new(allow_origin: Option<String>) -> Result<Self> {
+        let path = &crate::paths::get().config;
+        let config = match path.exists() {
+            true => AppConfig::load_from_file(path)?,
+            false => AppConfig::default(),
+        };
+        Ok(Self {
+            config,
+            allow_origin,
+            remote_hosts: false,
+        })
+    }
+
+    /// Listen on `addr` and answer requests on `workers` threads until the
+    /// process is stopped
+    pub fn run(mut self, addr: &str, workers: usize) -> Result<()> {
+        let server = tiny_http::Server::http(addr)
+            .map_err(|e| anyhow!("Could not listen on {}: {}", addr, e))?;
+        if let Some(ip) = server.server_addr().to_ip()
+            && !ip.ip().is_loopback()
+        {
+            eprintln!(
+                "Warning: listening on {} without authentication; anyone who can reach it can read your bookmarks",
+                ip
+            );
+            self.remote_hosts = true;
+        }
+        eprintln!("Serving the bookmark API on http://{}", addr);
+
+        let server = Arc::new(server);
+        let api = Arc::new(self);
+        let handles: Vec<_> = (0..workers.max(1))
+            .map(|_| {
+                let server = Arc::clone(&server);
+                let api = Arc::clone(&api);
+                thread::spawn(move || {
+                    for request in server.incoming_requests() {
+                        api.respond(request);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            let _ = handle.join();
+        }
+        Ok(())
+    }
+
+    fn respond(&self, mut request: tiny_http::Request) {
+        let headers: Vec<(String, String)> = request
+            .headers()
+            .iter()
+            .map(|h| (h.field.as_str().to_string(), h.value.as_str().to_string()))
+            .collect();
+        let mut body = String::new();
+        let reply = match request.as_reader().take(MAX_BODY).read_to_string(&mut body) {
+            Ok(_) => self.handle(request.method().as_str(), request.url(), &headers, &body),
+            Err(e) => Reply::error(400, format!("Could not read the request body: {}", e)),
+        };
+        log::info!("{} {} {}", request.method(), request.url(), reply.status);
+
+        let mut response = tiny_http::Response::from_string(reply.body)
+            .with_status_code(reply.status)
+            .with_header(header("Content-Type", reply.content_type));
+        if let Some(origin) = &self.allow_origin {
+            response = response
+                .with_header(header("Access-Control-Allow-Origin", origin))
+                .with_header(header("Access-Control-Allow-Headers", "Content-Type"))
+                .with_header(header("Access-Control-Allow-Methods", "GET, POST, OPTIONS"));
+        }
+        if let Err(e) = request.respond(response) {
+            log::warn!("Could not send the response: {}", e);
+        }
+    }
+
+    /// Answer one request: `url` is the path with its query string, and
+    /// header names are matched case-insensitively
+    pub fn handle(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(String, String)],
+        body: &str,
+    ) -> Reply {
+        if let Some(host) = header_value(headers, "Host")
+            && !is_local_host(host)
+            && !(self.remote_hosts && is_ip_host(host))
+        {
+            return Reply::error(403, format!("Requests for {} are not allowed", host));
+        }
+        if let Some(origin) = header_value(headers, "Origin")
+            && !is_local_origin(origin)
+            && !matches!(self.allow_origin.as_deref(), Some(allowed) if allowed == "*" || allowed == origin)
+        {
+            return Reply::error(403, format!("Requests from {} are not allowed", origin));
+        }
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        let result = match (method, path.trim_end_matches('/')) {
+            ("OPTIONS", _) => Ok(Reply {
+                status: 204,
+                content_type: JSON,
+                body: String::new(),
+            }),
+            ("GET", "") => Ok(Reply {
+                status: 200,
+                content_type: HTML,
+                body: DASHBOARD_HTML.to_string(),
+            }),
+            ("GET", "/api") => Reply::json(&json!({
+                "name": "bookmark",
+                "version": env!("CARGO_PKG_VERSION"),
+                "endpoints": [
+                    "GET /bookmarks",
+                    "GET /search?q=",
+                    "GET /folders",
+                    "GET /duplicates",
+                    "POST /process",
+                    "GET /graph",
+                ],
+            })),
+            ("GET", "/bookmarks") => self.bookmarks(&params),
+            ("GET", "/search") => self.search(&params),
+            ("GET", "/folders") => self.folders(&params),
+            ("GET", "/duplicates") => self.duplicates(&params),
+            ("POST", "/process") => self.process(body),
+            ("GET", "/graph") => self.graph(&params),
+            (
+                _,
+                "" | "/api" | "/bookmarks" | "/search" | "/folders" | "/duplicates" | "/process"
+                | "/graph",
+            ) => {
+                return Reply::error(405, format!("{} is not allowed on {}", method, path));
+            }
+            _ => return Reply::error(404, format!("No endpoint {}", path)),
+        };
+        result.unwrap_or_else(|e| match e.downcast_ref::<BadRequest>() {
+            Some(bad) => Reply::error(400, bad),
+            None => Reply::error(500, format!("{:#}", e)),
+        })
+    }
+
+    /// `live`, `limit` and `offset`
+    fn bookmarks(&self, params: &HashMap<String, String>) -> Result<Reply> {
+        let limit = number(params, "limit")?.unwrap_or(usize::MAX);
+        let offset = number(params, "offset")?.unwrap_or(0);
+        let listed: Vec<Listed> = load_all_bookmarks(flag(params, "live")?)
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|(bookmark, browser)| Listed { bookmark, browser })
+            .collect();
+        Reply::json(&listed)
+    }
+
+    /// `q`, `limit`, `title_only`, `url_only`, `sort` and `media`
+    fn search(&self, params: &HashMap<String, String>) -> Result<Reply> {
+        let query = params
+            .get("q")
+            .filter(|q| !q.trim().is_empty())
+            .ok_or_else(|| bad_request("Missing query parameter q"))?;
+        let options = SearchOptions {
+            title_only: flag(params, "title_only")?,
+            url_only: flag(params, "url_only")?,
+            limit: number(params, "limit")?.unwrap_or(20),
+            media_type: params
+                .get("media")
+                .map(|m| MediaType::from_str(m))
+                .transpose()
+                .map_err(|e| bad_request(e.to_string()))?,
+            sort: params
+                .get("sort")
+                .map(|s| SortOrder::from_str(s))
+                .transpose()
+                .map_err(|e| bad_request(e.to_string()))?
+                .unwrap_or_default(),
+        };
+        Reply::json(&search_bookmarks_internal(query, &options)?)
+    }
+
+    /// `live`
+    fn folders(&self, params: &HashMap<String, String>) -> Result<Reply> {
+        let bookmarks: Vec<Bookmark> = load_all_bookmarks(flag(params, "live")?)
+            .into_iter()
+            .map(|(bookmark, _)| bookmark)
+            .collect();
+        Reply::json(&FolderTree::build(&bookmarks))
+    }
+
+    /// `live`; grouped with the config's deduplication and site settings,
+    /// but without following redirects, so the report needs no network
+    fn duplicates(&self, params: &HashMap<String, String>) -> Result<Reply> {
+        let bookmarks: Vec<Bookmark> = load_all_bookmarks(flag(params, "live")?)
+            .into_iter()
+            .map(|(bookmark, _)| bookmark)
+            .collect();
+        let deduplicator = BookmarkDeduplicator::new(DeduplicationConfig {
+            sites: self.config.sites.clone(),
+            resolve_redirects: false,
+            ..self.config.deduplication.clone()
+        });
+        let groups: Vec<DuplicateGroup> = deduplicator
+            .duplicate_groups(&bookmarks)?
+            .into_iter()
+            .map(|(url, bookmarks)| DuplicateGroup { url, bookmarks })
+            .collect();
+        Reply::json(&groups)
+    }
+
+    /// Run the posted bookmarks through the pipeline with the config's
+    /// rules and site settings
+    fn process(&self, body: &str) -> Result<Reply> {
+        let request: ProcessRequest = serde_json::from_str(body)
+            .map_err(|e| bad_request(format!("Invalid process request: {}", e)))?;
+        let merge_strategy = request
+            .strategy
+            .as_deref()
+            .map(MergeStrategy::from_str)
+            .transpose()
+            .map_err(|e| bad_request(e.to_string()))?
+            .unwrap_or(MergeStrategy::MergeMetadata);
+        let config = ProcessingConfig {
+            deduplication_config: DeduplicationConfig {
+                merge_strategy,
+                sites: self.config.sites.clone(),
+                ..Default::default()
+            },
+            organization_config: OrganizationConfig {
+                custom_rules: self.config.organization.custom_rules.clone(),
+                sites: self.config.sites.clone(),
+                ..Default::default()
+            },
+            dry_run: true,
+            backup_original: false,
+            expire_policies: self.config.expire.clone(),
+            folder_quotas: self.config.quotas.clone(),
+            ..Default::default()
+        };
+        let mut processor = BookmarkProcessor::new(config);
+        if let Some(names) = &request.stages {
+            let stages = names
+                .iter()
+                .map(|name| processor.config().stage(name))
+                .collect::<Result<Vec<_>>>()
+                .map_err(|e| bad_request(e.to_string()))?;
+            processor = processor.with_stages(stages);
+        }
+        let result = processor.process_bookmarks(&request.bookmarks)?;
+        Reply::json(&json!({
+            "summary": result.processing_summary,
+            "bookmarks": result.processed_bookmarks,
+        }))
+    }
+
+    /// `format` (json, dot, gexf, mermaid, html), `browser`, `data_type`,
+    /// `detail`, `min_threshold`, `live` and `layout`
+    fn graph(&self, params: &HashMap<String, String>) -> Result<Reply> {
+        let format = params.get("format").map_or("json", String::as_str);
+        let browser = params.get("browser").map_or("all", String::as_str);
+        let data_type = params.get("data_type").map_or("both", String::as_str);
+        let detail_level = match params.get("detail").map_or("standard", String::as_str) {
+            "overview" => DetailLevel::Overview,
+            "standard" => DetailLevel::Standard,
+            "detailed" => DetailLevel::Detailed,
+            other => {
+                return Err(bad_request(format!(
+                    "Invalid detail level: {}. Use overview, standard, or detailed",
+                    other
+                )));
+            }
+        };
+        let layout = flag(params, "layout")?;
+        let content_type = match format {
+            "json" => JSON,
+            "gexf" => "application/xml",
+            "html" => HTML,
+            "dot" | "mermaid" if !layout => "text/plain; charset=utf-8",
+            "dot" | "mermaid" => {
+                return Err(bad_request("layout only applies to gexf, json and html"));
+            }
+            _ => {
+                return Err(bad_request(format!(
+                    "Invalid format: {}. Use json, dot, gexf, mermaid, or html",
+                    format
+                )));
+            }
+        };
+
+        let (bookmarks, history) = graph_data(browser, data_type, flag(params, "live")?)?;
+        let config = GraphConfig {
+            min_domain_threshold: number(params, "min_threshold")?.unwrap_or(5),
+            detail_level,
+            sites: self.config.sites.clone(),
+            theme: self.config.theme.clone(),
+            ..Default::default()
+        };
+        let mut builder = GraphBuilder::new(config);
+        let mut graph = match data_type {
+            "bookmarks" => builder.from_bookmarks(&bookmarks)?,
+            "history" => builder.from_history(&history)?,
+            "both" => builder.from_both(&bookmarks, &history)?,
+            other => {
+                return Err(bad_request(format!(
+                    "Invalid data type: {}. Use bookmarks, history, or both",
+                    other
+                )));
+            }
+        };
+        if layout {
+            graph.apply_layout();
+        }
+        let body = match format {
+            "json" => formats::to_json(&graph),
+            "gexf" => formats::to_gexf(&graph),
+            "html" => formats::to_html(&graph, &self.config.html.load()?),
+            "dot" => formats::to_dot(&graph),
+            _ => formats::to_mermaid(&graph),
+        };
+        Ok(Reply {
+            status: 200,
+            content_type,
+            body,
+        })
+    }
+}
+
+/// Bookmarks and history to graph: the store's when it has been synced and
+/// every browser is wanted, else the browsers'
+fn graph_data(
+    browser: &str,
+    data_type: &str,
+    live: bool,
+) -> Result<(Vec<Bookmark>, Vec<UrlEntry>)> {
+    if browser == "all"
+        && !live
+        && let Some(store) = Store::open_if_synced()?
+    {
+        let bookmarks = match data_type {
+            "history" => Vec::new(),
+            _ => store.bookmarks()?,
+        };
+        let history = match data_type {
+            "bookmarks" => Vec::new(),
+            _ => store.history()?,
+        };
+        return Ok((bookmarks, history));
+    }
+    load_browser_data(browser, data_type)
+}
+
+fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(field, _)| field.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Whether a `Host` header is an IP address, which a page served through
+/// DNS rebinding can't send: its `Host` is the attacker's domain name
+fn is_ip_host(host: &str) -> bool {
+    Url::parse(&format!("http://{}", host))
+        .is_ok_and(|url| matches!(url.host(), Some(Host::Ipv4(_) | Host::Ipv6(_))))
+}
+
+fn header(name: &str, value: &str) -> tiny_http::Header {
+    tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes())
+        .expect("header names and values are ASCII")
+}
+
+fn flag(params: &HashMap<String, String>, name: &str) -> Result<bool> {
+    match params.get(name).map(String::as_str) {
+        None | Some("false" | "0") => Ok(false),
+        Some("" | "true" | "1") => Ok(true),
+        Some(other) => Err(bad_request(format!(
+            "Invalid {}: {} (expected true or false)",
+            name, other
+        ))),
+    }
+}
+
+fn number(params: &HashMap<String, String>, name: &str) -> Result<Option<usize>> {
+    params
+        .get(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| bad_request(format!("Invalid {}: {}", name, value)))
+        })
+        .transpose()
+}
+
+/// The page at `/`; everything it shows comes from the endpoints above
+const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Bookmarks</title>
+<style>
+  * { margin: 0; padding: 0; box-sizing: border-box; }
+  :root { --bg: #f5f5f5; --fg: #333; --panel: #fff; --line: #ddd; --link: #0b57d0; --muted: #777; }
+  @media (prefers-color-scheme: dark) {
+    :root { --bg: #1a1a2e; --fg: #e0e0e0; --panel: #24243e; --line: #333; --link: #80d8ff; --muted: #999; }
+  }
+  body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif; background: var(--bg); color: var(--fg); font-size: 14px; }
+  header { display: flex; align-items: center; gap: 16px; padding: 12px 16px; border-bottom: 1px solid var(--line); background: var(--panel); }
+  header h1 { font-size: 17px; }
+  nav { display: flex; gap: 6px; }
+  nav button, .more { padding: 6px 14px; border-radius: 6px; cursor: pointer; border: 1px solid var(--line); background: var(--bg); color: var(--fg); font-size: 13px; }
+  nav button[aria-selected=true] { background: #4fc3f7; color: #111; border-color: #4fc3f7; }
+  :focus-visible { outline: 3px solid #ffbf47; outline-offset: 2px; }
+  main section { padding: 16px; }
+  main section[hidden] { display: none; }
+  a { color: var(--link); text-decoration: none; }
+  a:hover { text-decoration: underline; }
+  .toolbar { display: flex; align-items: center; gap: 12px; margin-bottom: 12px; }
+  .toolbar input { flex: 1; max-width: 480px; padding: 7px 10px; border-radius: 6px; border: 1px solid var(--line); background: var(--panel); color: var(--fg); font-size: 14px; }
+  .chip { padding: 3px 10px; border-radius: 12px; background: #fff176; color: #111; cursor: pointer; font-size: 12px; }
+  .muted { color: var(--muted); font-size: 12px; }
+  table { width: 100%; border-collapse: collapse; background: var(--panel); }
+  th, td { text-align: left; padding: 6px 10px; border-bottom: 1px solid var(--line); vertical-align: top; }
+  th { font-size: 12px; text-transform: uppercase; letter-spacing: 0.04em; color: var(--muted); }
+  td.url { color: var(--muted); font-size: 12px; word-break: break-all; }
+  .more { margin: 12px 0; }
+  .tree ul { list-style: none; margin-left: 18px; }
+  .tree > ul { margin-left: 0; }
+  .tree li { margin: 2px 0; }
+  .tree summary { cursor: pointer; }
+  .tree .leaf { margin-left: 14px; }
+  .tree button { background: none; border: none; color: var(--link); cursor: pointer; font-size: 14px; }
+  .group { background: var(--panel); border: 1px solid var(--line); border-radius: 8px; padding: 10px 14px; margin-bottom: 10px; }
+  .group h3 { font-size: 13px; font-weight: normal; word-break: break-all; margin-bottom: 6px; }
+  .group li { margin-left: 20px; }
+  #graph-frame { width: 100%; height: calc(100vh - 90px); border: 1px solid var(--line); border-radius: 8px; }
+  .error { color: #e57373; }
+</style>
+</head>
+<body>
+<header>
+  <h1>Bookmarks</h1>
+  <nav role="tablist">
+    <button role="tab" data-tab="bookmarks" aria-selected="true">Bookmarks</button>
+    <button role="tab" data-tab="folders" aria-selected="false">Folders</button>
+    <button role="tab" data-tab="duplicates" aria-selected="false">Duplicates</button>
+    <button role="tab" data-tab="graph" aria-selected="false">Graph</button>
+  </nav>
+</header>
+<main>
+  <section id="bookmarks">
+    <div class="toolbar">
+      <input id="query" type="search" placeholder="Search titles and URLs" aria-label="Search bookmarks">
+      <span id="folder-filter" class="chip" hidden title="Show all folders"></span>
+      <span id="count" class="muted"></span>
+    </div>
+    <table>
+      <thead><tr><th>Title</th><th>URL</th><th>Folder</th><th>Browser</th></tr></thead>
+      <tbody id="rows"></tbody>
+    </table>
+    <button id="more" class="more" hidden>Show more</button>
+  </section>
+  <section id="folders" class="tree" hidden></section>
+  <section id="duplicates" hidden></section>
+  <section id="graph" hidden></section>
+</main>
+<script>
+const PAGE = 200;
+const $ = id => document.getElementById(id);
+let all = null, shown = [], visible = 0, folder = null, searchTimer = null;
+
+function el(tag, text, attrs) {
+  const node = document.createElement(tag);
+  if (text != null) node.textContent = text;
+  Object.entries(attrs || {}).forEach(([k, v]) => node.setAttribute(k, v));
+  return node;
+}
+
+function link(bookmark) {
+  const url = bookmark.url || '';
+  return /^https?:/i.test(url) ? el('a', bookmark.title || url, { href: url, target: '_blank', rel: 'noopener' }) : el('span', bookmark.title);
+}
+
+async function api(path) {
+  const response = await fetch(path);
+  const body = await response.json();
+  if (!response.ok) throw new Error(body.error || response.statusText);
+  return body;
+}
+
+function fail(target, error) {
+  target.replaceChildren(el('p', error.message, { class: 'error' }));
+}
+
+function inFolder(bookmark) {
+  const f = bookmark.folder || '';
+  return folder == null || f === folder || f.startsWith(folder + '/');
+}
+
+function showRows(list) {
+  shown = list.filter(inFolder);
+  visible = 0;
+  $('rows').replaceChildren();
+  $('count').textContent = shown.length + (shown.length === 1 ? ' bookmark' : ' bookmarks');
+  more();
+}
+
+function more() {
+  const rows = $('rows');
+  shown.slice(visible, visible + PAGE).forEach(b => {
+    const tr = el('tr');
+    tr.append(el('td'), el('td', b.url, { class: 'url' }), el('td', b.folder || ''), el('td', b.browser || b.source_browser || ''));
+    tr.firstChild.append(link(b));
+    rows.append(tr);
+  });
+  visible += PAGE;
+  $('more').hidden = visible >= shown.length;
+}
+
+async function refresh() {
+  const query = $('query').value.trim();
+  try {
+    if (query) {
+      showRows(await api('/search?limit=500&q=' + encodeURIComponent(query)));
+    } else {
+      all = all || await api('/bookmarks');
+      showRows(all);
+    }
+  } catch (e) {
+    $('count').textContent = '';
+    const tr = el('tr');
+    tr.append(el('td', e.message, { class: 'error', colspan: 4 }));
+    $('rows').replaceChildren(tr);
+    $('more').hidden = true;
+  }
+}
+
+function filterFolder(path) {
+  folder = path || null;
+  $('folder-filter').hidden = folder == null;
+  $('folder-filter').textContent = (folder || '') + ' ×';
+  select('bookmarks');
+  refresh();
+}
+
+function folderNode(tree) {
+  const li = el('li');
+  const open = el('button', tree.name + ' (' + tree.total + ')');
+  open.onclick = () => filterFolder(tree.path);
+  if (tree.children.length === 0) {
+    open.className = 'leaf';
+    li.append(open);
+    return li;
+  }
+  const details = el('details'), summary = el('summary'), ul = el('ul');
+  summary.append(open);
+  tree.children.forEach(child => ul.append(folderNode(child)));
+  details.append(summary, ul);
+  li.append(details);
+  return li;
+}
+
+const loaders = {
+  async folders(section) {
+    const tree = await api('/folders');
+    const ul = el('ul');
+    ul.append(folderNode({ ...tree, name: 'All bookmarks' }));
+    ul.querySelector('details')?.setAttribute('open', '');
+    section.replaceChildren(ul);
+  },
+  async duplicates(section) {
+    const groups = await api('/duplicates');
+    const extra = groups.reduce((n, g) => n + g.bookmarks.length - 1, 0);
+    section.replaceChildren(el('p', groups.length + ' pages bookmarked more than once; merging would remove ' + extra + ' bookmarks', { class: 'muted' }));
+    groups.forEach(group => {
+      const div = el('div', null, { class: 'group' }), ul = el('ul');
+      group.bookmarks.forEach(b => {
+        const li = el('li');
+        li.append(link(b), el('span', ' ' + (b.folder || 'no folder'), { class: 'muted' }));
+        ul.append(li);
+      });
+      div.append(el('h3', group.url + ' (' + group.bookmarks.length + ')'), ul);
+      section.append(div);
+    });
+  },
+  async graph(section) {
+    section.replaceChildren(el('iframe', null, { id: 'graph-frame', src: '/graph?format=html', title: 'Knowledge graph' }));
+  },
+};
+const loaded = new Set();
+
+function select(tab) {
+  document.querySelectorAll('nav button').forEach(b => b.setAttribute('aria-selected', b.dataset.tab === tab));
+  document.querySelectorAll('main section').forEach(s => s.hidden = s.id !== tab);
+  const section = $(tab);
+  if (loaders[tab] && !loaded.has(tab)) {
+    loaded.add(tab);
+    section.replaceChildren(el('p', 'Loading…', { class: 'muted' }));
+    loaders[tab](section).catch(e => { loaded.delete(tab); fail(section, e); });
+  }
+}
+
+document.querySelectorAll('nav button').forEach(b => b.onclick = () => select(b.dataset.tab));
+$('query').oninput = () => { clearTimeout(searchTimer); searchTimer = setTimeout(refresh, 250); };
+$('folder-filter').onclick = () => filterFolder(null);
+$('more').onclick = more;
+refresh();
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(id: &str, url: &str) -> Bookmark {
+        Bookmark {
+            id: id.to_string(),
+            title: url.to_string(),
+            url: Some(url.to_string()),
+            folder: None,
+            date_added: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_routes_and_process() {
+        let api = ApiServer {
+            config: AppConfig::default(),
+            allow_origin: None,
+            remote_hosts: false,
+        };
+        let dashboard = api.handle("GET", "/", &[], "");
+        assert_eq!((dashboard.status, dashboard.content_type), (200, HTML));
+        for endpoint in [
+            "/bookmarks",
+            "/search?",
+            "/folders",
+            "/duplicates",
+            "/graph?",
+        ] {
+            assert!(dashboard.body.contains(&format!("'{}", endpoint)));
+        }
+        let index: serde_json::Value =
+            serde_json::from_str(&api.handle("GET", "/api", &[], "").body).unwrap();
+        assert_eq!(index["endpoints"].as_array().unwrap().len(), 6);
+        assert_eq!(api.handle("POST", "/", &[], "").status, 405);
+        assert_eq!(api.handle("GET", "/nowhere", &[], "").status, 404);
+        assert_eq!(api.handle("DELETE", "/bookmarks", &[], "").status, 405);
+        assert_eq!(api.handle("GET", "/search?q=", &[], "").status, 400);
+        assert_eq!(api.handle("GET", "/graph?format=svg", &[], "").status, 400);
+        assert_eq!(api.handle("POST", "/process", &[], "not json").status, 400);
+
+        let body = json!({
+            "bookmarks": [
+                bookmark("1", "https://example.com/a"),
+                bookmark("2", "https://www.example.com/a/"),
+                bookmark("3", "https://rust-lang.org/"),
+            ],
+            "strategy": "first",
+        });
+        let reply = api.handle("POST", "/process", &[], &body.to_string());
+        assert_eq!((reply.status, reply.content_type), (200, JSON));
+        let result: serde_json::Value = serde_json::from_str(&reply.body).unwrap();
+        assert_eq!(result["summary"]["duplicates_removed"], 1);
+        assert_eq!(result["bookmarks"].as_array().unwrap().len(), 2);
+
+        let unknown_stage = json!({ "bookmarks": [], "stages": ["nope"] });
+        assert_eq!(
+            api.handle("POST", "/process", &[], &unknown_stage.to_string())
+                .status,
+            400
+        );
+    }
+
+    #[test]
+    fn test_rejects_foreign_hosts_and_origins() {
+        let mut api = ApiServer {
+            config: AppConfig::default(),
+            allow_origin: Some("http://ui.example".to_string()),
+            remote_hosts: false,
+        };
+        let status = |api: &ApiServer, name: &str, value: &str| {
+            let headers = [(name.to_string(), value.to_string())];
+            api.handle("GET", "/api", &headers, "").status
+        };
+        assert_eq!(status(&api, "Host", "localhost:8787"), 200);
+        assert_eq!(status(&api, "host", "127.0.0.1:8787"), 200);
+        assert_eq!(status(&api, "Host", "[::1]:8787"), 200);
+        assert_eq!(status(&api, "Host", "rebound.example:8787"), 403);
+        assert_eq!(status(&api, "Host", "192.168.1.20:8787"), 403);
+        assert_eq!(status(&api, "Origin", "http://localhost:3000"), 200);
+        assert_eq!(status(&api, "Origin", "http://ui.example"), 200);
+        assert_eq!(status(&api, "Origin", "https://evil.example"), 403);
+
+        api.remote_hosts = true;
+        assert_eq!(status(&api, "Host", "192.168.1.20:8787"), 200);
+        assert_eq!(status(&api, "Host", "rebound.example:8787"), 403);
+    }
+}
@@ -0,0 +1,118 @@
+//! Saved versions of the bookmark set.
+//!
+//! A [`Snapshot`] is a copy of the store's bookmarks (or an export's) at one
+//! moment, kept in the store's `snapshots` table. Its id is a hash of the
+//! bookmarks alone, so taking a snapshot of an unchanged collection finds
+//! the existing one instead of adding a copy. Snapshots are compared with
+//! [`crate::diff::BookmarkDiff`] and restored to an export file for
+//! `apply`, the way the trash is; the store itself follows the browsers.
+//! These are unrelated to the browser database copies under the
+//! `snapshots` path, which are temporary.
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+use crate::exporter::{Bookmark, flatten_bookmarks};
+
+/// Hex digits of the content hash used as an id
+const ID_LEN: usize = 12;
+
+/// A saved bookmark set
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// Hash of `bookmarks`
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    /// What was taken, e.g. `before cleanup`
+    pub label: Option<String>,
+    /// Flat, ordered by URL, without store ids
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// A snapshot as listed, without its bookmarks
+#[derive(Debug, Clone)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub label: Option<String>,
+    pub bookmarks: usize,
+}
+
+impl Snapshot {
+    /// Snapshot `bookmarks`, nested or flat. Ids are dropped, as they are
+    /// store row numbers or browser ids that say nothing about the content.
+    pub fn new(bookmarks: &[Bookmark], label: Option<String>) -> Self {
+        let mut bookmarks: Vec<Bookmark> = flatten_bookmarks(bookmarks)
+            .into_iter()
+            .map(|bookmark| Bookmark {
+                id: String::new(),
+                ..bookmark
+            })
+            .collect();
+        bookmarks.sort_by(|a, b| (&a.url, &a.folder, &a.title).cmp(&(&b.url, &b.folder, &b.title)));
+        Self {
+            id: content_id(&bookmarks),
+            created_at: Utc::now(),
+            label,
+            bookmarks,
+        }
+    }
+}
+
+/// Short SHA-256 of the bookmarks' JSON
+fn content_id(bookmarks: &[Bookmark]) -> String {
+    let json = serde_json::to_vec(bookmarks).unwrap_or_default();
+    Sha256::digest(&json)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>()[..ID_LEN]
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(id: &str, url: &str, folder: &str) -> Bookmark {
+        Bookmark {
+            id: id.to_string(),
+            title: url.to_string(),
+            url: Some(url.to_string()),
+            folder: Some(folder.to_string()),
+            date_added: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_id_depends_on_content_only() {
+        let a = Snapshot::new(
+            &[
+                bookmark("1", "https://a.example/", "Dev"),
+                bookmark("2", "https://b.example/", "News"),
+            ],
+            None,
+        );
+        // Other ids, other order, a label: same content
+        let b = Snapshot::new(
+            &[
+                bookmark("9", "https://b.example/", "News"),
+                bookmark("8", "https://a.example/", "Dev"),
+            ],
+            Some("again".to_string()),
+        );
+        let moved = Snapshot::new(
+            &[
+                bookmark("1", "https://a.example/", "Rust"),
+                bookmark("2", "https://b.example/", "News"),
+            ],
+            None,
+        );
+
+        assert_eq!(a.id.len(), ID_LEN);
+        assert_eq!(a.id, b.id);
+        assert_ne!(a.id, moved.id);
+        assert_eq!(b.bookmarks[0].url.as_deref(), Some("https://a.example/"));
+        assert!(b.bookmarks.iter().all(|b| b.id.is_empty()));
+    }
+}
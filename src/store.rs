@@ -0,0 +1,1461 @@
+//! Persistent bookmark store.
+//!
+//! A SQLite database (see [`crate::paths`] for its location) holding the
+//! merged bookmark set from every synced browser profile, so searching,
+//! opening and graphing don't have to re-read browser databases each run.
+//! Canonical `bookmarks` rows are unique by URL; `bookmark_sources` records
+//! which browser profiles hold each one (the provenance), and `syncs` records
+//! when each profile was last synced along with its high-water marks, so
+//! later syncs only read what is newer. History is kept per profile and
+//! merged on read. `pair_baselines` holds what two browsers agreed on after
+//! their last `sync --between`, the base the next one diffs against.
+//! Bookmarks that disappear from every browser, or that `apply --prune`
+//! deletes, are kept in `trash` until restored or past their retention.
+//! `snapshots` holds saved copies of the bookmark set (see
+//! [`crate::snapshot`]), and `journal` what recent `process` runs wrote (see
+//! [`crate::journal`]).
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::browser::Browser;
+use crate::exporter::{self, Bookmark, BrowserData, UrlEntry, flatten_bookmarks};
+use crate::journal::{JOURNAL_KEEP, JournalEntry};
+use crate::reconcile::Entry;
+use crate::snapshot::{Snapshot, SnapshotInfo};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS bookmarks (
+    id INTEGER PRIMARY KEY,
+    url TEXT NOT NULL UNIQUE,
+    title TEXT NOT NULL,
+    folder TEXT,
+    date_added TEXT,
+    keyword TEXT,
+    description TEXT,
+    updated_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS bookmark_tags (
+    bookmark_id INTEGER NOT NULL REFERENCES bookmarks(id),
+    tag TEXT NOT NULL,
+    PRIMARY KEY (bookmark_id, tag)
+);
+CREATE TABLE IF NOT EXISTS bookmark_sources (
+    bookmark_id INTEGER NOT NULL REFERENCES bookmarks(id),
+    browser TEXT NOT NULL,
+    profile TEXT NOT NULL,
+    source_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    folder TEXT,
+    synced_at TEXT NOT NULL,
+    PRIMARY KEY (browser, profile, source_id)
+);
+CREATE TABLE IF NOT EXISTS history (
+    url TEXT NOT NULL,
+    browser TEXT NOT NULL,
+    profile TEXT NOT NULL,
+    title TEXT NOT NULL,
+    visit_count INTEGER NOT NULL,
+    last_visit TEXT,
+    PRIMARY KEY (url, browser, profile)
+);
+CREATE TABLE IF NOT EXISTS syncs (
+    browser TEXT NOT NULL,
+    profile TEXT NOT NULL,
+    synced_at TEXT NOT NULL,
+    bookmarks INTEGER,
+    history INTEGER,
+    tool_version TEXT NOT NULL,
+    browser_version TEXT,
+    bookmarks_through TEXT,
+    history_through TEXT,
+    PRIMARY KEY (browser, profile)
+);
+CREATE TABLE IF NOT EXISTS pair_baselines (
+    pair TEXT NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    folder TEXT NOT NULL,
+    date_added TEXT,
+    PRIMARY KEY (pair, url)
+);
+CREATE TABLE IF NOT EXISTS trash (
+    id INTEGER PRIMARY KEY,
+    url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    folder TEXT,
+    date_added TEXT,
+    keyword TEXT,
+    description TEXT,
+    tags TEXT,
+    reason TEXT NOT NULL,
+    deleted_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS snapshots (
+    id TEXT PRIMARY KEY,
+    created_at TEXT NOT NULL,
+    label TEXT,
+    bookmarks INTEGER NOT NULL,
+    content TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS journal (
+    id INTEGER PRIMARY KEY,
+    operation TEXT NOT NULL,
+    at TEXT NOT NULL,
+    output TEXT NOT NULL,
+    previous TEXT,
+    written TEXT NOT NULL,
+    input TEXT NOT NULL,
+    settings TEXT NOT NULL,
+    undone_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_bookmark_sources_bookmark ON bookmark_sources(bookmark_id);
+CREATE INDEX IF NOT EXISTS idx_history_last_visit ON history(last_visit);
+";
+
+/// What one profile contributed to a sync
+#[derive(Debug)]
+pub struct ProfileSync {
+    pub browser: String,
+    pub profile: String,
+    /// Bookmarks written; `None` when bookmarks were not part of the sync
+    pub bookmarks: Option<usize>,
+    /// History URLs written
+    pub history: Option<usize>,
+    /// Whether only data newer than the profile's high-water marks was read
+    pub incremental: bool,
+    /// Bookmarks no profile holds any more, moved to the trash
+    pub trashed: usize,
+}
+
+/// A deleted bookmark kept in the trash
+#[derive(Debug, Clone)]
+pub struct TrashEntry {
+    /// Trash id, used to restore it
+    pub id: i64,
+    pub bookmark: Bookmark,
+    /// What deleted it: `sync` (gone from every browser) or `prune`
+    pub reason: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Newest bookmark added and page visited that the store has seen for a
+/// profile. A section without a mark is read and replaced in full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HighWaterMarks {
+    pub bookmarks: Option<DateTime<Utc>>,
+    pub history: Option<DateTime<Utc>>,
+}
+
+/// What [`Store::import`] did with a file's bookmarks
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// Bookmarks the store didn't have
+    pub added: usize,
+    /// Known bookmarks given the imported title, folder and tags
+    pub updated: usize,
+    /// Known bookmarks, and repeats within the file, left alone
+    pub skipped: usize,
+}
+
+/// Row counts across the whole store
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct StoreStats {
+    pub bookmarks: usize,
+    pub history: usize,
+    pub profiles: usize,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// Open (creating if needed) the store at `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Open the store at the configured location
+    pub fn open_default() -> Result<Self> {
+        Self::open(&crate::paths::get().store)
+    }
+
+    /// Open the configured store only if it has been synced at least once
+    pub fn open_if_synced() -> Result<Option<Self>> {
+        if !crate::paths::get().store.exists() {
+            return Ok(None);
+        }
+        let store = Self::open_default()?;
+        Ok(store.last_synced()?.is_some().then_some(store))
+    }
+
+    /// Refresh the store from a browser (or `all`). `data_type` is
+    /// `bookmarks`, `history` or `both`. Profiles synced before only have
+    /// what is newer than their high-water marks read, unless `full` is set;
+    /// a full sync also notices deleted and renamed bookmarks.
+    pub fn sync(&mut self, browser: &str, data_type: &str, full: bool) -> Result<Vec<ProfileSync>> {
+        if !matches!(data_type, "bookmarks" | "history" | "both") {
+            return Err(anyhow!("Invalid data type: {}", data_type));
+        }
+        let browsers: Vec<&str> = if browser == "all" {
+            vec!["chrome", "firefox", "safari", "edge"]
+        } else {
+            vec![browser]
+        };
+
+        let mut synced = Vec::new();
+        for name in browsers {
+            let profiles = match Browser::from_str(name)?.find_profiles(None) {
+                Ok(profiles) if !profiles.is_empty() => profiles,
+                Ok(_) if browser == "all" => continue,
+                Ok(_) => return Err(anyhow!("No profiles found for {}", name)),
+                Err(e) if browser == "all" => {
+                    log::debug!("Skipping {}: {}", name, e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            for profile_path in profiles {
+                let profile = profile_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                let since = if full {
+                    HighWaterMarks::default()
+                } else {
+                    self.high_water_marks(name, &profile)?
+                };
+                let data = exporter::read_profile(name, &profile_path, data_type, since.history)?;
+                synced.push(self.ingest_since(&data, &since)?);
+            }
+        }
+        Ok(synced)
+    }
+
+    /// Replace everything the store holds for `data`'s browser profile with
+    /// its contents. Sections that are `None` are left as they were.
+    pub fn ingest(&mut self, data: &BrowserData) -> Result<ProfileSync> {
+        self.ingest_since(data, &HighWaterMarks::default())
+    }
+
+    /// Like [`Store::ingest`], but a section with a high-water mark is merged
+    /// instead of replaced: only bookmarks added and URLs visited since the
+    /// mark are written, and nothing is removed. Browser timestamps are read
+    /// at second precision, so rows at the mark itself are rewritten too.
+    pub fn ingest_since(
+        &mut self,
+        data: &BrowserData,
+        since: &HighWaterMarks,
+    ) -> Result<ProfileSync> {
+        let now = Utc::now().to_rfc3339();
+        let tx = self.conn.transaction()?;
+        let mut marks = *since;
+        let mut trashed = 0;
+
+        let bookmarks = match &data.bookmarks {
+            Some(bookmarks) => {
+                let bookmarks = flatten_bookmarks(bookmarks);
+                let newest = bookmarks.iter().filter_map(|b| b.date_added).max();
+                marks.bookmarks = marks.bookmarks.max(newest);
+                Some(match since.bookmarks {
+                    None => {
+                        let (count, gone) = replace_bookmarks(&tx, data, &bookmarks, &now)?;
+                        trashed = gone;
+                        count
+                    }
+                    Some(mark) => {
+                        // Undated bookmarks can't be placed against the mark; upserting them is harmless
+                        let newer: Vec<Bookmark> = bookmarks
+                            .into_iter()
+                            .filter(|b| b.date_added.is_none_or(|d| d >= mark))
+                            .collect();
+                        upsert_bookmarks(&tx, data, &newer, &now)?
+                    }
+                })
+            }
+            None => None,
+        };
+        let history = match &data.history {
+            Some(history) => {
+                let newest = history.urls.iter().filter_map(|u| u.last_visit).max();
+                marks.history = marks.history.max(newest);
+                Some(match since.history {
+                    None => replace_history(&tx, data, &history.urls)?,
+                    Some(mark) => {
+                        let newer: Vec<&UrlEntry> = history
+                            .urls
+                            .iter()
+                            .filter(|u| u.last_visit.is_some_and(|d| d >= mark))
+                            .collect();
+                        upsert_history(&tx, data, newer)?
+                    }
+                })
+            }
+            None => None,
+        };
+
+        let provenance = data.provenance.as_ref();
+        tx.execute(
+            "INSERT INTO syncs
+                 (browser, profile, synced_at, bookmarks, history, tool_version, browser_version,
+                  bookmarks_through, history_through)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT (browser, profile) DO UPDATE SET
+                 synced_at = excluded.synced_at,
+                 bookmarks = COALESCE(excluded.bookmarks, bookmarks),
+                 history = COALESCE(excluded.history, history),
+                 tool_version = excluded.tool_version,
+                 browser_version = COALESCE(excluded.browser_version, browser_version),
+                 bookmarks_through = COALESCE(excluded.bookmarks_through, bookmarks_through),
+                 history_through = COALESCE(excluded.history_through, history_through)",
+            params![
+                data.browser,
+                data.profile,
+                now,
+                bookmarks.map(|n| n as i64),
+                history.map(|n| n as i64),
+                env!("CARGO_PKG_VERSION"),
+                provenance.and_then(|p| p.browser_version.clone()),
+                data.bookmarks
+                    .as_ref()
+                    .and(marks.bookmarks)
+                    .map(|d| d.to_rfc3339()),
+                data.history
+                    .as_ref()
+                    .and(marks.history)
+                    .map(|d| d.to_rfc3339())
+            ],
+        )?;
+        tx.commit()?;
+
+        Ok(ProfileSync {
+            browser: data.browser.clone(),
+            profile: data.profile.clone(),
+            bookmarks,
+            history,
+            incremental: since.bookmarks.is_some() || since.history.is_some(),
+            trashed,
+        })
+    }
+
+    /// Newest bookmark and visit already in the store for a profile; empty
+    /// if the profile has never been synced
+    pub fn high_water_marks(&self, browser: &str, profile: &str) -> Result<HighWaterMarks> {
+        let marks: Option<(Option<String>, Option<String>)> = self
+            .conn
+            .query_row(
+                "SELECT bookmarks_through, history_through FROM syncs
+                 WHERE browser = ?1 AND profile = ?2",
+                params![browser, profile],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let (bookmarks, history) = marks.unwrap_or_default();
+        Ok(HighWaterMarks {
+            bookmarks: parse_date(bookmarks),
+            history: parse_date(history),
+        })
+    }
+
+    /// The merged bookmark set, oldest first
+    pub fn bookmarks(&self) -> Result<Vec<Bookmark>> {
+        Ok(self
+            .bookmarks_with_browsers()?
+            .into_iter()
+            .map(|(bookmark, _)| bookmark)
+            .collect())
+    }
+
+    /// The merged bookmark set, each with the browsers that hold it
+    pub fn bookmarks_with_browsers(&self) -> Result<Vec<(Bookmark, Vec<String>)>> {
+        let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+        let mut stmt = self
+            .conn
+            .prepare("SELECT bookmark_id, tag FROM bookmark_tags ORDER BY tag")?;
+        for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (id, tag): (i64, String) = row?;
+            tags.entry(id).or_default().push(tag);
+        }
+
+        let mut browsers: HashMap<i64, Vec<String>> = HashMap::new();
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT bookmark_id, browser FROM bookmark_sources ORDER BY browser",
+        )?;
+        for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (id, browser): (i64, String) = row?;
+            browsers.entry(id).or_default().push(browser);
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, url, title, folder, date_added, keyword, description
+             FROM bookmarks ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            Ok((
+                id,
+                Bookmark {
+                    id: id.to_string(),
+                    title: row.get(2)?,
+                    url: Some(row.get(1)?),
+                    folder: row.get(3)?,
+                    date_added: parse_date(row.get(4)?),
+                    keyword: row.get(5)?,
+                    description: row.get(6)?,
+                    ..Default::default()
+                },
+            ))
+        })?;
+
+        let mut bookmarks = Vec::new();
+        for row in rows {
+            let (id, mut bookmark) = row?;
+            bookmark.tags = tags.remove(&id);
+            bookmarks.push((bookmark, browsers.remove(&id).unwrap_or_default()));
+        }
+        Ok(bookmarks)
+    }
+
+    /// The profiles holding each stored URL, without repeats, by name
+    pub fn profiles_by_url(&self) -> Result<HashMap<String, Vec<String>>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT b.url, s.profile FROM bookmark_sources s
+             JOIN bookmarks b ON b.id = s.bookmark_id ORDER BY s.browser, s.profile",
+        )?;
+        let mut profiles: HashMap<String, Vec<String>> = HashMap::new();
+        for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (url, profile): (String, String) = row?;
+            let held = profiles.entry(url).or_default();
+            if !held.contains(&profile) {
+                held.push(profile);
+            }
+        }
+        Ok(profiles)
+    }
+
+    /// The bookmark set as it stood just before `at`: stored bookmarks added
+    /// by then, and trashed ones added by then but deleted after, with the
+    /// browsers holding each today (none for trashed ones). Titles, folders
+    /// and tags are the latest known, as edits are not recorded; bookmarks
+    /// without a date count as always there, and an emptied trash forgets
+    /// what it held.
+    pub fn bookmarks_as_of(&self, at: DateTime<Utc>) -> Result<Vec<(Bookmark, Vec<String>)>> {
+        let added = |b: &Bookmark| b.date_added.is_none_or(|added| added < at);
+        let mut bookmarks: Vec<(Bookmark, Vec<String>)> = self
+            .bookmarks_with_browsers()?
+            .into_iter()
+            .filter(|(bookmark, _)| added(bookmark))
+            .collect();
+        let mut urls: HashSet<String> = bookmarks
+            .iter()
+            .filter_map(|(bookmark, _)| bookmark.url.clone())
+            .collect();
+        // Most recent deletion first, so a URL trashed twice keeps its last state
+        for entry in self.trash()? {
+            if entry.deleted_at >= at
+                && added(&entry.bookmark)
+                && let Some(url) = &entry.bookmark.url
+                && urls.insert(url.clone())
+            {
+                bookmarks.push((entry.bookmark, Vec::new()));
+            }
+        }
+        Ok(bookmarks)
+    }
+
+    /// Write edited title, folder, keyword, description and tags back to the
+    /// canonical rows of `bookmarks`, which must come from this store. The
+    /// next sync of a profile holding one replaces its title and folder
+    /// again; tags are only ever added by syncs.
+    pub fn update_bookmarks(&mut self, bookmarks: &[Bookmark]) -> Result<usize> {
+        let now = Utc::now().to_rfc3339();
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+        for bookmark in bookmarks {
+            let id: i64 = bookmark
+                .id
+                .parse()
+                .map_err(|_| anyhow!("Not a stored bookmark id: {}", bookmark.id))?;
+            count += tx.execute(
+                "UPDATE bookmarks
+                 SET title = ?2, folder = ?3, keyword = ?4, description = ?5, updated_at = ?6
+                 WHERE id = ?1",
+                params![
+                    id,
+                    bookmark.title,
+                    bookmark.folder,
+                    bookmark.keyword,
+                    bookmark.description,
+                    now
+                ],
+            )?;
+            tx.execute(
+                "DELETE FROM bookmark_tags WHERE bookmark_id = ?1",
+                params![id],
+            )?;
+            for tag in bookmark.tags.iter().flatten() {
+                tx.execute(
+                    "INSERT OR IGNORE INTO bookmark_tags (bookmark_id, tag) VALUES (?1, ?2)",
+                    params![id, tag],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Keep copies of `bookmarks`, deleted elsewhere (e.g. by `apply
+    /// --prune`), in the trash
+    pub fn trash_bookmarks(&mut self, bookmarks: &[Bookmark], reason: &str) -> Result<usize> {
+        let now = Utc::now().to_rfc3339();
+        let tx = self.conn.transaction()?;
+        for bookmark in bookmarks {
+            tx.execute(
+                "INSERT INTO trash
+                     (url, title, folder, date_added, keyword, description, tags, reason, deleted_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    bookmark.url.as_deref().unwrap_or_default(),
+                    bookmark.title,
+                    bookmark.folder,
+                    bookmark.date_added.map(|d| d.to_rfc3339()),
+                    bookmark.keyword,
+                    bookmark.description,
+                    bookmark.tags.as_ref().map(|tags| tags.join("\n")),
+                    reason,
+                    now
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(bookmarks.len())
+    }
+
+    /// Delete `bookmarks`, which must come from this store, keeping copies
+    /// in the trash. Browsers still holding one bring it back on their next
+    /// full sync.
+    pub fn delete_bookmarks(&mut self, bookmarks: &[Bookmark], reason: &str) -> Result<usize> {
+        let ids = bookmarks
+            .iter()
+            .map(|b| {
+                b.id.parse::<i64>()
+                    .map_err(|_| anyhow!("Not a stored bookmark id: {}", b.id))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.trash_bookmarks(bookmarks, reason)?;
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+        for id in ids {
+            tx.execute(
+                "DELETE FROM bookmark_tags WHERE bookmark_id = ?1",
+                params![id],
+            )?;
+            tx.execute(
+                "DELETE FROM bookmark_sources WHERE bookmark_id = ?1",
+                params![id],
+            )?;
+            count += tx.execute("DELETE FROM bookmarks WHERE id = ?1", params![id])?;
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Everything in the trash, most recently deleted first
+    pub fn trash(&self) -> Result<Vec<TrashEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, url, title, folder, date_added, keyword, description, tags, reason,
+                    deleted_at
+             FROM trash ORDER BY deleted_at DESC, id DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            let tags: Option<String> = row.get(7)?;
+            Ok(TrashEntry {
+                id,
+                bookmark: Bookmark {
+                    id: format!("trash:{}", id),
+                    title: row.get(2)?,
+                    url: Some(row.get(1)?),
+                    folder: row.get(3)?,
+                    date_added: parse_date(row.get(4)?),
+                    keyword: row.get(5)?,
+                    tags: tags.map(|t| t.lines().map(str::to_string).collect()),
+                    description: row.get(6)?,
+                    ..Default::default()
+                },
+                reason: row.get(8)?,
+                deleted_at: parse_date(row.get(9)?).unwrap_or_default(),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Take the entries with these trash ids out of the trash, returning
+    /// their bookmarks. Fails without removing anything if an id is unknown.
+    pub fn restore_from_trash(&mut self, ids: &[i64]) -> Result<Vec<Bookmark>> {
+        let mut entries: HashMap<i64, TrashEntry> =
+            self.trash()?.into_iter().map(|e| (e.id, e)).collect();
+        let mut restored = Vec::new();
+        for id in ids {
+            let entry = entries
+                .remove(id)
+                .ok_or_else(|| anyhow!("No bookmark with id {} in the trash", id))?;
+            restored.push(entry.bookmark);
+        }
+        let tx = self.conn.transaction()?;
+        for id in ids {
+            tx.execute("DELETE FROM trash WHERE id = ?1", params![id])?;
+        }
+        tx.commit()?;
+        Ok(restored)
+    }
+
+    /// Delete trash entries for good: those deleted before `before`, or all
+    pub fn empty_trash(&mut self, before: Option<DateTime<Utc>>) -> Result<usize> {
+        let count = match before {
+            Some(before) => self.conn.execute(
+                "DELETE FROM trash WHERE deleted_at < ?1",
+                params![before.to_rfc3339()],
+            )?,
+            None => self.conn.execute("DELETE FROM trash", [])?,
+        };
+        Ok(count)
+    }
+
+    /// History merged across profiles: visit counts summed, latest visit
+    /// kept, most recently visited first
+    pub fn history(&self) -> Result<Vec<UrlEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT url, MAX(title), SUM(visit_count), MAX(last_visit)
+             FROM history GROUP BY url ORDER BY MAX(last_visit) DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(UrlEntry {
+                url: row.get(0)?,
+                title: row.get(1)?,
+                visit_count: row.get(2)?,
+                last_visit: parse_date(row.get(3)?),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// [`Store::history`] limited to URLs last visited before `at`. A URL
+    /// visited both before and since is missing, as only the latest visit
+    /// is kept, and visit counts include later visits.
+    pub fn history_as_of(&self, at: DateTime<Utc>) -> Result<Vec<UrlEntry>> {
+        Ok(self
+            .history()?
+            .into_iter()
+            .filter(|entry| entry.last_visit.is_some_and(|visit| visit < at))
+            .collect())
+    }
+
+    /// Add an imported file's bookmarks (`data.browser` is the format and
+    /// `data.profile` the file name), matching them against the store by
+    /// `key`, a normalized URL. A bookmark already stored, or repeated in
+    /// the file, is skipped, or with `update` overwrites the stored title
+    /// and folder and adds its tags. Nothing is removed, so
+    /// importing the same file again adds nothing. An import is not a sync:
+    /// it leaves [`Store::last_synced`] alone, so a store that holds only
+    /// imports doesn't stand in for the browsers.
+    pub fn import(
+        &mut self,
+        data: BrowserData,
+        key: impl Fn(&str) -> String,
+        update: bool,
+    ) -> Result<ImportSummary> {
+        let mut statement = self.conn.prepare("SELECT url FROM bookmarks")?;
+        let stored: HashMap<String, String> = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|url| url.map(|url| (key(&url), url)))
+            .collect::<rusqlite::Result<_>>()?;
+        drop(statement);
+
+        let mut summary = ImportSummary::default();
+        let mut seen = HashSet::new();
+        let mut bookmarks = Vec::new();
+        for bookmark in flatten_bookmarks(data.bookmarks.as_deref().unwrap_or_default()) {
+            let Some(url) = bookmark.url.as_deref().filter(|u| !u.is_empty()) else {
+                continue;
+            };
+            let key = key(url);
+            if !seen.insert(key.clone()) {
+                summary.skipped += 1;
+                continue;
+            }
+            match stored.get(&key) {
+                None => {
+                    summary.added += 1;
+                    bookmarks.push(bookmark);
+                }
+                Some(existing) if update => {
+                    summary.updated += 1;
+                    bookmarks.push(Bookmark {
+                        url: Some(existing.clone()),
+                        ..bookmark
+                    });
+                }
+                Some(_) => summary.skipped += 1,
+            }
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let tx = self.conn.transaction()?;
+        upsert_bookmarks(&tx, &data, &bookmarks, &now)?;
+        tx.commit()?;
+        Ok(summary)
+    }
+
+    /// When any profile was last synced
+    pub fn last_synced(&self) -> Result<Option<DateTime<Utc>>> {
+        let latest: Option<String> = self
+            .conn
+            .query_row("SELECT MAX(synced_at) FROM syncs", [], |row| row.get(0))
+            .optional()?
+            .flatten();
+        Ok(parse_date(latest))
+    }
+
+    pub fn stats(&self) -> Result<StoreStats> {
+        let count = |sql: &str| -> Result<usize> {
+            Ok(self.conn.query_row(sql, [], |row| row.get::<_, i64>(0))? as usize)
+        };
+        Ok(StoreStats {
+            bookmarks: count("SELECT COUNT(*) FROM bookmarks")?,
+            history: count("SELECT COUNT(DISTINCT url) FROM history")?,
+            profiles: count("SELECT COUNT(*) FROM syncs")?,
+        })
+    }
+
+    /// Keep `snapshot` unless one with the same content is already kept,
+    /// returning that one's info
+    pub fn save_snapshot(&mut self, snapshot: &Snapshot) -> Result<Option<SnapshotInfo>> {
+        if let Some(existing) = self.snapshots()?.into_iter().find(|s| s.id == snapshot.id) {
+            return Ok(Some(existing));
+        }
+        self.conn.execute(
+            "INSERT INTO snapshots (id, created_at, label, bookmarks, content)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                snapshot.id,
+                snapshot.created_at.to_rfc3339(),
+                snapshot.label,
+                snapshot.bookmarks.len() as i64,
+                serde_json::to_string(&snapshot.bookmarks)?
+            ],
+        )?;
+        Ok(None)
+    }
+
+    /// Record a run in the journal, dropping the oldest beyond
+    /// [`JOURNAL_KEEP`], and return its id
+    pub fn record_journal(&mut self, entry: &JournalEntry) -> Result<i64> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO journal (operation, at, output, previous, written, input, settings)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                entry.operation,
+                entry.at.to_rfc3339(),
+                entry.output.to_string_lossy(),
+                entry.previous,
+                entry.written,
+                serde_json::to_string(&entry.input)?,
+                serde_json::to_string(&entry.settings)?
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        tx.execute(
+            "DELETE FROM journal WHERE id NOT IN
+                 (SELECT id FROM journal ORDER BY id DESC LIMIT ?1)",
+            params![JOURNAL_KEEP as i64],
+        )?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// The recorded runs, most recent first
+    pub fn journal(&self) -> Result<Vec<JournalEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, operation, at, output, previous, written, input, settings, undone_at
+             FROM journal ORDER BY id DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, String>(6)?,
+                row.get::<_, String>(7)?,
+                row.get::<_, Option<String>>(8)?,
+            ))
+        })?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id, operation, at, output, previous, written, input, settings, undone_at) = row?;
+            entries.push(JournalEntry {
+                id,
+                operation,
+                at: parse_date(Some(at)).unwrap_or_default(),
+                output: output.into(),
+                previous,
+                written,
+                input: serde_json::from_str(&input)?,
+                settings: serde_json::from_str(&settings)?,
+                undone_at: parse_date(undone_at),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Note that a journal entry has been undone
+    pub fn mark_undone(&mut self, id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE journal SET undone_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
+
+    /// Every kept snapshot, most recent first
+    pub fn snapshots(&self) -> Result<Vec<SnapshotInfo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, created_at, label, bookmarks FROM snapshots ORDER BY created_at DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(SnapshotInfo {
+                id: row.get(0)?,
+                created_at: parse_date(row.get(1)?).unwrap_or_default(),
+                label: row.get(2)?,
+                bookmarks: row.get::<_, i64>(3)? as usize,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// The snapshot whose id starts with `prefix`; fails if none or several do
+    pub fn snapshot(&self, prefix: &str) -> Result<Snapshot> {
+        let mut matches: Vec<SnapshotInfo> = self
+            .snapshots()?
+            .into_iter()
+            .filter(|s| s.id.starts_with(prefix))
+            .collect();
+        let info = match matches.len() {
+            0 => return Err(anyhow!("No snapshot with id {}", prefix)),
+            1 => matches.remove(0),
+            n => return Err(anyhow!("{} snapshots have ids starting with {}", n, prefix)),
+        };
+        let content: String = self.conn.query_row(
+            "SELECT content FROM snapshots WHERE id = ?1",
+            params![info.id],
+            |row| row.get(0),
+        )?;
+        Ok(Snapshot {
+            id: info.id,
+            created_at: info.created_at,
+            label: info.label,
+            bookmarks: serde_json::from_str(&content)?,
+        })
+    }
+
+    /// What the two profiles in `pair` agreed on after their last
+    /// `sync --between`; empty before the first
+    pub fn pair_baseline(&self, pair: &str) -> Result<BTreeMap<String, Entry>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT url, title, folder, date_added FROM pair_baselines WHERE pair = ?1")?;
+        let rows = stmt.query_map(params![pair], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                Entry {
+                    title: row.get(1)?,
+                    folder: row.get(2)?,
+                    date_added: parse_date(row.get(3)?),
+                },
+            ))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Replace `pair`'s baseline with `merged`
+    pub fn set_pair_baseline(
+        &mut self,
+        pair: &str,
+        merged: &BTreeMap<String, Entry>,
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM pair_baselines WHERE pair = ?1", params![pair])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO pair_baselines (pair, url, title, folder, date_added)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (url, entry) in merged {
+                insert.execute(params![
+                    pair,
+                    url,
+                    entry.title,
+                    entry.folder,
+                    entry.date_added.map(|d| d.to_rfc3339())
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// Swap a profile's bookmark sources for `bookmarks`, dropping canonical
+/// rows that no profile holds any more, keeping those in the trash. Returns
+/// the bookmarks written and the number trashed.
+fn replace_bookmarks(
+    tx: &Transaction,
+    data: &BrowserData,
+    bookmarks: &[Bookmark],
+    now: &str,
+) -> Result<(usize, usize)> {
+    tx.execute(
+        "DELETE FROM bookmark_sources WHERE browser = ?1 AND profile = ?2",
+        params![data.browser, data.profile],
+    )?;
+    let count = upsert_bookmarks(tx, data, bookmarks, now)?;
+
+    // Bookmarks deleted in every browser that held them
+    let trashed = tx.execute(
+        "INSERT INTO trash
+             (url, title, folder, date_added, keyword, description, tags, reason, deleted_at)
+         SELECT url, title, folder, date_added, keyword, description,
+                (SELECT group_concat(tag, char(10)) FROM bookmark_tags
+                 WHERE bookmark_id = bookmarks.id),
+                'sync', ?1
+         FROM bookmarks WHERE id NOT IN (SELECT bookmark_id FROM bookmark_sources)",
+        params![now],
+    )?;
+    tx.execute_batch(
+        "DELETE FROM bookmark_tags
+             WHERE bookmark_id NOT IN (SELECT bookmark_id FROM bookmark_sources);
+         DELETE FROM bookmarks WHERE id NOT IN (SELECT bookmark_id FROM bookmark_sources);",
+    )?;
+    Ok((count, trashed))
+}
+
+/// Upsert the canonical rows and this profile's sources for `bookmarks`
+fn upsert_bookmarks(
+    tx: &Transaction,
+    data: &BrowserData,
+    bookmarks: &[Bookmark],
+    now: &str,
+) -> Result<usize> {
+    let mut count = 0;
+    for bookmark in bookmarks {
+        let Some(url) = bookmark.url.as_deref().filter(|u| !u.is_empty()) else {
+            continue;
+        };
+        // The latest sync wins for title and folder; the earliest date added is kept
+        let id: i64 = tx.query_row(
+            "INSERT INTO bookmarks
+                 (url, title, folder, date_added, keyword, description, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (url) DO UPDATE SET
+                 title = excluded.title,
+                 folder = excluded.folder,
+                 date_added = MIN(COALESCE(date_added, excluded.date_added),
+                                  COALESCE(excluded.date_added, date_added)),
+                 keyword = COALESCE(excluded.keyword, keyword),
+                 description = COALESCE(excluded.description, description),
+                 updated_at = excluded.updated_at
+             RETURNING id",
+            params![
+                url,
+                bookmark.title,
+                bookmark.folder,
+                bookmark.date_added.map(|d| d.to_rfc3339()),
+                bookmark.keyword,
+                bookmark.description,
+                now
+            ],
+            |row| row.get(0),
+        )?;
+        for tag in bookmark.tags.iter().flatten() {
+            tx.execute(
+                "INSERT OR IGNORE INTO bookmark_tags (bookmark_id, tag) VALUES (?1, ?2)",
+                params![id, tag],
+            )?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO bookmark_sources
+                 (bookmark_id, browser, profile, source_id, title, folder, synced_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                id,
+                data.browser,
+                data.profile,
+                bookmark.id,
+                bookmark.title,
+                bookmark.folder,
+                now
+            ],
+        )?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+fn replace_history(tx: &Transaction, data: &BrowserData, urls: &[UrlEntry]) -> Result<usize> {
+    tx.execute(
+        "DELETE FROM history WHERE browser = ?1 AND profile = ?2",
+        params![data.browser, data.profile],
+    )?;
+    upsert_history(tx, data, urls)
+}
+
+fn upsert_history<'a>(
+    tx: &Transaction,
+    data: &BrowserData,
+    urls: impl IntoIterator<Item = &'a UrlEntry>,
+) -> Result<usize> {
+    let mut stmt = tx.prepare(
+        "INSERT OR REPLACE INTO history
+             (url, browser, profile, title, visit_count, last_visit)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    let mut count = 0;
+    for entry in urls {
+        stmt.execute(params![
+            entry.url,
+            data.browser,
+            data.profile,
+            entry.title,
+            entry.visit_count,
+            entry.last_visit.map(|d| d.to_rfc3339())
+        ])?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn parse_date(value: Option<String>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+        .map(|d| d.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::HistoryEntry;
+    use tempfile::tempdir;
+
+    fn bookmark_at(id: &str, url: &str) -> Bookmark {
+        Bookmark {
+            id: id.to_string(),
+            title: id.to_string(),
+            url: Some(url.to_string()),
+            folder: None,
+            date_added: None,
+            ..Default::default()
+        }
+    }
+
+    fn profile(browser: &str, bookmarks: Vec<(&str, &str, &str)>) -> BrowserData {
+        BrowserData {
+            browser: browser.to_string(),
+            profile: "Default".to_string(),
+            export_date: Utc::now(),
+            bookmarks: Some(
+                bookmarks
+                    .into_iter()
+                    .map(|(id, title, url)| Bookmark {
+                        id: id.to_string(),
+                        title: title.to_string(),
+                        url: Some(url.to_string()),
+                        folder: Some("Dev".to_string()),
+                        date_added: DateTime::from_timestamp(1_700_000_000, 0),
+                        tags: Some(vec![browser.to_string()]),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+            history: Some(HistoryEntry {
+                urls: vec![UrlEntry {
+                    url: "https://github.com/".to_string(),
+                    title: "GitHub".to_string(),
+                    visit_count: 3,
+                    last_visit: DateTime::from_timestamp(1_700_000_000, 0),
+                }],
+            }),
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        }
+    }
+
+    #[test]
+    fn test_ingest_merges_profiles_by_url() {
+        let dir = tempdir().unwrap();
+        let mut store = Store::open(&dir.path().join("store.db")).unwrap();
+        assert!(store.last_synced().unwrap().is_none());
+
+        store
+            .ingest(&profile(
+                "chrome",
+                vec![
+                    ("1", "GitHub", "https://github.com/"),
+                    ("2", "Rust", "https://www.rust-lang.org/"),
+                ],
+            ))
+            .unwrap();
+        store
+            .ingest(&profile(
+                "firefox",
+                vec![("10", "GitHub (Firefox)", "https://github.com/")],
+            ))
+            .unwrap();
+
+        let bookmarks = store.bookmarks_with_browsers().unwrap();
+        assert_eq!(bookmarks.len(), 2);
+        let (github, browsers) = &bookmarks[0];
+        assert_eq!(github.title, "GitHub (Firefox)");
+        assert_eq!(browsers, &["chrome", "firefox"]);
+        assert_eq!(
+            github.tags,
+            Some(vec!["chrome".to_string(), "firefox".to_string()])
+        );
+        assert_eq!(
+            github.date_added,
+            DateTime::from_timestamp(1_700_000_000, 0)
+        );
+
+        let history = store.history().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].visit_count, 6);
+
+        // Re-syncing chrome without Rust drops it; GitHub survives via Firefox
+        store
+            .ingest(&profile(
+                "chrome",
+                vec![("1", "GitHub", "https://github.com/")],
+            ))
+            .unwrap();
+        store.ingest(&profile("firefox", vec![])).unwrap();
+        let urls: Vec<String> = store
+            .bookmarks()
+            .unwrap()
+            .into_iter()
+            .filter_map(|b| b.url)
+            .collect();
+        assert_eq!(urls, ["https://github.com/"]);
+        assert_eq!(
+            store.stats().unwrap(),
+            StoreStats {
+                bookmarks: 1,
+                history: 1,
+                profiles: 2
+            }
+        );
+        assert!(store.last_synced().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_deleted_bookmarks_go_to_trash() {
+        let dir = tempdir().unwrap();
+        let mut store = Store::open(&dir.path().join("store.db")).unwrap();
+        store
+            .ingest(&profile(
+                "chrome",
+                vec![
+                    ("1", "GitHub", "https://github.com/"),
+                    ("2", "Rust", "https://www.rust-lang.org/"),
+                ],
+            ))
+            .unwrap();
+        let synced = store
+            .ingest(&profile(
+                "chrome",
+                vec![("1", "GitHub", "https://github.com/")],
+            ))
+            .unwrap();
+        assert_eq!(synced.trashed, 1);
+        store
+            .trash_bookmarks(&[bookmark_at("9", "https://old.example.com/")], "prune")
+            .unwrap();
+
+        let trash = store.trash().unwrap();
+        let reasons: Vec<&str> = trash.iter().map(|e| e.reason.as_str()).collect();
+        assert_eq!(reasons, ["prune", "sync"]);
+        let rust = &trash[1].bookmark;
+        assert_eq!(rust.title, "Rust");
+        assert_eq!(rust.folder.as_deref(), Some("Dev"));
+        assert_eq!(rust.tags, Some(vec!["chrome".to_string()]));
+
+        assert!(store.restore_from_trash(&[trash[1].id, 999]).is_err());
+        let restored = store.restore_from_trash(&[trash[1].id]).unwrap();
+        assert_eq!(
+            restored[0].url.as_deref(),
+            Some("https://www.rust-lang.org/")
+        );
+        assert_eq!(store.trash().unwrap().len(), 1);
+
+        assert_eq!(
+            store
+                .empty_trash(Some(Utc::now() - chrono::Duration::days(1)))
+                .unwrap(),
+            0
+        );
+        assert_eq!(store.empty_trash(None).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_journal_keeps_recent_runs() {
+        let dir = tempdir().unwrap();
+        let mut store = Store::open(&dir.path().join("store.db")).unwrap();
+        let entry = |n: usize| JournalEntry {
+            id: 0,
+            operation: "process".to_string(),
+            at: Utc::now(),
+            output: dir.path().join("out.yaml"),
+            previous: None,
+            written: format!("run {}", n),
+            input: vec![bookmark_at("a", "https://a.example/")],
+            settings: BTreeMap::from([("stages".to_string(), "dedupe".to_string())]),
+            undone_at: None,
+        };
+        for n in 0..JOURNAL_KEEP + 2 {
+            store.record_journal(&entry(n)).unwrap();
+        }
+
+        let journal = store.journal().unwrap();
+        assert_eq!(journal.len(), JOURNAL_KEEP);
+        assert_eq!(journal[0].written, format!("run {}", JOURNAL_KEEP + 1));
+        assert_eq!(journal[0].input[0].title, "a");
+        assert_eq!(journal[0].settings["stages"], "dedupe");
+        store.mark_undone(journal[0].id).unwrap();
+        assert!(store.journal().unwrap()[0].undone_at.is_some());
+    }
+
+    #[test]
+    fn test_snapshots_are_kept_once() {
+        let dir = tempdir().unwrap();
+        let mut store = Store::open(&dir.path().join("store.db")).unwrap();
+        let bookmarks = vec![bookmark_at("a", "https://a.example/")];
+        let snapshot = Snapshot::new(&bookmarks, Some("first".to_string()));
+
+        assert!(store.save_snapshot(&snapshot).unwrap().is_none());
+        let again = store
+            .save_snapshot(&Snapshot::new(&bookmarks, None))
+            .unwrap()
+            .unwrap();
+        assert_eq!(again.label.as_deref(), Some("first"));
+        assert_eq!(store.snapshots().unwrap().len(), 1);
+
+        let loaded = store.snapshot(&snapshot.id[..4]).unwrap();
+        assert_eq!(loaded.bookmarks.len(), 1);
+        assert_eq!(loaded.bookmarks[0].title, "a");
+        assert!(store.snapshot("zzz").is_err());
+    }
+
+    #[test]
+    fn test_bookmarks_as_of() {
+        let dir = tempdir().unwrap();
+        let mut store = Store::open(&dir.path().join("store.db")).unwrap();
+        store
+            .ingest(&profile(
+                "chrome",
+                vec![
+                    ("1", "GitHub", "https://github.com/"),
+                    ("2", "Rust", "https://www.rust-lang.org/"),
+                ],
+            ))
+            .unwrap();
+        store
+            .ingest(&profile(
+                "chrome",
+                vec![("1", "GitHub", "https://github.com/")],
+            ))
+            .unwrap();
+        let urls = |at: DateTime<Utc>| -> Vec<String> {
+            let mut urls: Vec<String> = store
+                .bookmarks_as_of(at)
+                .unwrap()
+                .into_iter()
+                .filter_map(|(bookmark, _)| bookmark.url)
+                .collect();
+            urls.sort();
+            urls
+        };
+
+        let added = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        assert!(urls(added).is_empty());
+        let yesterday = Utc::now() - chrono::Duration::days(1);
+        assert_eq!(
+            urls(yesterday),
+            ["https://github.com/", "https://www.rust-lang.org/"]
+        );
+        assert_eq!(
+            urls(Utc::now() + chrono::Duration::seconds(1)),
+            ["https://github.com/"]
+        );
+        assert!(store.history_as_of(added).unwrap().is_empty());
+        assert_eq!(store.history_as_of(yesterday).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_import_skips_known_urls() {
+        let dir = tempdir().unwrap();
+        let mut store = Store::open(&dir.path().join("store.db")).unwrap();
+        store
+            .ingest(&profile(
+                "chrome",
+                vec![("1", "GitHub", "https://github.com/")],
+            ))
+            .unwrap();
+        let key = |url: &str| url.trim_end_matches('/').replace("://www.", "://");
+        let dump = || {
+            let mut data = profile(
+                "onetab",
+                vec![
+                    ("a", "GitHub home", "https://www.github.com"),
+                    ("b", "Rust", "https://www.rust-lang.org/"),
+                    ("c", "Rust again", "https://rust-lang.org"),
+                ],
+            );
+            data.profile = "onetab.txt".to_string();
+            data
+        };
+
+        let first = store.import(dump(), key, false).unwrap();
+        assert_eq!(
+            first,
+            ImportSummary {
+                added: 1,
+                updated: 0,
+                skipped: 2
+            }
+        );
+        let again = store.import(dump(), key, false).unwrap();
+        assert_eq!((again.added, again.skipped), (0, 3));
+        assert_eq!(store.stats().unwrap().bookmarks, 2);
+
+        let updated = store.import(dump(), key, true).unwrap();
+        assert_eq!((updated.added, updated.updated), (0, 2));
+        let github = store
+            .bookmarks()
+            .unwrap()
+            .into_iter()
+            .find(|b| b.url.as_deref() == Some("https://github.com/"))
+            .unwrap();
+        assert_eq!(github.title, "GitHub home");
+        assert_eq!(store.stats().unwrap().bookmarks, 2);
+    }
+
+    #[test]
+    fn test_import_alone_leaves_store_unsynced() {
+        let dir = tempdir().unwrap();
+        let mut store = Store::open(&dir.path().join("store.db")).unwrap();
+        let data = profile("onetab", vec![("a", "Rust", "https://rust-lang.org/")]);
+        let summary = store.import(data, |url| url.to_string(), false).unwrap();
+
+        assert_eq!(summary.added, 1);
+        assert_eq!(store.bookmarks().unwrap().len(), 1);
+        assert_eq!(store.last_synced().unwrap(), None);
+        assert_eq!(store.stats().unwrap().profiles, 0);
+    }
+
+    #[test]
+    fn test_update_bookmarks() {
+        let dir = tempdir().unwrap();
+        let mut store = Store::open(&dir.path().join("store.db")).unwrap();
+        store
+            .ingest(&profile(
+                "chrome",
+                vec![("1", "GitHub", "https://github.com/")],
+            ))
+            .unwrap();
+
+        let mut github = store.bookmarks().unwrap().remove(0);
+        github.folder = Some("Reading/Code".to_string());
+        github.tags = Some(vec!["code".to_string()]);
+        assert_eq!(store.update_bookmarks(&[github]).unwrap(), 1);
+
+        let github = store.bookmarks().unwrap().remove(0);
+        assert_eq!(github.folder.as_deref(), Some("Reading/Code"));
+        assert_eq!(github.tags, Some(vec!["code".to_string()]));
+        assert!(
+            store
+                .update_bookmarks(&[bookmark_at("x", "https://x/")])
+                .is_err()
+        );
+
+        assert_eq!(store.delete_bookmarks(&[github], "tui").unwrap(), 1);
+        assert!(store.bookmarks().unwrap().is_empty());
+        assert_eq!(store.trash().unwrap()[0].reason, "tui");
+    }
+
+    #[test]
+    fn test_incremental_ingest_only_adds_newer_rows() {
+        let dir = tempdir().unwrap();
+        let mut store = Store::open(&dir.path().join("store.db")).unwrap();
+        store
+            .ingest(&profile(
+                "chrome",
+                vec![("1", "GitHub", "https://github.com/")],
+            ))
+            .unwrap();
+        let marks = store.high_water_marks("chrome", "Default").unwrap();
+        assert_eq!(marks.bookmarks, DateTime::from_timestamp(1_700_000_000, 0));
+        assert_eq!(marks.history, DateTime::from_timestamp(1_700_000_000, 0));
+
+        // A bookmark dated before the mark is skipped, a newer one is added, and
+        // GitHub stays even though this read no longer has it
+        let mut data = profile("chrome", vec![("2", "Old", "https://old.example/")]);
+        let bookmarks = data.bookmarks.as_mut().unwrap();
+        bookmarks[0].date_added = DateTime::from_timestamp(1_600_000_000, 0);
+        bookmarks.push(Bookmark {
+            date_added: DateTime::from_timestamp(1_800_000_000, 0),
+            ..bookmark_at("3", "https://new.example/")
+        });
+        let history = &mut data.history.as_mut().unwrap().urls;
+        history[0].visit_count = 4;
+        history[0].last_visit = DateTime::from_timestamp(1_800_000_000, 0);
+
+        let synced = store.ingest_since(&data, &marks).unwrap();
+        assert!(synced.incremental);
+        assert_eq!(synced.bookmarks, Some(1));
+        assert_eq!(synced.history, Some(1));
+
+        let urls: Vec<String> = store
+            .bookmarks()
+            .unwrap()
+            .into_iter()
+            .filter_map(|b| b.url)
+            .collect();
+        assert_eq!(urls, ["https://github.com/", "https://new.example/"]);
+        assert_eq!(store.history().unwrap()[0].visit_count, 4);
+        assert_eq!(
+            store.high_water_marks("chrome", "Default").unwrap(),
+            HighWaterMarks {
+                bookmarks: DateTime::from_timestamp(1_800_000_000, 0),
+                history: DateTime::from_timestamp(1_800_000_000, 0),
+            }
+        );
+    }
+}
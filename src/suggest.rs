@@ -0,0 +1,235 @@
+//! "Did you mean" suggestions for searches that find nothing.
+//!
+//! The vocabulary is the words of bookmark titles and tags, and the sites
+//! bookmarked. Each query word the vocabulary lacks is swapped for the
+//! closest words it has, by edit distance (or a word it starts), the more
+//! common first; a one-word query is also compared with each site's name
+//! and host.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use url::Url;
+
+use crate::exporter::Bookmark;
+
+/// Suggestions of each kind offered at most
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Alternatives to a query that found nothing
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct Suggestions {
+    /// Queries with misspelt words replaced
+    pub terms: Vec<String>,
+    /// Bookmarked sites resembling the query
+    pub domains: Vec<String>,
+}
+
+impl Suggestions {
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.domains.is_empty()
+    }
+
+    /// A line for each kind of suggestion, or `None` without any
+    pub fn to_text(&self) -> Option<String> {
+        let quoted = |items: &[String]| {
+            items
+                .iter()
+                .map(|item| format!("'{}'", item))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut lines = Vec::new();
+        if !self.terms.is_empty() {
+            lines.push(format!("Did you mean {}?", quoted(&self.terms)));
+        }
+        if !self.domains.is_empty() {
+            lines.push(format!(
+                "Bookmarked sites like it: {}",
+                quoted(&self.domains)
+            ));
+        }
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+}
+
+/// Suggest queries close to `query` from the words and sites of `bookmarks`
+pub fn suggest<'a>(query: &str, bookmarks: impl IntoIterator<Item = &'a Bookmark>) -> Suggestions {
+    let mut words: HashMap<String, usize> = HashMap::new();
+    let mut domains: HashMap<String, usize> = HashMap::new();
+    for bookmark in bookmarks {
+        let tags = bookmark.tags.iter().flatten().map(String::as_str);
+        for word in std::iter::once(bookmark.title.as_str())
+            .chain(tags)
+            .flat_map(split_words)
+        {
+            *words.entry(word).or_default() += 1;
+        }
+        if let Some(host) = bookmark
+            .url
+            .as_deref()
+            .and_then(|u| Url::parse(u).ok())
+            .and_then(|u| {
+                u.host_str()
+                    .map(|h| h.trim_start_matches("www.").to_lowercase())
+            })
+        {
+            *domains.entry(host).or_default() += 1;
+        }
+    }
+
+    let query_words = split_words(query);
+    let candidates: Vec<Vec<String>> = query_words
+        .iter()
+        .map(|word| match words.contains_key(word) {
+            true => Vec::new(),
+            false => closest(word, &words, |candidate| vec![candidate]),
+        })
+        .collect();
+    let mut terms = Vec::new();
+    for rank in 0..MAX_SUGGESTIONS {
+        if candidates.iter().all(|c| c.len() <= rank) {
+            break;
+        }
+        let term = query_words
+            .iter()
+            .zip(&candidates)
+            .map(|(word, c)| c.get(rank).or(c.first()).unwrap_or(word).as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+
+    let domains = match query_words.as_slice() {
+        [_] => {
+            let query = query.trim().to_lowercase();
+            closest(&query, &domains, |host| {
+                // The whole host, and its labels but the top-level one
+                let labels: Vec<&str> = host.split('.').collect();
+                std::iter::once(host)
+                    .chain(labels[..labels.len().saturating_sub(1)].iter().copied())
+                    .collect()
+            })
+        }
+        _ => Vec::new(),
+    };
+    Suggestions { terms, domains }
+}
+
+/// Lowercase words of three letters or more
+fn split_words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The entries of `vocabulary` within reach of `word`, nearest and then most
+/// common first; `forms` gives the strings of an entry to compare against
+fn closest<'a>(
+    word: &str,
+    vocabulary: &'a HashMap<String, usize>,
+    forms: impl Fn(&'a str) -> Vec<&'a str>,
+) -> Vec<String> {
+    let reach = match word.chars().count() {
+        0..=2 => return Vec::new(),
+        3..=4 => 1,
+        5..=8 => 2,
+        _ => 3,
+    };
+    let mut found: Vec<(usize, Reverse<usize>, &str)> = vocabulary
+        .iter()
+        .filter_map(|(entry, count)| {
+            let distance = forms(entry)
+                .into_iter()
+                .map(|form| match form.starts_with(word) && form != word {
+                    true => 1,
+                    false => edit_distance(word, form),
+                })
+                .min()?;
+            (distance <= reach).then_some((distance, Reverse(*count), entry.as_str()))
+        })
+        .collect();
+    found.sort();
+    found
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, _, entry)| entry.to_string())
+        .collect()
+}
+
+/// Edits (insert, delete, substitute or swap two neighbours) turning `a`
+/// into `b`, counted over characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(title: &str, url: &str, tags: &[&str]) -> Bookmark {
+        Bookmark {
+            id: title.to_string(),
+            title: title.to_string(),
+            url: Some(url.to_string()),
+            folder: None,
+            date_added: None,
+            tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let bookmarks = vec![
+            bookmark(
+                "Rust async book",
+                "https://rust-lang.github.io/async-book/",
+                &[],
+            ),
+            bookmark(
+                "Rust by example",
+                "https://doc.rust-lang.org/",
+                &["programming"],
+            ),
+            bookmark("Trust and safety", "https://www.github.com/trust", &[]),
+            bookmark("Issues", "https://github.com/rust-lang/rust/issues", &[]),
+        ];
+
+        let typo = suggest("rsut", &bookmarks);
+        assert_eq!(typo.terms[0], "rust");
+        let phrase = suggest("asynk programing", &bookmarks);
+        assert_eq!(phrase.terms[0], "async programming");
+
+        // github.com is bookmarked twice, once with www.
+        let site = suggest("githb", &bookmarks);
+        assert_eq!(site.domains, ["github.com", "rust-lang.github.io"]);
+        assert_eq!(
+            site.to_text().unwrap(),
+            "Bookmarked sites like it: 'github.com', 'rust-lang.github.io'"
+        );
+        assert!(suggest("zzzzzz", &bookmarks).is_empty());
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("rsut", "rust"), 1);
+    }
+}
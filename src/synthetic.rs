@@ -0,0 +1,297 @@
+//! Synthetic bookmark collections for benchmarking and trying out settings.
+//!
+//! Domains follow a Zipf (power-law) popularity distribution, most domains
+//! have a "home" folder in a generated folder tree, and a share of bookmarks
+//! re-save an earlier URL with the usual cosmetic differences (`www.`,
+//! trailing slash, fragment) so deduplication has real work to do. Output is
+//! fully determined by the seed.
+
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+
+use crate::exporter::{Bookmark, BrowserData, HistoryEntry, Provenance, UrlEntry};
+
+const WORDS: &[&str] = &[
+    "alpha", "atlas", "beacon", "bright", "cedar", "cloud", "code", "craft", "data", "delta",
+    "echo", "ember", "field", "forge", "garden", "grid", "harbor", "hub", "iron", "jade", "kite",
+    "lab", "lantern", "logic", "maple", "matrix", "nest", "nova", "orbit", "pixel", "prism",
+    "quartz", "quest", "river", "rocket", "sage", "signal", "solar", "spark", "stack", "summit",
+    "terra", "thread", "tide", "unity", "vector", "wave", "willow", "zen", "zone",
+];
+const TLDS: &[&str] = &["com", "org", "net", "io", "dev", "co.uk", "de"];
+const ROOT_FOLDERS: &[&str] = &["Bookmarks bar", "Other bookmarks", "Reading"];
+
+/// Zipf exponent for domain popularity; ~1 matches observed browsing data
+const ZIPF_EXPONENT: f64 = 1.1;
+
+#[derive(Debug, Clone)]
+pub struct SyntheticConfig {
+    /// Number of bookmarks to generate
+    pub count: usize,
+    /// Number of distinct domains
+    pub domains: usize,
+    /// Fraction of bookmarks that re-save an earlier URL (0.0-1.0)
+    pub duplicate_ratio: f64,
+    /// Also generate a history section with power-law visit counts
+    pub history: bool,
+    pub seed: u64,
+}
+
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        Self {
+            count: 10_000,
+            domains: 500,
+            duplicate_ratio: 0.05,
+            history: false,
+            seed: 42,
+        }
+    }
+}
+
+/// SplitMix64: tiny, fast and good enough for test data
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn word(&mut self) -> &'static str {
+        WORDS[self.below(WORDS.len())]
+    }
+}
+
+/// Cumulative Zipf weights over `n` ranks, sampled by binary search
+struct Zipf {
+    cumulative: Vec<f64>,
+}
+
+impl Zipf {
+    fn new(n: usize) -> Self {
+        let mut total = 0.0;
+        let cumulative = (1..=n.max(1))
+            .map(|rank| {
+                total += 1.0 / (rank as f64).powf(ZIPF_EXPONENT);
+                total
+            })
+            .collect();
+        Self { cumulative }
+    }
+
+    fn sample(&self, rng: &mut Rng) -> usize {
+        let target = rng.unit() * self.cumulative.last().copied().unwrap_or(0.0);
+        self.cumulative
+            .partition_point(|&c| c < target)
+            .min(self.cumulative.len() - 1)
+    }
+}
+
+/// Generate a synthetic export document
+pub fn generate(config: &SyntheticConfig) -> BrowserData {
+    let mut rng = Rng(config.seed);
+    let domains: Vec<String> = (0..config.domains.max(1))
+        .map(|i| {
+            format!(
+                "{}{}{}.{}",
+                rng.word(),
+                rng.word(),
+                i,
+                TLDS[rng.below(TLDS.len())]
+            )
+        })
+        .collect();
+    let folders = folder_tree(&mut rng, config.count);
+    // Most domains live in one folder; the rest get scattered
+    let home_folders: Vec<Option<usize>> = domains
+        .iter()
+        .map(|_| (rng.unit() < 0.7).then(|| rng.below(folders.len())))
+        .collect();
+    let zipf = Zipf::new(domains.len());
+    let end: DateTime<Utc> = DateTime::from_timestamp(1_735_689_600, 0).unwrap_or_default();
+
+    let mut bookmarks: Vec<Bookmark> = Vec::with_capacity(config.count);
+    for index in 0..config.count {
+        let date_added = Some(end - Duration::minutes(rng.below(5 * 365 * 24 * 60) as i64));
+
+        if !bookmarks.is_empty() && rng.unit() < config.duplicate_ratio {
+            let original = bookmarks[rng.below(bookmarks.len())].clone();
+            let url = original.url.as_deref().unwrap_or_default();
+            let url = match rng.below(3) {
+                0 => url.replacen("https://", "https://www.", 1),
+                1 => format!("{}/", url.trim_end_matches('/')),
+                _ => format!("{}#{}", url, rng.word()),
+            };
+            bookmarks.push(Bookmark {
+                id: (index + 1).to_string(),
+                url: Some(url),
+                date_added,
+                ..original
+            });
+            continue;
+        }
+
+        let domain = zipf.sample(&mut rng);
+        let folder = home_folders[domain]
+            .filter(|_| rng.unit() < 0.8)
+            .unwrap_or_else(|| rng.below(folders.len()));
+        let depth = 1 + rng.below(3);
+        let path: Vec<&str> = (0..depth).map(|_| rng.word()).collect();
+        let title_words: Vec<String> = (0..2 + rng.below(4))
+            .map(|_| capitalize(rng.word()))
+            .collect();
+        bookmarks.push(Bookmark {
+            id: (index + 1).to_string(),
+            title: format!("{} - {}", title_words.join(" "), domains[domain]),
+            url: Some(format!(
+                "https://{}/{}?p={}",
+                domains[domain],
+                path.join("/"),
+                index
+            )),
+            folder: Some(folders[folder].clone()),
+            date_added,
+            ..Default::default()
+        });
+    }
+
+    let history = config.history.then(|| {
+        let mut urls = Vec::new();
+        for bookmark in &bookmarks {
+            if rng.unit() >= 0.6 {
+                continue;
+            }
+            urls.push(UrlEntry {
+                url: bookmark.url.clone().unwrap_or_default(),
+                title: bookmark.title.clone(),
+                // Pareto-like tail: most pages visited a few times, some hundreds
+                visit_count: (1.0 / (1.0 - rng.unit()).max(1e-3)).powf(1.2).round() as i64,
+                last_visit: bookmark
+                    .date_added
+                    .map(|d| d + Duration::days(rng.below(365) as i64)),
+            });
+        }
+        HistoryEntry { urls }
+    });
+
+    let mut data = BrowserData {
+        browser: "synthetic".to_string(),
+        profile: format!("seed-{}", config.seed),
+        export_date: end,
+        bookmarks: Some(bookmarks),
+        history,
+        passwords: None,
+        aliases: None,
+        profile_name: None,
+        profile_email: None,
+        provenance: None,
+    };
+    let settings = BTreeMap::from([
+        ("count".to_string(), config.count.to_string()),
+        ("domains".to_string(), config.domains.to_string()),
+        (
+            "duplicate_ratio".to_string(),
+            config.duplicate_ratio.to_string(),
+        ),
+        ("history".to_string(), config.history.to_string()),
+        ("seed".to_string(), config.seed.to_string()),
+    ]);
+    data.provenance = Some(Provenance::new("generate", settings).describe(&data));
+    data
+}
+
+/// Folder paths, up to three levels deep under the usual browser roots,
+/// roughly one folder per 50 bookmarks
+fn folder_tree(rng: &mut Rng, count: usize) -> Vec<String> {
+    let mut folders: Vec<String> = ROOT_FOLDERS.iter().map(|r| r.to_string()).collect();
+    for _ in 0..(count / 50).max(1) {
+        let parent = folders[rng.below(folders.len())].clone();
+        if parent.matches('/').count() >= 3 {
+            continue;
+        }
+        let child = format!("{}/{}", parent, capitalize(rng.word()));
+        if !folders.contains(&child) {
+            folders.push(child);
+        }
+    }
+    folders
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_generate_is_deterministic_and_shaped() {
+        let config = SyntheticConfig {
+            count: 2000,
+            domains: 100,
+            duplicate_ratio: 0.1,
+            history: true,
+            seed: 7,
+        };
+        let data = generate(&config);
+        let again = generate(&config);
+        let bookmarks = data.bookmarks.as_ref().unwrap();
+        assert_eq!(bookmarks.len(), 2000);
+        assert_eq!(
+            bookmarks.iter().map(|b| &b.url).collect::<Vec<_>>(),
+            again
+                .bookmarks
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|b| &b.url)
+                .collect::<Vec<_>>()
+        );
+
+        // Power law: the most popular domain dwarfs the median one
+        let mut per_domain: HashMap<String, usize> = HashMap::new();
+        for b in bookmarks {
+            let url = url::Url::parse(b.url.as_deref().unwrap()).unwrap();
+            let host = url
+                .host_str()
+                .unwrap()
+                .trim_start_matches("www.")
+                .to_string();
+            *per_domain.entry(host).or_default() += 1;
+        }
+        let mut counts: Vec<usize> = per_domain.into_values().collect();
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        assert!(counts[0] > 10 * counts[counts.len() / 2]);
+
+        // Roughly the requested share are near-duplicates of an earlier URL
+        let titles: HashSet<&str> = bookmarks.iter().map(|b| b.title.as_str()).collect();
+        let duplicates = bookmarks.len() - titles.len();
+        assert!(
+            (100..400).contains(&duplicates),
+            "{} duplicates",
+            duplicates
+        );
+
+        assert!(bookmarks.iter().all(|b| b.folder.is_some()));
+        assert!(!data.history.as_ref().unwrap().urls.is_empty());
+        assert_eq!(data.provenance.unwrap().counts.bookmarks, 2000);
+    }
+}
@@ -0,0 +1,211 @@
+//! The folder hierarchy of a collection, with bookmark counts.
+//!
+//! [`FolderTree::build`] reads folders from both flat `folder` paths and
+//! nested `children`, so it works on store contents and on any export.
+//! Folders with no bookmarks at all still show up when the input is nested.
+//! [`FolderTree::render`] draws it like the `tree` command does.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::exporter::{Bookmark, FOLDER_PATH_SEPARATOR};
+
+/// One folder and everything below it
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct FolderTree {
+    pub name: String,
+    /// Full path from the top, empty for the root
+    pub path: String,
+    /// Bookmarks filed directly in this folder
+    pub bookmarks: usize,
+    /// Bookmarks here and in every subfolder
+    pub total: usize,
+    /// Subfolders, sorted by name without regard to case
+    pub children: Vec<FolderTree>,
+}
+
+/// Line prefixes for each level: (branch, last branch, continuation, blank)
+const UNICODE_GUIDES: [&str; 4] = ["├── ", "└── ", "│   ", "    "];
+const ASCII_GUIDES: [&str; 4] = ["|-- ", "`-- ", "|   ", "    "];
+
+#[derive(Default)]
+struct Builder {
+    bookmarks: usize,
+    children: BTreeMap<String, Builder>,
+}
+
+impl Builder {
+    /// The folder at `path`, created along with its parents as needed
+    fn folder(&mut self, path: &str) -> &mut Builder {
+        path.split(FOLDER_PATH_SEPARATOR)
+            .filter(|s| !s.is_empty())
+            .fold(self, |node, segment| {
+                node.children.entry(segment.to_string()).or_default()
+            })
+    }
+
+    fn walk(&mut self, nodes: &[Bookmark], parent: Option<&str>) {
+        for node in nodes {
+            let folder = parent.map(str::to_string).or_else(|| node.folder.clone());
+            if node.is_folder() {
+                let path = match &folder {
+                    Some(folder) => format!("{}{}{}", folder, FOLDER_PATH_SEPARATOR, node.title),
+                    None => node.title.clone(),
+                };
+                self.folder(&path);
+                self.walk(node.children.as_deref().unwrap_or_default(), Some(&path));
+            } else {
+                self.folder(folder.as_deref().unwrap_or_default()).bookmarks += 1;
+            }
+        }
+    }
+
+    fn finish(self, name: String, path: String) -> FolderTree {
+        let mut children: Vec<FolderTree> = self
+            .children
+            .into_iter()
+            .map(|(name, child)| {
+                let path = if path.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}{}{}", path, FOLDER_PATH_SEPARATOR, name)
+                };
+                child.finish(name, path)
+            })
+            .collect();
+        children.sort_by_cached_key(|c| c.name.to_lowercase());
+        FolderTree {
+            total: self.bookmarks + children.iter().map(|c| c.total).sum::<usize>(),
+            name,
+            path,
+            bookmarks: self.bookmarks,
+            children,
+        }
+    }
+}
+
+impl FolderTree {
+    /// The folders of `bookmarks`, under a root holding the unfiled ones
+    pub fn build(bookmarks: &[Bookmark]) -> Self {
+        let mut root = Builder::default();
+        root.walk(bookmarks, None);
+        root.finish(String::new(), String::new())
+    }
+
+    /// The folder at `path`, matching each segment without regard to case;
+    /// an empty path is the root
+    pub fn find(&self, path: &str) -> Option<&FolderTree> {
+        path.split(FOLDER_PATH_SEPARATOR)
+            .filter(|s| !s.is_empty())
+            .try_fold(self, |node, segment| {
+                node.children
+                    .iter()
+                    .find(|c| c.name.eq_ignore_ascii_case(segment))
+            })
+    }
+
+    /// Draw the tree with a `name (count)` line per folder, the count
+    /// including subfolders. `max_depth` limits how many levels below this
+    /// folder are shown; `ascii` swaps the box-drawing guides for `|--`.
+    pub fn render(&self, max_depth: Option<usize>, ascii: bool) -> String {
+        let guides = if ascii { ASCII_GUIDES } else { UNICODE_GUIDES };
+        let name = if self.path.is_empty() {
+            "All bookmarks"
+        } else {
+            &self.path
+        };
+        let mut out = format!("{} ({})\n", name, self.total);
+        self.render_children(&mut out, "", 1, max_depth, &guides);
+        out
+    }
+
+    fn render_children(
+        &self,
+        out: &mut String,
+        prefix: &str,
+        depth: usize,
+        max_depth: Option<usize>,
+        guides: &[&str; 4],
+    ) {
+        if max_depth.is_some_and(|max| depth > max) {
+            return;
+        }
+        for (i, child) in self.children.iter().enumerate() {
+            let last = i + 1 == self.children.len();
+            let _ = writeln!(
+                out,
+                "{}{}{} ({})",
+                prefix,
+                guides[if last { 1 } else { 0 }],
+                child.name,
+                child.total
+            );
+            let prefix = format!("{}{}", prefix, guides[if last { 3 } else { 2 }]);
+            child.render_children(out, &prefix, depth + 1, max_depth, guides);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(title: &str, url: Option<&str>, folder: Option<&str>) -> Bookmark {
+        Bookmark {
+            id: title.to_string(),
+            title: title.to_string(),
+            url: url.map(str::to_string),
+            folder: folder.map(str::to_string),
+            date_added: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tree_from_paths_and_children() {
+        let mut empty = bookmark("Empty", None, None);
+        empty.children = Some(Vec::new());
+        let mut news = bookmark("news", None, Some("bookmark_bar"));
+        news.children = Some(vec![
+            bookmark("BBC", Some("https://bbc.co.uk"), None),
+            empty,
+        ]);
+        let bookmarks = vec![
+            bookmark(
+                "Tokio",
+                Some("https://tokio.rs"),
+                Some("bookmark_bar/Dev/Rust"),
+            ),
+            bookmark(
+                "Serde",
+                Some("https://serde.rs"),
+                Some("bookmark_bar/Dev/Rust"),
+            ),
+            bookmark("MDN", Some("https://mdn.dev"), Some("bookmark_bar/Dev")),
+            bookmark("Loose", Some("https://example.com"), None),
+            news,
+        ];
+        let tree = FolderTree::build(&bookmarks);
+
+        assert_eq!(tree.total, 5);
+        assert_eq!(tree.bookmarks, 1);
+        assert_eq!(
+            tree.render(None, false),
+            "All bookmarks (5)\n\
+             └── bookmark_bar (4)\n    \
+                 ├── Dev (3)\n    \
+                 │   └── Rust (2)\n    \
+                 └── news (1)\n        \
+                     └── Empty (0)\n"
+        );
+
+        let dev = tree.find("BOOKMARK_BAR/dev").unwrap();
+        assert_eq!((dev.bookmarks, dev.total), (1, 3));
+        assert_eq!(
+            dev.render(Some(1), true),
+            "bookmark_bar/Dev (3)\n`-- Rust (2)\n"
+        );
+        assert_eq!(tree.render(Some(1), true).lines().count(), 2);
+        assert!(tree.find("bookmark_bar/Missing").is_none());
+    }
+}
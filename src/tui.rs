@@ -0,0 +1,567 @@
+//! Interactive terminal browser over the bookmark store.
+//!
+//! `bookmark tui` shows three panes: the folder tree, the bookmarks matching
+//! the search line (the [`Filter`] syntax of `edit`) within the selected
+//! folder, and a preview of the highlighted bookmark. Keys open, tag, move
+//! and delete bookmarks. Changes are written to the store straight away, so
+//! as with `edit`, the next sync of a profile holding a bookmark puts its
+//! title and folder back; deleted bookmarks go to the store's trash.
+
+use anyhow::{Result, anyhow};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeSet;
+
+use crate::edit::{Edit, EditField, Filter};
+use crate::exporter::{Bookmark, FOLDER_PATH_SEPARATOR};
+use crate::store::Store;
+
+const HELP: &str = "/ search  tab switch pane  enter open  t tag  m move  d delete  q quit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Folders,
+    Bookmarks,
+}
+
+/// What typed keys go to
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    Browse,
+    Search,
+    /// Tags to add, `-tag` to remove
+    Tag(String),
+    /// Folder path to move to
+    Move(String),
+    ConfirmDelete,
+}
+
+/// What a key asks the event loop to do
+#[derive(Debug, Clone)]
+pub enum Action {
+    None,
+    Quit,
+    Open(String),
+    /// Write this edited bookmark to the store
+    Update(Bookmark),
+    /// Delete this bookmark from the store
+    Delete(Bookmark),
+}
+
+/// Browser state, kept apart from the terminal so keys can be tested
+pub struct App {
+    /// Every stored bookmark with the browsers holding it
+    bookmarks: Vec<(Bookmark, Vec<String>)>,
+    /// Folder paths, parents before children; row 0 of the pane is "All"
+    folders: Vec<String>,
+    folder: usize,
+    query: String,
+    filter: Option<Filter>,
+    /// Indices into `bookmarks` shown in the bookmarks pane
+    visible: Vec<usize>,
+    selected: usize,
+    pane: Pane,
+    mode: Mode,
+    status: String,
+}
+
+impl App {
+    pub fn new(bookmarks: Vec<(Bookmark, Vec<String>)>) -> Self {
+        let mut app = App {
+            bookmarks,
+            folders: Vec::new(),
+            folder: 0,
+            query: String::new(),
+            filter: None,
+            visible: Vec::new(),
+            selected: 0,
+            pane: Pane::Bookmarks,
+            mode: Mode::Browse,
+            status: String::new(),
+        };
+        app.rebuild_folders();
+        app
+    }
+
+    fn rebuild_folders(&mut self) {
+        let current = self.selected_folder().map(str::to_string);
+        let mut folders = BTreeSet::new();
+        for (bookmark, _) in &self.bookmarks {
+            let Some(folder) = bookmark.folder.as_deref() else {
+                continue;
+            };
+            let mut path = String::new();
+            for segment in folder
+                .split(FOLDER_PATH_SEPARATOR)
+                .filter(|s| !s.is_empty())
+            {
+                if !path.is_empty() {
+                    path.push_str(FOLDER_PATH_SEPARATOR);
+                }
+                path.push_str(segment);
+                folders.insert(path.clone());
+            }
+        }
+        self.folders = folders.into_iter().collect();
+        self.folder = current
+            .and_then(|c| self.folders.iter().position(|f| *f == c))
+            .map_or(0, |i| i + 1);
+        self.refresh();
+    }
+
+    fn selected_folder(&self) -> Option<&str> {
+        self.folder
+            .checked_sub(1)
+            .and_then(|i| self.folders.get(i))
+            .map(String::as_str)
+    }
+
+    /// Recompute the bookmarks pane from the folder and search
+    fn refresh(&mut self) {
+        let folder = self.selected_folder();
+        let below = folder.map(|f| format!("{}{}", f, FOLDER_PATH_SEPARATOR));
+        self.visible = self
+            .bookmarks
+            .iter()
+            .enumerate()
+            .filter(|(_, (bookmark, _))| {
+                folder.is_none_or(|folder| {
+                    bookmark.folder.as_deref().is_some_and(|f| {
+                        f == folder || below.as_deref().is_some_and(|b| f.starts_with(b))
+                    })
+                })
+            })
+            .filter(|(_, (bookmark, _))| self.filter.as_ref().is_none_or(|f| f.matches(bookmark)))
+            .map(|(i, _)| i)
+            .collect();
+        self.selected = self.selected.min(self.visible.len().saturating_sub(1));
+    }
+
+    fn current(&self) -> Option<&Bookmark> {
+        self.visible
+            .get(self.selected)
+            .map(|&i| &self.bookmarks[i].0)
+    }
+
+    fn set_query(&mut self, query: String) {
+        self.query = query;
+        match self.query.parse::<Filter>() {
+            Ok(filter) => {
+                self.filter = Some(filter);
+                self.status.clear();
+            }
+            // Keep the last good filter while a quote is still open
+            Err(e) if !self.query.trim().is_empty() => self.status = e.to_string(),
+            Err(_) => {
+                self.filter = None;
+                self.status.clear();
+            }
+        }
+        self.selected = 0;
+        self.refresh();
+    }
+
+    /// Show a bookmark the store has saved
+    pub fn updated(&mut self, bookmark: Bookmark) {
+        self.status = format!("Saved {}", bookmark.title);
+        if let Some(entry) = self.bookmarks.iter_mut().find(|(b, _)| b.id == bookmark.id) {
+            entry.0 = bookmark;
+        }
+        self.rebuild_folders();
+    }
+
+    /// Drop a bookmark the store has deleted
+    pub fn removed(&mut self, bookmark: &Bookmark) {
+        self.status = format!("Deleted {} (see `bookmark trash`)", bookmark.title);
+        self.bookmarks.retain(|(b, _)| b.id != bookmark.id);
+        self.rebuild_folders();
+    }
+
+    fn move_selection(&mut self, down: bool) {
+        let (index, len) = match self.pane {
+            Pane::Folders => (&mut self.folder, self.folders.len() + 1),
+            Pane::Bookmarks => (&mut self.selected, self.visible.len()),
+        };
+        *index = match down {
+            true => (*index + 1).min(len.saturating_sub(1)),
+            false => index.saturating_sub(1),
+        };
+        if self.pane == Pane::Folders {
+            self.selected = 0;
+            self.refresh();
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+        match std::mem::replace(&mut self.mode, Mode::Browse) {
+            Mode::Browse => self.browse_key(key),
+            Mode::Search => {
+                match key.code {
+                    KeyCode::Esc => self.set_query(String::new()),
+                    KeyCode::Enter => self.pane = Pane::Bookmarks,
+                    code => {
+                        let mut query = self.query.clone();
+                        type_into(&mut query, code);
+                        self.set_query(query);
+                        self.mode = Mode::Search;
+                    }
+                }
+                Action::None
+            }
+            Mode::Tag(input) => match key.code {
+                KeyCode::Esc => Action::None,
+                KeyCode::Enter => self.edit_current(tag_edit(&input)),
+                code => {
+                    let mut input = input;
+                    type_into(&mut input, code);
+                    self.mode = Mode::Tag(input);
+                    Action::None
+                }
+            },
+            Mode::Move(input) => match key.code {
+                KeyCode::Esc => Action::None,
+                KeyCode::Enter => self.edit_current(Edit {
+                    set: vec![(
+                        EditField::Folder,
+                        input.trim().trim_matches('/').to_string(),
+                    )],
+                    ..Edit::default()
+                }),
+                code => {
+                    let mut input = input;
+                    type_into(&mut input, code);
+                    self.mode = Mode::Move(input);
+                    Action::None
+                }
+            },
+            Mode::ConfirmDelete => match (key.code, self.current()) {
+                (KeyCode::Char('y'), Some(bookmark)) => Action::Delete(bookmark.clone()),
+                _ => {
+                    self.status = "Not deleted".to_string();
+                    Action::None
+                }
+            },
+        }
+    }
+
+    fn browse_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.pane = match self.pane {
+                    Pane::Folders => Pane::Bookmarks,
+                    Pane::Bookmarks => Pane::Folders,
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(true),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(false),
+            KeyCode::Enter if self.pane == Pane::Folders => self.pane = Pane::Bookmarks,
+            KeyCode::Enter | KeyCode::Char('o') => {
+                if let Some(url) = self.current().and_then(|b| b.url.clone()) {
+                    return Action::Open(url);
+                }
+            }
+            KeyCode::Char('t') if self.current().is_some() => self.mode = Mode::Tag(String::new()),
+            KeyCode::Char('m') => {
+                if let Some(bookmark) = self.current() {
+                    self.mode = Mode::Move(bookmark.folder.clone().unwrap_or_default());
+                }
+            }
+            KeyCode::Char('d') if self.current().is_some() => self.mode = Mode::ConfirmDelete,
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// An update of the highlighted bookmark, if `edit` changes it
+    fn edit_current(&mut self, edit: Edit) -> Action {
+        let Some(mut bookmark) = self.current().cloned() else {
+            return Action::None;
+        };
+        if edit.apply(&mut bookmark).is_empty() {
+            self.status = "No change".to_string();
+            return Action::None;
+        }
+        Action::Update(bookmark)
+    }
+}
+
+fn type_into(input: &mut String, code: KeyCode) {
+    match code {
+        KeyCode::Char(c) => input.push(c),
+        KeyCode::Backspace => {
+            input.pop();
+        }
+        _ => {}
+    }
+}
+
+/// `rust, -temp reading` adds rust and reading and removes temp
+fn tag_edit(input: &str) -> Edit {
+    let mut edit = Edit::default();
+    for tag in input
+        .split([',', ' '])
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+    {
+        match tag.strip_prefix('-') {
+            Some(tag) if !tag.is_empty() => edit.remove_tags.push(tag.to_string()),
+            Some(_) => {}
+            None => edit.add_tags.push(tag.to_string()),
+        }
+    }
+    edit
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let [search, main, status] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [folders, bookmarks, preview] = Layout::horizontal([
+        Constraint::Percentage(25),
+        Constraint::Percentage(40),
+        Constraint::Percentage(35),
+    ])
+    .areas(main);
+    let focused = |yes: bool| match yes {
+        true => Style::default().add_modifier(Modifier::REVERSED),
+        false => Style::default().add_modifier(Modifier::BOLD),
+    };
+
+    let cursor = if app.mode == Mode::Search { "_" } else { "" };
+    frame.render_widget(
+        Paragraph::new(format!("{}{}", app.query, cursor))
+            .block(Block::bordered().title(" Search (/) ")),
+        search,
+    );
+
+    let folder_items =
+        std::iter::once(ListItem::new("All bookmarks")).chain(app.folders.iter().map(|path| {
+            let depth = path.matches(FOLDER_PATH_SEPARATOR).count();
+            let name = path.rsplit(FOLDER_PATH_SEPARATOR).next().unwrap_or(path);
+            ListItem::new(format!("{}{}", "  ".repeat(depth), name))
+        }));
+    frame.render_stateful_widget(
+        List::new(folder_items)
+            .block(Block::bordered().title(" Folders "))
+            .highlight_style(focused(app.pane == Pane::Folders)),
+        folders,
+        &mut ListState::default().with_selected(Some(app.folder)),
+    );
+
+    let bookmark_items = app.visible.iter().map(|&i| {
+        let bookmark = &app.bookmarks[i].0;
+        ListItem::new(match bookmark.title.trim() {
+            "" => bookmark.url.clone().unwrap_or_default(),
+            title => title.to_string(),
+        })
+    });
+    frame.render_stateful_widget(
+        List::new(bookmark_items)
+            .block(Block::bordered().title(format!(" Bookmarks ({}) ", app.visible.len())))
+            .highlight_style(focused(app.pane == Pane::Bookmarks)),
+        bookmarks,
+        &mut ListState::default().with_selected((!app.visible.is_empty()).then_some(app.selected)),
+    );
+
+    let lines = match app.visible.get(app.selected).map(|&i| &app.bookmarks[i]) {
+        Some((bookmark, browsers)) => preview_lines(bookmark, browsers),
+        None => vec![Line::from("No bookmarks match")],
+    };
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(" Preview ")),
+        preview,
+    );
+
+    let line = match &app.mode {
+        Mode::Tag(input) => format!("Tags to add, -tag to remove: {}_", input),
+        Mode::Move(input) => format!("Move to folder: {}_", input),
+        Mode::ConfirmDelete => format!(
+            "Delete \"{}\" from the store? y/n",
+            app.current().map(|b| b.title.as_str()).unwrap_or_default()
+        ),
+        _ if !app.status.is_empty() => app.status.clone(),
+        _ => HELP.to_string(),
+    };
+    frame.render_widget(Paragraph::new(line), status);
+}
+
+fn preview_lines<'a>(bookmark: &'a Bookmark, browsers: &[String]) -> Vec<Line<'a>> {
+    let field = |name: &'static str, value: String| {
+        Line::from(vec![
+            Span::styled(
+                format!("{}: ", name),
+                Style::default().add_modifier(Modifier::DIM),
+            ),
+            Span::raw(value),
+        ])
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(
+            bookmark.title.as_str(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(bookmark.url.as_deref().unwrap_or_default()),
+        Line::default(),
+    ];
+    if let Some(folder) = &bookmark.folder {
+        lines.push(field("Folder", folder.clone()));
+    }
+    if let Some(tags) = bookmark.tags.as_ref().filter(|t| !t.is_empty()) {
+        lines.push(field("Tags", tags.join(", ")));
+    }
+    if !browsers.is_empty() {
+        lines.push(field("Browsers", browsers.join(", ")));
+    }
+    if let Some(added) = bookmark.date_added {
+        lines.push(field("Added", added.format("%Y-%m-%d").to_string()));
+    }
+    if let Some(keyword) = &bookmark.keyword {
+        lines.push(field("Keyword", keyword.clone()));
+    }
+    if let Some(description) = &bookmark.description {
+        lines.push(Line::default());
+        lines.push(Line::from(description.as_str()));
+    }
+    lines
+}
+
+/// Browse the store until the user quits
+pub fn run() -> Result<()> {
+    let mut store = Store::open_if_synced()?
+        .ok_or_else(|| anyhow!("The bookmark store is empty; run `bookmark sync` first"))?;
+    let mut app = App::new(store.bookmarks_with_browsers()?);
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app, &mut store);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App, store: &mut Store) -> Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.handle_key(key) {
+            Action::None => {}
+            Action::Quit => return Ok(()),
+            Action::Open(url) => {
+                app.status = match open::that(&url) {
+                    Ok(()) => format!("Opened {}", url),
+                    Err(e) => format!("Could not open {}: {}", url, e),
+                }
+            }
+            Action::Update(bookmark) => {
+                match store.update_bookmarks(std::slice::from_ref(&bookmark)) {
+                    Ok(_) => app.updated(bookmark),
+                    Err(e) => app.status = format!("Could not save: {:#}", e),
+                }
+            }
+            Action::Delete(bookmark) => {
+                match store.delete_bookmarks(std::slice::from_ref(&bookmark), "tui") {
+                    Ok(_) => app.removed(&bookmark),
+                    Err(e) => app.status = format!("Could not delete: {:#}", e),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(id: &str, title: &str, folder: &str) -> (Bookmark, Vec<String>) {
+        let bookmark = Bookmark {
+            id: id.to_string(),
+            title: title.to_string(),
+            url: Some(format!("https://example.com/{}", id)),
+            folder: Some(folder.to_string()),
+            date_added: None,
+            ..Default::default()
+        };
+        (bookmark, vec!["firefox".to_string()])
+    }
+
+    fn press(app: &mut App, keys: &str) -> Action {
+        let mut action = Action::None;
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\t' => KeyCode::Tab,
+                '\u{8}' => KeyCode::Backspace,
+                c => KeyCode::Char(c),
+            };
+            action = app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+        action
+    }
+
+    #[test]
+    fn test_search_folders_and_actions() {
+        let mut app = App::new(vec![
+            bookmark("1", "Rust book", "Dev/Rust"),
+            bookmark("2", "Rust blog", "Reading"),
+            bookmark("3", "Python docs", "Dev"),
+        ]);
+        assert_eq!(app.folders, ["Dev", "Dev/Rust", "Reading"]);
+
+        // Incremental search, then the Dev folder narrows it further
+        press(&mut app, "/rust");
+        assert_eq!(app.visible, [0, 1]);
+        press(&mut app, "\n\tj\t");
+        assert_eq!(app.selected_folder(), Some("Dev"));
+        assert_eq!(app.visible, [0]);
+
+        assert!(
+            matches!(press(&mut app, "o"), Action::Open(url) if url == "https://example.com/1")
+        );
+        let Action::Update(tagged) = press(&mut app, "tlang, -none\n") else {
+            panic!("expected an update");
+        };
+        assert_eq!(tagged.tags, Some(vec!["lang".to_string()]));
+        app.updated(tagged);
+
+        // Move starts from the current folder
+        let Action::Update(moved) = press(&mut app, "m\u{8}\u{8}\u{8}\u{8}Go\n") else {
+            panic!("expected an update");
+        };
+        assert_eq!(moved.folder.as_deref(), Some("Dev/Go"));
+        app.updated(moved);
+        assert_eq!(app.folders, ["Dev", "Dev/Go", "Reading"]);
+        assert_eq!(app.visible, [0]);
+
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 12)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(screen.contains("Rust book") && screen.contains("lang"));
+
+        assert!(matches!(press(&mut app, "dn"), Action::None));
+        assert!(matches!(press(&mut app, "dy"), Action::Delete(b) if b.id == "1"));
+        assert!(matches!(press(&mut app, "q"), Action::Quit));
+    }
+}
@@ -1,8 +1,21 @@
 //! Utility functions for file I/O and platform-specific operations
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use std::fs;
+use std::io::Write;
 use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use url::{Host, Url};
+
+/// Largest output `--clipboard` will accept; anything bigger belongs in a file
+pub const CLIPBOARD_LIMIT: usize = 1024 * 1024;
+
+/// Whether an `--output` path means "write to stdout" (`-o -`)
+pub fn is_stdout(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
 
 /// Open a file in the default application for the current platform
 pub fn open_file(path: &Path) -> Result<()> {
@@ -21,6 +34,53 @@ pub fn open_file(path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Copy text to the system clipboard via the platform's clipboard command
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    if text.len() > CLIPBOARD_LIMIT {
+        return Err(anyhow!(
+            "Output is {} KiB, too large for the clipboard (limit {} KiB); use -o instead",
+            text.len() / 1024,
+            CLIPBOARD_LIMIT / 1024
+        ));
+    }
+
+    let candidates: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(target_os = "windows") {
+        &[("clip", &[])]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    };
+
+    for (program, args) in candidates {
+        let Ok(mut child) = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .spawn()
+        else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(anyhow!("{} exited with {}", program, status));
+        }
+        return Ok(());
+    }
+
+    let names: Vec<&str> = candidates.iter().map(|(program, _)| *program).collect();
+    Err(anyhow!(
+        "No clipboard command found (tried {}); use -o - and pipe instead",
+        names.join(", ")
+    ))
+}
+
 /// Create a redirect HTML file
 pub fn create_redirect_html(output_path: &Path, target_url: &str) -> Result<()> {
     let html = format!(
@@ -34,3 +94,92 @@ pub fn create_redirect_html(output_path: &Path, target_url: &str) -> Result<()>
     fs::write(output_path, html)?;
     Ok(())
 }
+
+/// Whether a page at `origin`, as an `Origin` header gives it, is served
+/// from this machine
+pub fn is_local_origin(origin: &str) -> bool {
+    Url::parse(origin).is_ok_and(|url| match url.host() {
+        Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    })
+}
+
+/// Whether a `Host` header names this machine: `localhost` or a loopback
+/// address, with or without a port
+pub fn is_local_host(host: &str) -> bool {
+    is_local_origin(&format!("http://{}", host))
+}
+
+/// A job boxed for [`run_with_timeout`]
+pub type Job<T> = Box<dyn FnOnce() -> Result<T> + Send>;
+
+/// How a job run by [`run_with_timeout`] ended
+#[derive(Debug)]
+pub enum JobOutcome<T> {
+    Done(Result<T>),
+    TimedOut,
+}
+
+/// Run every job on its own thread and wait up to `timeout` for each, or
+/// as long as it takes when `None`. A job that overruns is abandoned rather
+/// than killed: its thread carries on, and whatever it returns is dropped.
+/// Outcomes come back in the order of `jobs`.
+pub fn run_with_timeout<T: Send + 'static>(
+    jobs: Vec<Job<T>>,
+    timeout: Option<Duration>,
+) -> Vec<JobOutcome<T>> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let (sender, receiver) = mpsc::channel();
+    let count = jobs.len();
+    for (index, job) in jobs.into_iter().enumerate() {
+        let sender = sender.clone();
+        std::thread::spawn(move || {
+            // The receiver is gone once the deadline has passed
+            let _ = sender.send((index, job()));
+        });
+    }
+    drop(sender);
+
+    let mut outcomes: Vec<JobOutcome<T>> = (0..count).map(|_| JobOutcome::TimedOut).collect();
+    for _ in 0..count {
+        let received = match deadline {
+            Some(deadline) => {
+                receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => receiver
+                .recv()
+                .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok((index, result)) => outcomes[index] = JobOutcome::Done(result),
+            Err(_) => break,
+        }
+    }
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_with_timeout_abandons_slow_jobs() {
+        let jobs: Vec<Job<u32>> = vec![
+            Box::new(|| Ok(1)),
+            Box::new(|| {
+                std::thread::sleep(Duration::from_secs(5));
+                Ok(2)
+            }),
+            Box::new(|| Err(anyhow!("unreadable"))),
+        ];
+        let started = Instant::now();
+        let outcomes = run_with_timeout(jobs, Some(Duration::from_millis(200)));
+
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(matches!(outcomes[0], JobOutcome::Done(Ok(1))));
+        assert!(matches!(outcomes[1], JobOutcome::TimedOut));
+        assert!(matches!(outcomes[2], JobOutcome::Done(Err(_))));
+    }
+}
@@ -0,0 +1,58 @@
+#[cfg(all(feature = "host", target_os = "linux"))]
+mod host_tests {
+    use bookmark::host::NativeHost;
+    use bookmark::paths::{self, PathOverrides};
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn test_search_reads_browsers_after_add() {
+        let dir = tempfile::tempdir().unwrap();
+        let profile = dir.path().join(".config/google-chrome/Default");
+        fs::create_dir_all(&profile).unwrap();
+        let chrome = json!({
+            "roots": {
+                "bookmark_bar": {
+                    "type": "folder",
+                    "name": "Bookmarks bar",
+                    "children": [{
+                        "type": "url",
+                        "id": "1",
+                        "name": "The Rust Programming Language",
+                        "url": "https://doc.rust-lang.org/book/",
+                    }],
+                },
+            },
+            "version": 1,
+        });
+        fs::write(profile.join("Bookmarks"), chrome.to_string()).unwrap();
+        // Browser profiles are found under HOME; this test binary sets it once
+        unsafe { std::env::set_var("HOME", dir.path()) };
+        paths::init(&PathOverrides {
+            config: Some(dir.path().join("config.yaml")),
+            store: Some(dir.path().join("store.db")),
+            cache: Some(dir.path().join("cache")),
+            backups: Some(dir.path().join("backups")),
+            archives: Some(dir.path().join("archives")),
+            snapshots: Some(dir.path().join("snapshots")),
+        });
+
+        let host = NativeHost::new().unwrap();
+        let added = host.handle(&json!({
+            "action": "add",
+            "url": "https://example.com/clipped",
+            "title": "Clipped from the extension",
+        }));
+        assert_eq!(added["result"]["added"], true, "{}", added);
+
+        let found = host.handle(&json!({ "action": "search", "query": "rust" }));
+        assert_eq!(found["ok"], true, "{}", found);
+        let urls: Vec<&str> = found["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|result| result["url"].as_str())
+            .collect();
+        assert_eq!(urls, ["https://doc.rust-lang.org/book/"]);
+    }
+}
@@ -1,24 +1,44 @@
 #[cfg(feature = "mcp")]
 mod mcp_tests {
     use bookmark::mcp::McpServer;
-    use serde_json::json;
 
     #[test]
     fn test_mcp_server_initialization() {
-        let server = McpServer::new();
-        assert!(true);
+        let _server = McpServer::new();
     }
 
     #[test]
     fn test_mcp_tools_available() {
-        let expected_tools = vec![
-            "export_bookmarks",
-            "search_bookmarks",
-            "list_browsers",
-            "process_bookmarks",
-            "generate_graph",
-        ];
-        
-        assert_eq!(expected_tools.len(), 5);
+        let server = McpServer::new();
+        let response = server
+            .respond(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#)
+            .unwrap();
+        let tools: Vec<&str> = response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|tool| tool["name"].as_str())
+            .collect();
+        assert_eq!(
+            tools,
+            [
+                "export_bookmarks",
+                "search_bookmarks",
+                "list_browsers",
+                "process_bookmarks",
+                "generate_graph",
+                "preview_duplicates",
+                "organize_bookmarks",
+                "open_bookmark",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_self_test_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = McpServer::new().self_test(dir.path()).unwrap();
+        assert!(report.passed(), "{}", report);
+        assert!(report.checks.len() > 12);
     }
 }
@@ -0,0 +1,84 @@
+use bookmark::config::AppConfig;
+use bookmark::exporter::BrowserData;
+use bookmark::paths::{self, PathOverrides};
+use bookmark::search::{SearchOptions, SortOrder, search_bookmarks_internal};
+use bookmark::store::Store;
+use bookmark::{Bookmark, MatchedField};
+
+fn bookmark(id: &str, title: &str, url: &str, description: Option<&str>) -> Bookmark {
+    Bookmark {
+        id: id.to_string(),
+        title: title.to_string(),
+        url: Some(url.to_string()),
+        folder: Some("Reading".to_string()),
+        date_added: None,
+        description: description.map(str::to_string),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_weighted_fields_are_searched() {
+    let dir = tempfile::tempdir().unwrap();
+    let paths = paths::init(&PathOverrides {
+        config: Some(dir.path().join("config.yaml")),
+        store: Some(dir.path().join("store.db")),
+        cache: Some(dir.path().join("cache")),
+        backups: Some(dir.path().join("backups")),
+        archives: Some(dir.path().join("archives")),
+        snapshots: Some(dir.path().join("snapshots")),
+    });
+    let mut config = AppConfig::default();
+    config.search.weights.description = 8.0;
+    config.search.weights.recency = 0.0;
+    config.save_to_file(&paths.config).unwrap();
+
+    Store::open_default()
+        .unwrap()
+        .ingest(&BrowserData {
+            browser: "chrome".to_string(),
+            profile: "Default".to_string(),
+            export_date: chrono::Utc::now(),
+            bookmarks: Some(vec![
+                bookmark("1", "Home", "https://rust.example/", None),
+                bookmark(
+                    "2",
+                    "Paper",
+                    "https://example.com/p",
+                    Some("Notes on rust lifetimes"),
+                ),
+            ]),
+            history: None,
+            passwords: None,
+            aliases: None,
+            profile_name: None,
+            profile_email: None,
+            provenance: None,
+        })
+        .unwrap();
+    let options = SearchOptions {
+        title_only: false,
+        url_only: false,
+        limit: 10,
+        media_type: None,
+        sort: SortOrder::Relevance,
+    };
+    let titles = |query: &str| -> Vec<String> {
+        search_bookmarks_internal(query, &options)
+            .unwrap()
+            .into_iter()
+            .map(|result| result.bookmark.title)
+            .collect()
+    };
+
+    // Only the notes mention lifetimes
+    let notes = search_bookmarks_internal("lifetimes", &options).unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].bookmark.title, "Paper");
+    assert_eq!(notes[0].matched_field, MatchedField::Description);
+    assert_eq!(notes[0].snippet, "Notes on rust lifetimes");
+    // The heavy description weight outranks the URL match
+    assert_eq!(titles("rust"), ["Paper", "Home"]);
+    // A folder (weight 1 by default) is searched too
+    assert_eq!(titles("reading").len(), 2);
+}